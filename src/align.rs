@@ -0,0 +1,506 @@
+use crate::backend::TranscriptionBackend;
+use crate::error::Result;
+use crate::search::normalize;
+use crate::types::{TranscriptionOptions, TranscriptionResult, TranscriptionSegment, WordTiming};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Slack either side of the diagonal the word alignment may wander, beyond the difference
+/// in length of the two texts
+const MIN_BAND: usize = 256;
+
+/// Cost of a missing or extra word. A substitution costs half as much between similar words
+/// and as much as both between dissimilar ones, so "brown"/"frown" pair up rather than
+/// "brown" with whichever word happens to sit opposite.
+const GAP_COST: u32 = 2;
+
+fn substitution_cost(reference: &str, hypothesis: &str) -> u32 {
+    if reference == hypothesis {
+        0
+    } else if similarity(reference, hypothesis) >= 0.5 {
+        1
+    } else {
+        2 * GAP_COST
+    }
+}
+
+/// One step of a word alignment between a reference text and what was recognized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// Reference word and recognized word agree
+    Match(usize, usize),
+    /// Reference word recognized as something else
+    Substitute(usize, usize),
+    /// Reference word missing from the recognition
+    Delete(usize),
+    /// Recognized word not in the reference
+    Insert(usize),
+}
+
+/// Minimum word edit alignment of `hypothesis` against `reference`, in order.
+///
+/// Words are compared lowercased and without surrounding punctuation. The search is limited
+/// to a band around the diagonal, so long transcripts align in memory proportional to their
+/// length rather than its square; an alignment that would need to stray further is found as
+/// substitutions instead.
+pub fn align_words<R: AsRef<str>, H: AsRef<str>>(reference: &[R], hypothesis: &[H]) -> Vec<Edit> {
+    let reference: Vec<String> = reference.iter().map(|w| normalize(w.as_ref())).collect();
+    let hypothesis: Vec<String> = hypothesis.iter().map(|w| normalize(w.as_ref())).collect();
+    let (n, m) = (reference.len(), hypothesis.len());
+    if n == 0 {
+        return (0..m).map(Edit::Insert).collect();
+    }
+
+    const DIAGONAL: u8 = 0;
+    const UP: u8 = 1;
+    const LEFT: u8 = 2;
+    const INFINITE: u32 = u32::MAX / 2;
+    let band = n.abs_diff(m) + MIN_BAND;
+    let range = |i: usize| {
+        let center = i * m / n;
+        (center.saturating_sub(band), (center + band).min(m))
+    };
+
+    // Backpointers for each row's band, and two rolling rows of costs
+    let mut rows: Vec<(usize, Vec<u8>)> = Vec::with_capacity(n + 1);
+    let mut previous = vec![INFINITE; m + 1];
+    let mut current = vec![INFINITE; m + 1];
+    let (lo, hi) = range(0);
+    for (j, cost) in previous.iter_mut().enumerate().take(hi + 1).skip(lo) {
+        *cost = j as u32 * GAP_COST;
+    }
+    rows.push((lo, vec![LEFT; hi - lo + 1]));
+    for i in 1..=n {
+        let (lo, hi) = range(i);
+        let mut pointers = Vec::with_capacity(hi - lo + 1);
+        for j in lo..=hi {
+            let mut best = (previous[j] + GAP_COST, UP);
+            if j > 0 {
+                let substitution = substitution_cost(&reference[i - 1], &hypothesis[j - 1]);
+                let diagonal = previous[j - 1] + substitution;
+                if diagonal <= best.0 {
+                    best = (diagonal, DIAGONAL);
+                }
+                if j > lo && current[j - 1] + GAP_COST < best.0 {
+                    best = (current[j - 1] + GAP_COST, LEFT);
+                }
+            }
+            current[j] = best.0.min(INFINITE);
+            pointers.push(best.1);
+        }
+        rows.push((lo, pointers));
+        // The row just finished becomes the previous one; clear what the old one had set
+        let (old_lo, old_hi) = range(i - 1);
+        previous[old_lo..=old_hi].fill(INFINITE);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let mut edits = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let (lo, pointers) = &rows[i];
+        let pointer = if i == 0 { LEFT } else { pointers[j - lo] };
+        match pointer {
+            DIAGONAL => {
+                i -= 1;
+                j -= 1;
+                edits.push(if reference[i] == hypothesis[j] {
+                    Edit::Match(i, j)
+                } else {
+                    Edit::Substitute(i, j)
+                });
+            }
+            UP => {
+                i -= 1;
+                edits.push(Edit::Delete(i));
+            }
+            _ => {
+                j -= 1;
+                edits.push(Edit::Insert(j));
+            }
+        }
+    }
+    edits.reverse();
+    edits
+}
+
+/// How closely the recognition followed a reference text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlignmentSummary {
+    pub matched: usize,
+    pub substituted: usize,
+    /// Reference words with no recognized counterpart; their times are interpolated
+    pub missing: usize,
+    /// Recognized words not in the reference, dropped from the output
+    pub extra: usize,
+    /// (substituted + missing + extra) / reference words
+    pub word_error_rate: f64,
+}
+
+impl AlignmentSummary {
+    pub fn from_edits(edits: &[Edit]) -> Self {
+        let mut summary = Self::default();
+        for edit in edits {
+            match edit {
+                Edit::Match(..) => summary.matched += 1,
+                Edit::Substitute(..) => summary.substituted += 1,
+                Edit::Delete(_) => summary.missing += 1,
+                Edit::Insert(_) => summary.extra += 1,
+            }
+        }
+        let reference_words = summary.matched + summary.substituted + summary.missing;
+        let errors = summary.substituted + summary.missing + summary.extra;
+        summary.word_error_rate = if reference_words > 0 {
+            errors as f64 / reference_words as f64
+        } else if errors > 0 {
+            1.0
+        } else {
+            0.0
+        };
+        summary
+    }
+}
+
+/// Word error rate of `hypothesis` against `reference`, split on whitespace
+pub fn word_error_rate(reference: &str, hypothesis: &str) -> f64 {
+    let reference: Vec<&str> = reference.split_whitespace().collect();
+    let hypothesis: Vec<&str> = hypothesis.split_whitespace().collect();
+    AlignmentSummary::from_edits(&align_words(&reference, &hypothesis)).word_error_rate
+}
+
+/// How far two transcripts of the same audio agree, from 0 to 1: words the alignment
+/// matches over the mean of their word counts. Two empty texts agree fully.
+pub fn word_agreement(a: &str, b: &str) -> f64 {
+    let a: Vec<&str> = a.split_whitespace().collect();
+    let b: Vec<&str> = b.split_whitespace().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let matched = align_words(&a, &b)
+        .iter()
+        .filter(|edit| matches!(edit, Edit::Match(..)))
+        .count();
+    2.0 * matched as f64 / (a.len() + b.len()) as f64
+}
+
+/// 1 for identical words down to 0 for nothing in common, by character edit distance
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    1.0 - row[b.len()] as f64 / longest as f64
+}
+
+/// Recognized words with the segment they came from. Segments without word timestamps are
+/// split into words timed in proportion to their length.
+fn recognized_words(result: &TranscriptionResult) -> Vec<(usize, WordTiming)> {
+    let mut words = Vec::new();
+    for (index, segment) in result.segments.iter().enumerate() {
+        if !segment.words.is_empty() {
+            words.extend(segment.words.iter().map(|w| (index, w.clone())));
+            continue;
+        }
+        let tokens: Vec<&str> = segment.text.split_whitespace().collect();
+        let total: usize = tokens.iter().map(|t| t.chars().count()).sum();
+        let mut start = segment.start;
+        for token in tokens {
+            let share = token.chars().count() as f64 / total.max(1) as f64;
+            let end = start + (segment.end - segment.start) * share;
+            words.push((
+                index,
+                WordTiming {
+                    start,
+                    end,
+                    word: format!(" {}", token),
+                    probability: segment.avg_logprob.exp(),
+                    speaker: segment.speaker.clone(),
+                    tokens: None,
+                },
+            ));
+            start = end;
+        }
+    }
+    words
+}
+
+/// Retime `reference` with the word timings of `recognized`.
+///
+/// The result's words and segments carry the reference text, grouped into the recognized
+/// segments. A word's `probability` becomes its alignment confidence: the recognition's
+/// probability, scaled down by how far the recognized word differs for substitutions, and
+/// zero for reference words that weren't recognized at all, which are spread evenly over
+/// the time between their neighbours.
+pub fn align_transcript(recognized: &TranscriptionResult, reference: &str) -> TranscriptionResult {
+    let reference_words: Vec<&str> = reference.split_whitespace().collect();
+    let hypothesis = recognized_words(recognized);
+    let hypothesis_text: Vec<&str> = hypothesis.iter().map(|(_, w)| w.word.as_str()).collect();
+    let edits = align_words(&reference_words, &hypothesis_text);
+
+    // (segment, word) per reference word; None until timed
+    let mut aligned: Vec<Option<(usize, WordTiming)>> = vec![None; reference_words.len()];
+    for edit in &edits {
+        let (r, h, confidence) = match *edit {
+            Edit::Match(r, h) => (r, h, 1.0),
+            Edit::Substitute(r, h) => (
+                r,
+                h,
+                similarity(
+                    &normalize(reference_words[r]),
+                    &normalize(&hypothesis[h].1.word),
+                ),
+            ),
+            Edit::Delete(_) | Edit::Insert(_) => continue,
+        };
+        let (segment, word) = &hypothesis[h];
+        aligned[r] = Some((
+            *segment,
+            WordTiming {
+                word: format!(" {}", reference_words[r]),
+                probability: word.probability * confidence,
+                ..word.clone()
+            },
+        ));
+    }
+
+    // Spread runs of unrecognized words between the words either side
+    let mut timed: Vec<(usize, WordTiming)> = Vec::with_capacity(reference_words.len());
+    let mut r = 0;
+    while r < aligned.len() {
+        if let Some(word) = aligned[r].take() {
+            timed.push(word);
+            r += 1;
+            continue;
+        }
+        let run_end = (r..aligned.len())
+            .find(|&k| aligned[k].is_some())
+            .unwrap_or(aligned.len());
+        let (segment, from) = match timed.last() {
+            Some((segment, word)) => (*segment, word.end),
+            None => (0, 0.0),
+        };
+        let (segment, to) = match aligned.get(run_end).and_then(Option::as_ref) {
+            Some((next_segment, word)) if timed.is_empty() => (*next_segment, word.start),
+            Some((_, word)) => (segment, word.start),
+            None => (segment, recognized.duration.max(from)),
+        };
+        let step = (to - from).max(0.0) / (run_end - r) as f64;
+        for (k, word) in reference_words.iter().enumerate().take(run_end).skip(r) {
+            let start = from + step * (k - r) as f64;
+            timed.push((
+                segment,
+                WordTiming {
+                    start,
+                    end: start + step,
+                    word: format!(" {}", word),
+                    probability: 0.0,
+                    speaker: None,
+                    tokens: None,
+                },
+            ));
+        }
+        r = run_end;
+    }
+
+    let mut segments: Vec<TranscriptionSegment> = Vec::new();
+    let mut current: Option<usize> = None;
+    for (index, word) in timed {
+        if current != Some(index) || segments.is_empty() {
+            let source = recognized.segments.get(index);
+            segments.push(TranscriptionSegment {
+                start: word.start,
+                end: word.end,
+                text: String::new(),
+                no_speech_prob: source.map_or(0.0, |s| s.no_speech_prob),
+                avg_logprob: source.map_or(0.0, |s| s.avg_logprob),
+                words: Vec::new(),
+                speaker: source.and_then(|s| s.speaker.clone()),
+                tokens: None,
+            });
+            current = Some(index);
+        }
+        let segment = segments.last_mut().expect("pushed above");
+        segment.end = segment.end.max(word.end);
+        segment.text.push_str(&word.word);
+        segment.words.push(word);
+    }
+    for segment in &mut segments {
+        segment.text = segment.text.trim().to_string();
+    }
+
+    TranscriptionResult {
+        full_text: TranscriptionResult::text_from_segments(&segments, None),
+        segments,
+        alignment: Some(AlignmentSummary::from_edits(&edits)),
+        stats: None,
+        chapters: None,
+        redaction: None,
+        ..recognized.clone()
+    }
+}
+
+/// Transcribe `audio_path` with word timestamps and retime `transcript` to it; see
+/// `align_transcript`
+pub fn align(
+    backend: &dyn TranscriptionBackend,
+    audio_path: &Path,
+    transcript: &str,
+) -> Result<TranscriptionResult> {
+    let options = TranscriptionOptions {
+        word_timestamps: true,
+        ..backend.options().clone()
+    };
+    let recognized = backend.transcribe_path(audio_path, &options)?;
+    Ok(align_transcript(&recognized, transcript))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_words_and_wer() {
+        let edits = align_words(
+            &["the", "quick", "brown", "fox", "jumps"],
+            &["The", "quick", "frown", "fox", "fox", "jumps."],
+        );
+        assert_eq!(
+            edits,
+            [
+                Edit::Match(0, 0),
+                Edit::Match(1, 1),
+                Edit::Substitute(2, 2),
+                Edit::Insert(3),
+                Edit::Match(3, 4),
+                Edit::Match(4, 5),
+            ]
+        );
+        assert_eq!(word_error_rate("a b c d", "a c d"), 0.25);
+        assert_eq!(word_error_rate("", ""), 0.0);
+        assert_eq!(word_error_rate("", "extra"), 1.0);
+        assert_eq!(
+            align_words::<&str, &str>(&["a", "b"], &[]),
+            [Edit::Delete(0), Edit::Delete(1)]
+        );
+
+        assert_eq!(word_agreement("The cat sat.", "the cat sat"), 1.0);
+        // 3 of 4 and 3 of 5 words match: 6 / 9
+        let partial = word_agreement("the cat sat down", "the cat sat on mats");
+        assert!((partial - 6.0 / 9.0).abs() < 1e-12);
+        assert_eq!(
+            partial,
+            word_agreement("the cat sat on mats", "the cat sat down")
+        );
+        assert_eq!(word_agreement("", ""), 1.0);
+        assert_eq!(word_agreement("", "thank you"), 0.0);
+    }
+
+    #[test]
+    fn test_long_alignment_stays_in_band() {
+        let reference: Vec<String> = (0..1000).map(|i| format!("w{}", i)).collect();
+        let mut hypothesis = reference.clone();
+        hypothesis.remove(400);
+        hypothesis.insert(800, "um".to_string());
+        let summary = AlignmentSummary::from_edits(&align_words(&reference, &hypothesis));
+        assert_eq!(
+            (summary.matched, summary.missing, summary.extra),
+            (999, 1, 1)
+        );
+    }
+
+    fn word(start: f64, text: &str, probability: f64) -> WordTiming {
+        WordTiming {
+            start,
+            end: start + 0.5,
+            word: format!(" {}", text),
+            probability,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    fn segment(words: Vec<WordTiming>) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start: words[0].start,
+            end: words[words.len() - 1].end,
+            text: words
+                .iter()
+                .map(|w| w.word.as_str())
+                .collect::<String>()
+                .trim()
+                .to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: -0.1,
+            words,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_align_transcript_takes_text_from_reference() {
+        let recognized = TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 10.0,
+            segments: vec![
+                segment(vec![
+                    word(0.0, "to", 0.9),
+                    word(0.5, "be", 0.9),
+                    word(1.0, "or", 0.8),
+                ]),
+                segment(vec![
+                    word(3.0, "knot", 0.6),
+                    word(4.0, "two", 0.5),
+                    word(5.0, "bee", 0.7),
+                ]),
+            ],
+            full_text: String::new(),
+            transcription_time: 1.0,
+            real_time_factor: 10.0,
+            ..Default::default()
+        };
+        let aligned = align_transcript(&recognized, "To be, or not to be:\nthat is the question.");
+
+        assert_eq!(aligned.segments.len(), 2);
+        assert_eq!(aligned.segments[0].text, "To be, or");
+        assert_eq!(aligned.segments[1].text, "not to be: that is the question.");
+        assert_eq!(
+            aligned.full_text,
+            "To be, or not to be: that is the question."
+        );
+
+        let words = &aligned.segments[1].words;
+        // "knot" for "not": timed by the recognition, with lowered confidence
+        assert_eq!((words[0].start, words[0].end), (3.0, 3.5));
+        assert!(words[0].probability > 0.3 && words[0].probability < 0.6);
+        assert!((words[2].probability - 0.7 * (2.0 / 3.0)).abs() < 1e-9);
+        // Unrecognized words spread from the last recognized word to the end of the audio
+        assert_eq!(words[3].word, " that");
+        assert_eq!(words[3].probability, 0.0);
+        assert_eq!((words[3].start, words[6].end), (5.5, 10.0));
+
+        let summary = aligned.alignment.unwrap();
+        assert_eq!(
+            (
+                summary.matched,
+                summary.substituted,
+                summary.missing,
+                summary.extra
+            ),
+            (3, 3, 4, 0)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::batch::{BatchReport, FileStatus};
+use crate::error::{Result, TranscriptionError};
+use crate::output;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Component, Path};
+use std::str::FromStr;
+use tracing::{debug, warn};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The batch's CSV summary, at the root of every archive
+pub const SUMMARY_ENTRY: &str = "summary.csv";
+/// Failed inputs and why, added only when something failed
+pub const ERRORS_ENTRY: &str = "errors.txt";
+
+/// How archive entries are compressed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchiveCompression {
+    Stored,
+    #[default]
+    Deflate,
+}
+
+impl FromStr for ArchiveCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stored" | "none" => Ok(ArchiveCompression::Stored),
+            "deflate" => Ok(ArchiveCompression::Deflate),
+            other => Err(format!(
+                "Unknown archive compression '{}' (expected stored or deflate)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ArchiveCompression::Stored => "stored",
+            ArchiveCompression::Deflate => "deflate",
+        })
+    }
+}
+
+/// What `write_archive` put in the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    /// Transcript files, not counting the summary and error list
+    pub outputs: usize,
+    pub failures: usize,
+}
+
+fn archive_error(e: zip::result::ZipError) -> TranscriptionError {
+    TranscriptionError::IoError(std::io::Error::other(format!("Archive: {}", e)))
+}
+
+/// Entry name for `output`: its path below `root` with `/` separators, or just its file
+/// name when it lies elsewhere
+fn entry_name(output: &Path, root: Option<&Path>) -> Option<String> {
+    let relative = root
+        .and_then(|root| output.strip_prefix(root).ok())
+        .filter(|relative| {
+            relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        })
+        .unwrap_or_else(|| Path::new(output.file_name().unwrap_or_default()));
+    let parts: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Zip the outputs of a finished batch into `path`, with the CSV summary and, when
+/// anything failed, an `errors.txt` listing the failures.
+///
+/// Outputs keep their path relative to `root` (normally the output directory); the other
+/// formats and chapter lists written beside them are included. Files are streamed into the archive one at a
+/// time rather than read into memory. The archive only appears at `path` once complete.
+pub fn write_archive(
+    path: &Path,
+    report: &BatchReport,
+    root: Option<&Path>,
+    compression: ArchiveCompression,
+) -> Result<ArchiveSummary> {
+    let options = SimpleFileOptions::default().compression_method(match compression {
+        ArchiveCompression::Stored => CompressionMethod::Stored,
+        ArchiveCompression::Deflate => CompressionMethod::Deflated,
+    });
+    let tmp = output::tmp_path(path);
+    let written = File::create(&tmp)
+        .map_err(TranscriptionError::from)
+        .and_then(|file| write_entries(file, path, report, root, options))
+        .and_then(|(file, summary)| {
+            output::persist(&file, &tmp, path)?;
+            Ok(summary)
+        });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Fill the archive being written to `file`, which will be renamed to `path`
+fn write_entries(
+    file: File,
+    path: &Path,
+    report: &BatchReport,
+    root: Option<&Path>,
+    options: SimpleFileOptions,
+) -> Result<(File, ArchiveSummary)> {
+    let mut zip = ZipWriter::new(file);
+    let mut names = BTreeSet::new();
+    let mut outputs = 0;
+
+    let succeeded = report
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.status == FileStatus::Succeeded);
+    for outcome in succeeded {
+        let Some(output) = outcome.output.as_deref() else {
+            continue;
+        };
+        let chapters = output::chapters_path(output);
+        let files = outcome.written().chain([chapters.as_path()]);
+        for file in files {
+            if !file.is_file() || file == path {
+                continue;
+            }
+            let Some(name) = entry_name(file, root) else {
+                continue;
+            };
+            if !names.insert(name.clone()) {
+                warn!(
+                    "{} is already in the archive; skipping {}",
+                    name,
+                    file.display()
+                );
+                continue;
+            }
+            debug!("Archiving {} as {}", file.display(), name);
+            zip.start_file(name, options).map_err(archive_error)?;
+            std::io::copy(&mut File::open(file)?, &mut zip)?;
+            if file != chapters {
+                outputs += 1;
+            }
+        }
+    }
+
+    zip.start_file(SUMMARY_ENTRY, options)
+        .map_err(archive_error)?;
+    zip.write_all(report.to_csv().as_bytes())?;
+
+    let failures: Vec<_> = report.failures().collect();
+    if !failures.is_empty() {
+        zip.start_file(ERRORS_ENTRY, options)
+            .map_err(archive_error)?;
+        for outcome in &failures {
+            if let FileStatus::Failed(reason) = &outcome.status {
+                writeln!(zip, "{}: {}", outcome.input.display(), reason)?;
+            }
+        }
+    }
+    let file = zip.finish().map_err(archive_error)?;
+
+    Ok((
+        file,
+        ArchiveSummary {
+            outputs,
+            failures: failures.len(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::FileOutcome;
+    use crate::types::TranscriptionResult;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    fn result() -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration: 10.0,
+            segments: vec![],
+            full_text: String::new(),
+            transcription_time: 1.0,
+            real_time_factor: 10.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_archive_keeps_relative_paths_and_failures() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("out");
+        std::fs::create_dir_all(out.join("fr")).unwrap();
+        std::fs::write(out.join("a.srt"), "1\n").unwrap();
+        std::fs::write(out.join("fr").join("b.srt"), "2\n").unwrap();
+        std::fs::write(out.join("fr").join("b.chapters.txt"), "00:00 Intro\n").unwrap();
+
+        let mut report = BatchReport::new();
+        for name in ["a.srt", "fr/b.srt"] {
+            report.push(FileOutcome::succeeded(
+                name.into(),
+                Some(out.join(name)),
+                &result(),
+            ));
+        }
+        std::fs::write(out.join("a.txt"), "text\n").unwrap();
+        report.outcomes[0].artifacts = vec![out.join("a.txt")];
+        report.push(FileOutcome::failed("c.wav".into(), None, "decode error"));
+
+        let path = dir.path().join("results.zip");
+        let summary =
+            write_archive(&path, &report, Some(&out), ArchiveCompression::Deflate).unwrap();
+        assert_eq!(
+            summary,
+            ArchiveSummary {
+                outputs: 3,
+                failures: 1
+            }
+        );
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "a.srt",
+                "a.txt",
+                "errors.txt",
+                "fr/b.chapters.txt",
+                "fr/b.srt",
+                "summary.csv"
+            ]
+        );
+        let mut errors = String::new();
+        archive
+            .by_name(ERRORS_ENTRY)
+            .unwrap()
+            .read_to_string(&mut errors)
+            .unwrap();
+        assert_eq!(errors, "c.wav: decode error\n");
+    }
+
+    #[test]
+    fn test_compression_names() {
+        assert_eq!("Stored".parse(), Ok(ArchiveCompression::Stored));
+        assert_eq!(ArchiveCompression::default().to_string(), "deflate");
+        assert!("bzip2".parse::<ArchiveCompression>().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(feature = "preprocess")]
+use crate::backend::SAMPLE_RATE;
+#[cfg(feature = "preprocess")]
+use crate::error::{Result, TranscriptionError};
+#[cfg(feature = "preprocess")]
+use std::path::Path;
+
+/// Average interleaved frames of `channels` samples into mono
+pub fn downmix(interleaved: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return interleaved.to_vec();
+    }
+    interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Linear-interpolation resampling; adequate for speech recognition input
+pub fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+    (0..out_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position.floor() as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Split interleaved frames into one buffer per channel
+pub fn split_channels(interleaved: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    let channels = channels.max(1);
+    (0..channels)
+        .map(|channel| {
+            interleaved
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+/// Read a WAV file as mono 16 kHz samples in [-1, 1]
+#[cfg(feature = "preprocess")]
+pub fn read_wav_16k(path: &Path) -> Result<Vec<f32>> {
+    let (spec, interleaved) = read_wav(path)?;
+    let mono = downmix(&interleaved, spec.channels as usize);
+    Ok(resample_linear(&mono, spec.sample_rate, SAMPLE_RATE))
+}
+
+/// Read a WAV file as one buffer of 16 kHz samples per channel
+#[cfg(feature = "preprocess")]
+pub fn read_wav_channels_16k(path: &Path) -> Result<Vec<Vec<f32>>> {
+    let (spec, interleaved) = read_wav(path)?;
+    Ok(split_channels(&interleaved, spec.channels as usize)
+        .iter()
+        .map(|channel| resample_linear(channel, spec.sample_rate, SAMPLE_RATE))
+        .collect())
+}
+
+/// Interleaved samples in [-1, 1], with the format they were stored in
+#[cfg(feature = "preprocess")]
+fn read_wav(path: &Path) -> Result<(hound::WavSpec, Vec<f32>)> {
+    let is_wav = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+    if !is_wav {
+        return Err(TranscriptionError::UnsupportedFormat(
+            "only WAV files can be read without Python".to_string(),
+        ));
+    }
+    let mut reader = hound::WavReader::open(path).map_err(|e| {
+        TranscriptionError::InvalidPath(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<std::result::Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<std::result::Result<_, _>>()
+        }
+    }
+    .map_err(|e| {
+        TranscriptionError::InvalidPath(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    Ok((spec, interleaved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_and_downmix() {
+        assert_eq!(downmix(&[1.0, 0.0, 0.5, 0.5], 2), vec![0.5, 0.5]);
+        let resampled = resample_linear(&[0.0, 1.0, 2.0, 3.0], 32_000, 16_000);
+        assert_eq!(resampled, vec![0.0, 2.0]);
+        let upsampled = resample_linear(&[0.0, 1.0], 8_000, 16_000);
+        assert_eq!(upsampled, vec![0.0, 0.5, 1.0, 1.0]);
+        assert_eq!(
+            split_channels(&[1.0, -1.0, 0.5, -0.5], 2),
+            vec![vec![1.0, 0.5], vec![-1.0, -0.5]]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+#[cfg(feature = "candle")]
+use crate::candle::CandleTranscriber;
+use crate::error::Result;
+#[cfg(not(all(feature = "whispercpp", feature = "candle")))]
+use crate::error::TranscriptionError;
+use crate::transcriber::{FasterWhisperTranscriber, WarmupReport};
+use crate::types::{
+    Backend, ModelConfig, TranscriptionOptions, TranscriptionResult, TranscriptionSegment,
+};
+#[cfg(feature = "whispercpp")]
+use crate::whispercpp::WhisperCppTranscriber;
+use std::path::Path;
+use std::time::Instant;
+
+/// Sample rate `transcribe_samples` expects: mono 16 kHz, as every Whisper model uses
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Half a second of silence, enough to run the encoder and decoder once
+const WARMUP_SAMPLES: usize = SAMPLE_RATE as usize / 2;
+
+/// Receives a transcription piece by piece as it is decoded; see
+/// `TranscriptionBackend::transcribe_path_into`
+pub trait SegmentSink {
+    /// Called once, before any segment, when the language and duration are known
+    fn begin(&mut self, language: &str, language_probability: f64, duration: f64) -> Result<()>;
+
+    fn segment(&mut self, segment: &TranscriptionSegment) -> Result<()>;
+}
+
+/// A speech-to-text engine.
+///
+/// Implementations load their model lazily on the first transcription, so `load` is only
+/// needed to pay that cost up front. They are `Send + Sync` and can be shared as
+/// `Arc<dyn TranscriptionBackend>`.
+pub trait TranscriptionBackend: Send + Sync {
+    fn config(&self) -> &ModelConfig;
+
+    /// Decoding options used when the caller has none of its own
+    fn options(&self) -> &TranscriptionOptions;
+
+    /// Load the model now instead of on the first transcription
+    fn load(&self) -> Result<()>;
+
+    /// Whether a model is currently held in memory
+    fn is_loaded(&self) -> bool;
+
+    fn transcribe_path(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult>;
+
+    /// Like `transcribe_path`, calling `on_segment` with each segment as it is decoded and
+    /// the audio's duration in seconds. Backends that can't stream call it for every segment
+    /// once the transcription is done.
+    fn transcribe_path_streaming(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+        on_segment: &mut dyn FnMut(&TranscriptionSegment, f64),
+    ) -> Result<TranscriptionResult> {
+        let result = self.transcribe_path(audio_path, options)?;
+        for segment in &result.segments {
+            on_segment(segment, result.duration);
+        }
+        Ok(result)
+    }
+
+    /// Like `transcribe_path_streaming`, but hands each segment to `sink` without keeping
+    /// it, for transcripts too long to hold in memory twice. The result has everything
+    /// except its segments. Backends that can't stream decode the whole file first.
+    fn transcribe_path_into(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+        sink: &mut dyn SegmentSink,
+    ) -> Result<TranscriptionResult> {
+        let mut result = self.transcribe_path(audio_path, options)?;
+        sink.begin(
+            &result.language,
+            result.language_probability,
+            result.duration,
+        )?;
+        for segment in std::mem::take(&mut result.segments) {
+            sink.segment(&segment)?;
+        }
+        Ok(result)
+    }
+
+    /// Transcribe mono samples in [-1, 1] at `SAMPLE_RATE`
+    fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult>;
+
+    /// What the model runs on, for display
+    fn device_info(&self) -> Result<String>;
+
+    /// Drop the loaded model, returning whether one was loaded. The next transcription loads
+    /// it again.
+    fn unload(&self) -> bool;
+
+    /// Load the model and transcribe half a second of silence, so the first real request
+    /// doesn't pay for setup
+    fn warmup(&self) -> Result<WarmupReport> {
+        let already_loaded = self.is_loaded();
+        let load_start = Instant::now();
+        self.load()?;
+        let load_time = load_start.elapsed();
+
+        let warmup_start = Instant::now();
+        let options = TranscriptionOptions {
+            beam_size: Some(1),
+            ..self.options().clone()
+        };
+        self.transcribe_samples(&[0.0; WARMUP_SAMPLES], &options)?;
+
+        Ok(WarmupReport {
+            load_time,
+            warmup_time: warmup_start.elapsed(),
+            downloaded: false,
+            already_loaded,
+        })
+    }
+}
+
+/// Build the backend `config.backend` names, failing if it isn't compiled into this build
+pub fn create(
+    config: ModelConfig,
+    options: TranscriptionOptions,
+) -> Result<Box<dyn TranscriptionBackend>> {
+    match config.backend {
+        Backend::FasterWhisper => Ok(Box::new(
+            FasterWhisperTranscriber::new(config)?.with_options(options)?,
+        )),
+        #[cfg(feature = "whispercpp")]
+        Backend::WhisperCpp => Ok(Box::new(
+            WhisperCppTranscriber::new(config)?.with_options(options)?,
+        )),
+        #[cfg(feature = "candle")]
+        Backend::Candle => Ok(Box::new(
+            CandleTranscriber::new(config)?.with_options(options)?,
+        )),
+        #[cfg(not(all(feature = "whispercpp", feature = "candle")))]
+        backend => Err(TranscriptionError::ConfigError(format!(
+            "The {} backend isn't part of this build; rebuild with --features {}",
+            backend, backend
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockBackend;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_trait_objects() {
+        let backend: Box<dyn TranscriptionBackend> = Box::new(MockBackend::new());
+        assert!(!backend.is_loaded());
+        let result = backend
+            .transcribe_samples(&[0.0; 8_000], backend.options())
+            .unwrap();
+        assert_eq!(result.duration, 0.5);
+        assert!(backend.unload());
+        assert!(!backend.unload());
+
+        let report = backend.warmup().unwrap();
+        assert!(!report.already_loaded);
+        assert!(backend.is_loaded());
+        assert!(backend.warmup().unwrap().already_loaded);
+
+        let shared: Arc<dyn TranscriptionBackend> = Arc::new(MockBackend::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || {
+                    shared
+                        .transcribe_path(Path::new("clip.wav"), shared.options())
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().full_text, "mock");
+        }
+    }
+
+    #[test]
+    fn test_concrete_types_are_backends() {
+        fn assert_backend<T: TranscriptionBackend + 'static>() {}
+        assert_backend::<FasterWhisperTranscriber>();
+        #[cfg(feature = "whispercpp")]
+        assert_backend::<WhisperCppTranscriber>();
+        #[cfg(feature = "candle")]
+        assert_backend::<CandleTranscriber>();
+    }
+
+    #[test]
+    fn test_create() {
+        let backend = create(
+            ModelConfig::new("base", "cpu", "float32"),
+            TranscriptionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(backend.config().backend, Backend::FasterWhisper);
+        assert!(!backend.is_loaded());
+
+        assert!(create(
+            ModelConfig::new("invalid", "cpu", "float32"),
+            TranscriptionOptions::default()
+        )
+        .is_err());
+
+        if !cfg!(feature = "whispercpp") {
+            let mut config = ModelConfig::new("base", "cpu", "float32");
+            config.backend = Backend::WhisperCpp;
+            let err = create(config, TranscriptionOptions::default())
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("--features whispercpp"));
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::error::{Result, TranscriptionError};
+use crate::template::civil_date;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How backups of a replaced output are named
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackupStyle {
+    /// `name.json.bak` for the newest, then `name.json.bak.1`, `name.json.bak.2`, ...
+    #[default]
+    Simple,
+    /// `name.json.2024-03-09T142501.250Z.bak`, named for when the backup was made
+    Timestamp,
+}
+
+impl FromStr for BackupStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "simple" | "numbered" => Ok(BackupStyle::Simple),
+            "timestamp" | "time" => Ok(BackupStyle::Timestamp),
+            other => Err(format!(
+                "Invalid backup style: {} (expected simple or timestamp)",
+                other
+            )),
+        }
+    }
+}
+
+/// Keep what an output held before it's overwritten, e.g. a transcript with manual fixes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupOptions {
+    pub style: BackupStyle,
+    /// Most backups kept per output; older ones are deleted
+    pub keep: usize,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            style: BackupStyle::Simple,
+            keep: 3,
+        }
+    }
+}
+
+impl BackupOptions {
+    pub fn new(style: BackupStyle, keep: usize) -> Result<Self> {
+        if keep == 0 {
+            return Err(TranscriptionError::ConfigError(
+                "--backup-keep must be at least 1".to_string(),
+            ));
+        }
+        Ok(Self { style, keep })
+    }
+
+    /// Move `path` aside as its newest backup, deleting backups beyond `keep`. Returns
+    /// where it went, or `None` when there was nothing at `path` to keep.
+    pub fn back_up(&self, path: &Path) -> Result<Option<PathBuf>> {
+        self.back_up_at(path, SystemTime::now())
+    }
+
+    fn back_up_at(&self, path: &Path, now: SystemTime) -> Result<Option<PathBuf>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let backup = match self.style {
+            BackupStyle::Simple => self.rotate(path)?,
+            BackupStyle::Timestamp => {
+                let backup = free_timestamp_path(path, now);
+                fs::rename(path, &backup)?;
+                self.prune_timestamped(path)?;
+                backup
+            }
+        };
+        Ok(Some(backup))
+    }
+
+    /// Shift each numbered backup up one, dropping those that would pass `keep`, then
+    /// move `path` to `.bak`
+    fn rotate(&self, path: &Path) -> Result<PathBuf> {
+        let mut existing = backups(path, |suffix| match suffix {
+            "bak" => Some(0),
+            _ => suffix.strip_prefix("bak.")?.parse::<usize>().ok(),
+        })?;
+        // Highest first, so nothing is renamed onto a backup that hasn't moved yet
+        existing.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+        for (index, backup) in existing {
+            if index + 1 >= self.keep {
+                fs::remove_file(&backup)?;
+            } else {
+                fs::rename(&backup, simple_path(path, index + 1))?;
+            }
+        }
+        let newest = simple_path(path, 0);
+        fs::rename(path, &newest)?;
+        Ok(newest)
+    }
+
+    /// Delete all but the `keep` newest timestamped backups of `path`
+    fn prune_timestamped(&self, path: &Path) -> Result<()> {
+        let mut existing = backups(path, |suffix| {
+            let stamp = suffix.strip_suffix(".bak")?;
+            is_timestamp(stamp).then(|| stamp.to_string())
+        })?;
+        // The stamps are fixed-width, so they sort by time
+        existing.sort_by(|a, b| b.0.cmp(&a.0));
+        for (_, backup) in existing.into_iter().skip(self.keep) {
+            fs::remove_file(backup)?;
+        }
+        Ok(())
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The `index`th numbered backup of `path`, 0 being the newest
+fn simple_path(path: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => with_suffix(path, "bak"),
+        n => with_suffix(path, &format!("bak.{}", n)),
+    }
+}
+
+/// A timestamped backup name for `path` nothing has taken yet; backups made within the
+/// same millisecond are stamped a millisecond apart so their order survives
+fn free_timestamp_path(path: &Path, now: SystemTime) -> PathBuf {
+    let mut at = now;
+    loop {
+        let candidate = with_suffix(path, &format!("{}.bak", timestamp(at)));
+        if !candidate.exists() {
+            return candidate;
+        }
+        at += Duration::from_millis(1);
+    }
+}
+
+/// Backups of `path` in its directory, with whatever `parse` makes of the part of their
+/// name after `path`'s, skipping names it rejects
+fn backups<K>(path: &Path, parse: impl Fn(&str) -> Option<K>) -> Result<Vec<(K, PathBuf)>> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}.", name);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(suffix) = file_name.to_str().and_then(|n| n.strip_prefix(&prefix)) else {
+            continue;
+        };
+        if let Some(key) = parse(suffix) {
+            found.push((key, path.with_file_name(&file_name)));
+        }
+    }
+    Ok(found)
+}
+
+/// `2024-03-09T142501.250Z`: UTC to the millisecond, fixed width and safe in file names
+fn timestamp(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let of_day = seconds % 86_400;
+    format!(
+        "{}T{:02}{:02}{:02}.{:03}Z",
+        civil_date(seconds),
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Whether `s` has the shape `timestamp` gives
+fn is_timestamp(s: &str) -> bool {
+    const SHAPE: &str = "dddd-dd-ddTdddddd.dddZ";
+    s.len() == SHAPE.len()
+        && s.bytes().zip(SHAPE.bytes()).all(|(c, shape)| match shape {
+            b'd' => c.is_ascii_digit(),
+            _ => c == shape,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_simple_backups_rotate_and_prune() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("talk.json");
+        let options = BackupOptions::new(BackupStyle::Simple, 3).unwrap();
+        assert_eq!(options.back_up(&output).unwrap(), None);
+
+        for version in 1..=5 {
+            fs::write(&output, format!("v{}", version)).unwrap();
+            let backup = options.back_up(&output).unwrap().unwrap();
+            assert_eq!(backup, dir.path().join("talk.json.bak"));
+            assert!(!output.exists());
+        }
+        // Newest first, and only three kept
+        assert_eq!(read(&dir.path().join("talk.json.bak")), "v5");
+        assert_eq!(read(&dir.path().join("talk.json.bak.1")), "v4");
+        assert_eq!(read(&dir.path().join("talk.json.bak.2")), "v3");
+        assert!(!dir.path().join("talk.json.bak.3").exists());
+
+        // Lowering the limit drops the oldest; other outputs' backups are left alone
+        fs::write(dir.path().join("talk.srt.bak"), "srt").unwrap();
+        fs::write(&output, "v6").unwrap();
+        BackupOptions::new(BackupStyle::Simple, 1)
+            .unwrap()
+            .back_up(&output)
+            .unwrap();
+        let mut names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["talk.json.bak", "talk.srt.bak"]);
+        assert_eq!(read(&dir.path().join("talk.json.bak")), "v6");
+    }
+
+    #[test]
+    fn test_timestamped_backups_prune_oldest() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("talk.srt");
+        let options = BackupOptions::new(BackupStyle::Timestamp, 2).unwrap();
+        let start = UNIX_EPOCH + Duration::from_secs(1_709_942_400);
+
+        let mut backups = Vec::new();
+        for (version, offset) in [(1, 0), (2, 90), (3, 90), (4, 3600)] {
+            fs::write(&output, format!("v{}", version)).unwrap();
+            let at = start + Duration::from_secs(offset);
+            backups.push(options.back_up_at(&output, at).unwrap().unwrap());
+        }
+        assert_eq!(
+            backups[0],
+            dir.path().join("talk.srt.2024-03-09T000000.000Z.bak")
+        );
+        // Same moment as the one before it, so a millisecond later
+        assert_eq!(
+            backups[2],
+            dir.path().join("talk.srt.2024-03-09T000130.001Z.bak")
+        );
+        assert!(!backups[0].exists());
+        assert!(!backups[1].exists());
+        assert_eq!(read(&backups[2]), "v3");
+        assert_eq!(read(&backups[3]), "v4");
+    }
+
+    #[test]
+    fn test_parse_style_and_keep() {
+        assert_eq!("Timestamp".parse(), Ok(BackupStyle::Timestamp));
+        assert_eq!("simple".parse(), Ok(BackupStyle::Simple));
+        assert!("rolling".parse::<BackupStyle>().is_err());
+        let err = BackupOptions::new(BackupStyle::Simple, 0).unwrap_err();
+        assert_eq!(err.kind(), "config");
+        assert!(is_timestamp("2024-03-09T000130.001Z"));
+        assert!(!is_timestamp("2024-03-09T000130Z"));
+    }
+}
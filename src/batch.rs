@@ -0,0 +1,1574 @@
+use crate::backend::TranscriptionBackend;
+use crate::error::{DecodeFailure, Result, TranscriptionError};
+use crate::output;
+use crate::plan::{self, Discovery, DuplicateGroup, PlanOptions, PlannedAction, PlannedFile};
+use crate::space::OutputBudget;
+use crate::state::BatchState;
+use crate::stats;
+use crate::timing::{PhaseTimings, TimingReport};
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+/// Process exit code when every file succeeded (or nothing needed doing)
+pub const EXIT_SUCCESS: i32 = 0;
+/// Process exit code when no file could be transcribed
+pub const EXIT_TOTAL_FAILURE: i32 = 1;
+/// Process exit code when some files succeeded and others failed
+pub const EXIT_PARTIAL_FAILURE: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "lowercase")]
+pub enum FileStatus {
+    Succeeded,
+    Failed(String),
+    Skipped(String),
+}
+
+/// Columns of `BatchReport::to_csv`
+pub const CSV_HEADER: &str = "file,status,duration,language,language_probability,\
+transcription_time,real_time_factor,segments,words,output,error";
+
+/// Quote `value` for CSV when it holds a separator, quote or line break
+pub(crate) fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// The parts of a `TranscriptionResult` a batch report keeps once the result is written out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultSummary {
+    pub language: String,
+    pub language_probability: f64,
+    pub duration: f64,
+    pub transcription_time: f64,
+    pub real_time_factor: f64,
+    pub segments_count: usize,
+    #[serde(default)]
+    pub word_count: usize,
+    /// Where the file's time went, when the backend measured it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<PhaseTimings>,
+}
+
+impl From<&TranscriptionResult> for ResultSummary {
+    fn from(result: &TranscriptionResult) -> Self {
+        Self {
+            language: result.language.clone(),
+            language_probability: result.language_probability,
+            duration: result.duration,
+            transcription_time: result.transcription_time,
+            real_time_factor: result.real_time_factor,
+            segments_count: result.segments.len(),
+            word_count: stats::word_count(result),
+            timings: result.timings,
+        }
+    }
+}
+
+/// What happened to a single input file of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileOutcome {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    #[serde(flatten)]
+    pub status: FileStatus,
+    pub result: Option<ResultSummary>,
+    /// The identical input whose transcript was copied to this file's output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+    /// `TranscriptionError::kind` of the failure, when it came from a transcription error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+    /// Why the audio couldn't be decoded, for `audio_decode` failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decode_failure: Option<DecodeFailure>,
+    /// Outputs written beside `output` in the other requested formats
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<PathBuf>,
+}
+
+impl FileOutcome {
+    pub fn succeeded(
+        input: PathBuf,
+        output: Option<PathBuf>,
+        result: &TranscriptionResult,
+    ) -> Self {
+        Self {
+            input,
+            output,
+            status: FileStatus::Succeeded,
+            result: Some(result.into()),
+            duplicate_of: None,
+            error_kind: None,
+            decode_failure: None,
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// A file that was given a copy of `primary`'s result. It carries no result summary, so
+    /// its audio isn't counted twice in the statistics.
+    pub fn duplicate(input: PathBuf, output: Option<PathBuf>, primary: PathBuf) -> Self {
+        Self {
+            input,
+            output,
+            status: FileStatus::Succeeded,
+            result: None,
+            duplicate_of: Some(primary),
+            error_kind: None,
+            decode_failure: None,
+            artifacts: Vec::new(),
+        }
+    }
+
+    pub fn failed(input: PathBuf, output: Option<PathBuf>, error: impl ToString) -> Self {
+        Self {
+            input,
+            output,
+            status: FileStatus::Failed(error.to_string()),
+            result: None,
+            duplicate_of: None,
+            error_kind: None,
+            decode_failure: None,
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// A failed file, keeping the error's kind and any outputs the other formats did
+    /// write. The reason leaves out the path the error carries, since the outcome already
+    /// records it as `input`.
+    pub fn from_error(input: PathBuf, output: Option<PathBuf>, error: &TranscriptionError) -> Self {
+        let artifacts = match error.inner() {
+            TranscriptionError::OutputWriteFailed { written, .. } => written
+                .iter()
+                .filter(|path| Some(*path) != output.as_ref())
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+        let decode_failure = match error.inner() {
+            TranscriptionError::AudioDecodeError { reason, .. } => Some(*reason),
+            _ => None,
+        };
+        Self {
+            error_kind: Some(error.kind().to_string()),
+            decode_failure,
+            artifacts,
+            ..Self::failed(input, output, error.inner())
+        }
+    }
+
+    pub fn skipped(input: PathBuf, reason: impl ToString) -> Self {
+        Self {
+            input,
+            output: None,
+            status: FileStatus::Skipped(reason.to_string()),
+            result: None,
+            duplicate_of: None,
+            error_kind: None,
+            decode_failure: None,
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// The outcome of a planned file that won't be transcribed
+    pub fn not_transcribed(file: PlannedFile) -> Self {
+        match file.action {
+            PlannedAction::Missing => {
+                error!("✗ Input file not found: {}", file.input.display());
+                Self::failed(file.input, file.output, "input file not found")
+            }
+            PlannedAction::AlreadyDone => Self::skipped(file.input, "done in a previous run"),
+            PlannedAction::PreviouslyFailed => {
+                Self::skipped(file.input, "failed in a previous run (use --retry-failed)")
+            }
+            PlannedAction::TooLong => {
+                let minutes = file.duration.unwrap_or_default() / 60.0;
+                warn!(
+                    "Skipping {}: {:.1} minutes is over --max-duration-minutes",
+                    file.input.display(),
+                    minutes
+                );
+                Self {
+                    error_kind: Some("duration_limit".to_string()),
+                    ..Self::skipped(
+                        file.input,
+                        format!("{:.1} minutes is over --max-duration-minutes", minutes),
+                    )
+                }
+            }
+            _ => Self::skipped(file.input, "output already exists"),
+        }
+    }
+
+    pub fn with_artifacts(mut self, artifacts: Vec<PathBuf>) -> Self {
+        self.artifacts = artifacts;
+        self
+    }
+
+    /// `output` followed by the artifacts
+    pub fn written(&self) -> impl Iterator<Item = &Path> {
+        self.output
+            .iter()
+            .chain(&self.artifacts)
+            .map(PathBuf::as_path)
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self.status, FileStatus::Failed(_))
+    }
+}
+
+/// The slowest file of a batch, by transcription time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowestFile {
+    pub input: PathBuf,
+    pub transcription_time: f64,
+    pub real_time_factor: f64,
+}
+
+/// Aggregate timing statistics over the successful files of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchStatistics {
+    pub files_succeeded: usize,
+    pub files_failed: usize,
+    pub files_skipped: usize,
+    pub total_audio_seconds: f64,
+    pub total_transcription_seconds: f64,
+    pub wall_time_seconds: f64,
+    /// Total audio duration divided by the batch's wall time
+    pub aggregate_real_time_factor: f64,
+    pub average_real_time_factor: f64,
+    pub slowest_file: Option<SlowestFile>,
+    pub failed_files: Vec<PathBuf>,
+    /// Successful files per transcription language, forced or detected
+    #[serde(default)]
+    pub languages: BTreeMap<String, usize>,
+}
+
+impl BatchStatistics {
+    pub fn total_audio_hours(&self) -> f64 {
+        self.total_audio_seconds / 3600.0
+    }
+}
+
+/// Per-file outcomes of a batch run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub outcomes: Vec<FileOutcome>,
+    /// Wall-clock time of the whole batch, set by whoever drove it
+    pub wall_time_seconds: f64,
+}
+
+#[derive(Serialize)]
+struct BatchSummaryFile<'a> {
+    statistics: BatchStatistics,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    duplicates: Vec<DuplicateGroup>,
+    files: &'a [FileOutcome],
+}
+
+impl BatchReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, outcome: FileOutcome) {
+        self.outcomes.push(outcome);
+    }
+
+    pub fn succeeded(&self) -> usize {
+        self.count(|status| matches!(status, FileStatus::Succeeded))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, FileStatus::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|status| matches!(status, FileStatus::Skipped(_)))
+    }
+
+    fn count(&self, predicate: impl Fn(&FileStatus) -> bool) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| predicate(&outcome.status))
+            .count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &FileOutcome> {
+        self.outcomes.iter().filter(|outcome| outcome.is_failure())
+    }
+
+    /// Failed files counted by why their audio couldn't be decoded, most common first
+    pub fn decode_failures(&self) -> Vec<(DecodeFailure, usize)> {
+        let mut counts: BTreeMap<DecodeFailure, usize> = BTreeMap::new();
+        for reason in self.failures().filter_map(|outcome| outcome.decode_failure) {
+            *counts.entry(reason).or_default() += 1;
+        }
+        let mut counts: Vec<(DecodeFailure, usize)> = counts.into_iter().collect();
+        counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        counts
+    }
+
+    /// Inputs that were given a copy of an identical file's transcript, grouped by that file
+    pub fn duplicate_groups(&self) -> Vec<DuplicateGroup> {
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        for outcome in &self.outcomes {
+            let Some(primary) = &outcome.duplicate_of else {
+                continue;
+            };
+            match groups.iter_mut().find(|group| group.primary == *primary) {
+                Some(group) => group.duplicates.push(outcome.input.clone()),
+                None => groups.push(DuplicateGroup {
+                    primary: primary.clone(),
+                    duplicates: vec![outcome.input.clone()],
+                }),
+            }
+        }
+        groups
+    }
+
+    /// One-line summary such as `68 ok, 12 failed, 3 skipped`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ok, {} failed, {} skipped",
+            self.succeeded(),
+            self.failed(),
+            self.skipped()
+        )
+    }
+
+    pub fn statistics(&self) -> BatchStatistics {
+        let results: Vec<(&Path, &ResultSummary)> = self
+            .outcomes
+            .iter()
+            .filter_map(|o| o.result.as_ref().map(|r| (o.input.as_path(), r)))
+            .collect();
+
+        // Summing an empty f64 iterator yields -0.0, so fold from +0.0 instead
+        let total_audio_seconds = results.iter().fold(0.0, |acc, (_, r)| acc + r.duration);
+        let total_transcription_seconds = results
+            .iter()
+            .fold(0.0, |acc, (_, r)| acc + r.transcription_time);
+        let average_real_time_factor = if results.is_empty() {
+            0.0
+        } else {
+            results.iter().map(|(_, r)| r.real_time_factor).sum::<f64>() / results.len() as f64
+        };
+        let aggregate_real_time_factor = if self.wall_time_seconds > 0.0 {
+            total_audio_seconds / self.wall_time_seconds
+        } else {
+            0.0
+        };
+        let slowest_file = results
+            .iter()
+            .max_by(|a, b| {
+                a.1.transcription_time
+                    .partial_cmp(&b.1.transcription_time)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(input, r)| SlowestFile {
+                input: input.to_path_buf(),
+                transcription_time: r.transcription_time,
+                real_time_factor: r.real_time_factor,
+            });
+
+        BatchStatistics {
+            files_succeeded: self.succeeded(),
+            files_failed: self.failed(),
+            files_skipped: self.skipped(),
+            total_audio_seconds,
+            total_transcription_seconds,
+            wall_time_seconds: self.wall_time_seconds,
+            aggregate_real_time_factor,
+            average_real_time_factor,
+            slowest_file,
+            failed_files: self.failures().map(|o| o.input.clone()).collect(),
+            languages: results.iter().fold(BTreeMap::new(), |mut counts, (_, r)| {
+                *counts.entry(r.language.clone()).or_default() += 1;
+                counts
+            }),
+        }
+    }
+
+    /// Write the statistics and every per-file outcome as pretty JSON
+    pub fn write_summary_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let summary = BatchSummaryFile {
+            statistics: self.statistics(),
+            duplicates: self.duplicate_groups(),
+            files: &self.outcomes,
+        };
+        output::write_atomic(path.as_ref(), serde_json::to_string_pretty(&summary)?)?;
+        Ok(())
+    }
+
+    /// One CSV row per input, with a header row. Fields holding commas, quotes or line
+    /// breaks are quoted; non-UTF-8 paths are converted lossily.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for outcome in &self.outcomes {
+            let (status, error) = match &outcome.status {
+                FileStatus::Succeeded => ("succeeded", ""),
+                FileStatus::Failed(reason) => ("failed", reason.as_str()),
+                FileStatus::Skipped(_) => ("skipped", ""),
+            };
+            let result = outcome.result.as_ref();
+            let number = |value: Option<String>| value.unwrap_or_default();
+            let fields = [
+                outcome.input.to_string_lossy().into_owned(),
+                status.to_string(),
+                number(result.map(|r| format!("{:.3}", r.duration))),
+                number(result.map(|r| r.language.clone())),
+                number(result.map(|r| format!("{:.4}", r.language_probability))),
+                number(result.map(|r| format!("{:.3}", r.transcription_time))),
+                number(result.map(|r| format!("{:.2}", r.real_time_factor))),
+                number(result.map(|r| r.segments_count.to_string())),
+                number(result.map(|r| r.word_count.to_string())),
+                outcome
+                    .written()
+                    .map(|path| path.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                error.to_string(),
+            ];
+            let row: Vec<Cow<str>> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn write_summary_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        output::write_atomic(path.as_ref(), self.to_csv())?;
+        Ok(())
+    }
+
+    /// Phase timings of the transcribed files that have them, with percentiles per phase
+    pub fn timing_report(&self) -> TimingReport {
+        TimingReport::new(self.outcomes.iter().filter_map(|outcome| {
+            let timings = outcome.result.as_ref()?.timings?;
+            Some((outcome.input.clone(), timings))
+        }))
+    }
+
+    /// Write `timing_report` as pretty JSON
+    pub fn write_timing_report<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let report = serde_json::to_string_pretty(&self.timing_report())?;
+        output::write_atomic(path.as_ref(), report)?;
+        Ok(())
+    }
+
+    /// 0 when nothing failed, 1 when nothing succeeded, 2 for a partial failure
+    pub fn exit_code(&self) -> i32 {
+        match (self.succeeded(), self.failed()) {
+            (_, 0) => EXIT_SUCCESS,
+            (0, _) => EXIT_TOTAL_FAILURE,
+            _ => EXIT_PARTIAL_FAILURE,
+        }
+    }
+}
+
+type FileCallback<'a> = Box<dyn FnMut(&Path, &Result<TranscriptionResult>) + 'a>;
+type FileCompleteCallback<'a> = Box<dyn FnMut(FileOutcome) + Send + 'a>;
+
+/// How `transcribe_many` works through its files
+pub struct BatchOptions<'a> {
+    /// Files transcribed at the same time, each on its own thread
+    pub jobs: usize,
+    /// Keep going after a file fails; otherwise files not yet started are skipped
+    pub continue_on_error: bool,
+    /// Once set, no further file is started; those under way still finish
+    pub cancel: Option<&'a AtomicBool>,
+    on_file: Option<RefCell<FileCallback<'a>>>,
+    on_file_complete: Option<Mutex<FileCompleteCallback<'a>>>,
+}
+
+impl Default for BatchOptions<'_> {
+    fn default() -> Self {
+        Self {
+            jobs: 1,
+            continue_on_error: true,
+            cancel: None,
+            on_file: None,
+            on_file_complete: None,
+        }
+    }
+}
+
+impl<'a> BatchOptions<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Call `callback` as each file finishes, in the order they finish. It runs on the
+    /// calling thread, so it needn't be `Send`.
+    pub fn on_file<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Path, &Result<TranscriptionResult>) + 'a,
+    {
+        self.on_file = Some(RefCell::new(Box::new(callback)));
+        self
+    }
+
+    /// Call `callback` with each file's outcome (its result summary with timings, or its
+    /// error) the moment it finishes, before the batch moves on. It runs on the worker thread
+    /// that transcribed the file, one call at a time in the order files finish, which is
+    /// also their order in the report. A panic in it is caught and logged; the batch goes on.
+    pub fn on_file_complete<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(FileOutcome) + Send + 'a,
+    {
+        self.on_file_complete = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+    }
+}
+
+/// Transcribe `paths` with one backend, so its model is loaded once and shared by every
+/// file. Each file's outcome is in the report; its output path is left unset.
+pub fn transcribe_many(
+    backend: &dyn TranscriptionBackend,
+    paths: &[PathBuf],
+    options: &BatchOptions,
+) -> BatchReport {
+    let transcription_options = backend.options().clone();
+    run_batch(paths.iter().cloned(), options, |path| {
+        backend.transcribe_path(path, &transcription_options)
+    })
+}
+
+/// Run `transcribe` over `inputs`, `options.jobs` files at a time.
+///
+/// Worker threads take files from `inputs` as they become free, so `inputs` may still be
+/// producing paths (e.g. a `plan::Discovery`) while the first files are transcribed. The
+/// workers run the `on_file_complete` callback; results come back to the calling thread,
+/// which runs the `on_file` callback and builds the report. Outcomes are in the order files
+/// finish and carry their paths. Files never started, because of a failure or cancellation,
+/// are reported as skipped, which waits for `inputs` to end.
+pub fn run_batch<I, F>(inputs: I, options: &BatchOptions, transcribe: F) -> BatchReport
+where
+    I: IntoIterator<Item = PathBuf>,
+    I::IntoIter: Send,
+    F: Fn(&Path) -> Result<TranscriptionResult> + Sync,
+{
+    let started = Instant::now();
+    let inputs = inputs.into_iter();
+    let jobs = options
+        .jobs
+        .clamp(1, inputs.size_hint().1.unwrap_or(usize::MAX).max(1));
+    let next = Mutex::new(inputs);
+    let failed = AtomicBool::new(false);
+    let cancel = options.cancel;
+    let continue_on_error = options.continue_on_error;
+    let stopping = || {
+        failed.load(Ordering::SeqCst) || cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+    };
+    let mut report = BatchReport::new();
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, failed, stopping, transcribe) = (&next, &failed, &stopping, &transcribe);
+            let on_complete = options.on_file_complete.as_ref();
+            scope.spawn(move || {
+                while !stopping() {
+                    let input = next.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let Some(input) = input else {
+                        break;
+                    };
+                    let result = transcribe(&input);
+                    // Set here rather than when the result is received, so no other file
+                    // starts in between
+                    if result.is_err() && !continue_on_error {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    let outcome = match &result {
+                        Ok(result) => FileOutcome::succeeded(input.clone(), None, result),
+                        Err(e) => FileOutcome::from_error(input.clone(), None, e),
+                    };
+                    // Held until the result is sent, so the report's order is the order
+                    // the callback saw
+                    let _callback = on_complete.map(|callback| {
+                        let mut callback = callback.lock().unwrap_or_else(PoisonError::into_inner);
+                        let call = AssertUnwindSafe(|| callback(outcome.clone()));
+                        if std::panic::catch_unwind(call).is_err() {
+                            warn!("The file callback panicked on {}", input.display());
+                        }
+                        callback
+                    });
+                    if sender.send((result, outcome)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for (result, outcome) in receiver {
+            if let Some(callback) = &options.on_file {
+                (callback.borrow_mut())(&outcome.input, &result);
+            }
+            report.push(outcome);
+        }
+    });
+
+    let reason = if options.is_cancelled() {
+        "cancelled"
+    } else {
+        "aborted after a failure"
+    };
+    for input in next.into_inner().unwrap_or_else(PoisonError::into_inner) {
+        report.push(FileOutcome::skipped(input, reason));
+    }
+    report.wall_time_seconds = started.elapsed().as_secs_f64();
+    report
+}
+
+/// Transcribes and writes the files of a directory run. `run_planned` and `run_discovered`
+/// decide which files to hand it and when, and report what came of them.
+pub trait FileWriter: Sync {
+    /// Transcribe `file` and write its outputs
+    fn transcribe(&self, file: &PlannedFile) -> Result<TranscriptionResult>;
+
+    /// Write `result` to `file`'s outputs, for an input identical to the one it came from
+    fn write(&self, file: &PlannedFile, result: &TranscriptionResult) -> Result<()>;
+
+    /// Files written beside `output` besides `output` itself
+    fn artifacts(&self, output: Option<&Path>) -> Vec<PathBuf>;
+}
+
+/// How `run_planned` and `run_discovered` work through a directory
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectoryOptions<'a> {
+    /// Files transcribed at the same time
+    pub jobs: usize,
+    /// Start no further file after one fails
+    pub fail_fast: bool,
+    /// Once set, no further file is started; those under way still finish
+    pub cancel: Option<&'a AtomicBool>,
+}
+
+impl<'a> DirectoryOptions<'a> {
+    fn batch_options(&self) -> BatchOptions<'a> {
+        let options = BatchOptions::new()
+            .with_jobs(self.jobs)
+            .with_continue_on_error(!self.fail_fast);
+        match self.cancel {
+            Some(cancel) => options.with_cancel(cancel),
+            None => options,
+        }
+    }
+}
+
+/// What `run_discovered` checks of each file as it is found, which planning would otherwise
+/// have checked up front
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryOptions {
+    /// Let several inputs write the same output path
+    pub allow_collisions: bool,
+    /// Create missing output directories
+    pub create_dirs: bool,
+    /// Give inputs identical to an earlier one its transcript
+    pub dedupe: bool,
+    /// Free space in the output directory, which the transcripts of the files found are
+    /// counted against
+    pub space: Option<OutputBudget>,
+}
+
+/// Transcribe and write each planned file, then give duplicates the transcript of the file
+/// they match. With `state`, each outcome is recorded and saved as it comes in; with
+/// `keep_results`, the results of the files that succeeded are returned for merging. The
+/// batch runs on its own threads, so this blocks.
+pub fn run_planned(
+    writer: &dyn FileWriter,
+    files: Vec<PlannedFile>,
+    options: &DirectoryOptions,
+    mut state: Option<(BatchState, &Path)>,
+    keep_results: bool,
+) -> (BatchReport, Vec<(PathBuf, TranscriptionResult)>) {
+    let (to_transcribe, to_skip): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|file| file.action == PlannedAction::Transcribe);
+    let (duplicates, to_skip): (Vec<_>, Vec<_>) = to_skip
+        .into_iter()
+        .partition(|file| file.action == PlannedAction::Duplicate);
+    info!(
+        "Processing {} files ({} concurrently, {} skipped, {} duplicate)",
+        to_transcribe.len(),
+        options.jobs,
+        to_skip.len(),
+        duplicates.len()
+    );
+
+    let mut report = BatchReport::new();
+    for file in to_skip {
+        report.push(FileOutcome::not_transcribed(file));
+    }
+    if let Some((state, path)) = &state {
+        if let Err(e) = state.save(path) {
+            warn!("Could not write state file {}: {}", path.display(), e);
+        }
+    }
+
+    let inputs: Vec<PathBuf> = to_transcribe.iter().map(|f| f.input.clone()).collect();
+    let planned: HashMap<&Path, &PlannedFile> = to_transcribe
+        .iter()
+        .map(|file| (file.input.as_path(), file))
+        .collect();
+    let mut finished = Vec::new();
+    let mut results = Vec::new();
+    let batch_options = options.batch_options().on_file(|input, result| {
+        let file = planned[input];
+        let outcome = finished_outcome(writer, file.clone(), result, options.fail_fast);
+        let result = result.as_ref().ok();
+        let copies: Vec<_> = duplicates
+            .iter()
+            .filter(|d| d.duplicate_of.as_deref() == Some(input))
+            .map(|duplicate| copy_to_duplicate(writer, duplicate, &outcome, result))
+            .collect();
+
+        let result = result.cloned();
+        for (outcome, result) in std::iter::once((outcome, result)).chain(copies) {
+            if let Some(result) = result.filter(|_| keep_results) {
+                results.push((outcome.input.clone(), result));
+            }
+            if let Some((state, path)) = &mut state {
+                state.record(&outcome);
+                if let Err(e) = state.save(*path) {
+                    warn!("Could not update state file {}: {}", path.display(), e);
+                }
+            }
+            finished.push(outcome);
+        }
+    });
+    let batch = run_batch(inputs, &batch_options, |input| {
+        writer.transcribe(planned[input])
+    });
+    drop(batch_options);
+
+    // The batch's report has the files it never started; the rest come with outputs
+    report.outcomes.extend(finished);
+    let mut not_started = Vec::new();
+    for outcome in batch.outcomes {
+        if let FileStatus::Skipped(reason) = &outcome.status {
+            let copies = duplicates
+                .iter()
+                .filter(|d| d.duplicate_of.as_ref() == Some(&outcome.input))
+                .map(|d| FileOutcome::skipped(d.input.clone(), reason));
+            not_started.extend(copies);
+            not_started.push(outcome);
+        }
+    }
+    report.outcomes.extend(not_started);
+    report.wall_time_seconds = batch.wall_time_seconds;
+    (report, results)
+}
+
+/// A file of a streamed directory run that others were found identical to
+enum StreamedPrimary {
+    /// Not finished yet; these duplicates get its transcript when it is
+    Running(Vec<PlannedFile>),
+    /// Finished while the directory was still being listed, kept for copies found later
+    Finished(Box<(FileOutcome, Option<TranscriptionResult>)>),
+}
+
+/// Like `run_planned` while the directory is still being listed: each file is planned as it
+/// is found and transcribed as soon as a job is free. Nothing here may wait for the whole
+/// list, so there is no resume state or merged output, and an output path already claimed
+/// by an earlier file fails the later one instead of the whole run. Duplicates are found as
+/// they turn up and get their primary's transcript once it is done.
+pub fn run_discovered(
+    writer: &dyn FileWriter,
+    discovery: Discovery,
+    plan_options: &PlanOptions,
+    options: &DirectoryOptions,
+    mut discovery_options: DiscoveryOptions,
+) -> BatchReport {
+    let progress = discovery.progress();
+    let planned: Mutex<HashMap<PathBuf, PlannedFile>> = Mutex::new(HashMap::new());
+    let not_transcribed: Mutex<Vec<FileOutcome>> = Mutex::new(Vec::new());
+    let finished: Mutex<Vec<FileOutcome>> = Mutex::new(Vec::new());
+    let primaries: Mutex<HashMap<PathBuf, StreamedPrimary>> = Mutex::new(HashMap::new());
+    let listed = AtomicBool::new(false);
+    let mut deduper = discovery_options.dedupe.then(plan::Deduper::default);
+    let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut checked_dirs: HashSet<PathBuf> = HashSet::new();
+    let to_transcribe = discovery.map(Some).chain([None]).filter_map(|input| {
+        let Some(input) = input else {
+            // Nothing can turn out to be a copy of a finished primary any more
+            listed.store(true, Ordering::SeqCst);
+            lock(&primaries).retain(|_, primary| matches!(primary, StreamedPrimary::Running(_)));
+            return None;
+        };
+        let language = plan_options.mapped_language(&input);
+        let output = plan_options.output_path(&input, language.as_deref());
+        let mut file = plan::plan_file(input, output, plan_options);
+        if let Some(output) = &file.output {
+            let first = claimed.get(output);
+            if let Some(first) = first.filter(|_| !discovery_options.allow_collisions) {
+                let reason = format!(
+                    "{} is already written by {} (adjust --output-template or pass --allow-collisions)",
+                    output.display(),
+                    first.display()
+                );
+                error!("✗ Failed {}: {}", file.input.display(), reason);
+                let outcome = FileOutcome::failed(file.input, file.output.clone(), reason);
+                lock(&not_transcribed).push(outcome);
+                return None;
+            }
+            claimed.insert(output.clone(), file.input.clone());
+            let parent = output.parent().filter(|p| !p.as_os_str().is_empty());
+            let parent = parent.unwrap_or(Path::new(".")).to_path_buf();
+            if !checked_dirs.contains(&parent) {
+                if let Err(e) = plan::ensure_writable_dir(&parent, discovery_options.create_dirs)
+                {
+                    let outcome = FileOutcome::from_error(file.input, file.output.clone(), &e);
+                    lock(&not_transcribed).push(outcome);
+                    return None;
+                }
+                checked_dirs.insert(parent);
+            }
+        }
+        if deduper.as_mut().is_some_and(|deduper| deduper.check(&mut file)) {
+            let primary = file.duplicate_of.clone().expect("set by the deduper");
+            info!(
+                "Deduplicating {}: identical to {}",
+                file.input.display(),
+                primary.display()
+            );
+            let mut primaries = lock(&primaries);
+            match primaries.get_mut(&primary) {
+                Some(StreamedPrimary::Finished(primary)) => {
+                    let (outcome, result) = primary.as_ref().clone();
+                    drop(primaries);
+                    let copy = copy_to_duplicate(writer, &file, &outcome, result.as_ref());
+                    lock(&finished).push(copy.0);
+                }
+                Some(StreamedPrimary::Running(waiting)) => waiting.push(file),
+                None => {
+                    primaries.insert(primary, StreamedPrimary::Running(vec![file]));
+                }
+            }
+            return None;
+        }
+        if file.action != PlannedAction::Transcribe {
+            lock(&not_transcribed).push(FileOutcome::not_transcribed(file));
+            return None;
+        }
+        if let (Some(budget), Some(duration)) = (&mut discovery_options.space, file.duration) {
+            budget.add(duration);
+        }
+        let input = file.input.clone();
+        lock(&planned).insert(input.clone(), file);
+        Some(input)
+    });
+
+    let batch_options = options.batch_options().on_file(|input, result| {
+        let Some(file) = lock(&planned).remove(input) else {
+            return;
+        };
+        let outcome = finished_outcome(writer, file, result, options.fail_fast);
+        let result = result.as_ref().ok();
+        let waiting = {
+            let mut primaries = lock(&primaries);
+            let waiting = match primaries.remove(input) {
+                Some(StreamedPrimary::Running(waiting)) => waiting,
+                _ => Vec::new(),
+            };
+            // Later copies are only possible while the listing is still going
+            if discovery_options.dedupe && !listed.load(Ordering::SeqCst) {
+                let kept = StreamedPrimary::Finished(Box::new((outcome.clone(), result.cloned())));
+                primaries.insert(input.to_path_buf(), kept);
+            }
+            waiting
+        };
+        let copies: Vec<FileOutcome> = waiting
+            .iter()
+            .map(|duplicate| copy_to_duplicate(writer, duplicate, &outcome, result).0)
+            .collect();
+        let mut finished = lock(&finished);
+        finished.push(outcome);
+        finished.extend(copies);
+        info!(
+            "Finished {} of {} file(s)",
+            finished.len() + lock(&not_transcribed).len(),
+            match progress.total() {
+                Some(total) => total.to_string(),
+                None => "?".to_string(),
+            }
+        );
+    });
+    let batch = run_batch(to_transcribe, &batch_options, |input| {
+        let file = lock(&planned).get(input).cloned();
+        writer.transcribe(&file.expect("planned before it was handed out"))
+    });
+    drop(batch_options);
+
+    let mut report = BatchReport::new();
+    report
+        .outcomes
+        .extend(not_transcribed.into_inner().unwrap_or_default());
+    report
+        .outcomes
+        .extend(finished.into_inner().unwrap_or_default());
+    // Files never started take their duplicates with them
+    let mut primaries = primaries.into_inner().unwrap_or_default();
+    for outcome in batch.outcomes {
+        if let FileStatus::Skipped(reason) = &outcome.status {
+            if let Some(StreamedPrimary::Running(waiting)) = primaries.remove(&outcome.input) {
+                let copies = waiting
+                    .into_iter()
+                    .map(|d| FileOutcome::skipped(d.input, reason));
+                report.outcomes.extend(copies);
+            }
+            report.outcomes.push(outcome);
+        }
+    }
+    report.wall_time_seconds = batch.wall_time_seconds;
+    report
+}
+
+/// The outcome of a transcribed `file`, with the artifacts written beside its output
+fn finished_outcome(
+    writer: &dyn FileWriter,
+    file: PlannedFile,
+    result: &Result<TranscriptionResult>,
+    fail_fast: bool,
+) -> FileOutcome {
+    match result {
+        Ok(result) => {
+            let artifacts = writer.artifacts(file.output.as_deref());
+            FileOutcome::succeeded(file.input, file.output, result).with_artifacts(artifacts)
+        }
+        Err(e) => {
+            if fail_fast {
+                warn!("Aborting batch after first failure (--fail-fast)");
+            }
+            FileOutcome::from_error(file.input, file.output, e)
+        }
+    }
+}
+
+/// Give a deduplicated file the result of the identical input transcribed in its place
+fn copy_to_duplicate(
+    writer: &dyn FileWriter,
+    duplicate: &PlannedFile,
+    primary: &FileOutcome,
+    result: Option<&TranscriptionResult>,
+) -> (FileOutcome, Option<TranscriptionResult>) {
+    let input = duplicate.input.clone();
+    let output = duplicate.output.clone();
+    let Some(result) = result else {
+        let reason = format!("identical to {}, which failed", primary.input.display());
+        return (FileOutcome::failed(input, output, reason), None);
+    };
+    match writer.write(duplicate, result) {
+        Ok(()) => {
+            info!(
+                "Deduplicated: {} is identical to {}; reused its transcript",
+                input.display(),
+                primary.input.display()
+            );
+            let artifacts = writer.artifacts(output.as_deref());
+            let outcome = FileOutcome::duplicate(input, output, primary.input.clone())
+                .with_artifacts(artifacts);
+            (outcome, Some(result.clone()))
+        }
+        Err(e) => {
+            error!("✗ Failed {}: {}", input.display(), e);
+            (FileOutcome::from_error(input, output, &e), None)
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockBackend, ResultBuilder};
+    use std::sync::Arc;
+
+    fn result(duration: f64, transcription_time: f64) -> TranscriptionResult {
+        ResultBuilder::new()
+            .language_probability(0.9)
+            .duration(duration)
+            .transcription_time(transcription_time)
+            .build()
+    }
+
+    /// Fails on files named `bad*`
+    fn mock() -> MockBackend {
+        MockBackend::new().responding(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.starts_with("bad") {
+                return Err(TranscriptionError::TranscriptionFailed(format!(
+                    "cannot decode {}",
+                    name
+                )));
+            }
+            Ok(result(60.0, 10.0))
+        })
+    }
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_transcribe_many_shares_the_model_and_reports_each_file() {
+        let backend = mock();
+        let inputs = paths(&["a.wav", "bad.wav", "c.wav", "d.wav"]);
+        let mut seen = Vec::new();
+        let options = BatchOptions::new()
+            .with_jobs(3)
+            .on_file(|path, result| seen.push((path.to_path_buf(), result.is_ok())));
+        let report = transcribe_many(&backend, &inputs, &options);
+        drop(options);
+
+        assert_eq!(backend.loads.load(Ordering::SeqCst), 1);
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(report.summary(), "3 ok, 1 failed, 0 skipped");
+        let failure = report.failures().next().unwrap();
+        assert_eq!(failure.input, PathBuf::from("bad.wav"));
+        assert_eq!(failure.error_kind.as_deref(), Some("transcription_failed"));
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                ("a.wav".into(), true),
+                ("bad.wav".into(), false),
+                ("c.wav".into(), true),
+                ("d.wav".into(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_on_file_complete_fires_once_per_file_as_each_finishes() {
+        // Up to 20ms per file, differently each run, so files finish out of order
+        let backend = mock().before_each(|path, _| {
+            use std::hash::BuildHasher;
+            let random = std::collections::hash_map::RandomState::new().hash_one(path);
+            std::thread::sleep(std::time::Duration::from_millis(random % 20));
+        });
+        let inputs: Vec<PathBuf> = (0..12)
+            .map(|i| {
+                PathBuf::from(format!(
+                    "{}{}.wav",
+                    if i % 5 == 0 { "bad" } else { "ok" },
+                    i
+                ))
+            })
+            .collect();
+        let completed = Mutex::new(Vec::new());
+        let options = BatchOptions::new()
+            .with_jobs(4)
+            .on_file_complete(|outcome: FileOutcome| {
+                let input = outcome.input.clone();
+                completed.lock().unwrap().push(outcome);
+                if input == Path::new("ok3.wav") {
+                    panic!("the table row is gone");
+                }
+            });
+        let report = transcribe_many(&backend, &inputs, &options);
+        drop(options);
+
+        // The panic neither stopped the batch nor lost the file
+        assert_eq!(report.summary(), "9 ok, 3 failed, 0 skipped");
+        let completed = completed.into_inner().unwrap();
+        let order = |outcomes: &[FileOutcome]| -> Vec<PathBuf> {
+            outcomes
+                .iter()
+                .map(|outcome| outcome.input.clone())
+                .collect()
+        };
+        assert_eq!(order(&completed), order(&report.outcomes));
+        let mut once_each = order(&completed);
+        once_each.sort();
+        once_each.dedup();
+        assert_eq!(once_each.len(), inputs.len());
+
+        let ok = completed
+            .iter()
+            .find(|o| o.input == Path::new("ok1.wav"))
+            .unwrap();
+        assert_eq!(ok.result.as_ref().unwrap().transcription_time, 10.0);
+        let bad = completed
+            .iter()
+            .find(|o| o.input == Path::new("bad5.wav"))
+            .unwrap();
+        assert_eq!(bad.error_kind.as_deref(), Some("transcription_failed"));
+    }
+
+    #[test]
+    fn test_files_start_before_discovery_ends() {
+        use crate::plan::Discovery;
+
+        // Finds a file every 20ms
+        let listed = Arc::new(AtomicBool::new(false));
+        let slow_listing = {
+            let listed = listed.clone();
+            (0..6)
+                .map(|i| {
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    PathBuf::from(format!("{}.wav", i))
+                })
+                .chain(std::iter::from_fn(move || {
+                    listed.store(true, Ordering::SeqCst);
+                    None
+                }))
+        };
+        let discovery = Discovery::spawn(2, slow_listing);
+        let progress = discovery.progress();
+        let backend = mock();
+        let first_completion = Mutex::new(None);
+        let options = BatchOptions::new()
+            .with_jobs(2)
+            .on_file_complete(|outcome| {
+                first_completion.lock().unwrap().get_or_insert((
+                    outcome.input,
+                    listed.load(Ordering::SeqCst),
+                    progress.total(),
+                ));
+            });
+        let report = run_batch(discovery, &options, |path| {
+            backend.transcribe_path(path, &Default::default())
+        });
+        drop(options);
+
+        let (first, listing_done, total) = first_completion.into_inner().unwrap().unwrap();
+        assert_eq!(first, PathBuf::from("0.wav"));
+        assert!(!listing_done);
+        assert_eq!(total, None);
+        assert_eq!(progress.total(), Some(6));
+        assert_eq!(report.summary(), "6 ok, 0 failed, 0 skipped");
+    }
+
+    /// Transcribes with `mock()` and writes nothing, remembering which files got a copy
+    struct Recorder {
+        backend: MockBackend,
+        copies: Mutex<Vec<PathBuf>>,
+    }
+
+    impl Recorder {
+        fn new() -> Self {
+            Self {
+                backend: mock(),
+                copies: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl FileWriter for Recorder {
+        fn transcribe(&self, file: &PlannedFile) -> Result<TranscriptionResult> {
+            self.backend
+                .transcribe_path(&file.input, self.backend.options())
+        }
+
+        fn write(&self, file: &PlannedFile, _result: &TranscriptionResult) -> Result<()> {
+            lock(&self.copies).push(file.input.clone());
+            Ok(())
+        }
+
+        fn artifacts(&self, output: Option<&Path>) -> Vec<PathBuf> {
+            output
+                .map(|output| output.with_extension("srt"))
+                .into_iter()
+                .collect()
+        }
+    }
+
+    fn planned(input: &str, action: PlannedAction, duplicate_of: Option<&str>) -> PlannedFile {
+        PlannedFile {
+            input: input.into(),
+            output: Some(Path::new(input).with_extension("json")),
+            action,
+            language: None,
+            duration: None,
+            duplicate_of: duplicate_of.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn test_run_planned_gives_duplicates_their_primary_result() {
+        let writer = Recorder::new();
+        let files = vec![
+            planned("a.wav", PlannedAction::Transcribe, None),
+            planned("copy of a.wav", PlannedAction::Duplicate, Some("a.wav")),
+            planned("bad.wav", PlannedAction::Transcribe, None),
+            planned("copy of bad.wav", PlannedAction::Duplicate, Some("bad.wav")),
+            planned("done.wav", PlannedAction::SkipExisting, None),
+        ];
+        let options = DirectoryOptions {
+            jobs: 2,
+            ..Default::default()
+        };
+        let (report, results) = run_planned(&writer, files, &options, None, true);
+
+        assert_eq!(report.summary(), "2 ok, 2 failed, 1 skipped");
+        assert_eq!(*lock(&writer.copies), vec![PathBuf::from("copy of a.wav")]);
+        let outcome = |input: &str| {
+            report
+                .outcomes
+                .iter()
+                .find(|outcome| outcome.input == Path::new(input))
+                .unwrap()
+        };
+        let copy = outcome("copy of a.wav");
+        assert_eq!(copy.duplicate_of.as_deref(), Some(Path::new("a.wav")));
+        assert_eq!(copy.artifacts, vec![PathBuf::from("copy of a.srt")]);
+        assert_eq!(
+            outcome("copy of bad.wav").status,
+            FileStatus::Failed("identical to bad.wav, which failed".to_string())
+        );
+        let mut kept: Vec<_> = results.into_iter().map(|(input, _)| input).collect();
+        kept.sort();
+        assert_eq!(kept, paths(&["a.wav", "copy of a.wav"]));
+    }
+
+    #[test]
+    fn test_run_discovered_plans_and_dedupes_files_as_they_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs: Vec<PathBuf> = [
+            ("a.wav", "RIFF one"),
+            ("b.wav", "RIFF one"),
+            ("x.wav", "RIFF three"),
+            ("x.mp3", "ID3 fourth"),
+        ]
+        .iter()
+        .map(|(name, contents)| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        })
+        .collect();
+        let writer = Recorder::new();
+        let plan_options = PlanOptions {
+            output_dir: Some(dir.path().join("out")),
+            probe_durations: false,
+            ..Default::default()
+        };
+        let discovery_options = DiscoveryOptions {
+            create_dirs: true,
+            dedupe: true,
+            ..Default::default()
+        };
+        let report = run_discovered(
+            &writer,
+            Discovery::spawn(2, inputs),
+            &plan_options,
+            &DirectoryOptions::default(),
+            discovery_options,
+        );
+
+        assert_eq!(report.summary(), "3 ok, 1 failed, 0 skipped");
+        assert!(dir.path().join("out").is_dir());
+        assert_eq!(*lock(&writer.copies), vec![dir.path().join("b.wav")]);
+        assert_eq!(
+            report.duplicate_groups(),
+            vec![DuplicateGroup {
+                primary: dir.path().join("a.wav"),
+                duplicates: vec![dir.path().join("b.wav")],
+            }]
+        );
+        // Both x files would be written to out/x.json; the first one found keeps it
+        let collision = report.failures().next().unwrap();
+        assert_eq!(collision.input, dir.path().join("x.mp3"));
+    }
+
+    #[test]
+    fn test_transcribe_many_stops_on_error_or_cancel() {
+        let backend = mock();
+        let inputs = paths(&["a.wav", "bad.wav", "c.wav", "d.wav"]);
+        let options = BatchOptions::new().with_continue_on_error(false);
+        let report = transcribe_many(&backend, &inputs, &options);
+        assert_eq!(report.summary(), "1 ok, 1 failed, 2 skipped");
+        assert_eq!(
+            report.outcomes[2].status,
+            FileStatus::Skipped("aborted after a failure".to_string())
+        );
+
+        // Cancels the batch from inside the second transcription
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+        let backend = mock().before_each(move |_, calls| {
+            if calls >= 2 {
+                flag.store(true, Ordering::SeqCst);
+            }
+        });
+        let inputs = paths(&["a.wav", "b.wav", "c.wav", "d.wav"]);
+        let report = transcribe_many(&backend, &inputs, &BatchOptions::new().with_cancel(&cancel));
+        assert_eq!(report.summary(), "2 ok, 0 failed, 2 skipped");
+        assert_eq!(
+            report.outcomes[3].status,
+            FileStatus::Skipped("cancelled".to_string())
+        );
+    }
+
+    fn report(ok: usize, failed: usize, skipped: usize) -> BatchReport {
+        let mut report = BatchReport::new();
+        for i in 0..ok {
+            report.push(FileOutcome::succeeded(
+                format!("ok{}.wav", i).into(),
+                None,
+                &result(60.0, 10.0),
+            ));
+        }
+        for i in 0..failed {
+            report.push(FileOutcome::failed(
+                format!("bad{}.wav", i).into(),
+                None,
+                "boom",
+            ));
+        }
+        for i in 0..skipped {
+            report.push(FileOutcome::skipped(
+                format!("skip{}.wav", i).into(),
+                "fail-fast",
+            ));
+        }
+        report
+    }
+
+    #[test]
+    fn test_summary_counts() {
+        let report = report(68, 12, 3);
+        assert_eq!(report.summary(), "68 ok, 12 failed, 3 skipped");
+        assert_eq!(report.failures().count(), 12);
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(BatchReport::new().exit_code(), EXIT_SUCCESS);
+        assert_eq!(report(5, 0, 2).exit_code(), EXIT_SUCCESS);
+        assert_eq!(report(0, 3, 0).exit_code(), EXIT_TOTAL_FAILURE);
+        assert_eq!(report(0, 3, 4).exit_code(), EXIT_TOTAL_FAILURE);
+        assert_eq!(report(2, 1, 0).exit_code(), EXIT_PARTIAL_FAILURE);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut report = BatchReport::new();
+        report.push(FileOutcome::succeeded(
+            "a.wav".into(),
+            None,
+            &result(3600.0, 600.0),
+        ));
+        report.push(FileOutcome::succeeded(
+            "b.wav".into(),
+            None,
+            &result(1800.0, 900.0),
+        ));
+        report.push(FileOutcome::failed("c.wav".into(), None, "boom"));
+        report.wall_time_seconds = 1800.0;
+
+        let stats = report.statistics();
+        assert_eq!(stats.total_audio_hours(), 1.5);
+        assert_eq!(stats.total_transcription_seconds, 1500.0);
+        assert_eq!(stats.aggregate_real_time_factor, 3.0);
+        assert_eq!(stats.average_real_time_factor, 4.0); // (6x + 2x) / 2
+        assert_eq!(stats.slowest_file.unwrap().input, PathBuf::from("b.wav"));
+        assert_eq!(stats.failed_files, vec![PathBuf::from("c.wav")]);
+        assert_eq!(stats.languages, BTreeMap::from([("en".to_string(), 2)]));
+    }
+
+    #[test]
+    fn test_csv_summary_quotes_fields() {
+        let mut report = BatchReport::new();
+        let mut ok = result(60.0, 10.0);
+        ok.segments.push(crate::types::TranscriptionSegment {
+            start: 0.0,
+            end: 2.0,
+            text: "Olá, mundo".to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: 0.0,
+            words: vec![],
+            speaker: None,
+            tokens: None,
+        });
+        report.push(
+            FileOutcome::succeeded(
+                "talks/ação, parte 1.wav".into(),
+                Some("out/a.json".into()),
+                &ok,
+            )
+            .with_artifacts(vec!["out/a.srt".into()]),
+        );
+        report.push(FileOutcome::failed(
+            "b.wav".into(),
+            None,
+            "decode error: \"moov\" atom not found, giving up",
+        ));
+        report.push(FileOutcome::skipped(
+            "c.wav".into(),
+            "output already exists",
+        ));
+
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "\"talks/ação, parte 1.wav\",succeeded,60.000,en,0.9000,10.000,6.00,1,2,out/a.json;out/a.srt,"
+        );
+        assert_eq!(
+            lines[2],
+            "b.wav,failed,,,,,,,,,\"decode error: \"\"moov\"\" atom not found, giving up\""
+        );
+        assert_eq!(lines[3], "c.wav,skipped,,,,,,,,,");
+    }
+
+    #[test]
+    fn test_statistics_empty_report() {
+        let stats = BatchReport::new().statistics();
+        assert!(stats.total_audio_seconds.is_sign_positive());
+        assert_eq!(stats.total_audio_seconds, 0.0);
+        assert_eq!(stats.aggregate_real_time_factor, 0.0);
+        assert_eq!(stats.average_real_time_factor, 0.0);
+        assert!(stats.slowest_file.is_none());
+    }
+
+    #[test]
+    fn test_outcome_serialization() {
+        let outcome = FileOutcome::failed("a.wav".into(), None, "decode error");
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["reason"], "decode error");
+        assert!(json.get("error_kind").is_none());
+
+        let error = TranscriptionError::WouldBlock.with_path("a.wav");
+        let outcome = FileOutcome::from_error("a.wav".into(), None, &error);
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(json["reason"], "Transcriber is busy with another request");
+        assert_eq!(json["error_kind"], "would_block");
+    }
+
+    #[test]
+    fn test_decode_failures_are_grouped() {
+        let mut report = report(1, 1, 0);
+        let undecodable = |name: &str, reason: DecodeFailure| {
+            let error = TranscriptionError::AudioDecodeError {
+                path: name.into(),
+                reason,
+                detail: reason.to_string(),
+            };
+            FileOutcome::from_error(name.into(), None, &error)
+        };
+        report.push(undecodable("notes.wav", DecodeFailure::NotAudio));
+        report.push(undecodable("empty.mp3", DecodeFailure::Empty));
+        report.push(undecodable("readme.wav", DecodeFailure::NotAudio));
+
+        assert_eq!(
+            report.decode_failures(),
+            vec![(DecodeFailure::NotAudio, 2), (DecodeFailure::Empty, 1)]
+        );
+        let json = serde_json::to_value(&report.outcomes[2]).unwrap();
+        assert_eq!(json["error_kind"], "audio_decode");
+        assert_eq!(json["decode_failure"], "not_audio");
+    }
+
+    #[test]
+    fn test_timing_report_covers_timed_files() {
+        let mut report = BatchReport::new();
+        let timed = |inference: f64| TranscriptionResult {
+            timings: Some(PhaseTimings {
+                decode: 0.5,
+                inference,
+                ..Default::default()
+            }),
+            ..result(60.0, inference)
+        };
+        report.push(FileOutcome::succeeded("a.wav".into(), None, &timed(4.0)));
+        report.push(FileOutcome::succeeded("b.wav".into(), None, &timed(9.0)));
+        report.push(FileOutcome::succeeded(
+            "cached.wav".into(),
+            None,
+            &result(60.0, 0.1),
+        ));
+        report.push(FileOutcome::failed("bad.wav".into(), None, "broken"));
+
+        let timings = report.timing_report();
+        let inputs: Vec<&Path> = timings.files.iter().map(|f| f.input.as_path()).collect();
+        assert_eq!(inputs, [Path::new("a.wav"), Path::new("b.wav")]);
+        assert_eq!(timings.slowest(1)[0].total, 9.5);
+        assert_eq!(timings.percentiles["decode"].max, 0.5);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timings.json");
+        report.write_timing_report(&path).unwrap();
+        let written: TimingReport =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, timings);
+    }
+
+    #[test]
+    fn test_duplicates_are_not_counted_twice() {
+        let mut report = BatchReport::new();
+        report.push(FileOutcome::succeeded(
+            "a.wav".into(),
+            None,
+            &result(60.0, 10.0),
+        ));
+        report.push(FileOutcome::duplicate(
+            "copy.wav".into(),
+            Some("copy.json".into()),
+            "a.wav".into(),
+        ));
+        report.wall_time_seconds = 10.0;
+
+        assert_eq!(report.summary(), "2 ok, 0 failed, 0 skipped");
+        assert_eq!(report.statistics().total_audio_seconds, 60.0);
+        assert_eq!(
+            report.duplicate_groups(),
+            vec![DuplicateGroup {
+                primary: "a.wav".into(),
+                duplicates: vec!["copy.wav".into()],
+            }]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("summary.json");
+        report.write_summary_json(&path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["duplicates"][0]["primary"], "a.wav");
+        assert_eq!(json["files"][1]["duplicate_of"], "a.wav");
+        assert!(json["files"][0].get("duplicate_of").is_none());
+    }
+}
@@ -1,12 +1,28 @@
-use crate::error::Result;
-use crate::transcriber::FasterWhisperTranscriber;
-use crate::types::{ModelConfig, TranscriptionResult};
-use log::info;
+use crate::align;
+use crate::backend::TranscriptionBackend;
+use crate::batch::csv_field;
+use crate::confidence::{GREEN, RED, RESET};
+use crate::error::{Result, TranscriptionError};
+use crate::model_manager::{ModelPool, ModelPoolLimits, PoolStats, WhenFull};
+use crate::output::{self, OutputFormat};
+use crate::plan;
+use crate::rss;
+use crate::template::rfc3339;
+use crate::throttle::{Reading, ThrottleCheck, ThrottleMonitor};
+use crate::types::{Backend, ModelConfig, TranscriptionOptions, TranscriptionResult};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BenchmarkResult {
+    #[serde(default)]
+    pub backend: Backend,
     pub model_size: String,
     pub device: String,
     pub compute_type: String,
@@ -16,11 +32,68 @@ pub struct BenchmarkResult {
     pub memory_usage_mb: Option<f64>,
     pub accuracy_score: Option<f64>,
     pub segments_count: usize,
+    /// What the config was labeled in the benchmark, such as `beam 5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Beam size the backend ran with; `None` for its default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beam_size: Option<usize>,
+    /// Whether the backend ran with the VAD filter; `None` in results saved before it was
+    /// recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vad_filter: Option<bool>,
+    /// Seconds covered by the segments, which VAD shrinks by dropping silence
+    #[serde(default)]
+    pub segments_duration: f64,
+    /// Mean word agreement with the other configs' transcripts of the same file, a quality
+    /// proxy when there is no reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agreement_score: Option<f64>,
+    /// `config_hash` of the model and options the result ran with; `None` in results saved
+    /// before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// When the run finished, in RFC 3339 UTC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded_at: Option<String>,
+    /// Seconds each warm-up transcription took before the measured one; they count toward
+    /// none of the other figures
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warmup_times: Vec<f64>,
+    /// The whole process's resident memory high-water mark after the run, in MB. The
+    /// interpreter and earlier configs' models count toward it, and it never goes down.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_mb: Option<f64>,
+    /// How far the run, model loading included, raised that high-water mark. A config that
+    /// needs less than an earlier one raises it by nothing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_delta_mb: Option<f64>,
+    /// The high-water mark when the benchmark started, before it loaded any model; what
+    /// the first config adds to it includes starting the interpreter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_rss_mb: Option<f64>,
+    /// Whether the reference workload ran slower than the throttle check allows just before
+    /// or just after this config, so the machine was likely throttling and its figures
+    /// aren't comparable with the others
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub throttling_suspected: bool,
+    /// The worse of the reference workload's slowdowns just before and just after the run,
+    /// in percent of its first time; `None` without the throttle check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_slowdown_pct: Option<f64>,
+    /// Where the run's full transcript was saved, as JSON: the directory given to
+    /// `Benchmark::set_save_transcripts` joined with a file name from the config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript_path: Option<PathBuf>,
+    /// The transcript, kept for agreement scoring but not saved
+    #[serde(skip)]
+    pub full_text: String,
 }
 
 impl BenchmarkResult {
     pub fn from_transcription(config: &ModelConfig, result: &TranscriptionResult) -> Self {
         Self {
+            backend: config.backend,
             model_size: config.model_size.clone(),
             device: config.device.clone(),
             compute_type: config.compute_type.clone(),
@@ -30,47 +103,1170 @@ impl BenchmarkResult {
             memory_usage_mb: None, // TODO: Implement memory monitoring
             accuracy_score: None,  // TODO: Implement accuracy calculation if reference available
             segments_count: result.segments.len(),
+            label: None,
+            beam_size: None,
+            vad_filter: None,
+            segments_duration: result
+                .segments
+                .iter()
+                .map(|segment| (segment.end - segment.start).max(0.0))
+                .sum(),
+            agreement_score: None,
+            config_hash: None,
+            recorded_at: None,
+            warmup_times: Vec::new(),
+            peak_rss_mb: None,
+            peak_rss_delta_mb: None,
+            baseline_rss_mb: None,
+            throttling_suspected: false,
+            reference_slowdown_pct: None,
+            transcript_path: None,
+            full_text: result.full_text.clone(),
         }
     }
+
+    /// Count a reference workload `slowdown_pct` around the run, keeping the worse
+    fn note_reference_slowdown(&mut self, slowdown_pct: f64, check: &ThrottleCheck) {
+        let worst = self
+            .reference_slowdown_pct
+            .map_or(slowdown_pct, |s| s.max(slowdown_pct));
+        self.reference_slowdown_pct = Some(worst);
+        self.throttling_suspected = check.is_throttled(worst);
+    }
+}
+
+/// A hash identifying everything about a config that can change a benchmark: the backend,
+/// model, device, compute type, CPU threads and every decoding option. Where the model is
+/// stored, offline mode and the config's label are left out.
+///
+/// The hash is of a canonical rendering: keys sorted, unset options dropped (so adding an
+/// option doesn't change existing hashes until it's set) and numbers with fixed precision.
+/// It's the same on every run and platform.
+pub fn config_hash(config: &ModelConfig, options: &TranscriptionOptions) -> Result<String> {
+    let value = serde_json::json!({
+        "model": {
+            "backend": config.backend.as_str(),
+            "model_size": config.model_size,
+            "device": config.device,
+            "compute_type": config.compute_type,
+            "cpu_threads": config.cpu_threads,
+        },
+        "options": serde_json::to_value(options)?,
+    });
+    let mut canonical = String::new();
+    write_canonical(&value, &mut canonical);
+    let hash = blake3::hash(canonical.as_bytes()).to_hex();
+    Ok(hash[..CONFIG_HASH_LEN].to_string())
+}
+
+/// Hex digits kept of the hash, enough to tell apart any configs one person benchmarks
+const CONFIG_HASH_LEN: usize = 16;
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => out.push_str(&i.to_string()),
+            (_, Some(u), _) => out.push_str(&u.to_string()),
+            (_, _, Some(f)) => out.push_str(&format!("{:.6}", f)),
+            _ => out.push_str(&n.to_string()),
+        },
+        Value::String(s) => out.push_str(&Value::String(s.clone()).to_string()),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().filter(|(_, v)| !v.is_null()).collect();
+            entries.sort_by_key(|(key, _)| key.as_str());
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Agreement this far below the median marks a config as an outlier
+const AGREEMENT_OUTLIER_MARGIN: f64 = 0.15;
+
+/// Set each result's `agreement_score` to its mean `align::word_agreement` with every other
+/// result. All results must be transcripts of the same file; with fewer than two nothing
+/// is scored.
+pub fn score_agreement(results: &mut [BenchmarkResult]) {
+    if results.len() < 2 {
+        return;
+    }
+    let mut totals = vec![0.0; results.len()];
+    for i in 0..results.len() {
+        for j in i + 1..results.len() {
+            let agreement = align::word_agreement(&results[i].full_text, &results[j].full_text);
+            totals[i] += agreement;
+            totals[j] += agreement;
+        }
+    }
+    let others = (results.len() - 1) as f64;
+    for (result, total) in results.iter_mut().zip(totals) {
+        result.agreement_score = Some(total / others);
+    }
+}
+
+/// Indexes of results whose agreement is well below the median of the scored ones, a sign
+/// the model is hallucinating. Needs at least three scored results to tell which side of a
+/// disagreement is the odd one out.
+pub fn agreement_outliers(results: &[BenchmarkResult]) -> Vec<usize> {
+    let mut scores: Vec<f64> = results.iter().filter_map(|r| r.agreement_score).collect();
+    if scores.len() < 3 {
+        return Vec::new();
+    }
+    scores.sort_by(f64::total_cmp);
+    let middle = scores.len() / 2;
+    let median = if scores.len().is_multiple_of(2) {
+        (scores[middle - 1] + scores[middle]) / 2.0
+    } else {
+        scores[middle]
+    };
+    results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| {
+            r.agreement_score
+                .is_some_and(|score| score < median - AGREEMENT_OUTLIER_MARGIN)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The same setup benchmarked with VAD on and off
+#[derive(Debug, Clone, PartialEq)]
+pub struct VadComparison {
+    /// `model/device/compute_type`, with the backend unless it is faster-whisper
+    pub setup: String,
+    pub rtf_with_vad: f64,
+    pub rtf_without_vad: f64,
+    pub segments_with_vad: f64,
+    pub segments_without_vad: f64,
+}
+
+impl VadComparison {
+    /// Real-time factor with VAD relative to without: `0.2` is 20% faster, `-0.2` slower
+    pub fn rtf_change(&self) -> f64 {
+        if self.rtf_without_vad > 0.0 {
+            self.rtf_with_vad / self.rtf_without_vad - 1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Seconds of segments VAD trimmed away
+    pub fn trimmed_seconds(&self) -> f64 {
+        self.segments_without_vad - self.segments_with_vad
+    }
+}
+
+/// Pair results that differ only in `vad_filter`, in the order the VAD-on ones appear
+pub fn compare_vad(results: &[BenchmarkResult]) -> Vec<VadComparison> {
+    results
+        .iter()
+        .filter(|on| on.vad_filter == Some(true))
+        .filter_map(|on| {
+            let off = results.iter().find(|off| {
+                off.vad_filter == Some(false)
+                    && off.backend == on.backend
+                    && off.model_size == on.model_size
+                    && off.device == on.device
+                    && off.compute_type == on.compute_type
+                    && off.beam_size == on.beam_size
+            })?;
+            let mut setup = format!("{}/{}/{}", on.model_size, on.device, on.compute_type);
+            if on.backend != Backend::FasterWhisper {
+                setup = format!("{} {}", on.backend.as_str(), setup);
+            }
+            Some(VadComparison {
+                setup,
+                rtf_with_vad: on.real_time_factor,
+                rtf_without_vad: off.real_time_factor,
+                segments_with_vad: on.segments_duration,
+                segments_without_vad: off.segments_duration,
+            })
+        })
+        .collect()
+}
+
+/// Column a `BenchmarkReport` is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BenchmarkSort {
+    /// Real-time factor, fastest first by default
+    #[default]
+    Rtf,
+    /// Seconds spent transcribing, quickest first by default
+    Time,
+    /// Memory use, smallest first by default
+    Memory,
+    /// Accuracy against a reference transcript, best first by default
+    Accuracy,
+    /// Agreement with the other configs' transcripts, highest first by default
+    Agreement,
+}
+
+impl FromStr for BenchmarkSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rtf" | "speed" => Ok(BenchmarkSort::Rtf),
+            "time" => Ok(BenchmarkSort::Time),
+            "memory" | "mem" => Ok(BenchmarkSort::Memory),
+            "accuracy" => Ok(BenchmarkSort::Accuracy),
+            "agreement" => Ok(BenchmarkSort::Agreement),
+            other => Err(format!(
+                "Unknown sort '{}' (expected rtf, time, memory, accuracy or agreement)",
+                other
+            )),
+        }
+    }
+}
+
+impl BenchmarkSort {
+    /// The order that puts the best results first
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            BenchmarkSort::Time | BenchmarkSort::Memory => SortOrder::Ascending,
+            BenchmarkSort::Rtf | BenchmarkSort::Accuracy | BenchmarkSort::Agreement => {
+                SortOrder::Descending
+            }
+        }
+    }
+
+    fn value(self, result: &BenchmarkResult) -> Option<f64> {
+        match self {
+            BenchmarkSort::Rtf => Some(result.real_time_factor),
+            BenchmarkSort::Time => Some(result.transcription_time),
+            BenchmarkSort::Memory => result.memory_usage_mb,
+            BenchmarkSort::Accuracy => result.accuracy_score,
+            BenchmarkSort::Agreement => result.agreement_score,
+        }
+        .filter(|value| !value.is_nan())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "asc" | "ascending" => Ok(SortOrder::Ascending),
+            "desc" | "descending" => Ok(SortOrder::Descending),
+            other => Err(format!("Unknown order '{}' (expected asc or desc)", other)),
+        }
+    }
+}
+
+/// Setting a `RowFilter` tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterField {
+    Backend,
+    Model,
+    Device,
+    Compute,
+    Label,
+    Beam,
+    Vad,
+}
+
+/// A `field=value` condition on benchmark rows, such as `device=mps`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowFilter {
+    field: FilterField,
+    value: String,
+}
+
+impl FromStr for RowFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (field, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid filter '{}' (expected field=value)", s))?;
+        let field = match field.trim().to_lowercase().as_str() {
+            "backend" => FilterField::Backend,
+            "model" => FilterField::Model,
+            "device" => FilterField::Device,
+            "compute" | "compute_type" => FilterField::Compute,
+            "label" => FilterField::Label,
+            "beam" | "beam_size" => FilterField::Beam,
+            "vad" => FilterField::Vad,
+            other => {
+                return Err(format!(
+                    "Unknown filter field '{}' (expected backend, model, device, compute, \
+                     label, beam or vad)",
+                    other
+                ))
+            }
+        };
+        let value = value.trim().to_lowercase();
+        if field == FilterField::Vad && !matches!(value.as_str(), "on" | "off" | "true" | "false") {
+            return Err(format!(
+                "Invalid vad filter '{}' (expected on or off)",
+                value
+            ));
+        }
+        Ok(Self { field, value })
+    }
+}
+
+impl RowFilter {
+    pub fn matches(&self, result: &BenchmarkResult) -> bool {
+        let actual = match self.field {
+            FilterField::Backend => Some(result.backend.as_str().to_string()),
+            FilterField::Model => Some(result.model_size.clone()),
+            FilterField::Device => Some(result.device.clone()),
+            FilterField::Compute => Some(result.compute_type.clone()),
+            FilterField::Label => result.label.clone(),
+            FilterField::Beam => result.beam_size.map(|beam| beam.to_string()),
+            FilterField::Vad => {
+                let on = matches!(self.value.as_str(), "on" | "true");
+                return result.vad_filter == Some(on);
+            }
+        };
+        actual.is_some_and(|actual| actual.to_lowercase() == self.value)
+    }
+}
+
+/// Benchmark results to show, sorted and filtered. Rows are kept in the order they ran
+/// until sorted.
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkReport {
+    results: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkReport {
+    pub fn new(results: Vec<BenchmarkResult>) -> Self {
+        Self { results }
+    }
+
+    pub fn results(&self) -> &[BenchmarkResult] {
+        &self.results
+    }
+
+    pub fn into_results(self) -> Vec<BenchmarkResult> {
+        self.results
+    }
+
+    /// Keep the rows `filters` allow: filters on the same field are alternatives, and a row
+    /// must satisfy every field filtered on
+    pub fn filter(mut self, filters: &[RowFilter]) -> Self {
+        self.results.retain(|result| {
+            filters.iter().all(|filter| {
+                filters
+                    .iter()
+                    .filter(|other| other.field == filter.field)
+                    .any(|other| other.matches(result))
+            })
+        });
+        self
+    }
+
+    /// Sort the rows by `key`. Ties keep their order, and rows without a value for `key`
+    /// (memory or accuracy that wasn't measured) go last either way.
+    pub fn sort(mut self, key: BenchmarkSort, order: SortOrder) -> Self {
+        self.results
+            .sort_by(|a, b| match (key.value(a), key.value(b)) {
+                (Some(a), Some(b)) => match order {
+                    SortOrder::Ascending => a.total_cmp(&b),
+                    SortOrder::Descending => b.total_cmp(&a),
+                },
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+        self
+    }
+}
+
+/// The machine a benchmark ran on, saved with its results
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    /// `std::env::consts::OS`, such as `macos`
+    pub os: String,
+    /// `std::env::consts::ARCH`, such as `aarch64`
+    pub arch: String,
+    /// Logical CPUs available to the process
+    pub cpus: usize,
+    /// Version of this app that ran the benchmark
+    pub app_version: String,
+}
+
+impl SystemInfo {
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// `macos-aarch64`: machines whose benchmarks are worth comparing
+    pub fn machine_class(&self) -> String {
+        format!("{}-{}", self.os, self.arch)
+    }
+}
+
+/// What `Benchmark::save_results_json` writes. Files saved before the system was recorded
+/// hold a bare array of results instead.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedRun {
+    system: SystemInfo,
+    results: Vec<BenchmarkResult>,
+}
+
+/// A benchmark result read back from disk
+#[derive(Debug, Clone)]
+pub struct RecordedResult {
+    /// The file it was read from
+    pub source: PathBuf,
+    /// The machine it ran on; `None` for files saved before that was recorded
+    pub system: Option<SystemInfo>,
+    pub result: BenchmarkResult,
+}
+
+/// Columns of `BenchmarkResultSet::to_csv`
+pub const RESULTS_CSV_HEADER: &str = "source,os,arch,backend,model,device,compute,label,\
+beam,vad,audio_duration,transcription_time,real_time_factor,memory_mb,accuracy,agreement,\
+segments,config_hash,recorded_at,peak_rss_mb,peak_rss_delta_mb,baseline_rss_mb,\
+throttling_suspected,reference_slowdown_pct,transcript_path";
+
+/// Benchmark results saved over many runs, to query together
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkResultSet {
+    results: Vec<RecordedResult>,
+    skipped: Vec<PathBuf>,
+}
+
+impl BenchmarkResultSet {
+    /// Read every `.json` file in `dir` holding benchmark results, in name order. Results
+    /// saved with or without system info both load; other JSON files, such as
+    /// transcripts, are skipped and listed in `skipped`.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            let is_json = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+            if is_json && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut set = Self::default();
+        for path in paths {
+            match Self::load_file(&path) {
+                Ok(results) => set.results.extend(results),
+                Err(TranscriptionError::InvalidTranscript(_)) => set.skipped.push(path),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(set)
+    }
+
+    /// The results in one saved file, in either format. A file that isn't benchmark
+    /// results is an `InvalidTranscript` error.
+    pub fn load_file(path: &Path) -> Result<Vec<RecordedResult>> {
+        let invalid = |detail: String| {
+            TranscriptionError::InvalidTranscript(format!(
+                "{} is not benchmark results: {}",
+                path.display(),
+                detail
+            ))
+        };
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| invalid(e.to_string()))?;
+        let (system, results) = if json.is_array() {
+            let results = serde_json::from_value(json).map_err(|e| invalid(e.to_string()))?;
+            (None, results)
+        } else {
+            let run: SavedRun = serde_json::from_value(json).map_err(|e| invalid(e.to_string()))?;
+            (Some(run.system), run.results)
+        };
+        Ok(results
+            .into_iter()
+            .map(|result| RecordedResult {
+                source: path.to_path_buf(),
+                system: system.clone(),
+                result,
+            })
+            .collect())
+    }
+
+    pub fn results(&self) -> &[RecordedResult] {
+        &self.results
+    }
+
+    /// JSON files `load_dir` passed over because they don't hold benchmark results
+    pub fn skipped(&self) -> &[PathBuf] {
+        &self.skipped
+    }
+
+    pub fn filter(mut self, mut keep: impl FnMut(&RecordedResult) -> bool) -> Self {
+        self.results.retain(|recorded| keep(recorded));
+        self
+    }
+
+    /// The result with the highest real-time factor; the first saved wins a tie
+    pub fn best_by_rtf(&self) -> Option<&RecordedResult> {
+        self.results
+            .iter()
+            .filter(|recorded| !recorded.result.real_time_factor.is_nan())
+            .reduce(|best, recorded| {
+                if recorded.result.real_time_factor > best.result.real_time_factor {
+                    recorded
+                } else {
+                    best
+                }
+            })
+    }
+
+    /// The results for each model size, keeping their order
+    pub fn group_by_model(&self) -> BTreeMap<String, Vec<&RecordedResult>> {
+        let mut groups: BTreeMap<String, Vec<&RecordedResult>> = BTreeMap::new();
+        for recorded in &self.results {
+            groups
+                .entry(recorded.result.model_size.clone())
+                .or_default()
+                .push(recorded);
+        }
+        groups
+    }
+
+    /// One CSV row per result, with a header row. Values that weren't recorded are empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(RESULTS_CSV_HEADER);
+        csv.push('\n');
+        let number = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        for recorded in &self.results {
+            let result = &recorded.result;
+            let system = recorded.system.as_ref();
+            let fields = [
+                recorded.source.to_string_lossy().into_owned(),
+                system.map(|s| s.os.clone()).unwrap_or_default(),
+                system.map(|s| s.arch.clone()).unwrap_or_default(),
+                result.backend.as_str().to_string(),
+                result.model_size.clone(),
+                result.device.clone(),
+                result.compute_type.clone(),
+                result.label.clone().unwrap_or_default(),
+                result.beam_size.map(|b| b.to_string()).unwrap_or_default(),
+                result.vad_filter.map(|v| v.to_string()).unwrap_or_default(),
+                result.audio_duration.to_string(),
+                result.transcription_time.to_string(),
+                result.real_time_factor.to_string(),
+                number(result.memory_usage_mb),
+                number(result.accuracy_score),
+                number(result.agreement_score),
+                result.segments_count.to_string(),
+                result.config_hash.clone().unwrap_or_default(),
+                result.recorded_at.clone().unwrap_or_default(),
+                number(result.peak_rss_mb),
+                number(result.peak_rss_delta_mb),
+                number(result.baseline_rss_mb),
+                result.throttling_suspected.to_string(),
+                number(result.reference_slowdown_pct),
+                result
+                    .transcript_path
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ];
+            let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// A config found in both of two benchmark runs
+#[derive(Debug, Clone)]
+pub struct ResultChange {
+    pub old: BenchmarkResult,
+    pub new: BenchmarkResult,
+    /// Whether the two were matched by `config_hash`, rather than by backend, model, device,
+    /// compute type and label
+    pub by_hash: bool,
+}
+
+/// Percentage change from `old` to `new`; `None` without both, or from zero
+fn percent_change(old: Option<f64>, new: Option<f64>) -> Option<f64> {
+    match (old, new) {
+        (Some(old), Some(new)) if old != 0.0 && old.is_finite() && new.is_finite() => {
+            Some((new - old) / old.abs() * 100.0)
+        }
+        _ => None,
+    }
+}
+
+impl ResultChange {
+    /// Percentage change in real-time factor; positive is faster
+    pub fn rtf_change(&self) -> Option<f64> {
+        percent_change(
+            Some(self.old.real_time_factor),
+            Some(self.new.real_time_factor),
+        )
+    }
+
+    /// Percentage change in memory use; positive is more memory
+    pub fn memory_change(&self) -> Option<f64> {
+        percent_change(self.old.memory_usage_mb, self.new.memory_usage_mb)
+    }
+
+    /// Percentage change in accuracy score; positive is more accurate
+    pub fn accuracy_change(&self) -> Option<f64> {
+        percent_change(self.old.accuracy_score, self.new.accuracy_score)
+    }
+
+    /// How far the worst metric got worse, in percent; zero or less when none did
+    pub fn regression(&self) -> f64 {
+        [
+            self.rtf_change().map(|change| -change),
+            self.memory_change(),
+            self.accuracy_change().map(|change| -change),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0.0, f64::max)
+    }
+}
+
+/// What changed between two benchmark runs
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkDiff {
+    /// Configs in both runs, in the newer run's order
+    pub changes: Vec<ResultChange>,
+    /// Configs only the older run has
+    pub only_old: Vec<BenchmarkResult>,
+    /// Configs only the newer run has
+    pub only_new: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkDiff {
+    /// Pair up `old` and `new` results. Results with a `config_hash` are matched on it
+    /// first. A result without one, from before hashes were recorded, falls back to
+    /// matching backend, model, device, compute type and label; two results with different
+    /// hashes never match. Repeats of a config pair up in order.
+    pub fn new(old: &[BenchmarkResult], new: &[BenchmarkResult]) -> Self {
+        let mut old_left: Vec<Option<&BenchmarkResult>> = old.iter().map(Some).collect();
+        let mut pairs: Vec<Option<(usize, bool)>> = vec![None; new.len()];
+
+        // Claim the first old result left that `matches`
+        let mut take = |matches: &dyn Fn(&BenchmarkResult) -> bool| {
+            let at = old_left.iter().position(|old| old.is_some_and(matches))?;
+            old_left[at].take();
+            Some(at)
+        };
+        for (i, new_result) in new.iter().enumerate() {
+            if let Some(hash) = &new_result.config_hash {
+                let found = take(&|old| old.config_hash.as_ref() == Some(hash));
+                pairs[i] = found.map(|at| (at, true));
+            }
+        }
+        for (i, new_result) in new.iter().enumerate() {
+            if pairs[i].is_some() {
+                continue;
+            }
+            let found = take(&|old| {
+                (old.config_hash.is_none() || new_result.config_hash.is_none())
+                    && same_setup(old, new_result)
+            });
+            pairs[i] = found.map(|at| (at, false));
+        }
+
+        let mut diff = Self::default();
+        for (new_result, pair) in new.iter().zip(pairs) {
+            match pair {
+                Some((at, by_hash)) => diff.changes.push(ResultChange {
+                    old: old[at].clone(),
+                    new: new_result.clone(),
+                    by_hash,
+                }),
+                None => diff.only_new.push(new_result.clone()),
+            }
+        }
+        diff.only_old = old_left.into_iter().flatten().cloned().collect();
+        diff
+    }
+
+    /// Changes where some metric got worse by more than `threshold` percent
+    pub fn regressions(&self, threshold: f64) -> Vec<&ResultChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.regression() > threshold)
+            .collect()
+    }
+
+    /// A table of the changes, then the configs only one run has and those either run
+    /// suspected of throttling. With `color`, improvements are green and regressions red.
+    pub fn render(&self, color: bool) -> String {
+        let mut out = format!(
+            "{:<36} {:>22} {:>24} {:>22}\n",
+            "Config", "RT Factor", "Memory (MB)", "Accuracy"
+        );
+        out.push_str(&"-".repeat(107));
+        out.push('\n');
+        for change in &self.changes {
+            let rtf = metric_cell(
+                Some(change.old.real_time_factor),
+                Some(change.new.real_time_factor),
+                change.rtf_change(),
+                true,
+                color,
+            );
+            let memory = metric_cell(
+                change.old.memory_usage_mb,
+                change.new.memory_usage_mb,
+                change.memory_change(),
+                false,
+                color,
+            );
+            let accuracy = metric_cell(
+                change.old.accuracy_score,
+                change.new.accuracy_score,
+                change.accuracy_change(),
+                true,
+                color,
+            );
+            out.push_str(&format!(
+                "{:<36} {} {} {}\n",
+                setup_name(&change.new),
+                pad(&rtf, 22),
+                pad(&memory, 24),
+                pad(&accuracy, 22)
+            ));
+        }
+        for (results, side) in [(&self.only_old, "old"), (&self.only_new, "new")] {
+            for result in results.iter() {
+                out.push_str(&format!(
+                    "{:<36} only in the {} results\n",
+                    setup_name(result),
+                    side
+                ));
+            }
+        }
+        for change in &self.changes {
+            let side = match (
+                change.old.throttling_suspected,
+                change.new.throttling_suspected,
+            ) {
+                (true, true) => "both runs",
+                (true, false) => "the old run",
+                (false, true) => "the new run",
+                (false, false) => continue,
+            };
+            out.push_str(&format!(
+                "{:<36} throttling suspected in {}\n",
+                setup_name(&change.new),
+                side
+            ));
+        }
+        out
+    }
+}
+
+/// Whether `a` and `b` ran the same backend, model, device and compute type under the
+/// same label
+fn same_setup(a: &BenchmarkResult, b: &BenchmarkResult) -> bool {
+    a.backend == b.backend
+        && a.model_size == b.model_size
+        && a.device == b.device
+        && a.compute_type == b.compute_type
+        && a.label == b.label
+}
+
+/// `medium/mps/float16 (beam 5)`, naming a config in the diff table
+fn setup_name(result: &BenchmarkResult) -> String {
+    let mut name = format!(
+        "{}/{}/{}",
+        result.model_size, result.device, result.compute_type
+    );
+    if result.backend != Backend::default() {
+        name = format!("{} {}", result.backend.as_str(), name);
+    }
+    if let Some(label) = &result.label {
+        name.push_str(&format!(" ({})", label));
+    }
+    name
+}
+
+/// `12.0 -> 15.0 (+25.0%)`, colored by whether the change is for the better
+fn metric_cell(
+    old: Option<f64>,
+    new: Option<f64>,
+    change: Option<f64>,
+    higher_is_better: bool,
+    color: bool,
+) -> String {
+    let value = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.1}", v));
+    let Some(change) = change else {
+        return format!("{} -> {}", value(old), value(new));
+    };
+    let percent = format!("({:+.1}%)", change);
+    let percent = match (color, change.partial_cmp(&0.0)) {
+        (true, Some(Ordering::Greater | Ordering::Less)) => {
+            let better = (change > 0.0) == higher_is_better;
+            let paint = if better { GREEN } else { RED };
+            format!("{}{}{}", paint, percent, RESET)
+        }
+        _ => percent,
+    };
+    format!("{} -> {} {}", value(old), value(new), percent)
+}
+
+/// Right-align `cell` to `width` columns, not counting color codes
+fn pad(cell: &str, width: usize) -> String {
+    let visible = cell.replace(GREEN, "").replace(RED, "").replace(RESET, "");
+    let fill = width.saturating_sub(visible.chars().count());
+    format!("{}{}", " ".repeat(fill), cell)
+}
+
+/// Which of `results` ran warm-up transcriptions first, when any did, so times measured
+/// warm and cold aren't compared as if alike
+pub fn warmup_note(results: &[BenchmarkResult]) -> Option<String> {
+    let mut runs: Vec<usize> = results
+        .iter()
+        .map(|result| result.warmup_times.len())
+        .collect();
+    runs.sort_unstable();
+    runs.dedup();
+    if runs.iter().all(|&count| count == 0) {
+        return None;
+    }
+    if runs.len() == 1 {
+        return Some(format!(
+            "🔥 Measured after {} warm-up run(s) per config, which aren't counted",
+            runs[0]
+        ));
+    }
+    Some(format!(
+        "⚠️  Configs ran between {} and {} warm-up run(s) first; times after different warm-ups aren't comparable",
+        runs[0],
+        runs[runs.len() - 1]
+    ))
+}
+
+/// The process's memory high-water mark after each result, with how far its run raised it
+/// and where it stood before any model was loaded; nothing when none was recorded
+pub fn peak_rss_lines(results: &[BenchmarkResult]) -> Vec<String> {
+    if results.iter().all(|result| result.peak_rss_mb.is_none()) {
+        return Vec::new();
+    }
+    let mut lines = vec![match results.iter().find_map(|r| r.baseline_rss_mb) {
+        Some(baseline) => format!(
+            "🧠 Peak memory of the whole process ({:.0} MB before any model was loaded):",
+            baseline
+        ),
+        None => "🧠 Peak memory of the whole process:".to_string(),
+    }];
+    for result in results {
+        let Some(peak) = result.peak_rss_mb else {
+            continue;
+        };
+        lines.push(format!(
+            "   {} on {} with {}{}: {:.0} MB{}",
+            result.model_size,
+            result.device,
+            result.compute_type,
+            result
+                .label
+                .as_ref()
+                .map(|label| format!(", {}", label))
+                .unwrap_or_default(),
+            peak,
+            result
+                .peak_rss_delta_mb
+                .map(|delta| format!(" (+{:.0} MB during its run)", delta))
+                .unwrap_or_default()
+        ));
+    }
+    lines
+}
+
+/// Which of `results` ran while the machine was likely throttling, by how far the reference
+/// workload slowed down around them; nothing when the throttle check didn't run
+pub fn throttle_lines(results: &[BenchmarkResult]) -> Vec<String> {
+    let Some(worst) = results
+        .iter()
+        .filter_map(|result| result.reference_slowdown_pct)
+        .reduce(f64::max)
+    else {
+        return Vec::new();
+    };
+    let suspected: Vec<_> = results.iter().filter(|r| r.throttling_suspected).collect();
+    if suspected.is_empty() {
+        return vec![format!(
+            "🌡️  No thermal throttling suspected; the reference workload ran at most {:.0}% slower than at first",
+            worst
+        )];
+    }
+    let mut lines = vec![
+        "⚠️  Thermal throttling suspected; the reference workload ran slower around:".to_string(),
+    ];
+    for result in suspected {
+        lines.push(format!(
+            "   {} on {} with {}{}: {:.0}% slower",
+            result.model_size,
+            result.device,
+            result.compute_type,
+            result
+                .label
+                .as_ref()
+                .map(|label| format!(", {}", label))
+                .unwrap_or_default(),
+            result.reference_slowdown_pct.unwrap_or_default()
+        ));
+    }
+    lines
+}
+
+/// Backends this build can run
+pub fn available_backends() -> Vec<Backend> {
+    let mut backends = vec![Backend::FasterWhisper];
+    if cfg!(feature = "whispercpp") {
+        backends.push(Backend::WhisperCpp);
+    }
+    if cfg!(feature = "candle") {
+        backends.push(Backend::Candle);
+    }
+    backends
+}
+
+/// Time one transcription of `audio_path` on an already constructed backend.
+///
+/// The model is loaded first so that loading isn't counted.
+pub fn benchmark_backend(
+    backend: &dyn TranscriptionBackend,
+    audio_path: &Path,
+) -> Result<BenchmarkResult> {
+    benchmark_backend_with_options(backend, backend.options(), audio_path)
+}
+
+/// `benchmark_backend` decoding with `options` rather than the backend's own, for a model
+/// shared by configs that differ in their options
+pub fn benchmark_backend_with_options(
+    backend: &dyn TranscriptionBackend,
+    options: &TranscriptionOptions,
+    audio_path: &Path,
+) -> Result<BenchmarkResult> {
+    measure(backend, options, audio_path).map(|(measured, _)| measured)
+}
+
+/// `benchmark_backend_with_options`, also returning the transcription it timed
+fn measure(
+    backend: &dyn TranscriptionBackend,
+    options: &TranscriptionOptions,
+    audio_path: &Path,
+) -> Result<(BenchmarkResult, TranscriptionResult)> {
+    backend.load()?;
+    let result = backend.transcribe_path(audio_path, options)?;
+    let measured = BenchmarkResult {
+        beam_size: options.beam_size,
+        vad_filter: Some(options.vad_filter),
+        config_hash: Some(config_hash(backend.config(), options)?),
+        recorded_at: Some(rfc3339(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        )),
+        ..BenchmarkResult::from_transcription(backend.config(), &result)
+    };
+    Ok((measured, result))
+}
+
+/// One configuration to benchmark: a model and the decoding options it runs with
+#[derive(Debug, Clone)]
+pub struct BenchmarkConfig {
+    pub model: ModelConfig,
+    pub options: TranscriptionOptions,
+    /// Tells apart configs of the same model in the comparison
+    pub label: Option<String>,
 }
 
 pub struct Benchmark {
-    configs: Vec<ModelConfig>,
+    configs: Vec<BenchmarkConfig>,
+    agreement: bool,
+    pool: Arc<ModelPool>,
+    warmup_runs: usize,
+    throttle: Option<ThrottleCheck>,
+    transcript_dir: Option<PathBuf>,
 }
 
 impl Benchmark {
     pub fn new() -> Self {
         Self {
             configs: Vec::new(),
+            agreement: false,
+            // One model at a time, kept for the next config if that uses the same one
+            pool: Arc::new(ModelPool::new(
+                ModelPoolLimits {
+                    max_models: Some(1),
+                    max_total_estimated_mb: None,
+                },
+                WhenFull::Evict,
+            )),
+            warmup_runs: 0,
+            throttle: None,
+            transcript_dir: None,
         }
     }
 
+    /// Transcribe the audio `runs` times with each config before the measured run, so
+    /// kernel compilation and cold caches (notably on Metal) aren't measured
+    pub fn set_warmup_runs(&mut self, runs: usize) {
+        self.warmup_runs = runs;
+    }
+
+    /// Time `check`'s reference workload before the first config, between configs and after
+    /// the last, and mark the results the machine may have been throttling around
+    pub fn set_throttle_check(&mut self, check: Option<ThrottleCheck>) {
+        self.throttle = check;
+    }
+
+    /// Save each config's full transcript as JSON in `dir`, created if need be, so a bad
+    /// score can be looked into. The file is named after the config and its label, and
+    /// its path is kept in the result.
+    pub fn set_save_transcripts(&mut self, dir: Option<PathBuf>) {
+        self.transcript_dir = dir;
+    }
+
+    /// Keep the models loaded for the configs within `limits` instead of one at a time
+    pub fn with_model_limits(self, limits: ModelPoolLimits, when_full: WhenFull) -> Self {
+        self.with_model_pool(Arc::new(ModelPool::new(limits, when_full)))
+    }
+
+    /// Take the configs' models from `pool`, which may be shared with other users
+    pub fn with_model_pool(mut self, pool: Arc<ModelPool>) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Hits and misses of the model pool so far
+    pub fn model_pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    /// Score how far the configs' transcripts agree with each other, for audio without a
+    /// reference transcript. See `score_agreement`.
+    pub fn with_agreement(mut self, agreement: bool) -> Self {
+        self.agreement = agreement;
+        self
+    }
+
     pub fn add_config(&mut self, config: ModelConfig) {
-        self.configs.push(config);
+        self.add_config_with_options(config, TranscriptionOptions::default(), None);
+    }
+
+    /// Benchmark `config` decoding with `options`, shown as `label` in the comparison
+    pub fn add_config_with_options(
+        &mut self,
+        config: ModelConfig,
+        options: TranscriptionOptions,
+        label: Option<String>,
+    ) {
+        self.configs.push(BenchmarkConfig {
+            model: config,
+            options,
+            label,
+        });
+    }
+
+    /// One config per beam size, otherwise identical, to find the smallest beam that is
+    /// accurate enough for a model. Fails without adding any if a beam size is invalid.
+    pub fn add_beam_size_sweep(
+        &mut self,
+        model_size: &str,
+        device: &str,
+        compute_type: &str,
+        beams: &[usize],
+    ) -> Result<()> {
+        let options: Vec<_> = beams
+            .iter()
+            .map(|&beam_size| {
+                let options = TranscriptionOptions {
+                    beam_size: Some(beam_size),
+                    ..TranscriptionOptions::default()
+                };
+                options
+                    .validate()
+                    .map(|()| options)
+                    .map_err(TranscriptionError::ConfigError)
+            })
+            .collect::<Result<_>>()?;
+        for options in options {
+            let label = format!("beam {}", options.beam_size.unwrap_or_default());
+            self.add_config_with_options(
+                ModelConfig::new(model_size, device, compute_type),
+                options,
+                Some(label),
+            );
+        }
+        Ok(())
     }
 
     pub fn add_cpu_vs_metal_comparison(&mut self, model_size: &str, compute_type: &str) {
         // Add CPU configuration
-        self.configs
-            .push(ModelConfig::new(model_size, "cpu", compute_type));
+        self.add_config(ModelConfig::new(model_size, "cpu", compute_type));
         // Add Metal/MPS configuration for macOS
-        self.configs
-            .push(ModelConfig::new(model_size, "mps", compute_type));
+        self.add_config(ModelConfig::new(model_size, "mps", compute_type));
     }
 
     pub fn add_model_size_comparison(&mut self, device: &str, compute_type: &str) {
         let models = ["tiny", "base", "small", "medium"];
         for model in models {
-            self.configs
-                .push(ModelConfig::new(model, device, compute_type));
+            self.add_config(ModelConfig::new(model, device, compute_type));
         }
     }
 
     pub fn add_compute_type_comparison(&mut self, model_size: &str, device: &str) {
         let compute_types = ["float16", "float32"];
         for compute_type in compute_types {
-            self.configs
-                .push(ModelConfig::new(model_size, device, compute_type));
+            self.add_config(ModelConfig::new(model_size, device, compute_type));
+        }
+    }
+
+    /// Two configs differing only in the VAD filter, to see what it costs and what it trims
+    pub fn add_vad_comparison(&mut self, model_size: &str, device: &str, compute_type: &str) {
+        for vad_filter in [true, false] {
+            let options = TranscriptionOptions {
+                vad_filter,
+                ..TranscriptionOptions::default()
+            };
+            let label = if vad_filter { "vad on" } else { "vad off" };
+            self.add_config_with_options(
+                ModelConfig::new(model_size, device, compute_type),
+                options,
+                Some(label.to_string()),
+            );
         }
     }
 
@@ -81,14 +1277,23 @@ impl Benchmark {
         self.add_config(ModelConfig::new("medium", device, compute_type));
     }
 
+    /// Run the same model on every backend compiled into this build
+    pub fn add_backend_comparison(&mut self, model_size: &str, device: &str, compute_type: &str) {
+        for backend in available_backends() {
+            let mut config = ModelConfig::new(model_size, device, compute_type);
+            config.backend = backend;
+            self.add_config(config);
+        }
+    }
+
     /// Add Metal acceleration specific benchmarks
     pub fn add_metal_optimized_benchmarks(&mut self) {
         info!("Adding Metal-optimized benchmarks for Apple Silicon");
-        
+
         // Compare CPU vs Metal for medium model
         self.add_config(ModelConfig::new("medium", "cpu", "float32"));
         self.add_config(ModelConfig::new("medium", "auto", "float16")); // Auto detects Metal
-        
+
         // Different compute types on Metal
         self.add_config(ModelConfig::new("medium", "auto", "float16"));
         self.add_config(ModelConfig::new("medium", "auto", "float32"));
@@ -96,78 +1301,221 @@ impl Benchmark {
 
     pub async fn run<P: AsRef<Path>>(&self, audio_path: P) -> Result<Vec<BenchmarkResult>> {
         let audio_path = audio_path.as_ref();
-        let mut results = Vec::new();
+        let mut results: Vec<BenchmarkResult> = Vec::new();
 
         info!(
             "Starting benchmark with {} configurations",
             self.configs.len()
         );
         info!("Audio file: {}", audio_path.display());
+        let baseline_rss_mb = rss::peak_rss_mb();
+        let mut monitor = self.throttle_monitor(audio_path);
+        let transcript_dir = self.transcript_dir.as_deref().filter(|dir| {
+            match plan::ensure_writable_dir(dir, true) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Not saving transcripts: {}", e);
+                    false
+                }
+            }
+        });
+        let mut transcript_names = HashSet::new();
+        // Where the config just run put its result, for the next reading to account for
+        let mut last: Option<usize> = None;
 
         for (i, config) in self.configs.iter().enumerate() {
             info!(
-                "Running benchmark {}/{}: {} on {} with {}",
+                "Running benchmark {}/{}: {} on {} with {}{}",
                 i + 1,
                 self.configs.len(),
-                config.model_size,
-                config.device,
-                config.compute_type
+                config.model.model_size,
+                config.model.device,
+                config.model.compute_type,
+                config
+                    .label
+                    .as_ref()
+                    .map(|label| format!(" ({})", label))
+                    .unwrap_or_default()
             );
 
+            let reading = read_reference(&mut monitor, true).await;
+            if let (Some(reading), Some(at), Some(check)) = (reading, last.take(), &self.throttle) {
+                results[at].note_reference_slowdown(reading.first_percent, check);
+            }
+
             match self.run_single_benchmark(config, audio_path).await {
-                Ok(result) => {
+                Ok((result, transcription)) => {
                     info!(
                         "✓ Completed: {:.2}s ({}x real-time)",
                         result.transcription_time, result.real_time_factor
                     );
+                    let mut result = BenchmarkResult {
+                        label: config.label.clone(),
+                        baseline_rss_mb,
+                        ..result
+                    };
+                    if let (Some(reading), Some(check)) = (reading, &self.throttle) {
+                        result.note_reference_slowdown(reading.settled_percent, check);
+                    }
+                    if let Some(dir) = transcript_dir {
+                        let name = unique_name(transcript_name(config), &mut transcript_names);
+                        let path = dir.join(format!("{}.json", name));
+                        match save_transcript(&transcription, &path) {
+                            Ok(()) => result.transcript_path = Some(path),
+                            Err(e) => {
+                                warn!("Couldn't save the transcript to {}: {}", path.display(), e)
+                            }
+                        }
+                    }
+                    last = Some(results.len());
                     results.push(result);
                 }
                 Err(e) => {
-                    eprintln!("✗ Failed {}/{}: {}", config.model_size, config.device, e);
+                    eprintln!(
+                        "✗ Failed {}/{}: {}",
+                        config.model.model_size, config.model.device, e
+                    );
                 }
             }
         }
 
+        let reading = read_reference(&mut monitor, false).await;
+        if let (Some(reading), Some(at), Some(check)) = (reading, last, &self.throttle) {
+            results[at].note_reference_slowdown(reading.first_percent, check);
+        }
+
+        let stats = self.pool.stats();
+        info!(
+            "Model pool: {} hit(s), {} miss(es)",
+            stats.hits, stats.misses
+        );
+        if self.agreement {
+            score_agreement(&mut results);
+        }
         Ok(results)
     }
 
+    /// The throttle check's monitor with its reference model loaded; `None` without a check
+    /// or when the reference can't be set up, which is only warned about
+    fn throttle_monitor(&self, audio_path: &Path) -> Option<ThrottleMonitor> {
+        let check = self.throttle.clone()?;
+        let setup = check.reference_samples(audio_path).and_then(|samples| {
+            // Held apart from the pool, so the configs' models don't evict it
+            let reference = self.pool.load_apart(&check.model)?;
+            reference.load()?;
+            Ok((reference, samples))
+        });
+        match setup {
+            Ok((reference, samples)) => Some(ThrottleMonitor::new(check, reference, samples)),
+            Err(e) => {
+                warn!("Not checking for thermal throttling: {}", e);
+                None
+            }
+        }
+    }
+
     async fn run_single_benchmark<P: AsRef<Path>>(
         &self,
-        config: &ModelConfig,
+        config: &BenchmarkConfig,
         audio_path: P,
-    ) -> Result<BenchmarkResult> {
-        let transcriber = FasterWhisperTranscriber::new(config.clone())?;
-
-        // Warm up - not counted in benchmark
-        if let Err(e) = transcriber.test_initialization() {
-            return Err(e);
+    ) -> Result<(BenchmarkResult, TranscriptionResult)> {
+        let audio_path = audio_path.as_ref();
+        let rss_before = rss::peak_rss_mb();
+        let model = self.pool.acquire(&config.model)?;
+        model.load()?;
+        let mut warmup_times = Vec::with_capacity(self.warmup_runs);
+        for run in 1..=self.warmup_runs {
+            info!("Warm-up {}/{}", run, self.warmup_runs);
+            let result = model.transcribe_path(audio_path, &config.options)?;
+            warmup_times.push(result.transcription_time);
         }
-
-        let result = transcriber.transcribe(audio_path)?;
-        Ok(BenchmarkResult::from_transcription(config, &result))
+        let (result, transcription) = measure(&*model, &config.options, audio_path)?;
+        let peak_rss_mb = rss::peak_rss_mb();
+        let result = BenchmarkResult {
+            warmup_times,
+            peak_rss_mb,
+            peak_rss_delta_mb: rss_before
+                .zip(peak_rss_mb)
+                .map(|(before, after)| after - before),
+            ..result
+        };
+        Ok((result, transcription))
     }
 
     pub fn print_comparison(&self, results: &[BenchmarkResult]) {
         println!("\n📊 Benchmark Results Comparison");
         println!(
-            "{:<10} {:<8} {:<10} {:<8} {:<12} {:<8} {:<8}",
-            "Model", "Device", "Compute", "Audio", "Transcr.", "RT Factor", "Segments"
+            "{:<15} {:<10} {:<8} {:<10} {:<5} {:<8} {:<12} {:<8} {:<8} {:<6}",
+            "Backend",
+            "Model",
+            "Device",
+            "Compute",
+            "Beam",
+            "Audio",
+            "Transcr.",
+            "RT Factor",
+            "Segments",
+            "Agree"
         );
-        println!("{}", "-".repeat(80));
+        println!("{}", "-".repeat(109));
 
         for result in results {
             println!(
-                "{:<10} {:<8} {:<10} {:<8.1}s {:<12.2}s {:<8.1}x {:<8}",
+                "{:<15} {:<10} {:<8} {:<10} {:<5} {:<8.1}s {:<12.2}s {:<8.1}x {:<8} {:<6}",
+                result.backend.as_str(),
                 result.model_size,
                 result.device,
                 result.compute_type,
+                result
+                    .beam_size
+                    .map(|beam| beam.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
                 result.audio_duration,
                 result.transcription_time,
                 result.real_time_factor,
-                result.segments_count
+                result.segments_count,
+                result
+                    .agreement_score
+                    .map(|score| format!("{:.0}%", score * 100.0))
+                    .unwrap_or_else(|| "-".to_string())
             );
         }
 
+        if let Some(note) = warmup_note(results) {
+            println!("\n{}", note);
+        }
+
+        let throttling = throttle_lines(results);
+        if !throttling.is_empty() {
+            println!();
+            throttling.iter().for_each(|line| println!("{}", line));
+        }
+
+        let memory = peak_rss_lines(results);
+        if !memory.is_empty() {
+            println!();
+            memory.iter().for_each(|line| println!("{}", line));
+        }
+
+        let outliers = agreement_outliers(results);
+        if !outliers.is_empty() {
+            println!("\n⚠️  Low agreement with the other configs (possible hallucination):");
+            for result in outliers.iter().map(|&i| &results[i]) {
+                println!(
+                    "   {} on {} with {}{}: {:.0}%",
+                    result.model_size,
+                    result.device,
+                    result.compute_type,
+                    result
+                        .label
+                        .as_ref()
+                        .map(|label| format!(", {}", label))
+                        .unwrap_or_default(),
+                    result.agreement_score.unwrap_or_default() * 100.0
+                );
+            }
+        }
+
         // Find best performance
         if let Some(fastest) = results.iter().max_by(|a, b| {
             a.real_time_factor
@@ -176,8 +1524,21 @@ impl Benchmark {
         }) {
             println!("\n🏆 Fastest Configuration:");
             println!(
-                "   {} on {} with {} - {:.1}x real-time",
-                fastest.model_size, fastest.device, fastest.compute_type, fastest.real_time_factor
+                "   {} on {} with {}{} - {:.1}x real-time{}",
+                fastest.model_size,
+                fastest.device,
+                fastest.compute_type,
+                fastest
+                    .label
+                    .as_ref()
+                    .map(|label| format!(", {}", label))
+                    .unwrap_or_default(),
+                fastest.real_time_factor,
+                if fastest.throttling_suspected {
+                    " (throttling suspected)"
+                } else {
+                    ""
+                }
             );
         }
 
@@ -204,19 +1565,103 @@ impl Benchmark {
                 }
             }
         }
+
+        let vad = compare_vad(results);
+        if !vad.is_empty() {
+            println!("\n🔇 VAD on vs off:");
+            for comparison in &vad {
+                println!(
+                    "   {}: VAD changes speed by {:+.1}% ({:.1}x vs {:.1}x) and trims {:.1}s of segments ({:.1}s vs {:.1}s)",
+                    comparison.setup,
+                    comparison.rtf_change() * 100.0,
+                    comparison.rtf_with_vad,
+                    comparison.rtf_without_vad,
+                    comparison.trimmed_seconds(),
+                    comparison.segments_with_vad,
+                    comparison.segments_without_vad
+                );
+            }
+        }
     }
 
+    /// Save `results` with the machine they ran on, for `BenchmarkResultSet` to read back
     pub fn save_results_json<P: AsRef<Path>>(
         &self,
         results: &[BenchmarkResult],
         path: P,
     ) -> Result<()> {
-        let json = serde_json::to_string_pretty(results)?;
-        std::fs::write(path, json)?;
+        let json = serde_json::to_string_pretty(&SavedRun {
+            system: SystemInfo::current(),
+            results: results.to_vec(),
+        })?;
+        output::write_atomic(path.as_ref(), json)?;
         Ok(())
     }
 }
 
+/// `medium-mps-float16-beam-5`: the config's backend unless the default, model, device,
+/// compute type and label, with anything but letters, digits, `.` and `_` made a dash
+fn transcript_name(config: &BenchmarkConfig) -> String {
+    let model = &config.model;
+    let mut parts = vec![
+        model.model_size.as_str(),
+        model.device.as_str(),
+        model.compute_type.as_str(),
+    ];
+    if model.backend != Backend::default() {
+        parts.insert(0, model.backend.as_str());
+    }
+    parts.extend(config.label.as_deref());
+    let name: String = parts
+        .join("-")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    name.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// `name`, or `name-2`, `name-3` and so on when an earlier config of the run took it
+fn unique_name(name: String, taken: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut n = 1;
+    while !taken.insert(unique.clone()) {
+        n += 1;
+        unique = format!("{}-{}", name, n);
+    }
+    unique
+}
+
+fn save_transcript(transcription: &TranscriptionResult, path: &Path) -> Result<()> {
+    let json = output::render(transcription, OutputFormat::Json)?;
+    output::write_atomic(path, json)?;
+    Ok(())
+}
+
+/// A reading of `monitor`'s reference workload. If it fails, throttling is no longer
+/// checked for the rest of the run.
+async fn read_reference(monitor: &mut Option<ThrottleMonitor>, cool_down: bool) -> Option<Reading> {
+    match monitor.as_mut()?.read(cool_down).await {
+        Ok(reading) => Some(reading),
+        Err(e) => {
+            warn!(
+                "Reference workload failed, so no longer checking for throttling: {}",
+                e
+            );
+            *monitor = None;
+            None
+        }
+    }
+}
+
 impl Default for Benchmark {
     fn default() -> Self {
         Self::new()
@@ -226,6 +1671,26 @@ impl Default for Benchmark {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{segment, MockBackend, ResultBuilder};
+    use crate::transcriber::FasterWhisperTranscriber;
+    use crate::types::TranscriptionSegment;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Transcribes anything to one segment, instantly
+    fn mock(config: &ModelConfig) -> MockBackend {
+        let segment = TranscriptionSegment {
+            no_speech_prob: 0.0,
+            avg_logprob: -0.1,
+            ..segment(0.0, 10.0, "hello world")
+        };
+        let result = ResultBuilder::new()
+            .language_probability(1.0)
+            .segment(segment)
+            .build();
+        MockBackend::new()
+            .with_config(config.clone())
+            .with_result(result)
+    }
 
     #[test]
     fn test_benchmark_creation() {
@@ -242,8 +1707,8 @@ mod tests {
         benchmark.add_cpu_vs_metal_comparison("base", "float16");
 
         assert_eq!(benchmark.configs.len(), 2);
-        assert_eq!(benchmark.configs[0].device, "cpu");
-        assert_eq!(benchmark.configs[1].device, "mps");
+        assert_eq!(benchmark.configs[0].model.device, "cpu");
+        assert_eq!(benchmark.configs[1].model.device, "mps");
     }
 
     #[test]
@@ -254,6 +1719,468 @@ mod tests {
         assert_eq!(benchmark.configs.len(), 4); // tiny, base, small, medium
     }
 
+    #[test]
+    fn test_backend_comparison() {
+        let mut benchmark = Benchmark::new();
+        benchmark.add_backend_comparison("base", "auto", "float16");
+
+        assert_eq!(benchmark.configs.len(), available_backends().len());
+        assert_eq!(benchmark.configs[0].model.backend, Backend::FasterWhisper);
+        assert!(benchmark
+            .configs
+            .iter()
+            .all(|c| c.model.model_size == "base"));
+    }
+
+    #[test]
+    fn test_beam_size_sweep() {
+        let mut benchmark = Benchmark::new();
+        benchmark
+            .add_beam_size_sweep("small", "cpu", "int8", &[1, 2, 5])
+            .unwrap();
+
+        assert_eq!(benchmark.configs.len(), 3);
+        let beams: Vec<_> = benchmark
+            .configs
+            .iter()
+            .map(|c| (c.options.beam_size, c.label.as_deref()))
+            .collect();
+        assert_eq!(
+            beams,
+            vec![
+                (Some(1), Some("beam 1")),
+                (Some(2), Some("beam 2")),
+                (Some(5), Some("beam 5"))
+            ]
+        );
+        assert!(benchmark
+            .configs
+            .iter()
+            .all(|c| c.model.model_size == "small" && c.model.compute_type == "int8"));
+
+        // Nothing is added when any beam size is invalid
+        for beams in [&[5, 0][..], &[1, 1000]] {
+            let err = benchmark
+                .add_beam_size_sweep("small", "cpu", "int8", beams)
+                .unwrap_err();
+            assert_eq!(err.kind(), "config");
+        }
+        assert_eq!(benchmark.configs.len(), 3);
+    }
+
+    #[test]
+    fn test_vad_comparison() {
+        let mut benchmark = Benchmark::new();
+        benchmark.add_vad_comparison("base", "cpu", "int8");
+        assert_eq!(benchmark.configs.len(), 2);
+        assert!(benchmark.configs[0].options.vad_filter);
+        assert!(!benchmark.configs[1].options.vad_filter);
+        assert_eq!(benchmark.configs[1].label.as_deref(), Some("vad off"));
+
+        let transcription = TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.99,
+            duration: 60.0,
+            segments: vec![
+                TranscriptionSegment {
+                    start: 1.0,
+                    end: 30.0,
+                    text: "One".to_string(),
+                    no_speech_prob: 0.0,
+                    avg_logprob: -0.1,
+                    words: vec![],
+                    speaker: None,
+                    tokens: None,
+                },
+                TranscriptionSegment {
+                    start: 31.0,
+                    end: 57.0,
+                    text: "Two".to_string(),
+                    no_speech_prob: 0.0,
+                    avg_logprob: -0.1,
+                    words: vec![],
+                    speaker: None,
+                    tokens: None,
+                },
+            ],
+            full_text: "One Two".to_string(),
+            transcription_time: 5.0,
+            real_time_factor: 12.0,
+            ..Default::default()
+        };
+        let with_vad = BenchmarkResult {
+            vad_filter: Some(true),
+            ..BenchmarkResult::from_transcription(
+                &ModelConfig::new("base", "cpu", "int8"),
+                &transcription,
+            )
+        };
+        assert_eq!(with_vad.segments_duration, 55.0);
+        let without_vad = BenchmarkResult {
+            vad_filter: Some(false),
+            real_time_factor: 10.0,
+            segments_duration: 58.0,
+            ..with_vad.clone()
+        };
+        let results = [with_vad, without_vad];
+        let comparisons = compare_vad(&results);
+        assert_eq!(comparisons.len(), 1);
+        assert_eq!(comparisons[0].setup, "base/cpu/int8");
+        assert!((comparisons[0].rtf_change() - 0.2).abs() < 1e-9);
+        assert_eq!(comparisons[0].trimmed_seconds(), 3.0);
+
+        // Nothing to compare without both settings
+        assert!(compare_vad(&results[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_report_sorts_with_missing_values_last() {
+        let row = |model: &str, device: &str, rtf: f64, memory: Option<f64>| BenchmarkResult {
+            model_size: model.to_string(),
+            device: device.to_string(),
+            real_time_factor: rtf,
+            transcription_time: 60.0 / rtf,
+            memory_usage_mb: memory,
+            ..BenchmarkResult::from_transcription(
+                &ModelConfig::new("base", "cpu", "int8"),
+                &TranscriptionResult {
+                    language: "en".to_string(),
+                    language_probability: 0.99,
+                    duration: 60.0,
+                    segments: vec![],
+                    full_text: String::new(),
+                    transcription_time: 1.0,
+                    real_time_factor: 60.0,
+                    ..Default::default()
+                },
+            )
+        };
+        let report = BenchmarkReport::new(vec![
+            row("base", "cpu", 8.0, Some(900.0)),
+            row("medium", "mps", 12.0, None),
+            row("base", "mps", 30.0, Some(700.0)),
+            row("tiny", "MPS", 30.0, Some(400.0)),
+        ]);
+        let models = |report: &BenchmarkReport| -> Vec<String> {
+            report
+                .results()
+                .iter()
+                .map(|r| r.model_size.clone())
+                .collect()
+        };
+
+        let key = BenchmarkSort::Rtf;
+        let fastest = report.clone().sort(key, key.default_order());
+        // Ties keep the order they ran in
+        assert_eq!(models(&fastest), ["base", "tiny", "medium", "base"]);
+        assert_eq!(fastest.results()[0].device, "mps");
+        let slowest = report
+            .clone()
+            .sort(BenchmarkSort::Time, SortOrder::Descending);
+        assert_eq!(slowest.results()[0].model_size, "base");
+        assert_eq!(slowest.results()[0].device, "cpu");
+
+        // Unmeasured memory goes last whichever way it's sorted
+        let smallest = report
+            .clone()
+            .sort(BenchmarkSort::Memory, SortOrder::Ascending);
+        assert_eq!(models(&smallest), ["tiny", "base", "base", "medium"]);
+        let largest = report
+            .clone()
+            .sort(BenchmarkSort::Memory, SortOrder::Descending);
+        assert_eq!(models(&largest), ["base", "base", "tiny", "medium"]);
+
+        // Filters on one field are alternatives; filters on different fields all apply
+        let filters: Vec<RowFilter> = ["device=mps", "model=base", "model=tiny"]
+            .iter()
+            .map(|f| f.parse().unwrap())
+            .collect();
+        let filtered = report.clone().filter(&filters);
+        assert_eq!(models(&filtered), ["base", "tiny"]);
+        assert_eq!(report.clone().filter(&[]).results().len(), 4);
+        let vad_off: RowFilter = "vad=off".parse().unwrap();
+        assert!(report.filter(&[vad_off]).into_results().is_empty());
+    }
+
+    #[test]
+    fn test_parse_sort_and_filters() {
+        assert_eq!("RTF".parse(), Ok(BenchmarkSort::Rtf));
+        assert_eq!("mem".parse(), Ok(BenchmarkSort::Memory));
+        assert!("wer".parse::<BenchmarkSort>().is_err());
+        assert_eq!(BenchmarkSort::Time.default_order(), SortOrder::Ascending);
+        assert_eq!(
+            BenchmarkSort::Accuracy.default_order(),
+            SortOrder::Descending
+        );
+        assert_eq!("desc".parse(), Ok(SortOrder::Descending));
+        assert!("up".parse::<SortOrder>().is_err());
+
+        assert!("device=mps".parse::<RowFilter>().is_ok());
+        assert!(" Compute_Type = int8".parse::<RowFilter>().is_ok());
+        assert!("device".parse::<RowFilter>().is_err());
+        assert!("gpu=mps".parse::<RowFilter>().is_err());
+        assert!("vad=maybe".parse::<RowFilter>().is_err());
+    }
+
+    #[test]
+    fn test_result_set_loads_every_saved_format() {
+        let dir = tempfile::tempdir().unwrap();
+        // The first format: a bare array, before backends, labels or VAD were recorded
+        std::fs::write(
+            dir.path().join("a-original.json"),
+            r#"[{"model_size":"medium","device":"mps","compute_type":"float16",
+                "audio_duration":60.0,"transcription_time":5.0,"real_time_factor":12.0,
+                "memory_usage_mb":null,"accuracy_score":null,"segments_count":9}]"#,
+        )
+        .unwrap();
+        // Still a bare array, with the per-config fields and agreement
+        std::fs::write(
+            dir.path().join("b-configs.json"),
+            r#"[{"backend":"whispercpp","model_size":"medium","device":"cpu",
+                "compute_type":"int8","audio_duration":60.0,"transcription_time":3.0,
+                "real_time_factor":20.0,"memory_usage_mb":null,"accuracy_score":null,
+                "segments_count":9,"label":"beam 1","beam_size":1,"vad_filter":true,
+                "segments_duration":55.0,"agreement_score":0.9},
+               {"backend":"faster-whisper","model_size":"base","device":"cpu",
+                "compute_type":"int8","audio_duration":60.0,"transcription_time":1.0,
+                "real_time_factor":60.0,"memory_usage_mb":null,"accuracy_score":null,
+                "segments_count":8}]"#,
+        )
+        .unwrap();
+        // The current format, with the machine it ran on
+        let current = BenchmarkResult {
+            real_time_factor: 15.0,
+            ..serde_json::from_str::<Vec<BenchmarkResult>>(
+                &std::fs::read_to_string(dir.path().join("a-original.json")).unwrap(),
+            )
+            .unwrap()
+            .remove(0)
+        };
+        Benchmark::new()
+            .save_results_json(&[current], dir.path().join("c-current.json"))
+            .unwrap();
+        // Not benchmark results
+        std::fs::write(dir.path().join("talk.json"), r#"{"language":"en"}"#).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not json").unwrap();
+
+        let set = BenchmarkResultSet::load_dir(dir.path()).unwrap();
+        assert_eq!(set.results().len(), 4);
+        assert_eq!(set.skipped(), [dir.path().join("talk.json")]);
+        let original = &set.results()[0];
+        assert_eq!(original.result.backend, Backend::FasterWhisper);
+        assert_eq!(
+            (original.result.label.as_ref(), original.system.as_ref()),
+            (None, None)
+        );
+        assert_eq!(set.results()[1].result.vad_filter, Some(true));
+        assert_eq!(set.results()[3].system, Some(SystemInfo::current()));
+
+        let groups = set.group_by_model();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), ["base", "medium"]);
+        assert_eq!(groups["medium"].len(), 3);
+        assert_eq!(set.best_by_rtf().unwrap().result.model_size, "base");
+        let medium = set.clone().filter(|r| r.result.model_size == "medium");
+        let best = medium.best_by_rtf().unwrap();
+        assert_eq!(
+            (best.result.real_time_factor, best.result.device.as_str()),
+            (20.0, "cpu")
+        );
+        assert!(set.clone().filter(|_| false).best_by_rtf().is_none());
+
+        let csv = medium.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], RESULTS_CSV_HEADER);
+        assert_eq!(lines.len(), 4);
+        assert!(
+            lines[1].ends_with(",,faster-whisper,medium,mps,float16,,,,60,5,12,,,,9,,,,,,false,,")
+        );
+        // The label is written as is; quoting is only for separators
+        assert!(lines[2].contains(",whispercpp,medium,cpu,int8,beam 1,1,true,"));
+    }
+
+    #[test]
+    fn test_config_hash_is_pinned_and_ignores_where_the_model_lives() {
+        let config = ModelConfig::new("medium", "mps", "float16");
+        let options = TranscriptionOptions::default();
+        let hash = config_hash(&config, &options).unwrap();
+        // Changing this breaks matching against every saved result
+        assert_eq!(hash, "2a015a59feea8385");
+
+        let elsewhere = ModelConfig {
+            model_dir: Some("/models".into()),
+            offline: true,
+            ..config.clone()
+        };
+        assert_eq!(config_hash(&elsewhere, &options).unwrap(), hash);
+        // An option left unset hashes as if it didn't exist
+        let unset = TranscriptionOptions {
+            clip_start: None,
+            ..options.clone()
+        };
+        assert_eq!(config_hash(&config, &unset).unwrap(), hash);
+
+        let threads = ModelConfig {
+            cpu_threads: Some(4),
+            ..config.clone()
+        };
+        let beam = TranscriptionOptions {
+            beam_size: Some(5),
+            ..options.clone()
+        };
+        let threshold = TranscriptionOptions {
+            vad_threshold: 0.55,
+            ..options.clone()
+        };
+        let hashes = [
+            config_hash(&threads, &options).unwrap(),
+            config_hash(&config, &beam).unwrap(),
+            config_hash(&config, &threshold).unwrap(),
+        ];
+        assert!(hashes.iter().all(|other| *other != hash));
+        assert_ne!(hashes[1], hashes[2]);
+    }
+
+    #[test]
+    fn test_diff_matches_by_hash_then_setup() {
+        let base = BenchmarkResult::from_transcription(
+            &ModelConfig::new("medium", "mps", "float16"),
+            &TranscriptionResult {
+                language: "en".to_string(),
+                language_probability: 0.99,
+                duration: 60.0,
+                segments: vec![],
+                full_text: String::new(),
+                transcription_time: 5.0,
+                real_time_factor: 12.0,
+                ..Default::default()
+            },
+        );
+        let row =
+            |model: &str, hash: Option<&str>, rtf: f64, memory: Option<f64>| BenchmarkResult {
+                model_size: model.to_string(),
+                config_hash: hash.map(str::to_string),
+                real_time_factor: rtf,
+                memory_usage_mb: memory,
+                ..base.clone()
+            };
+        let beam = |result: BenchmarkResult| BenchmarkResult {
+            label: Some("beam 5".to_string()),
+            ..result
+        };
+        let old = [
+            row("base", Some("a1"), 10.0, None),
+            // Saved before hashes were recorded
+            row("medium", None, 12.0, Some(1000.0)),
+            row("small", Some("c3"), 20.0, None),
+            beam(row("tiny", Some("d4"), 40.0, None)),
+        ];
+        let new = [
+            row("base", Some("a1"), 8.0, None),
+            row("medium", Some("b2"), 15.0, Some(1100.0)),
+            // Same setup and label, but the options changed
+            beam(row("tiny", Some("d5"), 45.0, None)),
+            row("large-v3", Some("e5"), 3.0, None),
+        ];
+
+        let diff = BenchmarkDiff::new(&old, &new);
+        let names = |results: &[BenchmarkResult]| -> Vec<String> {
+            results.iter().map(|r| r.model_size.clone()).collect()
+        };
+        let matched: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|c| (c.new.model_size.as_str(), c.by_hash))
+            .collect();
+        assert_eq!(matched, [("base", true), ("medium", false)]);
+        assert_eq!(names(&diff.only_old), ["small", "tiny"]);
+        assert_eq!(names(&diff.only_new), ["tiny", "large-v3"]);
+
+        let slower = &diff.changes[0];
+        assert!((slower.rtf_change().unwrap() + 20.0).abs() < 1e-9);
+        assert_eq!(slower.memory_change(), None);
+        assert!((slower.regression() - 20.0).abs() < 1e-9);
+        // Faster, but using 10% more memory
+        let medium = &diff.changes[1];
+        assert!((medium.rtf_change().unwrap() - 25.0).abs() < 1e-9);
+        assert!((medium.regression() - 10.0).abs() < 1e-9);
+        assert_eq!(diff.regressions(5.0).len(), 2);
+        assert_eq!(diff.regressions(15.0).len(), 1);
+        assert!(diff.regressions(25.0).is_empty());
+
+        let plain = diff.render(false);
+        assert!(plain.contains("10.0 -> 8.0 (-20.0%)"));
+        assert!(plain.contains("1000.0 -> 1100.0 (+10.0%)"));
+        assert!(plain.contains("small/mps/float16"));
+        assert!(plain.contains("only in the old results"));
+        assert!(plain.contains("tiny/mps/float16 (beam 5)"));
+        assert!(!plain.contains('\x1b'));
+        let colored = diff.render(true);
+        assert!(colored.contains(&format!("{}(-20.0%){}", RED, RESET)));
+        assert!(colored.contains(&format!("{}(+25.0%){}", GREEN, RESET)));
+        assert!(colored.contains(&format!("{}(+10.0%){}", RED, RESET)));
+        // Columns line up whether or not they're colored
+        let widths = |table: &str| -> Vec<usize> {
+            table
+                .lines()
+                .map(|line| line.replace(RED, "").replace(GREEN, "").replace(RESET, ""))
+                .map(|line| line.chars().count())
+                .collect()
+        };
+        assert_eq!(widths(&plain), widths(&colored));
+    }
+
+    #[test]
+    fn test_agreement_scores_and_outliers() {
+        let result = |text: &str| BenchmarkResult {
+            full_text: text.to_string(),
+            ..BenchmarkResult::from_transcription(
+                &ModelConfig::new("base", "cpu", "int8"),
+                &TranscriptionResult {
+                    language: "en".to_string(),
+                    language_probability: 0.99,
+                    duration: 10.0,
+                    segments: vec![],
+                    full_text: String::new(),
+                    transcription_time: 1.0,
+                    real_time_factor: 10.0,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // A single result has nothing to agree with
+        let mut alone = [result("the quick brown fox")];
+        score_agreement(&mut alone);
+        assert_eq!(alone[0].agreement_score, None);
+
+        let mut results = [
+            result("the quick brown fox jumps over the lazy dog"),
+            result("the quick brown fox jumped over the lazy dog"),
+            result("The quick brown fox jumps over the lazy dog."),
+            result("thank you for watching please subscribe"),
+        ];
+        score_agreement(&mut results);
+        let scores: Vec<f64> = results.iter().map(|r| r.agreement_score.unwrap()).collect();
+        // Identical pairs agree fully, one changed word costs 1 of 9, the hallucination
+        // shares nothing
+        assert!((scores[0] - (8.0 / 9.0 + 1.0) / 3.0).abs() < 1e-12);
+        assert_eq!(scores[0], scores[2]);
+        assert!((scores[1] - 16.0 / 27.0).abs() < 1e-12);
+        assert_eq!(scores[3], 0.0);
+        assert_eq!(agreement_outliers(&results), vec![3]);
+
+        // Scoring again gives the same numbers
+        score_agreement(&mut results);
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| r.agreement_score.unwrap())
+                .collect::<Vec<_>>(),
+            scores
+        );
+        assert!(agreement_outliers(&results[..2]).is_empty());
+    }
+
     #[test]
     fn test_benchmark_result_creation() {
         let config = ModelConfig::new("base", "mps", "float16");
@@ -265,6 +2192,7 @@ mod tests {
             full_text: "Test".to_string(),
             transcription_time: 2.0,
             real_time_factor: 15.0,
+            ..Default::default()
         };
 
         let benchmark_result = BenchmarkResult::from_transcription(&config, &transcription_result);
@@ -274,4 +2202,227 @@ mod tests {
         assert_eq!(benchmark_result.compute_type, "float16");
         assert_eq!(benchmark_result.real_time_factor, 15.0);
     }
+
+    #[tokio::test]
+    async fn test_configs_differing_in_options_share_one_model() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let pool = ModelPool::new(
+            ModelPoolLimits {
+                max_models: Some(1),
+                max_total_estimated_mb: None,
+            },
+            WhenFull::Evict,
+        )
+        .with_loader({
+            let loads = loads.clone();
+            move |config| {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(mock(config)))
+            }
+        });
+        let mut benchmark = Benchmark::new().with_model_pool(Arc::new(pool));
+        benchmark
+            .add_beam_size_sweep("small", "cpu", "int8", &[1, 5])
+            .unwrap();
+
+        let results = benchmark.run("clip.wav").await.unwrap();
+        let beams: Vec<_> = results.iter().map(|result| result.beam_size).collect();
+        assert_eq!(beams, [Some(1), Some(5)]);
+        assert_ne!(results[0].config_hash, results[1].config_hash);
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        let stats = benchmark.model_pool_stats();
+        assert_eq!((stats.hits, stats.misses, stats.loaded), (1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_warmup_runs_are_recorded_apart() {
+        let pool = ModelPool::default().with_loader(|config| Ok(Box::new(mock(config))));
+        let mut benchmark = Benchmark::new().with_model_pool(Arc::new(pool));
+        benchmark.add_config(ModelConfig::new("medium", "mps", "float16"));
+        benchmark.set_warmup_runs(2);
+
+        let results = benchmark.run("clip.wav").await.unwrap();
+        assert_eq!(results[0].warmup_times, [1.0, 1.0]);
+        assert_eq!(results[0].transcription_time, 1.0);
+        let json = serde_json::to_value(&results[0]).unwrap();
+        assert_eq!(json["warmup_times"].as_array().unwrap().len(), 2);
+        if cfg!(any(target_os = "linux", target_os = "macos")) {
+            let peak = results[0].peak_rss_mb.unwrap();
+            assert!(peak >= results[0].baseline_rss_mb.unwrap());
+            assert!(results[0].peak_rss_delta_mb.unwrap() >= 0.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcripts_are_saved_per_config() {
+        let pool = ModelPool::default().with_loader(|config| Ok(Box::new(mock(config))));
+        let mut benchmark = Benchmark::new().with_model_pool(Arc::new(pool));
+        benchmark
+            .add_beam_size_sweep("large-v3", "mps", "float16", &[5])
+            .unwrap();
+        benchmark.add_config(ModelConfig::new("medium", "auto", "float16"));
+        benchmark.add_config(ModelConfig::new("medium", "auto", "float16"));
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("bench_out");
+        benchmark.set_save_transcripts(Some(out.clone()));
+
+        let results = benchmark.run("clip.wav").await.unwrap();
+        let paths: Vec<_> = results
+            .iter()
+            .map(|result| result.transcript_path.clone().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                out.join("large-v3-mps-float16-beam-5.json"),
+                out.join("medium-auto-float16.json"),
+                out.join("medium-auto-float16-2.json"),
+            ]
+        );
+        let saved: TranscriptionResult =
+            serde_json::from_str(&std::fs::read_to_string(&paths[0]).unwrap()).unwrap();
+        assert_eq!(saved.full_text, "hello world");
+        assert_eq!(saved.segments.len(), 1);
+        assert!(!output::tmp_path(&paths[0]).exists());
+
+        // A directory that can't be written is only warned about
+        let blocked = dir.path().join("blocked");
+        std::fs::write(&blocked, "a file").unwrap();
+        benchmark.set_save_transcripts(Some(blocked));
+        let results = benchmark.run("clip.wav").await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.transcript_path.is_none()));
+    }
+
+    #[test]
+    fn test_warmup_note() {
+        let config = ModelConfig::new("base", "cpu", "int8");
+        let mock = mock(&config);
+        let transcription = mock.transcribe_samples(&[], mock.options()).unwrap();
+        let result = |warmups: usize| BenchmarkResult {
+            warmup_times: vec![2.0; warmups],
+            ..BenchmarkResult::from_transcription(&config, &transcription)
+        };
+        assert_eq!(warmup_note(&[result(0), result(0)]), None);
+        assert!(warmup_note(&[result(1), result(1)])
+            .unwrap()
+            .contains("after 1 warm-up run(s) per config"));
+        assert!(warmup_note(&[result(0), result(2)])
+            .unwrap()
+            .contains("between 0 and 2 warm-up run(s)"));
+
+        let saved = serde_json::to_value(result(0)).unwrap();
+        assert!(saved.get("warmup_times").is_none());
+    }
+
+    #[test]
+    fn test_peak_rss_lines() {
+        let config = ModelConfig::new("base", "cpu", "int8");
+        let mock = mock(&config);
+        let transcription = mock.transcribe_samples(&[], mock.options()).unwrap();
+        let result = |peak: Option<f64>, delta: Option<f64>| BenchmarkResult {
+            peak_rss_mb: peak,
+            peak_rss_delta_mb: delta,
+            baseline_rss_mb: Some(40.0),
+            ..BenchmarkResult::from_transcription(&config, &transcription)
+        };
+        assert!(peak_rss_lines(&[result(None, None)]).is_empty());
+
+        let lines = peak_rss_lines(&[
+            result(Some(900.0), Some(860.0)),
+            result(Some(900.0), Some(0.0)),
+        ]);
+        assert_eq!(
+            lines,
+            [
+                "🧠 Peak memory of the whole process (40 MB before any model was loaded):",
+                "   base on cpu with int8: 900 MB (+860 MB during its run)",
+                "   base on cpu with int8: 900 MB (+0 MB during its run)",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_results_around_a_slow_reference_are_suspected() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("decoded.wav");
+        std::fs::write(&wav, crate::probe::wav_bytes(16000, 1, 1600)).unwrap();
+        let ffmpeg = dir.path().join("ffmpeg");
+        std::fs::write(&ffmpeg, format!("#!/bin/sh\ncat '{}'\n", wav.display())).unwrap();
+        std::fs::set_permissions(&ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let pool = ModelPool::default().with_loader(|config| {
+            let mock = mock(config);
+            if config.model_size != "tiny" {
+                return Ok(Box::new(mock));
+            }
+            // A warm-up, the baseline, then a reading between the configs and after them
+            let times = vec![3.0, 1.0, 1.05, 1.5];
+            Ok(Box::new(mock.with_times(&times)))
+        });
+        let mut benchmark = Benchmark::new().with_model_pool(Arc::new(pool));
+        benchmark.add_config(ModelConfig::new("base", "cpu", "int8"));
+        benchmark.add_config(ModelConfig::new("medium", "cpu", "int8"));
+        benchmark.set_throttle_check(Some(ThrottleCheck {
+            decoder: crate::ffmpeg::FfmpegDecoder::new().with_binary(&ffmpeg),
+            ..ThrottleCheck::default()
+        }));
+
+        let results = benchmark.run(dir.path().join("clip.wav")).await.unwrap();
+        assert!(!results[0].throttling_suspected);
+        assert!((results[0].reference_slowdown_pct.unwrap() - 5.0).abs() < 1e-9);
+        assert!(results[1].throttling_suspected);
+        assert_eq!(results[1].reference_slowdown_pct, Some(50.0));
+        // The reference model isn't one of the pool's
+        assert_eq!(benchmark.model_pool_stats().misses, 2);
+
+        let json = serde_json::to_value(&results[1]).unwrap();
+        assert_eq!(json["throttling_suspected"], true);
+        let clean = serde_json::to_value(&results[0]).unwrap();
+        assert!(clean.get("throttling_suspected").is_none());
+
+        assert_eq!(
+            throttle_lines(&results),
+            [
+                "⚠️  Thermal throttling suspected; the reference workload ran slower around:",
+                "   medium on cpu with int8: 50% slower",
+            ]
+        );
+        assert_eq!(
+            throttle_lines(&results[..1]),
+            ["🌡️  No thermal throttling suspected; the reference workload ran at most 5% slower than at first"]
+        );
+        let diff = BenchmarkDiff::new(&results, &results);
+        assert!(diff
+            .render(false)
+            .contains("medium/cpu/int8                      throttling suspected in both runs"));
+
+        // Without ffmpeg the configs still run, unchecked
+        let mut benchmark = Benchmark::new().with_model_pool(Arc::new(
+            ModelPool::default().with_loader(|config| Ok(Box::new(mock(config)))),
+        ));
+        benchmark.add_config(ModelConfig::new("base", "cpu", "int8"));
+        benchmark.set_throttle_check(Some(ThrottleCheck {
+            decoder: crate::ffmpeg::FfmpegDecoder::new().with_binary(dir.path().join("none")),
+            ..ThrottleCheck::default()
+        }));
+        let results = benchmark.run("clip.wav").await.unwrap();
+        assert_eq!(results[0].reference_slowdown_pct, None);
+        assert!(throttle_lines(&results).is_empty());
+    }
+
+    #[test]
+    fn test_benchmark_backend_takes_trait_objects() {
+        let transcriber =
+            FasterWhisperTranscriber::new(ModelConfig::new("tiny", "cpu", "float32")).unwrap();
+        let backend: &dyn TranscriptionBackend = &transcriber;
+        // Either faster-whisper is missing or the audio file is
+        let err = benchmark_backend(backend, Path::new("/nonexistent/clip.wav")).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            "model_init" | "package_missing" | "invalid_path"
+        ));
+    }
 }
@@ -0,0 +1,389 @@
+use crate::backend::TranscriptionBackend;
+use crate::error::Result;
+use crate::transcriber::WarmupReport;
+use crate::types::{ModelConfig, TranscriptionOptions, TranscriptionResult, TranscriptionSegment};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Bumped whenever the key derivation or entry layout changes, so old entries become misses
+const CACHE_VERSION: &str = "whisper-cache-v1";
+const ENTRY_EXTENSION: &str = "json";
+
+/// blake3 hash of a file's contents, read in chunks rather than all at once
+pub fn content_hash(path: &Path) -> Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+/// Identifies one transcription: the audio's contents plus everything that affects the output
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// Hash `audio_path`'s contents, streamed rather than read into memory, together with the
+    /// model and decoding settings. Model location and offline mode don't change the output
+    /// and are left out.
+    pub fn new(
+        audio_path: &Path,
+        config: &ModelConfig,
+        options: &TranscriptionOptions,
+    ) -> Result<Self> {
+        let content = content_hash(audio_path)?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(CACHE_VERSION.as_bytes());
+        hasher.update(content.as_bytes());
+        for field in [
+            config.backend.as_str(),
+            &config.model_size,
+            &config.device,
+            &config.compute_type,
+        ] {
+            hasher.update(&[0]);
+            hasher.update(field.as_bytes());
+        }
+        hasher.update(&[0]);
+        hasher.update(&serde_json::to_vec(options)?);
+        Ok(Self(hasher.finalize().to_hex().to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Transcription results stored on disk, one JSON file per key.
+///
+/// Entries are written to a temporary file and renamed into place, so readers never see a
+/// partial entry. An entry that can't be parsed anyway is treated as a miss.
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+impl ResultCache {
+    /// A cache in `dir`, which is created on the first write
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", key.as_str(), ENTRY_EXTENSION))
+    }
+
+    /// The stored result for `key`, marked `cached`
+    pub fn get(&self, key: &CacheKey) -> Option<TranscriptionResult> {
+        let path = self.entry_path(key);
+        let contents = match std::fs::read(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Cannot read cache entry {}: {}", path.display(), e);
+                }
+                return None;
+            }
+        };
+        match serde_json::from_slice::<TranscriptionResult>(&contents) {
+            Ok(mut result) => {
+                result.cached = true;
+                Some(result)
+            }
+            Err(e) => {
+                warn!("Ignoring corrupted cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: &CacheKey, result: &TranscriptionResult) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(key);
+        // Unique per writer, so concurrent stores of the same key can't interleave
+        let tmp_path = self
+            .dir
+            .join(format!(".{}.{}.tmp", key.as_str(), uuid::Uuid::new_v4()));
+        let stored = TranscriptionResult {
+            cached: false,
+            stats: None,
+            redaction: None,
+            chapters: None,
+            speakers: None,
+            alignment: None,
+            ..result.clone()
+        };
+        std::fs::write(&tmp_path, serde_json::to_vec(&stored)?)?;
+        if let Err(e) = std::fs::rename(&tmp_path, &path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Delete every entry, returning how many were removed. A missing directory is empty.
+    pub fn clear(&self) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            let is_entry = path
+                .extension()
+                .is_some_and(|ext| ext == ENTRY_EXTENSION || ext == "tmp");
+            if path.is_file() && is_entry {
+                std::fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// A backend that answers repeated file transcriptions from a `ResultCache`.
+///
+/// Only `transcribe_path` is cached; sample buffers go straight to the wrapped backend.
+/// Failing to read or write the cache is logged and never fails the transcription.
+pub struct CachedBackend {
+    inner: Box<dyn TranscriptionBackend>,
+    cache: ResultCache,
+}
+
+impl CachedBackend {
+    pub fn new(inner: Box<dyn TranscriptionBackend>, cache: ResultCache) -> Self {
+        Self { inner, cache }
+    }
+
+    pub fn cache(&self) -> &ResultCache {
+        &self.cache
+    }
+
+    fn key(&self, audio_path: &Path, options: &TranscriptionOptions) -> Option<CacheKey> {
+        match CacheKey::new(audio_path, self.inner.config(), options) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                debug!("Not caching {}: {}", audio_path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn store(&self, key: Option<&CacheKey>, audio_path: &Path, result: &TranscriptionResult) {
+        if let Some(key) = key {
+            if let Err(e) = self.cache.put(key, result) {
+                warn!("Could not cache result for {}: {}", audio_path.display(), e);
+            }
+        }
+    }
+}
+
+impl TranscriptionBackend for CachedBackend {
+    fn config(&self) -> &ModelConfig {
+        self.inner.config()
+    }
+
+    fn options(&self) -> &TranscriptionOptions {
+        self.inner.options()
+    }
+
+    fn load(&self) -> Result<()> {
+        self.inner.load()
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.inner.is_loaded()
+    }
+
+    fn transcribe_path(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let key = self.key(audio_path, options);
+        if let Some(result) = key.as_ref().and_then(|key| self.cache.get(key)) {
+            info!("Cache hit for {}", audio_path.display());
+            return Ok(result);
+        }
+
+        let result = self.inner.transcribe_path(audio_path, options)?;
+        self.store(key.as_ref(), audio_path, &result);
+        Ok(result)
+    }
+
+    fn transcribe_path_streaming(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+        on_segment: &mut dyn FnMut(&TranscriptionSegment, f64),
+    ) -> Result<TranscriptionResult> {
+        let key = self.key(audio_path, options);
+        if let Some(result) = key.as_ref().and_then(|key| self.cache.get(key)) {
+            info!("Cache hit for {}", audio_path.display());
+            for segment in &result.segments {
+                on_segment(segment, result.duration);
+            }
+            return Ok(result);
+        }
+
+        let result = self
+            .inner
+            .transcribe_path_streaming(audio_path, options, on_segment)?;
+        self.store(key.as_ref(), audio_path, &result);
+        Ok(result)
+    }
+
+    fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.inner.transcribe_samples(samples, options)
+    }
+
+    fn device_info(&self) -> Result<String> {
+        self.inner.device_info()
+    }
+
+    fn unload(&self) -> bool {
+        self.inner.unload()
+    }
+
+    fn warmup(&self) -> Result<WarmupReport> {
+        self.inner.warmup()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{segment, MockBackend, ResultBuilder};
+    use std::sync::atomic::Ordering;
+    use tempfile::tempdir;
+
+    fn sample_result() -> TranscriptionResult {
+        ResultBuilder::new()
+            .segment(segment(0.0, 2.0, "Hello"))
+            .transcription_time(0.5)
+            .build()
+    }
+
+    #[test]
+    fn test_key_covers_contents_and_settings() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.wav");
+        let b = dir.path().join("b.wav");
+        std::fs::write(&a, b"RIFF one").unwrap();
+        std::fs::write(&b, b"RIFF one").unwrap();
+        let config = ModelConfig::new("base", "cpu", "float32");
+        let options = TranscriptionOptions::default();
+
+        let key = CacheKey::new(&a, &config, &options).unwrap();
+        // Same contents under another name is the same entry
+        assert_eq!(key, CacheKey::new(&b, &config, &options).unwrap());
+
+        let other_model = ModelConfig::new("small", "cpu", "float32");
+        assert_ne!(key, CacheKey::new(&a, &other_model, &options).unwrap());
+        let forced = TranscriptionOptions {
+            language: Some("en".to_string()),
+            ..options.clone()
+        };
+        assert_ne!(key, CacheKey::new(&a, &config, &forced).unwrap());
+
+        std::fs::write(&b, b"RIFF two").unwrap();
+        assert_ne!(key, CacheKey::new(&b, &config, &options).unwrap());
+    }
+
+    #[test]
+    fn test_put_get_and_clear() {
+        let dir = tempdir().unwrap();
+        let audio = dir.path().join("clip.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+        let cache = ResultCache::new(dir.path().join("cache"));
+        let key = CacheKey::new(
+            &audio,
+            &ModelConfig::default(),
+            &TranscriptionOptions::default(),
+        )
+        .unwrap();
+
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, &sample_result()).unwrap();
+        let hit = cache.get(&key).unwrap();
+        assert!(hit.cached);
+        assert_eq!(hit.full_text, "Hello");
+        // Only the entry itself is left behind
+        assert_eq!(std::fs::read_dir(cache.dir()).unwrap().count(), 1);
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.get(&key).is_none());
+        assert_eq!(
+            ResultCache::new(dir.path().join("missing"))
+                .clear()
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_corrupted_entry_is_a_miss() {
+        let dir = tempdir().unwrap();
+        let audio = dir.path().join("clip.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+        let cache = ResultCache::new(dir.path());
+        let key = CacheKey::new(
+            &audio,
+            &ModelConfig::default(),
+            &TranscriptionOptions::default(),
+        )
+        .unwrap();
+
+        cache.put(&key, &sample_result()).unwrap();
+        std::fs::write(cache.entry_path(&key), b"{\"language\": \"en\"").unwrap();
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_cached_backend_skips_inference_on_hit() {
+        let dir = tempdir().unwrap();
+        let audio = dir.path().join("clip.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+        let mock = MockBackend::new()
+            .with_config(ModelConfig::new("base", "cpu", "float32"))
+            .with_result(sample_result());
+        let calls = mock.calls.clone();
+        let backend =
+            CachedBackend::new(Box::new(mock), ResultCache::new(dir.path().join("cache")));
+        let options = backend.options().clone();
+
+        let first = backend.transcribe_path(&audio, &options).unwrap();
+        assert!(!first.cached);
+        let second = backend.transcribe_path(&audio, &options).unwrap();
+        assert!(second.cached);
+        assert_eq!(second.full_text, first.full_text);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Streaming callers still see every segment of a hit
+        let mut streamed = Vec::new();
+        let third = backend
+            .transcribe_path_streaming(&audio, &options, &mut |segment, duration| {
+                streamed.push((segment.text.clone(), duration))
+            })
+            .unwrap();
+        assert!(third.cached);
+        assert_eq!(streamed, vec![("Hello".to_string(), 2.0)]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A file that can't be hashed goes to the backend, which reports its own error
+        backend
+            .transcribe_path(&dir.path().join("missing.wav"), &options)
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
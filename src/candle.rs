@@ -0,0 +1,719 @@
+use crate::audio::read_wav_16k;
+use crate::backend::TranscriptionBackend;
+use crate::error::{Result, TranscriptionError};
+use crate::language::{self, LanguageOverride, LANGUAGES};
+use crate::timing::Phase;
+use crate::transcriber::validate_audio_path;
+use crate::types::{ModelConfig, TranscriptionOptions, TranscriptionResult, TranscriptionSegment};
+use candle_core::{Device, IndexOp, Tensor, D};
+use candle_nn::{ops::softmax, VarBuilder};
+use candle_transformers::models::whisper::{self as m, audio, model::Whisper, Config};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+use tokenizers::Tokenizer;
+use tracing::{debug, info};
+
+/// Seconds per timestamp token
+const TIME_PRECISION: f64 = 0.02;
+
+/// Hugging Face repository holding the safetensors weights for a model size
+pub fn hf_repo(model_size: &str) -> Option<&'static str> {
+    match model_size {
+        "tiny" => Some("openai/whisper-tiny"),
+        "base" => Some("openai/whisper-base"),
+        "small" => Some("openai/whisper-small"),
+        "medium" => Some("openai/whisper-medium"),
+        "large-v2" => Some("openai/whisper-large-v2"),
+        "large-v3" => Some("openai/whisper-large-v3"),
+        _ => None,
+    }
+}
+
+fn candle_error(e: candle_core::Error) -> TranscriptionError {
+    TranscriptionError::TranscriptionFailed(format!("Candle inference failed: {}", e))
+}
+
+struct LoadedModel {
+    model: Whisper,
+    config: Config,
+    tokenizer: Tokenizer,
+    mel_filters: Vec<f32>,
+    device: Device,
+}
+
+/// Pure-Rust Whisper inference with candle, so no Python runtime is needed.
+///
+/// Weights are fetched as safetensors from the Hugging Face hub into the configured model
+/// directory. Decoding is greedy; word timestamps and the VAD filter aren't supported. With the
+/// `candle-metal` feature, `mps` and `auto` run on the GPU.
+pub struct CandleTranscriber {
+    config: ModelConfig,
+    options: TranscriptionOptions,
+    model: Mutex<Option<LoadedModel>>,
+}
+
+impl CandleTranscriber {
+    pub fn new(config: ModelConfig) -> Result<Self> {
+        config
+            .validate()
+            .map_err(TranscriptionError::ModelInitError)?;
+        if hf_repo(&config.model_size).is_none() {
+            return Err(TranscriptionError::ModelInitError(format!(
+                "Model {} isn't available for the candle backend",
+                config.model_size
+            )));
+        }
+
+        Ok(Self {
+            config,
+            options: TranscriptionOptions::default(),
+            model: Mutex::new(None),
+        })
+    }
+
+    /// Replace the default decoding options used by `transcribe`
+    pub fn with_options(mut self, options: TranscriptionOptions) -> Result<Self> {
+        options
+            .validate()
+            .map_err(TranscriptionError::ModelInitError)?;
+        self.options = options;
+        Ok(self)
+    }
+
+    pub fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    pub fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    pub fn transcribe<P: AsRef<Path>>(&self, audio_path: P) -> Result<TranscriptionResult> {
+        self.transcribe_with_options(audio_path, &self.options)
+    }
+
+    /// Transcribe using per-call options instead of the transcriber defaults.
+    ///
+    /// Only 16-bit integer and 32-bit float WAV input is read by this backend.
+    pub fn transcribe_with_options<P: AsRef<Path>>(
+        &self,
+        audio_path: P,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let audio_path = audio_path.as_ref();
+        let decode_start = Instant::now();
+        validate_audio_path(audio_path)?;
+        let samples = read_wav_16k(audio_path)?;
+        let decode_time = decode_start.elapsed();
+        info!(
+            "Starting candle transcription for: {}",
+            audio_path.display()
+        );
+        let Some(start) = options.clip_start.filter(|&start| start > 0.0) else {
+            let mut result = self.transcribe_pcm(&samples, options)?;
+            result.add_timing(Phase::Decode, decode_time);
+            return Ok(result);
+        };
+        let skipped = ((start * m::SAMPLE_RATE as f64) as usize).min(samples.len());
+        let mut result = self.transcribe_pcm(&samples[skipped..], options)?;
+        result.shift(skipped as f64 / m::SAMPLE_RATE as f64);
+        result.add_timing(Phase::Decode, decode_time);
+        Ok(result)
+    }
+
+    fn transcribe_pcm(
+        &self,
+        samples: &[f32],
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let wait_start = Instant::now();
+        let mut cached = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        if cached.is_none() {
+            *cached = Some(self.load_model()?);
+        }
+        let loaded = cached.as_mut().expect("model loaded above");
+        let model_wait = wait_start.elapsed();
+
+        let start_time = Instant::now();
+        let mut result = decode(loaded, samples, options).map_err(candle_error)?;
+        result.calculate_real_time_factor(start_time.elapsed().as_secs_f64());
+        result.add_timing(Phase::ModelWait, model_wait);
+        result.add_timing(Phase::Inference, start_time.elapsed());
+        info!(
+            "Transcription completed in {:.2}s ({:.2}x real-time)",
+            result.transcription_time, result.real_time_factor
+        );
+        Ok(result)
+    }
+
+    /// Fetch (or find in the cache) the weights, config and tokenizer, and build the model
+    fn load_model(&self) -> Result<LoadedModel> {
+        let repo_id = hf_repo(&self.config.model_size).expect("checked in new");
+        let init_error = |what: &str, e: &dyn std::fmt::Display| {
+            TranscriptionError::ModelInitError(format!("Failed to {} for {}: {}", what, repo_id, e))
+        };
+
+        let cache = match &self.config.model_dir {
+            Some(dir) => hf_hub::Cache::new(dir.clone()),
+            None => hf_hub::Cache::from_env(),
+        };
+        let fetch = |file: &str| -> Result<PathBuf> {
+            if self.config.offline {
+                cache.model(repo_id.to_string()).get(file).ok_or_else(|| {
+                    TranscriptionError::ModelInitError(format!(
+                        "{} of {} isn't in {} and offline mode is on",
+                        file,
+                        repo_id,
+                        cache.path().display()
+                    ))
+                })
+            } else {
+                hf_hub::api::sync::ApiBuilder::from_cache(cache.clone())
+                    .build()
+                    .and_then(|api| api.model(repo_id.to_string()).get(file))
+                    .map_err(|e| init_error(&format!("download {}", file), &e))
+            }
+        };
+        let config_path = fetch("config.json")?;
+        let tokenizer_path = fetch("tokenizer.json")?;
+        let weights_path = fetch("model.safetensors")?;
+
+        let device = select_device(&self.config.device)?;
+        info!(
+            "Loading candle model {} on {:?}",
+            self.config.model_size, device
+        );
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+        let tokenizer =
+            Tokenizer::from_file(tokenizer_path).map_err(|e| init_error("load tokenizer", &e))?;
+        // SAFETY: the weights file is only read, and not modified while mapped
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], m::DTYPE, &device) }
+            .map_err(|e| init_error("map weights", &e))?;
+        let model =
+            Whisper::load(&vb, config.clone()).map_err(|e| init_error("build model", &e))?;
+
+        Ok(LoadedModel {
+            mel_filters: mel_filters(config.num_mel_bins),
+            model,
+            config,
+            tokenizer,
+            device,
+        })
+    }
+}
+
+impl TranscriptionBackend for CandleTranscriber {
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    fn load(&self) -> Result<()> {
+        let mut cached = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        if cached.is_none() {
+            *cached = Some(self.load_model()?);
+        }
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.model
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some()
+    }
+
+    fn transcribe_path(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_with_options(audio_path, options)
+            .map_err(|e| e.with_path(audio_path))
+    }
+
+    fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_pcm(samples, options)
+    }
+
+    fn device_info(&self) -> Result<String> {
+        let device = select_device(&self.config.device)?;
+        Ok(format!("{:?} (candle)", device))
+    }
+
+    fn unload(&self) -> bool {
+        self.model
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .is_some()
+    }
+}
+
+#[cfg(feature = "candle-metal")]
+fn select_device(device: &str) -> Result<Device> {
+    match device {
+        "cpu" => Ok(Device::Cpu),
+        _ => Device::new_metal(0).map_err(|e| {
+            TranscriptionError::ModelInitError(format!("Metal device unavailable: {}", e))
+        }),
+    }
+}
+
+#[cfg(not(feature = "candle-metal"))]
+fn select_device(device: &str) -> Result<Device> {
+    match device {
+        "cpu" | "auto" => Ok(Device::Cpu),
+        other => Err(TranscriptionError::ModelInitError(format!(
+            "Device {} needs the candle-metal feature; use --device cpu",
+            other
+        ))),
+    }
+}
+
+/// Slaney-style mel filterbank as used by Whisper (librosa's default), `n_mels` rows of
+/// `N_FFT / 2 + 1` weights
+pub fn mel_filters(n_mels: usize) -> Vec<f32> {
+    let n_freqs = m::N_FFT / 2 + 1;
+    let sample_rate = m::SAMPLE_RATE as f64;
+
+    // Slaney mel scale: linear below 1 kHz, logarithmic above
+    let f_sp = 200.0 / 3.0;
+    let min_log_hz = 1000.0;
+    let min_log_mel = min_log_hz / f_sp;
+    let log_step = 6.4f64.ln() / 27.0;
+    let hz_to_mel = |hz: f64| {
+        if hz < min_log_hz {
+            hz / f_sp
+        } else {
+            min_log_mel + (hz / min_log_hz).ln() / log_step
+        }
+    };
+    let mel_to_hz = |mel: f64| {
+        if mel < min_log_mel {
+            mel * f_sp
+        } else {
+            min_log_hz * ((mel - min_log_mel) * log_step).exp()
+        }
+    };
+
+    let max_mel = hz_to_mel(sample_rate / 2.0);
+    let edges: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(max_mel * i as f64 / (n_mels + 1) as f64))
+        .collect();
+    let fft_freqs: Vec<f64> = (0..n_freqs)
+        .map(|i| i as f64 * sample_rate / m::N_FFT as f64)
+        .collect();
+
+    let mut filters = vec![0f32; n_mels * n_freqs];
+    for mel in 0..n_mels {
+        let (lower, center, upper) = (edges[mel], edges[mel + 1], edges[mel + 2]);
+        // Area normalization
+        let norm = 2.0 / (upper - lower);
+        for (bin, &freq) in fft_freqs.iter().enumerate() {
+            let rising = (freq - lower) / (center - lower);
+            let falling = (upper - freq) / (upper - center);
+            let weight = rising.min(falling).max(0.0);
+            filters[mel * n_freqs + bin] = (weight * norm) as f32;
+        }
+    }
+    filters
+}
+
+/// Special token ids the decoder needs
+struct Tokens {
+    sot: u32,
+    transcribe: u32,
+    eot: u32,
+    no_timestamps: u32,
+    no_speech: Option<u32>,
+    /// First timestamp token, `<|0.00|>`
+    timestamp_begin: u32,
+}
+
+impl Tokens {
+    fn new(tokenizer: &Tokenizer) -> candle_core::Result<Self> {
+        let id = |token: &str| {
+            tokenizer
+                .token_to_id(token)
+                .ok_or_else(|| candle_core::Error::Msg(format!("tokenizer lacks {}", token)))
+        };
+        let no_timestamps = id(m::NO_TIMESTAMPS_TOKEN)?;
+        Ok(Self {
+            sot: id(m::SOT_TOKEN)?,
+            transcribe: id(m::TRANSCRIBE_TOKEN)?,
+            eot: id(m::EOT_TOKEN)?,
+            no_timestamps,
+            no_speech: m::NO_SPEECH_TOKENS
+                .iter()
+                .find_map(|token| tokenizer.token_to_id(token)),
+            timestamp_begin: no_timestamps + 1,
+        })
+    }
+}
+
+/// Transcribe 30-second windows one after another with greedy decoding
+fn decode(
+    loaded: &mut LoadedModel,
+    samples: &[f32],
+    options: &TranscriptionOptions,
+) -> candle_core::Result<TranscriptionResult> {
+    let duration = samples.len() as f64 / m::SAMPLE_RATE as f64;
+    let tokens = Tokens::new(&loaded.tokenizer)?;
+    let num_mel_bins = loaded.config.num_mel_bins;
+    let mel = audio::pcm_to_mel(&loaded.config, samples, &loaded.mel_filters);
+    let mel_len = mel.len() / num_mel_bins;
+    let mel = Tensor::from_vec(mel, (1, num_mel_bins, mel_len), &loaded.device)?;
+
+    let suppress = suppress_mask(&loaded.config, &tokens, &loaded.device)?;
+    let mut language: Option<String> = options.language.clone();
+    let mut language_probability = if language.is_some() { 1.0 } else { 0.0 };
+    let mut language_override = None;
+
+    let mut segments = Vec::new();
+    let mut seek = 0;
+    while seek < mel_len {
+        let window = usize::min(mel_len - seek, m::N_FRAMES);
+        let offset = (seek * m::HOP_LENGTH) as f64 / m::SAMPLE_RATE as f64;
+        let window_duration = (window * m::HOP_LENGTH) as f64 / m::SAMPLE_RATE as f64;
+        let mel_window = mel.narrow(2, seek, window)?;
+        let features = loaded.model.encoder.forward(&mel_window, true)?;
+
+        if language.is_none() {
+            let probabilities = detect_language(loaded, &features, &tokens)?;
+            let (code, probability) = probabilities
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .cloned()
+                .unwrap_or_else(|| ("en".to_string(), 0.0));
+            match options
+                .allowed_languages
+                .as_ref()
+                .filter(|allowed| !allowed.contains(&code))
+            {
+                Some(allowed) => {
+                    let (forced, forced_probability) = language::rerank(&probabilities, allowed)
+                        .unwrap_or_else(|| (allowed[0].clone(), 0.0));
+                    debug!("Detected language {} isn't allowed; using {}", code, forced);
+                    language = Some(forced);
+                    language_probability = forced_probability;
+                    language_override = Some(LanguageOverride {
+                        detected: code,
+                        detected_probability: probability,
+                    });
+                }
+                None => {
+                    language = Some(code);
+                    language_probability = probability;
+                }
+            }
+        }
+        let language_token = language
+            .as_deref()
+            .and_then(|code| loaded.tokenizer.token_to_id(&format!("<|{}|>", code)));
+
+        let mut prompt = vec![tokens.sot];
+        prompt.extend(language_token);
+        prompt.push(tokens.transcribe);
+        let window_result = decode_window(loaded, &features, &prompt, &tokens, &suppress)?;
+        debug!(
+            "Window at {:.1}s: no_speech={:.2} avg_logprob={:.2}",
+            offset, window_result.no_speech_prob, window_result.avg_logprob
+        );
+        seek += window;
+
+        if window_result.no_speech_prob > m::NO_SPEECH_THRESHOLD
+            && window_result.avg_logprob < m::LOGPROB_THRESHOLD
+        {
+            continue;
+        }
+        for (start, end, text_tokens) in
+            split_segments(&window_result.tokens, &tokens, window_duration)
+        {
+            let text = loaded
+                .tokenizer
+                .decode(&text_tokens, true)
+                .map_err(|e| candle_core::Error::Msg(e.to_string()))?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            segments.push(TranscriptionSegment {
+                start: offset + start,
+                end: offset + end,
+                text: text.trim().to_string(),
+                no_speech_prob: window_result.no_speech_prob,
+                avg_logprob: window_result.avg_logprob,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            });
+        }
+    }
+
+    let full_text = TranscriptionResult::text_from_segments(&segments, options.paragraph_gap);
+    Ok(TranscriptionResult {
+        language: language.unwrap_or_else(|| "en".to_string()),
+        language_probability,
+        duration,
+        segments,
+        full_text,
+        transcription_time: 0.0,
+        real_time_factor: 0.0,
+        language_override,
+        ..Default::default()
+    })
+}
+
+/// Additive logit mask: `-inf` for the model's suppressed tokens and `<|notimestamps|>`
+fn suppress_mask(config: &Config, tokens: &Tokens, device: &Device) -> candle_core::Result<Tensor> {
+    let mask: Vec<f32> = (0..config.vocab_size as u32)
+        .map(|id| {
+            if config.suppress_tokens.contains(&id) || id == tokens.no_timestamps {
+                f32::NEG_INFINITY
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    Tensor::new(mask.as_slice(), device)
+}
+
+/// Probability of each language for a window, from the decoder's first prediction after
+/// `<|sot|>`
+fn detect_language(
+    loaded: &mut LoadedModel,
+    features: &Tensor,
+    tokens: &Tokens,
+) -> candle_core::Result<Vec<(String, f64)>> {
+    let candidates: Vec<(&'static str, u32)> = LANGUAGES
+        .iter()
+        .filter_map(|code| {
+            loaded
+                .tokenizer
+                .token_to_id(&format!("<|{}|>", code))
+                .map(|id| (*code, id))
+        })
+        .collect();
+    if candidates.is_empty() {
+        // English-only vocabulary
+        return Ok(vec![("en".to_string(), 1.0)]);
+    }
+
+    let sot = Tensor::new(&[[tokens.sot]], &loaded.device)?;
+    let ys = loaded.model.decoder.forward(&sot, features, true)?;
+    let logits = loaded
+        .model
+        .decoder
+        .final_linear(&ys.i((..1, ..1))?)?
+        .i(0)?
+        .i(0)?;
+    let ids: Vec<u32> = candidates.iter().map(|(_, id)| *id).collect();
+    let ids = Tensor::new(ids.as_slice(), &loaded.device)?;
+    let probs = softmax(&logits.index_select(&ids, 0)?, D::Minus1)?.to_vec1::<f32>()?;
+    Ok(candidates
+        .iter()
+        .zip(probs)
+        .map(|((code, _), p)| (code.to_string(), p as f64))
+        .collect())
+}
+
+struct WindowResult {
+    /// Generated tokens, without the prompt
+    tokens: Vec<u32>,
+    avg_logprob: f64,
+    no_speech_prob: f64,
+}
+
+fn decode_window(
+    loaded: &mut LoadedModel,
+    features: &Tensor,
+    prompt: &[u32],
+    tokens: &Tokens,
+    suppress: &Tensor,
+) -> candle_core::Result<WindowResult> {
+    let max_len = loaded.config.max_target_positions / 2;
+    let mut sequence = prompt.to_vec();
+    let mut sum_logprob = 0.0;
+    let mut no_speech_prob = 0.0;
+
+    for i in 0..max_len {
+        let input = Tensor::new(sequence.as_slice(), &loaded.device)?.unsqueeze(0)?;
+        let ys = loaded.model.decoder.forward(&input, features, i == 0)?;
+        if i == 0 {
+            if let Some(no_speech) = tokens.no_speech {
+                let first = loaded.model.decoder.final_linear(&ys.i(..1)?)?.i(0)?.i(0)?;
+                no_speech_prob = softmax(&first, 0)?
+                    .i(no_speech as usize)?
+                    .to_scalar::<f32>()? as f64;
+            }
+        }
+
+        let (_, seq_len, _) = ys.dims3()?;
+        let logits = loaded
+            .model
+            .decoder
+            .final_linear(&ys.i((..1, seq_len - 1..))?)?
+            .i(0)?
+            .i(0)?;
+        let logits = logits.broadcast_add(suppress)?;
+        let next = logits.argmax(D::Minus1)?.to_scalar::<u32>()?;
+        let probability = softmax(&logits, D::Minus1)?
+            .i(next as usize)?
+            .to_scalar::<f32>()?;
+        sequence.push(next);
+        sum_logprob += (probability as f64).max(f64::MIN_POSITIVE).ln();
+        if next == tokens.eot {
+            break;
+        }
+    }
+
+    let generated = sequence[prompt.len()..].to_vec();
+    Ok(WindowResult {
+        avg_logprob: sum_logprob / generated.len().max(1) as f64,
+        tokens: generated,
+        no_speech_prob,
+    })
+}
+
+/// Split generated tokens into `(start, end, text tokens)` at timestamp tokens.
+///
+/// Text with no closing timestamp runs to `window_duration`; a window without any timestamps
+/// becomes one segment covering the whole window.
+fn split_segments(
+    generated: &[u32],
+    tokens: &Tokens,
+    window_duration: f64,
+) -> Vec<(f64, f64, Vec<u32>)> {
+    let timestamp = |token: u32| (token - tokens.timestamp_begin) as f64 * TIME_PRECISION;
+
+    let mut segments = Vec::new();
+    let mut start = 0.0;
+    let mut text = Vec::new();
+    for &token in generated {
+        if token == tokens.eot {
+            break;
+        }
+        if token >= tokens.timestamp_begin {
+            let time = timestamp(token).min(window_duration);
+            if !text.is_empty() {
+                segments.push((start, time, std::mem::take(&mut text)));
+            }
+            start = time;
+        } else if token < tokens.eot {
+            text.push(token);
+        }
+    }
+    if !text.is_empty() {
+        segments.push((start, window_duration.max(start), text));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens() -> Tokens {
+        Tokens {
+            sot: 50258,
+            transcribe: 50359,
+            eot: 50257,
+            no_timestamps: 50363,
+            no_speech: Some(50362),
+            timestamp_begin: 50364,
+        }
+    }
+
+    #[test]
+    fn test_split_segments() {
+        let t = tokens();
+        // <|0.00|> a b <|1.50|> <|1.50|> c <|3.00|> d <eot>
+        let generated = vec![
+            t.timestamp_begin,
+            10,
+            11,
+            t.timestamp_begin + 75,
+            t.timestamp_begin + 75,
+            12,
+            t.timestamp_begin + 150,
+            13,
+            t.eot,
+        ];
+        let segments = split_segments(&generated, &t, 10.0);
+        assert_eq!(
+            segments,
+            vec![
+                (0.0, 1.5, vec![10, 11]),
+                (1.5, 3.0, vec![12]),
+                (3.0, 10.0, vec![13]),
+            ]
+        );
+
+        let untimed = split_segments(&[10, 11, t.eot], &t, 7.5);
+        assert_eq!(untimed, vec![(0.0, 7.5, vec![10, 11])]);
+    }
+
+    #[test]
+    fn test_mel_filters() {
+        let n_freqs = m::N_FFT / 2 + 1;
+        for n_mels in [80, 128] {
+            let filters = mel_filters(n_mels);
+            assert_eq!(filters.len(), n_mels * n_freqs);
+            assert!(filters.iter().all(|w| *w >= 0.0));
+            // Every filter covers at least one FFT bin
+            assert!(filters
+                .chunks(n_freqs)
+                .all(|row| row.iter().any(|w| *w > 0.0)));
+        }
+        // Slaney normalization gives each triangle unit area in Hz; wide high-frequency
+        // filters are sampled finely enough for the sum to show it
+        let filters = mel_filters(80);
+        let bin_hz = m::SAMPLE_RATE as f32 / m::N_FFT as f32;
+        let area: f32 = filters[79 * n_freqs..].iter().sum::<f32>() * bin_hz;
+        assert!((area - 1.0).abs() < 0.05, "area {}", area);
+    }
+
+    #[test]
+    fn test_read_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 32_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..3200 {
+            writer.write_sample(i16::MAX).unwrap();
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let samples = read_wav_16k(&path).unwrap();
+        assert_eq!(samples.len(), 1600);
+        assert!((samples[0] - 0.5).abs() < 1e-3);
+
+        let mp3 = dir.path().join("clip.mp3");
+        std::fs::write(&mp3, b"ID3").unwrap();
+        assert!(matches!(
+            read_wav_16k(&mp3),
+            Err(TranscriptionError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_model_repos() {
+        assert_eq!(hf_repo("base"), Some("openai/whisper-base"));
+        assert_eq!(hf_repo("large-v3"), Some("openai/whisper-large-v3"));
+        assert!(CandleTranscriber::new(ModelConfig::new("tiny", "cpu", "float32")).is_ok());
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-channel transcription of recordings that keep each speaker on a channel of their own,
+//! such as stereo call recordings.
+
+use crate::error::{Result, TranscriptionError};
+use crate::speakers::{SpeakerTurns, METHOD_CHANNELS};
+use crate::types::TranscriptionResult;
+#[cfg(feature = "preprocess")]
+use crate::{backend::TranscriptionBackend, types::TranscriptionOptions};
+use std::path::Path;
+
+/// Speaker labels for `channels` channels: `names` when given, otherwise "left" and "right"
+/// for stereo and C1, C2, ... for anything wider
+pub fn channel_names(names: &[String], channels: usize) -> Result<Vec<String>> {
+    if !names.is_empty() {
+        if names.len() != channels {
+            return Err(TranscriptionError::ConfigError(format!(
+                "{} channel name(s) given for {} channels",
+                names.len(),
+                channels
+            )));
+        }
+        return Ok(names.to_vec());
+    }
+    Ok(match channels {
+        2 => vec!["left".to_string(), "right".to_string()],
+        _ => (1..=channels)
+            .map(|channel| format!("C{}", channel))
+            .collect(),
+    })
+}
+
+/// Check that `channel`, counting from 1, is one of the `available` channels of `path`
+pub fn check_channel(path: &Path, channel: usize, available: usize) -> Result<()> {
+    if channel == 0 || channel > available {
+        return Err(TranscriptionError::ConfigError(format!(
+            "{} has {} channel(s); there is no channel {} (channels count from 1)",
+            path.display(),
+            available,
+            channel
+        )));
+    }
+    Ok(())
+}
+
+/// Combine the results of each channel into one, labelling every segment and word with its
+/// channel's name and ordering segments by start time, so overlapping speech interleaves.
+///
+/// Ties keep channel order. Language, duration and timing are combined as in
+/// [`TranscriptionResult::merge`], with every part starting at zero.
+pub fn merge_channels(
+    parts: Vec<(String, TranscriptionResult)>,
+    paragraph_gap: Option<f64>,
+) -> TranscriptionResult {
+    let channels = parts.len();
+    let labelled: Vec<(f64, TranscriptionResult)> = parts
+        .into_iter()
+        .map(|(name, mut part)| {
+            for segment in &mut part.segments {
+                segment.speaker = Some(name.clone());
+                for word in &mut segment.words {
+                    word.speaker = Some(name.clone());
+                }
+            }
+            (0.0, part)
+        })
+        .collect();
+    let mut merged = TranscriptionResult::merge(&labelled);
+    merged.segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    merged.full_text = TranscriptionResult::text_from_segments(&merged.segments, paragraph_gap);
+    let turns = merged
+        .segments
+        .windows(2)
+        .filter(|pair| pair[0].speaker != pair[1].speaker)
+        .count();
+    merged.speakers = Some(SpeakerTurns {
+        method: METHOD_CHANNELS.to_string(),
+        max_speakers: channels,
+        turns,
+        diarization_seconds: None,
+    });
+    merged
+}
+
+/// Transcribe each channel of the WAV file at `path` separately with `backend` and merge the
+/// results, labelled with `names` (see [`channel_names`]). Mono input is an error.
+#[cfg(feature = "preprocess")]
+pub fn transcribe_channels(
+    backend: &dyn TranscriptionBackend,
+    path: &Path,
+    names: &[String],
+    options: &TranscriptionOptions,
+) -> Result<TranscriptionResult> {
+    let channels = crate::audio::read_wav_channels_16k(path)?;
+    if channels.len() < 2 {
+        return Err(TranscriptionError::UnsupportedFormat(format!(
+            "{} is mono; splitting channels needs two or more",
+            path.display()
+        )));
+    }
+    let names = channel_names(names, channels.len())?;
+    let mut parts = Vec::with_capacity(channels.len());
+    for (name, samples) in names.into_iter().zip(&channels) {
+        parts.push((name, backend.transcribe_samples(samples, options)?));
+    }
+    Ok(merge_channels(parts, options.paragraph_gap))
+}
+
+/// Transcribe only `channel` (counting from 1) of the WAV file at `path`, leaving the other
+/// channels out instead of downmixing them in
+#[cfg(feature = "preprocess")]
+pub fn transcribe_channel(
+    backend: &dyn TranscriptionBackend,
+    path: &Path,
+    channel: usize,
+    options: &TranscriptionOptions,
+) -> Result<TranscriptionResult> {
+    // The header gives the channel count before anything is decoded
+    if let Some(probe) = crate::probe::probe_audio(path) {
+        check_channel(path, channel, probe.channels as usize)?;
+    }
+    let channels = crate::audio::read_wav_channels_16k(path)?;
+    check_channel(path, channel, channels.len())?;
+    backend.transcribe_samples(&channels[channel - 1], options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{render, OutputFormat};
+    use crate::types::TranscriptionSegment;
+
+    fn part(spans: &[(f64, f64, &str)], duration: f64) -> TranscriptionResult {
+        let segments: Vec<TranscriptionSegment> = spans
+            .iter()
+            .map(|&(start, end, text)| TranscriptionSegment {
+                start,
+                end,
+                text: text.to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            })
+            .collect();
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration,
+            full_text: TranscriptionResult::text_from_segments(&segments, None),
+            segments,
+            transcription_time: 1.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_channel_names() {
+        assert_eq!(channel_names(&[], 2).unwrap(), vec!["left", "right"]);
+        assert_eq!(channel_names(&[], 3).unwrap(), vec!["C1", "C2", "C3"]);
+        let named = vec!["agent".to_string(), "customer".to_string()];
+        assert_eq!(channel_names(&named, 2).unwrap(), named);
+        assert!(matches!(
+            channel_names(&named, 3),
+            Err(TranscriptionError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_channel() {
+        let path = Path::new("call.wav");
+        assert!(check_channel(path, 1, 2).is_ok());
+        assert!(check_channel(path, 2, 2).is_ok());
+        for channel in [0, 3] {
+            let err = check_channel(path, channel, 2).unwrap_err();
+            assert!(
+                err.to_string().contains("call.wav has 2 channel(s)"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_channels_interleaves_overlapping_speech() {
+        let agent = part(
+            &[(0.0, 3.0, "Hello, how can I help?"), (5.0, 7.0, "Sure.")],
+            8.0,
+        );
+        let customer = part(&[(2.5, 5.5, "Hi, I need a refund.")], 7.5);
+        let merged = merge_channels(
+            vec![
+                ("agent".to_string(), agent),
+                ("customer".to_string(), customer),
+            ],
+            None,
+        );
+
+        let order: Vec<(&str, f64)> = merged
+            .segments
+            .iter()
+            .map(|segment| (segment.speaker.as_deref().unwrap(), segment.start))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("agent", 0.0), ("customer", 2.5), ("agent", 5.0)]
+        );
+        assert_eq!(
+            merged.full_text,
+            "Hello, how can I help? Hi, I need a refund. Sure."
+        );
+        assert_eq!(merged.duration, 8.0);
+        assert_eq!(merged.transcription_time, 2.0);
+        let speakers = merged.speakers.as_ref().unwrap();
+        assert_eq!(speakers.method, METHOD_CHANNELS);
+        assert_eq!((speakers.max_speakers, speakers.turns), (2, 2));
+
+        let srt = render(&merged, OutputFormat::Srt).unwrap();
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:03,000\n[agent] Hello"));
+        assert!(srt.contains("2\n00:00:02,500 --> 00:00:05,500\n[customer] Hi"));
+        assert!(srt.contains("3\n00:00:05,000 --> 00:00:07,000\n[agent] Sure."));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+
+/// Words of the opening segment used as a chapter's title
+pub const TITLE_WORDS: usize = 8;
+
+/// A stretch of the transcript between two long pauses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+fn title(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut title = words[..words.len().min(TITLE_WORDS)].join(" ");
+    // A clipped sentence reads better without its last comma; a complete one keeps its stop
+    if words.len() > TITLE_WORDS {
+        title = title
+            .trim_end_matches(|c: char| c.is_ascii_punctuation())
+            .to_string();
+        title.push('…');
+    }
+    title
+}
+
+/// Split `result` into chapters at pauses longer than `min_gap` seconds.
+///
+/// Chapters shorter than `min_chapter_len` are merged, shortest first, into the one before
+/// (the first chapter into the one after). The first chapter starts at zero, as chapter
+/// lists expect, and each ends where the next starts; the last ends with the audio. Segments
+/// with no text neither open chapters nor title them.
+pub fn detect_chapters(
+    result: &TranscriptionResult,
+    min_gap: f64,
+    min_chapter_len: f64,
+) -> Vec<Chapter> {
+    let mut segments: Vec<_> = result
+        .segments
+        .iter()
+        .filter(|segment| !segment.text.trim().is_empty())
+        .collect();
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let Some(last) = segments.last() else {
+        return Vec::new();
+    };
+    let end = result.duration.max(last.end);
+
+    // Index of the segment opening each chapter
+    let mut openings = vec![0];
+    let mut speech_end = segments[0].end;
+    for (i, segment) in segments.iter().enumerate().skip(1) {
+        if segment.start - speech_end > min_gap {
+            openings.push(i);
+        }
+        speech_end = speech_end.max(segment.end);
+    }
+
+    let start_of = |openings: &[usize], chapter: usize| {
+        if chapter == 0 {
+            0.0
+        } else {
+            segments[openings[chapter]].start
+        }
+    };
+    let end_of = |openings: &[usize], chapter: usize| match openings.get(chapter + 1) {
+        Some(_) => start_of(openings, chapter + 1),
+        None => end,
+    };
+    while openings.len() > 1 {
+        let shortest = (0..openings.len())
+            .map(|c| (c, end_of(&openings, c) - start_of(&openings, c)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match shortest {
+            Some((chapter, length)) if length < min_chapter_len => {
+                openings.remove(chapter.max(1));
+            }
+            _ => break,
+        }
+    }
+
+    (0..openings.len())
+        .map(|c| Chapter {
+            start: start_of(&openings, c),
+            end: end_of(&openings, c),
+            title: title(&segments[openings[c]].text),
+        })
+        .collect()
+}
+
+/// `1:02:03` past an hour, `02:03` before, as video chapter lists write times
+fn youtube_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    let (hours, minutes, secs) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+/// One `00:00 Title` line per chapter, as pasted into a YouTube description
+pub fn render_youtube(chapters: &[Chapter]) -> String {
+    chapters
+        .iter()
+        .map(|chapter| format!("{} {}\n", youtube_timestamp(chapter.start), chapter.title))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TranscriptionSegment;
+
+    fn result(segments: &[(f64, f64, &str)]) -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 4000.0,
+            segments: segments
+                .iter()
+                .map(|&(start, end, text)| TranscriptionSegment {
+                    start,
+                    end,
+                    text: text.to_string(),
+                    no_speech_prob: 0.0,
+                    avg_logprob: 0.0,
+                    words: Vec::new(),
+                    speaker: None,
+                    tokens: None,
+                })
+                .collect(),
+            full_text: String::new(),
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_chapters_at_long_pauses() {
+        let result = result(&[
+            (1.0, 100.0, "Welcome to the show."),
+            (100.5, 200.0, "Today we talk about falcons."),
+            // 5s pause
+            (
+                205.0,
+                300.0,
+                "Falcons are the fastest birds alive on the planet by some margin.",
+            ),
+            // 3s pause, but only 17s until the next one
+            (303.0, 313.0, "Quick break."),
+            (320.0, 3690.0, "The interview starts now."),
+            (3695.0, 3699.0, "   "),
+            (3700.0, 3800.0, "And we're back."),
+        ]);
+        let chapters = result.detect_chapters(2.0, 60.0);
+        let starts: Vec<f64> = chapters.iter().map(|c| c.start).collect();
+        // "Quick break" is merged into the chapter before; the blank segment is ignored
+        assert_eq!(starts, [0.0, 205.0, 320.0, 3700.0]);
+        assert_eq!(chapters[0].end, 205.0);
+        assert_eq!(chapters[1].end, 320.0);
+        assert_eq!(chapters[3].end, 4000.0);
+        assert_eq!(chapters[0].title, "Welcome to the show.");
+        assert_eq!(
+            chapters[1].title,
+            "Falcons are the fastest birds alive on the…"
+        );
+
+        assert_eq!(
+            render_youtube(&chapters),
+            "00:00 Welcome to the show.\n\
+             03:25 Falcons are the fastest birds alive on the…\n\
+             05:20 The interview starts now.\n\
+             1:01:40 And we're back.\n"
+        );
+    }
+
+    #[test]
+    fn test_short_opening_chapter_merges_forward() {
+        let chapters =
+            result(&[(0.0, 5.0, "Hi."), (10.0, 200.0, "Main topic.")]).detect_chapters(2.0, 60.0);
+        assert_eq!(chapters.len(), 1);
+        assert_eq!(chapters[0].title, "Hi.");
+        assert_eq!((chapters[0].start, chapters[0].end), (0.0, 4000.0));
+
+        assert!(result(&[]).detect_chapters(2.0, 60.0).is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::output::{format_timestamp, TimestampStyle};
+use crate::types::{TranscriptionSegment, WordTiming};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub(crate) const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+pub(crate) const RED: &str = "\x1b[31m";
+pub(crate) const RESET: &str = "\x1b[0m";
+
+/// Whether console output should use ANSI colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color only when stdout is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            other => Err(format!(
+                "Invalid color choice: {} (expected auto, always or never)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    High,
+    Borderline,
+    Low,
+}
+
+impl Confidence {
+    fn color(&self) -> &'static str {
+        match self {
+            Confidence::High => GREEN,
+            Confidence::Borderline => YELLOW,
+            Confidence::Low => RED,
+        }
+    }
+}
+
+/// Probability cut-offs for coloring. Segment probability is `exp(avg_logprob)`; words use
+/// their own probability.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceThresholds {
+    /// At or above this probability text is confident
+    pub high: f64,
+    /// Below this probability text is likely wrong
+    pub low: f64,
+    /// Segments at or above this no-speech probability are likely hallucinated
+    pub no_speech: f64,
+}
+
+impl Default for ConfidenceThresholds {
+    fn default() -> Self {
+        Self {
+            high: 0.8,
+            low: 0.5,
+            no_speech: 0.6,
+        }
+    }
+}
+
+impl ConfidenceThresholds {
+    /// Parse `HIGH,LOW` probabilities, keeping the default no-speech cut-off
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (high, low) = s
+            .split_once(',')
+            .ok_or_else(|| format!("Expected HIGH,LOW probabilities, got '{}'", s))?;
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|p| (0.0..=1.0).contains(p))
+                .ok_or_else(|| format!("Invalid probability: '{}'", value.trim()))
+        };
+        let thresholds = Self {
+            high: parse(high)?,
+            low: parse(low)?,
+            ..Default::default()
+        };
+        if thresholds.low > thresholds.high {
+            return Err(format!(
+                "Low threshold {} is above high threshold {}",
+                thresholds.low, thresholds.high
+            ));
+        }
+        Ok(thresholds)
+    }
+
+    pub fn classify(&self, probability: f64) -> Confidence {
+        if probability >= self.high {
+            Confidence::High
+        } else if probability >= self.low {
+            Confidence::Borderline
+        } else {
+            Confidence::Low
+        }
+    }
+
+    pub fn classify_segment(&self, segment: &TranscriptionSegment) -> Confidence {
+        if segment.no_speech_prob >= self.no_speech {
+            Confidence::Low
+        } else {
+            self.classify(segment.avg_logprob.exp())
+        }
+    }
+
+    pub fn classify_word(&self, word: &WordTiming) -> Confidence {
+        self.classify(word.probability)
+    }
+}
+
+fn paint(text: &str, confidence: Confidence) -> String {
+    format!("{}{}{}", confidence.color(), text, RESET)
+}
+
+/// Like the segment's `Display`, with the text colored by confidence.
+///
+/// Words are colored individually when word probabilities are available, unless the whole
+/// segment is likely non-speech.
+pub fn render_colored(
+    segment: &TranscriptionSegment,
+    timestamps: TimestampStyle,
+    thresholds: &ConfidenceThresholds,
+) -> String {
+    let segment_confidence = thresholds.classify_segment(segment);
+    let text = if segment.words.is_empty() || segment.no_speech_prob >= thresholds.no_speech {
+        paint(segment.text.trim(), segment_confidence)
+    } else {
+        segment
+            .words
+            .iter()
+            .map(|word| paint(word.word.trim(), thresholds.classify_word(word)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    format!(
+        "[{} -> {}] {}",
+        format_timestamp(segment.start, timestamps),
+        format_timestamp(segment.end, timestamps),
+        text
+    )
+}
+
+/// Segments decoded with a lower average log probability are left out of the confident
+/// text by default. faster-whisper's own `log_prob_threshold`, so only decodes it would
+/// already treat as failed are dropped.
+pub const DEFAULT_MIN_AVG_LOGPROB: f64 = -1.0;
+/// Segments more likely than this to be non-speech are left out of the confident text by
+/// default; faster-whisper's `no_speech_threshold`
+pub const DEFAULT_MAX_NO_SPEECH: f64 = 0.6;
+/// Stands in for dropped segments in the confident text
+pub const DROPPED_MARKER: &str = "[...]";
+
+/// The transcript without the segments that fail the confidence thresholds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfidentText {
+    /// Kept segments joined with spaces, with `[...]` wherever segments were dropped
+    pub text: String,
+    /// Share of the segments' duration kept, from 0 to 1
+    pub retained: f64,
+}
+
+/// Check thresholds for `confident_text`: a log probability is at most 0 and a no-speech
+/// probability lies between 0 and 1
+pub fn validate_confident_thresholds(
+    min_avg_logprob: f64,
+    max_no_speech: f64,
+) -> Result<(), String> {
+    if !min_avg_logprob.is_finite() || min_avg_logprob > 0.0 {
+        return Err(format!(
+            "Minimum average log probability must be zero or below, got {}",
+            min_avg_logprob
+        ));
+    }
+    if !(0.0..=1.0).contains(&max_no_speech) {
+        return Err(format!(
+            "Maximum no-speech probability must be between 0 and 1, got {}",
+            max_no_speech
+        ));
+    }
+    Ok(())
+}
+
+/// Rebuild the text from the segments decoded with at least `min_avg_logprob` and at most
+/// `max_no_speech`. A run of dropped segments becomes a single `[...]`.
+pub fn confident_text(
+    segments: &[TranscriptionSegment],
+    min_avg_logprob: f64,
+    max_no_speech: f64,
+) -> ConfidentText {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut total = 0.0;
+    let mut kept = 0.0;
+    for segment in segments {
+        let duration = (segment.end - segment.start).max(0.0);
+        total += duration;
+        if segment.avg_logprob < min_avg_logprob || segment.no_speech_prob > max_no_speech {
+            if parts.last() != Some(&DROPPED_MARKER) {
+                parts.push(DROPPED_MARKER);
+            }
+            continue;
+        }
+        kept += duration;
+        let text = segment.text.trim();
+        if !text.is_empty() {
+            parts.push(text);
+        }
+    }
+    ConfidentText {
+        text: parts.join(" "),
+        retained: if total > 0.0 { kept / total } else { 1.0 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(
+        avg_logprob: f64,
+        no_speech_prob: f64,
+        words: Vec<WordTiming>,
+    ) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start: 1.0,
+            end: 2.5,
+            text: " Hello world".to_string(),
+            no_speech_prob,
+            avg_logprob,
+            words,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    fn word(word: &str, probability: f64) -> WordTiming {
+        WordTiming {
+            start: 1.0,
+            end: 2.0,
+            word: word.to_string(),
+            probability,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_segment_colors() {
+        let thresholds = ConfidenceThresholds::default();
+        let style = TimestampStyle::Seconds;
+
+        // exp(-0.1) ≈ 0.90, exp(-0.5) ≈ 0.61, exp(-1.5) ≈ 0.22
+        assert_eq!(
+            render_colored(&segment(-0.1, 0.0, vec![]), style, &thresholds),
+            "[1.00s -> 2.50s] \x1b[32mHello world\x1b[0m"
+        );
+        assert_eq!(
+            render_colored(&segment(-0.5, 0.0, vec![]), style, &thresholds),
+            "[1.00s -> 2.50s] \x1b[33mHello world\x1b[0m"
+        );
+        assert_eq!(
+            render_colored(&segment(-1.5, 0.0, vec![]), style, &thresholds),
+            "[1.00s -> 2.50s] \x1b[31mHello world\x1b[0m"
+        );
+        // Confident tokens but probably not speech
+        assert_eq!(
+            thresholds.classify_segment(&segment(-0.1, 0.9, vec![])),
+            Confidence::Low
+        );
+    }
+
+    #[test]
+    fn test_word_colors() {
+        let words = vec![word(" Hello", 0.95), word(" world", 0.3)];
+        let rendered = render_colored(
+            &segment(-0.1, 0.0, words),
+            TimestampStyle::Seconds,
+            &ConfidenceThresholds::default(),
+        );
+        assert_eq!(
+            rendered,
+            "[1.00s -> 2.50s] \x1b[32mHello\x1b[0m \x1b[31mworld\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_confident_text() {
+        let timed =
+            |start: f64, text: &str, avg_logprob: f64, no_speech_prob: f64| TranscriptionSegment {
+                start,
+                end: start + 2.0,
+                text: format!(" {}", text),
+                ..segment(avg_logprob, no_speech_prob, vec![])
+            };
+        let segments = vec![
+            timed(0.0, "Welcome back.", -0.2, 0.01),
+            timed(2.0, "Thanks for watching!", -1.4, 0.1),
+            timed(4.0, "Subscribe", -0.3, 0.9),
+            timed(6.0, "Today we look at tides.", -0.4, 0.05),
+            timed(8.0, "Mm.", -2.0, 0.2),
+        ];
+        let confident = confident_text(&segments, DEFAULT_MIN_AVG_LOGPROB, DEFAULT_MAX_NO_SPEECH);
+        assert_eq!(
+            confident.text,
+            "Welcome back. [...] Today we look at tides. [...]"
+        );
+        assert!((confident.retained - 0.4).abs() < 1e-9);
+
+        // Thresholds nothing fails keep everything
+        let everything = confident_text(&segments, -10.0, 1.0);
+        assert!(!everything.text.contains(DROPPED_MARKER));
+        assert_eq!(everything.retained, 1.0);
+        assert_eq!(confident_text(&[], -1.0, 0.6).retained, 1.0);
+
+        assert!(
+            validate_confident_thresholds(DEFAULT_MIN_AVG_LOGPROB, DEFAULT_MAX_NO_SPEECH).is_ok()
+        );
+        assert!(validate_confident_thresholds(0.5, 0.6).is_err());
+        assert!(validate_confident_thresholds(f64::NAN, 0.6).is_err());
+        assert!(validate_confident_thresholds(-1.0, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_thresholds_and_choice_parsing() {
+        let thresholds = ConfidenceThresholds::parse("0.9, 0.4").unwrap();
+        assert_eq!(thresholds.high, 0.9);
+        assert_eq!(thresholds.classify(0.45), Confidence::Borderline);
+        assert!(ConfidenceThresholds::parse("0.4,0.9").is_err());
+        assert!(ConfidenceThresholds::parse("0.9").is_err());
+        assert!(ConfidenceThresholds::parse("1.5,0.2").is_err());
+
+        assert!(ColorChoice::Auto.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+        assert!(ColorChoice::Always.enabled(false));
+        assert_eq!("NEVER".parse(), Ok(ColorChoice::Never));
+    }
+}
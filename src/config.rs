@@ -0,0 +1,681 @@
+use crate::error::{Result, TranscriptionError};
+use crate::language;
+use crate::output::{self, OutputFormat};
+use crate::types::{Backend, ModelConfig, TranscriptionOptions};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Directory name under the user config dir (`~/.config/whisper-cli`)
+pub const CONFIG_DIR_NAME: &str = "whisper-cli";
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "backend",
+    "model",
+    "device",
+    "compute_type",
+    "format",
+    "jobs",
+    "model_dir",
+    "offline",
+    "python_venv",
+    "vad",
+    "decoding",
+];
+/// Prefix shared by every environment variable override (`WHISPER_MODEL`, ...)
+pub const ENV_PREFIX: &str = "WHISPER_";
+
+const VAD_KEYS: &[&str] = &["enabled", "threshold"];
+const DECODING_KEYS: &[&str] = &[
+    "language",
+    "beam_size",
+    "best_of",
+    "temperature",
+    "word_timestamps",
+    "paragraph_gap",
+    "allowed_languages",
+    "include_tokens",
+];
+
+/// Commented default configuration written by `config init`
+pub const DEFAULT_CONFIG_TEMPLATE: &str = r#"# whisper-cli configuration
+#
+# Values here are used when the corresponding command line flag is not given.
+# Precedence: CLI flag > environment variable > this file > built-in default.
+
+# Inference backend: faster-whisper, whispercpp or candle (the last two need build features)
+# backend = "faster-whisper"
+
+# Model size: tiny, base, small, medium, large-v2, large-v3
+# model = "medium"
+
+# Device: auto, cpu, cuda, mps
+# device = "auto"
+
+# Compute type: float16, float32, int8, int8_float16
+# compute_type = "float16"
+
+# Output format: json, txt, srt, vtt, openai-json; a comma-separated list or an array writes several
+# format = "json"
+
+# Number of files processed concurrently in directory mode
+# jobs = 1
+
+# Directory models are downloaded to and loaded from
+# model_dir = "~/models/whisper"
+
+# Only use models already on disk; never contact the Hugging Face hub
+# offline = false
+
+# Virtualenv providing faster-whisper, if not the interpreter's own site-packages
+# python_venv = "~/.venvs/whisper"
+
+[vad]
+# Skip silent regions with the Silero VAD filter
+# enabled = true
+# threshold = 0.5
+
+[decoding]
+# Force a language instead of auto-detecting it
+# language = "en"
+# beam_size = 5
+# best_of = 5
+# temperature = 0.0
+# word_timestamps = true
+# Start a new paragraph in the text after pauses longer than this many seconds
+# paragraph_gap = 2.0
+# Only accept these languages from auto-detection
+# allowed_languages = ["en", "es", "pt"]
+# Keep token ids on segments and words (faster-whisper only; much larger JSON)
+# include_tokens = false
+"#;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadSettings {
+    pub enabled: Option<bool>,
+    pub threshold: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodingSettings {
+    pub language: Option<String>,
+    pub beam_size: Option<usize>,
+    pub best_of: Option<usize>,
+    pub temperature: Option<f64>,
+    pub word_timestamps: Option<bool>,
+    pub paragraph_gap: Option<f64>,
+    pub allowed_languages: Option<Vec<String>>,
+    pub include_tokens: Option<bool>,
+}
+
+/// One layer of settings (CLI flags, config file, ...) where every field is optional
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartialSettings {
+    pub backend: Option<Backend>,
+    pub model: Option<String>,
+    pub device: Option<String>,
+    pub compute_type: Option<String>,
+    #[serde(with = "format_list")]
+    pub format: Option<Vec<OutputFormat>>,
+    pub jobs: Option<usize>,
+    pub model_dir: Option<PathBuf>,
+    pub offline: Option<bool>,
+    pub python_venv: Option<PathBuf>,
+    pub vad: VadSettings,
+    pub decoding: DecodingSettings,
+}
+
+/// Fully resolved settings after applying every layer and the built-in defaults
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub model: ModelConfig,
+    /// The first requested format, written to `--output`
+    pub format: OutputFormat,
+    /// Any further formats, written beside it from the same transcription
+    pub extra_formats: Vec<OutputFormat>,
+    pub jobs: usize,
+    pub options: TranscriptionOptions,
+    pub python_venv: Option<PathBuf>,
+}
+
+impl PartialSettings {
+    /// Fill every unset field of `self` from `lower`, so `self` takes precedence
+    pub fn or(self, lower: PartialSettings) -> PartialSettings {
+        PartialSettings {
+            backend: self.backend.or(lower.backend),
+            model: self.model.or(lower.model),
+            device: self.device.or(lower.device),
+            compute_type: self.compute_type.or(lower.compute_type),
+            format: self.format.or(lower.format),
+            jobs: self.jobs.or(lower.jobs),
+            model_dir: self.model_dir.or(lower.model_dir),
+            offline: self.offline.or(lower.offline),
+            python_venv: self.python_venv.or(lower.python_venv),
+            vad: VadSettings {
+                enabled: self.vad.enabled.or(lower.vad.enabled),
+                threshold: self.vad.threshold.or(lower.vad.threshold),
+            },
+            decoding: DecodingSettings {
+                language: self.decoding.language.or(lower.decoding.language),
+                beam_size: self.decoding.beam_size.or(lower.decoding.beam_size),
+                best_of: self.decoding.best_of.or(lower.decoding.best_of),
+                temperature: self.decoding.temperature.or(lower.decoding.temperature),
+                word_timestamps: self
+                    .decoding
+                    .word_timestamps
+                    .or(lower.decoding.word_timestamps),
+                paragraph_gap: self.decoding.paragraph_gap.or(lower.decoding.paragraph_gap),
+                allowed_languages: self
+                    .decoding
+                    .allowed_languages
+                    .or(lower.decoding.allowed_languages),
+                include_tokens: self
+                    .decoding
+                    .include_tokens
+                    .or(lower.decoding.include_tokens),
+            },
+        }
+    }
+
+    /// Apply built-in defaults to any field still unset, and expand a leading `~` in the model
+    /// directory and the venv
+    pub fn resolve(self) -> Settings {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let model_defaults = ModelConfig::default();
+        let option_defaults = TranscriptionOptions::default();
+        let mut formats = self.format.unwrap_or_default().into_iter();
+        let format = formats.next().unwrap_or(OutputFormat::Json);
+
+        Settings {
+            model: ModelConfig {
+                backend: self.backend.unwrap_or(model_defaults.backend),
+                model_size: self.model.unwrap_or(model_defaults.model_size),
+                device: self.device.unwrap_or(model_defaults.device),
+                compute_type: self.compute_type.unwrap_or(model_defaults.compute_type),
+                model_dir: self.model_dir.map(|dir| expand_home(dir, home.as_deref())),
+                offline: self.offline.unwrap_or(model_defaults.offline),
+                cpu_threads: None,
+            },
+            format,
+            extra_formats: formats.collect(),
+            jobs: self.jobs.unwrap_or(1).max(1),
+            options: TranscriptionOptions {
+                language: self.decoding.language,
+                beam_size: self.decoding.beam_size,
+                best_of: self.decoding.best_of,
+                temperature: self.decoding.temperature,
+                word_timestamps: self
+                    .decoding
+                    .word_timestamps
+                    .unwrap_or(option_defaults.word_timestamps),
+                vad_filter: self.vad.enabled.unwrap_or(option_defaults.vad_filter),
+                vad_threshold: self.vad.threshold.unwrap_or(option_defaults.vad_threshold),
+                paragraph_gap: self.decoding.paragraph_gap,
+                allowed_languages: self.decoding.allowed_languages.map(|allowed| {
+                    allowed
+                        .iter()
+                        .map(|code| language::normalize_code(code))
+                        .collect()
+                }),
+                clip_start: None,
+                include_tokens: self
+                    .decoding
+                    .include_tokens
+                    .unwrap_or(option_defaults.include_tokens),
+            },
+            python_venv: self
+                .python_venv
+                .map(|venv| expand_home(venv, home.as_deref())),
+        }
+    }
+}
+
+impl PartialSettings {
+    /// Read the `WHISPER_*` environment overrides using `lookup` (normally `std::env::var`)
+    pub fn from_env<F>(lookup: F) -> Result<PartialSettings>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |name: &str| {
+            lookup(&format!("{}{}", ENV_PREFIX, name)).filter(|value| !value.trim().is_empty())
+        };
+        let invalid = |name: &str, value: &str, expected: &str| {
+            TranscriptionError::ConfigError(format!(
+                "Invalid {}{}={}: expected {}",
+                ENV_PREFIX, name, value, expected
+            ))
+        };
+
+        let backend =
+            match var("BACKEND") {
+                Some(value) => Some(value.parse::<Backend>().map_err(|_| {
+                    invalid("BACKEND", &value, "faster-whisper, whispercpp or candle")
+                })?),
+                None => None,
+            };
+        let format = match var("FORMAT") {
+            Some(value) => Some(output::parse_formats(&value).map_err(|_| {
+                invalid(
+                    "FORMAT",
+                    &value,
+                    "json, txt, srt, vtt or openai-json, or a comma-separated list",
+                )
+            })?),
+            None => None,
+        };
+        let jobs = match var("JOBS") {
+            Some(value) => Some(
+                value
+                    .trim()
+                    .parse::<NonZeroUsize>()
+                    .map_err(|_| invalid("JOBS", &value, "a positive integer"))?
+                    .get(),
+            ),
+            None => None,
+        };
+        let offline = match var("OFFLINE") {
+            Some(value) => Some(
+                parse_bool(&value).ok_or_else(|| invalid("OFFLINE", &value, "true or false"))?,
+            ),
+            None => None,
+        };
+
+        Ok(PartialSettings {
+            backend,
+            model: var("MODEL"),
+            device: var("DEVICE"),
+            compute_type: var("COMPUTE_TYPE"),
+            format,
+            jobs,
+            model_dir: var("MODEL_DIR").map(PathBuf::from),
+            offline,
+            python_venv: var("VENV").map(PathBuf::from),
+            ..Default::default()
+        })
+    }
+
+    /// Overrides from the real process environment
+    pub fn from_process_env() -> Result<PartialSettings> {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+}
+
+/// `format` is a single name, a comma-separated list such as `"json,srt"` or an array of
+/// names
+mod format_list {
+    use crate::output::{self, OutputFormat};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Formats {
+        List(String),
+        Array(Vec<String>),
+    }
+
+    pub fn serialize<S: Serializer>(
+        formats: &Option<Vec<OutputFormat>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match formats {
+            Some(formats) => serializer.serialize_some(
+                &formats
+                    .iter()
+                    .map(|format| format.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<OutputFormat>>, D::Error> {
+        let list = match Option::<Formats>::deserialize(deserializer)? {
+            Some(Formats::List(list)) => list,
+            Some(Formats::Array(names)) => names.join(","),
+            None => return Ok(None),
+        };
+        output::parse_formats(&list)
+            .map(Some)
+            .map_err(D::Error::custom)
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_auto = |value: Option<String>| value.unwrap_or_else(|| "auto".to_string());
+        write!(
+            f,
+            "backend={} model={} device={} compute_type={} format={} jobs={} model_dir={} offline={} \
+             vad={} vad_threshold={} language={} beam_size={} best_of={} temperature={} \
+             word_timestamps={} paragraph_gap={} allowed_languages={} include_tokens={} \
+             python_venv={}",
+            self.model.backend,
+            self.model.model_size,
+            self.model.device,
+            self.model.compute_type,
+            std::iter::once(self.format)
+                .chain(self.extra_formats.iter().copied())
+                .map(|format| format.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            self.jobs,
+            self.model
+                .model_dir
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_else(|| "default".to_string()),
+            self.model.offline,
+            self.options.vad_filter,
+            self.options.vad_threshold,
+            or_auto(self.options.language.clone()),
+            or_auto(self.options.beam_size.map(|b| b.to_string())),
+            or_auto(self.options.best_of.map(|b| b.to_string())),
+            or_auto(self.options.temperature.map(|t| t.to_string())),
+            self.options.word_timestamps,
+            self.options
+                .paragraph_gap
+                .map(|gap| gap.to_string())
+                .unwrap_or_else(|| "off".to_string()),
+            self.options
+                .allowed_languages
+                .as_ref()
+                .map(|allowed| allowed.join(","))
+                .unwrap_or_else(|| "any".to_string()),
+            self.options.include_tokens,
+            self.python_venv
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_else(|| "none".to_string()),
+        )
+    }
+}
+
+/// `path` with a leading `~` replaced by `home`; paths from the config file and the
+/// environment don't pass through a shell that would do it. `~user` is left alone.
+pub fn expand_home(path: PathBuf, home: Option<&Path>) -> PathBuf {
+    let Some(home) = home else {
+        return path;
+    };
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path,
+    }
+}
+
+/// `$XDG_CONFIG_HOME/whisper-cli/config.toml`, falling back to `~/.config/whisper-cli/config.toml`
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Keys in a parsed config table that this version does not understand
+pub fn unknown_keys(table: &toml::Table) -> Vec<String> {
+    let mut unknown = Vec::new();
+    for (key, value) in table {
+        let nested = match key.as_str() {
+            "vad" => Some(VAD_KEYS),
+            "decoding" => Some(DECODING_KEYS),
+            _ => None,
+        };
+        if !TOP_LEVEL_KEYS.contains(&key.as_str()) {
+            unknown.push(key.clone());
+        } else if let (Some(known), Some(section)) = (nested, value.as_table()) {
+            unknown.extend(
+                section
+                    .keys()
+                    .filter(|k| !known.contains(&k.as_str()))
+                    .map(|k| format!("{}.{}", key, k)),
+            );
+        }
+    }
+    unknown
+}
+
+/// Parse config file contents, warning (not failing) on unknown keys
+pub fn parse_config(contents: &str, origin: &Path) -> Result<PartialSettings> {
+    let table: toml::Table = contents
+        .parse()
+        .map_err(|e| TranscriptionError::ConfigError(format!("{}: {}", origin.display(), e)))?;
+
+    for key in unknown_keys(&table) {
+        warn!(
+            "Ignoring unknown config key '{}' in {}",
+            key,
+            origin.display()
+        );
+    }
+
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| TranscriptionError::ConfigError(format!("{}: {}", origin.display(), e)))
+}
+
+pub fn load_config_file<P: AsRef<Path>>(path: P) -> Result<PartialSettings> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        TranscriptionError::ConfigError(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    let settings = parse_config(&contents, path)?;
+    info!("Loaded configuration from {}", path.display());
+    Ok(settings)
+}
+
+/// Load the default config file if it exists; a missing file is not an error
+pub fn load_default_config() -> Result<PartialSettings> {
+    match default_config_path() {
+        Some(path) if path.is_file() => load_config_file(path),
+        _ => Ok(PartialSettings::default()),
+    }
+}
+
+/// Write the commented default config, refusing to clobber an existing file unless `force`
+pub fn write_default_config<P: AsRef<Path>>(path: P, force: bool) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() && !force {
+        return Err(TranscriptionError::ConfigError(format!(
+            "{} already exists (use --force to overwrite)",
+            path.display()
+        )));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, DEFAULT_CONFIG_TEMPLATE)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_precedence_chain() {
+        let cli = PartialSettings {
+            model: Some("large-v3".to_string()),
+            ..Default::default()
+        };
+        let file = parse_config(
+            "backend = \"whispercpp\"\nmodel = \"small\"\ndevice = \"cpu\"\n[decoding]\nbeam_size = 2\nparagraph_gap = 1.5\nallowed_languages = [\"EN\", \"es\"]\n",
+            Path::new("test.toml"),
+        )
+        .unwrap();
+
+        let settings = cli.or(file).resolve();
+        // CLI wins over the file
+        assert_eq!(settings.model.model_size, "large-v3");
+        // File wins over the built-in default
+        assert_eq!(settings.model.device, "cpu");
+        assert_eq!(settings.model.backend, Backend::WhisperCpp);
+        assert_eq!(settings.options.beam_size, Some(2));
+        assert_eq!(settings.options.paragraph_gap, Some(1.5));
+        assert_eq!(
+            settings.options.allowed_languages,
+            Some(vec!["en".to_string(), "es".to_string()])
+        );
+        // Built-in default fills the rest
+        assert_eq!(settings.model.compute_type, "float16");
+        assert_eq!(settings.format, OutputFormat::Json);
+        assert!(settings.options.vad_filter);
+    }
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+    }
+
+    #[test]
+    fn test_env_overrides_config_file() {
+        let cli = PartialSettings {
+            device: Some("mps".to_string()),
+            ..Default::default()
+        };
+        let env_layer = PartialSettings::from_env(env(&[
+            ("WHISPER_MODEL", "large-v3"),
+            ("WHISPER_DEVICE", "cpu"),
+            ("WHISPER_JOBS", "4"),
+            ("WHISPER_OFFLINE", "1"),
+            ("WHISPER_MODEL_DIR", "/models"),
+            ("WHISPER_VENV", "/venvs/whisper"),
+        ]))
+        .unwrap();
+        let file = parse_config(
+            "model = \"small\"\njobs = 2\nformat = \"srt\"\n",
+            Path::new("test.toml"),
+        )
+        .unwrap();
+
+        let settings = cli.or(env_layer).or(file).resolve();
+        // CLI beats env, env beats file, file beats default
+        assert_eq!(settings.model.device, "mps");
+        assert_eq!(settings.model.model_size, "large-v3");
+        assert_eq!(settings.jobs, 4);
+        assert_eq!(settings.format, OutputFormat::Srt);
+        assert!(settings.extra_formats.is_empty());
+        assert!(settings.model.offline);
+        assert_eq!(settings.model.model_dir, Some(PathBuf::from("/models")));
+        assert_eq!(settings.python_venv, Some(PathBuf::from("/venvs/whisper")));
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = Some(Path::new("/home/ada"));
+        let expand = |path: &str| expand_home(PathBuf::from(path), home);
+        assert_eq!(
+            expand("~/models/whisper"),
+            PathBuf::from("/home/ada/models/whisper")
+        );
+        assert_eq!(expand("~"), PathBuf::from("/home/ada"));
+        assert_eq!(expand("/opt/models"), PathBuf::from("/opt/models"));
+        assert_eq!(expand("models/~"), PathBuf::from("models/~"));
+        assert_eq!(expand("~ada/models"), PathBuf::from("~ada/models"));
+        assert_eq!(
+            expand_home(PathBuf::from("~/models"), None),
+            PathBuf::from("~/models")
+        );
+
+        // The template's example paths work once uncommented
+        let uncommented = DEFAULT_CONFIG_TEMPLATE
+            .replace("# model_dir =", "model_dir =")
+            .replace("# python_venv =", "python_venv =");
+        let settings = parse_config(&uncommented, Path::new("config.toml"))
+            .unwrap()
+            .resolve();
+        if let Some(home) = std::env::var_os("HOME") {
+            assert!(settings.model.model_dir.unwrap().starts_with(&home));
+            let venv = settings.python_venv.unwrap();
+            assert_eq!(venv, Path::new(&home).join(".venvs/whisper"));
+        }
+    }
+
+    #[test]
+    fn test_invalid_env_values() {
+        let result = PartialSettings::from_env(env(&[("WHISPER_JOBS", "lots")]));
+        assert!(
+            matches!(result, Err(TranscriptionError::ConfigError(msg)) if msg.contains("WHISPER_JOBS"))
+        );
+
+        let result = PartialSettings::from_env(env(&[("WHISPER_JOBS", "0")]));
+        assert!(
+            matches!(result, Err(TranscriptionError::ConfigError(msg)) if msg.contains("WHISPER_JOBS"))
+        );
+
+        let result = PartialSettings::from_env(env(&[("WHISPER_OFFLINE", "maybe")]));
+        assert!(result.is_err());
+
+        // Empty values are treated as unset
+        let settings = PartialSettings::from_env(env(&[("WHISPER_MODEL", "")])).unwrap();
+        assert_eq!(settings.model, None);
+    }
+
+    #[test]
+    fn test_format_lists() {
+        let string = parse_config("format = \"srt, vtt\"\n", Path::new("test.toml")).unwrap();
+        let array = parse_config("format = [\"srt\", \"vtt\"]\n", Path::new("test.toml")).unwrap();
+        assert_eq!(string, array);
+
+        let env_layer = PartialSettings::from_env(env(&[("WHISPER_FORMAT", "json,txt")])).unwrap();
+        let settings = env_layer.or(string).resolve();
+        assert_eq!(settings.format, OutputFormat::Json);
+        assert_eq!(settings.extra_formats, vec![OutputFormat::Txt]);
+        assert!(settings.to_string().contains(" format=json,txt "));
+
+        assert!(parse_config("format = \"json,docx\"\n", Path::new("test.toml")).is_err());
+        assert!(PartialSettings::from_env(env(&[("WHISPER_FORMAT", "docx")])).is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_warn_not_error() {
+        let contents =
+            "model = \"base\"\ncolour = \"blue\"\n[vad]\nenabled = false\naggressive = true\n";
+        let table: toml::Table = contents.parse().unwrap();
+        assert_eq!(unknown_keys(&table), vec!["colour", "vad.aggressive"]);
+
+        let settings = parse_config(contents, Path::new("test.toml")).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("base"));
+        assert_eq!(settings.vad.enabled, Some(false));
+    }
+
+    #[test]
+    fn test_invalid_config_errors() {
+        let result = parse_config("jobs = \"many\"", Path::new("bad.toml"));
+        assert!(matches!(result, Err(TranscriptionError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_default_template_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested").join(CONFIG_FILE_NAME);
+
+        write_default_config(&path, false).unwrap();
+        assert!(write_default_config(&path, false).is_err());
+        write_default_config(&path, true).unwrap();
+
+        // Everything is commented out, so the template resolves to the defaults
+        let settings = load_config_file(&path).unwrap();
+        assert_eq!(settings, PartialSettings::default());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::{Result, TranscriptionError};
+use crate::python_env;
+use crate::speakers::{self, DiarizedTurn};
+use crate::types::TranscriptionResult;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::Path;
+use std::time::Instant;
+use tracing::info;
+
+/// The gated Hugging Face model; its conditions must be accepted once for the token's account
+pub const PIPELINE: &str = "pyannote/speaker-diarization";
+
+/// pyannote's speaker diarization pipeline, loaded once and run per file
+#[derive(Debug)]
+pub struct Diarizer {
+    pipeline: Py<PyAny>,
+}
+
+impl Diarizer {
+    /// Load the pipeline with a Hugging Face access token
+    pub fn new(hf_token: &str) -> Result<Self> {
+        if hf_token.trim().is_empty() {
+            return Err(TranscriptionError::ConfigError(
+                "Diarization needs a Hugging Face access token: pass --hf-token or set HF_TOKEN"
+                    .to_string(),
+            ));
+        }
+        Python::with_gil(|py| {
+            let pyannote = python_env::import_package(py, "pyannote.audio")?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("use_auth_token", hf_token)?;
+            info!("Loading diarization pipeline {}", PIPELINE);
+            let pipeline = pyannote
+                .getattr("Pipeline")?
+                .call_method("from_pretrained", (PIPELINE,), Some(&kwargs))
+                .map_err(|e| {
+                    TranscriptionError::ModelInitError(format!(
+                        "Failed to load {}: {}",
+                        PIPELINE, e
+                    ))
+                })?;
+            // from_pretrained logs and returns None when the token can't see the model
+            if pipeline.is_none() {
+                return Err(TranscriptionError::ModelInitError(format!(
+                    "Could not load {}: check the token and accept the model's conditions at \
+                     https://huggingface.co/{}",
+                    PIPELINE, PIPELINE
+                )));
+            }
+            Ok(Self {
+                pipeline: pipeline.unbind(),
+            })
+        })
+    }
+
+    /// Who spoke when in `audio_path`, in the model's own speaker labels
+    pub fn diarize(&self, audio_path: &Path) -> Result<Vec<DiarizedTurn>> {
+        Python::with_gil(|py| -> Result<Vec<DiarizedTurn>> {
+            let annotation = self
+                .pipeline
+                .bind(py)
+                .call1((audio_path.to_string_lossy().as_ref(),))?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("yield_label", true)?;
+            let mut turns = Vec::new();
+            for track in annotation
+                .call_method("itertracks", (), Some(&kwargs))?
+                .try_iter()?
+            {
+                let (segment, _track, speaker): (Bound<'_, PyAny>, Bound<'_, PyAny>, String) =
+                    track?.extract()?;
+                turns.push(DiarizedTurn {
+                    start: segment.getattr("start")?.extract()?,
+                    end: segment.getattr("end")?.extract()?,
+                    speaker,
+                });
+            }
+            Ok(turns)
+        })
+        .map_err(|e| e.with_path(audio_path))
+    }
+
+    /// Diarize `audio_path` and label `result`'s segments and words with the speakers,
+    /// recording how long diarization took
+    pub fn label(&self, audio_path: &Path, result: &mut TranscriptionResult) -> Result<()> {
+        let started = Instant::now();
+        let turns = self.diarize(audio_path)?;
+        let elapsed = started.elapsed().as_secs_f64();
+        speakers::assign_turns(result, &turns, elapsed);
+        info!(
+            "Diarized {} in {:.2}s: {} speaker(s)",
+            audio_path.display(),
+            elapsed,
+            result.speakers.as_ref().map_or(0, |s| s.max_speakers)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+use crate::error::{Result, TranscriptionError};
+use ring::digest;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Further attempts after a dropped connection, unless `--download-retries` says otherwise
+pub const DEFAULT_RETRIES: u32 = 5;
+
+/// How a URL input is fetched
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Refuse anything larger. Checked against the advertised size before the body is
+    /// read, and again as bytes arrive for servers that don't advertise one.
+    pub max_bytes: Option<u64>,
+    /// Further attempts after a dropped connection or a 5xx response
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub retry_delay: Duration,
+    /// Connect and read timeout for each attempt
+    pub timeout: Duration,
+    /// Lowercase hex SHA-256 the finished file must match
+    pub expect_sha256: Option<String>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            retries: DEFAULT_RETRIES,
+            retry_delay: Duration::from_secs(1),
+            timeout: Duration::from_secs(30),
+            expect_sha256: None,
+        }
+    }
+}
+
+/// Reported after every chunk written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    /// Full size, when the server advertised one
+    pub total: Option<u64>,
+}
+
+pub fn is_url(input: &str) -> bool {
+    let lower = input.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Last path segment of `url` without its query or fragment, or `download` when it has none
+pub fn file_name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    path.split_once('/')
+        .and_then(|(_, path)| path.rsplit('/').next())
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .unwrap_or("download")
+        .to_string()
+}
+
+/// Parse a size such as `500M`, `2G`, `750k` or a plain byte count (binary multiples)
+pub fn parse_size(size: &str) -> std::result::Result<u64, String> {
+    let trimmed = size.trim();
+    let digits = trimmed.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = trimmed[digits.len()..].to_ascii_lowercase();
+    let multiplier: u64 = match unit.trim_end_matches('b') {
+        "" => 1,
+        "k" | "ki" => 1 << 10,
+        "m" | "mi" => 1 << 20,
+        "g" | "gi" => 1 << 30,
+        _ => return Err(format!("Unknown size unit in '{}'", size)),
+    };
+    let value: f64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", size))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(format!("Size must be positive: '{}'", size));
+    }
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// Check a user-supplied SHA-256 and normalise it to lowercase hex
+pub fn parse_sha256(hex: &str) -> std::result::Result<String, String> {
+    let hex = hex.trim().to_ascii_lowercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "Expected a SHA-256 of 64 hex digits, got '{}'",
+            hex
+        ));
+    }
+    Ok(hex)
+}
+
+/// Why one attempt stopped
+enum Interrupted {
+    /// Worth another attempt, resuming from what's on disk
+    Retry(TranscriptionError),
+    Fatal(TranscriptionError),
+}
+
+impl From<std::io::Error> for Interrupted {
+    fn from(e: std::io::Error) -> Self {
+        Interrupted::Fatal(e.into())
+    }
+}
+
+/// Download `url` to `destination`, resuming with HTTP range requests after dropped
+/// connections when the server supports them and starting over when it doesn't.
+///
+/// Bytes go to `<destination>.part`, which is renamed into place only once the download
+/// is complete and matches `expect_sha256`; it is removed on every failure. Returns the
+/// size of the file.
+pub fn download(
+    url: &str,
+    destination: &Path,
+    options: &DownloadOptions,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<u64> {
+    let partial = partial_path(destination);
+    let result = fetch(url, &partial, options, progress).and_then(|size| {
+        if let Some(expected) = &options.expect_sha256 {
+            let actual = sha256_file(&partial)?;
+            if actual != *expected {
+                return Err(TranscriptionError::ChecksumMismatch {
+                    url: url.to_string(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        fs::rename(&partial, destination)?;
+        Ok(size)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn partial_path(destination: &Path) -> PathBuf {
+    let mut name = destination.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+fn fetch(
+    url: &str,
+    partial: &Path,
+    options: &DownloadOptions,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<u64> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(options.timeout)
+        .timeout_read(options.timeout)
+        .build();
+    let mut file = File::create(partial)?;
+    let mut state = DownloadProgress {
+        downloaded: 0,
+        total: None,
+    };
+    let mut attempt = 0;
+    loop {
+        let error = match fetch_once(&agent, url, &mut file, &mut state, options, progress) {
+            Ok(()) => return Ok(state.downloaded),
+            Err(Interrupted::Fatal(e)) => return Err(e),
+            Err(Interrupted::Retry(e)) if attempt >= options.retries => return Err(e),
+            Err(Interrupted::Retry(e)) => e,
+        };
+        attempt += 1;
+        let delay = options.retry_delay * 2u32.saturating_pow(attempt - 1);
+        warn!(
+            "Download of {} interrupted after {} bytes ({}); retrying in {:.1}s ({}/{})",
+            url,
+            state.downloaded,
+            error,
+            delay.as_secs_f64(),
+            attempt,
+            options.retries
+        );
+        std::thread::sleep(delay);
+    }
+}
+
+fn fetch_once(
+    agent: &ureq::Agent,
+    url: &str,
+    file: &mut File,
+    state: &mut DownloadProgress,
+    options: &DownloadOptions,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> std::result::Result<(), Interrupted> {
+    let failed = |detail: String| TranscriptionError::DownloadFailed {
+        url: url.to_string(),
+        detail,
+    };
+
+    let mut request = agent.get(url);
+    if state.downloaded > 0 {
+        request = request.set("Range", &format!("bytes={}-", state.downloaded));
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, _)) if code >= 500 || code == 429 => {
+            return Err(Interrupted::Retry(failed(format!("HTTP {}", code))))
+        }
+        Err(ureq::Error::Status(code, response)) => {
+            return Err(Interrupted::Fatal(failed(format!(
+                "HTTP {} {}",
+                code,
+                response.status_text()
+            ))))
+        }
+        Err(ureq::Error::Transport(e)) => return Err(Interrupted::Retry(failed(e.to_string()))),
+    };
+
+    let resumed = state.downloaded > 0
+        && response.status() == 206
+        && response
+            .header("Content-Range")
+            .is_some_and(|range| range.starts_with(&format!("bytes {}-", state.downloaded)));
+    if resumed {
+        debug!("Resuming {} at byte {}", url, state.downloaded);
+    } else if state.downloaded > 0 {
+        debug!("{} doesn't support resuming; starting over", url);
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        state.downloaded = 0;
+    }
+
+    let advertised = if resumed {
+        response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit('/').next())
+            .and_then(|total| total.parse().ok())
+    } else {
+        response
+            .header("Content-Length")
+            .and_then(|length| length.parse().ok())
+    };
+    if advertised.is_some() {
+        state.total = advertised;
+    }
+    let too_large = |size: u64| match options.max_bytes {
+        Some(limit) if size > limit => Some(TranscriptionError::DownloadTooLarge {
+            url: url.to_string(),
+            size,
+            limit,
+        }),
+        _ => None,
+    };
+    if let Some(e) = state.total.and_then(too_large) {
+        return Err(Interrupted::Fatal(e));
+    }
+
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) => return Err(Interrupted::Retry(failed(e.to_string()))),
+        };
+        file.write_all(&buffer[..read])?;
+        state.downloaded += read as u64;
+        if let Some(e) = too_large(state.downloaded) {
+            return Err(Interrupted::Fatal(e));
+        }
+        progress(*state);
+    }
+    file.flush()?;
+
+    match state.total {
+        Some(total) if state.downloaded < total => Err(Interrupted::Retry(failed(format!(
+            "connection closed at {} of {} bytes",
+            state.downloaded, total
+        )))),
+        _ => Ok(()),
+    }
+}
+
+/// Lowercase hex SHA-256 of a file, read in chunks
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = digest::Context::new(&digest::SHA256);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::net::TcpListener;
+    use tempfile::tempdir;
+
+    const BODY: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// Serve one connection per handler, in order. Each handler gets the request's `Range`
+    /// start and returns the raw response, which may stop short of its `Content-Length`.
+    fn serve(handlers: Vec<fn(Option<usize>) -> Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/media/talk.wav?sig=1",
+            listener.local_addr().unwrap()
+        );
+        std::thread::spawn(move || {
+            for handler in handlers {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        range = value.trim().trim_end_matches('-').parse().ok();
+                    }
+                }
+                let _ = (&stream).write_all(&handler(range));
+            }
+        });
+        url
+    }
+
+    fn full(_: Option<usize>) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            BODY.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(BODY);
+        response
+    }
+
+    fn cut_off(range: Option<usize>) -> Vec<u8> {
+        let mut response = full(range);
+        response.truncate(response.len() - BODY.len() / 2);
+        response
+    }
+
+    fn ranged(range: Option<usize>) -> Vec<u8> {
+        let start = range.unwrap();
+        let mut response = format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            start,
+            BODY.len() - 1,
+            BODY.len(),
+            BODY.len() - start
+        )
+        .into_bytes();
+        response.extend_from_slice(&BODY[start..]);
+        response
+    }
+
+    fn options() -> DownloadOptions {
+        DownloadOptions {
+            retry_delay: Duration::ZERO,
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resumes_after_disconnect() {
+        let dir = tempdir().unwrap();
+        let destination = dir.path().join("talk.wav");
+        let mut updates = Vec::new();
+        let size = download(
+            &serve(vec![cut_off, ranged]),
+            &destination,
+            &options(),
+            &mut |progress| updates.push(progress),
+        )
+        .unwrap();
+        assert_eq!(size, BODY.len() as u64);
+        assert_eq!(std::fs::read(&destination).unwrap(), BODY);
+        assert!(!partial_path(&destination).exists());
+        assert_eq!(
+            updates.last(),
+            Some(&DownloadProgress {
+                downloaded: BODY.len() as u64,
+                total: Some(BODY.len() as u64)
+            })
+        );
+
+        // A server without range support sends everything again
+        std::fs::remove_file(&destination).unwrap();
+        download(
+            &serve(vec![cut_off, full]),
+            &destination,
+            &options(),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(&destination).unwrap(), BODY);
+    }
+
+    #[test]
+    fn test_failures_leave_no_files() {
+        let dir = tempdir().unwrap();
+        let destination = dir.path().join("talk.wav");
+
+        let limited = DownloadOptions {
+            max_bytes: Some(10),
+            ..options()
+        };
+        let err = download(&serve(vec![full]), &destination, &limited, &mut |_| {}).unwrap_err();
+        assert_eq!(err.kind(), "download_too_large");
+
+        let no_retries = DownloadOptions {
+            retries: 0,
+            ..options()
+        };
+        let err = download(
+            &serve(vec![cut_off]),
+            &destination,
+            &no_retries,
+            &mut |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "download");
+
+        let checked = DownloadOptions {
+            expect_sha256: Some("0".repeat(64)),
+            ..options()
+        };
+        let err = download(&serve(vec![full]), &destination, &checked, &mut |_| {}).unwrap_err();
+        assert_eq!(err.kind(), "checksum_mismatch");
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_helpers() {
+        assert_eq!(parse_size("500M"), Ok(500 << 20));
+        assert_eq!(parse_size("1.5 GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("-1M").is_err());
+
+        assert!(parse_sha256(&"AB".repeat(32)).unwrap().starts_with("abab"));
+        assert!(parse_sha256("abc").is_err());
+
+        assert_eq!(
+            file_name_from_url("https://example.com/media/talk.wav?sig=1"),
+            "talk.wav"
+        );
+        assert_eq!(file_name_from_url("https://example.com/"), "download");
+        assert!(is_url("HTTPS://example.com/a.mp3"));
+        assert!(!is_url("s3://bucket/a.mp3"));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::types::ModelConfig;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -22,6 +24,651 @@ pub enum TranscriptionError {
 
     #[error("Transcription failed: {0}")]
     TranscriptionFailed(String),
+
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    #[error("Output location is not writable: {}", .0.display())]
+    OutputUnwritable(std::path::PathBuf),
+
+    #[error("Failed to write {} output(s): {}", .failures.len(), .failures.join("; "))]
+    OutputWriteFailed {
+        /// One message per format that failed, naming the format
+        failures: Vec<String>,
+        /// Outputs the other formats did write
+        written: Vec<std::path::PathBuf>,
+    },
+
+    #[error("Transcriber is busy with another request")]
+    WouldBlock,
+
+    /// A queued job that was cancelled or dropped at shutdown before it ran
+    #[error("Job was cancelled before it ran")]
+    Cancelled,
+
+    #[error("{option} requires faster-whisper >= {need} (installed: {have})")]
+    RequiresVersion {
+        option: String,
+        have: String,
+        need: String,
+    },
+
+    #[error("Python interpreter is unusable: {0}")]
+    PythonUnavailable(String),
+
+    #[error("Python package {package} is not installed. Install with: {pip_hint}")]
+    PackageMissing { package: String, pip_hint: String },
+
+    #[error("Failed to download model {model}: {source}")]
+    ModelDownloadFailed { model: String, source: pyo3::PyErr },
+
+    #[error("Could not decode audio {}: {detail}", .path.display())]
+    AudioDecodeError {
+        path: std::path::PathBuf,
+        reason: DecodeFailure,
+        detail: String,
+    },
+
+    /// `--ffmpeg-fallback` couldn't start ffmpeg
+    #[error(
+        "ffmpeg is not available ({detail}); install it or set {} to its path",
+        crate::ffmpeg::BINARY_ENV_VAR
+    )]
+    FfmpegMissing { detail: String },
+
+    /// ffmpeg ran but exited with an error
+    #[error("ffmpeg could not decode {} ({status}): {stderr}", .path.display())]
+    FfmpegFailed {
+        path: std::path::PathBuf,
+        status: String,
+        stderr: String,
+    },
+
+    /// ffmpeg exited successfully without producing any samples
+    #[error("ffmpeg decoded no audio from {}", .path.display())]
+    FfmpegNoAudio { path: std::path::PathBuf },
+
+    #[error("Out of memory running model {model} on device {device}. {suggestion}")]
+    OutOfMemory {
+        device: String,
+        model: String,
+        suggestion: String,
+    },
+
+    #[error("Access to {uri} was refused; check the AWS credentials and permissions: {detail}")]
+    StorageAccessDenied { uri: String, detail: String },
+
+    #[error("Object storage request for {uri} failed: {detail}")]
+    StorageError { uri: String, detail: String },
+
+    #[error("Failed to download {url}: {detail}")]
+    DownloadFailed { url: String, detail: String },
+
+    #[error("{url} is {size} bytes, over the {limit} byte download limit")]
+    DownloadTooLarge { url: String, size: u64, limit: u64 },
+
+    #[error("Checksum mismatch for {url}: expected SHA-256 {expected}, got {actual}")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// An error a worker process reported, with the kind it had there
+    #[error("{message}")]
+    Worker { kind: String, message: String },
+
+    #[error("Worker process failed: {0}")]
+    WorkerCrashed(String),
+
+    /// The input is longer than `--max-duration-minutes` allows; durations are in seconds
+    #[error(
+        "{} is {:.1} minutes long, over the {} minute limit (--max-duration-minutes)",
+        .path.display(),
+        .duration / 60.0,
+        .limit / 60.0
+    )]
+    DurationLimitExceeded {
+        path: std::path::PathBuf,
+        duration: f64,
+        limit: f64,
+    },
+
+    /// Too little free space at `path` for a download or outputs; sizes are in bytes
+    #[error(
+        "Not enough disk space at {}: needs about {}, {} free (--skip-space-check to try anyway)",
+        .path.display(),
+        crate::space::format_size(*.needed),
+        crate::space::format_size(*.available)
+    )]
+    InsufficientDiskSpace {
+        needed: u64,
+        available: u64,
+        path: std::path::PathBuf,
+    },
+
+    /// A transcript from another tool that couldn't be read
+    #[error("Invalid transcript: {0}")]
+    InvalidTranscript(String),
+
+    /// Another error, raised while working on the file at `path`
+    #[error("{}: {source}", .path.display())]
+    WithPath {
+        path: std::path::PathBuf,
+        source: Box<TranscriptionError>,
+    },
+}
+
+impl TranscriptionError {
+    /// Short, stable name of the variant for logs and metrics. A `WithPath` reports the kind
+    /// of the error it wraps, and a `Worker` error the kind it had in the worker.
+    pub fn kind(&self) -> &str {
+        match self {
+            TranscriptionError::WithPath { source, .. } => source.kind(),
+            TranscriptionError::PythonError(_) => "python",
+            TranscriptionError::IoError(_) => "io",
+            TranscriptionError::JsonError(_) => "json",
+            TranscriptionError::InvalidPath(_) => "invalid_path",
+            TranscriptionError::UnsupportedFormat(_) => "unsupported_format",
+            TranscriptionError::ModelInitError(_) => "model_init",
+            TranscriptionError::TranscriptionFailed(_) => "transcription_failed",
+            TranscriptionError::ConfigError(_) => "config",
+            TranscriptionError::OutputUnwritable(_) => "output_unwritable",
+            TranscriptionError::OutputWriteFailed { .. } => "output_write",
+            TranscriptionError::WouldBlock => "would_block",
+            TranscriptionError::Cancelled => "cancelled",
+            TranscriptionError::RequiresVersion { .. } => "requires_version",
+            TranscriptionError::PythonUnavailable(_) => "python_unavailable",
+            TranscriptionError::PackageMissing { .. } => "package_missing",
+            TranscriptionError::ModelDownloadFailed { .. } => "model_download",
+            TranscriptionError::AudioDecodeError { .. } => "audio_decode",
+            TranscriptionError::FfmpegMissing { .. } => "ffmpeg_missing",
+            TranscriptionError::FfmpegFailed { .. } => "ffmpeg_failed",
+            TranscriptionError::FfmpegNoAudio { .. } => "ffmpeg_no_audio",
+            TranscriptionError::OutOfMemory { .. } => "out_of_memory",
+            TranscriptionError::StorageAccessDenied { .. } => "storage_access",
+            TranscriptionError::StorageError { .. } => "storage",
+            TranscriptionError::DownloadFailed { .. } => "download",
+            TranscriptionError::DownloadTooLarge { .. } => "download_too_large",
+            TranscriptionError::ChecksumMismatch { .. } => "checksum_mismatch",
+            TranscriptionError::Worker { kind, .. } => kind,
+            TranscriptionError::WorkerCrashed(_) => "worker_crashed",
+            TranscriptionError::InvalidTranscript(_) => "invalid_transcript",
+            TranscriptionError::DurationLimitExceeded { .. } => "duration_limit",
+            TranscriptionError::InsufficientDiskSpace { .. } => "insufficient_disk_space",
+        }
+    }
+
+    /// An `OutOfMemory` for `config`, suggesting the cheapest changes likely to make it fit
+    pub fn out_of_memory(config: &ModelConfig) -> Self {
+        // ctranslate2 has no int8 path on Metal
+        let int8 = match config.device.as_str() {
+            "cuda" => Some("int8_float16"),
+            "mps" => None,
+            _ => Some("int8"),
+        }
+        .filter(|_| !config.compute_type.starts_with("int8"));
+        let smaller = smaller_model(&config.model_size);
+        let suggestion = match (int8, smaller) {
+            (Some(compute), Some(model)) => format!(
+                "Try --compute-type {} or a smaller model such as --model {}",
+                compute, model
+            ),
+            (Some(compute), None) => format!("Try --compute-type {}", compute),
+            (None, Some(model)) => format!("Try a smaller model such as --model {}", model),
+            (None, None) => {
+                "Free memory held by other processes, or run with --device cpu".to_string()
+            }
+        };
+        TranscriptionError::OutOfMemory {
+            device: config.device.clone(),
+            model: config.model_size.clone(),
+            suggestion,
+        }
+    }
+
+    /// Attach the file this error concerns, unless the error already names it
+    pub fn with_path(self, path: impl Into<std::path::PathBuf>) -> Self {
+        match self {
+            TranscriptionError::WithPath { .. }
+            | TranscriptionError::InvalidPath(_)
+            | TranscriptionError::AudioDecodeError { .. }
+            | TranscriptionError::FfmpegFailed { .. }
+            | TranscriptionError::FfmpegNoAudio { .. }
+            | TranscriptionError::DurationLimitExceeded { .. }
+            | TranscriptionError::InsufficientDiskSpace { .. }
+            | TranscriptionError::OutputUnwritable(_) => self,
+            source => TranscriptionError::WithPath {
+                path: path.into(),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The file this error concerns, if it is known
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            TranscriptionError::WithPath { path, .. }
+            | TranscriptionError::AudioDecodeError { path, .. }
+            | TranscriptionError::FfmpegFailed { path, .. }
+            | TranscriptionError::FfmpegNoAudio { path }
+            | TranscriptionError::DurationLimitExceeded { path, .. }
+            | TranscriptionError::InsufficientDiskSpace { path, .. }
+            | TranscriptionError::OutputUnwritable(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The underlying error with any `WithPath` context removed, for matching on the cause
+    pub fn inner(&self) -> &TranscriptionError {
+        match self {
+            TranscriptionError::WithPath { source, .. } => source.inner(),
+            other => other,
+        }
+    }
+}
+
+/// The next size down from a standard Whisper model, keeping an English-only `.en` suffix
+fn smaller_model(model: &str) -> Option<String> {
+    let (base, suffix) = match model.strip_suffix(".en") {
+        Some(base) => (base, ".en"),
+        None => (model, ""),
+    };
+    let smaller = match base {
+        "turbo" | "large-v3-turbo" | "distil-large-v3" | "distil-large-v2" => "medium",
+        large if large.starts_with("large") => "medium",
+        "medium" | "distil-medium" => "small",
+        "small" | "distil-small" => "base",
+        "base" => "tiny",
+        _ => return None,
+    };
+    // Only tiny through medium come in English-only variants
+    Some(format!("{}{}", smaller, suffix))
+}
+
+/// Lowercased message fragments of allocation failures from Python, ctranslate2, CUDA and Metal
+const OUT_OF_MEMORY_PATTERNS: &[&str] = &[
+    "out of memory",
+    "outofmemory",
+    "failed to allocate",
+    "cannot allocate memory",
+    "insufficient memory",
+    "bad_alloc",
+    "alloc_failed",
+];
+
+/// What a Python exception means, judged from its type and message alone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PythonFailure {
+    /// The interpreter's own standard library can't be loaded, e.g. a wrong `PYTHONHOME`
+    PythonUnavailable,
+    /// Importing this top-level module failed because it isn't installed
+    PackageMissing(String),
+    /// Fetching model files from the Hugging Face Hub failed
+    ModelDownload,
+    /// PyAV/FFmpeg couldn't read the audio
+    AudioDecode,
+    /// Host or device memory ran out
+    OutOfMemory,
+    /// Nothing more specific applies
+    Other,
+}
+
+/// Why an audio file couldn't be decoded, for grouping failures across a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeFailure {
+    /// Zero bytes long
+    Empty,
+    /// Something else, such as text, under an audio extension
+    NotAudio,
+    /// Cut off before the end of its container or stream
+    Truncated,
+    /// A real audio file in a codec FFmpeg can't decode
+    UnsupportedCodec,
+    /// Encrypted, e.g. a DRM-protected purchase from a music store
+    Protected,
+    /// Nothing more specific applies
+    Other,
+}
+
+impl DecodeFailure {
+    /// Short description that reads after a file count, as in "17 files: not actually audio"
+    pub fn label(&self) -> &'static str {
+        match self {
+            DecodeFailure::Empty => "empty file",
+            DecodeFailure::NotAudio => "not actually audio",
+            DecodeFailure::Truncated => "truncated",
+            DecodeFailure::UnsupportedCodec => "codec unsupported",
+            DecodeFailure::Protected => "DRM-protected",
+            DecodeFailure::Other => "could not be decoded",
+        }
+    }
+}
+
+impl std::fmt::Display for DecodeFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Classify the message of a PyAV/FFmpeg decoding error. Encryption and truncation are
+/// checked first, since FFmpeg often reports them as invalid data too.
+pub fn classify_decode_failure(message: &str) -> DecodeFailure {
+    let lowered = message.to_lowercase();
+    let mentions = |patterns: &[&str]| patterns.iter().any(|pattern| lowered.contains(pattern));
+
+    if mentions(&["encrypt", "drm", "decryption", "protected"]) {
+        DecodeFailure::Protected
+    } else if mentions(&[
+        "end of file",
+        "eoferror",
+        "truncat",
+        "moov atom not found",
+        "partial file",
+        "premature",
+    ]) {
+        DecodeFailure::Truncated
+    } else if mentions(&[
+        "decoder not found",
+        "decodernotfound",
+        "codec not",
+        "unsupported codec",
+        "could not find codec",
+        "patch welcome",
+        "not implemented in ffmpeg",
+    ]) {
+        DecodeFailure::UnsupportedCodec
+    } else if mentions(&[
+        "invalid data found",
+        "no audio stream",
+        "does not contain any stream",
+    ]) {
+        DecodeFailure::NotAudio
+    } else {
+        DecodeFailure::Other
+    }
+}
+
+/// Classify a Python exception given its type name (qualified, e.g. `av.error.InvalidDataError`,
+/// or bare) and its message
+pub fn classify_python_error(exception: &str, message: &str) -> PythonFailure {
+    let (module, name) = exception.rsplit_once('.').unwrap_or(("", exception));
+    let lowered = message.to_lowercase();
+
+    if name.ends_with("MemoryError")
+        || OUT_OF_MEMORY_PATTERNS
+            .iter()
+            .any(|pattern| lowered.contains(pattern))
+    {
+        return PythonFailure::OutOfMemory;
+    }
+
+    if name == "ModuleNotFoundError" || name == "ImportError" {
+        let missing = message
+            .split_once("No module named '")
+            .and_then(|(_, rest)| rest.split_once('\''))
+            .map(|(module, _)| module.split('.').next().unwrap_or(module));
+        return match missing {
+            Some("encodings") => PythonFailure::PythonUnavailable,
+            Some(module) => PythonFailure::PackageMissing(module.to_string()),
+            None => PythonFailure::Other,
+        };
+    }
+
+    if module == "av"
+        || module.starts_with("av.")
+        || name == "InvalidDataError"
+        || message.contains("Invalid data found when processing input")
+    {
+        return PythonFailure::AudioDecode;
+    }
+
+    let download_module = ["huggingface_hub", "requests", "urllib3"]
+        .iter()
+        .any(|prefix| module == *prefix || module.starts_with(&format!("{}.", prefix)));
+    if download_module
+        || name.ends_with("EntryNotFoundError")
+        || name == "RepositoryNotFoundError"
+        || name == "HfHubHTTPError"
+        || lowered.contains("locate the files on the hub")
+        || lowered.contains("cached snapshot folder")
+    {
+        return PythonFailure::ModelDownload;
+    }
+
+    PythonFailure::Other
 }
 
 pub type Result<T> = std::result::Result<T, TranscriptionError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_python_error() {
+        let cases = [
+            (
+                "ModuleNotFoundError",
+                "No module named 'faster_whisper'",
+                PythonFailure::PackageMissing("faster_whisper".to_string()),
+            ),
+            (
+                "builtins.ImportError",
+                "No module named 'ctranslate2._ext'",
+                PythonFailure::PackageMissing("ctranslate2".to_string()),
+            ),
+            (
+                "ModuleNotFoundError",
+                "No module named 'encodings'",
+                PythonFailure::PythonUnavailable,
+            ),
+            (
+                "ImportError",
+                "cannot import name 'WhisperModel' from 'faster_whisper'",
+                PythonFailure::Other,
+            ),
+            (
+                "huggingface_hub.errors.LocalEntryNotFoundError",
+                "Cannot find an appropriate cached snapshot folder for the specified revision",
+                PythonFailure::ModelDownload,
+            ),
+            (
+                "requests.exceptions.ConnectionError",
+                "HTTPSConnectionPool(host='huggingface.co', port=443): Max retries exceeded",
+                PythonFailure::ModelDownload,
+            ),
+            (
+                "RuntimeError",
+                "An error happened while trying to locate the files on the Hub",
+                PythonFailure::ModelDownload,
+            ),
+            (
+                "av.error.InvalidDataError",
+                "[Errno 1094995529] Invalid data found when processing input: 'clip.wav'",
+                PythonFailure::AudioDecode,
+            ),
+            (
+                "ValueError",
+                "Invalid model size 'huge', expected one of: tiny, base",
+                PythonFailure::Other,
+            ),
+        ];
+        for (exception, message, expected) in cases {
+            assert_eq!(
+                classify_python_error(exception, message),
+                expected,
+                "{}: {}",
+                exception,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_classify_decode_failure() {
+        let cases = [
+            (
+                "[Errno 1094995529] Invalid data found when processing input: 'notes.wav'",
+                DecodeFailure::NotAudio,
+            ),
+            (
+                "[Errno 1094995529] Invalid data found when processing input: 'clip.m4a'; \
+                 moov atom not found",
+                DecodeFailure::Truncated,
+            ),
+            (
+                "[Errno 541478725] End of file: 'cut.mp3'",
+                DecodeFailure::Truncated,
+            ),
+            (
+                "[Errno 1128613112] Decoder not found: 'voice.amr'",
+                DecodeFailure::UnsupportedCodec,
+            ),
+            (
+                "[Errno 1414092869] Patch welcome: 'song.m4a'",
+                DecodeFailure::UnsupportedCodec,
+            ),
+            (
+                "Error while decoding: stream is encrypted (drms)",
+                DecodeFailure::Protected,
+            ),
+            (
+                "[Errno 5] Input/output error: 'share.wav'",
+                DecodeFailure::Other,
+            ),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(classify_decode_failure(message), expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_out_of_memory_messages() {
+        // Allocation failures as reported by ctranslate2, CUDA, torch, Metal and the OS
+        let captured = [
+            ("RuntimeError", "CUDA failed with error out of memory"),
+            (
+                "RuntimeError",
+                "cuBLAS failed with status CUBLAS_STATUS_ALLOC_FAILED",
+            ),
+            (
+                "RuntimeError",
+                "cuDNN failed with status CUDNN_STATUS_ALLOC_FAILED",
+            ),
+            (
+                "torch.OutOfMemoryError",
+                "CUDA out of memory. Tried to allocate 20.00 MiB (GPU 0; 7.79 GiB total capacity)",
+            ),
+            (
+                "RuntimeError",
+                "MPS backend out of memory (MPS allocated: 17.99 GB, other allocations: 1.02 GB)",
+            ),
+            (
+                "RuntimeError",
+                "[METAL] Command buffer execution failed: Insufficient Memory \
+                 (00000008:kIOGPUCommandBufferCallbackErrorOutOfMemory)",
+            ),
+            ("RuntimeError", "std::bad_alloc"),
+            (
+                "RuntimeError",
+                "Failed to allocate memory for the model weights (1550000000 bytes)",
+            ),
+            (
+                "numpy.core._exceptions._ArrayMemoryError",
+                "Unable to allocate 3.58 GiB for an array with shape (480000000,)",
+            ),
+            ("MemoryError", ""),
+            ("OSError", "[Errno 12] Cannot allocate memory"),
+        ];
+        for (exception, message) in captured {
+            assert_eq!(
+                classify_python_error(exception, message),
+                PythonFailure::OutOfMemory,
+                "{}: {}",
+                exception,
+                message
+            );
+        }
+
+        let not_memory = [
+            (
+                "RuntimeError",
+                "CUDA failed with error no CUDA-capable device is detected",
+            ),
+            (
+                "ValueError",
+                "Requested int8_float16 compute type, but the target device does not support it",
+            ),
+        ];
+        for (exception, message) in not_memory {
+            assert_eq!(
+                classify_python_error(exception, message),
+                PythonFailure::Other,
+                "{}: {}",
+                exception,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_out_of_memory_suggestion() {
+        let suggestion = |model: &str, device: &str, compute_type: &str| {
+            match TranscriptionError::out_of_memory(&ModelConfig::new(model, device, compute_type))
+            {
+                TranscriptionError::OutOfMemory { suggestion, .. } => suggestion,
+                other => panic!("unexpected {:?}", other),
+            }
+        };
+        assert_eq!(
+            suggestion("large-v3", "cuda", "float16"),
+            "Try --compute-type int8_float16 or a smaller model such as --model medium"
+        );
+        assert_eq!(
+            suggestion("medium.en", "mps", "int8"),
+            "Try a smaller model such as --model small.en"
+        );
+        assert_eq!(
+            suggestion("tiny", "cpu", "float32"),
+            "Try --compute-type int8"
+        );
+        assert_eq!(
+            suggestion("tiny", "cuda", "int8_float16"),
+            "Free memory held by other processes, or run with --device cpu"
+        );
+
+        let error =
+            TranscriptionError::out_of_memory(&ModelConfig::new("large-v3", "mps", "float16"));
+        assert_eq!(
+            error.to_string(),
+            "Out of memory running model large-v3 on device mps. \
+             Try a smaller model such as --model medium"
+        );
+    }
+
+    #[test]
+    fn test_with_path_keeps_inner_error() {
+        let error = TranscriptionError::TranscriptionFailed("decoder crashed".to_string())
+            .with_path("calls/monday.wav");
+        assert_eq!(
+            error.to_string(),
+            "calls/monday.wav: Transcription failed: decoder crashed"
+        );
+        assert_eq!(error.kind(), "transcription_failed");
+        assert_eq!(error.path(), Some(std::path::Path::new("calls/monday.wav")));
+        assert!(matches!(
+            error.inner(),
+            TranscriptionError::TranscriptionFailed(_)
+        ));
+        assert!(std::error::Error::source(&error).is_some());
+
+        // Wrapping again, or wrapping an error that already names its file, changes nothing
+        let error = error.with_path("other.wav");
+        assert_eq!(error.path(), Some(std::path::Path::new("calls/monday.wav")));
+        let error = TranscriptionError::InvalidPath("File does not exist: a.wav".to_string())
+            .with_path("a.wav");
+        assert!(matches!(error, TranscriptionError::InvalidPath(_)));
+    }
+}
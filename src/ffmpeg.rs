@@ -0,0 +1,251 @@
+use crate::audio::{downmix, resample_linear};
+use crate::backend::{TranscriptionBackend, SAMPLE_RATE};
+use crate::error::{DecodeFailure, Result, TranscriptionError};
+use crate::types::{TranscriptionOptions, TranscriptionResult};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+/// ffmpeg, looked up on `PATH` unless `WHISPER_FFMPEG_BIN` names another binary
+pub const DEFAULT_BINARY: &str = "ffmpeg";
+pub const BINARY_ENV_VAR: &str = "WHISPER_FFMPEG_BIN";
+/// `TranscriptionResult::audio_decoder` for audio decoded by this module
+pub const DECODER_NAME: &str = "ffmpeg";
+
+/// Decodes audio by running the system's ffmpeg, for containers PyAV can't open
+#[derive(Debug, Clone)]
+pub struct FfmpegDecoder {
+    binary: PathBuf,
+}
+
+impl Default for FfmpegDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FfmpegDecoder {
+    pub fn new() -> Self {
+        let binary = std::env::var_os(BINARY_ENV_VAR)
+            .filter(|bin| !bin.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BINARY));
+        Self { binary }
+    }
+
+    /// Use a specific ffmpeg binary instead of `ffmpeg` from `PATH`
+    pub fn with_binary<P: Into<PathBuf>>(mut self, binary: P) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    /// Decode `path` to mono 16 kHz samples in [-1, 1]
+    pub fn decode(&self, path: &Path) -> Result<Vec<f32>> {
+        self.run(path, None)
+    }
+
+    /// Decode only the first `seconds` of `path`, without holding the rest in memory
+    pub fn decode_head(&self, path: &Path, seconds: f64) -> Result<Vec<f32>> {
+        self.run(path, Some(seconds))
+    }
+
+    fn run(&self, path: &Path, seconds: Option<f64>) -> Result<Vec<f32>> {
+        let mut command = Command::new(&self.binary);
+        command.args(["-nostdin", "-v", "error", "-i"]).arg(path);
+        if let Some(seconds) = seconds {
+            command.arg("-t").arg(seconds.to_string());
+        }
+        command
+            .args([
+                "-ar",
+                &SAMPLE_RATE.to_string(),
+                "-ac",
+                "1",
+                "-f",
+                "wav",
+                "pipe:",
+            ])
+            .stdin(Stdio::null());
+        debug!("Running {:?}", command);
+        let output = command
+            .output()
+            .map_err(|e| TranscriptionError::FfmpegMissing {
+                detail: format!("cannot run {}: {}", self.binary.display(), e),
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(TranscriptionError::FfmpegFailed {
+                path: path.to_path_buf(),
+                status: output.status.to_string(),
+                stderr: last_lines(stderr.trim(), 5),
+            });
+        }
+        match parse_wav(&output.stdout) {
+            Some(samples) if !samples.is_empty() => Ok(samples),
+            _ => Err(TranscriptionError::FfmpegNoAudio {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+}
+
+/// Whether `error` means the backend couldn't read the audio, so ffmpeg is worth a try. Empty
+/// files are left alone, since nothing can decode them.
+pub fn should_fall_back(error: &TranscriptionError) -> bool {
+    match error.inner() {
+        TranscriptionError::AudioDecodeError { reason, .. } => *reason != DecodeFailure::Empty,
+        TranscriptionError::UnsupportedFormat(_) => true,
+        _ => false,
+    }
+}
+
+/// Decode `path` with ffmpeg and transcribe the samples, noting ffmpeg as the decoder
+pub fn transcribe_decoded(
+    backend: &dyn TranscriptionBackend,
+    decoder: &FfmpegDecoder,
+    path: &Path,
+    options: &TranscriptionOptions,
+) -> Result<TranscriptionResult> {
+    info!("Decoding {} with ffmpeg", path.display());
+    let samples = decoder.decode(path)?;
+    let mut result = backend.transcribe_samples(&samples, options)?;
+    result.audio_decoder = Some(DECODER_NAME.to_string());
+    Ok(result)
+}
+
+/// Samples of the 16-bit PCM WAV ffmpeg writes to a pipe. It can't seek back to fill in the
+/// sizes there, so the data chunk runs to the end of the output whatever its header says.
+fn parse_wav(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut format: Option<(u16, u32)> = None;
+    let mut offset = 12;
+    loop {
+        let id = bytes.get(offset..offset + 4)?;
+        let size = u32::from_le_bytes(bytes.get(offset + 4..offset + 8)?.try_into().ok()?);
+        let body = offset + 8;
+        match id {
+            b"fmt " => {
+                let fmt = bytes.get(body..body + 16)?;
+                let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                if u16::from_le_bytes([fmt[0], fmt[1]]) != 1 || bits != 16 {
+                    return None;
+                }
+                let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                format = Some((channels, sample_rate));
+            }
+            b"data" => {
+                let (channels, sample_rate) = format?;
+                let interleaved: Vec<f32> = bytes[body..]
+                    .chunks_exact(2)
+                    .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+                    .collect();
+                let mono = downmix(&interleaved, channels as usize);
+                return Some(resample_linear(&mono, sample_rate, SAMPLE_RATE));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even number of bytes
+        offset = body + size as usize + (size % 2) as usize;
+    }
+}
+
+fn last_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::wav_bytes;
+
+    /// An executable shell script standing in for ffmpeg
+    #[cfg(unix)]
+    fn fake_ffmpeg(dir: &Path, script: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("ffmpeg");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_piped_wav() {
+        let mut bytes = wav_bytes(16000, 1, 4);
+        bytes[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes[44..46].copy_from_slice(&i16::MIN.to_le_bytes());
+        let samples = parse_wav(&bytes).unwrap();
+        assert_eq!(samples, vec![-1.0, 0.0, 0.0, 0.0]);
+
+        assert!(parse_wav(b"not a wav file").is_none());
+        assert_eq!(parse_wav(&wav_bytes(16000, 1, 0)), Some(vec![]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_decode_failures_are_distinct() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("clip.mxf");
+        std::fs::write(&input, b"MXF").unwrap();
+        let kind = |decoder: FfmpegDecoder| decoder.decode(&input).unwrap_err().kind().to_string();
+
+        let missing = FfmpegDecoder::new().with_binary(dir.path().join("no-such-ffmpeg"));
+        assert_eq!(kind(missing), "ffmpeg_missing");
+
+        let failing = fake_ffmpeg(dir.path(), "echo 'clip.mxf: Invalid data' >&2; exit 1");
+        let err = FfmpegDecoder::new()
+            .with_binary(&failing)
+            .decode(&input)
+            .unwrap_err();
+        assert_eq!(err.kind(), "ffmpeg_failed");
+        assert!(err.to_string().contains("Invalid data"), "{}", err);
+
+        let silent = fake_ffmpeg(dir.path(), "exit 0");
+        assert_eq!(
+            kind(FfmpegDecoder::new().with_binary(silent)),
+            "ffmpeg_no_audio"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_decode_reads_piped_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("decoded.wav");
+        std::fs::write(&wav, wav_bytes(8000, 2, 800)).unwrap();
+        let script = format!("cat '{}'", wav.display());
+        let decoder = FfmpegDecoder::new().with_binary(fake_ffmpeg(dir.path(), &script));
+
+        let samples = decoder.decode(&dir.path().join("clip.amr")).unwrap();
+        assert_eq!(samples.len(), 1600);
+
+        // Only a limited decode passes `-t`
+        let script = format!(
+            "case \" $* \" in *' -t 2.5 '*) cat '{}';; *) exit 1;; esac",
+            wav.display()
+        );
+        let decoder = FfmpegDecoder::new().with_binary(fake_ffmpeg(dir.path(), &script));
+        let clip = dir.path().join("clip.amr");
+        assert_eq!(decoder.decode_head(&clip, 2.5).unwrap().len(), 1600);
+        assert_eq!(decoder.decode(&clip).unwrap_err().kind(), "ffmpeg_failed");
+    }
+
+    #[test]
+    fn test_should_fall_back() {
+        let decode = |reason| TranscriptionError::AudioDecodeError {
+            path: "a.mxf".into(),
+            reason,
+            detail: String::new(),
+        };
+        assert!(should_fall_back(&decode(DecodeFailure::Other)));
+        assert!(should_fall_back(&decode(DecodeFailure::UnsupportedCodec)));
+        assert!(!should_fall_back(&decode(DecodeFailure::Empty)));
+        assert!(should_fall_back(
+            &TranscriptionError::UnsupportedFormat("mxf".to_string()).with_path("a.mxf")
+        ));
+        assert!(!should_fall_back(&TranscriptionError::WouldBlock));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::progress::clock;
+use crate::types::TranscriptionSegment;
+use log::info;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// What the transcription has decoded so far
+#[derive(Debug, Default)]
+struct Decoded {
+    segments: usize,
+    /// End of the furthest segment, in seconds
+    latest_end: Option<f64>,
+    beats: usize,
+}
+
+/// Logs that a transcription is still running every `interval`, so supervisors watching
+/// the log of a headless job don't take a long file for a hung one.
+///
+/// A watchdog thread does the logging, so a heartbeat goes out even while no segment
+/// arrives; segments reported through `segment` add where decoding has got to. The
+/// thread stops when the `Heartbeat` is dropped.
+pub struct Heartbeat {
+    decoded: Arc<Mutex<Decoded>>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    pub fn start(label: String, interval: Duration) -> Self {
+        let decoded = Arc::new(Mutex::new(Decoded::default()));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let shared = decoded.clone();
+        let started = Instant::now();
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let mut decoded = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                decoded.beats += 1;
+                let elapsed = started.elapsed().as_secs_f64();
+                let latest = decoded
+                    .latest_end
+                    .map(|end| format!(", decoded to {}", clock(end)))
+                    .unwrap_or_default();
+                // Through `log` rather than `tracing`, so the fields stay structured for
+                // `--log-format json`
+                info!(
+                    event = "heartbeat",
+                    file = label.as_str(),
+                    elapsed = elapsed,
+                    segments = decoded.segments,
+                    latest_segment_end = decoded.latest_end;
+                    "Still transcribing {}: {} elapsed, {} segments{}",
+                    label,
+                    clock(elapsed),
+                    decoded.segments,
+                    latest
+                );
+            }
+        });
+        Self {
+            decoded,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Record a decoded segment for the next heartbeat
+    pub fn segment(&self, segment: &TranscriptionSegment) {
+        let mut decoded = self.decoded.lock().unwrap_or_else(PoisonError::into_inner);
+        decoded.segments += 1;
+        if decoded.latest_end.is_none_or(|end| segment.end > end) {
+            decoded.latest_end = Some(segment.end);
+        }
+    }
+
+    /// Heartbeats logged so far
+    pub fn beats(&self) -> usize {
+        self.decoded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .beats
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beats_until_dropped() {
+        let heartbeat = Heartbeat::start("long.wav".to_string(), Duration::from_millis(20));
+        heartbeat.segment(&TranscriptionSegment {
+            start: 0.0,
+            end: 4.5,
+            text: "hello".to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: -0.1,
+            words: vec![],
+            speaker: None,
+            tokens: None,
+        });
+        std::thread::sleep(Duration::from_millis(110));
+        assert!(heartbeat.beats() >= 2, "{} beats", heartbeat.beats());
+        {
+            let decoded = heartbeat.decoded.lock().unwrap();
+            assert_eq!(decoded.segments, 1);
+            assert_eq!(decoded.latest_end, Some(4.5));
+        }
+
+        // Dropping stops the thread without waiting out the interval
+        let heartbeat = Heartbeat::start("long.wav".to_string(), Duration::from_secs(3600));
+        let start = Instant::now();
+        drop(heartbeat);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
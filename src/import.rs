@@ -0,0 +1,242 @@
+use crate::error::{Result, TranscriptionError};
+use crate::types::{TranscriptionResult, TranscriptionSegment, WordTiming};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct CppOutput {
+    #[serde(default)]
+    result: CppResult,
+    transcription: Vec<CppSegment>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CppResult {
+    #[serde(default)]
+    language: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CppSegment {
+    /// Milliseconds
+    offsets: CppOffsets,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<CppToken>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct CppOffsets {
+    from: u64,
+    to: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CppToken {
+    text: String,
+    /// Token probability, only present with `--output-json-full`
+    #[serde(default)]
+    p: Option<f64>,
+    #[serde(default)]
+    offsets: Option<CppOffsets>,
+}
+
+impl CppToken {
+    /// Special tokens such as [_BEG_] and [_TT_150] carry no text
+    fn is_special(&self) -> bool {
+        self.text.starts_with("[_")
+    }
+}
+
+/// Convert whisper.cpp's `--output-json` output into a `TranscriptionResult`.
+///
+/// Duration is taken from the last segment, as the file doesn't record the audio length.
+/// `avg_logprob` is the mean log probability of the segment's text tokens, and words are
+/// rebuilt from the tokens when they carry their own offsets. whisper.cpp reports neither
+/// a no-speech nor a language probability, so both are 0.
+pub fn whispercpp_json(json: &str) -> Result<TranscriptionResult> {
+    let output: CppOutput = serde_json::from_str(json).map_err(|e| {
+        TranscriptionError::InvalidTranscript(format!("not whisper.cpp JSON output: {}", e))
+    })?;
+
+    let segments: Vec<TranscriptionSegment> = output
+        .transcription
+        .into_iter()
+        .map(|segment| {
+            let logprobs: Vec<f64> = segment
+                .tokens
+                .iter()
+                .filter(|token| !token.is_special())
+                .filter_map(|token| token.p)
+                .filter(|p| *p > 0.0)
+                .map(f64::ln)
+                .collect();
+            let avg_logprob = if logprobs.is_empty() {
+                0.0
+            } else {
+                logprobs.iter().sum::<f64>() / logprobs.len() as f64
+            };
+            TranscriptionSegment {
+                start: seconds(segment.offsets.from),
+                end: seconds(segment.offsets.to),
+                text: segment.text.trim().to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob,
+                words: words(&segment.tokens),
+                speaker: None,
+                tokens: None,
+            }
+        })
+        .collect();
+
+    let full_text = TranscriptionResult::text_from_segments(&segments, None);
+    let duration = segments.last().map(|segment| segment.end).unwrap_or(0.0);
+
+    Ok(TranscriptionResult {
+        language: output.result.language,
+        language_probability: 0.0,
+        duration,
+        segments,
+        full_text,
+        transcription_time: 0.0,
+        real_time_factor: 0.0,
+        ..Default::default()
+    })
+}
+
+fn seconds(milliseconds: u64) -> f64 {
+    milliseconds as f64 / 1000.0
+}
+
+/// Join sub-word tokens into words: a token starting with a space starts a new word. A
+/// word's probability is the mean of its tokens'. Empty unless every text token has offsets.
+fn words(tokens: &[CppToken]) -> Vec<WordTiming> {
+    let text_tokens: Vec<&CppToken> = tokens.iter().filter(|token| !token.is_special()).collect();
+    if text_tokens.is_empty() || text_tokens.iter().any(|token| token.offsets.is_none()) {
+        return Vec::new();
+    }
+    let mut words: Vec<(WordTiming, usize)> = Vec::new();
+    for token in text_tokens {
+        let offsets = token.offsets.unwrap();
+        let probability = token.p.unwrap_or(0.0);
+        match words.last_mut() {
+            Some((word, count)) if !token.text.starts_with(' ') => {
+                word.word.push_str(&token.text);
+                word.end = seconds(offsets.to);
+                word.probability += probability;
+                *count += 1;
+            }
+            _ => words.push((
+                WordTiming {
+                    start: seconds(offsets.from),
+                    end: seconds(offsets.to),
+                    word: token.text.clone(),
+                    probability,
+                    speaker: None,
+                    tokens: None,
+                },
+                1,
+            )),
+        }
+    }
+    words
+        .into_iter()
+        .map(|(mut word, count)| {
+            word.probability /= count as f64;
+            word
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "systeminfo": "AVX = 1 | METAL = 1",
+        "model": {"type": "base"},
+        "params": {"model": "models/ggml-base.bin", "language": "auto", "translate": false},
+        "result": {"language": "de"},
+        "transcription": [
+            {
+                "timestamps": {"from": "00:00:00,000", "to": "00:00:02,500"},
+                "offsets": {"from": 0, "to": 2500},
+                "text": " Guten Tag.",
+                "tokens": [
+                    {"text": "[_BEG_]", "p": 0.9},
+                    {"text": " Guten", "p": 0.8},
+                    {"text": " Tag.", "p": 0.5}
+                ]
+            },
+            {
+                "timestamps": {"from": "00:00:02,500", "to": "00:00:04,000"},
+                "offsets": {"from": 2500, "to": 4000},
+                "text": " Wie geht's?"
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_whispercpp_json() {
+        let result = whispercpp_json(SAMPLE).unwrap();
+        assert_eq!(result.language, "de");
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!(result.segments[0].text, "Guten Tag.");
+        assert_eq!(result.segments[1].start, 2.5);
+        assert_eq!(result.segments[1].end, 4.0);
+        assert_eq!(result.duration, 4.0);
+        assert_eq!(result.full_text, "Guten Tag. Wie geht's?");
+
+        let expected = (0.8f64.ln() + 0.5f64.ln()) / 2.0;
+        assert!((result.segments[0].avg_logprob - expected).abs() < 1e-9);
+        assert_eq!(result.segments[1].avg_logprob, 0.0);
+        // Tokens without offsets give no words
+        assert!(result.segments[0].words.is_empty());
+    }
+
+    #[test]
+    fn test_words_from_token_offsets() {
+        let json = r#"{
+            "transcription": [{
+                "offsets": {"from": 1000, "to": 3000},
+                "text": " Hello Kenobi",
+                "tokens": [
+                    {"text": "[_BEG_]", "p": 0.9, "offsets": {"from": 1000, "to": 1000}},
+                    {"text": " Hello", "p": 0.9, "offsets": {"from": 1000, "to": 1600}},
+                    {"text": " Ken", "p": 0.6, "offsets": {"from": 1700, "to": 2200}},
+                    {"text": "obi", "p": 0.4, "offsets": {"from": 2200, "to": 3000}}
+                ]
+            }]
+        }"#;
+        let words = &whispercpp_json(json).unwrap().segments[0].words;
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[1].word, " Kenobi");
+        assert_eq!((words[1].start, words[1].end), (1.7, 3.0));
+        assert!((words[1].probability - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_errors_name_the_missing_field() {
+        let err = whispercpp_json(r#"{"result": {"language": "en"}}"#).unwrap_err();
+        assert_eq!(err.kind(), "invalid_transcript");
+        assert!(
+            err.to_string().contains("missing field `transcription`"),
+            "{}",
+            err
+        );
+
+        let err = whispercpp_json(r#"{"transcription": [{"text": " Hi"}]}"#).unwrap_err();
+        assert!(
+            err.to_string().contains("missing field `offsets`"),
+            "{}",
+            err
+        );
+
+        // Offsets as strings, as some other tools write them
+        let err = whispercpp_json(
+            r#"{"transcription": [{"offsets": {"from": "0", "to": "10"}, "text": " Hi"}]}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid type"), "{}", err);
+        assert!(whispercpp_json("not json").is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::error::{Result, TranscriptionError};
+use crate::output;
+use crate::types::{TranscriptionResult, TranscriptionSegment};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// First line of a sidecar, naming the audio its segments came from
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    input: PathBuf,
+}
+
+/// Segments saved by an interrupted transcription
+#[derive(Debug, Clone)]
+pub struct Partial {
+    pub input: PathBuf,
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+/// Where segments of the transcript bound for `output` are saved while it is decoded:
+/// `talk.json` becomes `talk.json.partial.jsonl`
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial.jsonl");
+    output.with_file_name(name)
+}
+
+/// Appends each decoded segment to a sidecar as a line of JSON, so a crash loses at most
+/// the segment being written
+pub struct SidecarWriter {
+    file: File,
+}
+
+impl SidecarWriter {
+    /// Start the sidecar at `path` for `input`, holding `saved` segments from an earlier
+    /// run. It replaces any sidecar there in one step, so a crash now can't lose `saved`.
+    pub fn create(path: &Path, input: &Path, saved: &[TranscriptionSegment]) -> Result<Self> {
+        let mut contents = serde_json::to_string(&Header {
+            input: input.to_path_buf(),
+        })?;
+        contents.push('\n');
+        for segment in saved {
+            contents.push_str(&serde_json::to_string(segment)?);
+            contents.push('\n');
+        }
+        output::write_atomic(path, contents)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    pub fn push(&mut self, segment: &TranscriptionSegment) -> Result<()> {
+        let mut line = serde_json::to_string(segment)?;
+        line.push('\n');
+        // One write per line, so a crash can only cut off the last one
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Read the sidecar at `path`. A last line cut off mid-write is dropped; any other line
+/// that isn't a segment makes the sidecar invalid.
+pub fn read_sidecar(path: &Path) -> Result<Partial> {
+    let invalid = |detail: String| {
+        TranscriptionError::InvalidTranscript(format!("{}: {}", path.display(), detail))
+    };
+    let contents = std::fs::read_to_string(path)?;
+    let complete = contents.ends_with('\n');
+    let mut lines = contents.lines();
+    let header: Header = lines
+        .next()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .ok_or_else(|| invalid("missing the header line".to_string()))?;
+    let lines: Vec<&str> = lines.collect();
+    let mut segments = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(segment) => segments.push(segment),
+            Err(_) if index + 1 == lines.len() && !complete => break,
+            Err(e) => return Err(invalid(format!("line {}: {}", index + 2, e))),
+        }
+    }
+    Ok(Partial {
+        input: header.input,
+        segments,
+    })
+}
+
+/// Where to pick up after `saved`: the end of the last segment saved, or `None` when nothing
+/// was. Audio before it is already transcribed.
+pub fn resume_point(saved: &[TranscriptionSegment]) -> Option<f64> {
+    saved
+        .iter()
+        .map(|segment| segment.end)
+        .reduce(f64::max)
+        .filter(|&end| end > 0.0)
+}
+
+/// Put `saved` in front of the segments `resumed` decoded from their resume point on. A
+/// resumed segment mostly before that point repeats speech already saved and is dropped;
+/// one mostly after it is kept whole.
+pub fn stitch(
+    saved: Vec<TranscriptionSegment>,
+    resumed: TranscriptionResult,
+    paragraph_gap: Option<f64>,
+) -> TranscriptionResult {
+    let Some(resume_at) = resume_point(&saved) else {
+        return resumed;
+    };
+    let mut segments = saved;
+    segments.extend(
+        resumed
+            .segments
+            .into_iter()
+            .filter(|segment| (segment.start + segment.end) / 2.0 >= resume_at),
+    );
+    TranscriptionResult {
+        full_text: TranscriptionResult::text_from_segments(&segments, paragraph_gap),
+        segments,
+        ..resumed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn segment(start: f64, end: f64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start,
+            end,
+            text: format!(" {}", text),
+            no_speech_prob: 0.0,
+            avg_logprob: -0.2,
+            words: vec![],
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    fn result(segments: Vec<TranscriptionSegment>) -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration: 30.0,
+            full_text: TranscriptionResult::text_from_segments(&segments, None),
+            segments,
+            transcription_time: 2.0,
+            real_time_factor: 15.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sidecar_survives_a_cut_off_line() {
+        let dir = tempdir().unwrap();
+        let path = sidecar_path(&dir.path().join("talk.json"));
+        assert_eq!(path, dir.path().join("talk.json.partial.jsonl"));
+
+        let mut writer =
+            SidecarWriter::create(&path, Path::new("talk.wav"), &[segment(0.0, 4.0, "One.")])
+                .unwrap();
+        writer.push(&segment(4.0, 9.5, "Two.")).unwrap();
+        drop(writer);
+        // A crash halfway through writing the third segment
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"start":9.5,"end":12"#).unwrap();
+
+        let partial = read_sidecar(&path).unwrap();
+        assert_eq!(partial.input, PathBuf::from("talk.wav"));
+        assert_eq!(partial.segments.len(), 2);
+        assert_eq!(resume_point(&partial.segments), Some(9.5));
+
+        // Starting again from what was read drops the broken line for good
+        SidecarWriter::create(&path, &partial.input, &partial.segments).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().ends_with('\n'));
+        assert_eq!(read_sidecar(&path).unwrap().segments.len(), 2);
+
+        std::fs::write(&path, "{\"input\":\"talk.wav\"}\nnot json\n").unwrap();
+        let err = read_sidecar(&path).unwrap_err();
+        assert_eq!(err.kind(), "invalid_transcript");
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_resume_point() {
+        assert_eq!(resume_point(&[]), None);
+        assert_eq!(resume_point(&[segment(0.0, 0.0, "")]), None);
+        // Segments can arrive slightly out of order; the latest end counts
+        let saved = [segment(0.0, 5.0, "A."), segment(4.0, 4.5, "B.")];
+        assert_eq!(resume_point(&saved), Some(5.0));
+    }
+
+    #[test]
+    fn test_stitch_drops_repeated_speech_at_the_seam() {
+        let saved = vec![segment(0.0, 4.0, "One."), segment(4.0, 10.0, "Two.")];
+        let resumed = result(vec![
+            // Decoding restarted just before the seam and mostly repeats "Two."
+            segment(8.0, 10.5, "Two."),
+            // Straddles the seam but is mostly new
+            segment(9.5, 14.0, "Three."),
+            segment(14.0, 20.0, "Four."),
+        ]);
+        let stitched = stitch(saved, resumed, None);
+        let texts: Vec<_> = stitched.segments.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(texts, ["One.", "Two.", "Three.", "Four."]);
+        assert_eq!(stitched.full_text, "One. Two. Three. Four.");
+        assert_eq!(stitched.duration, 30.0);
+
+        // Nothing saved: the resumed run is the whole transcript
+        let fresh = stitch(Vec::new(), result(vec![segment(0.0, 3.0, "Hi.")]), None);
+        assert_eq!((fresh.segments.len(), fresh.full_text.as_str()), (1, "Hi."));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::backend::{SegmentSink, TranscriptionBackend};
+use crate::error::Result;
+use crate::types::{TranscriptionOptions, TranscriptionResult, TranscriptionSegment};
+use std::io::Write;
+use std::path::Path;
+
+/// Where the segments go in the pretty JSON of a result without any
+const EMPTY_SEGMENTS: &str = "\"segments\": []";
+
+/// Writes a result as the same pretty JSON `--format json` does, one segment at a time, so
+/// the segments never have to be in memory together. Feed it as a `SegmentSink`, then pass
+/// the rest of the result to `finish`.
+pub struct JsonStreamWriter<W: Write> {
+    out: W,
+    /// Everything written before the segments, to check against the finished result
+    head: String,
+    segments: usize,
+}
+
+impl<W: Write> JsonStreamWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            head: String::new(),
+            segments: 0,
+        }
+    }
+
+    /// Close the segment list and write the fields after it from `result`, whose own
+    /// segments are ignored. Returns the writer, flushed.
+    pub fn finish(mut self, result: &TranscriptionResult) -> Result<W> {
+        let without_segments;
+        let rest = if result.segments.is_empty() {
+            result
+        } else {
+            without_segments = TranscriptionResult {
+                segments: Vec::new(),
+                ..result.clone()
+            };
+            &without_segments
+        };
+        let document = serde_json::to_string_pretty(rest)?;
+        let at = document
+            .find(EMPTY_SEGMENTS)
+            .expect("results always serialize their segments");
+        if self.head.is_empty() {
+            // Nothing was streamed, not even the language
+            self.head = document[..at].to_string();
+            self.out.write_all(self.head.as_bytes())?;
+            self.out.write_all(b"\"segments\": [")?;
+        }
+        debug_assert_eq!(
+            self.head,
+            document[..at],
+            "language changed while streaming"
+        );
+        if self.segments > 0 {
+            self.out.write_all(b"\n  ]")?;
+        } else {
+            self.out.write_all(b"]")?;
+        }
+        self.out
+            .write_all(&document.as_bytes()[at + EMPTY_SEGMENTS.len()..])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write> SegmentSink for JsonStreamWriter<W> {
+    fn begin(&mut self, language: &str, language_probability: f64, duration: f64) -> Result<()> {
+        self.head = format!(
+            "{{\n  \"language\": {},\n  \"language_probability\": {},\n  \"duration\": {},\n  ",
+            serde_json::to_string(language)?,
+            serde_json::to_string(&language_probability)?,
+            serde_json::to_string(&duration)?
+        );
+        self.out.write_all(self.head.as_bytes())?;
+        self.out.write_all(b"\"segments\": [")?;
+        Ok(())
+    }
+
+    fn segment(&mut self, segment: &TranscriptionSegment) -> Result<()> {
+        let json = serde_json::to_string_pretty(segment)?;
+        let mut entry = String::with_capacity(json.len() + 64);
+        entry.push_str(if self.segments == 0 { "\n" } else { ",\n" });
+        // Strings escape their newlines, so every line break is the formatter's
+        for (i, line) in json.lines().enumerate() {
+            if i > 0 {
+                entry.push('\n');
+            }
+            entry.push_str("    ");
+            entry.push_str(line);
+        }
+        self.out.write_all(entry.as_bytes())?;
+        self.segments += 1;
+        Ok(())
+    }
+}
+
+/// Transcribe `audio_path` straight into `out` as `--format json` would write it, without
+/// holding every segment in memory. On 15,000 segments with word timings this peaks at
+/// 3.7 MB instead of 66 MB, since only `full_text` grows with the transcript (see
+/// `tests/json_stream_memory.rs`). Returns the result without its segments.
+///
+/// The segments are written as decoded, so speaker labels, replacements and redaction,
+/// which need the whole transcript, aren't applied.
+pub fn transcribe_to_writer<W: Write>(
+    backend: &dyn TranscriptionBackend,
+    audio_path: &Path,
+    options: &TranscriptionOptions,
+    out: W,
+) -> Result<TranscriptionResult> {
+    let mut writer = JsonStreamWriter::new(out);
+    let result = backend.transcribe_path_into(audio_path, options, &mut writer)?;
+    writer.finish(&result)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::Chapter;
+    use crate::output::{self, OutputFormat};
+    use crate::test_support::{word, MockBackend, ResultBuilder};
+
+    fn segment(start: f64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start,
+            end: start + 2.5,
+            text: format!(" {}", text),
+            no_speech_prob: 0.01,
+            avg_logprob: -0.25,
+            words: vec![],
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    fn result(segments: Vec<TranscriptionSegment>) -> TranscriptionResult {
+        ResultBuilder::new()
+            .language_probability(0.97)
+            .segments(segments)
+            .duration(12.0)
+            .transcription_time(1.5)
+            .build()
+    }
+
+    fn streamed(result: &TranscriptionResult) -> String {
+        let mut writer = JsonStreamWriter::new(Vec::new());
+        writer
+            .begin(
+                &result.language,
+                result.language_probability,
+                result.duration,
+            )
+            .unwrap();
+        for segment in &result.segments {
+            writer.segment(segment).unwrap();
+        }
+        String::from_utf8(writer.finish(result).unwrap()).unwrap()
+    }
+
+    fn rendered(result: &TranscriptionResult) -> String {
+        output::render(result, OutputFormat::Json).unwrap()
+    }
+
+    #[test]
+    fn test_matches_the_json_output_byte_for_byte() {
+        let mut quoted = segment(0.0, "She said \"stop\",\nthen left. 🚪");
+        quoted.words = vec![word(" She", 0.0, 0.4)];
+        quoted.speaker = Some("S1".to_string());
+        let mut full = result(vec![quoted, segment(3.0, "Two."), segment(6.0, "Three.")]);
+        full.chapters = Some(vec![Chapter {
+            start: 0.0,
+            end: 12.0,
+            title: "Intro".to_string(),
+        }]);
+        full.cached = true;
+        assert_eq!(streamed(&full), rendered(&full));
+
+        let empty = result(vec![]);
+        assert_eq!(streamed(&empty), rendered(&empty));
+        // A sink that never began still writes the whole document
+        let out = JsonStreamWriter::new(Vec::new()).finish(&empty).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), rendered(&empty));
+    }
+
+    #[test]
+    fn test_default_transcribe_path_into_streams_the_finished_result() {
+        let backend = MockBackend::new()
+            .with_result(result(vec![segment(0.0, "One."), segment(3.0, "Two.")]));
+        let mut out = Vec::new();
+        let summary =
+            transcribe_to_writer(&backend, Path::new("a.wav"), backend.options(), &mut out)
+                .unwrap();
+        assert!(summary.segments.is_empty());
+        assert_eq!(summary.full_text, "One. Two.");
+        let expected = backend
+            .transcribe_path(Path::new("a.wav"), backend.options())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), rendered(&expected));
+    }
+}
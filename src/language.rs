@@ -0,0 +1,419 @@
+use crate::error::{Result, TranscriptionError};
+use crate::manifest::split_csv_line;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Language codes Whisper knows, in its token order
+pub const LANGUAGES: &[&str] = &[
+    "en", "zh", "de", "es", "ru", "ko", "fr", "ja", "pt", "tr", "pl", "ca", "nl", "ar", "sv", "it",
+    "id", "hi", "fi", "vi", "he", "uk", "el", "ms", "cs", "ro", "da", "hu", "ta", "no", "th", "ur",
+    "hr", "bg", "lt", "la", "mi", "ml", "cy", "sk", "te", "fa", "lv", "bn", "sr", "az", "sl", "kn",
+    "et", "mk", "br", "eu", "is", "hy", "ne", "mn", "bs", "kk", "sq", "sw", "gl", "mr", "pa", "si",
+    "km", "sn", "yo", "so", "af", "oc", "ka", "be", "tg", "sd", "gu", "am", "yi", "lo", "uz", "fo",
+    "ht", "ps", "tk", "nn", "mt", "sa", "lb", "my", "bo", "tl", "mg", "as", "tt", "haw", "ln",
+    "ha", "ba", "jw", "su",
+];
+
+/// English names of `LANGUAGES`, as Whisper spells them, in the same order
+pub const LANGUAGE_NAMES: &[&str] = &[
+    "english",
+    "chinese",
+    "german",
+    "spanish",
+    "russian",
+    "korean",
+    "french",
+    "japanese",
+    "portuguese",
+    "turkish",
+    "polish",
+    "catalan",
+    "dutch",
+    "arabic",
+    "swedish",
+    "italian",
+    "indonesian",
+    "hindi",
+    "finnish",
+    "vietnamese",
+    "hebrew",
+    "ukrainian",
+    "greek",
+    "malay",
+    "czech",
+    "romanian",
+    "danish",
+    "hungarian",
+    "tamil",
+    "norwegian",
+    "thai",
+    "urdu",
+    "croatian",
+    "bulgarian",
+    "lithuanian",
+    "latin",
+    "maori",
+    "malayalam",
+    "welsh",
+    "slovak",
+    "telugu",
+    "persian",
+    "latvian",
+    "bengali",
+    "serbian",
+    "azerbaijani",
+    "slovenian",
+    "kannada",
+    "estonian",
+    "macedonian",
+    "breton",
+    "basque",
+    "icelandic",
+    "armenian",
+    "nepali",
+    "mongolian",
+    "bosnian",
+    "kazakh",
+    "albanian",
+    "swahili",
+    "galician",
+    "marathi",
+    "punjabi",
+    "sinhala",
+    "khmer",
+    "shona",
+    "yoruba",
+    "somali",
+    "afrikaans",
+    "occitan",
+    "georgian",
+    "belarusian",
+    "tajik",
+    "sindhi",
+    "gujarati",
+    "amharic",
+    "yiddish",
+    "lao",
+    "uzbek",
+    "faroese",
+    "haitian creole",
+    "pashto",
+    "turkmen",
+    "nynorsk",
+    "maltese",
+    "sanskrit",
+    "luxembourgish",
+    "myanmar",
+    "tibetan",
+    "tagalog",
+    "malagasy",
+    "assamese",
+    "tatar",
+    "hawaiian",
+    "lingala",
+    "hausa",
+    "bashkir",
+    "javanese",
+    "sundanese",
+];
+
+/// Whisper's name for a language code: `de` -> `german`
+pub fn language_name(code: &str) -> Option<&'static str> {
+    let code = normalize_code(code);
+    LANGUAGES
+        .iter()
+        .position(|&known| known == code)
+        .map(|index| LANGUAGE_NAMES[index])
+}
+
+/// Recorded when `allowed_languages` overrode the model's own language detection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageOverride {
+    /// Language the model detected before the override
+    pub detected: String,
+    pub detected_probability: f64,
+}
+
+/// Normalise a user-supplied language code for comparison (`EN ` -> `en`)
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_lowercase()
+}
+
+/// Parse a comma-separated list such as `en,es,pt`, dropping empty entries
+pub fn parse_language_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(normalize_code)
+        .filter(|code| !code.is_empty())
+        .collect()
+}
+
+/// One rule of a `--language-map`
+#[derive(Debug, Clone)]
+struct MapRule {
+    line: usize,
+    pattern: MapPattern,
+    language: String,
+}
+
+#[derive(Debug, Clone)]
+enum MapPattern {
+    /// An exact file, resolved against the map's directory
+    Path(PathBuf),
+    /// A glob matched against the path relative to the map's directory, or only against
+    /// the file name when it has no `/`
+    Glob { regex: Regex, file_name_only: bool },
+}
+
+/// Per-file language hints for a batch: `path,language` rows and `pattern=language` globs.
+///
+/// Paths and patterns are relative to the map file's directory, `*` and `?` stay within one
+/// path component and `**` crosses them. The first matching rule wins; unmatched files are
+/// auto-detected.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageMap {
+    base_dir: PathBuf,
+    rules: Vec<MapRule>,
+}
+
+impl LanguageMap {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Language of the first rule matching `input`
+    pub fn language_for(&self, input: &Path) -> Option<&str> {
+        let input = std::path::absolute(input).ok()?;
+        let relative = input.strip_prefix(&self.base_dir).ok().map(slash_path);
+        let file_name = input.file_name().map(|name| name.to_string_lossy());
+        self.rules
+            .iter()
+            .find(|rule| match &rule.pattern {
+                MapPattern::Path(path) => *path == input,
+                MapPattern::Glob {
+                    regex,
+                    file_name_only: true,
+                } => file_name
+                    .as_deref()
+                    .is_some_and(|name| regex.is_match(name)),
+                MapPattern::Glob { regex, .. } => relative
+                    .as_deref()
+                    .is_some_and(|relative| regex.is_match(relative)),
+            })
+            .map(|rule| {
+                debug!(
+                    "{}: language {} from map line {}",
+                    input.display(),
+                    rule.language,
+                    rule.line
+                );
+                rule.language.as_str()
+            })
+    }
+}
+
+/// Parse a language map. Blank lines, `#` comments and a `path,language` header are
+/// skipped; unknown language codes are an error naming the line.
+pub fn parse_language_map(contents: &str, base_dir: &Path) -> Result<LanguageMap> {
+    let base_dir = std::path::absolute(base_dir)?;
+    let mut rules = Vec::new();
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line = index + 1;
+        let row = raw_line.trim();
+        if row.is_empty() || row.starts_with('#') {
+            continue;
+        }
+
+        let (pattern, language) = if row.contains(',') {
+            let fields = split_csv_line(row).map_err(|e| map_error(line, &e))?;
+            match fields.as_slice() {
+                [path, language] => (path.trim().to_string(), language.trim().to_string()),
+                _ => return Err(map_error(line, "expected path,language")),
+            }
+        } else if let Some((pattern, language)) = row.rsplit_once('=') {
+            (pattern.trim().to_string(), language.trim().to_string())
+        } else {
+            return Err(map_error(
+                line,
+                "expected path,language or pattern=language",
+            ));
+        };
+        if rules.is_empty()
+            && pattern.eq_ignore_ascii_case("path")
+            && language.eq_ignore_ascii_case("language")
+        {
+            continue;
+        }
+        if pattern.is_empty() {
+            return Err(map_error(line, "missing path"));
+        }
+
+        let language = normalize_code(&language);
+        if !LANGUAGES.contains(&language.as_str()) {
+            return Err(map_error(
+                line,
+                &format!("unknown language code '{}'", language),
+            ));
+        }
+        let pattern = if pattern.contains(['*', '?']) {
+            MapPattern::Glob {
+                regex: glob_regex(&pattern),
+                file_name_only: !pattern.contains('/'),
+            }
+        } else {
+            MapPattern::Path(base_dir.join(&pattern))
+        };
+        rules.push(MapRule {
+            line,
+            pattern,
+            language,
+        });
+    }
+    Ok(LanguageMap { base_dir, rules })
+}
+
+pub fn load_language_map<P: AsRef<Path>>(path: P) -> Result<LanguageMap> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        TranscriptionError::ConfigError(format!(
+            "Cannot read language map {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    parse_language_map(&contents, base_dir).map_err(|e| match e {
+        TranscriptionError::ConfigError(msg) => {
+            TranscriptionError::ConfigError(format!("{}: {}", path.display(), msg))
+        }
+        other => other,
+    })
+}
+
+fn map_error(line: usize, message: &str) -> TranscriptionError {
+    TranscriptionError::ConfigError(format!("language map line {}: {}", line, message))
+}
+
+/// `path` with `/` separators, for glob matching
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn glob_regex(pattern: &str) -> Regex {
+    let pattern = pattern.trim_start_matches("./");
+    let mut regex = String::from("^");
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            regex.push_str("(?:.*/)?");
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            regex.push_str(".*");
+            rest = after;
+            continue;
+        }
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            other => regex.push_str(&regex::escape(&other.to_string())),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
+}
+
+/// Most probable language among `allowed`, with its probability renormalised over the
+/// allowed set.
+///
+/// `probabilities` are the detector's scores for every language it knows. None when no
+/// allowed language appears in them; ties go to the earlier entry of `allowed`.
+pub fn rerank(probabilities: &[(String, f64)], allowed: &[String]) -> Option<(String, f64)> {
+    let candidates: Vec<(&String, f64)> = allowed
+        .iter()
+        .filter_map(|code| {
+            probabilities
+                .iter()
+                .find(|(language, _)| language == code)
+                .map(|(_, probability)| (code, *probability))
+        })
+        .collect();
+    let total: f64 = candidates.iter().map(|(_, probability)| probability).sum();
+    let (best, probability) =
+        candidates
+            .into_iter()
+            .reduce(|best, next| if next.1 > best.1 { next } else { best })?;
+    let probability = if total > 0.0 {
+        probability / total
+    } else {
+        0.0
+    };
+    Some((best.clone(), probability))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probabilities() -> Vec<(String, f64)> {
+        [("gl", 0.5), ("pt", 0.3), ("es", 0.1), ("en", 0.05)]
+            .into_iter()
+            .map(|(code, p)| (code.to_string(), p))
+            .collect()
+    }
+
+    #[test]
+    fn test_language_map_rules() {
+        let map = parse_language_map(
+            "path,language\n# archive\nfr/**=fr\n*.es.wav = ES\n\"fr/english, really.wav\",en\n",
+            Path::new("/data"),
+        )
+        .unwrap();
+        let language = |path: &str| map.language_for(Path::new(path));
+        assert_eq!(language("/data/fr/2021/a.wav"), Some("fr"));
+        assert_eq!(language("/other/talk.es.wav"), Some("es"));
+        assert_eq!(language("/data/de/talk.wav"), None);
+        // Earlier rules win
+        assert_eq!(language("/data/fr/english, really.wav"), Some("fr"));
+        assert_eq!(language("/data/fr"), None);
+    }
+
+    #[test]
+    fn test_language_map_errors_name_the_line() {
+        let err = parse_language_map("a.wav,en\nb.wav,xx\n", Path::new("/data")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("line 2: unknown language code 'xx'"));
+        assert!(parse_language_map("a.wav\n", Path::new("/data")).is_err());
+        assert!(parse_language_map("a.wav,en,extra\n", Path::new("/data")).is_err());
+    }
+
+    #[test]
+    fn test_language_names() {
+        assert_eq!(LANGUAGE_NAMES.len(), LANGUAGES.len());
+        assert_eq!(language_name("en"), Some("english"));
+        assert_eq!(language_name(" HAW"), Some("hawaiian"));
+        assert_eq!(language_name("su"), Some("sundanese"));
+        assert_eq!(language_name("xx"), None);
+    }
+
+    #[test]
+    fn test_rerank_restricts_to_allowed() {
+        let allowed = parse_language_list("EN, es,,pt");
+        assert_eq!(allowed, vec!["en", "es", "pt"]);
+
+        let (language, probability) = rerank(&probabilities(), &allowed).unwrap();
+        assert_eq!(language, "pt");
+        assert!((probability - 0.3 / 0.45).abs() < 1e-9);
+
+        assert_eq!(rerank(&probabilities(), &["fr".to_string()]), None);
+        let tied = vec![("es".to_string(), 0.0), ("en".to_string(), 0.0)];
+        assert_eq!(rerank(&tied, &allowed), Some(("en".to_string(), 0.0)));
+    }
+}
@@ -1,9 +1,74 @@
+pub mod align;
+pub mod archive;
+pub mod audio;
+pub mod backend;
+pub mod backup;
+pub mod batch;
 pub mod benchmark;
+pub mod cache;
+#[cfg(feature = "candle")]
+pub mod candle;
+pub mod channels;
+pub mod chapters;
+pub mod confidence;
+pub mod config;
+#[cfg(feature = "diarization")]
+pub mod diarize;
+pub mod download;
 pub mod error;
+pub mod ffmpeg;
+pub mod heartbeat;
+pub mod import;
+pub mod incremental;
+pub mod json_stream;
+pub mod language;
+pub mod listen;
+pub mod logging;
+pub mod manifest;
+pub mod merge;
+#[cfg(feature = "mic")]
+pub mod mic;
+pub mod model_manager;
+pub mod models;
+pub mod openai;
+pub mod output;
+pub mod plan;
+pub mod pool;
+pub mod probe;
+pub mod progress;
+pub mod python_env;
+pub mod queue;
+pub mod redact;
+pub mod replace;
+pub mod rss;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod schedule;
+pub mod search;
+pub mod space;
+pub mod speakers;
+pub mod state;
+pub mod stats;
+pub mod stitch;
+pub mod template;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod throttle;
+pub mod timing;
 pub mod transcriber;
 pub mod types;
+pub mod uncertain;
+pub mod vad;
+pub mod version;
+pub mod watch;
+#[cfg(feature = "whispercpp")]
+pub mod whispercpp;
+pub mod window;
 
+pub use backend::TranscriptionBackend;
+pub use batch::{BatchOptions, BatchReport};
 pub use benchmark::BenchmarkResult;
 pub use error::TranscriptionError;
-pub use transcriber::FasterWhisperTranscriber;
-pub use types::{TranscriptionResult, TranscriptionSegment};
+pub use output::OutputFormat;
+pub use transcriber::{EnvironmentInfo, FasterWhisperTranscriber, WarmupReport};
+pub use types::{TranscriptionOptions, TranscriptionResult, TranscriptionSegment};
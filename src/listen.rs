@@ -0,0 +1,508 @@
+use crate::backend::SAMPLE_RATE;
+use crate::types::{TranscriptionResult, TranscriptionSegment, WordTiming};
+use std::time::Duration;
+
+/// A word that reappears this soon after the committed part ends is taken to be the same
+/// word heard again by the overlapping window
+const REPEAT_TOLERANCE: f64 = 0.3;
+
+#[derive(Debug, Clone)]
+pub struct ListenOptions {
+    /// Length of audio transcribed at a time
+    pub window: Duration,
+    /// How much of each window is transcribed again at the start of the next, so words cut
+    /// at a window boundary are heard whole
+    pub overlap: Duration,
+}
+
+impl Default for ListenOptions {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(10),
+            overlap: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ListenOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window.is_zero() {
+            return Err("Window length must be greater than zero".to_string());
+        }
+        if self.overlap >= self.window {
+            return Err(format!(
+                "Overlap ({:.1}s) must be shorter than the window ({:.1}s)",
+                self.overlap.as_secs_f64(),
+                self.window.as_secs_f64()
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn to_samples(duration: Duration) -> usize {
+    (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as usize
+}
+
+fn to_seconds(samples: usize) -> f64 {
+    samples as f64 / SAMPLE_RATE as f64
+}
+
+/// A stretch of the stream to transcribe
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    /// Seconds from the start of the stream to the first sample
+    pub start: f64,
+    /// Mono samples at `SAMPLE_RATE`
+    pub samples: Vec<f32>,
+    /// No later window covers audio before this time, so words ending earlier are final
+    pub commit_until: f64,
+}
+
+impl Window {
+    pub fn end(&self) -> f64 {
+        self.start + to_seconds(self.samples.len())
+    }
+}
+
+/// Buffers a continuous stream of samples and cuts it into overlapping windows
+#[derive(Debug)]
+pub struct RollingWindow {
+    window_samples: usize,
+    overlap_samples: usize,
+    buffer: Vec<f32>,
+    /// Stream position of `buffer[0]`, in samples
+    offset: usize,
+    /// Leading samples of `buffer` that a window has already covered
+    covered: usize,
+}
+
+impl RollingWindow {
+    pub fn new(options: &ListenOptions) -> Self {
+        Self {
+            window_samples: to_samples(options.window).max(1),
+            overlap_samples: to_samples(options.overlap),
+            buffer: Vec::new(),
+            offset: 0,
+            covered: 0,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.buffer.extend_from_slice(samples);
+    }
+
+    /// The next full window, once enough audio has arrived. The last `overlap` of it stays
+    /// buffered as the start of the following window.
+    pub fn next_window(&mut self) -> Option<Window> {
+        if self.buffer.len() < self.window_samples {
+            return None;
+        }
+        let samples = self.buffer[..self.window_samples].to_vec();
+        let start = to_seconds(self.offset);
+        let advance = self.window_samples - self.overlap_samples;
+        self.buffer.drain(..advance);
+        self.offset += advance;
+        self.covered = self.overlap_samples;
+        Some(Window {
+            start,
+            samples,
+            commit_until: to_seconds(self.offset),
+        })
+    }
+
+    /// Whatever is still buffered as one final, possibly short, window. `None` when no audio
+    /// arrived since the last window.
+    pub fn flush(&mut self) -> Option<Window> {
+        if self.buffer.len() <= self.covered {
+            return None;
+        }
+        let samples = std::mem::take(&mut self.buffer);
+        let start = to_seconds(self.offset);
+        self.offset += samples.len();
+        self.covered = 0;
+        Some(Window {
+            start,
+            samples,
+            commit_until: f64::INFINITY,
+        })
+    }
+
+    /// Seconds of audio received so far
+    pub fn duration(&self) -> f64 {
+        to_seconds(self.offset + self.buffer.len())
+    }
+}
+
+/// What one window changed in the running transcript
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StitchUpdate {
+    /// Words that became final with this window
+    pub committed: Vec<WordTiming>,
+    /// Provisional words the next window may still revise
+    pub tail: Vec<WordTiming>,
+}
+
+impl StitchUpdate {
+    pub fn committed_text(&self) -> String {
+        words_text(&self.committed)
+    }
+
+    pub fn tail_text(&self) -> String {
+        words_text(&self.tail)
+    }
+}
+
+fn words_text<'a>(words: impl IntoIterator<Item = &'a WordTiming>) -> String {
+    words
+        .into_iter()
+        .map(|w| w.word.as_str())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn normalized(word: &str) -> String {
+    word.trim()
+        .trim_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase()
+}
+
+/// Segment a stitched word came from, minus its words
+#[derive(Debug, Clone)]
+struct Source {
+    segment: TranscriptionSegment,
+    /// The backend reported word timings, as opposed to one pseudo-word for the segment
+    timed: bool,
+}
+
+/// Joins the results of overlapping windows into one transcript.
+///
+/// Words are placed on the stream's timeline by their timestamps. A window's words before the
+/// committed point were already taken from an earlier window and are dropped; words in its
+/// trailing overlap stay provisional until the next window, which heard them with more
+/// context, replaces them.
+#[derive(Debug, Default)]
+pub struct TranscriptStitcher {
+    sources: Vec<Source>,
+    /// Committed words and the index of their source segment
+    committed: Vec<(WordTiming, usize)>,
+    tail: Vec<(WordTiming, usize)>,
+    committed_until: f64,
+    /// Language, probability and audio seconds of each window
+    languages: Vec<(String, f64, f64)>,
+    transcription_time: f64,
+    end: f64,
+}
+
+impl TranscriptStitcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a window's result, which has timestamps relative to the window start
+    pub fn push(&mut self, window: &Window, result: &TranscriptionResult) -> StitchUpdate {
+        let offset = window.start;
+        let mut words = Vec::new();
+        for segment in &result.segments {
+            let index = self.sources.len();
+            self.sources.push(Source {
+                segment: TranscriptionSegment {
+                    start: segment.start + offset,
+                    end: segment.end + offset,
+                    words: Vec::new(),
+                    ..segment.clone()
+                },
+                timed: !segment.words.is_empty(),
+            });
+            if segment.words.is_empty() {
+                if !segment.text.trim().is_empty() {
+                    let word = WordTiming {
+                        start: segment.start + offset,
+                        end: segment.end + offset,
+                        word: format!(" {}", segment.text.trim()),
+                        probability: segment.avg_logprob.exp(),
+                        speaker: None,
+                        tokens: None,
+                    };
+                    words.push((word, index));
+                }
+                continue;
+            }
+            for word in &segment.words {
+                let word = WordTiming {
+                    start: word.start + offset,
+                    end: word.end + offset,
+                    ..word.clone()
+                };
+                words.push((word, index));
+            }
+        }
+
+        let midpoint = |word: &WordTiming| (word.start + word.end) / 2.0;
+        let mut words: Vec<_> = words
+            .into_iter()
+            .filter(|(word, _)| midpoint(word) >= self.committed_until)
+            .collect();
+        // A word straddling the committed point can be heard by both windows
+        while let (Some((first, _)), Some((last, _))) = (words.first(), self.committed.last()) {
+            let repeated = normalized(&first.word) == normalized(&last.word)
+                && first.start < self.committed_until + REPEAT_TOLERANCE;
+            if !repeated {
+                break;
+            }
+            words.remove(0);
+        }
+
+        let (committed, tail): (Vec<_>, Vec<_>) = words
+            .into_iter()
+            .partition(|(word, _)| midpoint(word) < window.commit_until);
+        self.committed_until = self
+            .committed_until
+            .max(window.commit_until.min(window.end()));
+        self.committed.extend(committed.iter().cloned());
+        self.tail = tail;
+
+        self.languages.push((
+            result.language.clone(),
+            result.language_probability,
+            window.end() - window.start,
+        ));
+        self.transcription_time += result.transcription_time;
+        self.end = self.end.max(window.end());
+
+        StitchUpdate {
+            committed: committed.into_iter().map(|(word, _)| word).collect(),
+            tail: self.tail.iter().map(|(word, _)| word.clone()).collect(),
+        }
+    }
+
+    /// Committed and provisional text so far
+    pub fn text(&self) -> String {
+        words_text(
+            self.committed
+                .iter()
+                .chain(self.tail.iter())
+                .map(|(word, _)| word),
+        )
+    }
+
+    /// Commit the provisional tail and assemble the consolidated transcript
+    pub fn finish(mut self) -> TranscriptionResult {
+        let tail = std::mem::take(&mut self.tail);
+        self.committed.extend(tail);
+
+        let mut segments: Vec<TranscriptionSegment> = Vec::new();
+        let mut current: Option<usize> = None;
+        for (word, index) in self.committed {
+            let source = &self.sources[index];
+            if current != Some(index) {
+                current = Some(index);
+                segments.push(TranscriptionSegment {
+                    start: word.start,
+                    end: word.end,
+                    text: String::new(),
+                    words: Vec::new(),
+                    ..source.segment.clone()
+                });
+            }
+            let segment = segments.last_mut().expect("pushed above");
+            segment.end = word.end;
+            segment.text.push_str(&word.word);
+            if source.timed {
+                segment.words.push(word);
+            }
+        }
+        for segment in &mut segments {
+            segment.text = segment.text.trim().to_string();
+        }
+        let full_text = segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut totals: Vec<(&str, f64, f64)> = Vec::new();
+        for (language, probability, seconds) in &self.languages {
+            match totals.iter_mut().find(|(l, _, _)| l == language) {
+                Some((_, weighted, total)) => {
+                    *weighted += probability * seconds;
+                    *total += seconds;
+                }
+                None => totals.push((language, probability * seconds, *seconds)),
+            }
+        }
+        let (language, language_probability) = totals
+            .iter()
+            .fold(None::<&(&str, f64, f64)>, |best, entry| match best {
+                Some(best) if best.2 >= entry.2 => Some(best),
+                _ => Some(entry),
+            })
+            .map(|&(language, weighted, total)| {
+                let probability = if total > 0.0 { weighted / total } else { 0.0 };
+                (language.to_string(), probability)
+            })
+            .unwrap_or_default();
+
+        let mut result = TranscriptionResult {
+            language,
+            language_probability,
+            duration: self.end,
+            segments,
+            full_text,
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        };
+        result.calculate_real_time_factor(self.transcription_time);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(start: f64, end: f64, text: &str) -> WordTiming {
+        WordTiming {
+            start,
+            end,
+            word: format!(" {}", text),
+            probability: 0.9,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    fn result(words: Vec<WordTiming>, duration: f64) -> TranscriptionResult {
+        let text = words_text(&words);
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration,
+            segments: vec![TranscriptionSegment {
+                start: words.first().map(|w| w.start).unwrap_or(0.0),
+                end: words.last().map(|w| w.end).unwrap_or(0.0),
+                text: text.clone(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words,
+                speaker: None,
+                tokens: None,
+            }],
+            full_text: text,
+            transcription_time: 1.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn options(window: u64, overlap: u64) -> ListenOptions {
+        ListenOptions {
+            window: Duration::from_secs(window),
+            overlap: Duration::from_secs(overlap),
+        }
+    }
+
+    #[test]
+    fn test_options_validation() {
+        assert!(ListenOptions::default().validate().is_ok());
+        assert!(options(0, 0).validate().is_err());
+        assert!(options(4, 4).validate().is_err());
+    }
+
+    #[test]
+    fn test_rolling_windows() {
+        let mut rolling = RollingWindow::new(&options(4, 1));
+        let second = SAMPLE_RATE as usize;
+        rolling.push(&vec![0.0; 3 * second]);
+        assert!(rolling.next_window().is_none());
+
+        rolling.push(&vec![0.0; 4 * second]);
+        let first = rolling.next_window().unwrap();
+        assert_eq!(
+            (first.start, first.end(), first.commit_until),
+            (0.0, 4.0, 3.0)
+        );
+        let next = rolling.next_window().unwrap();
+        assert_eq!((next.start, next.end(), next.commit_until), (3.0, 7.0, 6.0));
+        assert!(rolling.next_window().is_none());
+
+        // Only the overlap is left, and it was already transcribed
+        assert!(rolling.flush().is_none());
+
+        rolling.push(&vec![0.0; second / 2]);
+        let last = rolling.flush().unwrap();
+        assert_eq!((last.start, last.end()), (6.0, 7.5));
+        assert_eq!(rolling.duration(), 7.5);
+    }
+
+    #[test]
+    fn test_stitch_overlapping_windows() {
+        let mut rolling = RollingWindow::new(&options(4, 1));
+        rolling.push(&vec![0.0; 7 * SAMPLE_RATE as usize]);
+        let first = rolling.next_window().unwrap();
+        let second = rolling.next_window().unwrap();
+        let mut stitcher = TranscriptStitcher::new();
+
+        let update = stitcher.push(
+            &first,
+            &result(
+                vec![
+                    word(0.5, 1.0, "the"),
+                    word(1.0, 2.0, "quick"),
+                    word(2.2, 2.9, "brown"),
+                    word(3.4, 4.0, "fo"),
+                ],
+                4.0,
+            ),
+        );
+        assert_eq!(update.committed_text(), "the quick brown");
+        assert_eq!(update.tail_text(), "fo");
+
+        // The second window starts at 3s; it re-hears "brown" at its very start and
+        // revises the cut-off tail
+        let update = stitcher.push(
+            &second,
+            &result(
+                vec![
+                    word(0.0, 0.1, "brown"),
+                    word(0.4, 1.0, "fox"),
+                    word(1.1, 1.6, "jumps"),
+                    word(3.2, 3.8, "over"),
+                ],
+                4.0,
+            ),
+        );
+        assert_eq!(update.committed_text(), "fox jumps");
+        assert_eq!(update.tail_text(), "over");
+        assert_eq!(stitcher.text(), "the quick brown fox jumps over");
+
+        let transcript = stitcher.finish();
+        assert_eq!(transcript.full_text, "the quick brown fox jumps over");
+        assert_eq!(transcript.segments.len(), 2);
+        assert_eq!(transcript.segments[1].start, 3.4);
+        assert_eq!(transcript.segments[1].end, 6.8);
+        assert_eq!(transcript.duration, 7.0);
+        assert_eq!(transcript.transcription_time, 2.0);
+        assert_eq!(transcript.language, "en");
+    }
+
+    #[test]
+    fn test_segments_without_word_timings() {
+        let mut rolling = RollingWindow::new(&options(4, 1));
+        rolling.push(&vec![0.0; 2 * SAMPLE_RATE as usize]);
+        let window = rolling.flush().unwrap();
+        let mut untimed = result(vec![], 2.0);
+        untimed.segments[0].start = 0.2;
+        untimed.segments[0].end = 1.8;
+        untimed.segments[0].text = "hello there".to_string();
+
+        let mut stitcher = TranscriptStitcher::new();
+        let update = stitcher.push(&window, &untimed);
+        assert_eq!(update.committed_text(), "hello there");
+        let transcript = stitcher.finish();
+        assert_eq!(transcript.full_text, "hello there");
+        assert!(transcript.segments[0].words.is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use log::kv::{self, Key, Value, VisitSource};
+use log::Record;
+use serde_json::{Map, Number};
+use std::str::FromStr;
+
+/// How log records are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// env_logger's usual human-readable lines
+    #[default]
+    Human,
+    /// One JSON object per line, for log aggregators
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "human" | "text" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!(
+                "Invalid log format: {} (expected human or json)",
+                other
+            )),
+        }
+    }
+}
+
+struct FieldCollector<'a>(&'a mut Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.insert(key.as_str().to_string(), json_value(&value));
+        Ok(())
+    }
+}
+
+fn json_value(value: &Value<'_>) -> serde_json::Value {
+    if let Some(b) = value.to_bool() {
+        serde_json::Value::Bool(b)
+    } else if let Some(n) = value.to_i64() {
+        serde_json::Value::Number(n.into())
+    } else if let Some(n) = value.to_u64() {
+        serde_json::Value::Number(n.into())
+    } else if let Some(n) = value.to_f64().and_then(Number::from_f64) {
+        serde_json::Value::Number(n)
+    } else {
+        serde_json::Value::String(value.to_string())
+    }
+}
+
+/// Render a record as a single JSON line: `timestamp`, `level`, `target` and `message`,
+/// followed by the record's key-value fields (e.g. `file`, `model`, `elapsed`)
+pub fn json_line(record: &Record<'_>, timestamp: &str) -> String {
+    let mut object = Map::new();
+    object.insert("timestamp".to_string(), timestamp.into());
+    object.insert("level".to_string(), record.level().as_str().into());
+    object.insert("target".to_string(), record.target().into());
+    object.insert("message".to_string(), record.args().to_string().into());
+
+    let mut fields = Map::new();
+    // Visiting an in-memory source can't fail
+    let _ = record.key_values().visit(&mut FieldCollector(&mut fields));
+    for (key, value) in fields {
+        // Fields never overwrite the fixed keys
+        object.entry(key).or_insert(value);
+    }
+
+    serde_json::Value::Object(object).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_json_line_with_fields() {
+        let fields: [(&str, Value); 5] = [
+            ("event", Value::from("file_completed")),
+            ("file", Value::from("talk.wav")),
+            ("segments", Value::from(12u64)),
+            ("elapsed", Value::from(1.5f64)),
+            ("level", Value::from("not the real level")),
+        ];
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("✓ Completed: {}", "talk.wav"))
+                .level(Level::Info)
+                .target("rust_whisper_app")
+                .key_values(&fields)
+                .build(),
+            "2024-01-01T00:00:00.000Z",
+        );
+
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["timestamp"], "2024-01-01T00:00:00.000Z");
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["message"], "✓ Completed: talk.wav");
+        assert_eq!(json["event"], "file_completed");
+        assert_eq!(json["file"], "talk.wav");
+        assert_eq!(json["segments"], 12);
+        assert_eq!(json["elapsed"], 1.5);
+    }
+
+    #[test]
+    fn test_json_line_escapes_message() {
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("quote \" and\nnewline"))
+                .level(Level::Warn)
+                .build(),
+            "t",
+        );
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["message"], "quote \" and\nnewline");
+        assert_eq!(json["level"], "WARN");
+    }
+
+    #[test]
+    fn test_log_format_parsing() {
+        assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+        assert_eq!("human".parse(), Ok(LogFormat::Human));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
@@ -1,340 +1,4273 @@
 use anyhow::Result;
-use clap::{Arg, Command};
-use futures::future;
-use log::{error, info, warn};
+use clap::{Arg, ArgMatches, Command};
+use log::{error, info, warn, LevelFilter};
+#[cfg(feature = "diarization")]
+use rust_whisper_app::diarize::Diarizer;
+#[cfg(feature = "s3")]
+use rust_whisper_app::s3::{S3Client, S3Uri};
 use rust_whisper_app::{
-    benchmark::Benchmark, transcriber::FasterWhisperTranscriber, types::ModelConfig,
+    align,
+    archive::{self, ArchiveCompression},
+    backend::{self, TranscriptionBackend},
+    backup::{BackupOptions, BackupStyle},
+    batch::{self, BatchReport, FileOutcome, FileStatus},
+    benchmark::{
+        self, Benchmark, BenchmarkDiff, BenchmarkReport, BenchmarkResultSet, BenchmarkSort,
+        RowFilter, SortOrder, SystemInfo,
+    },
+    cache::{CachedBackend, ResultCache},
+    chapters,
+    confidence::{self, ColorChoice, ConfidenceThresholds},
+    config::{self, DecodingSettings, PartialSettings, Settings, VadSettings},
+    download::{self, DownloadOptions, DownloadProgress},
+    ffmpeg::{self, FfmpegDecoder},
+    heartbeat::Heartbeat,
+    incremental, language,
+    logging::{self, LogFormat},
+    manifest,
+    merge::{self, MergeFormat},
+    model_manager::{ModelPoolLimits, WhenFull},
+    models::{self, CacheSort, VerificationReport},
+    output::{self, ConsoleOptions, OutputFormat, RenderOptions, TimestampStyle},
+    plan::{self, BatchPlan, Discovery, PlanOptions, PlannedAction, PlannedFile},
+    pool::{self, WorkerPool, WorkerReply, WorkerRequest},
+    probe,
+    progress::ProgressEstimator,
+    python_env::PythonEnv,
+    redact::{self, Redactor},
+    replace::{self, RuleSet},
+    schedule::{self, Schedule},
+    search::SearchOptions,
+    space::{self, format_size},
+    speakers::{self, SpeakerOptions},
+    state::BatchState,
+    stitch,
+    template::OutputTemplate,
+    throttle::ThrottleCheck,
+    timing::Phase,
+    transcriber::FasterWhisperTranscriber,
+    types::{Backend, ModelConfig, TranscriptionOptions, TranscriptionResult},
+    uncertain::UncertainMarking,
+    vad::VadOptions,
+    watch::{self, WatchOptions},
+    TranscriptionError,
 };
-use std::path::PathBuf;
+#[cfg(feature = "mic")]
+use rust_whisper_app::{listen::ListenOptions, mic};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
+use tracing::Instrument;
+
+/// How results are written, shared by single-file, batch and watch runs
+#[derive(Debug, Clone)]
+struct OutputOptions {
+    format: OutputFormat,
+    /// Further formats written beside each output from the same result
+    extra_formats: Vec<OutputFormat>,
+    console: ConsoleOptions,
+    /// Attach `TranscriptStats` to every result before it is written
+    stats: bool,
+    /// Label segments with heuristic speaker turns
+    speakers: Option<SpeakerOptions>,
+    /// Label segments and words with pyannote's speakers
+    #[cfg(feature = "diarization")]
+    diarizer: Option<Arc<Diarizer>>,
+    /// Transcribe each channel on its own, labelled with these names (or the defaults when
+    /// empty)
+    #[cfg(feature = "preprocess")]
+    split_channels: Option<Vec<String>>,
+    /// Transcribe only this channel, counting from 1
+    #[cfg(feature = "preprocess")]
+    channel: Option<usize>,
+    /// Attach chapters split at pauses: (minimum pause, minimum chapter length) in seconds
+    chapters: Option<(f64, f64)>,
+    /// Attach the text without dubious segments: (minimum average log probability, maximum
+    /// no-speech probability)
+    confident_text: Option<(f64, f64)>,
+    /// Uncertainty markers in the text formats
+    render: RenderOptions,
+    /// Vocabulary fixes applied to every transcription
+    replacements: Option<RuleSet>,
+    /// Personal data masked after the replacements, so nothing downstream sees it
+    redactor: Option<Redactor>,
+    /// Redraw an estimate of how far each transcription has got on stderr
+    progress: bool,
+    /// Log that each transcription is still running this often
+    heartbeat: Option<Duration>,
+    /// Move outputs about to be overwritten aside first
+    backup: Option<BackupOptions>,
+    /// Save each segment beside the output as it's decoded
+    incremental: bool,
+    /// Pick up where a run that saved segments incrementally stopped
+    resume_incremental: bool,
+    /// Decode with ffmpeg and transcribe the samples when the backend can't read a file
+    ffmpeg_fallback: Option<FfmpegDecoder>,
+}
+
+impl OutputOptions {
+    /// The extra formats to write beside `output_path`. One whose file would be
+    /// `output_path` itself, as with `-o talk.srt --format json,srt`, is skipped with a
+    /// warning.
+    fn extra_formats_for(&self, output_path: &Path) -> Vec<OutputFormat> {
+        self.extra_formats
+            .iter()
+            .copied()
+            .filter(|&format| {
+                let collides = output::artifact_path(output_path, format) == output_path;
+                if collides {
+                    warn!(
+                        "Not writing {} output: it would overwrite {}",
+                        format,
+                        output_path.display()
+                    );
+                }
+                !collides
+            })
+            .collect()
+    }
+
+    /// Paths the extra formats are written to beside `output_path`
+    fn artifacts(&self, output_path: Option<&Path>) -> Vec<PathBuf> {
+        let Some(output_path) = output_path else {
+            return Vec::new();
+        };
+        self.extra_formats
+            .iter()
+            .map(|&format| output::artifact_path(output_path, format))
+            .filter(|path| path != output_path)
+            .collect()
+    }
+}
 
 async fn transcribe_file(
-    transcriber: &FasterWhisperTranscriber,
+    transcriber: &dyn TranscriptionBackend,
     input_path: PathBuf,
     output_path: Option<PathBuf>,
-) -> Result<()> {
-    info!("Processing: {}", input_path.display());
+    output_options: &OutputOptions,
+    language: Option<String>,
+) -> Result<TranscriptionResult> {
+    // Lifecycle events carry structured fields for --log-format json
+    let file = input_path.display().to_string();
+    let model = transcriber.config().model_size.clone();
+    info!(
+        event = "file_started", file = file.as_str(), model = model.as_str();
+        "Processing: {}", file
+    );
 
-    let result = transcriber
-        .transcribe(&input_path)
-        .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
-    // Output results
-    if let Some(output_path) = output_path {
-        let json_output = serde_json::to_string_pretty(&result)?;
-        fs::write(&output_path, json_output).await?;
-        info!("Results saved to: {}", output_path.display());
+    match write_transcription(
+        transcriber,
+        &input_path,
+        output_path,
+        output_options,
+        language,
+    )
+    .await
+    {
+        Ok(result) => {
+            info!(
+                event = "file_completed",
+                file = file.as_str(),
+                model = model.as_str(),
+                language = result.language.as_str(),
+                segments = result.segments.len(),
+                duration = result.duration,
+                elapsed = result.transcription_time,
+                real_time_factor = result.real_time_factor,
+                cached = result.cached;
+                "✓ Completed: {} ({} segments, {:.2}s)",
+                file,
+                result.segments.len(),
+                result.transcription_time
+            );
+            Ok(result)
+        }
+        Err(e) => {
+            error!(
+                event = "file_failed", file = file.as_str(), model = model.as_str(),
+                error_kind = error_kind(&e);
+                "✗ Failed {}: {}", file, error_message(&e)
+            );
+            Err(e)
+        }
+    }
+}
+
+/// An error's message without the path a `WithPath` adds, for lines that name the file already
+fn error_message(error: &anyhow::Error) -> String {
+    match error.downcast_ref::<TranscriptionError>() {
+        Some(e) => e.inner().to_string(),
+        None => error.to_string(),
+    }
+}
+
+/// A failed batch outcome, keeping the kind of a `TranscriptionError`
+#[cfg(feature = "s3")]
+fn failed_outcome(input: PathBuf, output: Option<PathBuf>, error: &anyhow::Error) -> FileOutcome {
+    match error.downcast_ref::<TranscriptionError>() {
+        Some(e) => FileOutcome::from_error(input, output, e),
+        None => FileOutcome::failed(input, output, error),
+    }
+}
+
+/// Stable name of an error's cause for structured logs
+fn error_kind(error: &anyhow::Error) -> &str {
+    if let Some(e) = error.downcast_ref::<TranscriptionError>() {
+        e.kind()
+    } else if error.downcast_ref::<std::io::Error>().is_some() {
+        "io"
     } else {
-        // Print to stdout
-        println!("\n=== Transcription Results ===");
-        println!(
-            "Language: {} (confidence: {:.2}%)",
-            result.language,
-            result.language_probability * 100.0
-        );
-        println!("Duration: {:.2}s", result.duration);
-        println!("Transcription Time: {:.2}s", result.transcription_time);
-        println!("Real-time Factor: {:.2}x", result.real_time_factor);
-        println!("\nFull Text:\n{}", result.full_text);
-
-        if !result.segments.is_empty() {
-            println!("\n=== Segments ===");
-            for (i, segment) in result.segments.iter().enumerate() {
-                println!(
-                    "[{:03}] [{:.2}s -> {:.2}s] {}",
-                    i + 1,
-                    segment.start,
-                    segment.end,
-                    segment.text
+        "other"
+    }
+}
+
+async fn write_transcription(
+    transcriber: &dyn TranscriptionBackend,
+    input_path: &Path,
+    output_path: Option<PathBuf>,
+    output_options: &OutputOptions,
+    language: Option<String>,
+) -> Result<TranscriptionResult> {
+    let mut result = process_transcription(
+        transcriber,
+        input_path,
+        output_path.as_deref(),
+        output_options,
+        language,
+    )?;
+    let output_start = std::time::Instant::now();
+    write_result(&result, output_path.as_deref(), output_options)
+        .instrument(tracing::info_span!("write_output", file = %input_path.display()))
+        .await?;
+    result.add_timing(Phase::Output, output_start.elapsed());
+    if let Some(output_path) = output_path.filter(|_| output_options.incremental) {
+        // The output now holds everything the sidecar did
+        let _ = std::fs::remove_file(incremental::sidecar_path(&output_path));
+    }
+    Ok(result)
+}
+
+/// Transcribe `input_path` and apply the speaker labels, replacements and redaction. With
+/// incremental saving, segments go to a sidecar beside `output_path` as they're decoded.
+fn process_transcription(
+    transcriber: &dyn TranscriptionBackend,
+    input_path: &Path,
+    output_path: Option<&Path>,
+    output_options: &OutputOptions,
+    language: Option<String>,
+) -> Result<TranscriptionResult> {
+    let mut options = transcriber.options().clone();
+    if language.is_some() {
+        options.language = language;
+    }
+    #[cfg(feature = "preprocess")]
+    if let Some(names) = &output_options.split_channels {
+        let mut result = rust_whisper_app::channels::transcribe_channels(
+            transcriber,
+            input_path,
+            names,
+            &options,
+        )?;
+        apply_text_rules(&mut result, output_options);
+        return Ok(result);
+    }
+    #[cfg(feature = "preprocess")]
+    if let Some(channel) = output_options.channel {
+        let mut result = rust_whisper_app::channels::transcribe_channel(
+            transcriber,
+            input_path,
+            channel,
+            &options,
+        )?;
+        apply_text_rules(&mut result, output_options);
+        return Ok(result);
+    }
+    let sidecar = output_path
+        .filter(|_| output_options.incremental)
+        .map(incremental::sidecar_path);
+    let mut saved = Vec::new();
+    if let Some(sidecar) = sidecar.as_deref() {
+        if output_options.resume_incremental && sidecar.exists() {
+            let partial = incremental::read_sidecar(sidecar)?;
+            if partial.input == input_path {
+                saved = partial.segments;
+            } else {
+                warn!(
+                    "{} was saved from {}, not {}; starting over",
+                    sidecar.display(),
+                    partial.input.display(),
+                    input_path.display()
                 );
             }
         }
+        if let Some(resume_at) = incremental::resume_point(&saved) {
+            info!(
+                "Resuming {} at {:.1}s from {} saved segment(s)",
+                input_path.display(),
+                resume_at,
+                saved.len()
+            );
+            options.clip_start = Some(resume_at);
+        }
+    }
+    let mut sidecar_writer = sidecar
+        .as_deref()
+        .map(|sidecar| incremental::SidecarWriter::create(sidecar, input_path, &saved))
+        .transpose()?;
+    let heartbeat = output_options
+        .heartbeat
+        .map(|interval| Heartbeat::start(input_path.display().to_string(), interval));
+    let start = std::time::Instant::now();
+    let mut estimator = output_options.progress.then(ProgressEstimator::new);
+    let mut drawn = false;
+    let result =
+        transcriber.transcribe_path_streaming(input_path, &options, &mut |segment, duration| {
+            if let Some(heartbeat) = &heartbeat {
+                heartbeat.segment(segment);
+            }
+            if let Some(writer) = &mut sidecar_writer {
+                if let Err(e) = writer.push(segment) {
+                    warn!("Stopped saving segments incrementally: {}", e);
+                    sidecar_writer = None;
+                }
+            }
+            if let Some(estimator) = &mut estimator {
+                let progress = estimator.update(segment.end, duration, start.elapsed());
+                let mut stderr = std::io::stderr().lock();
+                let _ = write!(stderr, "\r\x1b[KTranscribing {}", progress);
+                let _ = stderr.flush();
+                drawn = true;
+            }
+        });
+    drop(heartbeat);
+    if drawn {
+        eprint!("\r\x1b[K");
+    }
+    let mut result = match (result, &output_options.ffmpeg_fallback) {
+        (Err(e), Some(decoder)) if ffmpeg::should_fall_back(&e) => {
+            warn!("{}; decoding with ffmpeg instead", e);
+            ffmpeg::transcribe_decoded(transcriber, decoder, input_path, &options)?
+        }
+        (result, _) => result?,
+    };
+    let post_processing = std::time::Instant::now();
+    if !saved.is_empty() {
+        result = incremental::stitch(saved, result, options.paragraph_gap);
+    }
+    if let Some(speaker_options) = &output_options.speakers {
+        let samples = speaker_samples(input_path);
+        speakers::assign_speakers(&mut result, speaker_options, samples.as_deref());
     }
+    #[cfg(feature = "diarization")]
+    if let Some(diarizer) = &output_options.diarizer {
+        diarizer.label(input_path, &mut result)?;
+    }
+    apply_text_rules(&mut result, output_options);
+    result.add_timing(Phase::PostProcessing, post_processing.elapsed());
+    Ok(result)
+}
 
-    Ok(())
+/// Vocabulary fixes, then redaction, so nothing downstream sees the masked text
+fn apply_text_rules(result: &mut TranscriptionResult, output_options: &OutputOptions) {
+    if let Some(rules) = &output_options.replacements {
+        replace::apply_replacements(result, rules);
+    }
+    if let Some(redactor) = &output_options.redactor {
+        redactor.redact(result);
+    }
+}
+
+/// Audio for the speaker heuristic's loudness check. Only WAV can be decoded without Python,
+/// and only in builds with the preprocess feature; otherwise turns come from pauses alone.
+fn speaker_samples(input_path: &Path) -> Option<Vec<f32>> {
+    #[cfg(feature = "preprocess")]
+    {
+        rust_whisper_app::audio::read_wav_16k(input_path).ok()
+    }
+    #[cfg(not(feature = "preprocess"))]
+    {
+        let _ = input_path;
+        None
+    }
 }
 
-async fn transcribe_multiple_files(
-    transcriber: &FasterWhisperTranscriber,
-    input_paths: Vec<PathBuf>,
-    output_dir: Option<PathBuf>,
+/// Save `result` to `output_path`, plus every extra format beside it, or print it when there
+/// is no path. A format that can't be written doesn't stop the others.
+async fn write_result(
+    result: &TranscriptionResult,
+    output_path: Option<&Path>,
+    output_options: &OutputOptions,
 ) -> Result<()> {
-    info!("Processing {} files concurrently", input_paths.len());
-
-    let futures: Vec<_> = input_paths
-        .into_iter()
-        .map(|input_path| {
-            let output_path = output_dir.as_ref().map(|dir| {
-                let mut output_name = input_path.file_stem().unwrap().to_owned();
-                output_name.push("_transcription.json");
-                dir.join(output_name)
-            });
+    let with_extras;
+    let wants_stats = output_options.stats && result.stats.is_none();
+    let wants_chapters = output_options.chapters.is_some() && result.chapters.is_none();
+    let wants_confident =
+        output_options.confident_text.is_some() && result.full_text_confident.is_none();
+    let result = if wants_stats || wants_chapters || wants_confident {
+        with_extras = TranscriptionResult {
+            stats: result
+                .stats
+                .clone()
+                .or_else(|| wants_stats.then(|| result.stats())),
+            chapters: result.chapters.clone().or_else(|| {
+                let (min_gap, min_length) = output_options.chapters?;
+                Some(result.detect_chapters(min_gap, min_length))
+            }),
+            full_text_confident: result.full_text_confident.clone().or_else(|| {
+                let (min_avg_logprob, max_no_speech) = output_options.confident_text?;
+                Some(result.confident_text(min_avg_logprob, max_no_speech))
+            }),
+            ..result.clone()
+        };
+        &with_extras
+    } else {
+        result
+    };
+    if let Some(output_path) = output_path {
+        let extras = output_options.extra_formats_for(output_path);
+        if let Some(backup) = &output_options.backup {
+            let chapters_path = result
+                .chapters
+                .is_some()
+                .then(|| output::chapters_path(output_path));
+            let replaced = std::iter::once(output_path.to_path_buf())
+                .chain(
+                    extras
+                        .iter()
+                        .map(|&format| output::artifact_path(output_path, format)),
+                )
+                .chain(chapters_path);
+            for path in replaced {
+                if let Some(saved) = backup.back_up(&path)? {
+                    info!("Backed up {} to {}", path.display(), saved.display());
+                }
+            }
+        }
+        let mut written = Vec::new();
+        let mut failures = Vec::new();
+        let primary =
+            match output::render_with(result, output_options.format, &output_options.render) {
+                Ok(rendered) => output::write_atomic(output_path, rendered).map_err(Into::into),
+                Err(e) => Err(e),
+            };
+        match primary {
+            Ok(()) => written.push(output_path.to_path_buf()),
+            // Nothing else was asked for, so the error stands as it is
+            Err(e) if extras.is_empty() => return Err(e.into()),
+            Err(e) => failures.push(format!(
+                "{} to {}: {}",
+                output_options.format,
+                output_path.display(),
+                e
+            )),
+        }
+        match output::write_outputs(result, output_path, &extras, &output_options.render) {
+            Ok(paths) => written.extend(paths),
+            Err(TranscriptionError::OutputWriteFailed {
+                failures: failed,
+                written: paths,
+            }) => {
+                written.extend(paths);
+                failures.extend(failed);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        for path in &written {
+            info!("Results saved to: {}", path.display());
+        }
+        if !failures.is_empty() {
+            return Err(TranscriptionError::OutputWriteFailed { failures, written }.into());
+        }
+        if let Some(chapters) = &result.chapters {
+            let chapters_path = output::chapters_path(output_path);
+            output::write_atomic(&chapters_path, chapters::render_youtube(chapters))?;
+            info!("Chapters saved to: {}", chapters_path.display());
+        }
+    } else {
+        output::write_console(
+            result,
+            output_options.format,
+            &output_options.console,
+            &output_options.render,
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?;
+    }
+    Ok(())
+}
+
+/// A batch file's failure as a `TranscriptionError`, keeping its kind where it has one
+fn into_transcription_error(error: anyhow::Error) -> TranscriptionError {
+    match error.downcast::<TranscriptionError>() {
+        Ok(e) => e,
+        Err(error) => match error.downcast::<std::io::Error>() {
+            Ok(e) => e.into(),
+            Err(error) => TranscriptionError::TranscriptionFailed(error.to_string()),
+        },
+    }
+}
+
+/// Where `--jobs` runs a batch's files
+enum Isolation<'a> {
+    /// Threads sharing one model, and with it the Python interpreter
+    Thread(&'a dyn TranscriptionBackend),
+    /// Worker processes with an interpreter and model each
+    Process(WorkerPool),
+}
 
-            async move {
-                match transcribe_file(transcriber, input_path.clone(), output_path).await {
-                    Ok(_) => info!("✓ Completed: {}", input_path.display()),
-                    Err(e) => error!("✗ Failed {}: {}", input_path.display(), e),
+impl Isolation<'_> {
+    /// Transcribe `file` and write its outputs
+    fn transcribe(
+        &self,
+        file: &PlannedFile,
+        output_options: &OutputOptions,
+        runtime: &tokio::runtime::Handle,
+    ) -> std::result::Result<TranscriptionResult, TranscriptionError> {
+        match self {
+            Isolation::Thread(transcriber) => runtime
+                .block_on(transcribe_file(
+                    *transcriber,
+                    file.input.clone(),
+                    file.output.clone(),
+                    output_options,
+                    file.language.clone(),
+                ))
+                .map_err(into_transcription_error),
+            Isolation::Process(pool) => {
+                let mut result = pool.transcribe(&WorkerRequest {
+                    input: file.input.clone(),
+                    output: file.output.clone(),
+                    language: file.language.clone(),
+                })?;
+                // A worker's stdout carries its replies, so console output is printed here
+                if file.output.is_none() {
+                    let output_start = std::time::Instant::now();
+                    runtime
+                        .block_on(write_result(&result, None, output_options))
+                        .map_err(into_transcription_error)?;
+                    result.add_timing(Phase::Output, output_start.elapsed());
                 }
+                Ok(result)
             }
-        })
-        .collect();
+        }
+    }
+}
 
-    future::join_all(futures).await;
-    Ok(())
+/// Writes a directory run's files with the output settings, transcribing them in `isolation`
+struct BatchWriter<'a> {
+    isolation: &'a Isolation<'a>,
+    output_options: &'a OutputOptions,
+    runtime: tokio::runtime::Handle,
 }
 
-async fn run_benchmark(input_path: PathBuf, output_path: Option<PathBuf>) -> Result<()> {
-    info!("🚀 Starting comprehensive benchmark...");
+impl batch::FileWriter for BatchWriter<'_> {
+    fn transcribe(
+        &self,
+        file: &PlannedFile,
+    ) -> std::result::Result<TranscriptionResult, TranscriptionError> {
+        self.isolation
+            .transcribe(file, self.output_options, &self.runtime)
+    }
 
-    let mut benchmark = Benchmark::new();
+    fn write(
+        &self,
+        file: &PlannedFile,
+        result: &TranscriptionResult,
+    ) -> std::result::Result<(), TranscriptionError> {
+        let write = write_result(result, file.output.as_deref(), self.output_options);
+        self.runtime
+            .block_on(write)
+            .map_err(into_transcription_error)
+    }
 
-    // Add medium vs base comparison - the primary focus
-    info!("Adding medium vs base model comparison...");
-    benchmark.add_medium_vs_base_comparison("auto", "float16");
+    fn artifacts(&self, output: Option<&Path>) -> Vec<PathBuf> {
+        self.output_options.artifacts(output)
+    }
+}
 
-    // Add Metal-specific optimizations
-    info!("Adding Metal acceleration benchmarks...");
-    benchmark.add_metal_optimized_benchmarks();
+/// Workers for `--isolation process`: this binary again, with the same arguments plus the
+/// hidden worker flag
+fn worker_pool(max_workers: Option<usize>) -> Result<WorkerPool> {
+    let program = std::env::current_exe()?;
+    let mut args: Vec<std::ffi::OsString> = std::env::args_os().skip(1).collect();
+    args.push(pool::WORKER_FLAG.into());
+    Ok(WorkerPool::new(program, args).with_max_workers(max_workers))
+}
 
-    // Add CPU vs Metal comparison for base model (for reference)
-    info!("Adding CPU vs Metal comparison tests...");
-    benchmark.add_cpu_vs_metal_comparison("base", "float16");
+/// `--max-models`, `--max-models-mb` and `--when-models-full`
+fn model_limits(matches: &ArgMatches) -> Result<(ModelPoolLimits, WhenFull)> {
+    let limits = ModelPoolLimits {
+        max_models: matches
+            .get_one::<u64>("max_models")
+            .map(|&max| max as usize),
+        max_total_estimated_mb: matches.get_one::<u64>("max_models_mb").copied(),
+    };
+    let when_full = matches
+        .get_one::<String>("when_models_full")
+        .unwrap()
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    Ok((limits, when_full))
+}
 
-    // Add compute type comparison for medium model
-    info!("Adding compute type comparison tests...");
-    benchmark.add_compute_type_comparison("medium", "auto");
+/// `model_limits` when any of its flags is given, for uses with defaults of their own
+fn explicit_model_limits(matches: &ArgMatches) -> Result<Option<(ModelPoolLimits, WhenFull)>> {
+    let given = ["max_models", "max_models_mb", "when_models_full"]
+        .iter()
+        .any(|id| matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine));
+    given.then(|| model_limits(matches)).transpose()
+}
 
-    let results = benchmark
-        .run(&input_path)
-        .await
-        .map_err(|e| anyhow::anyhow!("Benchmark failed: {}", e))?;
+/// The throttle check `--detect-throttling` asks for, if it does
+fn throttle_check(matches: &ArgMatches) -> Result<Option<ThrottleCheck>> {
+    if !matches.get_flag("detect_throttling") {
+        return Ok(None);
+    }
+    let mut check = ThrottleCheck::default();
+    if let Some(&threshold) = matches.get_one::<f64>("throttle_threshold") {
+        if !(threshold.is_finite() && threshold >= 0.0) {
+            anyhow::bail!("--throttle-threshold must be a non-negative percentage");
+        }
+        check.threshold_percent = threshold;
+    }
+    check.cooldown = matches
+        .get_one::<u64>("cooldown_secs")
+        .map(|&secs| Duration::from_secs(secs));
+    Ok(Some(check))
+}
 
-    // Print results
-    benchmark.print_comparison(&results);
+/// `--worker`: transcribe the files a parent running `--isolation process` sends as JSON
+/// lines on stdin, answering each with a line on stdout, until stdin closes
+async fn run_worker(
+    matches: &ArgMatches,
+    settings: &Settings,
+    cache: Option<ResultCache>,
+) -> Result<()> {
+    let output_options = output_options(matches, settings)?;
+    let transcriber = backend::create(settings.model.clone(), settings.options.clone())
+        .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+    let transcriber: Box<dyn TranscriptionBackend> = match cache {
+        Some(cache) => Box::new(CachedBackend::new(transcriber, cache)),
+        None => transcriber,
+    };
 
-    // Save to JSON if output path provided
-    if let Some(output_path) = output_path {
-        benchmark
-            .save_results_json(&results, &output_path)
-            .map_err(|e| anyhow::anyhow!("Failed to save benchmark results: {}", e))?;
-        info!("Benchmark results saved to: {}", output_path.display());
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::block_in_place(|| {
+        let mut stdout = std::io::stdout();
+        for line in std::io::stdin().lock().lines() {
+            let request: WorkerRequest = serde_json::from_str(&line?)?;
+            let result = match request.output {
+                Some(output) => runtime.block_on(transcribe_file(
+                    transcriber.as_ref(),
+                    request.input,
+                    Some(output),
+                    &output_options,
+                    request.language,
+                )),
+                // The parent prints it
+                None => process_transcription(
+                    transcriber.as_ref(),
+                    &request.input,
+                    None,
+                    &output_options,
+                    request.language,
+                ),
+            };
+            let reply = WorkerReply::from_result(&result.map_err(into_transcription_error));
+            writeln!(stdout, "{}", serde_json::to_string(&reply)?)?;
+            stdout.flush()?;
+        }
+        Ok(())
+    })
+}
+
+/// A flag set by the first Ctrl-C, so a batch stops starting files; a second one exits.
+/// Abort the returned task once the batch is over.
+fn stop_on_ctrl_c(
+    runtime: &tokio::runtime::Handle,
+) -> (Arc<AtomicBool>, tokio::task::JoinHandle<()>) {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = shutdown.clone();
+    let listener = runtime.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Stopping; finishing the files under way (Ctrl-C again to abort)");
+            flag.store(true, Ordering::SeqCst);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+    (shutdown, listener)
+}
+
+/// How evenly `schedule` spreads the audio of known duration over `jobs` jobs, which bounds
+/// how long the batch will take
+fn log_schedule(plan: &BatchPlan, schedule: Schedule, jobs: usize) {
+    let durations: Vec<f64> = plan
+        .to_transcribe()
+        .filter_map(|file| file.duration)
+        .collect();
+    if jobs < 2 || durations.is_empty() {
+        return;
     }
+    let total: f64 = durations.iter().sum();
+    let in_order: Vec<usize> = (0..durations.len()).collect();
+    let busiest = schedule::makespan(&durations, &in_order, jobs);
+    info!(
+        "Scheduled {} over {} jobs: the busiest job has {:.1} of the {:.1} minutes of audio with a known duration",
+        schedule,
+        jobs,
+        busiest / 60.0,
+        total / 60.0
+    );
+}
 
+/// Merge a directory's results into one document for `--merge-output`
+async fn write_merged_output(
+    results: Vec<(PathBuf, TranscriptionResult)>,
+    merge_path: &Path,
+    format: MergeFormat,
+    chapters: Option<(f64, f64)>,
+    render: &RenderOptions,
+) -> Result<()> {
+    let mut merged = merge::merge_transcripts(results);
+    // Chapters of the whole timeline, which the markdown sections are then split by
+    if let Some((min_gap, min_length)) = chapters {
+        merged.result.chapters = Some(merged.result.detect_chapters(min_gap, min_length));
+    }
+    if merged.has_language_conflict() {
+        warn!(
+            "Merged parts were detected in different languages ({}); using {}",
+            merged.languages().join(", "),
+            merged.result.language
+        );
+    }
+    output::write_atomic(merge_path, merge::render_merged(&merged, format, render)?)?;
+    info!(
+        "Merged {} transcript(s) into: {}",
+        merged.parts.len(),
+        merge_path.display()
+    );
     Ok(())
 }
 
-async fn run_medium_model_benchmark(
-    input_path: PathBuf,
-    device: &str,
-    compute_type: &str,
+/// Keep transcribing new files dropped into `dir` until Ctrl-C
+async fn run_watch(
+    transcriber: Box<dyn TranscriptionBackend>,
+    dir: PathBuf,
+    plan_options: PlanOptions,
+    output_options: OutputOptions,
+    options: WatchOptions,
 ) -> Result<()> {
-    info!("🚀 Starting medium model benchmark on Metal acceleration...");
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Shutdown requested; finishing the current file (Ctrl-C again to abort)");
+            flag.store(true, Ordering::SeqCst);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
 
-    let models = vec!["base", "medium"];
-    let results = FasterWhisperTranscriber::benchmark_model_comparison(
-        &input_path,
-        &models,
-        device,
-        compute_type,
-    )?;
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        watch::watch_directory(&dir, &options, &shutdown, |path| {
+            plan_options.check_duration(path, probe::probe_duration(path))?;
+            let language = plan_options.mapped_language(path);
+            let output_path = plan_options.output_path(path, language.as_deref());
+            runtime
+                .block_on(transcribe_file(
+                    transcriber.as_ref(),
+                    path.to_path_buf(),
+                    output_path,
+                    &output_options,
+                    language,
+                ))
+                .map(|_| ())
+                .map_err(into_transcription_error)
+        })
+    })
+    .await??;
+    Ok(())
+}
 
-    println!("\n=== Medium Model Benchmark Results ===");
-    println!("Audio file: {}", input_path.display());
-    println!("Device: {}, Compute Type: {}", device, compute_type);
-    println!();
+/// `--model-dir`, else the Hugging Face hub cache faster-whisper uses by default
+fn model_cache_dir(settings: &Settings) -> Result<PathBuf> {
+    settings
+        .model
+        .model_dir
+        .clone()
+        .or_else(models::default_cache_dir)
+        .ok_or_else(|| anyhow::anyhow!("Cannot find the model cache; set --model-dir"))
+}
 
-    for (model, result) in &results {
-        println!("Model: {}", model);
-        println!("  Duration: {:.2}s", result.duration);
-        println!("  Transcription Time: {:.2}s", result.transcription_time);
-        println!("  Real-time Factor: {:.2}x", result.real_time_factor);
-        println!("  Language: {} ({:.1}% confidence)", result.language, result.language_probability * 100.0);
+/// Fail before downloading model `name` into a cache whose volume is too full for it.
+/// Models already cached, local model paths and unknown names aren't checked.
+fn check_model_space(name: &str, settings: &Settings) -> Result<()> {
+    let Some(size) = models::approximate_size(name) else {
+        return Ok(());
+    };
+    let Ok(cache_dir) = model_cache_dir(settings) else {
+        return Ok(());
+    };
+    if models::has_snapshot(name, &cache_dir) {
+        return Ok(());
+    }
+    space::ensure_space(&cache_dir, size)?;
+    Ok(())
+}
 
-        // Show performance classification
-        let performance_class = if result.real_time_factor > 10.0 {
-            "🚀 Excellent"
-        } else if result.real_time_factor > 5.0 {
-            "⚡ Great"
-        } else if result.real_time_factor > 2.0 {
-            "✅ Good"
-        } else if result.real_time_factor > 1.0 {
-            "⚠️ Acceptable"
-        } else {
-            "❌ Slow"
-        };
+/// Download models into the cache without loading them, for offline use later
+fn run_models_pull(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    if settings.model.offline {
+        anyhow::bail!("models pull needs the network; drop --offline");
+    }
+    let cache_dir = model_cache_dir(settings)?;
+    let live = std::io::stderr().is_terminal();
+    let names: Vec<&String> = matches.get_many::<String>("names").unwrap().collect();
+    let mut failed = Vec::new();
+    for name in &names {
+        if !matches.get_flag("skip_space_check") {
+            if let Err(e) = check_model_space(name, settings) {
+                error!("Not pulling {}: {}", name, e);
+                failed.push(name.as_str());
+                continue;
+            }
+        }
+        let mut current = String::new();
+        let mut shown = None;
+        let pulled = models::pull(name, &cache_dir, &mut |file, progress| {
+            if !live {
+                return;
+            }
+            if current != file {
+                current = file.to_string();
+                shown = None;
+            }
+            draw_download_progress(&format!("{} {}", name, file), progress, &mut shown);
+        });
+        if live {
+            eprint!("\r\x1b[K");
+        }
+        match pulled {
+            Ok(report) => {
+                print_verification(&report);
+                if !report.is_ok() {
+                    failed.push(name.as_str());
+                }
+            }
+            Err(e) => {
+                error!("Failed to pull {}: {}", name, e);
+                failed.push(name.as_str());
+            }
+        }
+    }
+    if !failed.is_empty() {
+        error!(
+            "{} of {} model(s) failed: {}",
+            failed.len(),
+            names.len(),
+            failed.join(", ")
+        );
+        std::process::exit(1);
+    }
+    info!("Models saved to {}", cache_dir.display());
+    Ok(())
+}
 
-        println!("  Performance: {}", performance_class);
-        println!();
+/// Models in the cache with their size and when they were last used
+fn run_models_cached(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let cache_dir = model_cache_dir(settings)?;
+    let sort: CacheSort = matches
+        .get_one::<String>("sort")
+        .unwrap()
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    let mut cached = models::list_cached(&cache_dir)?;
+    models::sort_cached(&mut cached, sort);
+    if cached.is_empty() {
+        println!("No models cached in {}", cache_dir.display());
+        return Ok(());
+    }
+    for model in &cached {
+        println!(
+            "{:<20} {:>10}  {:<14} {}",
+            model.display_name(),
+            format_size(model.size),
+            model
+                .last_used
+                .map(format_age)
+                .unwrap_or_else(|| "unknown".to_string()),
+            model.path.display()
+        );
     }
+    println!(
+        "{} model(s), {} in {}",
+        cached.len(),
+        format_size(cached.iter().map(|model| model.size).sum()),
+        cache_dir.display()
+    );
+    Ok(())
+}
 
-    // Calculate improvement
-    if results.len() >= 2 {
-        let base_rtf = results[0].1.real_time_factor;
-        let medium_rtf = results[1].1.real_time_factor;
-        let improvement = if base_rtf > 0.0 {
-            (medium_rtf - base_rtf) / base_rtf * 100.0
-        } else {
-            0.0
-        };
+/// Ask whether to transcribe an input over `--max-duration-minutes` anyway. Without a
+/// terminal to ask on, or when the answer is no, the limit is an error.
+fn confirm_long_input(error: TranscriptionError) -> Result<()> {
+    if !(std::io::stdin().is_terminal() && std::io::stderr().is_terminal()) {
+        return Err(error.into());
+    }
+    eprint!("{}. Transcribe it anyway? [y/N] ", error);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        return Err(error.into());
+    }
+    Ok(())
+}
 
-        println!("=== Performance Comparison ===");
-        if improvement > 0.0 {
-            println!("🏆 Medium model is {:.1}% faster than base model", improvement);
-        } else {
-            println!("🐌 Medium model is {:.1}% slower than base model", -improvement);
+/// Delete a cached model, asking first unless `--yes` is given
+fn run_models_rm(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let cache_dir = model_cache_dir(settings)?;
+    let name = matches.get_one::<String>("name").unwrap();
+    let repo = models::repo_id(name);
+    let cached = models::list_cached(&cache_dir)?;
+    let Some(model) = cached
+        .iter()
+        .find(|model| model.display_name() == name || Some(&model.repo) == repo.as_ref())
+    else {
+        anyhow::bail!("{} isn't cached in {}", name, cache_dir.display());
+    };
+
+    if !matches.get_flag("yes") {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("Pass --yes to delete {} without confirmation", name);
+        }
+        eprint!(
+            "Delete {} ({}) at {}? [y/N] ",
+            model.display_name(),
+            format_size(model.size),
+            model.path.display()
+        );
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            eprintln!("Kept {}", model.display_name());
+            return Ok(());
         }
+    }
+    models::remove_cached(&model.repo, &cache_dir)?;
+    info!(
+        "Deleted {}, freeing {}",
+        model.display_name(),
+        format_size(model.size)
+    );
+    Ok(())
+}
 
-        let accuracy_note = if medium_rtf > 0.0 {
-            "Note: Medium model typically provides better accuracy despite potential speed differences"
-        } else {
-            "Note: Performance may vary based on audio content and system configuration"
-        };
-        println!("{}", accuracy_note);
+/// How long ago `time` was, in the largest whole unit
+fn format_age(time: std::time::SystemTime) -> String {
+    let seconds = time.elapsed().map(|age| age.as_secs()).unwrap_or(0);
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3_599 => format!("{} min ago", seconds / 60),
+        3_600..=86_399 => format!("{} h ago", seconds / 3_600),
+        _ => format!("{} days ago", seconds / 86_400),
+    }
+}
+
+/// Check a cached model's files, and with `--repair` download a damaged one again
+fn run_models_verify(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let name = matches
+        .get_one::<String>("name")
+        .unwrap_or(&settings.model.model_size);
+    let cache_dir = model_cache_dir(settings)?;
+
+    let verify = || -> Result<VerificationReport> {
+        let mut report = models::verify_cached(name, &cache_dir)?;
+        if let (Some(repo), Some(revision), false) =
+            (&report.repo, &report.revision, settings.model.offline)
+        {
+            match models::hub_checksums(repo, revision) {
+                Ok(checksums) => report.check_against(&checksums)?,
+                Err(e) => warn!("Skipping hub checksums: {}", e),
+            }
+        }
+        Ok(report)
+    };
+    let mut report = verify()?;
+    print_verification(&report);
+    if report.is_ok() {
+        return Ok(());
+    }
+    if !matches.get_flag("repair") {
+        if report.is_cached() && report.repo.is_some() {
+            eprintln!(
+                "Run `models verify {} --repair` to delete it and download it again",
+                name
+            );
+        }
+        std::process::exit(1);
+    }
+    if settings.model.offline {
+        anyhow::bail!("--repair needs to download the model; drop --offline");
     }
 
+    if models::remove_cached(name, &cache_dir)? {
+        info!("Deleted the cached copy of {}", name);
+    }
+    let config = ModelConfig {
+        model_size: name.clone(),
+        ..settings.model.clone()
+    };
+    let path = FasterWhisperTranscriber::new(config)?.download_model()?;
+    info!("Downloaded {} to {}", name, path.display());
+    report = verify()?;
+    print_verification(&report);
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
+fn print_verification(report: &VerificationReport) {
+    let Some(location) = &report.location else {
+        println!("{}: not downloaded", report.model);
+        return;
+    };
+    match &report.repo {
+        Some(repo) => println!("{} from {} ({})", report.model, repo, location.display()),
+        None => println!("{}", location.display()),
+    }
+    for file in &report.files {
+        let status = match &file.problem {
+            Some(problem) => problem.to_string(),
+            None if file.sha256_verified => "ok, SHA-256 verified".to_string(),
+            None => "ok".to_string(),
+        };
+        println!("  {:<24} {:>12}  {}", file.name, file.size, status);
+    }
+    for partial in &report.incomplete_downloads {
+        println!("  interrupted download left {}", partial.display());
+    }
+    let problems = report.problems().count();
+    if problems == 0 {
+        println!("{}: OK", report.model);
+    } else {
+        println!("{}: {} damaged file(s)", report.model, problems);
+    }
+}
+
+/// Download a URL input to a temporary directory and transcribe it like a local file
+async fn run_url(
+    matches: &ArgMatches,
+    settings: &Settings,
+    cache: Option<ResultCache>,
+    url: &str,
+) -> Result<()> {
+    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
+    if matches.get_flag("dry_run") {
+        println!(
+            "Dry run: 1 URL would be downloaded and transcribed\n  {:<20} {} -> {}",
+            "transcribe",
+            url,
+            output_path
+                .as_ref()
+                .map(|o| o.display().to_string())
+                .unwrap_or_else(|| "stdout".to_string())
+        );
+        return Ok(());
+    }
+    let options = DownloadOptions {
+        max_bytes: matches.get_one::<u64>("max_download_size").copied(),
+        retries: *matches.get_one::<u32>("download_retries").unwrap(),
+        expect_sha256: matches.get_one::<String>("expect_sha256").cloned(),
+        ..Default::default()
+    };
+    let mut output_options = output_options(matches, settings)?;
+
+    let work_dir = std::env::temp_dir().join(format!("whisper-url-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work_dir).await?;
+    let local_audio = work_dir.join(download::file_name_from_url(url));
+    info!("Downloading {}", url);
+    let source = url.to_string();
+    let destination = local_audio.clone();
+    // On a terminal the progress line is redrawn in place, at most once per percent
+    let live = std::io::stderr().is_terminal();
+    output_options.progress = live && !matches.get_flag("quiet");
+    let downloaded = tokio::task::spawn_blocking(move || {
+        let mut shown = None;
+        let result = download::download(&source, &destination, &options, &mut |progress| {
+            if live {
+                draw_download_progress("Downloading", progress, &mut shown);
+            }
+        });
+        if live && shown.is_some() {
+            eprint!("\r\x1b[K");
+        }
+        result
+    })
+    .await?;
+    let size = match downloaded {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&work_dir).await;
+            return Err(e.into());
+        }
+    };
+    info!("Downloaded {} bytes to {}", size, local_audio.display());
+
+    let transcribed = async {
+        let transcriber = backend::create(settings.model.clone(), settings.options.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+        let transcriber: Box<dyn TranscriptionBackend> = match cache {
+            Some(cache) => Box::new(CachedBackend::new(transcriber, cache)),
+            None => transcriber,
+        };
+        transcribe_file(
+            transcriber.as_ref(),
+            local_audio,
+            output_path,
+            &output_options,
+            None,
+        )
+        .await
+    }
+    .await;
+    let _ = fs::remove_dir_all(&work_dir).await;
+    transcribed.map(|_| ())
+}
+
+fn draw_download_progress(label: &str, progress: DownloadProgress, shown: &mut Option<u64>) {
+    let mb = progress.downloaded as f64 / (1 << 20) as f64;
+    let line = match progress.total.filter(|total| *total > 0) {
+        Some(total) => {
+            let percent = progress.downloaded * 100 / total;
+            if *shown == Some(percent) {
+                return;
+            }
+            *shown = Some(percent);
+            format!(
+                "{} {:>3}% ({:.1} of {:.1} MB)",
+                label,
+                percent,
+                mb,
+                total as f64 / (1 << 20) as f64
+            )
+        }
+        None => {
+            let tenths = progress.downloaded / (1 << 20) * 10;
+            if *shown == Some(tenths) {
+                return;
+            }
+            *shown = Some(tenths);
+            format!("{} {:.1} MB", label, mb)
+        }
+    };
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(stderr, "\r\x1b[K{}", line);
+    let _ = stderr.flush();
+}
+
+/// Transcribe the audio objects under an `s3://` URI, one local temporary copy at a time.
+/// Outputs go to a local `--output` directory or are uploaded to an `s3://` one.
+#[cfg(feature = "s3")]
+async fn run_s3(
+    matches: &ArgMatches,
+    settings: &Settings,
+    cache: Option<ResultCache>,
+    input: &str,
+) -> Result<()> {
+    let client = S3Client::new();
+    let source: S3Uri = input.parse()?;
+    let output = matches.get_one::<String>("output");
+    let remote_output = output
+        .filter(|output| S3Uri::is_s3(output))
+        .map(|output| output.parse::<S3Uri>())
+        .transpose()?;
+    let local_output = output
+        .filter(|output| !S3Uri::is_s3(output))
+        .map(PathBuf::from);
+
+    let objects = client.list_audio(&source)?;
+    if objects.is_empty() {
+        warn!("No audio objects found at {}", source);
+        return Ok(());
+    }
+    info!("Found {} audio object(s) at {}", objects.len(), source);
+
+    let output_options = output_options(matches, settings)?;
+    let transcriber = backend::create(settings.model.clone(), settings.options.clone())
+        .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+    let transcriber: Box<dyn TranscriptionBackend> = match cache {
+        Some(cache) => Box::new(CachedBackend::new(transcriber, cache)),
+        None => transcriber,
+    };
+
+    let work_dir = std::env::temp_dir().join(format!("whisper-s3-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work_dir).await?;
+    let started = std::time::Instant::now();
+    let mut report = BatchReport::new();
+    for (index, object) in objects.iter().enumerate() {
+        let uri = S3Uri {
+            bucket: source.bucket.clone(),
+            key: object.key.clone(),
+        };
+        let input_label = PathBuf::from(uri.to_string());
+        let relative = Path::new(source.relative_key(&object.key));
+        let output_relative = plan::output_path_for(
+            relative,
+            relative.parent().unwrap_or(Path::new("")),
+            settings.format,
+        );
+        let output_path = match (&remote_output, &local_output) {
+            (Some(_), _) => Some(work_dir.join("out").join(&output_relative)),
+            (None, Some(dir)) => Some(dir.join(&output_relative)),
+            (None, None) => None,
+        };
+        let remote = |relative: &Path| {
+            remote_output
+                .as_ref()
+                .map(|prefix| prefix.join(&relative.to_string_lossy().replace('\\', "/")))
+        };
+        let destination = remote(&output_relative);
+        let output_label = match &destination {
+            Some(destination) => Some(PathBuf::from(destination.to_string())),
+            None => output_path.clone(),
+        };
+        // The other formats, uploaded beside the output when it goes to S3
+        let artifacts = output_options.artifacts(output_path.as_deref());
+        let uploads: Vec<(PathBuf, S3Uri)> = output_options
+            .artifacts(output_path.is_some().then_some(output_relative.as_path()))
+            .iter()
+            .zip(&artifacts)
+            .filter_map(|(relative, local)| Some((local.clone(), remote(relative)?)))
+            .collect();
+        let artifact_labels: Vec<PathBuf> = match remote_output {
+            Some(_) => uploads
+                .iter()
+                .map(|(_, uri)| PathBuf::from(uri.to_string()))
+                .collect(),
+            None => artifacts,
+        };
+
+        let local_audio = work_dir.join(format!(
+            "{}-{}",
+            index,
+            relative.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        info!("Downloading {} ({} bytes)", uri, object.size);
+        let outcome = match client.download(&uri, &local_audio) {
+            // Every other object would be refused too
+            Err(e @ TranscriptionError::StorageAccessDenied { .. }) => {
+                let _ = fs::remove_dir_all(&work_dir).await;
+                return Err(e.into());
+            }
+            Err(e) => FileOutcome::from_error(input_label, output_label, &e),
+            Ok(()) => {
+                if let Some(parent) = output_path.as_deref().and_then(Path::parent) {
+                    fs::create_dir_all(parent).await?;
+                }
+                let transcribed = transcribe_file(
+                    transcriber.as_ref(),
+                    local_audio.clone(),
+                    output_path.clone(),
+                    &output_options,
+                    None,
+                )
+                .await;
+                let uploaded = match (&transcribed, &destination, &output_path) {
+                    (Ok(_), Some(destination), Some(written)) => {
+                        std::iter::once((written.clone(), destination.clone()))
+                            .chain(uploads)
+                            .try_for_each(|(local, uri)| {
+                                info!("Uploading {}", uri);
+                                client.upload(&local, &uri)
+                            })
+                    }
+                    _ => Ok(()),
+                };
+                let _ = fs::remove_file(&local_audio).await;
+                match (transcribed, uploaded) {
+                    (Ok(result), Ok(())) => {
+                        FileOutcome::succeeded(input_label, output_label, &result)
+                            .with_artifacts(artifact_labels)
+                    }
+                    (Err(e), _) => failed_outcome(input_label, output_label, &e),
+                    (Ok(_), Err(e @ TranscriptionError::StorageAccessDenied { .. })) => {
+                        let _ = fs::remove_dir_all(&work_dir).await;
+                        return Err(e.into());
+                    }
+                    (Ok(_), Err(e)) => FileOutcome::from_error(input_label, output_label, &e),
+                }
+            }
+        };
+        report.push(outcome);
+    }
+    let _ = fs::remove_dir_all(&work_dir).await;
+    report.wall_time_seconds = started.elapsed().as_secs_f64();
+
+    print_batch_summary(&report);
+    if let Some(csv_path) = matches.get_one::<String>("summary_csv") {
+        report.write_summary_csv(csv_path)?;
+        info!("CSV summary saved to: {}", csv_path);
+    }
+    if let Some(timing_path) = matches.get_one::<String>("timing_report") {
+        report.write_timing_report(timing_path)?;
+        info!("Timing report saved to: {}", timing_path);
+    }
+    if report.exit_code() != 0 {
+        std::process::exit(report.exit_code());
+    }
+    info!("🎉 All transcriptions completed successfully!");
+    Ok(())
+}
+
+#[cfg(not(feature = "s3"))]
+async fn run_s3(
+    _matches: &ArgMatches,
+    _settings: &Settings,
+    _cache: Option<ResultCache>,
+    _input: &str,
+) -> Result<()> {
+    anyhow::bail!("S3 input isn't part of this build; rebuild with --features s3")
+}
+
+/// Transcribe the microphone until Ctrl-C, then write the stitched transcript
+#[cfg(feature = "mic")]
+async fn run_listen(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let listen_options = ListenOptions {
+        window: Duration::try_from_secs_f64(*matches.get_one::<f64>("window").unwrap())?,
+        overlap: Duration::try_from_secs_f64(*matches.get_one::<f64>("overlap").unwrap())?,
+    };
+    listen_options.validate().map_err(anyhow::Error::msg)?;
+    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
+
+    let transcriber = backend::create(settings.model.clone(), settings.options.clone())
+        .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+    // Load up front so the first window isn't delayed by model loading
+    let report = transcriber
+        .warmup()
+        .map_err(|e| anyhow::anyhow!("Failed to load model: {}", e))?;
+    info!(
+        "Model {} ready in {:.2}s",
+        settings.model.model_size,
+        (report.load_time + report.warmup_time).as_secs_f64()
+    );
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Stopping; transcribing the remaining audio (Ctrl-C again to abort)");
+            flag.store(true, Ordering::SeqCst);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+
+    // Final words are printed as lines; on a terminal the provisional tail is shown dimmed
+    // after them and redrawn by the next window
+    let live = std::io::stderr().is_terminal();
+    let result = tokio::task::spawn_blocking(move || {
+        mic::listen(transcriber.as_ref(), &listen_options, &shutdown, |update| {
+            let mut stderr = std::io::stderr().lock();
+            if live {
+                let _ = write!(stderr, "\r\x1b[K");
+            }
+            let committed = update.committed_text();
+            if !committed.is_empty() {
+                let _ = writeln!(stderr, "{}", committed);
+            }
+            let tail = update.tail_text();
+            if live && !tail.is_empty() {
+                let _ = write!(stderr, "\x1b[2m{}\x1b[0m", tail);
+            }
+            let _ = stderr.flush();
+        })
+    })
+    .await??;
+    if live {
+        eprint!("\r\x1b[K");
+    }
+
+    match output_path {
+        Some(output_path) => {
+            output::write_atomic(&output_path, output::render(&result, settings.format)?)?;
+            info!("Transcript saved to: {}", output_path.display());
+        }
+        None => output::write_console(
+            &result,
+            settings.format,
+            &ConsoleOptions::default(),
+            &RenderOptions::default(),
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?,
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "mic"))]
+async fn run_listen(_matches: &ArgMatches, _settings: &Settings) -> Result<()> {
+    anyhow::bail!("Microphone capture isn't part of this build; rebuild with --features mic")
+}
+
+/// `align`: time the words of a known transcript against its audio
+async fn run_align(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let input_path = PathBuf::from(matches.get_one::<String>("input").unwrap());
+    let transcript_path = matches.get_one::<String>("transcript").unwrap();
+    let transcript = fs::read_to_string(transcript_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot read transcript {}: {}", transcript_path, e))?;
+    if transcript.trim().is_empty() {
+        anyhow::bail!("Transcript {} is empty", transcript_path);
+    }
+    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
+
+    let transcriber = backend::create(settings.model.clone(), settings.options.clone())
+        .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+    let result = align::align(transcriber.as_ref(), &input_path, &transcript)?;
+    if let Some(summary) = &result.alignment {
+        info!(
+            "Aligned {} word(s): {} matched, {} misrecognized, {} not recognized (WER {:.1}%)",
+            summary.matched + summary.substituted + summary.missing,
+            summary.matched,
+            summary.substituted,
+            summary.missing,
+            summary.word_error_rate * 100.0
+        );
+    }
+
+    match output_path {
+        Some(output_path) => {
+            output::write_atomic(&output_path, output::render(&result, settings.format)?)?;
+            info!("Aligned transcript saved to: {}", output_path.display());
+        }
+        None => output::write_console(
+            &result,
+            settings.format,
+            &ConsoleOptions::default(),
+            &RenderOptions::default(),
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?,
+    }
+    Ok(())
+}
+
+async fn run_vad(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let input = PathBuf::from(matches.get_one::<String>("input").unwrap());
+    let files = if input.is_dir() {
+        plan::discover_audio_files(&input)?
+    } else {
+        vec![input]
+    };
+    let vad_options = VadOptions {
+        threshold: matches
+            .get_one::<f64>("threshold")
+            .copied()
+            .unwrap_or(settings.options.vad_threshold),
+        min_speech_duration_ms: matches.get_one::<u64>("min_speech_ms").copied(),
+        min_silence_duration_ms: matches.get_one::<u64>("min_silence_ms").copied(),
+        speech_pad_ms: matches.get_one::<u64>("speech_pad_ms").copied(),
+    };
+    vad_options.validate().map_err(anyhow::Error::msg)?;
+    let transcriber = FasterWhisperTranscriber::new(settings.model.clone())?;
+    let json = matches.get_flag("json");
+
+    // Each file is reported as it finishes, so long screens show progress; failures are
+    // reported and counted rather than stopping the run
+    let mut stdout = std::io::stdout().lock();
+    let mut failed = 0;
+    let mut silent = 0;
+    for file in &files {
+        match transcriber.speech_report(file, &vad_options) {
+            Ok(report) if json => {
+                writeln!(stdout, "{}", serde_json::to_string(&report)?)?;
+                silent += usize::from(!report.has_speech());
+            }
+            Ok(report) => {
+                writeln!(
+                    stdout,
+                    "{}: {} region(s), {:.1}s of speech in {:.1}s ({:.0}%)",
+                    file.display(),
+                    report.regions.len(),
+                    report.speech_seconds,
+                    report.duration,
+                    report.speech_ratio * 100.0
+                )?;
+                for region in &report.regions {
+                    writeln!(
+                        stdout,
+                        "  [{} -> {}]",
+                        output::format_timestamp(region.start, TimestampStyle::Clock),
+                        output::format_timestamp(region.end, TimestampStyle::Clock)
+                    )?;
+                }
+                silent += usize::from(!report.has_speech());
+            }
+            // Nothing else would get further
+            Err(e)
+                if matches!(
+                    e.inner(),
+                    TranscriptionError::PackageMissing { .. }
+                        | TranscriptionError::PythonUnavailable(_)
+                ) =>
+            {
+                return Err(e.into())
+            }
+            Err(e) => {
+                error!("✗ {}: {}", file.display(), e.inner());
+                failed += 1;
+            }
+        }
+    }
+    info!(
+        "Screened {} file(s): {} without speech, {} failed",
+        files.len(),
+        silent,
+        failed
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Render a whisper.cpp transcript in the configured format, for `convert`
+async fn run_convert(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let input_path = PathBuf::from(matches.get_one::<String>("input").unwrap());
+    let json = fs::read_to_string(&input_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", input_path.display(), e))?;
+    let result =
+        TranscriptionResult::from_whispercpp_json(&json).map_err(|e| e.with_path(&input_path))?;
+    info!(
+        "Read {} segment(s) from {}",
+        result.segments.len(),
+        input_path.display()
+    );
+
+    match matches.get_one::<String>("output").map(PathBuf::from) {
+        Some(output_path) => {
+            output::write_atomic(&output_path, output::render(&result, settings.format)?)?;
+            info!("Converted transcript saved to: {}", output_path.display());
+        }
+        None => output::write_console(
+            &result,
+            settings.format,
+            &ConsoleOptions::default(),
+            &RenderOptions::default(),
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?,
+    }
+    Ok(())
+}
+
+/// Retime a transcription JSON and render it in the configured format, for `adjust`
+async fn run_adjust(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let input_path = PathBuf::from(matches.get_one::<String>("input").unwrap());
+    let shift = matches.get_one::<f64>("shift").copied();
+    let scale = matches.get_one::<f64>("scale").copied();
+    if shift.is_some_and(|shift| !shift.is_finite()) {
+        anyhow::bail!("--shift must be a number of seconds");
+    }
+    if scale.is_some_and(|scale| !(scale.is_finite() && scale > 0.0)) {
+        anyhow::bail!("--scale must be a positive factor");
+    }
+    let json = fs::read_to_string(&input_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", input_path.display(), e))?;
+    let mut result: TranscriptionResult = serde_json::from_str(&json).map_err(|e| {
+        anyhow::anyhow!(
+            "{} is not a transcription JSON: {}",
+            input_path.display(),
+            e
+        )
+    })?;
+
+    let segment_count = result.segments.len();
+    if let Some(scale) = scale {
+        result.scale(scale);
+    }
+    if let Some(shift) = shift {
+        result.shift(shift);
+    }
+    if result.segments.len() < segment_count {
+        info!(
+            "Dropped {} segment(s) that ended before zero",
+            segment_count - result.segments.len()
+        );
+    }
+
+    match matches.get_one::<String>("output").map(PathBuf::from) {
+        Some(output_path) => {
+            output::write_atomic(&output_path, output::render(&result, settings.format)?)?;
+            info!("Adjusted transcript saved to: {}", output_path.display());
+        }
+        None => output::write_console(
+            &result,
+            settings.format,
+            &ConsoleOptions::default(),
+            &RenderOptions::default(),
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?,
+    }
+    Ok(())
+}
+
+/// Put chunk transcripts back onto the original timeline and write one document, for `stitch`
+async fn run_stitch(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let manifest_path = PathBuf::from(matches.get_one::<String>("manifest").unwrap());
+    let auto_offsets = matches.get_flag("auto_offsets");
+    let overlap = stitch::OverlapOptions {
+        max_start_gap: *matches.get_one::<f64>("overlap_gap").unwrap(),
+        min_agreement: *matches.get_one::<f64>("overlap_similarity").unwrap(),
+    };
+    if !(overlap.max_start_gap.is_finite() && overlap.max_start_gap >= 0.0) {
+        anyhow::bail!("--overlap-gap must be a non-negative number of seconds");
+    }
+    if !(0.0..=1.0).contains(&overlap.min_agreement) {
+        anyhow::bail!("--overlap-similarity must be between 0 and 1");
+    }
+    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
+    // Validate the output up front rather than after transcribing hours of audio
+    let merge_format = output_path
+        .as_deref()
+        .map(MergeFormat::from_path)
+        .transpose()?;
+
+    let contents = fs::read_to_string(&manifest_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", manifest_path.display(), e))?;
+    let base_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let entries = stitch::parse_chunk_manifest(&contents, base_dir)?;
+    for entry in &entries {
+        match (entry.offset, auto_offsets) {
+            (Some(_), true) => anyhow::bail!(
+                "{} line {}: offsets can't be given with --auto-offsets",
+                manifest_path.display(),
+                entry.line
+            ),
+            (None, false) => anyhow::bail!(
+                "{} line {}: missing offset; give one or use --auto-offsets",
+                manifest_path.display(),
+                entry.line
+            ),
+            _ => {}
+        }
+    }
+
+    let mut transcriber = None;
+    let mut chunks = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let is_json = entry
+            .path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let result: TranscriptionResult = if is_json {
+            let json = fs::read_to_string(&entry.path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", entry.path.display(), e))?;
+            serde_json::from_str(&json).map_err(|e| {
+                anyhow::anyhow!(
+                    "{} is not a transcription JSON: {}",
+                    entry.path.display(),
+                    e
+                )
+            })?
+        } else {
+            if transcriber.is_none() {
+                transcriber = Some(
+                    backend::create(settings.model.clone(), settings.options.clone())
+                        .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?,
+                );
+            }
+            info!("Transcribing {}", entry.path.display());
+            transcriber
+                .as_ref()
+                .unwrap()
+                .transcribe_path(&entry.path, &settings.options)?
+        };
+        chunks.push((entry.path, entry.offset, result));
+    }
+
+    let segment_count: usize = chunks.iter().map(|(_, _, r)| r.segments.len()).sum();
+    let merged = stitch::stitch(chunks, &overlap);
+    if merged.result.segments.len() < segment_count {
+        info!(
+            "Dropped {} segment(s) repeated where chunks overlap",
+            segment_count - merged.result.segments.len()
+        );
+    }
+    if merged.has_language_conflict() {
+        warn!(
+            "Chunks were transcribed in different languages ({}); labelled {}",
+            merged.languages().join(", "),
+            merged.result.language
+        );
+    }
+
+    match (output_path, merge_format) {
+        (Some(output_path), Some(format)) => {
+            output::write_atomic(
+                &output_path,
+                merge::render_merged(&merged, format, &RenderOptions::default())?,
+            )?;
+            info!(
+                "Stitched {} chunk(s) into: {}",
+                merged.parts.len(),
+                output_path.display()
+            );
+        }
+        _ => output::write_console(
+            &merged.result,
+            settings.format,
+            &ConsoleOptions::default(),
+            &RenderOptions::default(),
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?,
+    }
+    Ok(())
+}
+
+async fn run_search(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let query = matches.get_one::<String>("query").unwrap();
+    let path = PathBuf::from(matches.get_one::<String>("transcript").unwrap());
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let result: TranscriptionResult = if is_json {
+        let json = fs::read_to_string(&path).await?;
+        serde_json::from_str(&json)
+            .map_err(|e| anyhow::anyhow!("{} is not a transcription JSON: {}", path.display(), e))?
+    } else {
+        let transcriber = backend::create(settings.model.clone(), settings.options.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+        // Word timestamps time matches to the word rather than the segment
+        let options = TranscriptionOptions {
+            word_timestamps: true,
+            ..settings.options.clone()
+        };
+        transcriber.transcribe_path(&path, &options)?
+    };
+
+    let options = SearchOptions {
+        fuzzy: matches.get_flag("fuzzy"),
+        context_words: *matches.get_one::<usize>("context").unwrap(),
+    };
+    let found = result.find(query, options);
+    let mut stdout = std::io::stdout().lock();
+    for m in &found {
+        writeln!(
+            stdout,
+            "[{} -> {}] {}\t{}",
+            output::format_timestamp(m.start, TimestampStyle::Clock),
+            output::format_timestamp(m.end, TimestampStyle::Clock),
+            m.text,
+            m.context
+        )?;
+    }
+    info!(
+        "{} match(es) for {:?} in {}",
+        found.len(),
+        query,
+        path.display()
+    );
+    if found.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Print what a run would do, for `--dry-run`
+fn print_plan(plan: &BatchPlan) {
+    let transcribe_count = plan.to_transcribe().count();
+    let duplicate_count = plan.to_write().count() - transcribe_count;
+    println!(
+        "Dry run: {} of {} file(s) would be transcribed, {} skipped{}",
+        transcribe_count,
+        plan.files.len(),
+        plan.files.len() - transcribe_count - duplicate_count,
+        if duplicate_count > 0 {
+            format!(", {} duplicate(s)", duplicate_count)
+        } else {
+            String::new()
+        }
+    );
+    for file in &plan.files {
+        let action = match file.action {
+            PlannedAction::Transcribe => "transcribe",
+            PlannedAction::SkipExisting => "skip (output exists)",
+            PlannedAction::Missing => "missing",
+            PlannedAction::AlreadyDone => "skip (done)",
+            PlannedAction::PreviouslyFailed => "skip (failed before)",
+            PlannedAction::Duplicate => "duplicate",
+            PlannedAction::TooLong => "skip (too long)",
+        };
+        let duration = file
+            .duration
+            .map(|d| format!(" [{:.1}s]", d))
+            .unwrap_or_default();
+        let language = file
+            .language
+            .as_ref()
+            .map(|language| format!(" ({})", language))
+            .unwrap_or_default();
+        let output = file
+            .output
+            .as_ref()
+            .map(|o| o.display().to_string())
+            .unwrap_or_else(|| "stdout".to_string());
+        println!(
+            "  {:<20} {}{}{} -> {}",
+            action,
+            file.input.display(),
+            language,
+            duration,
+            output
+        );
+    }
+    let groups = plan.duplicate_groups();
+    if !groups.is_empty() {
+        println!("Duplicate inputs (transcribed once, result copied):");
+        for group in &groups {
+            println!("  {}", group);
+        }
+    }
+    let (known, unknown) = plan.known_duration();
+    println!(
+        "Audio to transcribe: {:.1}s known{}",
+        known,
+        if unknown > 0 {
+            format!(", {} file(s) not probed", unknown)
+        } else {
+            String::new()
+        }
+    );
+}
+
+fn print_batch_summary(report: &BatchReport) {
+    let stats = report.statistics();
+
+    eprintln!("\n=== Batch Summary ===");
+    eprintln!("Files: {}", report.summary());
+    eprintln!(
+        "Audio processed: {:.2}h ({:.1}s)",
+        stats.total_audio_hours(),
+        stats.total_audio_seconds
+    );
+    eprintln!("Wall time: {:.2}s", stats.wall_time_seconds);
+    eprintln!(
+        "Aggregate real-time factor: {:.2}x",
+        stats.aggregate_real_time_factor
+    );
+    eprintln!(
+        "Average per-file real-time factor: {:.2}x",
+        stats.average_real_time_factor
+    );
+    if !stats.languages.is_empty() {
+        let languages: Vec<String> = stats
+            .languages
+            .iter()
+            .map(|(language, count)| format!("{} {}", language, count))
+            .collect();
+        eprintln!("Languages: {}", languages.join(", "));
+    }
+    // With phase timings, say where the slowest files spent their time
+    let timings = report.timing_report();
+    if !timings.files.is_empty() {
+        eprintln!("Slowest files:");
+        for file in timings.slowest(3) {
+            let (phase, seconds) = file.timings.dominant();
+            eprintln!(
+                "  {}: {:.2}s, mostly {} ({:.2}s)",
+                file.input.display(),
+                file.total,
+                phase,
+                seconds
+            );
+        }
+    } else if let Some(slowest) = &stats.slowest_file {
+        eprintln!(
+            "Slowest file: {} ({:.2}s, {:.2}x)",
+            slowest.input.display(),
+            slowest.transcription_time,
+            slowest.real_time_factor
+        );
+    }
+    // With several formats, name everything each input produced
+    if report.outcomes.iter().any(|o| !o.artifacts.is_empty()) {
+        eprintln!("Outputs:");
+        for outcome in report.outcomes.iter().filter(|o| o.output.is_some()) {
+            let written: Vec<String> = outcome
+                .written()
+                .map(|path| path.display().to_string())
+                .collect();
+            eprintln!("  {}: {}", outcome.input.display(), written.join(", "));
+        }
+    }
+    let groups = report.duplicate_groups();
+    if !groups.is_empty() {
+        eprintln!("Duplicates (transcribed once):");
+        for group in &groups {
+            eprintln!("  {}", group);
+        }
+    }
+    let too_long: Vec<&FileOutcome> = report
+        .outcomes
+        .iter()
+        .filter(|o| o.error_kind.as_deref() == Some("duration_limit"))
+        .collect();
+    if !too_long.is_empty() {
+        eprintln!("Skipped as too long:");
+        for outcome in too_long {
+            if let FileStatus::Skipped(reason) = &outcome.status {
+                eprintln!("  {}: {}", outcome.input.display(), reason);
+            }
+        }
+    }
+    let undecodable = report.decode_failures();
+    if !undecodable.is_empty() {
+        eprintln!("Undecodable audio:");
+        for (reason, count) in undecodable {
+            eprintln!(
+                "  {} file{}: {}",
+                count,
+                if count == 1 { "" } else { "s" },
+                reason
+            );
+        }
+    }
+    if !stats.failed_files.is_empty() {
+        eprintln!("Failed files:");
+        for failure in report.failures() {
+            if let FileStatus::Failed(reason) = &failure.status {
+                eprintln!("  ✗ {}: {}", failure.input.display(), reason);
+            }
+        }
+    }
+}
+
+/// Collect the settings given explicitly on the command line
+fn cli_settings(matches: &ArgMatches) -> Result<PartialSettings> {
+    let parse_format = |s: &String| output::parse_formats(s).map_err(anyhow::Error::msg);
+
+    Ok(PartialSettings {
+        backend: matches
+            .get_one::<String>("backend")
+            .map(|s| s.parse::<Backend>().map_err(anyhow::Error::msg))
+            .transpose()?,
+        model: matches.get_one::<String>("model").cloned(),
+        device: matches.get_one::<String>("device").cloned(),
+        compute_type: matches.get_one::<String>("compute_type").cloned(),
+        format: matches
+            .get_one::<String>("format")
+            .map(parse_format)
+            .transpose()?,
+        jobs: matches.get_one::<usize>("jobs").copied(),
+        model_dir: matches.get_one::<String>("model_dir").map(PathBuf::from),
+        offline: matches.get_flag("offline").then_some(true),
+        python_venv: matches.get_one::<String>("python_venv").map(PathBuf::from),
+        vad: VadSettings {
+            enabled: matches.get_flag("no_vad").then_some(false),
+            threshold: matches.get_one::<f64>("vad_threshold").copied(),
+        },
+        decoding: DecodingSettings {
+            language: matches.get_one::<String>("language").cloned(),
+            beam_size: matches.get_one::<usize>("beam_size").copied(),
+            best_of: matches.get_one::<usize>("best_of").copied(),
+            temperature: matches.get_one::<f64>("temperature").copied(),
+            word_timestamps: None,
+            paragraph_gap: matches.get_one::<f64>("paragraph_gap").copied(),
+            allowed_languages: matches
+                .get_one::<String>("allowed_languages")
+                .map(|list| language::parse_language_list(list)),
+            include_tokens: matches.get_flag("include_tokens").then_some(true),
+        },
+    })
+}
+
+/// Post-processing and output settings shared by every transcription of a run
+fn output_options(matches: &ArgMatches, settings: &Settings) -> Result<OutputOptions> {
+    let color: ColorChoice = matches
+        .get_one::<String>("color")
+        .unwrap()
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    let thresholds = matches
+        .get_one::<String>("confidence_thresholds")
+        .map(|s| ConfidenceThresholds::parse(s))
+        .transpose()
+        .map_err(anyhow::Error::msg)?
+        .unwrap_or_default();
+    #[cfg(not(feature = "diarization"))]
+    if matches.get_flag("diarize") {
+        anyhow::bail!("Diarization isn't part of this build; rebuild with --features diarization");
+    }
+    #[cfg(not(feature = "preprocess"))]
+    if matches.get_flag("split_channels") {
+        anyhow::bail!(
+            "Splitting channels needs WAV decoding in Rust; rebuild with --features preprocess"
+        );
+    }
+    #[cfg(not(feature = "preprocess"))]
+    if matches.contains_id("channel") {
+        anyhow::bail!(
+            "--channel requires the preprocess feature; rebuild with --features preprocess"
+        );
+    }
+    Ok(OutputOptions {
+        format: settings.format,
+        extra_formats: settings.extra_formats.clone(),
+        console: ConsoleOptions {
+            timestamps: matches
+                .get_one::<String>("timestamp_style")
+                .map(|s| s.parse::<TimestampStyle>())
+                .transpose()
+                .map_err(anyhow::Error::msg)?
+                .unwrap_or_default(),
+            colors: color
+                .enabled(std::io::stdout().is_terminal())
+                .then_some(thresholds),
+        },
+        stats: matches.get_flag("stats"),
+        speakers: matches
+            .get_one::<usize>("speakers")
+            .map(|&max_speakers| {
+                let options = SpeakerOptions {
+                    max_speakers,
+                    min_gap: *matches.get_one::<f64>("speaker_gap").unwrap(),
+                    ..Default::default()
+                };
+                options.validate().map(|()| options)
+            })
+            .transpose()
+            .map_err(anyhow::Error::msg)?,
+        #[cfg(feature = "diarization")]
+        diarizer: if matches.get_flag("diarize") {
+            let token = matches
+                .get_one::<String>("hf_token")
+                .cloned()
+                .or_else(|| std::env::var("HF_TOKEN").ok())
+                .unwrap_or_default();
+            Some(Arc::new(Diarizer::new(&token)?))
+        } else {
+            None
+        },
+        #[cfg(feature = "preprocess")]
+        split_channels: matches.get_flag("split_channels").then(|| {
+            matches
+                .get_one::<String>("channel_names")
+                .map(|names| {
+                    names
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        }),
+        #[cfg(feature = "preprocess")]
+        channel: matches.get_one::<usize>("channel").copied(),
+        chapters: matches.get_flag("chapters").then(|| {
+            (
+                *matches.get_one::<f64>("chapter_gap").unwrap(),
+                *matches.get_one::<f64>("chapter_min_length").unwrap(),
+            )
+        }),
+        confident_text: if matches.get_flag("confident_text") {
+            let min_avg_logprob = *matches.get_one::<f64>("confident_min_logprob").unwrap();
+            let max_no_speech = *matches.get_one::<f64>("confident_max_no_speech").unwrap();
+            confidence::validate_confident_thresholds(min_avg_logprob, max_no_speech)
+                .map_err(anyhow::Error::msg)?;
+            Some((min_avg_logprob, max_no_speech))
+        } else {
+            None
+        },
+        render: RenderOptions {
+            uncertain: matches
+                .get_one::<f64>("mark_uncertain")
+                .map(|&threshold| {
+                    UncertainMarking::new(threshold).and_then(|marking| {
+                        marking
+                            .with_markers(matches.get_one::<String>("uncertain_markers").unwrap())
+                    })
+                })
+                .transpose()
+                .map_err(anyhow::Error::msg)?,
+            words: settings.options.word_timestamps,
+        },
+        replacements: matches
+            .get_one::<String>("replace")
+            .map(|path| RuleSet::from_file(Path::new(path)))
+            .transpose()?,
+        redactor: redactor(matches)?,
+        // Only single-file runs turn this on; parallel files would overwrite each other's line
+        progress: false,
+        heartbeat: matches
+            .get_one::<u64>("heartbeat_secs")
+            .copied()
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs),
+        backup: if matches.get_flag("backup") {
+            let style: BackupStyle = matches
+                .get_one::<String>("backup_style")
+                .unwrap()
+                .parse()
+                .map_err(anyhow::Error::msg)?;
+            Some(BackupOptions::new(
+                style,
+                *matches.get_one::<usize>("backup_keep").unwrap(),
+            )?)
+        } else {
+            None
+        },
+        incremental: matches.get_flag("incremental_save") || matches.get_flag("resume_incremental"),
+        resume_incremental: matches.get_flag("resume_incremental"),
+        ffmpeg_fallback: matches.get_flag("ffmpeg_fallback").then(FfmpegDecoder::new),
+    })
+}
+
+/// `--redact` / `--redact-custom`, or None when neither was given
+fn redactor(matches: &ArgMatches) -> Result<Option<Redactor>> {
+    let categories: Vec<String> = matches
+        .get_many::<String>("redact")
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default();
+    let custom: Vec<String> = matches
+        .get_many::<String>("redact_custom")
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default();
+    if categories.is_empty() && custom.is_empty() {
+        return Ok(None);
+    }
+    let mask = matches
+        .get_one::<String>("redact_mask")
+        .map(String::as_str)
+        .unwrap_or(redact::DEFAULT_MASK);
+    Redactor::new(&categories, &custom, mask)
+        .map(Some)
+        .map_err(|e| TranscriptionError::ConfigError(e).into())
+}
+
+/// Config file layer: `--config` overrides the default location, `--no-config` skips it
+fn file_settings(matches: &ArgMatches) -> Result<PartialSettings> {
+    if matches.get_flag("no_config") {
+        return Ok(PartialSettings::default());
+    }
+    let settings = match matches.get_one::<String>("config") {
+        Some(path) => config::load_config_file(path)?,
+        None => config::load_default_config()?,
+    };
+    Ok(settings)
+}
+
+fn run_config_init(matches: &ArgMatches) -> Result<()> {
+    let path = match matches.get_one::<String>("path") {
+        Some(path) => PathBuf::from(path),
+        None => config::default_config_path()
+            .ok_or_else(|| anyhow::anyhow!("Cannot determine config directory; pass --path"))?,
+    };
+    config::write_default_config(&path, matches.get_flag("force"))?;
+    eprintln!("Wrote default configuration to {}", path.display());
+    Ok(())
+}
+
+/// How `--benchmark` shows its table
+struct BenchmarkView {
+    sort: BenchmarkSort,
+    order: SortOrder,
+    filters: Vec<RowFilter>,
+}
+
+fn benchmark_view(matches: &clap::ArgMatches) -> Result<BenchmarkView> {
+    let sort: BenchmarkSort = match matches.get_one::<String>("benchmark_sort") {
+        Some(key) => key.parse().map_err(anyhow::Error::msg)?,
+        None => BenchmarkSort::default(),
+    };
+    let order = match matches.get_one::<String>("benchmark_order") {
+        Some(order) => order.parse().map_err(anyhow::Error::msg)?,
+        None => sort.default_order(),
+    };
+    let filters = matches
+        .get_many::<String>("benchmark_only")
+        .unwrap_or_default()
+        .map(|filter| filter.parse().map_err(anyhow::Error::msg))
+        .collect::<Result<_>>()?;
+    Ok(BenchmarkView {
+        sort,
+        order,
+        filters,
+    })
+}
+
+async fn run_benchmark(
+    input_path: PathBuf,
+    output_path: Option<PathBuf>,
+    view: BenchmarkView,
+    model_limits: Option<(ModelPoolLimits, WhenFull)>,
+    warmup_runs: usize,
+    throttle: Option<ThrottleCheck>,
+    transcript_dir: Option<PathBuf>,
+) -> Result<()> {
+    info!("🚀 Starting comprehensive benchmark...");
+
+    // Every config transcribes the same file, so their transcripts can vouch for each other
+    let mut benchmark = Benchmark::new().with_agreement(true);
+    if let Some((limits, when_full)) = model_limits {
+        benchmark = benchmark.with_model_limits(limits, when_full);
+    }
+    benchmark.set_warmup_runs(warmup_runs);
+    benchmark.set_throttle_check(throttle);
+    benchmark.set_save_transcripts(transcript_dir);
+
+    // Add medium vs base comparison - the primary focus
+    info!("Adding medium vs base model comparison...");
+    benchmark.add_medium_vs_base_comparison("auto", "float16");
+
+    // Add Metal-specific optimizations
+    info!("Adding Metal acceleration benchmarks...");
+    benchmark.add_metal_optimized_benchmarks();
+
+    // Add CPU vs Metal comparison for base model (for reference)
+    info!("Adding CPU vs Metal comparison tests...");
+    benchmark.add_cpu_vs_metal_comparison("base", "float16");
+
+    // Add compute type comparison for medium model
+    info!("Adding compute type comparison tests...");
+    benchmark.add_compute_type_comparison("medium", "auto");
+
+    if benchmark::available_backends().len() > 1 {
+        info!("Adding backend comparison tests...");
+        benchmark.add_backend_comparison("base", "auto", "float16");
+    }
+
+    let results = benchmark
+        .run(&input_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Benchmark failed: {}", e))?;
+
+    // Print the rows asked for; the saved JSON keeps them all
+    let report = BenchmarkReport::new(results.clone())
+        .filter(&view.filters)
+        .sort(view.sort, view.order);
+    if report.results().is_empty() {
+        warn!("No benchmark results match --benchmark-only");
+    } else {
+        benchmark.print_comparison(report.results());
+    }
+
+    // Save to JSON if output path provided
+    if let Some(output_path) = output_path {
+        benchmark
+            .save_results_json(&results, &output_path)
+            .map_err(|e| anyhow::anyhow!("Failed to save benchmark results: {}", e))?;
+        info!("Benchmark results saved to: {}", output_path.display());
+    }
+
+    Ok(())
+}
+
+fn run_benchmark_query(matches: &ArgMatches) -> Result<()> {
+    let dir = PathBuf::from(matches.get_one::<String>("dir").unwrap());
+    let machine = match matches.get_one::<String>("machine") {
+        Some(class) if class.eq_ignore_ascii_case("any") => None,
+        Some(class) => Some(class.to_lowercase()),
+        None => Some(SystemInfo::current().machine_class()),
+    };
+    let model = matches.get_one::<String>("best_for");
+
+    let set = BenchmarkResultSet::load_dir(&dir)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", dir.display(), e))?;
+    for skipped in set.skipped() {
+        warn!(
+            "{} doesn't hold benchmark results; skipped",
+            skipped.display()
+        );
+    }
+    // Results saved before the machine was recorded can't be ruled out
+    let set = set.filter(|recorded| {
+        let on_machine = match (&machine, &recorded.system) {
+            (Some(class), Some(system)) => system.machine_class() == *class,
+            _ => true,
+        };
+        on_machine && model.is_none_or(|model| recorded.result.model_size == *model)
+    });
+
+    if matches.get_flag("csv") {
+        print!("{}", set.to_csv());
+        return Ok(());
+    }
+    let models: Vec<String> = set.group_by_model().into_keys().collect();
+    if models.is_empty() {
+        anyhow::bail!(
+            "No saved results{}{} in {}",
+            model.map(|m| format!(" for {}", m)).unwrap_or_default(),
+            machine
+                .as_ref()
+                .map(|class| format!(" on {}", class))
+                .unwrap_or_default(),
+            dir.display()
+        );
+    }
+    for model in models {
+        let of_model = set
+            .clone()
+            .filter(|recorded| recorded.result.model_size == model);
+        let Some(best) = of_model.best_by_rtf() else {
+            continue;
+        };
+        let result = &best.result;
+        println!(
+            "{:<10} {:>6.1}x  {} on {} with {}{}  ({}{}{})",
+            model,
+            result.real_time_factor,
+            result.backend.as_str(),
+            result.device,
+            result.compute_type,
+            result
+                .label
+                .as_ref()
+                .map(|label| format!(", {}", label))
+                .unwrap_or_default(),
+            best.source.display(),
+            if best.system.is_none() {
+                ", machine not recorded"
+            } else {
+                ""
+            },
+            if result.throttling_suspected {
+                ", throttling suspected"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+fn run_benchmark_diff(matches: &ArgMatches) -> Result<()> {
+    let threshold = matches.get_one::<f64>("threshold").copied();
+    if threshold.is_some_and(|t| !(t.is_finite() && t >= 0.0)) {
+        anyhow::bail!("--threshold must be a non-negative percentage");
+    }
+    let color: ColorChoice = matches
+        .get_one::<String>("color")
+        .unwrap()
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    let load = |id: &str| -> Result<Vec<benchmark::BenchmarkResult>> {
+        let path = Path::new(matches.get_one::<String>(id).unwrap());
+        let results = BenchmarkResultSet::load_file(path)?;
+        Ok(results
+            .into_iter()
+            .map(|recorded| recorded.result)
+            .collect())
+    };
+    let diff = BenchmarkDiff::new(&load("old")?, &load("new")?);
+
+    print!(
+        "{}",
+        diff.render(color.enabled(std::io::stdout().is_terminal()))
+    );
+    if let Some(threshold) = threshold {
+        let regressions = diff.regressions(threshold);
+        if !regressions.is_empty() {
+            eprintln!(
+                "{} config(s) regressed by more than {}%",
+                regressions.len(),
+                threshold
+            );
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+async fn run_medium_model_benchmark(
+    input_path: PathBuf,
+    device: &str,
+    compute_type: &str,
+) -> Result<()> {
+    info!("🚀 Starting medium model benchmark on Metal acceleration...");
+
+    let models = vec!["base", "medium"];
+    let results = FasterWhisperTranscriber::benchmark_model_comparison(
+        &input_path,
+        &models,
+        device,
+        compute_type,
+    )?;
+
+    println!("\n=== Medium Model Benchmark Results ===");
+    println!("Audio file: {}", input_path.display());
+    println!("Device: {}, Compute Type: {}", device, compute_type);
+    println!();
+
+    for (model, result) in &results {
+        println!("Model: {}", model);
+        println!("  Duration: {:.2}s", result.duration);
+        println!("  Transcription Time: {:.2}s", result.transcription_time);
+        println!("  Real-time Factor: {:.2}x", result.real_time_factor);
+        println!(
+            "  Language: {} ({:.1}% confidence)",
+            result.language,
+            result.language_probability * 100.0
+        );
+
+        // Show performance classification
+        let performance_class = if result.real_time_factor > 10.0 {
+            "🚀 Excellent"
+        } else if result.real_time_factor > 5.0 {
+            "⚡ Great"
+        } else if result.real_time_factor > 2.0 {
+            "✅ Good"
+        } else if result.real_time_factor > 1.0 {
+            "⚠️ Acceptable"
+        } else {
+            "❌ Slow"
+        };
+
+        println!("  Performance: {}", performance_class);
+        println!();
+    }
+
+    // Calculate improvement
+    if results.len() >= 2 {
+        let base_rtf = results[0].1.real_time_factor;
+        let medium_rtf = results[1].1.real_time_factor;
+        let improvement = if base_rtf > 0.0 {
+            (medium_rtf - base_rtf) / base_rtf * 100.0
+        } else {
+            0.0
+        };
+
+        println!("=== Performance Comparison ===");
+        if improvement > 0.0 {
+            println!(
+                "🏆 Medium model is {:.1}% faster than base model",
+                improvement
+            );
+        } else {
+            println!(
+                "🐌 Medium model is {:.1}% slower than base model",
+                -improvement
+            );
+        }
+
+        let accuracy_note = if medium_rtf > 0.0 {
+            "Note: Medium model typically provides better accuracy despite potential speed differences"
+        } else {
+            "Note: Performance may vary based on audio content and system configuration"
+        };
+        println!("{}", accuracy_note);
+    }
+
+    Ok(())
+}
+
+/// Logs always go to stderr; `-q`/`-v` override RUST_LOG, which defaults to `info`
+fn init_logging(matches: &ArgMatches) -> Result<()> {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    if matches.get_flag("quiet") {
+        builder.filter_level(LevelFilter::Error);
+    } else {
+        match matches.get_count("verbose") {
+            0 => {}
+            1 => {
+                builder.filter_level(LevelFilter::Debug);
+            }
+            _ => {
+                builder.filter_level(LevelFilter::Trace);
+            }
+        }
+    }
+    let log_format: LogFormat = matches
+        .get_one::<String>("log_format")
+        .unwrap()
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    if log_format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let timestamp = buf.timestamp_millis().to_string();
+            writeln!(buf, "{}", logging::json_line(record, &timestamp))
+        });
+    }
+    builder.target(env_logger::Target::Stderr).init();
+    Ok(())
+}
+
+/// The command line: transcription flags at the top level, everything else a subcommand
+fn cli() -> Command {
+    Command::new("FasterWhisper Rust Transcriber")
+        .version("1.0")
+        .author("Your Name")
+        .about(
+            "High-performance audio transcription using faster-whisper with Metal GPU acceleration",
+        )
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_name("FILE/DIR")
+                .help("Input audio file, directory or http(s) URL, or an s3:// URI with the s3 feature")
+                .required_unless_present_any(["file_list", "cache_clear"]),
+        )
+        .arg(
+            Arg::new("file_list")
+                .long("file-list")
+                .value_name("FILE")
+                .conflicts_with("input")
+                .help("Manifest of files to transcribe: one path per line, or CSV rows of path[,language][,output]"),
+        )
+        .arg(
+            Arg::new("language_map")
+                .long("language-map")
+                .value_name("FILE")
+                .help("Per-file languages: path,language rows or pattern=language globs (e.g. fr/**=fr); unmatched files are auto-detected"),
+        )
+        .subcommand_negates_reqs(true)
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE/DIR")
+                .global(true)
+                .help("Output file or directory for JSON results"),
+        )
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .value_name("BACKEND")
+                .global(true)
+                .help("Inference backend: faster-whisper, whispercpp or candle [default: faster-whisper]"),
+        )
+        .arg(
+            Arg::new("model")
+                .short('m')
+                .long("model")
+                .value_name("SIZE")
+                .global(true)
+                .help("Model size: tiny, base, small, medium, large-v2, large-v3 [default: medium]"),
+        )
+        .arg(
+            Arg::new("device")
+                .short('d')
+                .long("device")
+                .value_name("DEVICE")
+                .global(true)
+                .help("Device: auto, cpu, cuda, mps (Metal Performance Shaders for macOS) [default: auto]"),
+        )
+        .arg(
+            Arg::new("compute_type")
+                .short('c')
+                .long("compute-type")
+                .value_name("TYPE")
+                .global(true)
+                .help("Compute type: float16, float32, int8, int8_float16 [default: float16]"),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .global(true)
+                .help("Output format, or a comma-separated list written from one transcription: json, txt, srt, vtt, openai-json (OpenAI verbose_json) [default: json]"),
+        )
+        .arg(
+            Arg::new("timestamp_style")
+                .long("timestamp-style")
+                .value_name("STYLE")
+                .help("How segment times are shown on the console: clock (HH:MM:SS.mmm), short (MM:SS), seconds [default: clock]"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .help("Color console segments by confidence: green confident, yellow borderline, red likely wrong"),
+        )
+        .arg(
+            Arg::new("confidence_thresholds")
+                .long("confidence-thresholds")
+                .value_name("HIGH,LOW")
+                .help("Probabilities separating confident, borderline and likely-wrong text [default: 0.8,0.5]"),
+        )
+        .arg(
+            Arg::new("replace")
+                .long("replace")
+                .value_name("RULES_FILE")
+                .help("Fix recurring misrecognitions with tab-separated pattern/replacement rules (flags: r regex, i ignore case, w whole word)"),
+        )
+        .arg(
+            Arg::new("redact")
+                .long("redact")
+                .value_name("CATEGORIES")
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append)
+                .value_parser(redact::CATEGORIES.to_vec())
+                .help("Mask personal data in the transcript: comma-separated phone, email, credit_card"),
+        )
+        .arg(
+            Arg::new("redact_custom")
+                .long("redact-custom")
+                .value_name("REGEX")
+                .action(clap::ArgAction::Append)
+                .help("Also mask matches of this regex, counted as 'custom' (repeatable)"),
+        )
+        .arg(
+            Arg::new("redact_mask")
+                .long("redact-mask")
+                .value_name("TEMPLATE")
+                .default_value(redact::DEFAULT_MASK)
+                .help("Text that replaces redacted matches; {category} becomes the category name"),
+        )
+        .arg(
+            Arg::new("speakers")
+                .long("speakers")
+                .value_name("MAX")
+                .value_parser(clap::value_parser!(usize))
+                .help("Label likely speaker turns S1..SMAX from pauses (and loudness, for WAV in preprocess builds); a heuristic, not diarization"),
+        )
+        .arg(
+            Arg::new("speaker_gap")
+                .long("speaker-gap")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("1")
+                .help("Pause after which another speaker may start"),
+        )
+        .arg(
+            Arg::new("diarize")
+                .long("diarize")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("speakers")
+                .help("Label speakers with pyannote.audio (needs the diarization feature and a Hugging Face token)"),
+        )
+        .arg(
+            Arg::new("hf_token")
+                .long("hf-token")
+                .value_name("TOKEN")
+                .help("Hugging Face access token for the diarization model [default: $HF_TOKEN]"),
+        )
+        .arg(
+            Arg::new("split_channels")
+                .long("split-channels")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["speakers", "diarize", "incremental_save", "resume_incremental"])
+                .help("Transcribe each channel of a WAV recording on its own and label segments by channel, e.g. for stereo calls (needs the preprocess feature)"),
+        )
+        .arg(
+            Arg::new("channel")
+                .long("channel")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with_all(["split_channels", "incremental_save", "resume_incremental"])
+                .help("Transcribe only channel N of a WAV file, counting from 1, ignoring the others (needs the preprocess feature)"),
+        )
+        .arg(
+            Arg::new("channel_names")
+                .long("channel-names")
+                .value_name("NAMES")
+                .requires("split_channels")
+                .help("Comma-separated speaker names for the channels, in order [default: left,right]"),
+        )
+        .arg(
+            Arg::new("chapters")
+                .long("chapters")
+                .action(clap::ArgAction::SetTrue)
+                .help("Split the transcript into chapters at long pauses, saved next to each output as a YouTube-style list and in the JSON"),
+        )
+        .arg(
+            Arg::new("chapter_gap")
+                .long("chapter-gap")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("3")
+                .help("Pause that starts a new chapter"),
+        )
+        .arg(
+            Arg::new("chapter_min_length")
+                .long("chapter-min-length")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("60")
+                .help("Shorter chapters are merged into their neighbours"),
+        )
+        .arg(
+            Arg::new("confident_text")
+                .long("confident-text")
+                .action(clap::ArgAction::SetTrue)
+                .help("Add full_text_confident to the JSON: the text without low-confidence segments, with [...] where they were"),
+        )
+        .arg(
+            Arg::new("confident_min_logprob")
+                .long("confident-min-logprob")
+                .value_name("LOGPROB")
+                .value_parser(clap::value_parser!(f64))
+                .allow_negative_numbers(true)
+                .default_value("-1.0")
+                .help("Segments with a lower average log probability are left out of the confident text"),
+        )
+        .arg(
+            Arg::new("confident_max_no_speech")
+                .long("confident-max-no-speech")
+                .value_name("PROB")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0.6")
+                .help("Segments with a higher no-speech probability are left out of the confident text"),
+        )
+        .arg(
+            Arg::new("mark_uncertain")
+                .long("mark-uncertain")
+                .value_name("PROB")
+                .value_parser(clap::value_parser!(f64))
+                .help("Mark words below this probability in TXT and merged Markdown output so editors know where to listen; JSON is unchanged"),
+        )
+        .arg(
+            Arg::new("uncertain_markers")
+                .long("uncertain-markers")
+                .value_name("OPEN…CLOSE")
+                .default_value("⟦…⟧")
+                .help("Markers around uncertain spans in TXT output; Markdown always uses *…*"),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .action(clap::ArgAction::SetTrue)
+                .help("Add word count, speaking rate and silence statistics to the output"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of files processed concurrently in directory mode [default: 1]"),
+        )
+        .arg(
+            Arg::new("heartbeat_secs")
+                .long("heartbeat-secs")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("60")
+                .help("Log that a transcription is still running this often; 0 disables"),
+        )
+        .arg(
+            Arg::new("incremental_save")
+                .long("incremental-save")
+                .action(clap::ArgAction::SetTrue)
+                .help("Append each segment to name.json.partial.jsonl as it's decoded, so a crash doesn't lose the work"),
+        )
+        .arg(
+            Arg::new("resume_incremental")
+                .long("resume-incremental")
+                .action(clap::ArgAction::SetTrue)
+                .help("Continue from a .partial.jsonl an interrupted run left, transcribing only the audio after it (implies --incremental-save)"),
+        )
+        .arg(
+            Arg::new("ffmpeg_fallback")
+                .long("ffmpeg-fallback")
+                .action(clap::ArgAction::SetTrue)
+                .help("When a file can't be decoded, decode it with ffmpeg from PATH (or $WHISPER_FFMPEG_BIN) and transcribe the samples"),
+        )
+        .arg(
+            Arg::new("backup")
+                .long("backup")
+                .action(clap::ArgAction::SetTrue)
+                .help("Move existing outputs aside before overwriting them, e.g. to name.json.bak"),
+        )
+        .arg(
+            Arg::new("backup_style")
+                .long("backup-style")
+                .value_name("STYLE")
+                .value_parser(["simple", "timestamp"])
+                .default_value("simple")
+                .requires("backup")
+                .help("Name backups name.json.bak, .bak.1, ... (simple) or by when they were made (timestamp)"),
+        )
+        .arg(
+            Arg::new("backup_keep")
+                .long("backup-keep")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("3")
+                .requires("backup")
+                .help("Most backups kept per output; older ones are deleted"),
+        )
+        .arg(
+            Arg::new("isolation")
+                .long("isolation")
+                .value_name("MODE")
+                .value_parser(["thread", "process"])
+                .default_value("thread")
+                .help("How --jobs runs files: thread shares one model and Python interpreter; process starts a worker process with its own per job, using every core"),
+        )
+        .arg(
+            Arg::new("schedule")
+                .long("schedule")
+                .value_name("ORDER")
+                .value_parser(["longest-first", "fifo", "smallest-first"])
+                .default_value("fifo")
+                .help("Order a batch starts its files in, by probed duration: fifo keeps the listed order, longest-first balances the jobs, smallest-first gives quick results early. With fifo a directory's files start as they are found; the others list the whole directory first"),
+        )
+        .arg(
+            Arg::new("max_models")
+                .long("max-models")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Most models loaded at once, by --isolation process workers or by --benchmark configs"),
+        )
+        .arg(
+            Arg::new("max_models_mb")
+                .long("max-models-mb")
+                .value_name("MB")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Most memory the loaded models may take between them, by a per-model estimate for the compute type"),
+        )
+        .arg(
+            Arg::new("when_models_full")
+                .long("when-models-full")
+                .value_name("MODE")
+                .value_parser(["block", "evict"])
+                .default_value("block")
+                .help("When another model doesn't fit the limits: block unloads models once they're done and waits for one to be; evict keeps them for reuse and unloads the least recently used idle one"),
+        )
+        .arg(
+            Arg::new("worker")
+                .long(&pool::WORKER_FLAG[2..])
+                .action(clap::ArgAction::SetTrue)
+                .hide(true),
+        )
+        .arg(
+            Arg::new("language")
+                .short('l')
+                .long("language")
+                .value_name("LANG")
+                .global(true)
+                .help("Language code to force instead of auto-detection (e.g. en)"),
+        )
+        .arg(
+            Arg::new("allowed_languages")
+                .long("allowed-languages")
+                .value_name("LANGS")
+                .global(true)
+                .help("Only accept these auto-detected languages, comma-separated (e.g. en,es,pt)"),
+        )
+        .arg(
+            Arg::new("beam_size")
+                .long("beam-size")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Beam size for decoding [default: 5 for medium, 3 otherwise]"),
+        )
+        .arg(
+            Arg::new("best_of")
+                .long("best-of")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of candidates when sampling with non-zero temperature"),
+        )
+        .arg(
+            Arg::new("temperature")
+                .long("temperature")
+                .value_name("T")
+                .value_parser(clap::value_parser!(f64))
+                .help("Sampling temperature (0.0 for deterministic decoding)"),
+        )
+        .arg(
+            Arg::new("paragraph_gap")
+                .long("paragraph-gap")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .help("Start a new paragraph in the text after pauses longer than this"),
+        )
+        .arg(
+            Arg::new("include_tokens")
+                .long("include-tokens")
+                .action(clap::ArgAction::SetTrue)
+                .help("Keep each segment's and word's token ids in the JSON (faster-whisper only)"),
+        )
+        .arg(
+            Arg::new("no_vad")
+                .long("no-vad")
+                .action(clap::ArgAction::SetTrue)
+                .help("Disable the voice activity detection filter"),
+        )
+        .arg(
+            Arg::new("vad_threshold")
+                .long("vad-threshold")
+                .value_name("P")
+                .value_parser(clap::value_parser!(f64))
+                .help("Speech probability threshold for the VAD filter [default: 0.5]"),
+        )
+        .arg(
+            Arg::new("model_dir")
+                .long("model-dir")
+                .value_name("DIR")
+                .global(true)
+                .help("Directory models are downloaded to and loaded from"),
+        )
+        .arg(
+            Arg::new("python_venv")
+                .long("python-venv")
+                .value_name("DIR")
+                .help("Virtualenv to import faster-whisper from (env: WHISPER_VENV)"),
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .action(clap::ArgAction::SetTrue)
+                .help("Only use models already on disk; never contact the Hugging Face hub"),
+        )
+        .arg(
+            Arg::new("skip_space_check")
+                .long("skip-space-check")
+                .global(true)
+                .action(clap::ArgAction::SetTrue)
+                .help("Don't check free disk space before downloading a model or writing a batch's outputs"),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .action(clap::ArgAction::SetTrue)
+                .help("Show which files would be transcribed and where outputs would go, without loading a model"),
+        )
+        .arg(
+            Arg::new("output_template")
+                .long("output-template")
+                .value_name("TEMPLATE")
+                .help("Output file name template in directory mode, with {stem}, {ext}, {lang}, {model}, {date}, {format} [default: {stem}_transcription.{format}]"),
+        )
+        .arg(
+            Arg::new("allow_collisions")
+                .long("allow-collisions")
+                .action(clap::ArgAction::SetTrue)
+                .help("Allow several inputs to map to the same output file"),
+        )
+        .arg(
+            Arg::new("no_create_dirs")
+                .long("no-create-dirs")
+                .action(clap::ArgAction::SetTrue)
+                .help("Fail instead of creating missing output directories"),
+        )
+        .arg(
+            Arg::new("skip_existing")
+                .long("skip-existing")
+                .action(clap::ArgAction::SetTrue)
+                .help("Skip inputs whose output file already exists"),
+        )
+        .arg(
+            Arg::new("max_duration_minutes")
+                .long("max-duration-minutes")
+                .value_name("N")
+                .value_parser(clap::value_parser!(f64))
+                .help("Refuse inputs longer than this, asking first on a terminal; batches skip them. Only WAV durations can be read without decoding"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .action(clap::ArgAction::SetTrue)
+                .help("After processing existing files, keep watching the input directory for new ones"),
+        )
+        .arg(
+            Arg::new("move_done")
+                .long("move-done")
+                .action(clap::ArgAction::SetTrue)
+                .requires("watch")
+                .help("In watch mode, move each transcribed source into a done/ subfolder"),
+        )
+        .arg(
+            Arg::new("stable_secs")
+                .long("stable-secs")
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("2")
+                .help("In watch mode, wait until a new file's size is unchanged for this long"),
+        )
+        .arg(
+            Arg::new("preload")
+                .long("preload")
+                .action(clap::ArgAction::SetTrue)
+                .requires("watch")
+                .help("In watch mode, load and warm up the model at startup instead of on the first file"),
+        )
+        .arg(
+            Arg::new("state_file")
+                .long("state-file")
+                .value_name("FILE")
+                .help("Record per-file progress here and resume from it, skipping files already done"),
+        )
+        .arg(
+            Arg::new("retry_failed")
+                .long("retry-failed")
+                .action(clap::ArgAction::SetTrue)
+                .requires("state_file")
+                .help("Re-queue files the state file records as failed"),
+        )
+        .arg(
+            Arg::new("no_dedupe")
+                .long("no-dedupe")
+                .action(clap::ArgAction::SetTrue)
+                .help("Transcribe every input even when several have identical contents"),
+        )
+        .arg(
+            Arg::new("merge_output")
+                .long("merge-output")
+                .value_name("FILE")
+                .help("Also merge a directory's transcripts, in natural file order, into one .json, .md, .txt, .srt or .vtt document"),
+        )
+        .arg(
+            Arg::new("summary")
+                .long("summary")
+                .action(clap::ArgAction::SetTrue)
+                .help("Write batch_summary.json into the output directory after a directory run"),
+        )
+        .arg(
+            Arg::new("summary_csv")
+                .long("summary-csv")
+                .value_name("FILE")
+                .help("Write one CSV row per input file to FILE after a batch run"),
+        )
+        .arg(
+            Arg::new("timing_report")
+                .long("timing-report")
+                .value_name("FILE")
+                .help("Write per-file phase timings (decode, model wait, inference, post-processing, output) and their percentiles to FILE as JSON after a batch run"),
+        )
+        .arg(
+            Arg::new("archive")
+                .long("archive")
+                .value_name("FILE")
+                .help("Zip a batch's outputs, its CSV summary and any errors into FILE"),
+        )
+        .arg(
+            Arg::new("archive_compression")
+                .long("archive-compression")
+                .value_name("METHOD")
+                .value_parser(["stored", "deflate"])
+                .default_value("deflate")
+                .help("How --archive entries are compressed"),
+        )
+        .arg(
+            Arg::new("max_download_size")
+                .long("max-download-size")
+                .value_name("SIZE")
+                .value_parser(download::parse_size)
+                .help("Refuse URL inputs larger than this, e.g. 500M or 2G"),
+        )
+        .arg(
+            Arg::new("download_retries")
+                .long("download-retries")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("5")
+                .help("Retries after a URL download is interrupted; resumes where the server allows"),
+        )
+        .arg(
+            Arg::new("expect_sha256")
+                .long("expect-sha256")
+                .value_name("HEX")
+                .value_parser(download::parse_sha256)
+                .help("Fail unless the downloaded URL input has this SHA-256"),
+        )
+        .arg(
+            Arg::new("fail_fast")
+                .long("fail-fast")
+                .action(clap::ArgAction::SetTrue)
+                .help("Stop a directory batch at the first failed file"),
+        )
+        .arg(
+            Arg::new("cache_dir")
+                .long("cache-dir")
+                .value_name("DIR")
+                .help("Reuse results for audio already transcribed with the same settings, stored in this directory"),
+        )
+        .arg(
+            Arg::new("cache_clear")
+                .long("cache-clear")
+                .action(clap::ArgAction::SetTrue)
+                .requires("cache_dir")
+                .help("Delete every entry in the --cache-dir cache before running"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .global(true)
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("verbose")
+                .help("Only log errors"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .global(true)
+                .action(clap::ArgAction::Count)
+                .help("Increase log verbosity (-v debug, -vv trace)"),
+        )
+        .arg(
+            Arg::new("log_format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .global(true)
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .help("Log as human-readable lines or as one JSON object per line"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .conflicts_with("no_config")
+                .help("Read default options from this config file instead of ~/.config/whisper-cli/config.toml"),
+        )
+        .arg(
+            Arg::new("no_config")
+                .long("no-config")
+                .action(clap::ArgAction::SetTrue)
+                .help("Ignore the config file"),
+        )
+        .arg(
+            Arg::new("benchmark")
+                .short('b')
+                .long("benchmark")
+                .action(clap::ArgAction::SetTrue)
+                .help(
+                    "Run comprehensive benchmark comparing CPU vs Metal and different model sizes",
+                ),
+        )
+        .arg(
+            Arg::new("benchmark_sort")
+                .long("benchmark-sort")
+                .value_name("KEY")
+                .value_parser(["rtf", "time", "memory", "accuracy", "agreement"])
+                .requires("benchmark")
+                .help("Sort the benchmark table by rtf (default), time, memory, accuracy or agreement"),
+        )
+        .arg(
+            Arg::new("benchmark_order")
+                .long("benchmark-order")
+                .value_name("ORDER")
+                .value_parser(["asc", "desc"])
+                .requires("benchmark")
+                .help("Sort the benchmark table ascending or descending [default: best first]"),
+        )
+        .arg(
+            Arg::new("benchmark_only")
+                .long("benchmark-only")
+                .value_name("FIELD=VALUE")
+                .action(clap::ArgAction::Append)
+                .requires("benchmark")
+                .help("Only show benchmark rows matching, e.g. device=mps (backend, model, device, compute, label, beam or vad); repeatable"),
+        )
+        .arg(
+            Arg::new("bench_warmup")
+                .long("bench-warmup")
+                .value_name("RUNS")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("0")
+                .requires("benchmark")
+                .help("Full transcriptions per config before the measured one, recorded as warmup_times but not counted, so kernel compilation and cold caches aren't measured"),
+        )
+        .arg(
+            Arg::new("detect_throttling")
+                .long("detect-throttling")
+                .action(clap::ArgAction::SetTrue)
+                .requires("benchmark")
+                .help("Time the tiny model on the first 10s of the audio before, between and after the configs, and mark results with throttling_suspected when it slows down (needs ffmpeg)"),
+        )
+        .arg(
+            Arg::new("throttle_threshold")
+                .long("throttle-threshold")
+                .value_name("PERCENT")
+                .value_parser(clap::value_parser!(f64))
+                .requires("detect_throttling")
+                .help("How much slower than at first the reference workload may run before throttling is suspected [default: 15]"),
+        )
+        .arg(
+            Arg::new("cooldown_secs")
+                .long("cooldown-secs")
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .requires("detect_throttling")
+                .help("While throttling is suspected before a config, pause this long and check again, up to 10 times"),
+        )
+        .arg(
+            Arg::new("save_transcripts")
+                .long("save-transcripts")
+                .value_name("DIR")
+                .requires("benchmark")
+                .help("Save each config's full transcript as JSON in DIR, named after the config, with its path in the results"),
+        )
+        .arg(
+            Arg::new("medium_benchmark")
+                .long("medium-bench")
+                .action(clap::ArgAction::SetTrue)
+                .help(
+                    "Run specific benchmark comparing base vs medium model on Metal acceleration",
+                ),
+        )
+        .subcommand(config_command())
+        .subcommand(benchmark_command())
+        .subcommand(models_command())
+        .subcommand(listen_command())
+        .subcommand(align_command())
+        .subcommand(vad_command())
+        .subcommand(convert_command())
+        .subcommand(adjust_command())
+        .subcommand(stitch_command())
+        .subcommand(search_command())
+}
+
+/// The `config` subcommand
+fn config_command() -> Command {
+    Command::new("config")
+        .about("Manage the configuration file")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("init")
+                .about("Write a commented default configuration file")
+                .arg(
+                    Arg::new("path").long("path").value_name("FILE").help(
+                        "Where to write the file [default: ~/.config/whisper-cli/config.toml]",
+                    ),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Overwrite an existing file"),
+                ),
+        )
+}
+
+/// The `benchmark` subcommand
+fn benchmark_command() -> Command {
+    Command::new("benchmark")
+        .about("Look through saved benchmark results")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("query")
+                .about("Show the fastest saved config for each model on this kind of machine")
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .required(true)
+                        .help("Directory of results saved by --benchmark -o"),
+                )
+                .arg(
+                    Arg::new("best_for")
+                        .long("best-for")
+                        .value_name("MODEL")
+                        .help("Only answer for this model size, e.g. medium"),
+                )
+                .arg(
+                    Arg::new("machine")
+                        .long("machine")
+                        .value_name("CLASS")
+                        .help("Machine class (os-arch, e.g. macos-aarch64) the results must come from, or `any` [default: this machine's]"),
+                )
+                .arg(
+                    Arg::new("csv")
+                        .long("csv")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print every matching result as CSV instead"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare two saved benchmark runs config by config")
+                .arg(
+                    Arg::new("old")
+                        .value_name("OLD")
+                        .required(true)
+                        .help("Results saved by an earlier --benchmark -o"),
+                )
+                .arg(
+                    Arg::new("new")
+                        .value_name("NEW")
+                        .required(true)
+                        .help("Results saved by a later run"),
+                )
+                .arg(
+                    Arg::new("threshold")
+                        .long("threshold")
+                        .value_name("PERCENT")
+                        .value_parser(clap::value_parser!(f64))
+                        .help("Exit 1 when any config got slower, used more memory or lost accuracy by more than this many percent"),
+                )
+                .arg(
+                    Arg::new("color")
+                        .long("color")
+                        .value_name("WHEN")
+                        .value_parser(["auto", "always", "never"])
+                        .default_value("auto")
+                        .help("Color improvements green and regressions red"),
+                ),
+        )
+}
+
+/// The `models` subcommand
+fn models_command() -> Command {
+    Command::new("models")
+        .about("Manage downloaded models")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("pull")
+                .about("Download models ahead of time without transcribing anything")
+                .arg(
+                    Arg::new("names")
+                        .value_name("NAME")
+                        .num_args(1..)
+                        .required(true)
+                        .help("Model names or hub repositories, e.g. medium large-v3"),
+                ),
+        )
+        .subcommand(
+            Command::new("cached")
+                .about("List cached models with their size on disk and last use")
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .value_name("KEY")
+                        .value_parser(["name", "size", "last-used"])
+                        .default_value("name")
+                        .help(
+                            "Order by name, size (largest first) or last-used (most recent first)",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Delete a cached model")
+                .arg(
+                    Arg::new("name")
+                        .value_name("NAME")
+                        .required(true)
+                        .help("Model name or hub repository, as `models cached` lists it"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .short('y')
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Delete without asking"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check that a cached model is complete and uncorrupted")
+                .arg(Arg::new("name").value_name("NAME").help(
+                    "Model name, hub repository or directory [default: the configured model]",
+                ))
+                .arg(
+                    Arg::new("repair")
+                        .long("repair")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Delete a damaged model and download it again"),
+                ),
+        )
+}
+
+/// The `listen` subcommand
+fn listen_command() -> Command {
+    Command::new("listen")
+        .about("Transcribe the microphone live until Ctrl-C, then write the whole transcript (needs the mic feature)")
+        .arg(
+            Arg::new("window")
+                .long("window")
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("10")
+                .help("Seconds of audio transcribed at a time"),
+        )
+        .arg(
+            Arg::new("overlap")
+                .long("overlap")
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("2")
+                .help("Seconds each window re-transcribes from the end of the previous one"),
+        )
+}
 
-    let matches = Command::new("FasterWhisper Rust Transcriber")
-        .version("1.0")
-        .author("Your Name")
-        .about(
-            "High-performance audio transcription using faster-whisper with Metal GPU acceleration",
+/// The `align` subcommand
+fn align_command() -> Command {
+    Command::new("align")
+        .about("Time an existing transcript against the audio, e.g. subtitles for scripted content")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_name("FILE")
+                .required(true)
+                .help("Audio file the transcript was read from"),
+        )
+        .arg(
+            Arg::new("transcript")
+                .long("transcript")
+                .value_name("FILE")
+                .required(true)
+                .help("Plain-text transcript whose words are kept as written"),
         )
+}
+
+/// The `vad` subcommand
+fn vad_command() -> Command {
+    Command::new("vad")
+        .about("List speech regions with the VAD alone, to screen files for speech without transcribing")
         .arg(
             Arg::new("input")
                 .short('i')
                 .long("input")
                 .value_name("FILE/DIR")
-                .help("Input audio file or directory")
-                .required(true),
+                .required(true)
+                .help("Audio file, or directory of audio files"),
         )
         .arg(
-            Arg::new("output")
-                .short('o')
-                .long("output")
-                .value_name("FILE/DIR")
-                .help("Output file or directory for JSON results"),
+            Arg::new("json")
+                .long("json")
+                .action(clap::ArgAction::SetTrue)
+                .help("Print the regions and totals as JSON"),
         )
         .arg(
-            Arg::new("model")
-                .short('m')
-                .long("model")
-                .value_name("SIZE")
-                .help("Model size: tiny, base, small, medium, large-v2, large-v3")
-                .default_value("medium"),
+            Arg::new("threshold")
+                .long("threshold")
+                .value_name("P")
+                .value_parser(clap::value_parser!(f64))
+                .help("Speech probability threshold [default: the --vad-threshold setting]"),
         )
         .arg(
-            Arg::new("device")
-                .short('d')
-                .long("device")
-                .value_name("DEVICE")
-                .help("Device: auto, cpu, cuda, mps (Metal Performance Shaders for macOS)")
-                .default_value("auto"),
+            Arg::new("min_speech_ms")
+                .long("min-speech-ms")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Drop speech shorter than this"),
         )
         .arg(
-            Arg::new("compute_type")
-                .short('c')
-                .long("compute-type")
-                .value_name("TYPE")
-                .help("Compute type: float16, float32, int8")
-                .default_value("float16"),
+            Arg::new("min_silence_ms")
+                .long("min-silence-ms")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Silence needed to end a region"),
         )
         .arg(
-            Arg::new("benchmark")
-                .short('b')
-                .long("benchmark")
+            Arg::new("speech_pad_ms")
+                .long("speech-pad-ms")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Padding added around each region"),
+        )
+}
+
+/// The `convert` subcommand
+fn convert_command() -> Command {
+    Command::new("convert")
+        .about("Convert a whisper.cpp JSON transcript (from --output-json) to another format, without transcribing")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_name("FILE")
+                .required(true)
+                .help("whisper.cpp JSON file"),
+        )
+}
+
+/// The `adjust` subcommand
+fn adjust_command() -> Command {
+    Command::new("adjust")
+        .about("Shift or stretch the timestamps of a transcription JSON, e.g. after trimming the recording")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_name("FILE")
+                .required(true)
+                .help("Transcription JSON written by this tool"),
+        )
+        .arg(
+            Arg::new("shift")
+                .long("shift")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .allow_negative_numbers(true)
+                .help("Add this to every timestamp; negative after trimming the start. Segments ending before zero are dropped"),
+        )
+        .arg(
+            Arg::new("scale")
+                .long("scale")
+                .value_name("FACTOR")
+                .value_parser(clap::value_parser!(f64))
+                .help("Multiply every timestamp by this before shifting, to correct drift such as 1.001"),
+        )
+}
+
+/// The `stitch` subcommand
+fn stitch_command() -> Command {
+    Command::new("stitch")
+        .about("Transcribe or load the chunks of a split recording and write one transcript in the original file's time")
+        .arg(
+            Arg::new("manifest")
+                .long("manifest")
+                .value_name("FILE")
+                .required(true)
+                .help("CSV of file,offset_seconds rows; files are audio or transcription JSON, relative to the manifest"),
+        )
+        .arg(
+            Arg::new("auto_offsets")
+                .long("auto-offsets")
                 .action(clap::ArgAction::SetTrue)
-                .help(
-                    "Run comprehensive benchmark comparing CPU vs Metal and different model sizes",
-                ),
+                .help("Leave offsets out of the manifest and start each chunk where the previous one ends"),
         )
         .arg(
-            Arg::new("medium_benchmark")
-                .long("medium-bench")
+            Arg::new("overlap_gap")
+                .long("overlap-gap")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("1.5")
+                .help("Segments at a chunk boundary starting this close together may be the same speech cut twice"),
+        )
+        .arg(
+            Arg::new("overlap_similarity")
+                .long("overlap-similarity")
+                .value_name("RATIO")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0.6")
+                .help("Share of words two such segments need in common to keep only one"),
+        )
+}
+
+/// The `search` subcommand
+fn search_command() -> Command {
+    Command::new("search")
+        .about("Print when a word or phrase was said; exits 1 when it wasn't found")
+        .arg(
+            Arg::new("query")
+                .value_name("QUERY")
+                .required(true)
+                .help("Word or phrase to find, case-insensitively"),
+        )
+        .arg(
+            Arg::new("transcript")
+                .value_name("FILE")
+                .required(true)
+                .help("A transcription JSON, or an audio file to transcribe first"),
+        )
+        .arg(
+            Arg::new("fuzzy")
+                .long("fuzzy")
                 .action(clap::ArgAction::SetTrue)
-                .help(
-                    "Run specific benchmark comparing base vs medium model on Metal acceleration",
-                ),
+                .help("Also match words one letter off, for misrecognized names"),
         )
-        .get_matches();
+        .arg(
+            Arg::new("context")
+                .long("context")
+                .value_name("WORDS")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("5")
+                .help("Words shown either side of each match"),
+        )
+}
 
-    let input_path = PathBuf::from(matches.get_one::<String>("input").unwrap());
-    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
-    let model_size = matches.get_one::<String>("model").unwrap();
-    let device = matches.get_one::<String>("device").unwrap();
-    let compute_type = matches.get_one::<String>("compute_type").unwrap();
-    let run_benchmark_mode = matches.get_flag("benchmark");
-    let medium_benchmark = matches.get_flag("medium_benchmark");
-
-    if run_benchmark_mode {
-        if input_path.is_file() {
-            return run_benchmark(input_path, output_path).await;
-        } else {
+#[tokio::main]
+async fn main() -> Result<()> {
+    let matches = cli().get_matches();
+
+    init_logging(&matches)?;
+
+    if let Some(("config", config_matches)) = matches.subcommand() {
+        if let Some(("init", init_matches)) = config_matches.subcommand() {
+            return run_config_init(init_matches);
+        }
+    }
+    if let Some(("benchmark", benchmark_matches)) = matches.subcommand() {
+        if let Some(("query", query_matches)) = benchmark_matches.subcommand() {
+            return run_benchmark_query(query_matches);
+        }
+        if let Some(("diff", diff_matches)) = benchmark_matches.subcommand() {
+            return run_benchmark_diff(diff_matches);
+        }
+    }
+
+    let settings = resolve_settings(&matches)?;
+    info!("Effective configuration: {}", settings);
+
+    // Before anything imports faster_whisper
+    if let Some(venv) = &settings.python_venv {
+        PythonEnv::from_venv(venv)?.activate()?;
+    }
+
+    match matches.subcommand() {
+        Some(("listen", listen_matches)) => run_listen(listen_matches, &settings).await,
+        Some(("align", align_matches)) => run_align(align_matches, &settings).await,
+        Some(("vad", vad_matches)) => run_vad(vad_matches, &settings).await,
+        Some(("convert", convert_matches)) => run_convert(convert_matches, &settings).await,
+        Some(("adjust", adjust_matches)) => run_adjust(adjust_matches, &settings).await,
+        Some(("stitch", stitch_matches)) => run_stitch(stitch_matches, &settings).await,
+        Some(("search", search_matches)) => run_search(search_matches, &settings).await,
+        Some(("models", models_matches)) => run_models(models_matches, &settings),
+        _ => run_transcription(&matches, &settings).await,
+    }
+}
+
+/// The settings of this run. Precedence: CLI flag > WHISPER_* environment variable > config
+/// file > built-in default.
+fn resolve_settings(matches: &ArgMatches) -> Result<Settings> {
+    let mut layers = cli_settings(matches)?
+        .or(PartialSettings::from_process_env()?)
+        .or(file_settings(matches)?);
+    if let Some(("listen", _)) = matches.subcommand() {
+        // Live transcription needs a model small enough to keep up with the microphone
+        layers = layers.or(PartialSettings {
+            model: Some("base".to_string()),
+            ..Default::default()
+        });
+    }
+    Ok(layers.resolve())
+}
+
+fn run_models(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    match matches.subcommand() {
+        Some(("verify", verify_matches)) => run_models_verify(verify_matches, settings),
+        Some(("pull", pull_matches)) => run_models_pull(pull_matches, settings),
+        Some(("cached", cached_matches)) => run_models_cached(cached_matches, settings),
+        Some(("rm", rm_matches)) => run_models_rm(rm_matches, settings),
+        _ => unreachable!("clap requires a models subcommand"),
+    }
+}
+
+/// Transcribe what `--input` or `--file-list` names: a file, a directory, a manifest, a URL
+/// or an s3:// URI
+async fn run_transcription(matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    let cache = matches.get_one::<String>("cache_dir").map(ResultCache::new);
+    if matches.get_flag("worker") {
+        return run_worker(matches, settings, cache).await;
+    }
+    if let Some(cache) = cache.as_ref().filter(|_| matches.get_flag("cache_clear")) {
+        let removed = cache.clear()?;
+        info!(
+            "Cleared {} cached result(s) from {}",
+            removed,
+            cache.dir().display()
+        );
+        if !matches.contains_id("input") && !matches.contains_id("file_list") {
+            return Ok(());
+        }
+    }
+
+    if let Some(uri) = matches
+        .get_one::<String>("input")
+        .filter(|input| input.starts_with("s3://"))
+    {
+        return run_s3(matches, settings, cache, uri).await;
+    }
+    if let Some(url) = matches
+        .get_one::<String>("input")
+        .filter(|input| download::is_url(input))
+    {
+        return run_url(matches, settings, cache, url).await;
+    }
+    if matches
+        .get_one::<String>("output")
+        .is_some_and(|output| output.starts_with("s3://"))
+    {
+        anyhow::bail!("An s3:// --output needs s3:// input");
+    }
+    let input_path = matches
+        .get_one::<String>("input")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    if matches.get_flag("watch") && (matches.contains_id("file_list") || !input_path.is_dir()) {
+        error!("Watch mode requires a directory as input");
+        std::process::exit(1);
+    }
+    if matches.get_flag("benchmark") || matches.get_flag("medium_benchmark") {
+        return run_benchmark_mode(matches, settings, input_path).await;
+    }
+
+    let Some(run) = plan_run(matches, settings)? else {
+        return Ok(());
+    };
+    if run.single_file && run.plan.files[0].action == PlannedAction::TooLong {
+        let file = &run.plan.files[0];
+        let error = run
+            .plan_options
+            .check_duration(&file.input, file.duration)
+            .unwrap_err();
+        confirm_long_input(error)?;
+    }
+
+    let output_options = output_options(matches, settings)?;
+
+    // Fail before loading a model rather than after transcribing into a missing directory
+    plan::prepare_output_dirs(
+        &run.plan,
+        run.plan_options.output_dir.as_deref(),
+        !matches.get_flag("no_create_dirs"),
+    )?;
+
+    if !matches.get_flag("skip_space_check") {
+        check_space(settings, &run)?;
+    }
+
+    let transcriber = load_transcriber(matches, settings, cache, run.process_isolation)?;
+    if run.single_file {
+        run_single_file(matches, run, transcriber.as_ref(), output_options).await
+    } else {
+        run_directory(matches, settings, run, transcriber, output_options).await
+    }
+}
+
+/// `--benchmark` and `--medium-bench`, which time a single file
+async fn run_benchmark_mode(
+    matches: &ArgMatches,
+    settings: &Settings,
+    input_path: PathBuf,
+) -> Result<()> {
+    if matches.get_flag("benchmark") {
+        if !input_path.is_file() {
             error!("Benchmark mode requires a single audio file as input");
             std::process::exit(1);
         }
+        return run_benchmark(
+            input_path,
+            matches.get_one::<String>("output").map(PathBuf::from),
+            benchmark_view(matches)?,
+            explicit_model_limits(matches)?,
+            *matches.get_one::<usize>("bench_warmup").unwrap(),
+            throttle_check(matches)?,
+            matches
+                .get_one::<String>("save_transcripts")
+                .map(PathBuf::from),
+        )
+        .await;
+    }
+    if !input_path.is_file() {
+        error!("Medium benchmark mode requires a single audio file as input");
+        std::process::exit(1);
+    }
+    run_medium_model_benchmark(
+        input_path,
+        &settings.model.device,
+        &settings.model.compute_type,
+    )
+    .await
+}
+
+/// What a transcription run will do, worked out before any model is loaded
+struct RunPlan {
+    plan: BatchPlan,
+    plan_options: PlanOptions,
+    input_path: PathBuf,
+    output_path: Option<PathBuf>,
+    /// One file, rather than a batch of them
+    single_file: bool,
+    watch: bool,
+    /// A directory still being listed, whose files are planned as they are found
+    discovery: Option<Discovery>,
+    /// Where `--state-file` keeps the batch's progress, and what it held
+    state: Option<(BatchState, PathBuf)>,
+    merge_output: Option<(PathBuf, MergeFormat)>,
+    process_isolation: bool,
+}
+
+/// Plan the files of the run, deduplicated and checked for colliding outputs. `None` when
+/// there is nothing to transcribe; a dry run prints the plan and exits.
+fn plan_run(matches: &ArgMatches, settings: &Settings) -> Result<Option<RunPlan>> {
+    let file_list = matches.get_one::<String>("file_list").map(PathBuf::from);
+    let input_path = matches
+        .get_one::<String>("input")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let single_file = file_list.is_none() && input_path.is_file();
+    let watch_mode = matches.get_flag("watch");
+    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
+
+    let max_duration_minutes = matches.get_one::<f64>("max_duration_minutes").copied();
+    if max_duration_minutes.is_some_and(|minutes| !(minutes.is_finite() && minutes > 0.0)) {
+        anyhow::bail!("--max-duration-minutes must be a positive number of minutes");
     }
 
-    if medium_benchmark {
-        if input_path.is_file() {
-            return run_medium_model_benchmark(input_path, device, compute_type).await;
+    let plan_options = PlanOptions {
+        output_dir: if !single_file {
+            output_path.clone()
         } else {
-            error!("Medium benchmark mode requires a single audio file as input");
-            std::process::exit(1);
+            None
+        },
+        format: settings.format,
+        skip_existing: matches.get_flag("skip_existing"),
+        probe_durations: true,
+        template: match matches.get_one::<String>("output_template") {
+            Some(template) => OutputTemplate::parse(template)?,
+            None => OutputTemplate::default(),
+        },
+        model: settings.model.model_size.clone(),
+        language: settings.options.language.clone(),
+        language_map: matches
+            .get_one::<String>("language_map")
+            .map(language::load_language_map)
+            .transpose()?,
+        max_duration: max_duration_minutes.map(|minutes| minutes * 60.0),
+        ..Default::default()
+    };
+    let schedule: Schedule = matches
+        .get_one::<String>("schedule")
+        .unwrap()
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+
+    // Without anything that needs the whole list up front, transcription starts while a
+    // large directory is still being listed
+    let stream_discovery = !single_file
+        && !watch_mode
+        && file_list.is_none()
+        && input_path.is_dir()
+        && schedule == Schedule::Fifo
+        && !matches.get_flag("dry_run")
+        && matches.get_one::<String>("merge_output").is_none()
+        && matches.get_one::<String>("state_file").is_none();
+    let mut discovery = None;
+    let mut plan = if stream_discovery {
+        discovery = Some(plan::stream_audio_files(
+            &input_path,
+            plan::DISCOVERY_CAPACITY,
+        )?);
+        BatchPlan { files: Vec::new() }
+    } else if let Some(file_list) = &file_list {
+        let entries = manifest::load_manifest(file_list)?;
+        if entries.is_empty() {
+            warn!("Manifest lists no files: {}", file_list.display());
+            if matches.get_flag("dry_run") {
+                std::process::exit(1);
+            }
+            return Ok(None);
+        }
+        plan::plan_manifest(entries, &plan_options)
+    } else if single_file {
+        BatchPlan {
+            files: vec![plan::plan_file(
+                input_path.clone(),
+                output_path.clone(),
+                &plan_options,
+            )],
+        }
+    } else if input_path.is_dir() {
+        let audio_files = plan::discover_audio_files(&input_path)?;
+        if audio_files.is_empty() && !watch_mode {
+            warn!(
+                "No audio files found in directory: {}",
+                input_path.display()
+            );
+            if matches.get_flag("dry_run") {
+                std::process::exit(1);
+            }
+            return Ok(None);
+        }
+        plan::plan_batch(audio_files, &plan_options)
+    } else {
+        error!("Input path does not exist: {}", input_path.display());
+        std::process::exit(1);
+    };
+
+    let mut process_isolation = matches.get_one::<String>("isolation").unwrap() == "process";
+    if process_isolation && single_file {
+        warn!("--isolation process only applies to batch runs; ignoring it");
+        process_isolation = false;
+    }
+    if process_isolation && watch_mode {
+        anyhow::bail!("--isolation process doesn't support --watch yet");
+    }
+
+    if single_file && output_path.is_none() && !settings.extra_formats.is_empty() {
+        warn!(
+            "Printing {} to the console; the other formats are only written with --output",
+            settings.format
+        );
+    }
+
+    // Validate the merge target up front rather than after a long batch
+    let merge_output = match matches.get_one::<String>("merge_output") {
+        Some(_) if single_file => {
+            warn!("--merge-output only applies to batch runs; ignoring it");
+            None
+        }
+        Some(path) => {
+            let path = PathBuf::from(path);
+            let format = MergeFormat::from_path(&path)?;
+            Some((path, format))
+        }
+        None => None,
+    };
+    if merge_output.is_some() {
+        plan.files.sort_by(|a, b| {
+            merge::natural_cmp(&a.input.to_string_lossy(), &b.input.to_string_lossy())
+        });
+    }
+    if !single_file {
+        plan.schedule(schedule);
+        log_schedule(&plan, schedule, settings.jobs);
+    }
+
+    let mut state = None;
+    if let Some(state_path) = matches.get_one::<String>("state_file").map(PathBuf::from) {
+        if single_file {
+            warn!("--state-file only applies to batch runs; ignoring it");
+        } else {
+            let mut batch_state = BatchState::load(&state_path)?;
+            batch_state.apply_to_plan(&mut plan, matches.get_flag("retry_failed"));
+            let (pending, done, failed) = batch_state.counts();
+            info!(
+                "Resuming from {}: {} done, {} failed, {} pending",
+                state_path.display(),
+                done,
+                failed,
+                pending
+            );
+            state = Some((batch_state, state_path));
+        }
+    }
+
+    if !single_file && !matches.get_flag("no_dedupe") {
+        for group in plan.dedupe() {
+            info!(
+                "Deduplicating {} identical input(s) of {}",
+                group.duplicates.len(),
+                group.primary.display()
+            );
+        }
+    }
+
+    if !matches.get_flag("allow_collisions") {
+        plan.check_collisions()?;
+    }
+
+    if matches.get_flag("dry_run") {
+        print_plan(&plan);
+        std::process::exit(if plan.has_work() { 0 } else { 1 });
+    }
+
+    Ok(Some(RunPlan {
+        plan,
+        plan_options,
+        input_path,
+        output_path,
+        single_file,
+        watch: watch_mode,
+        discovery,
+        state,
+        merge_output,
+        process_isolation,
+    }))
+}
+
+/// Fail when the model won't fit in its cache, and warn when a batch's outputs may not fit
+/// in the output directory
+fn check_space(settings: &Settings, run: &RunPlan) -> Result<()> {
+    if settings.model.backend == Backend::FasterWhisper && !settings.model.offline {
+        check_model_space(&settings.model.model_size, settings)?;
+    }
+    if run.discovery.is_none() {
+        if let Some(mut budget) = output_budget(settings, run) {
+            budget.add(run.plan.known_duration().0);
         }
     }
+    Ok(())
+}
+
+/// What the output directory of a batch has free for its transcripts, when it can be told
+fn output_budget(settings: &Settings, run: &RunPlan) -> Option<space::OutputBudget> {
+    let dir = run
+        .plan_options
+        .output_dir
+        .as_ref()
+        .filter(|_| !run.single_file)?;
+    let formats = std::iter::once(settings.format)
+        .chain(settings.extra_formats.iter().copied())
+        .collect();
+    let available = space::available_space(dir)?;
+    Some(space::OutputBudget::new(
+        dir,
+        available,
+        formats,
+        settings.options.word_timestamps,
+    ))
+}
 
-    // Initialize the transcriber
-    let config = ModelConfig::new(model_size, device, compute_type);
-    let transcriber = FasterWhisperTranscriber::new(config)
+/// The backend the settings ask for, behind the result cache when there is one, loaded
+/// now with `--preload`
+fn load_transcriber(
+    matches: &ArgMatches,
+    settings: &Settings,
+    cache: Option<ResultCache>,
+    process_isolation: bool,
+) -> Result<Box<dyn TranscriptionBackend>> {
+    let transcriber = backend::create(settings.model.clone(), settings.options.clone())
         .map_err(|e| anyhow::anyhow!("Failed to create transcriber: {}", e))?;
+    let transcriber: Box<dyn TranscriptionBackend> = match cache {
+        Some(cache) => Box::new(CachedBackend::new(transcriber, cache)),
+        None => transcriber,
+    };
 
+    let model_size = &settings.model.model_size;
     info!("🚀 FasterWhisper Rust Transcriber starting...");
     info!(
-        "Model: {}, Device: {}, Compute Type: {}",
-        model_size, device, compute_type
+        "Backend: {}, Model: {}, Device: {}, Compute Type: {}",
+        settings.model.backend, model_size, settings.model.device, settings.model.compute_type
     );
 
-    if input_path.is_file() {
-        // Single file
-        transcribe_file(&transcriber, input_path, output_path).await?;
-    } else if input_path.is_dir() {
-        // Directory - find all audio files
-        let mut audio_files = Vec::new();
-        let mut entries = fs::read_dir(&input_path).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if let Some(ext) = path.extension() {
-                let ext = ext.to_string_lossy().to_lowercase();
-                if matches!(
-                    ext.as_str(),
-                    "wav" | "mp3" | "flac" | "m4a" | "ogg" | "mp4" | "webm"
-                ) {
-                    audio_files.push(path);
-                }
+    if matches.get_flag("preload") && process_isolation {
+        warn!("--preload doesn't apply to --isolation process; each worker loads its own model");
+    } else if matches.get_flag("preload") {
+        let report = transcriber
+            .warmup()
+            .map_err(|e| anyhow::anyhow!("Failed to preload model: {}", e))?;
+        info!(
+            event = "model_preloaded",
+            model = model_size.as_str(),
+            load_time = report.load_time.as_secs_f64(),
+            warmup_time = report.warmup_time.as_secs_f64(),
+            downloaded = report.downloaded;
+            "Model {} {} in {:.2}s, warmed up in {:.2}s",
+            model_size,
+            if report.downloaded { "downloaded and loaded" } else { "loaded" },
+            report.load_time.as_secs_f64(),
+            report.warmup_time.as_secs_f64()
+        );
+    }
+    Ok(transcriber)
+}
+
+async fn run_single_file(
+    matches: &ArgMatches,
+    run: RunPlan,
+    transcriber: &dyn TranscriptionBackend,
+    mut output_options: OutputOptions,
+) -> Result<()> {
+    output_options.progress = std::io::stderr().is_terminal() && !matches.get_flag("quiet");
+    if run.plan.files[0].action == PlannedAction::SkipExisting {
+        info!(
+            "Skipping {}: output already exists",
+            run.input_path.display()
+        );
+        return Ok(());
+    }
+    transcribe_file(
+        transcriber,
+        run.input_path,
+        run.output_path,
+        &output_options,
+        None,
+    )
+    .await?;
+    info!("🎉 All transcriptions completed successfully!");
+    Ok(())
+}
+
+/// Transcribe a directory or manifest, write the reports asked for, and with `--watch` go
+/// on to the files dropped in later. Exits with the batch's exit code when files failed.
+async fn run_directory(
+    matches: &ArgMatches,
+    settings: &Settings,
+    run: RunPlan,
+    transcriber: Box<dyn TranscriptionBackend>,
+    output_options: OutputOptions,
+) -> Result<()> {
+    let isolation = if run.process_isolation {
+        let (limits, _) = model_limits(matches)?;
+        let estimate =
+            models::estimated_memory_mb(&settings.model.model_size, &settings.model.compute_type);
+        let max_workers = limits.max_copies(estimate);
+        if let Some(max) = max_workers.filter(|&max| max < settings.jobs) {
+            warn!(
+                "Only {} worker(s) fit the model limits{}; the other jobs wait for one",
+                max,
+                estimate
+                    .map(|mb| format!(" at about {} MB per model", mb))
+                    .unwrap_or_default()
+            );
+        }
+        Isolation::Process(worker_pool(max_workers)?)
+    } else {
+        Isolation::Thread(transcriber.as_ref())
+    };
+    let writer = BatchWriter {
+        isolation: &isolation,
+        output_options: &output_options,
+        runtime: tokio::runtime::Handle::current(),
+    };
+    let (shutdown, listener) = stop_on_ctrl_c(&writer.runtime);
+    let directory_options = batch::DirectoryOptions {
+        jobs: settings.jobs,
+        fail_fast: matches.get_flag("fail_fast"),
+        cancel: Some(&shutdown),
+    };
+    // Only the files found so far are known, so their outputs are checked as they are
+    let space = match &run.discovery {
+        Some(_) if !matches.get_flag("skip_space_check") => output_budget(settings, &run),
+        _ => None,
+    };
+    let (report, results) = match run.discovery {
+        Some(discovery) => {
+            info!(
+                "Transcribing audio files as they are found in {}",
+                run.input_path.display()
+            );
+            let progress = discovery.progress();
+            let report = tokio::task::block_in_place(|| {
+                batch::run_discovered(
+                    &writer,
+                    discovery,
+                    &run.plan_options,
+                    &directory_options,
+                    batch::DiscoveryOptions {
+                        allow_collisions: matches.get_flag("allow_collisions"),
+                        create_dirs: !matches.get_flag("no_create_dirs"),
+                        dedupe: !matches.get_flag("no_dedupe"),
+                        space,
+                    },
+                )
+            });
+            listener.abort();
+            if progress.total() == Some(0) {
+                warn!(
+                    "No audio files found in directory: {}",
+                    run.input_path.display()
+                );
+                return Ok(());
             }
+            (report, Vec::new())
         }
+        None => {
+            info!("Found {} audio files", run.plan.files.len());
+            let (state, state_file) = run.state.unzip();
+            let finished = tokio::task::block_in_place(|| {
+                batch::run_planned(
+                    &writer,
+                    run.plan.files,
+                    &directory_options,
+                    state.zip(state_file.as_deref()),
+                    run.merge_output.is_some(),
+                )
+            });
+            listener.abort();
+            finished
+        }
+    };
 
-        if audio_files.is_empty() {
+    if let Some((merge_path, merge_format)) = &run.merge_output {
+        if report.failed() > 0 {
             warn!(
-                "No audio files found in directory: {}",
-                input_path.display()
+                "{} file(s) failed and are missing from the merged output",
+                report.failed()
             );
-            return Ok(());
         }
+        if results.is_empty() {
+            warn!("Nothing to merge; not writing {}", merge_path.display());
+        } else {
+            write_merged_output(
+                results,
+                merge_path,
+                *merge_format,
+                output_options.chapters,
+                &output_options.render,
+            )
+            .await?;
+        }
+    }
 
-        info!("Found {} audio files", audio_files.len());
-        transcribe_multiple_files(&transcriber, audio_files, output_path).await?;
-    } else {
-        error!("Input path does not exist: {}", input_path.display());
-        std::process::exit(1);
+    if !(run.watch && report.outcomes.is_empty()) {
+        print_batch_summary(&report);
     }
+    write_reports(matches, &report, run.output_path.as_deref(), run.watch)?;
 
-    info!("🎉 All transcriptions completed successfully!");
+    if run.watch {
+        let watch_options = WatchOptions {
+            stable_for: Duration::from_secs_f64(*matches.get_one::<f64>("stable_secs").unwrap()),
+            move_done: matches.get_flag("move_done"),
+            ..Default::default()
+        };
+        if watch_options.move_done {
+            for outcome in &report.outcomes {
+                if outcome.status == FileStatus::Succeeded {
+                    if let Err(e) = watch::move_to_done(&run.input_path, &outcome.input) {
+                        warn!("Could not move {} to done/: {}", outcome.input.display(), e);
+                    }
+                }
+            }
+        }
+        return run_watch(
+            transcriber,
+            run.input_path,
+            run.plan_options,
+            output_options,
+            watch_options,
+        )
+        .await;
+    }
+    if report.exit_code() != 0 {
+        std::process::exit(report.exit_code());
+    }
+    Ok(())
+}
+
+/// `--summary`, `--summary-csv`, `--timing-report` and `--archive` for a finished batch
+fn write_reports(
+    matches: &ArgMatches,
+    report: &BatchReport,
+    output_dir: Option<&Path>,
+    watch_mode: bool,
+) -> Result<()> {
+    if matches.get_flag("summary") {
+        match output_dir {
+            Some(dir) => {
+                let summary_path = dir.join("batch_summary.json");
+                report.write_summary_json(&summary_path)?;
+                info!("Batch summary saved to: {}", summary_path.display());
+            }
+            None => warn!("--summary requires --output to name a directory"),
+        }
+    }
+    if let Some(csv_path) = matches.get_one::<String>("summary_csv") {
+        report.write_summary_csv(csv_path)?;
+        info!("CSV summary saved to: {}", csv_path);
+    }
+    if let Some(timing_path) = matches.get_one::<String>("timing_report") {
+        report.write_timing_report(timing_path)?;
+        info!("Timing report saved to: {}", timing_path);
+    }
+    if let Some(archive_path) = matches.get_one::<String>("archive") {
+        if watch_mode {
+            warn!("--archive doesn't apply to watch mode; ignoring it");
+        } else {
+            let compression: ArchiveCompression = matches
+                .get_one::<String>("archive_compression")
+                .unwrap()
+                .parse()
+                .map_err(anyhow::Error::msg)?;
+            let archived =
+                archive::write_archive(Path::new(archive_path), report, output_dir, compression)?;
+            info!(
+                "Archived {} output(s) and {} failure(s) to {}",
+                archived.outputs, archived.failures, archive_path
+            );
+        }
+    }
     Ok(())
 }
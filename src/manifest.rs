@@ -0,0 +1,233 @@
+use crate::error::{Result, TranscriptionError};
+use std::path::{Path, PathBuf};
+
+/// One row of a `--file-list` manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// 1-based line number in the manifest, for error reporting
+    pub line: usize,
+    pub path: PathBuf,
+    pub language: Option<String>,
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Path,
+    Language,
+    Output,
+}
+
+const DEFAULT_COLUMNS: [Column; 3] = [Column::Path, Column::Language, Column::Output];
+
+/// Parse a manifest: one path per line, or CSV rows of `path[,language][,output]`.
+///
+/// Blank lines and `#` comments are ignored. An optional header row (`path,output,...`) may
+/// reorder the columns. Relative paths are resolved against `base_dir`.
+pub fn parse_manifest(contents: &str, base_dir: &Path) -> Result<Vec<ManifestEntry>> {
+    let mut columns: Vec<Column> = DEFAULT_COLUMNS.to_vec();
+    let mut entries = Vec::new();
+    let mut seen_row = false;
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = split_csv_line(line).map_err(|e| manifest_error(line_number, &e))?;
+
+        if !seen_row {
+            seen_row = true;
+            if let Some(header) = parse_header(&fields, line_number)? {
+                columns = header;
+                continue;
+            }
+        }
+
+        if fields.len() > columns.len() {
+            return Err(manifest_error(
+                line_number,
+                &format!(
+                    "expected at most {} columns, found {}",
+                    columns.len(),
+                    fields.len()
+                ),
+            ));
+        }
+
+        let mut entry = ManifestEntry {
+            line: line_number,
+            path: PathBuf::new(),
+            language: None,
+            output: None,
+        };
+        for (column, value) in columns.iter().zip(fields) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match column {
+                Column::Path => entry.path = resolve(base_dir, value),
+                Column::Language => entry.language = Some(value.to_lowercase()),
+                Column::Output => entry.output = Some(resolve(base_dir, value)),
+            }
+        }
+        if entry.path.as_os_str().is_empty() {
+            return Err(manifest_error(line_number, "missing path"));
+        }
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+pub fn load_manifest<P: AsRef<Path>>(path: P) -> Result<Vec<ManifestEntry>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        TranscriptionError::ConfigError(format!("Cannot read manifest {}: {}", path.display(), e))
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    parse_manifest(&contents, base_dir).map_err(|e| match e {
+        TranscriptionError::ConfigError(msg) => {
+            TranscriptionError::ConfigError(format!("{}: {}", path.display(), msg))
+        }
+        other => other,
+    })
+}
+
+fn manifest_error(line: usize, message: &str) -> TranscriptionError {
+    TranscriptionError::ConfigError(format!("manifest line {}: {}", line, message))
+}
+
+fn resolve(base_dir: &Path, value: &str) -> PathBuf {
+    let path = PathBuf::from(value);
+    if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    }
+}
+
+/// A first row is a header when its first field is literally `path`
+fn parse_header(fields: &[String], line: usize) -> Result<Option<Vec<Column>>> {
+    if !fields
+        .first()
+        .is_some_and(|f| f.trim().eq_ignore_ascii_case("path"))
+    {
+        return Ok(None);
+    }
+    let mut columns = Vec::new();
+    for field in fields {
+        let column = match field.trim().to_lowercase().as_str() {
+            "path" => Column::Path,
+            "language" | "lang" => Column::Language,
+            "output" => Column::Output,
+            other => {
+                return Err(manifest_error(
+                    line,
+                    &format!(
+                        "unknown column '{}' (expected path, language, output)",
+                        other
+                    ),
+                ))
+            }
+        };
+        if columns.contains(&column) {
+            return Err(manifest_error(
+                line,
+                &format!("duplicate column '{}'", field.trim()),
+            ));
+        }
+        columns.push(column);
+    }
+    Ok(Some(columns))
+}
+
+/// Split one CSV line, honouring double-quoted fields with `""` escapes
+pub(crate) fn split_csv_line(line: &str) -> std::result::Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+            }
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Vec<ManifestEntry>> {
+        parse_manifest(contents, Path::new("/data"))
+    }
+
+    #[test]
+    fn test_plain_path_list() {
+        let entries = parse("# recordings\n\nmeeting.wav\n  /abs/call.mp3  \n").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, PathBuf::from("/data/meeting.wav"));
+        assert_eq!(entries[0].line, 3);
+        assert_eq!(entries[1].path, PathBuf::from("/abs/call.mp3"));
+        assert_eq!(entries[1].language, None);
+    }
+
+    #[test]
+    fn test_csv_columns() {
+        let entries = parse("a.wav,EN\nb.wav,,out/b.json\nc.wav,fr,/tmp/c.srt\n").unwrap();
+        assert_eq!(entries[0].language.as_deref(), Some("en"));
+        assert_eq!(entries[0].output, None);
+        assert_eq!(entries[1].language, None);
+        assert_eq!(entries[1].output, Some(PathBuf::from("/data/out/b.json")));
+        assert_eq!(entries[2].output, Some(PathBuf::from("/tmp/c.srt")));
+    }
+
+    #[test]
+    fn test_header_reorders_columns() {
+        let entries = parse("path,output,language\na.wav,a.txt,de\n").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].output, Some(PathBuf::from("/data/a.txt")));
+        assert_eq!(entries[0].language.as_deref(), Some("de"));
+
+        assert!(parse("path,speaker\na.wav,bob\n").is_err());
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let entries = parse("\"talk, part 1.wav\",en\n\"say \"\"hi\"\".wav\"\n").unwrap();
+        assert_eq!(entries[0].path, PathBuf::from("/data/talk, part 1.wav"));
+        assert_eq!(entries[1].path, PathBuf::from("/data/say \"hi\".wav"));
+
+        let err = parse("\"unterminated.wav,en\n").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn test_invalid_rows_name_the_line() {
+        let err = parse("a.wav\n,en\n").unwrap_err();
+        assert!(err.to_string().contains("manifest line 2: missing path"));
+
+        let err = parse("a.wav,en,out.json,extra\n").unwrap_err();
+        assert!(err.to_string().contains("at most 3 columns"));
+    }
+}
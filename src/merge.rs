@@ -0,0 +1,434 @@
+use crate::error::{Result, TranscriptionError};
+use crate::output::{self, OutputFormat, RenderOptions};
+use crate::probe;
+use crate::types::TranscriptionResult;
+use crate::uncertain::{self, Piece};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// Compare strings so embedded numbers sort by value: `part2` before `part10`.
+///
+/// Letters compare case-insensitively; exact ties fall back to plain string order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_cmp_folded(a, b).then_with(|| a.cmp(b))
+}
+
+fn natural_cmp_folded(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut a_chars);
+                let y_digits = take_digits(&mut b_chars);
+                let x_value = x_digits.trim_start_matches('0');
+                let y_value = y_digits.trim_start_matches('0');
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        digits.push(*c);
+        chars.next();
+    }
+    digits
+}
+
+/// Sort paths naturally by their full path
+pub fn sort_naturally(paths: &mut [PathBuf]) {
+    paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+}
+
+/// Where one constituent file landed in a merged transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergedPart {
+    pub input: PathBuf,
+    /// Start of this file within the merged timeline, in seconds
+    pub offset: f64,
+    pub duration: f64,
+    pub language: String,
+}
+
+/// Several files' transcripts joined into one timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedTranscript {
+    pub parts: Vec<MergedPart>,
+    #[serde(flatten)]
+    pub result: TranscriptionResult,
+}
+
+impl MergedTranscript {
+    /// Distinct detected languages, in order of first appearance
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = Vec::new();
+        for part in &self.parts {
+            if !languages.contains(&part.language.as_str()) {
+                languages.push(&part.language);
+            }
+        }
+        languages
+    }
+
+    pub fn has_language_conflict(&self) -> bool {
+        self.languages().len() > 1
+    }
+}
+
+/// Merge per-file results in natural order of their inputs.
+///
+/// Each file starts where the previous one ended, using its probed duration when available
+/// and the duration faster-whisper reported otherwise.
+pub fn merge_transcripts(mut results: Vec<(PathBuf, TranscriptionResult)>) -> MergedTranscript {
+    results.sort_by(|a, b| natural_cmp(&a.0.to_string_lossy(), &b.0.to_string_lossy()));
+
+    let mut offset = 0.0;
+    let mut parts = Vec::with_capacity(results.len());
+    let mut offset_results = Vec::with_capacity(results.len());
+    for (input, result) in results {
+        let duration = probe::probe_duration(&input).unwrap_or(result.duration);
+        parts.push(MergedPart {
+            input,
+            offset,
+            duration,
+            language: result.language.clone(),
+        });
+        offset_results.push((offset, result));
+        offset += duration;
+    }
+
+    MergedTranscript {
+        parts,
+        result: TranscriptionResult::merge(&offset_results),
+    }
+}
+
+/// Output format of a merged transcript, chosen from the output file's extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeFormat {
+    /// The merged result plus the list of constituent files and their offsets
+    Json,
+    /// One section per constituent file
+    Markdown,
+    Plain(OutputFormat),
+}
+
+impl MergeFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "json" => Ok(MergeFormat::Json),
+            "md" | "markdown" => Ok(MergeFormat::Markdown),
+            other => other.parse().map(MergeFormat::Plain).map_err(|_| {
+                TranscriptionError::UnsupportedFormat(format!(
+                    "Cannot infer merge format from {} (use .json, .md, .txt, .srt or .vtt)",
+                    path.display()
+                ))
+            }),
+        }
+    }
+}
+
+pub fn render_merged(
+    merged: &MergedTranscript,
+    format: MergeFormat,
+    options: &RenderOptions,
+) -> Result<String> {
+    match format {
+        MergeFormat::Json => Ok(serde_json::to_string_pretty(merged)?),
+        MergeFormat::Markdown => Ok(render_markdown(merged, options)),
+        MergeFormat::Plain(format) => output::render_with(&merged.result, format, options),
+    }
+}
+
+fn render_markdown(merged: &MergedTranscript, options: &RenderOptions) -> String {
+    let chapters = merged.result.chapters.as_deref().unwrap_or_default();
+    let mut next_chapter = 0;
+    let mut out = String::from("# Transcript\n");
+    for part in &merged.parts {
+        let name = part
+            .input
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| part.input.display().to_string());
+        out.push_str(&format!("\n## {} ({})\n\n", name, clock_time(part.offset)));
+
+        let part_end = part.offset + part.duration;
+        let mut text: Vec<Piece> = Vec::new();
+        let mut speaker_in_text: Option<&String> = None;
+        for segment in merged
+            .result
+            .segments
+            .iter()
+            .filter(|segment| segment.start >= part.offset && segment.start < part_end)
+        {
+            // A chapter heading before the first segment at or after the chapter's start
+            let mut heading = None;
+            while chapters
+                .get(next_chapter)
+                .is_some_and(|chapter| chapter.start <= segment.start)
+            {
+                heading = Some(&chapters[next_chapter]);
+                next_chapter += 1;
+            }
+            if let Some(chapter) = heading {
+                if !text.is_empty() {
+                    out.push_str(&uncertain::render_markdown(&text));
+                    out.push_str("\n\n");
+                    text.clear();
+                }
+                out.push_str(&format!(
+                    "### {} ({})\n\n",
+                    chapter.title,
+                    clock_time(chapter.start)
+                ));
+                speaker_in_text = None;
+            }
+            // Each speaker turn is its own paragraph, opened by the label
+            if let Some(speaker) = &segment.speaker {
+                if speaker_in_text != Some(speaker) {
+                    if !text.is_empty() {
+                        out.push_str(&uncertain::render_markdown(&text));
+                        out.push_str("\n\n");
+                        text.clear();
+                    }
+                    out.push_str(&format!("**{}:** ", speaker));
+                    speaker_in_text = Some(speaker);
+                }
+            }
+            let mut pieces = match &options.uncertain {
+                Some(marking) => marking.pieces(segment),
+                None => uncertain::plain_pieces(segment),
+            };
+            if let (Some(first), false) = (pieces.first_mut(), text.is_empty()) {
+                first.separator = " ";
+            }
+            text.extend(pieces);
+        }
+        out.push_str(&uncertain::render_markdown(&text));
+        out.push('\n');
+    }
+    out
+}
+
+/// `H:MM:SS`
+fn clock_time(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TranscriptionSegment;
+
+    fn part(language: &str, duration: f64, texts: &[&str]) -> TranscriptionResult {
+        let step = duration / texts.len() as f64;
+        let segments: Vec<TranscriptionSegment> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| TranscriptionSegment {
+                start: i as f64 * step,
+                end: (i + 1) as f64 * step,
+                text: text.to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            })
+            .collect();
+        TranscriptionResult {
+            language: language.to_string(),
+            language_probability: 0.9,
+            duration,
+            full_text: texts.join(" "),
+            segments,
+            transcription_time: duration / 4.0,
+            real_time_factor: 4.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_natural_sort() {
+        let mut paths: Vec<PathBuf> = ["part10.wav", "part2.wav", "Part1.wav", "part02b.wav"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        sort_naturally(&mut paths);
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Part1.wav"),
+                PathBuf::from("part2.wav"),
+                PathBuf::from("part02b.wav"),
+                PathBuf::from("part10.wav"),
+            ]
+        );
+        assert_eq!(natural_cmp("Part2", "part10"), Ordering::Less);
+        assert_eq!(natural_cmp("take 7", "take 7"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_merge_offsets_and_metadata() {
+        let merged = merge_transcripts(vec![
+            ("lecture/part10.wav".into(), part("en", 30.0, &["Goodbye."])),
+            (
+                "lecture/part2.wav".into(),
+                part("en", 60.0, &["Hello.", "Welcome."]),
+            ),
+        ]);
+
+        assert_eq!(merged.parts[0].input, PathBuf::from("lecture/part2.wav"));
+        assert_eq!(merged.parts[1].offset, 60.0);
+        assert_eq!(merged.result.duration, 90.0);
+        assert_eq!(merged.result.segments[2].start, 60.0);
+        assert_eq!(merged.result.segments[2].end, 90.0);
+        assert_eq!(merged.result.full_text, "Hello. Welcome. Goodbye.");
+        assert_eq!(merged.result.real_time_factor, 4.0);
+        assert!(!merged.has_language_conflict());
+
+        let json: serde_json::Value = serde_json::from_str(
+            &render_merged(&merged, MergeFormat::Json, &RenderOptions::default()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["parts"][1]["offset"], 60.0);
+        assert_eq!(json["language"], "en");
+    }
+
+    #[test]
+    fn test_language_conflict_picks_dominant_language() {
+        let merged = merge_transcripts(vec![
+            ("a1.wav".into(), part("en", 100.0, &["Hi."])),
+            ("a2.wav".into(), part("de", 20.0, &["Hallo."])),
+        ]);
+        assert!(merged.has_language_conflict());
+        assert_eq!(merged.languages(), vec!["en", "de"]);
+        assert_eq!(merged.result.language, "en");
+    }
+
+    #[test]
+    fn test_merge_formats() {
+        assert_eq!(
+            MergeFormat::from_path(Path::new("merged.MD")).unwrap(),
+            MergeFormat::Markdown
+        );
+        assert_eq!(
+            MergeFormat::from_path(Path::new("merged.srt")).unwrap(),
+            MergeFormat::Plain(OutputFormat::Srt)
+        );
+        assert!(MergeFormat::from_path(Path::new("merged.docx")).is_err());
+
+        let merged = merge_transcripts(vec![
+            ("p1.wav".into(), part("en", 10.0, &["One."])),
+            ("p2.wav".into(), part("en", 3700.0, &["Two."])),
+        ]);
+        let markdown =
+            render_merged(&merged, MergeFormat::Markdown, &RenderOptions::default()).unwrap();
+        assert_eq!(
+            markdown,
+            "# Transcript\n\n## p1.wav (0:00:00)\n\nOne.\n\n## p2.wav (0:00:10)\n\nTwo.\n"
+        );
+        let txt = render_merged(
+            &merged,
+            MergeFormat::Plain(OutputFormat::Txt),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(txt, "One. Two.\n");
+    }
+
+    #[test]
+    fn test_markdown_speaker_paragraphs() {
+        let mut merged = merge_transcripts(vec![(
+            "p1.wav".into(),
+            part("en", 40.0, &["Hi.", "How are you?", "Fine.", "Good."]),
+        )]);
+        for (segment, speaker) in merged
+            .result
+            .segments
+            .iter_mut()
+            .zip(["S1", "S1", "S2", "S1"])
+        {
+            segment.speaker = Some(speaker.to_string());
+        }
+        let markdown =
+            render_merged(&merged, MergeFormat::Markdown, &RenderOptions::default()).unwrap();
+        assert_eq!(
+            markdown,
+            "# Transcript\n\n## p1.wav (0:00:00)\n\n**S1:** Hi. How are you?\n\n\
+             **S2:** Fine.\n\n**S1:** Good.\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_marks_uncertain_segments() {
+        let mut merged = merge_transcripts(vec![(
+            "p1.wav".into(),
+            part("en", 30.0, &["Hi.", "Tides *rise*", "Fine."]),
+        )]);
+        merged.result.segments[1].avg_logprob = -1.5;
+        let options = RenderOptions {
+            uncertain: Some(crate::uncertain::UncertainMarking::new(0.5).unwrap()),
+            ..Default::default()
+        };
+        let markdown = render_merged(&merged, MergeFormat::Markdown, &options).unwrap();
+        assert!(
+            markdown.ends_with("\n\nHi. *Tides \\*rise\\** Fine.\n"),
+            "{}",
+            markdown
+        );
+    }
+
+    #[test]
+    fn test_markdown_chapter_headings() {
+        let mut merged = merge_transcripts(vec![
+            (
+                "p1.wav".into(),
+                part("en", 100.0, &["Intro.", "More intro."]),
+            ),
+            ("p2.wav".into(), part("en", 100.0, &["Topic.", "Detail."])),
+        ]);
+        // Split the first part's opening from the rest
+        merged.result.segments[1].start = 60.0;
+        merged.result.chapters = Some(merged.result.detect_chapters(5.0, 30.0));
+        let markdown =
+            render_merged(&merged, MergeFormat::Markdown, &RenderOptions::default()).unwrap();
+        assert_eq!(
+            markdown,
+            "# Transcript\n\n## p1.wav (0:00:00)\n\n### Intro. (0:00:00)\n\nIntro.\n\n\
+             ### More intro. (0:01:00)\n\nMore intro.\n\n## p2.wav (0:01:40)\n\nTopic. Detail.\n"
+        );
+    }
+}
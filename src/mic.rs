@@ -0,0 +1,167 @@
+use crate::audio::{downmix, resample_linear};
+use crate::backend::{TranscriptionBackend, SAMPLE_RATE};
+use crate::error::{Result, TranscriptionError};
+use crate::listen::{ListenOptions, RollingWindow, StitchUpdate, TranscriptStitcher};
+use crate::types::{TranscriptionOptions, TranscriptionResult};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How long `listen` waits for audio before re-checking the shutdown flag
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+fn mic_error(e: impl std::fmt::Display) -> TranscriptionError {
+    TranscriptionError::IoError(std::io::Error::other(format!("Microphone: {}", e)))
+}
+
+fn on_stream_error(e: cpal::StreamError) {
+    warn!("Microphone stream error: {}", e);
+}
+
+/// Records the default input device.
+///
+/// Capture runs on cpal's own thread and stops when this is dropped. The underlying stream
+/// isn't `Send` on every platform, so create and read it on one thread.
+pub struct MicCapture {
+    _stream: cpal::Stream,
+    receiver: mpsc::Receiver<Vec<f32>>,
+    device_name: String,
+    sample_rate: u32,
+}
+
+impl MicCapture {
+    pub fn default_input() -> Result<Self> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| mic_error("no input device available"))?;
+        let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
+        let supported = device.default_input_config().map_err(mic_error)?;
+        let channels = supported.channels() as usize;
+        let sample_rate = supported.sample_rate().0;
+        let config = supported.config();
+
+        // Downmix on the audio thread; resampling waits for larger batches in `recv_timeout`
+        let (sender, receiver) = mpsc::channel();
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &_| {
+                    let _ = sender.send(downmix(data, channels));
+                },
+                on_stream_error,
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &_| {
+                    let data: Vec<f32> = data.iter().map(|&s| s as f32 / 32_768.0).collect();
+                    let _ = sender.send(downmix(&data, channels));
+                },
+                on_stream_error,
+                None,
+            ),
+            cpal::SampleFormat::U16 => device.build_input_stream(
+                &config,
+                move |data: &[u16], _: &_| {
+                    let data: Vec<f32> = data
+                        .iter()
+                        .map(|&s| (s as f32 - 32_768.0) / 32_768.0)
+                        .collect();
+                    let _ = sender.send(downmix(&data, channels));
+                },
+                on_stream_error,
+                None,
+            ),
+            other => return Err(mic_error(format!("unsupported sample format {}", other))),
+        }
+        .map_err(mic_error)?;
+        stream.play().map_err(mic_error)?;
+
+        debug!(
+            "Capturing from {} ({} Hz, {} channel(s))",
+            device_name, sample_rate, channels
+        );
+        Ok(Self {
+            _stream: stream,
+            receiver,
+            device_name,
+            sample_rate,
+        })
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Mono samples at `SAMPLE_RATE` captured since the last call, waiting up to `timeout`
+    /// for the first of them
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Vec<f32>> {
+        let mut samples = self.receiver.recv_timeout(timeout).ok()?;
+        while let Ok(more) = self.receiver.try_recv() {
+            samples.extend(more);
+        }
+        Some(resample_linear(&samples, self.sample_rate, SAMPLE_RATE))
+    }
+}
+
+/// Transcribe the default microphone in overlapping windows until `shutdown` is set.
+///
+/// `on_update` is called after every window with the words it made final and the provisional
+/// tail. Audio still buffered at shutdown is transcribed before the stitched transcript of
+/// the whole session is returned. Word timestamps are always requested, as stitching relies
+/// on them.
+pub fn listen<F>(
+    backend: &dyn TranscriptionBackend,
+    options: &ListenOptions,
+    shutdown: &AtomicBool,
+    mut on_update: F,
+) -> Result<TranscriptionResult>
+where
+    F: FnMut(&StitchUpdate),
+{
+    options
+        .validate()
+        .map_err(TranscriptionError::ConfigError)?;
+    let decoding = TranscriptionOptions {
+        word_timestamps: true,
+        ..backend.options().clone()
+    };
+
+    let capture = MicCapture::default_input()?;
+    info!(
+        "🎙️ Listening on {} ({:.0}s windows, {:.0}s overlap); Ctrl-C to stop",
+        capture.device_name(),
+        options.window.as_secs_f64(),
+        options.overlap.as_secs_f64()
+    );
+
+    let mut rolling = RollingWindow::new(options);
+    let mut stitcher = TranscriptStitcher::new();
+    while !shutdown.load(Ordering::SeqCst) {
+        if let Some(samples) = capture.recv_timeout(POLL_INTERVAL) {
+            rolling.push(&samples);
+        }
+        while let Some(window) = rolling.next_window() {
+            let result = backend.transcribe_samples(&window.samples, &decoding)?;
+            debug!(
+                "Window {:.1}-{:.1}s transcribed in {:.2}s",
+                window.start,
+                window.end(),
+                result.transcription_time
+            );
+            on_update(&stitcher.push(&window, &result));
+        }
+    }
+
+    if let Some(samples) = capture.recv_timeout(Duration::ZERO) {
+        rolling.push(&samples);
+    }
+    drop(capture);
+    if let Some(window) = rolling.flush() {
+        let result = backend.transcribe_samples(&window.samples, &decoding)?;
+        on_update(&stitcher.push(&window, &result));
+    }
+    Ok(stitcher.finish())
+}
@@ -0,0 +1,711 @@
+//! Keeps count of the models loaded in this process, so mixing models or configurations
+//! can't leave more of them resident than the machine has memory for.
+
+use crate::backend::{self, TranscriptionBackend};
+use crate::error::Result;
+use crate::models;
+use crate::types::{Backend, ModelConfig, TranscriptionOptions};
+use serde::Serialize;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use tracing::{debug, info, warn};
+
+/// How many models may be loaded at once; `None` leaves that measure unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModelPoolLimits {
+    pub max_models: Option<usize>,
+    /// Against `models::estimated_memory_mb`; models without an estimate count as nothing
+    pub max_total_estimated_mb: Option<u64>,
+}
+
+impl ModelPoolLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_models.is_none() && self.max_total_estimated_mb.is_none()
+    }
+
+    /// Whether `models` models taking `total_mb` between them are within the limits
+    pub fn allows(&self, models: usize, total_mb: u64) -> bool {
+        self.max_models.is_none_or(|max| models <= max)
+            && self
+                .max_total_estimated_mb
+                .is_none_or(|max| total_mb <= max)
+    }
+
+    /// How many copies of one model taking `estimated_mb` fit, for pools of identical
+    /// models; at least one, since a pool without any can't do anything
+    pub fn max_copies(&self, estimated_mb: Option<u64>) -> Option<usize> {
+        let by_memory = self
+            .max_total_estimated_mb
+            .zip(estimated_mb.filter(|&mb| mb > 0))
+            .map(|(max, mb)| (max / mb) as usize);
+        let copies = match (self.max_models, by_memory) {
+            (Some(a), Some(b)) => a.min(b),
+            (a, b) => a.or(b)?,
+        };
+        Some(copies.max(1))
+    }
+}
+
+/// What a request for a model that doesn't fit the limits does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhenFull {
+    /// Models are unloaded as soon as nothing uses them, and the request waits until enough
+    /// of those in use are released
+    #[default]
+    Block,
+    /// Models stay loaded after use so they can be reused, and the least recently used idle
+    /// ones are unloaded to make room. The request only waits when every model is in use.
+    Evict,
+}
+
+impl WhenFull {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WhenFull::Block => "block",
+            WhenFull::Evict => "evict",
+        }
+    }
+}
+
+impl fmt::Display for WhenFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WhenFull {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "block" | "wait" => Ok(WhenFull::Block),
+            "evict" | "lru" => Ok(WhenFull::Evict),
+            other => Err(format!(
+                "Invalid value for when models are full: {} (expected block or evict)",
+                other
+            )),
+        }
+    }
+}
+
+struct Resident {
+    key: String,
+    estimated_mb: u64,
+    /// `None` while it loads
+    backend: Option<Arc<dyn TranscriptionBackend>>,
+    leases: usize,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    residents: Vec<Resident>,
+    /// Counts uses, to tell which model was used least recently
+    clock: u64,
+}
+
+/// Loads models on request within `ModelPoolLimits`, sharing one copy among the callers that
+/// ask for the same key at the same time.
+///
+/// A caller holding a lease mustn't ask for a model that doesn't fit alongside it, since it
+/// would wait for itself.
+pub struct ModelManager {
+    limits: ModelPoolLimits,
+    when_full: WhenFull,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Default for ModelManager {
+    fn default() -> Self {
+        Self::new(ModelPoolLimits::default(), WhenFull::default())
+    }
+}
+
+impl ModelManager {
+    pub fn new(limits: ModelPoolLimits, when_full: WhenFull) -> Self {
+        Self {
+            limits,
+            when_full,
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn limits(&self) -> ModelPoolLimits {
+        self.limits
+    }
+
+    pub fn when_full(&self) -> WhenFull {
+        self.when_full
+    }
+
+    /// Keys of the models loaded or loading, least recently used first
+    pub fn resident(&self) -> Vec<String> {
+        let state = self.lock();
+        let mut residents: Vec<&Resident> = state.residents.iter().collect();
+        residents.sort_by_key(|resident| resident.last_used);
+        residents
+            .iter()
+            .map(|resident| resident.key.clone())
+            .collect()
+    }
+
+    /// The model `key`, calling `load` to create it unless it's already loaded. Waits, or
+    /// unloads idle models, as `WhenFull` says when it doesn't fit. A model too large for
+    /// the limits on its own is still loaded once nothing else is.
+    pub fn acquire<F>(
+        &self,
+        key: &str,
+        estimated_mb: Option<u64>,
+        load: F,
+    ) -> Result<ModelLease<'_>>
+    where
+        F: FnOnce() -> Result<Box<dyn TranscriptionBackend>>,
+    {
+        let estimated_mb = estimated_mb.unwrap_or(0);
+        let mut evicted = Vec::new();
+        let mut state = self.lock();
+        loop {
+            state.clock += 1;
+            let now = state.clock;
+            if let Some(resident) = state.residents.iter_mut().find(|r| r.key == key) {
+                if let Some(backend) = resident.backend.clone() {
+                    resident.leases += 1;
+                    resident.last_used = now;
+                    drop(state);
+                    evicted.into_iter().for_each(unload);
+                    debug!("Reusing the loaded model {}", key);
+                    return Ok(ModelLease {
+                        manager: self,
+                        key: key.to_string(),
+                        backend,
+                    });
+                }
+                // Another caller is loading it
+                state = self.wait(state);
+                continue;
+            }
+            let models = state.residents.len() + 1;
+            let total_mb =
+                state.residents.iter().map(|r| r.estimated_mb).sum::<u64>() + estimated_mb;
+            if self.limits.allows(models, total_mb) || state.residents.is_empty() {
+                if !self.limits.allows(models, total_mb) {
+                    warn!(
+                        "{} (about {} MB) is over the model limits on its own; loading it anyway",
+                        key, estimated_mb
+                    );
+                }
+                state.residents.push(Resident {
+                    key: key.to_string(),
+                    estimated_mb,
+                    backend: None,
+                    leases: 1,
+                    last_used: now,
+                });
+                break;
+            }
+            let idle = state
+                .residents
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| r.leases == 0 && r.backend.is_some())
+                .min_by_key(|(_, r)| r.last_used)
+                .map(|(index, _)| index);
+            match idle {
+                Some(index) if self.when_full == WhenFull::Evict => {
+                    evicted.push(state.residents.remove(index));
+                }
+                _ => {
+                    info!(
+                        "Waiting for a loaded model to be released before loading {}",
+                        key
+                    );
+                    state = self.wait(state);
+                }
+            }
+        }
+        drop(state);
+        for resident in evicted {
+            info!("Unloading {} to make room for {}", resident.key, key);
+            unload(resident);
+        }
+
+        let mut reservation = Reservation {
+            manager: self,
+            key,
+            completed: false,
+        };
+        let backend: Arc<dyn TranscriptionBackend> = Arc::from(load()?);
+        let mut state = self.lock();
+        let resident = state
+            .residents
+            .iter_mut()
+            .find(|r| r.key == key)
+            .expect("a model being loaded stays reserved");
+        resident.backend = Some(backend.clone());
+        reservation.completed = true;
+        drop(state);
+        self.changed.notify_all();
+        Ok(ModelLease {
+            manager: self,
+            key: key.to_string(),
+            backend,
+        })
+    }
+
+    fn release(&self, key: &str) {
+        let mut state = self.lock();
+        state.clock += 1;
+        let now = state.clock;
+        let Some(index) = state.residents.iter().position(|r| r.key == key) else {
+            return;
+        };
+        let resident = &mut state.residents[index];
+        resident.leases -= 1;
+        resident.last_used = now;
+        let released = (resident.leases == 0 && self.when_full == WhenFull::Block)
+            .then(|| state.residents.remove(index));
+        drop(state);
+        if let Some(resident) = released {
+            debug!("Unloading {}, which nothing uses now", resident.key);
+            unload(resident);
+        }
+        self.changed.notify_all();
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn unload(resident: Resident) {
+    if let Some(backend) = resident.backend {
+        backend.unload();
+    }
+}
+
+/// The place `acquire` keeps for a model while it loads. Unless the load completes it is
+/// given up when dropped, so a loader that fails or panics doesn't leave other callers
+/// waiting on a model that will never arrive.
+struct Reservation<'a> {
+    manager: &'a ModelManager,
+    key: &'a str,
+    completed: bool,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        self.manager.lock().residents.retain(|r| r.key != self.key);
+        self.manager.changed.notify_all();
+    }
+}
+
+/// A model handed out by `ModelManager`, released when dropped
+pub struct ModelLease<'a> {
+    manager: &'a ModelManager,
+    key: String,
+    backend: Arc<dyn TranscriptionBackend>,
+}
+
+impl ModelLease<'_> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Deref for ModelLease<'_> {
+    type Target = dyn TranscriptionBackend;
+
+    fn deref(&self) -> &Self::Target {
+        self.backend.as_ref()
+    }
+}
+
+impl Drop for ModelLease<'_> {
+    fn drop(&mut self) {
+        self.manager.release(&self.key);
+    }
+}
+
+/// What a loaded model is shared by: configs that differ only in decoding options can use
+/// the same one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModelKey {
+    pub backend: Backend,
+    pub model_size: String,
+    pub device: String,
+    pub compute_type: String,
+    pub cpu_threads: Option<usize>,
+}
+
+impl ModelKey {
+    pub fn of(config: &ModelConfig) -> Self {
+        Self {
+            backend: config.backend,
+            model_size: config.model_size.clone(),
+            device: config.device.clone(),
+            compute_type: config.compute_type.clone(),
+            cpu_threads: config.cpu_threads,
+        }
+    }
+}
+
+impl fmt::Display for ModelKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} on {} ({}",
+            self.backend, self.model_size, self.device, self.compute_type
+        )?;
+        if let Some(threads) = self.cpu_threads {
+            write!(f, ", {} threads", threads)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// How often a `ModelPool` had a model loaded already
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PoolStats {
+    /// Requests served by a model that was already loaded
+    pub hits: u64,
+    /// Requests that loaded a model
+    pub misses: u64,
+    /// Models loaded now
+    pub loaded: usize,
+}
+
+type Loader = dyn Fn(&ModelConfig) -> Result<Box<dyn TranscriptionBackend>> + Send + Sync;
+
+/// Loaded models shared by `ModelKey` within a `ModelManager`'s limits, so callers that only
+/// differ in decoding options don't each load their own. Callers pass their options with
+/// every transcription, since the model's own are the defaults.
+pub struct ModelPool {
+    manager: ModelManager,
+    loader: Box<Loader>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for ModelPool {
+    /// Every model stays loaded once used
+    fn default() -> Self {
+        Self::new(ModelPoolLimits::default(), WhenFull::Evict)
+    }
+}
+
+impl ModelPool {
+    pub fn new(limits: ModelPoolLimits, when_full: WhenFull) -> Self {
+        Self {
+            manager: ModelManager::new(limits, when_full),
+            loader: Box::new(|config| {
+                backend::create(config.clone(), TranscriptionOptions::default())
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Create models with `loader` instead of `backend::create`
+    pub fn with_loader<F>(mut self, loader: F) -> Self
+    where
+        F: Fn(&ModelConfig) -> Result<Box<dyn TranscriptionBackend>> + Send + Sync + 'static,
+    {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    pub fn manager(&self) -> &ModelManager {
+        &self.manager
+    }
+
+    /// The model for `config`, loading it unless one with the same `ModelKey` is loaded
+    pub fn acquire(&self, config: &ModelConfig) -> Result<ModelLease<'_>> {
+        let key = ModelKey::of(config).to_string();
+        let estimate = models::estimated_memory_mb(&config.model_size, &config.compute_type);
+        let mut loaded = false;
+        let lease = self.manager.acquire(&key, estimate, || {
+            loaded = true;
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            info!("Model pool miss: loading {}", key);
+            (self.loader)(config)
+        })?;
+        if !loaded {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            info!("Model pool hit: reusing {}", key);
+        }
+        Ok(lease)
+    }
+
+    /// Create the model for `config` with the pool's loader but keep it out of the pool,
+    /// its limits and its stats, for a caller that holds it to itself for a long time
+    pub fn load_apart(&self, config: &ModelConfig) -> Result<Box<dyn TranscriptionBackend>> {
+        (self.loader)(config)
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            loaded: self.manager.resident().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TranscriptionError;
+    use crate::test_support::MockBackend;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// A manager whose models count loads and unloads
+    struct Counted {
+        manager: ModelManager,
+        loads: AtomicUsize,
+        unloads: Arc<AtomicUsize>,
+    }
+
+    impl Counted {
+        fn new(max_models: Option<usize>, max_mb: Option<u64>, when_full: WhenFull) -> Self {
+            let limits = ModelPoolLimits {
+                max_models,
+                max_total_estimated_mb: max_mb,
+            };
+            Self {
+                manager: ModelManager::new(limits, when_full),
+                loads: AtomicUsize::new(0),
+                unloads: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        fn acquire(&self, key: &str, mb: u64) -> ModelLease<'_> {
+            self.manager
+                .acquire(key, Some(mb), || {
+                    self.loads.fetch_add(1, Ordering::SeqCst);
+                    Ok(Box::new(
+                        MockBackend::new()
+                            .with_config(ModelConfig::new(key, "cpu", "float32"))
+                            .loaded()
+                            .with_unloads(self.unloads.clone()),
+                    ))
+                })
+                .unwrap()
+        }
+
+        fn counts(&self) -> (usize, usize) {
+            (
+                self.loads.load(Ordering::SeqCst),
+                self.unloads.load(Ordering::SeqCst),
+            )
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_idle_model() {
+        let models = Counted::new(Some(2), None, WhenFull::Evict);
+        drop(models.acquire("tiny", 150));
+        drop(models.acquire("base", 290));
+        // Using tiny again makes base the least recently used
+        let tiny = models.acquire("tiny", 150);
+        assert_eq!(tiny.config().model_size, "tiny");
+        drop(tiny);
+        assert_eq!(models.counts(), (2, 0));
+
+        let _small = models.acquire("small", 950);
+        assert_eq!(models.counts(), (3, 1));
+        assert_eq!(models.manager.resident(), vec!["tiny", "small"]);
+    }
+
+    #[test]
+    fn test_evicts_to_stay_under_the_memory_cap() {
+        let models = Counted::new(None, Some(4000), WhenFull::Evict);
+        drop(models.acquire("medium", 2600));
+        drop(models.acquire("base", 290));
+        drop(models.acquire("small", 950));
+        assert_eq!(models.counts(), (3, 0));
+
+        // Only evicting medium leaves room
+        drop(models.acquire("medium.en", 2600));
+        assert_eq!(
+            models.manager.resident(),
+            vec!["base", "small", "medium.en"]
+        );
+        // Too large on its own: everything else goes, and it loads anyway
+        drop(models.acquire("large-v3", 9400));
+        assert_eq!(models.manager.resident(), vec!["large-v3"]);
+        assert_eq!(models.counts(), (5, 4));
+    }
+
+    #[test]
+    fn test_block_waits_for_a_model_to_be_released() {
+        let models = Counted::new(Some(1), None, WhenFull::Block);
+        let medium = models.acquire("medium", 2600);
+        let (sent, received) = mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let small = models.acquire("small", 950);
+                sent.send(small.key().to_string()).unwrap();
+            });
+            assert!(received.recv_timeout(Duration::from_millis(100)).is_err());
+            assert_eq!(models.manager.resident(), vec!["medium"]);
+
+            drop(medium);
+            assert_eq!(received.recv().unwrap(), "small");
+        });
+        // Block keeps nothing idle
+        assert!(models.manager.resident().is_empty());
+        assert_eq!(models.counts(), (2, 2));
+    }
+
+    #[test]
+    fn test_evict_waits_when_every_model_is_in_use() {
+        let models = Counted::new(Some(1), None, WhenFull::Evict);
+        let medium = models.acquire("medium", 2600);
+        let (sent, received) = mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                drop(models.acquire("small", 950));
+                sent.send(()).unwrap();
+            });
+            assert!(received.recv_timeout(Duration::from_millis(100)).is_err());
+            drop(medium);
+            received.recv().unwrap();
+        });
+        assert_eq!(models.manager.resident(), vec!["small"]);
+        assert_eq!(models.counts(), (2, 1));
+    }
+
+    #[test]
+    fn test_concurrent_requests_share_one_load() {
+        let models = Counted::new(Some(1), None, WhenFull::Block);
+        std::thread::scope(|scope| {
+            let leases: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| models.acquire("medium", 2600)))
+                .collect();
+            for lease in leases {
+                assert_eq!(lease.join().unwrap().key(), "medium");
+            }
+        });
+        assert_eq!(models.counts().0, 1);
+    }
+
+    #[test]
+    fn test_failed_load_frees_its_reservation() {
+        let manager = ModelManager::new(
+            ModelPoolLimits {
+                max_models: Some(1),
+                max_total_estimated_mb: None,
+            },
+            WhenFull::Block,
+        );
+        let err = manager
+            .acquire("medium", None, || {
+                Err(TranscriptionError::ModelInitError("no weights".to_string()))
+            })
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("no weights"), "{}", err);
+        assert!(manager.resident().is_empty());
+    }
+
+    #[test]
+    fn test_panicking_load_frees_its_reservation() {
+        let models = Counted::new(Some(1), None, WhenFull::Block);
+        let panicked = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = models
+                .manager
+                .acquire("medium", Some(2600), || panic!("loader bug"));
+        }));
+        assert!(panicked.is_err());
+        assert!(models.manager.resident().is_empty());
+        // Nothing is left waiting on the abandoned load
+        assert_eq!(models.acquire("medium", 2600).key(), "medium");
+        assert_eq!(models.counts(), (1, 1));
+    }
+
+    #[test]
+    fn test_pool_shares_models_by_key() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let pool = ModelPool::default().with_loader({
+            let loads = loads.clone();
+            move |config| {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(
+                    MockBackend::new().with_config(config.clone()).loaded(),
+                ))
+            }
+        });
+        let medium = ModelConfig::new("medium", "cpu", "int8");
+        drop(pool.acquire(&medium).unwrap());
+        // Where the model is stored doesn't make it another model
+        let elsewhere = ModelConfig {
+            model_dir: Some("/models".into()),
+            ..medium.clone()
+        };
+        let lease = pool.acquire(&elsewhere).unwrap();
+        assert_eq!(lease.key(), "faster-whisper medium on cpu (int8)");
+        drop(lease);
+        let threaded = ModelConfig {
+            cpu_threads: Some(4),
+            ..medium
+        };
+        assert_eq!(
+            pool.acquire(&threaded).unwrap().key(),
+            "faster-whisper medium on cpu (int8, 4 threads)"
+        );
+
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                hits: 1,
+                misses: 2,
+                loaded: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let unlimited = ModelPoolLimits::default();
+        assert!(unlimited.is_unlimited());
+        assert!(unlimited.allows(100, u64::MAX));
+        assert_eq!(unlimited.max_copies(Some(2600)), None);
+
+        let limits = ModelPoolLimits {
+            max_models: Some(3),
+            max_total_estimated_mb: Some(6000),
+        };
+        assert!(limits.allows(2, 5200));
+        assert!(!limits.allows(3, 7800));
+        assert_eq!(limits.max_copies(Some(2600)), Some(2));
+        assert_eq!(limits.max_copies(Some(150)), Some(3));
+        assert_eq!(limits.max_copies(None), Some(3));
+        assert_eq!(limits.max_copies(Some(9400)), Some(1));
+    }
+
+    #[test]
+    fn test_parse_when_full() {
+        assert_eq!("evict".parse(), Ok(WhenFull::Evict));
+        assert_eq!("BLOCK".parse(), Ok(WhenFull::Block));
+        assert!("drop".parse::<WhenFull>().is_err());
+        assert_eq!(WhenFull::default().to_string(), "block");
+    }
+}
@@ -0,0 +1,1073 @@
+use crate::download::{download, sha256_file, DownloadOptions, DownloadProgress};
+use crate::error::{Result, TranscriptionError};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+/// Files faster-whisper can't load a model without
+pub const REQUIRED_FILES: &[&str] = &["model.bin", "config.json", "tokenizer.json"];
+
+/// Hugging Face repositories behind faster-whisper's model names, as in its `_MODELS`
+const REPOSITORIES: &[(&str, &str)] = &[
+    ("tiny.en", "Systran/faster-whisper-tiny.en"),
+    ("tiny", "Systran/faster-whisper-tiny"),
+    ("base.en", "Systran/faster-whisper-base.en"),
+    ("base", "Systran/faster-whisper-base"),
+    ("small.en", "Systran/faster-whisper-small.en"),
+    ("small", "Systran/faster-whisper-small"),
+    ("medium.en", "Systran/faster-whisper-medium.en"),
+    ("medium", "Systran/faster-whisper-medium"),
+    ("large-v1", "Systran/faster-whisper-large-v1"),
+    ("large-v2", "Systran/faster-whisper-large-v2"),
+    ("large-v3", "Systran/faster-whisper-large-v3"),
+    ("large", "Systran/faster-whisper-large-v3"),
+    ("distil-large-v2", "Systran/faster-distil-whisper-large-v2"),
+    (
+        "distil-medium.en",
+        "Systran/faster-distil-whisper-medium.en",
+    ),
+    ("distil-small.en", "Systran/faster-distil-whisper-small.en"),
+    ("distil-large-v3", "Systran/faster-distil-whisper-large-v3"),
+    (
+        "large-v3-turbo",
+        "mobiuslabsgmbh/faster-whisper-large-v3-turbo",
+    ),
+    ("turbo", "mobiuslabsgmbh/faster-whisper-large-v3-turbo"),
+];
+
+const MB: u64 = 1_000_000;
+
+/// Approximate download size of each repository, for checking disk space before a download
+const APPROXIMATE_SIZES: &[(&str, u64)] = &[
+    ("Systran/faster-whisper-tiny.en", 75 * MB),
+    ("Systran/faster-whisper-tiny", 75 * MB),
+    ("Systran/faster-whisper-base.en", 145 * MB),
+    ("Systran/faster-whisper-base", 145 * MB),
+    ("Systran/faster-whisper-small.en", 484 * MB),
+    ("Systran/faster-whisper-small", 484 * MB),
+    ("Systran/faster-whisper-medium.en", 1530 * MB),
+    ("Systran/faster-whisper-medium", 1530 * MB),
+    ("Systran/faster-whisper-large-v1", 3090 * MB),
+    ("Systran/faster-whisper-large-v2", 3090 * MB),
+    ("Systran/faster-whisper-large-v3", 3090 * MB),
+    ("Systran/faster-distil-whisper-large-v2", 1510 * MB),
+    ("Systran/faster-distil-whisper-medium.en", 789 * MB),
+    ("Systran/faster-distil-whisper-small.en", 336 * MB),
+    ("Systran/faster-distil-whisper-large-v3", 1510 * MB),
+    ("mobiuslabsgmbh/faster-whisper-large-v3-turbo", 1620 * MB),
+];
+
+/// Roughly how many bytes downloading model `name` takes, when it's a known model
+pub fn approximate_size(name: &str) -> Option<u64> {
+    let repo = repo_id(name)?;
+    APPROXIMATE_SIZES
+        .iter()
+        .find(|(known, _)| *known == repo)
+        .map(|(_, size)| *size)
+}
+
+/// Approximate memory a loaded model takes at float16, in MB, by repository
+const RESIDENT_MB: &[(&str, u64)] = &[
+    ("Systran/faster-whisper-tiny.en", 150),
+    ("Systran/faster-whisper-tiny", 150),
+    ("Systran/faster-whisper-base.en", 290),
+    ("Systran/faster-whisper-base", 290),
+    ("Systran/faster-whisper-small.en", 950),
+    ("Systran/faster-whisper-small", 950),
+    ("Systran/faster-whisper-medium.en", 2600),
+    ("Systran/faster-whisper-medium", 2600),
+    ("Systran/faster-whisper-large-v1", 4700),
+    ("Systran/faster-whisper-large-v2", 4700),
+    ("Systran/faster-whisper-large-v3", 4700),
+    ("Systran/faster-distil-whisper-large-v2", 2500),
+    ("Systran/faster-distil-whisper-medium.en", 1400),
+    ("Systran/faster-distil-whisper-small.en", 650),
+    ("Systran/faster-distil-whisper-large-v3", 2500),
+    ("mobiuslabsgmbh/faster-whisper-large-v3-turbo", 2700),
+];
+
+/// How the compute type scales `RESIDENT_MB`, in percent. Types not listed, such as `auto`
+/// and `default`, may end up as float32 and are counted as such.
+const COMPUTE_TYPE_PERCENT: &[(&str, u64)] = &[
+    ("float16", 100),
+    ("bfloat16", 100),
+    ("int8_float16", 60),
+    ("int8_bfloat16", 60),
+    ("int8_float32", 60),
+    ("int8", 60),
+    ("float32", 200),
+];
+
+/// Roughly how much memory model `name` takes once loaded with `compute_type`, in MB, when
+/// it's a known model
+pub fn estimated_memory_mb(name: &str, compute_type: &str) -> Option<u64> {
+    let repo = repo_id(name)?;
+    let base = RESIDENT_MB
+        .iter()
+        .find(|(known, _)| *known == repo)
+        .map(|(_, mb)| *mb)?;
+    let percent = COMPUTE_TYPE_PERCENT
+        .iter()
+        .find(|(known, _)| compute_type.eq_ignore_ascii_case(known))
+        .map_or(200, |(_, percent)| *percent);
+    Some(base * percent / 100)
+}
+
+/// Hub repository for a model name; names containing `/` are already repository ids
+pub fn repo_id(name: &str) -> Option<String> {
+    if name.contains('/') {
+        return Some(name.to_string());
+    }
+    REPOSITORIES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, repo)| repo.to_string())
+}
+
+/// The Hugging Face hub cache: `HF_HUB_CACHE`, else `HF_HOME/hub`, else
+/// `~/.cache/huggingface/hub`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    if let Some(dir) = var("HF_HUB_CACHE") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(home) = var("HF_HOME") {
+        return Some(PathBuf::from(home).join("hub"));
+    }
+    var("HOME").map(|home| PathBuf::from(home).join(".cache/huggingface/hub"))
+}
+
+/// Where the hub caches `repo` below `cache_dir`
+pub fn repo_cache_dir(cache_dir: &Path, repo: &str) -> PathBuf {
+    cache_dir.join(format!("models--{}", repo.replace('/', "--")))
+}
+
+/// What's wrong with one file of a cached model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileProblem {
+    /// Absent, or a link to a blob that was never completed
+    Missing,
+    Empty,
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for FileProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileProblem::Missing => f.write_str("missing"),
+            FileProblem::Empty => f.write_str("empty"),
+            FileProblem::ChecksumMismatch { expected, actual } => {
+                write!(f, "SHA-256 {} doesn't match {}", actual, expected)
+            }
+        }
+    }
+}
+
+/// One file of a cached model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCheck {
+    pub name: String,
+    pub size: u64,
+    /// The contents were hashed and matched a known SHA-256
+    pub sha256_verified: bool,
+    pub problem: Option<FileProblem>,
+}
+
+/// Result of `verify_cached`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub model: String,
+    /// Hub repository, or `None` for a model loaded from a local directory
+    pub repo: Option<String>,
+    /// Snapshot or directory the files were read from; `None` when nothing is cached
+    pub location: Option<PathBuf>,
+    pub revision: Option<String>,
+    pub files: Vec<FileCheck>,
+    /// Partial blobs left by an interrupted download
+    pub incomplete_downloads: Vec<PathBuf>,
+}
+
+impl VerificationReport {
+    pub fn is_cached(&self) -> bool {
+        self.location.is_some()
+    }
+
+    /// Cached, with every file present, non-empty and matching any known checksum
+    pub fn is_ok(&self) -> bool {
+        self.is_cached() && self.problems().next().is_none()
+    }
+
+    pub fn problems(&self) -> impl Iterator<Item = (&str, &FileProblem)> {
+        self.files
+            .iter()
+            .filter_map(|file| Some((file.name.as_str(), file.problem.as_ref()?)))
+    }
+
+    /// Hash files not yet verified against `checksums` (file name to SHA-256, as the hub
+    /// reports them) and record any mismatch
+    pub fn check_against(&mut self, checksums: &BTreeMap<String, String>) -> Result<()> {
+        let Some(location) = &self.location else {
+            return Ok(());
+        };
+        for file in &mut self.files {
+            if file.sha256_verified || file.problem.is_some() {
+                continue;
+            }
+            if let Some(expected) = checksums.get(&file.name) {
+                let actual = sha256_file(&location.join(&file.name))?;
+                if actual == *expected {
+                    file.sha256_verified = true;
+                } else {
+                    file.problem = Some(FileProblem::ChecksumMismatch {
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the cached copy of model `name` below the hub cache `cache_dir`, or the directory
+/// `name` itself when it is one.
+///
+/// Every file in the snapshot is checked along with `REQUIRED_FILES`. Large files are
+/// stored as blobs named by their SHA-256, so those are hashed and compared without
+/// contacting the hub; `VerificationReport::check_against` covers the rest.
+pub fn verify_cached(name: &str, cache_dir: &Path) -> Result<VerificationReport> {
+    if Path::new(name).is_dir() {
+        let mut report = VerificationReport {
+            model: name.to_string(),
+            repo: None,
+            location: Some(PathBuf::from(name)),
+            revision: None,
+            files: Vec::new(),
+            incomplete_downloads: Vec::new(),
+        };
+        report.files = check_files(Path::new(name))?;
+        return Ok(report);
+    }
+
+    let repo = repo_id(name).ok_or_else(|| {
+        TranscriptionError::ConfigError(format!(
+            "Unknown model '{}'; expected a faster-whisper model name, a hub repository or \
+             a directory",
+            name
+        ))
+    })?;
+    let repo_dir = repo_cache_dir(cache_dir, &repo);
+    let mut report = VerificationReport {
+        model: name.to_string(),
+        repo: Some(repo),
+        location: None,
+        revision: None,
+        files: Vec::new(),
+        incomplete_downloads: incomplete_blobs(&repo_dir.join("blobs")),
+    };
+
+    let revision = fs::read_to_string(repo_dir.join("refs").join("main"))
+        .ok()
+        .map(|revision| revision.trim().to_string())
+        .filter(|revision| !revision.is_empty())
+        .or_else(|| latest_snapshot(&repo_dir.join("snapshots")));
+    let Some(revision) = revision else {
+        debug!("No snapshot of {} in {}", name, repo_dir.display());
+        return Ok(report);
+    };
+    let snapshot = repo_dir.join("snapshots").join(&revision);
+    if !snapshot.is_dir() {
+        return Ok(report);
+    }
+    report.files = check_files(&snapshot)?;
+    report.location = Some(snapshot);
+    report.revision = Some(revision);
+    Ok(report)
+}
+
+/// Whether a snapshot of model `name` with every required file is cached, without hashing
+/// anything as `verify_cached` does
+pub fn has_snapshot(name: &str, cache_dir: &Path) -> bool {
+    let Some(repo) = repo_id(name) else {
+        return false;
+    };
+    let Ok(snapshots) = fs::read_dir(repo_cache_dir(cache_dir, &repo).join("snapshots")) else {
+        return false;
+    };
+    snapshots.filter_map(|entry| entry.ok()).any(|entry| {
+        REQUIRED_FILES
+            .iter()
+            .all(|file| entry.path().join(file).is_file())
+    })
+}
+
+fn check_files(dir: &Path) -> Result<Vec<FileCheck>> {
+    let mut names: Vec<String> = REQUIRED_FILES.iter().map(|name| name.to_string()).collect();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if !names.contains(&name) && !name.starts_with('.') {
+            names.push(name);
+        }
+    }
+
+    let mut files = Vec::new();
+    for name in names {
+        let path = dir.join(&name);
+        // A link whose blob was never completed is as good as missing
+        let size = match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => continue,
+            Err(_) => {
+                files.push(FileCheck {
+                    name,
+                    size: 0,
+                    sha256_verified: false,
+                    problem: Some(FileProblem::Missing),
+                });
+                continue;
+            }
+        };
+        let mut check = FileCheck {
+            name,
+            size,
+            sha256_verified: false,
+            problem: (size == 0).then_some(FileProblem::Empty),
+        };
+        if let (None, Some(expected)) = (&check.problem, blob_sha256(&path)) {
+            debug!("Hashing {}", path.display());
+            let actual = sha256_file(&path)?;
+            if actual == expected {
+                check.sha256_verified = true;
+            } else {
+                check.problem = Some(FileProblem::ChecksumMismatch { expected, actual });
+            }
+        }
+        files.push(check);
+    }
+    Ok(files)
+}
+
+/// The SHA-256 a snapshot link's blob is named after, for files stored with Git LFS
+fn blob_sha256(path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    let name = target.file_name()?.to_str()?;
+    (name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit())).then(|| name.to_lowercase())
+}
+
+fn incomplete_blobs(blobs: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(blobs) else {
+        return Vec::new();
+    };
+    let mut incomplete: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "incomplete"))
+        .collect();
+    incomplete.sort();
+    incomplete
+}
+
+fn latest_snapshot(snapshots: &Path) -> Option<String> {
+    fs::read_dir(snapshots)
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.file_name().to_string_lossy().into_owned()))
+        })
+        .max()
+        .map(|(_, name)| name)
+}
+
+/// Delete everything cached for model `name`, so the next load downloads it again.
+/// Returns whether there was anything to delete; local model directories are never
+/// touched.
+pub fn remove_cached(name: &str, cache_dir: &Path) -> Result<bool> {
+    if Path::new(name).is_dir() {
+        return Err(TranscriptionError::ConfigError(format!(
+            "{} is a local model directory; it can't be downloaded again",
+            name
+        )));
+    }
+    let repo = repo_id(name)
+        .ok_or_else(|| TranscriptionError::ConfigError(format!("Unknown model '{}'", name)))?;
+    let repo_dir = repo_cache_dir(cache_dir, &repo);
+    match fs::symlink_metadata(&repo_dir) {
+        Err(_) => Ok(false),
+        // Only the link is the cache's to delete, not whatever it points at
+        Ok(metadata) if metadata.is_symlink() => {
+            fs::remove_file(&repo_dir)?;
+            Ok(true)
+        }
+        Ok(_) => {
+            fs::remove_dir_all(&repo_dir)?;
+            Ok(true)
+        }
+    }
+}
+
+/// A model found in the hub cache by `list_cached`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedModel {
+    pub repo: String,
+    /// faster-whisper's name for the repository, when it has one
+    pub name: Option<String>,
+    pub path: PathBuf,
+    /// Bytes on disk, counting every blob and partial download once
+    pub size: u64,
+    /// Most recent access time of any of its files, where the filesystem records them
+    pub last_used: Option<SystemTime>,
+}
+
+impl CachedModel {
+    /// The name to pass back to `--model` or `models rm`
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.repo)
+    }
+}
+
+/// Order for `sort_cached`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheSort {
+    #[default]
+    Name,
+    /// Largest first
+    Size,
+    /// Most recently used first
+    LastUsed,
+}
+
+impl FromStr for CacheSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(CacheSort::Name),
+            "size" => Ok(CacheSort::Size),
+            "last-used" | "used" => Ok(CacheSort::LastUsed),
+            other => Err(format!(
+                "Unknown sort '{}' (expected name, size or last-used)",
+                other
+            )),
+        }
+    }
+}
+
+pub fn sort_cached(models: &mut [CachedModel], sort: CacheSort) {
+    match sort {
+        CacheSort::Name => models.sort_by(|a, b| a.display_name().cmp(b.display_name())),
+        CacheSort::Size => models.sort_by_key(|model| Reverse(model.size)),
+        CacheSort::LastUsed => models.sort_by_key(|model| Reverse(model.last_used)),
+    }
+}
+
+/// CTranslate2 models in the hub cache `cache_dir`, recognised by a `model.bin` in one of
+/// their snapshots, sorted by name.
+///
+/// Symlinks are never followed, so a repository linked in from elsewhere is skipped and
+/// sizes count each blob once rather than again through its snapshot links.
+pub fn list_cached(cache_dir: &Path) -> Result<Vec<CachedModel>> {
+    let entries = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut models = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let dir_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(repo) = dir_name
+            .strip_prefix("models--")
+            .map(|repo| repo.replace("--", "/"))
+        else {
+            continue;
+        };
+        if !entry.file_type()?.is_dir() {
+            debug!("Skipping {}: not a directory", entry.path().display());
+            continue;
+        }
+        let path = entry.path();
+        if !has_model_bin(&path.join("snapshots")) {
+            continue;
+        }
+        let (size, last_used) = disk_usage(&path)?;
+        let name = REPOSITORIES
+            .iter()
+            .find(|(_, known)| *known == repo)
+            .map(|(alias, _)| alias.to_string());
+        models.push(CachedModel {
+            repo,
+            name,
+            path,
+            size,
+            last_used,
+        });
+    }
+    sort_cached(&mut models, CacheSort::Name);
+    Ok(models)
+}
+
+fn has_model_bin(snapshots: &Path) -> bool {
+    fs::read_dir(snapshots).is_ok_and(|entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .any(|entry| fs::symlink_metadata(entry.path().join("model.bin")).is_ok())
+    })
+}
+
+/// Total size of the regular files below `dir` and their latest access time, without
+/// following symlinks
+fn disk_usage(dir: &Path) -> Result<(u64, Option<SystemTime>)> {
+    let mut size = 0;
+    let mut last_used = None;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                let metadata = entry.metadata()?;
+                size += metadata.len();
+                last_used = last_used.max(metadata.accessed().ok());
+            }
+        }
+    }
+    Ok((size, last_used))
+}
+
+/// Files faster-whisper fetches from a model repository, as in its `download_model`
+const DOWNLOAD_PATTERNS: &[&str] = &[
+    "config.json",
+    "preprocessor_config.json",
+    "model.bin",
+    "tokenizer.json",
+    "vocabulary.",
+];
+
+fn is_model_file(name: &str) -> bool {
+    !name.contains('/')
+        && DOWNLOAD_PATTERNS
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('.') {
+                Some(stem) => name
+                    .strip_prefix(stem)
+                    .is_some_and(|rest| rest.starts_with('.')),
+                None => name == *pattern,
+            })
+}
+
+/// One file of a hub repository revision
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HubFile {
+    #[serde(rename = "rfilename")]
+    pub name: String,
+    /// Git object id, which names the blob of files not stored with LFS
+    #[serde(rename = "blobId", default)]
+    pub blob_id: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    lfs: Option<HubLfs>,
+}
+
+impl HubFile {
+    /// SHA-256 of a Git LFS file
+    pub fn sha256(&self) -> Option<String> {
+        self.lfs.as_ref().map(|lfs| lfs.sha256.to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct HubLfs {
+    sha256: String,
+}
+
+/// A repository revision as the hub API describes it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HubRevision {
+    /// Commit the revision resolved to
+    pub sha: String,
+    #[serde(rename = "siblings")]
+    pub files: Vec<HubFile>,
+}
+
+/// The hub, or the mirror `HF_ENDPOINT` names
+fn hub_endpoint() -> String {
+    std::env::var("HF_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty())
+        .unwrap_or_else(|| "https://huggingface.co".to_string())
+}
+
+/// The files of `repo` at `revision` (a branch or commit), from the hub API
+pub fn hub_revision(repo: &str, revision: &str) -> Result<HubRevision> {
+    fetch_revision(&hub_endpoint(), repo, revision)
+}
+
+fn fetch_revision(endpoint: &str, repo: &str, revision: &str) -> Result<HubRevision> {
+    let url = format!(
+        "{}/api/models/{}/revision/{}?blobs=true",
+        endpoint.trim_end_matches('/'),
+        repo,
+        revision
+    );
+    let failed = |detail: String| TranscriptionError::DownloadFailed {
+        url: url.clone(),
+        detail,
+    };
+    let body = ureq::get(&url)
+        .timeout(std::time::Duration::from_secs(15))
+        .call()
+        .map_err(|e| failed(e.to_string()))?
+        .into_string()
+        .map_err(|e| failed(e.to_string()))?;
+    serde_json::from_str(&body).map_err(|e| failed(e.to_string()))
+}
+
+/// SHA-256 of each Git LFS file of `repo` at `revision`, from the hub API
+pub fn hub_checksums(repo: &str, revision: &str) -> Result<BTreeMap<String, String>> {
+    Ok(checksums(&hub_revision(repo, revision)?))
+}
+
+fn checksums(revision: &HubRevision) -> BTreeMap<String, String> {
+    revision
+        .files
+        .iter()
+        .filter_map(|file| Some((file.name.clone(), file.sha256()?)))
+        .collect()
+}
+
+/// Download model `name` into the hub cache `cache_dir` without loading it, then verify
+/// it.
+///
+/// Files are laid out as `huggingface_hub` does, so faster-whisper finds them with
+/// `--model-dir` pointing at the same directory, even offline. Blobs already present are
+/// kept; interrupted files resume. `progress` is called with the file name as bytes
+/// arrive. The endpoint honours `HF_ENDPOINT`.
+pub fn pull(
+    name: &str,
+    cache_dir: &Path,
+    progress: &mut dyn FnMut(&str, DownloadProgress),
+) -> Result<VerificationReport> {
+    pull_from(&hub_endpoint(), name, cache_dir, progress)
+}
+
+fn pull_from(
+    endpoint: &str,
+    name: &str,
+    cache_dir: &Path,
+    progress: &mut dyn FnMut(&str, DownloadProgress),
+) -> Result<VerificationReport> {
+    if Path::new(name).is_dir() {
+        return Err(TranscriptionError::ConfigError(format!(
+            "{} is a local model directory; there is nothing to pull",
+            name
+        )));
+    }
+    let repo = repo_id(name)
+        .ok_or_else(|| TranscriptionError::ConfigError(format!("Unknown model '{}'", name)))?;
+    let revision = fetch_revision(endpoint, &repo, "main")?;
+    let repo_dir = repo_cache_dir(cache_dir, &repo);
+    let blobs = repo_dir.join("blobs");
+    let snapshot = repo_dir.join("snapshots").join(&revision.sha);
+    fs::create_dir_all(&blobs)?;
+    fs::create_dir_all(&snapshot)?;
+
+    for file in revision
+        .files
+        .iter()
+        .filter(|file| is_model_file(&file.name))
+    {
+        let blob_name = file
+            .sha256()
+            .or_else(|| file.blob_id.clone())
+            .ok_or_else(|| TranscriptionError::DownloadFailed {
+                url: format!("{}/{}", repo, file.name),
+                detail: "the hub listed no blob id".to_string(),
+            })?;
+        let blob = blobs.join(&blob_name);
+        let present = fs::metadata(&blob)
+            .is_ok_and(|metadata| file.size.is_none_or(|size| metadata.len() == size));
+        if present {
+            debug!("{} is already downloaded", file.name);
+        } else {
+            let url = format!(
+                "{}/{}/resolve/{}/{}",
+                endpoint.trim_end_matches('/'),
+                repo,
+                revision.sha,
+                file.name
+            );
+            info!("Fetching {} {}", name, file.name);
+            let options = DownloadOptions {
+                expect_sha256: file.sha256(),
+                ..Default::default()
+            };
+            download(&url, &blob, &options, &mut |update| {
+                progress(&file.name, update)
+            })?;
+        }
+        link_blob(&blob_name, &snapshot.join(&file.name))?;
+    }
+    fs::create_dir_all(repo_dir.join("refs"))?;
+    fs::write(repo_dir.join("refs").join("main"), &revision.sha)?;
+
+    let mut report = verify_cached(name, cache_dir)?;
+    report.check_against(&checksums(&revision))?;
+    Ok(report)
+}
+
+/// Point a snapshot entry at its blob, as `huggingface_hub` does: a relative symlink, or
+/// a copy where links aren't available
+fn link_blob(blob_name: &str, entry: &Path) -> Result<()> {
+    let _ = fs::remove_file(entry);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(Path::new("../../blobs").join(blob_name), entry)?;
+    #[cfg(not(unix))]
+    fs::copy(
+        entry
+            .parent()
+            .and_then(Path::parent)
+            .and_then(Path::parent)
+            .map(|repo_dir| repo_dir.join("blobs").join(blob_name))
+            .unwrap_or_default(),
+        entry,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    const REVISION: &str = "0123abcd";
+
+    /// Lay out a hub cache entry the way `huggingface_hub` does: content-addressed blobs
+    /// and a snapshot of links to them
+    fn cache_model(cache: &Path, files: &[(&str, &[u8])]) -> PathBuf {
+        let repo_dir = repo_cache_dir(cache, "Systran/faster-whisper-tiny");
+        let blobs = repo_dir.join("blobs");
+        let snapshot = repo_dir.join("snapshots").join(REVISION);
+        fs::create_dir_all(&blobs).unwrap();
+        fs::create_dir_all(&snapshot).unwrap();
+        fs::create_dir_all(repo_dir.join("refs")).unwrap();
+        fs::write(repo_dir.join("refs").join("main"), REVISION).unwrap();
+        for (name, contents) in files {
+            let staged = blobs.join("staged");
+            fs::write(&staged, contents).unwrap();
+            // model.bin goes through LFS, so its blob is named by its SHA-256
+            let blob = if *name == "model.bin" {
+                blobs.join(sha256_file(&staged).unwrap())
+            } else {
+                blobs.join(format!("etag-{}", name))
+            };
+            fs::rename(&staged, &blob).unwrap();
+            std::os::unix::fs::symlink(&blob, snapshot.join(name)).unwrap();
+        }
+        repo_dir
+    }
+
+    #[test]
+    fn test_verify_complete_and_corrupted_caches() {
+        let cache = tempdir().unwrap();
+        let repo_dir = cache_model(
+            cache.path(),
+            &[
+                ("model.bin", b"weights"),
+                ("config.json", b"{}"),
+                ("tokenizer.json", b"{}"),
+                ("vocabulary.txt", b"a\nb\n"),
+            ],
+        );
+        let report = verify_cached("tiny", cache.path()).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.revision.as_deref(), Some(REVISION));
+        assert_eq!(report.files.len(), 4);
+        let model = report.files.iter().find(|f| f.name == "model.bin").unwrap();
+        assert!(model.sha256_verified);
+        assert!(has_snapshot("tiny", cache.path()));
+        assert!(!has_snapshot("base", cache.path()));
+
+        // The laptop slept mid-download: weights truncated, tokenizer never fetched
+        let snapshot = repo_dir.join("snapshots").join(REVISION);
+        fs::write(
+            fs::canonicalize(snapshot.join("model.bin")).unwrap(),
+            b"weig",
+        )
+        .unwrap();
+        fs::remove_file(fs::canonicalize(snapshot.join("tokenizer.json")).unwrap()).unwrap();
+        fs::write(repo_dir.join("blobs").join("abc.incomplete"), b"").unwrap();
+        let report = verify_cached("tiny", cache.path()).unwrap();
+        assert!(!report.is_ok());
+        assert!(!has_snapshot("tiny", cache.path()));
+        let problems: BTreeMap<&str, &FileProblem> = report.problems().collect();
+        assert!(matches!(
+            problems["model.bin"],
+            FileProblem::ChecksumMismatch { .. }
+        ));
+        assert_eq!(problems["tokenizer.json"], &FileProblem::Missing);
+        assert_eq!(report.incomplete_downloads.len(), 1);
+
+        assert!(remove_cached("tiny", cache.path()).unwrap());
+        assert!(!verify_cached("tiny", cache.path()).unwrap().is_cached());
+        assert!(!remove_cached("tiny", cache.path()).unwrap());
+    }
+
+    #[test]
+    fn test_hub_checksums_and_local_directories() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("model.bin"), b"weights").unwrap();
+        fs::write(dir.path().join("config.json"), b"").unwrap();
+        let name = dir.path().to_str().unwrap();
+        let mut report = verify_cached(name, Path::new("/unused")).unwrap();
+        assert_eq!(report.repo, None);
+        let problems: BTreeMap<&str, &FileProblem> = report.problems().collect();
+        assert_eq!(problems["config.json"], &FileProblem::Empty);
+        assert_eq!(problems["tokenizer.json"], &FileProblem::Missing);
+
+        let checksums = BTreeMap::from([("model.bin".to_string(), "0".repeat(64))]);
+        report.check_against(&checksums).unwrap();
+        assert!(matches!(
+            report.problems().next(),
+            Some(("model.bin", FileProblem::ChecksumMismatch { .. }))
+        ));
+        assert!(remove_cached(name, Path::new("/unused")).is_err());
+    }
+
+    /// A hub serving one revision of `Systran/faster-whisper-tiny`, recording the paths
+    /// requested. With `corrupt_weights`, model.bin arrives scrambled.
+    fn serve_hub(
+        files: Vec<(&'static str, &'static [u8])>,
+        corrupt_weights: bool,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let log = requested.clone();
+        let siblings: Vec<serde_json::Value> = files
+            .iter()
+            .map(|(name, contents)| {
+                let mut sibling = serde_json::json!({
+                    "rfilename": name,
+                    "blobId": format!("oid-{}", name),
+                    "size": contents.len(),
+                });
+                if *name == "model.bin" {
+                    let digest = ring::digest::digest(&ring::digest::SHA256, contents);
+                    let sha: String = digest
+                        .as_ref()
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect();
+                    sibling["lfs"] = serde_json::json!({ "sha256": sha });
+                }
+                sibling
+            })
+            .collect();
+        let api = serde_json::json!({ "sha": REVISION, "siblings": siblings }).to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                std::io::BufReader::new(&stream)
+                    .read_line(&mut request)
+                    .unwrap();
+                let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+                log.lock().unwrap().push(path.clone());
+                let mut body: Vec<u8> = if path.starts_with("/api/") {
+                    api.clone().into_bytes()
+                } else {
+                    let name = path.rsplit('/').next().unwrap();
+                    files
+                        .iter()
+                        .find(|(file, _)| *file == name)
+                        .unwrap()
+                        .1
+                        .to_vec()
+                };
+                if corrupt_weights && path.ends_with("/model.bin") {
+                    body.reverse();
+                }
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&body);
+            }
+        });
+        (endpoint, requested)
+    }
+
+    #[test]
+    fn test_pull_lays_out_a_verifiable_cache() {
+        let cache = tempdir().unwrap();
+        let (endpoint, requested) = serve_hub(
+            vec![
+                ("model.bin", b"weights"),
+                ("config.json", b"{}"),
+                ("tokenizer.json", b"{}"),
+                ("vocabulary.txt", b"a\nb\n"),
+                ("README.md", b"# tiny"),
+            ],
+            false,
+        );
+        let mut updates = Vec::new();
+        let report = pull_from(&endpoint, "tiny", cache.path(), &mut |file, progress| {
+            updates.push((file.to_string(), progress.downloaded))
+        })
+        .unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.revision.as_deref(), Some(REVISION));
+        assert_eq!(report.files.len(), 4);
+        assert!(updates.contains(&("model.bin".to_string(), 7)));
+        assert!(!requested
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|path| path.ends_with("README.md")));
+
+        // Everything is in place, so pulling again only asks for the file list
+        let fetched = requested.lock().unwrap().len();
+        pull_from(&endpoint, "tiny", cache.path(), &mut |_, _| {}).unwrap();
+        assert_eq!(requested.lock().unwrap().len(), fetched + 1);
+    }
+
+    #[test]
+    fn test_pull_rejects_corrupted_weights() {
+        let cache = tempdir().unwrap();
+        let (endpoint, _) = serve_hub(
+            vec![("config.json", b"{}"), ("model.bin", b"weights")],
+            true,
+        );
+        let err = pull_from(&endpoint, "tiny", cache.path(), &mut |_, _| {}).unwrap_err();
+        assert_eq!(err.kind(), "checksum_mismatch");
+        let blobs = repo_cache_dir(cache.path(), "Systran/faster-whisper-tiny").join("blobs");
+        let mut left: Vec<String> = fs::read_dir(blobs)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        // config.json came before the weights and is kept for the next attempt
+        assert_eq!(left, vec!["oid-config.json"]);
+    }
+
+    #[test]
+    fn test_list_and_remove_cached_models() {
+        let cache = tempdir().unwrap();
+        cache_model(
+            cache.path(),
+            &[("model.bin", &[0; 1000]), ("config.json", b"{}")],
+        );
+        let other = repo_cache_dir(cache.path(), "pyannote/segmentation");
+        fs::create_dir_all(other.join("snapshots").join(REVISION)).unwrap();
+        fs::write(
+            other
+                .join("snapshots")
+                .join(REVISION)
+                .join("pytorch_model.bin"),
+            b"x",
+        )
+        .unwrap();
+
+        // A model linked in from outside the cache is neither listed nor followed
+        let outside = tempdir().unwrap();
+        cache_model(outside.path(), &[("model.bin", &[0; 10])]);
+        std::os::unix::fs::symlink(
+            repo_cache_dir(outside.path(), "Systran/faster-whisper-tiny"),
+            repo_cache_dir(cache.path(), "Systran/faster-whisper-base"),
+        )
+        .unwrap();
+
+        let models = list_cached(cache.path()).unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].display_name(), "tiny");
+        assert_eq!(models[0].repo, "Systran/faster-whisper-tiny");
+        // Both blobs and refs/main
+        assert_eq!(models[0].size, 1002 + REVISION.len() as u64);
+        assert!(models[0].last_used.is_some());
+        assert!(list_cached(&cache.path().join("missing"))
+            .unwrap()
+            .is_empty());
+
+        // Deleting the linked model removes the link and leaves its target alone
+        assert!(remove_cached("base", cache.path()).unwrap());
+        assert!(verify_cached("tiny", outside.path()).unwrap().is_cached());
+        assert!(remove_cached("tiny", cache.path()).unwrap());
+        assert!(list_cached(cache.path()).unwrap().is_empty());
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn test_sort_cached() {
+        let model = |repo: &str, size: u64, used: u64| CachedModel {
+            repo: repo.to_string(),
+            name: None,
+            path: PathBuf::from(repo),
+            size,
+            last_used: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(used)),
+        };
+        let mut models = vec![model("b", 1, 30), model("a", 3, 10), model("c", 2, 20)];
+        let order = |models: &[CachedModel]| -> Vec<String> {
+            models.iter().map(|m| m.repo.clone()).collect()
+        };
+        sort_cached(&mut models, "size".parse().unwrap());
+        assert_eq!(order(&models), ["a", "c", "b"]);
+        sort_cached(&mut models, CacheSort::LastUsed);
+        assert_eq!(order(&models), ["b", "c", "a"]);
+        sort_cached(&mut models, CacheSort::default());
+        assert_eq!(order(&models), ["a", "b", "c"]);
+        assert!("age".parse::<CacheSort>().is_err());
+    }
+
+    #[test]
+    fn test_repo_ids() {
+        assert_eq!(
+            repo_id("large").as_deref(),
+            Some("Systran/faster-whisper-large-v3")
+        );
+        assert_eq!(repo_id("me/custom-ct2").as_deref(), Some("me/custom-ct2"));
+        assert_eq!(repo_id("huge"), None);
+        assert!(is_model_file("vocabulary.json"));
+        assert!(!is_model_file("vocabulary"));
+        assert!(!is_model_file("onnx/model.bin"));
+        assert_eq!(
+            repo_cache_dir(Path::new("/hub"), "Systran/faster-whisper-tiny"),
+            PathBuf::from("/hub/models--Systran--faster-whisper-tiny")
+        );
+    }
+
+    #[test]
+    fn test_every_repository_has_a_size() {
+        for (alias, _) in REPOSITORIES {
+            assert!(approximate_size(alias).is_some(), "{}", alias);
+        }
+        assert_eq!(approximate_size("large"), approximate_size("large-v3"));
+        assert_eq!(approximate_size("me/custom-ct2"), None);
+    }
+
+    #[test]
+    fn test_every_repository_has_a_memory_estimate() {
+        for (alias, _) in REPOSITORIES {
+            assert!(estimated_memory_mb(alias, "float16").is_some(), "{}", alias);
+        }
+        assert_eq!(estimated_memory_mb("medium", "float16"), Some(2600));
+        assert_eq!(estimated_memory_mb("medium", "INT8"), Some(1560));
+        assert_eq!(estimated_memory_mb("medium", "auto"), Some(5200));
+        assert_eq!(estimated_memory_mb("me/custom-ct2", "float16"), None);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::{Result, TranscriptionError};
+use crate::language;
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+
+/// A transcription in the shape of the OpenAI Audio API's `verbose_json` response, for
+/// tooling written against that API.
+///
+/// faster-whisper doesn't report every field the API does; see `VerboseSegment` for what
+/// stands in for the missing ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerboseJson {
+    /// Always `transcribe`
+    pub task: String,
+    /// Whisper's English name for the language, such as `english`
+    pub language: String,
+    pub duration: f64,
+    pub text: String,
+    pub segments: Vec<VerboseSegment>,
+    /// Only present when word timestamps were requested, as with the API's
+    /// `timestamp_granularities[]=word`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<VerboseWord>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerboseSegment {
+    pub id: usize,
+    /// Start of the segment in 10 ms frames; the API reports the start of the decoding
+    /// window, which faster-whisper doesn't expose
+    pub seek: u64,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// Token ids aren't kept, so always empty
+    pub tokens: Vec<u32>,
+    /// The decoding temperature isn't kept, so the first one faster-whisper tries
+    pub temperature: f64,
+    pub avg_logprob: f64,
+    /// Not kept either; 1.0 reads as unremarkable to tools that filter on it
+    pub compression_ratio: f64,
+    pub no_speech_prob: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerboseWord {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// `result` as an OpenAI `verbose_json` response, with a top-level word list when `words`
+/// is set. Asking for words fails when the transcription has segments but no word timings.
+pub fn verbose_json(result: &TranscriptionResult, words: bool) -> Result<VerboseJson> {
+    let words = if words {
+        let listed: Vec<VerboseWord> = result
+            .segments
+            .iter()
+            .flat_map(|segment| &segment.words)
+            .filter(|word| !word.word.trim().is_empty())
+            .map(|word| VerboseWord {
+                word: word.word.trim().to_string(),
+                start: word.start,
+                end: word.end,
+            })
+            .collect();
+        if listed.is_empty() && !result.segments.is_empty() {
+            return Err(TranscriptionError::ConfigError(
+                "Words were requested for the OpenAI verbose_json output, but the transcription \
+                 has no word timestamps; enable word_timestamps"
+                    .to_string(),
+            ));
+        }
+        Some(listed)
+    } else {
+        None
+    };
+    Ok(VerboseJson {
+        task: "transcribe".to_string(),
+        language: language::language_name(&result.language)
+            .map(str::to_string)
+            .unwrap_or_else(|| result.language.clone()),
+        duration: result.duration,
+        text: result.full_text.clone(),
+        segments: result
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(id, segment)| VerboseSegment {
+                id,
+                seek: (segment.start.max(0.0) * 100.0).round() as u64,
+                start: segment.start,
+                end: segment.end,
+                text: segment.text.clone(),
+                tokens: Vec::new(),
+                temperature: 0.0,
+                avg_logprob: segment.avg_logprob,
+                compression_ratio: 1.0,
+                no_speech_prob: segment.no_speech_prob,
+            })
+            .collect(),
+        words,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{segment, word, ResultBuilder};
+    use crate::types::TranscriptionSegment;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    /// A response captured from the OpenAI API, shortened
+    const CAPTURED: &str = include_str!("../tests/fixtures/openai_verbose_json_response.json");
+    /// What `sample_result` is expected to convert to
+    const GOLDEN: &str = include_str!("../tests/fixtures/openai_verbose_json_expected.json");
+
+    fn sample_result() -> TranscriptionResult {
+        ResultBuilder::new()
+            .segment(TranscriptionSegment {
+                avg_logprob: -0.25,
+                words: vec![word(" Hello", 0.0, 0.8), word(" there.", 0.8, 2.0)],
+                ..segment(0.0, 2.0, " Hello there.")
+            })
+            .segment(TranscriptionSegment {
+                no_speech_prob: 0.02,
+                avg_logprob: -0.5,
+                words: vec![word(" General", 2.5, 3.2), word(" Kenobi.", 3.2, 4.5)],
+                ..segment(2.5, 4.5, " General Kenobi.")
+            })
+            .build()
+    }
+
+    /// Every object key path in `value`, with array elements folded together
+    fn shape(value: &Value, path: String, out: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let path = format!("{}.{}", path, key);
+                    out.insert(path.clone());
+                    shape(value, path, out);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    shape(item, format!("{}[]", path), out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_matches_golden_file() {
+        let converted =
+            serde_json::to_value(sample_result().to_openai_verbose_json(true).unwrap()).unwrap();
+        let golden: Value = serde_json::from_str(GOLDEN).unwrap();
+        assert_eq!(converted, golden);
+    }
+
+    #[test]
+    fn test_shape_matches_captured_response() {
+        let captured: Value = serde_json::from_str(CAPTURED).unwrap();
+        let converted =
+            serde_json::to_value(sample_result().to_openai_verbose_json(true).unwrap()).unwrap();
+        let (mut want, mut got) = (BTreeSet::new(), BTreeSet::new());
+        shape(&captured, String::new(), &mut want);
+        shape(&converted, String::new(), &mut got);
+        assert_eq!(got, want);
+
+        // And it reads back as the same structure
+        let parsed: VerboseJson = serde_json::from_str(CAPTURED).unwrap();
+        assert_eq!(parsed.segments[1].id, 1);
+        assert_eq!(parsed.words.unwrap()[1].word, "beach");
+    }
+
+    #[test]
+    fn test_words_are_optional_but_must_exist_when_requested() {
+        let mut result = sample_result();
+        let without = serde_json::to_value(result.to_openai_verbose_json(false).unwrap()).unwrap();
+        assert!(without.get("words").is_none());
+
+        for segment in &mut result.segments {
+            segment.words.clear();
+        }
+        let err = result.to_openai_verbose_json(true).unwrap_err();
+        assert_eq!(err.kind(), "config");
+
+        // Silence has no words to give
+        result.segments.clear();
+        assert_eq!(
+            result.to_openai_verbose_json(true).unwrap().words,
+            Some(vec![])
+        );
+
+        result.language = "xx".to_string();
+        assert_eq!(result.to_openai_verbose_json(false).unwrap().language, "xx");
+    }
+}
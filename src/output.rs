@@ -0,0 +1,795 @@
+use crate::chapters;
+use crate::confidence::{self, ConfidenceThresholds};
+use crate::error::{Result, TranscriptionError};
+use crate::stats::TranscriptStats;
+use crate::types::TranscriptionResult;
+use crate::uncertain::{self, UncertainMarking};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Json,
+    Txt,
+    Srt,
+    Vtt,
+    /// The OpenAI Audio API's `verbose_json` response
+    #[serde(rename = "openai-json")]
+    OpenAiJson,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 5] = [
+        OutputFormat::Json,
+        OutputFormat::Txt,
+        OutputFormat::Srt,
+        OutputFormat::Vtt,
+        OutputFormat::OpenAiJson,
+    ];
+
+    /// File extension used when deriving output paths
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Txt => "txt",
+            OutputFormat::Srt => "srt",
+            OutputFormat::Vtt => "vtt",
+            // Kept apart from the native JSON when both are written
+            OutputFormat::OpenAiJson => "openai.json",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::OpenAiJson => f.write_str("openai-json"),
+            _ => f.write_str(self.extension()),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "txt" | "text" => Ok(OutputFormat::Txt),
+            "srt" => Ok(OutputFormat::Srt),
+            "vtt" | "webvtt" => Ok(OutputFormat::Vtt),
+            "openai-json" | "verbose_json" => Ok(OutputFormat::OpenAiJson),
+            other => Err(format!(
+                "Invalid output format: {} (expected json, txt, srt, vtt or openai-json)",
+                other
+            )),
+        }
+    }
+}
+
+/// Parse a comma-separated list of formats such as `json,srt`, dropping repeats
+pub fn parse_formats(list: &str) -> std::result::Result<Vec<OutputFormat>, String> {
+    let mut formats = Vec::new();
+    for name in list
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let format: OutputFormat = name.parse()?;
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    if formats.is_empty() {
+        return Err("No output format given".to_string());
+    }
+    Ok(formats)
+}
+
+/// Where the `format` output for `base_path` goes: `base_path` with its extension replaced
+/// by the format's. A format's whole extension is replaced, so `talk.openai.json` gives
+/// `talk.srt` rather than `talk.openai.srt`.
+pub fn artifact_path(base_path: &Path, format: OutputFormat) -> PathBuf {
+    let mut path = without_format_extension(base_path).into_os_string();
+    path.push(".");
+    path.push(format.extension());
+    PathBuf::from(path)
+}
+
+/// Where the YouTube chapter list for `base_path` goes, beside its outputs: `talk.json` and
+/// `talk.openai.json` both give `talk.chapters.txt`
+pub fn chapters_path(base_path: &Path) -> PathBuf {
+    let mut path = without_format_extension(base_path).into_os_string();
+    path.push(".chapters.txt");
+    PathBuf::from(path)
+}
+
+/// `path` without the extension of the format it names, or without its last extension if
+/// it names none
+fn without_format_extension(path: &Path) -> PathBuf {
+    // Longest first, so `openai.json` goes before `json` can match half of it
+    let mut extensions: Vec<&str> = OutputFormat::ALL.iter().map(|f| f.extension()).collect();
+    extensions.sort_by_key(|ext| std::cmp::Reverse(ext.len()));
+    for extension in extensions {
+        let mut stem = path.to_path_buf();
+        let matched = extension.rsplit('.').all(|part| {
+            let found = stem
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(part));
+            if found {
+                stem.set_extension("");
+            }
+            found
+        });
+        if matched {
+            return stem;
+        }
+    }
+    path.with_extension("")
+}
+
+/// How the text formats are rendered; JSON always carries the result as it is
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RenderOptions {
+    /// Mark words the model was unsure of in TXT and Markdown
+    pub uncertain: Option<UncertainMarking>,
+    /// Word timestamps were asked for, so `openai-json` lists words and fails without them
+    pub words: bool,
+}
+
+/// Write `result` once per format, each to its `artifact_path`, returning the paths
+/// written.
+///
+/// A format that fails doesn't stop the rest: the failures are returned together as
+/// `TranscriptionError::OutputWriteFailed`, which also lists what was written.
+pub fn write_outputs(
+    result: &TranscriptionResult,
+    base_path: &Path,
+    formats: &[OutputFormat],
+    options: &RenderOptions,
+) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    let mut failures = Vec::new();
+    for &format in formats {
+        let path = artifact_path(base_path, format);
+        match render_with(result, format, options)
+            .and_then(|rendered| Ok(write_atomic(&path, rendered)?))
+        {
+            Ok(()) => written.push(path),
+            Err(e) => failures.push(format!("{} to {}: {}", format, path.display(), e)),
+        }
+    }
+    if failures.is_empty() {
+        Ok(written)
+    } else {
+        Err(TranscriptionError::OutputWriteFailed { failures, written })
+    }
+}
+
+/// The `.tmp` sibling `path` is written to before being renamed into place
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Write `contents` to `path` atomically: into its `.tmp` sibling first, which is flushed
+/// to disk and then renamed over `path`. A crash or Ctrl-C mid-write leaves the previous
+/// file, or none, rather than a truncated one.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let tmp = tmp_path(path);
+    let written = std::fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        persist(&file, &tmp, path)
+    });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Flush `file`, written at `tmp`, to disk and rename it over `path`
+pub fn persist(file: &std::fs::File, tmp: &Path, path: &Path) -> std::io::Result<()> {
+    file.sync_all()?;
+    std::fs::rename(tmp, path)
+}
+
+/// How segment timestamps are shown in console listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampStyle {
+    /// `HH:MM:SS.mmm`
+    #[default]
+    Clock,
+    /// `MM:SS`, with minutes running past 59 for long files
+    Short,
+    /// Raw seconds such as `3661.27s`
+    Seconds,
+}
+
+impl FromStr for TimestampStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "clock" | "hh:mm:ss.mmm" => Ok(TimestampStyle::Clock),
+            "short" | "mm:ss" => Ok(TimestampStyle::Short),
+            "seconds" | "secs" => Ok(TimestampStyle::Seconds),
+            other => Err(format!(
+                "Invalid timestamp style: {} (expected clock, short or seconds)",
+                other
+            )),
+        }
+    }
+}
+
+/// How the human-readable console listing is styled
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ConsoleOptions {
+    pub timestamps: TimestampStyle,
+    /// Color segments by confidence using these thresholds; `None` prints plain text
+    pub colors: Option<ConfidenceThresholds>,
+}
+
+/// Format a time offset for display. Negative and NaN inputs are shown as zero.
+pub fn format_timestamp(seconds: f64, style: TimestampStyle) -> String {
+    let seconds = seconds.max(0.0);
+    match style {
+        TimestampStyle::Clock => subtitle_timestamp(seconds, '.'),
+        TimestampStyle::Short => {
+            let total_secs = (seconds * 1000.0).round() as u64 / 1000;
+            format!("{:02}:{:02}", total_secs / 60, total_secs % 60)
+        }
+        TimestampStyle::Seconds => format!("{:.2}s", seconds),
+    }
+}
+
+/// Render a transcription result in the requested format
+pub fn render(result: &TranscriptionResult, format: OutputFormat) -> Result<String> {
+    render_with(result, format, &RenderOptions::default())
+}
+
+/// `render` with the text formats adjusted by `options`
+pub fn render_with(
+    result: &TranscriptionResult,
+    format: OutputFormat,
+    options: &RenderOptions,
+) -> Result<String> {
+    let rendered = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(result)?,
+        OutputFormat::Txt => render_txt(result, options.uncertain.as_ref()),
+        OutputFormat::Srt => render_srt(result),
+        OutputFormat::Vtt => render_vtt(result),
+        OutputFormat::OpenAiJson => {
+            serde_json::to_string_pretty(&result.to_openai_verbose_json(options.words)?)?
+        }
+    };
+    Ok(rendered)
+}
+
+/// Print a result when no output file was given.
+///
+/// Txt, SRT and WebVTT are written to `out` exactly as they would be to a file, so the output
+/// can be redirected. JSON keeps the human-readable summary, with metadata and headings on
+/// `err` and only the transcript itself on `out`.
+pub fn write_console<O: Write, E: Write>(
+    result: &TranscriptionResult,
+    format: OutputFormat,
+    console: &ConsoleOptions,
+    options: &RenderOptions,
+    out: &mut O,
+    err: &mut E,
+) -> Result<()> {
+    if format != OutputFormat::Json {
+        out.write_all(render_with(result, format, options)?.as_bytes())?;
+        out.flush()?;
+        write_summaries(result, err)?;
+        return Ok(());
+    }
+
+    writeln!(err, "\n=== Transcription Results ===")?;
+    writeln!(
+        err,
+        "Language: {} (confidence: {:.2}%)",
+        result.language,
+        result.language_probability * 100.0
+    )?;
+    if let Some(detected) = &result.language_override {
+        writeln!(
+            err,
+            "Detected {} ({:.2}%), outside the allowed languages",
+            detected.detected,
+            detected.detected_probability * 100.0
+        )?;
+    }
+    writeln!(err, "Duration: {:.2}s", result.duration)?;
+    writeln!(err, "Transcription Time: {:.2}s", result.transcription_time)?;
+    writeln!(err, "Real-time Factor: {:.2}x", result.real_time_factor)?;
+    writeln!(err, "\nFull Text:")?;
+    writeln!(out, "{}", result.full_text)?;
+
+    if !result.segments.is_empty() {
+        writeln!(err, "\n=== Segments ===")?;
+        for (i, segment) in result.segments.iter().enumerate() {
+            let line = match &console.colors {
+                Some(thresholds) => {
+                    confidence::render_colored(segment, console.timestamps, thresholds)
+                }
+                None => format!(
+                    "[{} -> {}] {}",
+                    format_timestamp(segment.start, console.timestamps),
+                    format_timestamp(segment.end, console.timestamps),
+                    segment.text
+                ),
+            };
+            writeln!(out, "[{:03}] {}", i + 1, line)?;
+        }
+    }
+    out.flush()?;
+    write_summaries(result, err)
+}
+
+/// Statistics and chapters, when attached, after the transcript
+fn write_summaries<E: Write>(result: &TranscriptionResult, err: &mut E) -> Result<()> {
+    if let Some(stats) = &result.stats {
+        write_stats(stats, err)?;
+    }
+    if let Some(chapters) = &result.chapters {
+        writeln!(err, "\n=== Chapters ===")?;
+        write!(err, "{}", chapters::render_youtube(chapters))?;
+    }
+    Ok(())
+}
+
+fn write_stats<E: Write>(stats: &TranscriptStats, err: &mut E) -> Result<()> {
+    let total = stats.speech_seconds + stats.silence_seconds;
+    let share = |seconds: f64| {
+        if total > 0.0 {
+            seconds / total * 100.0
+        } else {
+            0.0
+        }
+    };
+    writeln!(err, "\n=== Statistics ===")?;
+    writeln!(err, "Words: {}", stats.word_count)?;
+    writeln!(
+        err,
+        "Speaking rate: {:.0} words/min",
+        stats.words_per_minute
+    )?;
+    writeln!(
+        err,
+        "Speech: {:.1}s ({:.1}%), silence: {:.1}s ({:.1}%)",
+        stats.speech_seconds,
+        share(stats.speech_seconds),
+        stats.silence_seconds,
+        share(stats.silence_seconds)
+    )?;
+    writeln!(
+        err,
+        "Average segment: {:.1}s",
+        stats.average_segment_seconds
+    )?;
+    writeln!(
+        err,
+        "Longest silence: {:.1}s at {}",
+        stats.longest_silence_seconds,
+        format_timestamp(stats.longest_silence_start, TimestampStyle::Clock)
+    )?;
+    Ok(())
+}
+
+fn render_txt(result: &TranscriptionResult, uncertain: Option<&UncertainMarking>) -> String {
+    let mut out = match uncertain {
+        Some(marking) => uncertain::mark_text(result, marking),
+        None => result.full_text.clone(),
+    };
+    out.push('\n');
+    out
+}
+
+fn render_srt(result: &TranscriptionResult) -> String {
+    let mut out = String::new();
+    for (i, segment) in result.segments.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            subtitle_timestamp(segment.start, ','),
+            subtitle_timestamp(segment.end, ','),
+            match &segment.speaker {
+                Some(speaker) => format!("[{}] {}", speaker, segment.text),
+                None => segment.text.clone(),
+            }
+        ));
+    }
+    out
+}
+
+fn render_vtt(result: &TranscriptionResult) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for segment in &result.segments {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            subtitle_timestamp(segment.start, '.'),
+            subtitle_timestamp(segment.end, '.'),
+            // WebVTT's voice span, which players can show or style
+            match &segment.speaker {
+                Some(speaker) => format!("<v {}>{}", speaker, segment.text),
+                None => segment.text.clone(),
+            }
+        ));
+    }
+    out
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm` (WebVTT)
+fn subtitle_timestamp(seconds: f64, separator: char) -> String {
+    // f64::max treats NaN as missing, so NaN clamps to zero too
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_ms / 3_600_000;
+    let minutes = (total_ms % 3_600_000) / 60_000;
+    let secs = (total_ms % 60_000) / 1000;
+    let millis = total_ms % 1000;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        hours, minutes, secs, separator, millis
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{segment, ResultBuilder};
+    use crate::types::TranscriptionSegment;
+
+    fn sample_result() -> TranscriptionResult {
+        ResultBuilder::new()
+            .language_probability(0.99)
+            .segment(segment(0.0, 2.5, "Hello there."))
+            .segment(TranscriptionSegment {
+                no_speech_prob: 0.02,
+                avg_logprob: -0.3,
+                ..segment(2.5, 3661.2, "General Kenobi.")
+            })
+            .duration(5.0)
+            .build()
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.srt");
+        // An earlier run was killed mid-write, leaving a partial file and its temporary
+        std::fs::write(&path, "1\n00:00:00,000 --> 00:0").unwrap();
+        std::fs::write(tmp_path(&path), "1\n").unwrap();
+
+        write_atomic(&path, "1\n00:00:00,000 --> 00:00:01,000\n Hi.\n\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,000\n Hi.\n\n"
+        );
+        assert!(!tmp_path(&path).exists());
+        assert_eq!(tmp_path(&path), dir.path().join("a.srt.tmp"));
+
+        // A failed write leaves nothing behind
+        let missing = dir.path().join("no/such/dir/a.srt");
+        assert!(write_atomic(&missing, "x").is_err());
+        assert!(!tmp_path(&missing).exists());
+    }
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("SRT".parse::<OutputFormat>(), Ok(OutputFormat::Srt));
+        assert_eq!("webvtt".parse::<OutputFormat>(), Ok(OutputFormat::Vtt));
+        assert!("docx".parse::<OutputFormat>().is_err());
+        assert_eq!("openai-json".parse(), Ok(OutputFormat::OpenAiJson));
+        assert_eq!(OutputFormat::OpenAiJson.to_string(), "openai-json");
+        assert_eq!(
+            artifact_path(Path::new("talk.json"), OutputFormat::OpenAiJson),
+            Path::new("talk.openai.json")
+        );
+    }
+
+    #[test]
+    fn test_artifact_paths_beside_openai_json() {
+        // Primary openai-json with extras json,srt
+        let primary = Path::new("out/talk.v2.openai.json");
+        let paths: Vec<PathBuf> = [
+            OutputFormat::OpenAiJson,
+            OutputFormat::Json,
+            OutputFormat::Srt,
+        ]
+        .into_iter()
+        .map(|format| artifact_path(primary, format))
+        .collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("out/talk.v2.openai.json"),
+                Path::new("out/talk.v2.json"),
+                Path::new("out/talk.v2.srt"),
+            ]
+        );
+        // A name that isn't a format's keeps the old behaviour
+        assert_eq!(
+            artifact_path(Path::new("talk.out"), OutputFormat::Vtt),
+            Path::new("talk.vtt")
+        );
+        assert_eq!(
+            artifact_path(Path::new("talk"), OutputFormat::Txt),
+            Path::new("talk.txt")
+        );
+    }
+
+    #[test]
+    fn test_chapters_path_drops_the_whole_format_extension() {
+        assert_eq!(
+            chapters_path(Path::new("out/talk.openai.json")),
+            Path::new("out/talk.chapters.txt")
+        );
+        assert_eq!(
+            chapters_path(Path::new("out/talk.v2.srt")),
+            Path::new("out/talk.v2.chapters.txt")
+        );
+    }
+
+    #[test]
+    fn test_render_srt() {
+        let srt = render(&sample_result(), OutputFormat::Srt).unwrap();
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:02,500\nHello there.\n\n"));
+        assert!(srt.contains("2\n00:00:02,500 --> 01:01:01,200\nGeneral Kenobi.\n"));
+    }
+
+    #[test]
+    fn test_render_vtt_and_txt() {
+        let vtt = render(&sample_result(), OutputFormat::Vtt).unwrap();
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:02.500\n"));
+
+        let txt = render(&sample_result(), OutputFormat::Txt).unwrap();
+        assert_eq!(txt, "Hello there. General Kenobi.\n");
+    }
+
+    #[test]
+    fn test_subtitles_prefix_speakers() {
+        let mut result = sample_result();
+        result.segments[0].speaker = Some("S1".to_string());
+        result.segments[1].speaker = Some("S2".to_string());
+        let srt = render(&result, OutputFormat::Srt).unwrap();
+        assert!(srt.contains("00:00:02,500\n[S1] Hello there.\n"));
+        assert!(srt.contains("01:01:01,200\n[S2] General Kenobi.\n"));
+        let vtt = render(&result, OutputFormat::Vtt).unwrap();
+        assert!(vtt.contains("00:00:02.500\n<v S1>Hello there.\n"));
+    }
+
+    #[test]
+    fn test_uncertain_marking_only_changes_text() {
+        let mut result = sample_result();
+        result.segments[1].avg_logprob = -1.5;
+        let options = RenderOptions {
+            uncertain: Some(UncertainMarking::new(0.5).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_with(&result, OutputFormat::Txt, &options).unwrap(),
+            "Hello there. ⟦General Kenobi.⟧\n"
+        );
+        for format in [OutputFormat::Json, OutputFormat::Srt] {
+            assert_eq!(
+                render_with(&result, format, &options).unwrap(),
+                render(&result, format).unwrap()
+            );
+        }
+    }
+
+    fn console_with(format: OutputFormat, console: &ConsoleOptions) -> (String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_console(
+            &sample_result(),
+            format,
+            console,
+            &RenderOptions::default(),
+            &mut out,
+            &mut err,
+        )
+        .unwrap();
+        (
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    fn console(format: OutputFormat) -> (String, String) {
+        let console = ConsoleOptions {
+            timestamps: TimestampStyle::Seconds,
+            colors: None,
+        };
+        console_with(format, &console)
+    }
+
+    #[test]
+    fn test_console_machine_formats_only_use_stdout() {
+        for format in [OutputFormat::Txt, OutputFormat::Srt, OutputFormat::Vtt] {
+            let (out, err) = console(format);
+            assert_eq!(out, render(&sample_result(), format).unwrap());
+            assert!(err.is_empty(), "{} wrote to stderr: {}", format, err);
+        }
+    }
+
+    #[test]
+    fn test_console_summary_keeps_metadata_on_stderr() {
+        let (out, err) = console(OutputFormat::Json);
+        assert!(out.starts_with("Hello there. General Kenobi.\n[001] [0.00s -> 2.50s]"));
+        assert!(!out.contains("==="));
+        assert!(err.contains("=== Transcription Results ==="));
+        assert!(err.contains("Language: en (confidence: 99.00%)"));
+        assert!(!err.contains("Hello there."));
+    }
+
+    #[test]
+    fn test_console_colors_only_the_listing() {
+        let console = ConsoleOptions {
+            colors: Some(ConfidenceThresholds::default()),
+            ..Default::default()
+        };
+        let (out, err) = console_with(OutputFormat::Json, &console);
+        assert!(out.contains("[001] [00:00:00.000 -> 00:00:02.500] \x1b[32mHello there.\x1b[0m"));
+        assert!(!err.contains('\x1b'));
+
+        let (out, _) = console_with(OutputFormat::Srt, &console);
+        assert!(!out.contains('\x1b'), "machine formats are never colored");
+    }
+
+    #[test]
+    fn test_console_chapters_on_stderr() {
+        let mut result = sample_result();
+        result.chapters = Some(result.detect_chapters(2.0, 0.0));
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        write_console(
+            &result,
+            OutputFormat::Txt,
+            &ConsoleOptions::default(),
+            &RenderOptions::default(),
+            &mut out,
+            &mut err,
+        )
+        .unwrap();
+        let err = String::from_utf8(err).unwrap();
+        assert!(
+            err.ends_with("=== Chapters ===\n00:00 Hello there.\n"),
+            "{}",
+            err
+        );
+        assert!(!String::from_utf8(out).unwrap().contains("Chapters"));
+    }
+
+    #[test]
+    fn test_console_stats_block_on_stderr() {
+        let (_, err) = console(OutputFormat::Json);
+        assert!(!err.contains("=== Statistics ==="));
+
+        let mut result = sample_result();
+        result.duration = 10.0;
+        result.segments[1].end = 5.0;
+        result.stats = Some(result.stats());
+        for format in [OutputFormat::Json, OutputFormat::Txt] {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            write_console(
+                &result,
+                format,
+                &ConsoleOptions::default(),
+                &RenderOptions::default(),
+                &mut out,
+                &mut err,
+            )
+            .unwrap();
+            let (out, err) = (
+                String::from_utf8(out).unwrap(),
+                String::from_utf8(err).unwrap(),
+            );
+            assert!(!out.contains("Statistics"));
+            assert!(
+                err.contains("Words: 4\nSpeaking rate: 48 words/min\n"),
+                "{}",
+                err
+            );
+            assert!(err.contains("Speech: 5.0s (50.0%), silence: 5.0s (50.0%)"));
+            assert!(err.contains("Longest silence: 5.0s at 00:00:05.000"));
+        }
+
+        let json = render(&result, OutputFormat::Json).unwrap();
+        assert!(json.contains("\"words_per_minute\": 48.0"));
+    }
+
+    #[test]
+    fn test_format_timestamp_styles() {
+        assert_eq!(
+            format_timestamp(3661.27, TimestampStyle::Clock),
+            "01:01:01.270"
+        );
+        assert_eq!(format_timestamp(3661.27, TimestampStyle::Short), "61:01");
+        assert_eq!(
+            format_timestamp(3661.27, TimestampStyle::Seconds),
+            "3661.27s"
+        );
+        assert_eq!(
+            format_timestamp(3599.9996, TimestampStyle::Clock),
+            "01:00:00.000"
+        );
+        assert_eq!(format_timestamp(59.9996, TimestampStyle::Short), "01:00");
+        assert_eq!(
+            format_timestamp(1.0005, TimestampStyle::Clock),
+            "00:00:01.001"
+        );
+        assert_eq!(format_timestamp(7.999, TimestampStyle::Short), "00:07");
+        assert_eq!(
+            sample_result().segments[1].to_string(),
+            "[00:00:02.500 -> 01:01:01.200] General Kenobi."
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp_clamps_bad_input() {
+        for style in [
+            TimestampStyle::Clock,
+            TimestampStyle::Short,
+            TimestampStyle::Seconds,
+        ] {
+            assert_eq!(format_timestamp(-4.2, style), format_timestamp(0.0, style));
+            assert_eq!(
+                format_timestamp(f64::NAN, style),
+                format_timestamp(0.0, style)
+            );
+        }
+        assert_eq!(
+            format_timestamp(f64::NAN, TimestampStyle::Clock),
+            "00:00:00.000"
+        );
+        assert_eq!("MM:SS".parse(), Ok(TimestampStyle::Short));
+        assert!("iso".parse::<TimestampStyle>().is_err());
+    }
+
+    #[test]
+    fn test_write_outputs_fans_out_and_collects_failures() {
+        assert_eq!(
+            parse_formats("json, SRT,json"),
+            Ok(vec![OutputFormat::Json, OutputFormat::Srt])
+        );
+        assert!(parse_formats(" , ").is_err());
+        assert!(parse_formats("json,docx").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("talk_transcription.json");
+        let formats = [OutputFormat::Json, OutputFormat::Srt];
+        let written =
+            write_outputs(&sample_result(), &base, &formats, &RenderOptions::default()).unwrap();
+        assert_eq!(
+            written,
+            vec![base.clone(), dir.path().join("talk_transcription.srt")]
+        );
+        assert!(std::fs::read_to_string(&written[1])
+            .unwrap()
+            .starts_with("1\n00:00:00,000 --> 00:00:02,500"));
+
+        // A directory in the way of the SRT doesn't stop the VTT
+        let blocked = dir.path().join("blocked.srt");
+        std::fs::create_dir(&blocked).unwrap();
+        let formats = [OutputFormat::Srt, OutputFormat::Vtt];
+        match write_outputs(
+            &sample_result(),
+            &blocked,
+            &formats,
+            &RenderOptions::default(),
+        ) {
+            Err(TranscriptionError::OutputWriteFailed { failures, written }) => {
+                assert_eq!(failures.len(), 1);
+                assert!(failures[0].starts_with("srt to "));
+                assert_eq!(written, vec![dir.path().join("blocked.vtt")]);
+            }
+            other => panic!("expected OutputWriteFailed, got {:?}", other),
+        }
+    }
+}
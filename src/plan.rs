@@ -0,0 +1,1037 @@
+use crate::cache;
+use crate::error::{Result, TranscriptionError};
+use crate::language::LanguageMap;
+use crate::manifest::ManifestEntry;
+use crate::output::OutputFormat;
+use crate::probe;
+use crate::schedule::{self, Schedule};
+use crate::template::{self, OutputTemplate, TemplateContext};
+use crate::types::is_supported_audio_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use tracing::{debug, warn};
+
+/// How a batch should be planned
+#[derive(Debug, Clone)]
+pub struct PlanOptions {
+    /// Directory outputs are written into; `None` prints results instead
+    pub output_dir: Option<PathBuf>,
+    pub format: OutputFormat,
+    /// Don't re-transcribe files whose output already exists
+    pub skip_existing: bool,
+    /// Read durations from file headers where that is cheap
+    pub probe_durations: bool,
+    /// How output file names are derived from inputs
+    pub template: OutputTemplate,
+    /// Model size substituted for `{model}`
+    pub model: String,
+    /// Forced language substituted for `{lang}`; per-file languages take precedence
+    pub language: Option<String>,
+    /// Per-file languages for inputs without one of their own (e.g. from a manifest row)
+    pub language_map: Option<LanguageMap>,
+    /// Run date substituted for `{date}`
+    pub date: String,
+    /// Longest input to transcribe, in seconds; `None` for no limit
+    pub max_duration: Option<f64>,
+}
+
+impl Default for PlanOptions {
+    fn default() -> Self {
+        Self {
+            output_dir: None,
+            format: OutputFormat::Json,
+            skip_existing: false,
+            probe_durations: true,
+            template: OutputTemplate::default(),
+            model: "medium".to_string(),
+            language: None,
+            language_map: None,
+            date: template::today_utc(),
+            max_duration: None,
+        }
+    }
+}
+
+impl PlanOptions {
+    /// Output path for `input` under `output_dir`, or `None` when results are printed
+    pub fn output_path(&self, input: &Path, language: Option<&str>) -> Option<PathBuf> {
+        let dir = self.output_dir.as_ref()?;
+        let context = TemplateContext {
+            input,
+            format: self.format,
+            language: language.or(self.language.as_deref()),
+            model: &self.model,
+            date: &self.date,
+        };
+        Some(dir.join(self.template.expand(&context)))
+    }
+
+    /// Language the language map assigns to `input`, if any
+    pub fn mapped_language(&self, input: &Path) -> Option<String> {
+        self.language_map
+            .as_ref()?
+            .language_for(input)
+            .map(str::to_string)
+    }
+
+    /// Fail when `input`'s probed `duration` is over `max_duration`. Inputs whose duration
+    /// can't be read from the header aren't limited.
+    pub fn check_duration(&self, input: &Path, duration: Option<f64>) -> Result<()> {
+        match (duration, self.max_duration) {
+            (Some(duration), Some(limit)) if duration > limit => {
+                Err(TranscriptionError::DurationLimitExceeded {
+                    path: input.to_path_buf(),
+                    duration,
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedAction {
+    Transcribe,
+    SkipExisting,
+    /// The input (e.g. a manifest row) points at a file that doesn't exist
+    Missing,
+    /// Finished by an earlier run, according to the state file
+    AlreadyDone,
+    /// Failed in an earlier run and `--retry-failed` wasn't given
+    PreviouslyFailed,
+    /// Same contents as another planned input, whose result is written here as well
+    Duplicate,
+    /// Longer than `PlanOptions::max_duration`
+    TooLong,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub action: PlannedAction,
+    /// Per-file language override, e.g. from a manifest row
+    pub language: Option<String>,
+    /// Audio duration in seconds, when it could be probed without decoding
+    pub duration: Option<f64>,
+    /// The input transcribed in this file's place, for `Duplicate`s
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+}
+
+/// Inputs with identical contents: one is transcribed and the rest reuse its result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub primary: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// `a.wav = copy of a.wav, a (1).wav`
+impl fmt::Display for DuplicateGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} =", self.primary.display())?;
+        for (i, duplicate) in self.duplicates.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}", separator, duplicate.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// `BatchPlan::dedupe` one file at a time, so a directory can be deduplicated while it is
+/// still being listed. The first of several identical inputs is the primary.
+///
+/// Only files of equal size are hashed, and files with different language overrides are
+/// never merged. Unreadable files are left to fail on their own.
+#[derive(Debug, Default)]
+pub struct Deduper {
+    /// Primaries by size and language override, with their hashes once something of the
+    /// same size needed them
+    primaries: HashMap<(u64, Option<String>), Vec<Primary>>,
+}
+
+impl Deduper {
+    /// Plan `file` as a `Duplicate` if it's identical to an earlier file, returning whether
+    /// it was. Files not planned for transcription are left alone.
+    pub fn check(&mut self, file: &mut PlannedFile) -> bool {
+        if file.action != PlannedAction::Transcribe {
+            return false;
+        }
+        let Ok(metadata) = std::fs::metadata(&file.input) else {
+            return false;
+        };
+        let candidates = self
+            .primaries
+            .entry((metadata.len(), file.language.clone()))
+            .or_default();
+        if candidates.is_empty() {
+            candidates.push((file.input.clone(), None));
+            return false;
+        }
+        let Some(hash) = hash_input(&file.input) else {
+            return false;
+        };
+        for (primary, primary_hash) in candidates.iter_mut() {
+            if primary_hash.is_none() {
+                *primary_hash = hash_input(primary);
+            }
+            if *primary_hash == Some(hash) {
+                file.action = PlannedAction::Duplicate;
+                file.duplicate_of = Some(primary.clone());
+                return true;
+            }
+        }
+        candidates.push((file.input.clone(), Some(hash)));
+        false
+    }
+}
+
+/// An input others are compared against, hashed on first use
+type Primary = (PathBuf, Option<blake3::Hash>);
+
+fn hash_input(path: &Path) -> Option<blake3::Hash> {
+    cache::content_hash(path)
+        .map_err(|e| debug!("Cannot hash {}: {}", path.display(), e))
+        .ok()
+}
+
+/// The files a batch run would touch and what it would do with each
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchPlan {
+    pub files: Vec<PlannedFile>,
+}
+
+impl BatchPlan {
+    pub fn to_transcribe(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.files
+            .iter()
+            .filter(|file| file.action == PlannedAction::Transcribe)
+    }
+
+    pub fn to_skip(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.files
+            .iter()
+            .filter(|file| file.action != PlannedAction::Transcribe)
+    }
+
+    pub fn has_work(&self) -> bool {
+        self.to_transcribe().next().is_some()
+    }
+
+    /// Put the files in the order `schedule` starts them, by their probed durations
+    pub fn schedule(&mut self, schedule: Schedule) {
+        let durations: Vec<Option<f64>> = self.files.iter().map(|file| file.duration).collect();
+        let mut files: Vec<Option<PlannedFile>> = std::mem::take(&mut self.files)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.files = schedule::order(&durations, schedule)
+            .into_iter()
+            .filter_map(|index| files[index].take())
+            .collect();
+    }
+
+    /// Files that will get an output written: those transcribed and their duplicates
+    pub fn to_write(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.files.iter().filter(|file| {
+            matches!(
+                file.action,
+                PlannedAction::Transcribe | PlannedAction::Duplicate
+            )
+        })
+    }
+
+    /// Plan to transcribe only the first of several inputs with identical contents; the
+    /// others become `Duplicate`s of it and get a copy of its result.
+    ///
+    /// Only files of equal size are hashed, and files with different language overrides are
+    /// never merged. Unreadable files are left to fail on their own.
+    pub fn dedupe(&mut self) -> Vec<DuplicateGroup> {
+        let mut deduper = Deduper::default();
+        for file in &mut self.files {
+            deduper.check(file);
+        }
+        self.duplicate_groups()
+    }
+
+    /// Groups of inputs `dedupe` found to be identical, in plan order
+    pub fn duplicate_groups(&self) -> Vec<DuplicateGroup> {
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        for file in &self.files {
+            let Some(primary) = &file.duplicate_of else {
+                continue;
+            };
+            match groups.iter_mut().find(|group| group.primary == *primary) {
+                Some(group) => group.duplicates.push(file.input.clone()),
+                None => groups.push(DuplicateGroup {
+                    primary: primary.clone(),
+                    duplicates: vec![file.input.clone()],
+                }),
+            }
+        }
+        groups
+    }
+
+    /// Outputs that more than one planned input would write, with those inputs
+    pub fn collisions(&self) -> Vec<(PathBuf, Vec<PathBuf>)> {
+        let mut by_output: BTreeMap<&Path, Vec<PathBuf>> = BTreeMap::new();
+        for file in self.to_write() {
+            if let Some(output) = &file.output {
+                by_output
+                    .entry(output)
+                    .or_default()
+                    .push(file.input.clone());
+            }
+        }
+        by_output
+            .into_iter()
+            .filter(|(_, inputs)| inputs.len() > 1)
+            .map(|(output, inputs)| (output.to_path_buf(), inputs))
+            .collect()
+    }
+
+    /// Fail when two inputs would overwrite each other's output
+    pub fn check_collisions(&self) -> Result<()> {
+        let collisions = self.collisions();
+        if collisions.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = collisions
+            .iter()
+            .map(|(output, inputs)| {
+                let inputs: Vec<String> = inputs.iter().map(|i| i.display().to_string()).collect();
+                format!("{} <- {}", output.display(), inputs.join(", "))
+            })
+            .collect();
+        Err(TranscriptionError::ConfigError(format!(
+            "{} output path(s) would be written by more than one input: {} \
+             (adjust --output-template or pass --allow-collisions)",
+            collisions.len(),
+            details.join("; ")
+        )))
+    }
+
+    /// Sum of probed durations of the files to transcribe, and how many couldn't be probed
+    pub fn known_duration(&self) -> (f64, usize) {
+        self.to_transcribe()
+            .fold((0.0, 0), |(total, unknown), file| match file.duration {
+                Some(duration) => (total + duration, unknown),
+                None => (total, unknown + 1),
+            })
+    }
+}
+
+/// List the supported audio files directly inside `dir`, sorted by path
+pub fn discover_audio_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        return Err(TranscriptionError::InvalidPath(format!(
+            "Not a directory: {}",
+            dir.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_supported_audio_file(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Paths a streamed discovery may find ahead of the files being transcribed
+pub const DISCOVERY_CAPACITY: usize = 1024;
+
+/// Paths found by a discovery thread, yielded as they are found. The channel between them is
+/// bounded, so discovery stays at most `capacity` paths ahead of the consumer; iterating
+/// blocks until the next path turns up or discovery ends.
+pub struct Discovery {
+    receiver: mpsc::Receiver<PathBuf>,
+    progress: DiscoveryProgress,
+}
+
+/// How far a `Discovery` has got, readable while something else consumes it
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryProgress {
+    found: Arc<AtomicUsize>,
+    finished: Arc<AtomicBool>,
+}
+
+impl DiscoveryProgress {
+    /// Paths found so far
+    pub fn found(&self) -> usize {
+        self.found.load(Ordering::SeqCst)
+    }
+
+    /// Paths found in all, once discovery has finished
+    pub fn total(&self) -> Option<usize> {
+        self.finished.load(Ordering::SeqCst).then(|| self.found())
+    }
+}
+
+impl Discovery {
+    /// Run `source` on its own thread, passing on each path it yields
+    pub fn spawn<I>(capacity: usize, source: I) -> Self
+    where
+        I: IntoIterator<Item = PathBuf>,
+        I::IntoIter: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let progress = DiscoveryProgress::default();
+        let shared = progress.clone();
+        let source = source.into_iter();
+        std::thread::spawn(move || {
+            for path in source {
+                shared.found.fetch_add(1, Ordering::SeqCst);
+                // The consumer hung up, e.g. after a failure; nothing more is wanted
+                if sender.send(path).is_err() {
+                    break;
+                }
+            }
+            shared.finished.store(true, Ordering::SeqCst);
+        });
+        Self { receiver, progress }
+    }
+
+    pub fn progress(&self) -> DiscoveryProgress {
+        self.progress.clone()
+    }
+}
+
+impl Iterator for Discovery {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Like `discover_audio_files`, but listing `dir` on another thread and yielding files in
+/// directory order as they are found, so work can start before the listing ends
+pub fn stream_audio_files<P: AsRef<Path>>(dir: P, capacity: usize) -> Result<Discovery> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        return Err(TranscriptionError::InvalidPath(format!(
+            "Not a directory: {}",
+            dir.display()
+        )));
+    }
+    let entries = std::fs::read_dir(dir)?;
+    let files = entries.filter_map(|entry| match entry {
+        Ok(entry) => {
+            let path = entry.path();
+            (path.is_file() && is_supported_audio_file(&path)).then_some(path)
+        }
+        Err(e) => {
+            warn!("Skipping an unreadable directory entry: {}", e);
+            None
+        }
+    });
+    Ok(Discovery::spawn(capacity, files))
+}
+
+/// `<output_dir>/<stem>_transcription.<ext>`
+pub fn output_path_for(input: &Path, output_dir: &Path, format: OutputFormat) -> PathBuf {
+    let mut output_name = input.file_stem().unwrap_or_default().to_owned();
+    output_name.push(format!("_transcription.{}", format.extension()));
+    output_dir.join(output_name)
+}
+
+/// Work out output paths, skips and durations for `inputs` without touching the model
+pub fn plan_batch(inputs: Vec<PathBuf>, options: &PlanOptions) -> BatchPlan {
+    let files = plan_in_parallel(inputs, |input| {
+        let language = options.mapped_language(&input);
+        let output = options.output_path(&input, language.as_deref());
+        plan_file(input, output, options)
+    });
+    BatchPlan { files }
+}
+
+/// Threads planning a batch. Probing durations reads each file's header, which on network
+/// storage is mostly waiting, so this doesn't follow the CPU count.
+const PLAN_THREADS: usize = 8;
+
+/// `items.map(plan)` on up to `PLAN_THREADS` threads, keeping the order of `items`
+fn plan_in_parallel<T, F>(items: Vec<T>, plan: F) -> Vec<PlannedFile>
+where
+    T: Send,
+    F: Fn(T) -> PlannedFile + Sync,
+{
+    if items.len() < 2 {
+        return items.into_iter().map(plan).collect();
+    }
+    let per_thread = items.len().div_ceil(PLAN_THREADS);
+    let mut items = items.into_iter();
+    let chunks: Vec<Vec<T>> = std::iter::from_fn(|| {
+        let chunk: Vec<T> = items.by_ref().take(per_thread).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    })
+    .collect();
+    let plan = &plan;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(plan).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("planning thread panicked"))
+            .collect()
+    })
+}
+
+/// Whether an output exists and can be trusted as finished. A JSON output must also parse,
+/// since one cut short by a crash would otherwise be skipped over on every re-run.
+pub fn is_complete_output(path: &Path) -> bool {
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if !is_json {
+        return path.exists();
+    }
+    let Ok(contents) = std::fs::read(path) else {
+        return false;
+    };
+    let parses = serde_json::from_slice::<serde::de::IgnoredAny>(&contents).is_ok();
+    if !parses {
+        warn!(
+            "{} exists but isn't valid JSON; transcribing again",
+            path.display()
+        );
+    }
+    parses
+}
+
+/// Plan a single input whose output path is already known
+pub fn plan_file(input: PathBuf, output: Option<PathBuf>, options: &PlanOptions) -> PlannedFile {
+    let duration = if options.probe_durations || options.max_duration.is_some() {
+        probe::probe_duration(&input)
+    } else {
+        None
+    };
+    let action = match &output {
+        _ if !input.is_file() => PlannedAction::Missing,
+        Some(path) if options.skip_existing && is_complete_output(path) => {
+            PlannedAction::SkipExisting
+        }
+        _ if options.check_duration(&input, duration).is_err() => PlannedAction::TooLong,
+        _ => PlannedAction::Transcribe,
+    };
+    PlannedFile {
+        language: options.mapped_language(&input),
+        input,
+        output,
+        action,
+        duration,
+        duplicate_of: None,
+    }
+}
+
+/// Make sure `dir` exists (creating it if allowed) and that files can be created in it
+pub fn ensure_writable_dir(dir: &Path, create: bool) -> Result<()> {
+    let unwritable = |cause: &dyn std::fmt::Display| {
+        debug!("{} is not writable: {}", dir.display(), cause);
+        TranscriptionError::OutputUnwritable(dir.to_path_buf())
+    };
+
+    if !dir.is_dir() {
+        if !create || dir.exists() {
+            return Err(unwritable(&"not an existing directory"));
+        }
+        std::fs::create_dir_all(dir).map_err(|e| unwritable(&e))?;
+    }
+
+    let probe = dir.join(format!(".whisper-write-test-{}", std::process::id()));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| unwritable(&e))?;
+    std::fs::remove_file(&probe).map_err(|e| unwritable(&e))?;
+    Ok(())
+}
+
+/// Check every directory the plan will write into before any model is loaded.
+///
+/// `output_dir` is checked even when no file is planned yet (e.g. watch mode on an empty
+/// directory). Returns the directories that were checked.
+pub fn prepare_output_dirs(
+    plan: &BatchPlan,
+    output_dir: Option<&Path>,
+    create: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut dirs: BTreeSet<PathBuf> = output_dir.map(Path::to_path_buf).into_iter().collect();
+    for file in plan.to_write() {
+        if let Some(output) = &file.output {
+            let parent = match output.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            dirs.insert(parent);
+        }
+    }
+    for dir in &dirs {
+        ensure_writable_dir(dir, create)?;
+    }
+    Ok(dirs.into_iter().collect())
+}
+
+/// Plan manifest rows; explicit per-row outputs win over the derived `output_dir` path
+pub fn plan_manifest(entries: Vec<ManifestEntry>, options: &PlanOptions) -> BatchPlan {
+    let files = plan_in_parallel(entries, |entry| {
+        let language = entry
+            .language
+            .or_else(|| options.mapped_language(&entry.path));
+        let output = entry
+            .output
+            .or_else(|| options.output_path(&entry.path, language.as_deref()));
+        PlannedFile {
+            language,
+            ..plan_file(entry.path, output, options)
+        }
+    });
+    BatchPlan { files }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_filters_and_sorts() {
+        let dir = tempdir().unwrap();
+        for name in ["b.mp3", "a.WAV", "notes.txt", "c.flac"] {
+            std::fs::write(dir.path().join(name), b"x").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("nested.wav")).unwrap();
+
+        let files = discover_audio_files(dir.path()).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["a.WAV", "b.mp3", "c.flac"]);
+    }
+
+    #[test]
+    fn test_stream_audio_files() {
+        let dir = tempdir().unwrap();
+        for name in ["b.mp3", "a.WAV", "notes.txt", "c.flac"] {
+            std::fs::write(dir.path().join(name), b"x").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("nested.wav")).unwrap();
+
+        let discovery = stream_audio_files(dir.path(), 1).unwrap();
+        let progress = discovery.progress();
+        let mut names: Vec<_> = discovery
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["a.WAV", "b.mp3", "c.flac"]);
+        assert_eq!(progress.total(), Some(3));
+        assert!(stream_audio_files(dir.path().join("b.mp3"), 1).is_err());
+    }
+
+    #[test]
+    fn test_schedule_orders_planned_files_by_duration() {
+        let dir = tempdir().unwrap();
+        let mut inputs = Vec::new();
+        for (name, seconds) in [("a.wav", 2), ("b.mp3", 0), ("c.wav", 5), ("d.wav", 1)] {
+            let path = dir.path().join(name);
+            let bytes = match seconds {
+                0 => b"x".to_vec(),
+                _ => crate::probe::wav_bytes(16000, 1, 16000 * seconds),
+            };
+            std::fs::write(&path, bytes).unwrap();
+            inputs.push(path);
+        }
+        let names = |plan: &BatchPlan| -> Vec<String> {
+            plan.files
+                .iter()
+                .map(|f| f.input.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        // Planning runs on several threads but keeps the listed order
+        let mut plan = plan_batch(inputs, &PlanOptions::default());
+        assert_eq!(names(&plan), ["a.wav", "b.mp3", "c.wav", "d.wav"]);
+        plan.schedule(Schedule::LongestFirst);
+        assert_eq!(names(&plan), ["c.wav", "a.wav", "d.wav", "b.mp3"]);
+        plan.schedule(Schedule::SmallestFirst);
+        assert_eq!(names(&plan), ["d.wav", "a.wav", "c.wav", "b.mp3"]);
+    }
+
+    #[test]
+    fn test_plan_output_paths_and_skips() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let first = input_dir.path().join("first.wav");
+        let second = input_dir.path().join("second.mp3");
+        std::fs::write(&first, crate::probe::wav_bytes(16000, 1, 16000)).unwrap();
+        std::fs::write(&second, b"x").unwrap();
+        std::fs::write(output_dir.path().join("second_transcription.srt"), b"1\n").unwrap();
+
+        let options = PlanOptions {
+            output_dir: Some(output_dir.path().to_path_buf()),
+            format: OutputFormat::Srt,
+            skip_existing: true,
+            probe_durations: true,
+            ..Default::default()
+        };
+        let plan = plan_batch(vec![first, second], &options);
+
+        assert_eq!(
+            plan.files[0].output.as_deref(),
+            Some(output_dir.path().join("first_transcription.srt").as_path())
+        );
+        assert_eq!(plan.files[0].action, PlannedAction::Transcribe);
+        assert_eq!(plan.files[0].duration, Some(1.0));
+        assert_eq!(plan.files[1].action, PlannedAction::SkipExisting);
+        assert_eq!(plan.to_transcribe().count(), 1);
+        assert_eq!(plan.known_duration(), (1.0, 0));
+        assert!(plan.has_work());
+    }
+
+    #[test]
+    fn test_plan_without_skip_existing() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("a.wav");
+        std::fs::write(&input, b"x").unwrap();
+        std::fs::write(dir.path().join("a_transcription.json"), b"{}").unwrap();
+
+        let options = PlanOptions {
+            output_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let plan = plan_batch(vec![input], &options);
+        assert_eq!(plan.files[0].action, PlannedAction::Transcribe);
+
+        assert!(!BatchPlan::default().has_work());
+    }
+
+    #[test]
+    fn test_skip_existing_retranscribes_truncated_json() {
+        let dir = tempdir().unwrap();
+        let inputs: Vec<PathBuf> = ["done.wav", "cut.wav"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for input in &inputs {
+            std::fs::write(input, b"x").unwrap();
+        }
+        std::fs::write(
+            dir.path().join("done_transcription.json"),
+            br#"{"text": "ok"}"#,
+        )
+        .unwrap();
+        // What a write interrupted before outputs were renamed into place left behind
+        std::fs::write(
+            dir.path().join("cut_transcription.json"),
+            br#"{"language": "en", "segm"#,
+        )
+        .unwrap();
+
+        let options = PlanOptions {
+            output_dir: Some(dir.path().to_path_buf()),
+            skip_existing: true,
+            ..Default::default()
+        };
+        let plan = plan_batch(inputs, &options);
+        assert_eq!(plan.files[0].action, PlannedAction::SkipExisting);
+        assert_eq!(plan.files[1].action, PlannedAction::Transcribe);
+        assert!(!is_complete_output(&dir.path().join("missing.json")));
+        assert!(is_complete_output(&dir.path().join("done.wav")));
+    }
+
+    #[test]
+    fn test_plan_skips_inputs_over_the_duration_limit() {
+        let dir = tempdir().unwrap();
+        let long = dir.path().join("long.wav");
+        let short = dir.path().join("short.wav");
+        let unknown = dir.path().join("unknown.mp3");
+        std::fs::write(&long, crate::probe::wav_bytes(16000, 1, 16000 * 3)).unwrap();
+        std::fs::write(&short, crate::probe::wav_bytes(16000, 1, 16000)).unwrap();
+        std::fs::write(&unknown, b"x").unwrap();
+
+        let options = PlanOptions {
+            probe_durations: false,
+            max_duration: Some(2.0),
+            ..Default::default()
+        };
+        let plan = plan_batch(vec![long.clone(), short, unknown], &options);
+        let actions: Vec<_> = plan.files.iter().map(|f| f.action.clone()).collect();
+        assert_eq!(
+            actions,
+            [
+                PlannedAction::TooLong,
+                PlannedAction::Transcribe,
+                PlannedAction::Transcribe
+            ]
+        );
+        // Probed for the limit even when durations weren't asked for
+        assert_eq!(plan.files[0].duration, Some(3.0));
+
+        let err = options.check_duration(&long, Some(3.0)).unwrap_err();
+        assert_eq!(err.kind(), "duration_limit");
+        assert_eq!(err.path(), Some(long.as_path()));
+        assert!(PlanOptions::default()
+            .check_duration(&long, Some(1e9))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_plan_manifest_entries() {
+        let dir = tempdir().unwrap();
+        let present = dir.path().join("present.wav");
+        std::fs::write(&present, b"x").unwrap();
+        let entries = vec![
+            ManifestEntry {
+                line: 1,
+                path: present.clone(),
+                language: Some("es".to_string()),
+                output: Some(dir.path().join("custom.json")),
+            },
+            ManifestEntry {
+                line: 2,
+                path: dir.path().join("gone.wav"),
+                language: None,
+                output: None,
+            },
+        ];
+        let options = PlanOptions {
+            output_dir: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+
+        let plan = plan_manifest(entries, &options);
+        assert_eq!(plan.files[0].action, PlannedAction::Transcribe);
+        assert_eq!(plan.files[0].language.as_deref(), Some("es"));
+        assert_eq!(plan.files[0].output, Some(dir.path().join("custom.json")));
+        assert_eq!(plan.files[1].action, PlannedAction::Missing);
+        assert_eq!(
+            plan.files[1].output,
+            Some(dir.path().join("out").join("gone_transcription.json"))
+        );
+    }
+
+    #[test]
+    fn test_language_map_feeds_plan_and_template() {
+        let dir = tempdir().unwrap();
+        let map = crate::language::parse_language_map("*.fr.wav=fr\n", dir.path()).unwrap();
+        let options = PlanOptions {
+            output_dir: Some(dir.path().join("out")),
+            template: OutputTemplate::parse("{stem}.{lang}.{format}").unwrap(),
+            language_map: Some(map),
+            ..Default::default()
+        };
+
+        let inputs = vec![dir.path().join("talk.fr.wav"), dir.path().join("talk.wav")];
+        let plan = plan_batch(inputs, &options);
+        assert_eq!(plan.files[0].language.as_deref(), Some("fr"));
+        assert_eq!(
+            plan.files[0].output,
+            Some(dir.path().join("out").join("talk.fr.fr.json"))
+        );
+        assert_eq!(plan.files[1].language, None);
+        assert_eq!(
+            plan.files[1].output,
+            Some(dir.path().join("out").join("talk.auto.json"))
+        );
+
+        // A manifest row's own language wins over the map
+        let entries = vec![ManifestEntry {
+            line: 1,
+            path: dir.path().join("talk.fr.wav"),
+            language: Some("en".to_string()),
+            output: None,
+        }];
+        let plan = plan_manifest(entries, &options);
+        assert_eq!(plan.files[0].language.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn test_prepare_output_dirs_creates_nested_dirs() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("a.wav");
+        std::fs::write(&input, b"x").unwrap();
+        let output_dir = dir.path().join("out");
+        let options = PlanOptions {
+            output_dir: Some(output_dir.clone()),
+            template: OutputTemplate::parse("{lang}/{stem}.{format}").unwrap(),
+            ..Default::default()
+        };
+        let plan = plan_batch(vec![input], &options);
+
+        let err = prepare_output_dirs(&plan, Some(&output_dir), false).unwrap_err();
+        assert!(matches!(err, TranscriptionError::OutputUnwritable(ref p) if *p == output_dir));
+        assert!(
+            !output_dir.exists(),
+            "--no-create-dirs must not create anything"
+        );
+
+        let dirs = prepare_output_dirs(&plan, Some(&output_dir), true).unwrap();
+        assert_eq!(dirs, vec![output_dir.clone(), output_dir.join("auto")]);
+        assert!(output_dir.join("auto").is_dir());
+        assert_eq!(
+            std::fs::read_dir(output_dir.join("auto")).unwrap().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_unwritable_output_dir() {
+        let dir = tempdir().unwrap();
+        // A regular file where a directory is expected fails even for root
+        let blocker = dir.path().join("blocker");
+        std::fs::write(&blocker, b"x").unwrap();
+        let err = ensure_writable_dir(&blocker.join("out"), true).unwrap_err();
+        assert!(matches!(err, TranscriptionError::OutputUnwritable(_)));
+        assert!(ensure_writable_dir(&blocker, true).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let read_only = dir.path().join("read-only");
+            std::fs::create_dir(&read_only).unwrap();
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o555)).unwrap();
+            // Permission bits don't restrict root, so only assert when they take effect
+            if std::fs::write(read_only.join("probe"), b"x").is_err() {
+                let err = ensure_writable_dir(&read_only, true).unwrap_err();
+                assert_eq!(
+                    err.to_string(),
+                    format!("Output location is not writable: {}", read_only.display())
+                );
+            }
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_template_naming_and_collisions() {
+        let dir = tempdir().unwrap();
+        let inputs: Vec<PathBuf> = ["talk.wav", "talk.mp3", "other.wav"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                std::fs::write(&path, b"x").unwrap();
+                path
+            })
+            .collect();
+
+        let options = PlanOptions {
+            output_dir: Some(dir.path().join("out")),
+            template: OutputTemplate::parse("{stem}.{lang}.{format}").unwrap(),
+            language: Some("en".to_string()),
+            ..Default::default()
+        };
+        let plan = plan_batch(inputs.clone(), &options);
+        assert_eq!(
+            plan.files[2].output,
+            Some(dir.path().join("out").join("other.en.json"))
+        );
+        let collisions = plan.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0, dir.path().join("out").join("talk.en.json"));
+        assert_eq!(collisions[0].1, inputs[..2].to_vec());
+        let err = plan.check_collisions().unwrap_err();
+        assert!(err.to_string().contains("--allow-collisions"));
+
+        let options = PlanOptions {
+            template: OutputTemplate::parse("{stem}.{ext}.{format}").unwrap(),
+            ..options
+        };
+        assert!(plan_batch(inputs, &options).check_collisions().is_ok());
+    }
+
+    #[test]
+    fn test_deduper_checks_files_as_they_are_found() {
+        let dir = tempdir().unwrap();
+        let options = PlanOptions::default();
+        let mut deduper = Deduper::default();
+        let mut found = |name: &str, contents: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            let mut file = plan_file(path, None, &options);
+            deduper.check(&mut file);
+            file
+        };
+
+        let a = found("a.wav", b"RIFF same");
+        assert_eq!(a.action, PlannedAction::Transcribe);
+        assert_eq!(
+            found("b.wav", b"RIFF diff").action,
+            PlannedAction::Transcribe
+        );
+        let copy = found("copy of a.wav", b"RIFF same");
+        assert_eq!(copy.action, PlannedAction::Duplicate);
+        assert_eq!(copy.duplicate_of, Some(a.input.clone()));
+        assert_eq!(found("c.wav", b"RIFF").action, PlannedAction::Transcribe);
+
+        // Files that aren't transcribed are never primaries
+        let mut skipped = plan_file(dir.path().join("gone.wav"), None, &options);
+        assert!(!deduper.check(&mut skipped));
+        let mut german = plan_file(a.input.clone(), None, &options);
+        german.language = Some("de".to_string());
+        assert!(!deduper.check(&mut german));
+    }
+
+    #[test]
+    fn test_dedupe_identical_inputs() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, contents: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+        let inputs = vec![
+            write("a.wav", b"RIFF same"),
+            write("b.wav", b"RIFF diff"),
+            write("copy of a.wav", b"RIFF same"),
+            write("c.mp3", b"ID3"),
+            write("another a.wav", b"RIFF same"),
+        ];
+        let options = PlanOptions {
+            output_dir: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+        let mut plan = plan_batch(inputs.clone(), &options);
+
+        let groups = plan.dedupe();
+        assert_eq!(
+            groups,
+            vec![DuplicateGroup {
+                primary: inputs[0].clone(),
+                duplicates: vec![inputs[2].clone(), inputs[4].clone()],
+            }]
+        );
+        assert_eq!(plan.to_transcribe().count(), 3);
+        assert_eq!(plan.to_write().count(), 5);
+        assert_eq!(plan.files[2].action, PlannedAction::Duplicate);
+        assert_eq!(plan.files[2].duplicate_of.as_ref(), Some(&inputs[0]));
+        assert_eq!(plan.duplicate_groups(), groups);
+        assert_eq!(
+            groups[0].to_string(),
+            format!(
+                "{} = {}, {}",
+                inputs[0].display(),
+                inputs[2].display(),
+                inputs[4].display()
+            )
+        );
+
+        // A different forced language makes the same audio a separate job
+        let mut plan = plan_batch(inputs[..3].to_vec(), &options);
+        plan.files[2].language = Some("fr".to_string());
+        assert!(plan.dedupe().is_empty());
+    }
+}
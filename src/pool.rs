@@ -0,0 +1,406 @@
+use crate::error::{Result, TranscriptionError};
+use crate::timing::PhaseTimings;
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use tracing::{debug, info, warn};
+
+/// Hidden flag that turns the CLI into a worker reading requests from stdin
+pub const WORKER_FLAG: &str = "--worker";
+
+/// Attempts per file: the first worker, then one retry on another after a crash
+const ATTEMPTS: usize = 2;
+
+/// One file for a worker, sent as a line of JSON on its stdin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerRequest {
+    pub input: PathBuf,
+    /// Where the worker writes the transcript; without one it only returns the result
+    pub output: Option<PathBuf>,
+    pub language: Option<String>,
+}
+
+/// A worker's answer to one request, sent as a line of JSON on its stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerReply {
+    Done {
+        result: Box<TranscriptionResult>,
+        /// Sent separately, since results leave their timings out when serialized
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timings: Option<PhaseTimings>,
+    },
+    Failed {
+        kind: String,
+        message: String,
+    },
+}
+
+impl WorkerReply {
+    pub fn from_result(result: &Result<TranscriptionResult>) -> Self {
+        match result {
+            Ok(result) => WorkerReply::Done {
+                result: Box::new(result.clone()),
+                timings: result.timings,
+            },
+            Err(e) => WorkerReply::Failed {
+                kind: e.kind().to_string(),
+                message: e.inner().to_string(),
+            },
+        }
+    }
+
+    /// The result, or the worker's error as `TranscriptionError::Worker` with its kind
+    pub fn into_result(self) -> Result<TranscriptionResult> {
+        match self {
+            WorkerReply::Done {
+                mut result,
+                timings,
+            } => {
+                result.timings = timings;
+                Ok(*result)
+            }
+            WorkerReply::Failed { kind, message } => {
+                Err(TranscriptionError::Worker { kind, message })
+            }
+        }
+    }
+}
+
+/// A running worker process
+struct Worker {
+    id: usize,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Worker {
+    /// Send `request` and wait for the reply. `Err` means the worker died or broke the
+    /// protocol, not that the file failed.
+    fn run(&mut self, request: &WorkerRequest) -> std::result::Result<WorkerReply, String> {
+        let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+        line.push('\n');
+        let stdin = self.stdin.as_mut().ok_or("stdin is closed")?;
+        stdin
+            .write_all(line.as_bytes())
+            .and_then(|()| stdin.flush())
+            .map_err(|e| format!("cannot send the request: {}", e))?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|e| format!("cannot read the reply: {}", e))?;
+            if read == 0 {
+                return Err(match self.child.wait() {
+                    Ok(status) => format!("exited with {}", status),
+                    Err(e) => format!("closed its output: {}", e),
+                });
+            }
+            // Anything else on stdout, such as a library printing, isn't a reply
+            match serde_json::from_str(line.trim_end()) {
+                Ok(reply) => return Ok(reply),
+                Err(_) => debug!("Worker {}: {}", self.id, line.trim_end()),
+            }
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+impl Drop for Worker {
+    /// Closing stdin tells the worker to exit
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+/// Worker processes that each own an interpreter and a model, so files transcribe in
+/// parallel instead of queueing on one model and the GIL.
+///
+/// Workers are started on demand, one per concurrent caller up to `max_workers`, and reused
+/// afterwards. A worker that crashes is discarded and its file retried once on another.
+pub struct WorkerPool {
+    program: PathBuf,
+    args: Vec<OsString>,
+    workers: Mutex<Workers>,
+    returned: Condvar,
+    max_workers: Option<usize>,
+    started: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Workers {
+    idle: Vec<Worker>,
+    /// Running, whether idle or busy
+    live: usize,
+}
+
+impl WorkerPool {
+    /// Workers run `program args...`; the caller includes `WORKER_FLAG` in `args`
+    pub fn new<P: Into<PathBuf>>(program: P, args: Vec<OsString>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            workers: Mutex::new(Workers::default()),
+            returned: Condvar::new(),
+            max_workers: None,
+            started: AtomicUsize::new(0),
+        }
+    }
+
+    /// Run at most `max_workers` workers, each with a model of its own, however many callers
+    /// there are; the others wait for a worker to finish. See
+    /// `ModelPoolLimits::max_copies`.
+    pub fn with_max_workers(mut self, max_workers: Option<usize>) -> Self {
+        self.max_workers = max_workers;
+        self
+    }
+
+    /// Workers started so far, counting any that crashed
+    pub fn started(&self) -> usize {
+        self.started.load(Ordering::SeqCst)
+    }
+
+    fn spawn(&self) -> Result<Worker> {
+        let id = self.started.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        // Out of the terminal's process group, so Ctrl-C reaches only the parent, which
+        // lets the files under way finish
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn().map_err(|e| {
+            TranscriptionError::WorkerCrashed(format!(
+                "cannot start {}: {}",
+                self.program.display(),
+                e
+            ))
+        })?;
+        info!("Started worker {} (pid {})", id, child.id());
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Worker {
+            id,
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    fn take(&self) -> Result<Worker> {
+        let mut workers = self.lock();
+        loop {
+            if let Some(worker) = workers.idle.pop() {
+                return Ok(worker);
+            }
+            if self.max_workers.is_none_or(|max| workers.live < max) {
+                break;
+            }
+            workers = self
+                .returned
+                .wait(workers)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        workers.live += 1;
+        drop(workers);
+        self.spawn().inspect_err(|_| self.discard())
+    }
+
+    /// Take back a worker that finished a request
+    fn give_back(&self, worker: Worker) {
+        self.lock().idle.push(worker);
+        self.returned.notify_one();
+    }
+
+    /// Free the place of a worker that died or never started
+    fn discard(&self) {
+        self.lock().live -= 1;
+        self.returned.notify_one();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Workers> {
+        self.workers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Transcribe one file on an idle worker, starting one if none is free
+    pub fn transcribe(&self, request: &WorkerRequest) -> Result<TranscriptionResult> {
+        let mut crashes = Vec::new();
+        for attempt in 1..=ATTEMPTS {
+            let mut worker = self.take()?;
+            match worker.run(request) {
+                Ok(reply) => {
+                    self.give_back(worker);
+                    return reply.into_result();
+                }
+                Err(detail) => {
+                    worker.kill();
+                    self.discard();
+                    warn!(
+                        "Worker {} failed on {}: {}{}",
+                        worker.id,
+                        request.input.display(),
+                        detail,
+                        if attempt < ATTEMPTS {
+                            "; retrying on another worker"
+                        } else {
+                            ""
+                        }
+                    );
+                    crashes.push(format!("worker {} {}", worker.id, detail));
+                }
+            }
+        }
+        Err(TranscriptionError::WorkerCrashed(crashes.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn result() -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration: 10.0,
+            segments: vec![],
+            full_text: "hello".to_string(),
+            transcription_time: 1.0,
+            real_time_factor: 10.0,
+            ..Default::default()
+        }
+    }
+
+    /// A pool whose workers run `script` under `sh`
+    fn shell_pool(script: &str) -> WorkerPool {
+        WorkerPool::new("sh", vec!["-c".into(), script.into()])
+    }
+
+    #[test]
+    fn test_replies_round_trip() {
+        let request = WorkerRequest {
+            input: "a.wav".into(),
+            output: Some("a.json".into()),
+            language: None,
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            serde_json::from_str::<WorkerRequest>(&json).unwrap(),
+            request
+        );
+
+        let error = TranscriptionError::TranscriptionFailed("decoder crashed".to_string())
+            .with_path("a.wav");
+        let reply = WorkerReply::from_result(&Err(error));
+        let json = serde_json::to_string(&reply).unwrap();
+        assert!(json.starts_with("{\"failed\":"), "{}", json);
+        let error = serde_json::from_str::<WorkerReply>(&json)
+            .unwrap()
+            .into_result()
+            .unwrap_err();
+        // The path stays with the parent, which knows which file it sent
+        assert_eq!(error.kind(), "transcription_failed");
+        assert_eq!(error.to_string(), "Transcription failed: decoder crashed");
+
+        let timings = PhaseTimings {
+            inference: 2.5,
+            ..Default::default()
+        };
+        let reply = WorkerReply::from_result(&Ok(TranscriptionResult {
+            timings: Some(timings),
+            ..result()
+        }));
+        let json = serde_json::to_string(&reply).unwrap();
+        let back = serde_json::from_str::<WorkerReply>(&json)
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(back.full_text, "hello");
+        assert_eq!(back.timings, Some(timings));
+    }
+
+    #[test]
+    fn test_workers_are_reused_and_crashes_retried_once() {
+        let dir = tempdir().unwrap();
+        let reply = dir.path().join("reply.json");
+        let done = WorkerReply::from_result(&Ok(result()));
+        std::fs::write(&reply, serde_json::to_string(&done).unwrap() + "\n").unwrap();
+
+        // Noise on stdout is skipped; one worker answers every request
+        let pool = shell_pool(&format!(
+            "while read line; do echo progress; cat '{}'; done",
+            reply.display()
+        ));
+        let request = WorkerRequest {
+            input: "a.wav".into(),
+            output: None,
+            language: None,
+        };
+        for _ in 0..3 {
+            assert_eq!(pool.transcribe(&request).unwrap().full_text, "hello");
+        }
+        assert_eq!(pool.started(), 1);
+
+        // The first worker dies on its request; the retry lands on a fresh one
+        let marker = dir.path().join("crashed");
+        let pool = shell_pool(&format!(
+            "read line; if [ ! -e '{marker}' ]; then touch '{marker}'; exit 3; fi; cat '{reply}'",
+            marker = marker.display(),
+            reply = reply.display()
+        ));
+        assert_eq!(pool.transcribe(&request).unwrap().full_text, "hello");
+        assert_eq!(pool.started(), 2);
+
+        // Crashing twice fails the file
+        let pool = shell_pool("read line; exit 3");
+        let error = pool.transcribe(&request).unwrap_err();
+        assert_eq!(error.kind(), "worker_crashed");
+        assert_eq!(pool.started(), 2);
+        assert!(error.to_string().contains("exit status: 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_max_workers_makes_callers_wait_for_a_worker() {
+        let dir = tempdir().unwrap();
+        let reply = dir.path().join("reply.json");
+        let done = WorkerReply::from_result(&Ok(result()));
+        std::fs::write(&reply, serde_json::to_string(&done).unwrap() + "\n").unwrap();
+        let pool = shell_pool(&format!(
+            "while read line; do sleep 0.05; cat '{}'; done",
+            reply.display()
+        ))
+        .with_max_workers(Some(2));
+        let request = WorkerRequest {
+            input: "a.wav".into(),
+            output: None,
+            language: None,
+        };
+
+        std::thread::scope(|scope| {
+            let calls: Vec<_> = (0..6)
+                .map(|_| scope.spawn(|| pool.transcribe(&request)))
+                .collect();
+            for call in calls {
+                assert_eq!(call.join().unwrap().unwrap().full_text, "hello");
+            }
+        });
+        assert_eq!(pool.started(), 2);
+    }
+}
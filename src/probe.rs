@@ -0,0 +1,385 @@
+use crate::error::{DecodeFailure, Result, TranscriptionError};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How much of a file is read to recognise its format
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+/// Basic properties of an audio file that can be read without decoding it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioProbe {
+    pub duration: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Cheaply probe an audio file's duration from its container header.
+///
+/// Only RIFF/WAVE is understood for now; other formats return `None` so callers can fall back
+/// to decoding.
+pub fn probe_audio<P: AsRef<Path>>(path: P) -> Option<AudioProbe> {
+    let mut file = File::open(path).ok()?;
+    probe_wav(&mut file)
+}
+
+pub fn probe_duration<P: AsRef<Path>>(path: P) -> Option<f64> {
+    probe_audio(path).map(|probe| probe.duration)
+}
+
+fn probe_wav<R: Read + Seek>(reader: &mut R) -> Option<AudioProbe> {
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut format: Option<(u16, u32, u32)> = None;
+    loop {
+        let mut chunk = [0u8; 8];
+        reader.read_exact(&mut chunk).ok()?;
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+
+        match &chunk[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                reader.read_exact(&mut fmt).ok()?;
+                let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                let byte_rate = u32::from_le_bytes([fmt[8], fmt[9], fmt[10], fmt[11]]);
+                format = Some((channels, sample_rate, byte_rate));
+                skip_chunk(reader, size.checked_sub(16)?)?;
+            }
+            b"data" => {
+                let (channels, sample_rate, byte_rate) = format?;
+                if byte_rate == 0 {
+                    return None;
+                }
+                return Some(AudioProbe {
+                    duration: size as f64 / byte_rate as f64,
+                    sample_rate,
+                    channels,
+                });
+            }
+            _ => skip_chunk(reader, size)?,
+        }
+    }
+}
+
+/// Chunks are padded to an even number of bytes
+fn skip_chunk<R: Seek>(reader: &mut R, size: u32) -> Option<()> {
+    let padded = size as i64 + (size % 2) as i64;
+    reader.seek(SeekFrom::Current(padded)).ok()?;
+    Some(())
+}
+
+/// Audio container formats recognisable from their first bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Wav,
+    Mp3,
+    Flac,
+    /// MPEG-4/QuickTime: m4a, mp4, mov
+    Mp4,
+    Ogg,
+    /// Matroska and WebM
+    Matroska,
+    Aiff,
+    /// Raw AAC in ADTS frames
+    Aac,
+}
+
+impl Container {
+    /// The format a file with extension `ext` claims to be
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        Some(match ext.to_lowercase().as_str() {
+            "wav" | "wave" => Container::Wav,
+            "mp3" => Container::Mp3,
+            "flac" => Container::Flac,
+            "m4a" | "m4b" | "mp4" | "mov" => Container::Mp4,
+            "ogg" | "oga" | "opus" => Container::Ogg,
+            "webm" | "mkv" | "mka" => Container::Matroska,
+            "aif" | "aiff" => Container::Aiff,
+            "aac" => Container::Aac,
+            _ => return None,
+        })
+    }
+
+    /// The format `header` starts like, if any
+    pub fn sniff(header: &[u8]) -> Option<Self> {
+        let at =
+            |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+        if [b"RIFF", b"RIFX", b"RF64", b"BW64"]
+            .iter()
+            .any(|magic| at(0, *magic))
+            && at(8, b"WAVE")
+        {
+            return Some(Container::Wav);
+        }
+        if at(0, b"ID3") {
+            // An ID3v2 tag, usually before MP3 but sometimes before FLAC; its size is syncsafe
+            let size = header
+                .get(6..10)?
+                .iter()
+                .fold(0usize, |size, &byte| (size << 7) | (byte & 0x7f) as usize);
+            return match header.get(10 + size..) {
+                Some(rest) if rest.starts_with(b"fLaC") => Some(Container::Flac),
+                _ => Some(Container::Mp3),
+            };
+        }
+        if at(0, b"fLaC") {
+            return Some(Container::Flac);
+        }
+        if at(0, b"OggS") {
+            return Some(Container::Ogg);
+        }
+        if at(0, &[0x1a, 0x45, 0xdf, 0xa3]) {
+            return Some(Container::Matroska);
+        }
+        if [b"ftyp", b"moov", b"mdat", b"free", b"wide", b"skip"]
+            .iter()
+            .any(|atom| at(4, *atom))
+        {
+            return Some(Container::Mp4);
+        }
+        if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+            return Some(Container::Aiff);
+        }
+        match header {
+            [0xff, second, ..] if second & 0xf6 == 0xf0 => Some(Container::Aac),
+            // MPEG audio frame sync, layer III
+            [0xff, second, ..] if second & 0xe6 == 0xe2 => Some(Container::Mp3),
+            _ => None,
+        }
+    }
+
+    /// Leading bytes every file of this format has, for formats that have them. MP3 and AAC
+    /// streams may start with junk before the first frame, so they're left to the decoder.
+    fn magic(&self) -> Option<&'static [u8]> {
+        match self {
+            Container::Wav => Some(b"RIFF"),
+            Container::Flac => Some(b"fLaC"),
+            Container::Ogg => Some(b"OggS"),
+            Container::Matroska => Some(&[0x1a, 0x45, 0xdf, 0xa3]),
+            Container::Aiff => Some(b"FORM"),
+            Container::Mp4 => Some(b"\0\0\0"),
+            Container::Mp3 | Container::Aac => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Container::Wav => "WAV",
+            Container::Mp3 => "MP3",
+            Container::Flac => "FLAC",
+            Container::Mp4 => "MPEG-4",
+            Container::Ogg => "Ogg",
+            Container::Matroska => "Matroska/WebM",
+            Container::Aiff => "AIFF",
+            Container::Aac => "AAC",
+        }
+    }
+}
+
+/// Reject files that can't be audio before handing them to a decoder: empty files, text, and
+/// files missing the header their extension promises. A recognised format under the wrong
+/// extension is only logged, since FFmpeg goes by content. MPEG-4 files carrying a
+/// protection scheme are rejected as DRM-protected.
+pub fn check_audio_content(path: &Path) -> Result<()> {
+    let fail = |reason: DecodeFailure, detail: String| TranscriptionError::AudioDecodeError {
+        path: path.to_path_buf(),
+        reason,
+        detail,
+    };
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(SNIFF_BYTES)
+        .read_to_end(&mut header)?;
+    if header.is_empty() {
+        return Err(fail(
+            DecodeFailure::Empty,
+            "empty file (0 bytes)".to_string(),
+        ));
+    }
+
+    let claimed = path
+        .extension()
+        .and_then(|ext| Container::from_extension(&ext.to_string_lossy()));
+    match (Container::sniff(&header), claimed) {
+        (Some(Container::Mp4), _) if contains(&header, b"sinf") => Err(fail(
+            DecodeFailure::Protected,
+            "DRM-protected (the MPEG-4 file declares a protection scheme)".to_string(),
+        )),
+        (Some(found), Some(claimed)) if found != claimed => {
+            tracing::warn!(
+                "{} holds {} audio despite its extension; decoding it anyway",
+                path.display(),
+                found.name()
+            );
+            Ok(())
+        }
+        (Some(_), _) => Ok(()),
+        (None, Some(claimed))
+            if claimed
+                .magic()
+                .is_some_and(|magic| magic.starts_with(&header)) =>
+        {
+            Err(fail(
+                DecodeFailure::Truncated,
+                format!("truncated (only {} bytes)", header.len()),
+            ))
+        }
+        (None, _) if looks_like_text(&header) => Err(fail(
+            DecodeFailure::NotAudio,
+            "not actually audio (the file is text)".to_string(),
+        )),
+        (None, Some(claimed)) if claimed.magic().is_some() => Err(fail(
+            DecodeFailure::NotAudio,
+            format!(
+                "not actually audio (no {} header, nor any other format's)",
+                claimed.name()
+            ),
+        )),
+        (None, _) => Ok(()),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// No control characters besides whitespace, and valid UTF-8 apart from a character cut off
+/// at the end of the sample
+fn looks_like_text(bytes: &[u8]) -> bool {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    valid
+        && bytes
+            .iter()
+            .all(|&byte| byte >= 0x20 || matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c))
+}
+
+#[cfg(test)]
+pub(crate) fn wav_bytes(sample_rate: u32, channels: u16, samples_per_channel: u32) -> Vec<u8> {
+    let bits_per_sample = 16u16;
+    let block_align = channels * bits_per_sample / 8;
+    let byte_rate = sample_rate * block_align as u32;
+    let data_len = samples_per_channel * block_align as u32;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&byte_rate.to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&bits_per_sample.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    bytes.resize(bytes.len() + data_len as usize, 0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_probe_wav_duration() {
+        let bytes = wav_bytes(16000, 1, 32000);
+        let probe = probe_wav(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(probe.duration, 2.0);
+        assert_eq!(probe.sample_rate, 16000);
+        assert_eq!(probe.channels, 1);
+
+        let stereo = wav_bytes(44100, 2, 44100);
+        assert_eq!(probe_wav(&mut Cursor::new(stereo)).unwrap().duration, 1.0);
+    }
+
+    #[test]
+    fn test_probe_skips_unknown_chunks() {
+        let mut bytes = wav_bytes(8000, 1, 4000);
+        // Insert an odd-sized LIST chunk (with padding byte) before "fmt "
+        let list: Vec<u8> = [b"LIST".as_slice(), &3u32.to_le_bytes(), b"abc\0"].concat();
+        bytes.splice(12..12, list);
+        assert_eq!(probe_wav(&mut Cursor::new(bytes)).unwrap().duration, 0.5);
+    }
+
+    #[test]
+    fn test_sniff_containers() {
+        assert_eq!(
+            Container::sniff(&wav_bytes(16000, 1, 10)),
+            Some(Container::Wav)
+        );
+        assert_eq!(
+            Container::sniff(b"ID3\x03\0\0\0\0\0\0\xff\xfb"),
+            Some(Container::Mp3)
+        );
+        assert_eq!(
+            Container::sniff(b"ID3\x04\0\0\0\0\0\x02\0\0fLaC"),
+            Some(Container::Flac)
+        );
+        assert_eq!(Container::sniff(b"\xff\xfb\x90\x64"), Some(Container::Mp3));
+        assert_eq!(Container::sniff(b"\xff\xf1\x50\x80"), Some(Container::Aac));
+        assert_eq!(
+            Container::sniff(b"\0\0\0\x20ftypM4A "),
+            Some(Container::Mp4)
+        );
+        assert_eq!(
+            Container::sniff(b"\x1a\x45\xdf\xa3\x01"),
+            Some(Container::Matroska)
+        );
+        assert_eq!(Container::sniff(b"Hello, world"), None);
+        assert_eq!(Container::from_extension("M4A"), Some(Container::Mp4));
+    }
+
+    #[test]
+    fn test_check_audio_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = |name: &str, bytes: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            match check_audio_content(&path) {
+                Ok(()) => None,
+                Err(TranscriptionError::AudioDecodeError { reason, .. }) => Some(reason),
+                Err(e) => panic!("{}: {}", name, e),
+            }
+        };
+
+        assert_eq!(check("ok.wav", &wav_bytes(16000, 1, 100)), None);
+        // FFmpeg reads by content, so a mislabelled format is let through
+        assert_eq!(check("mislabelled.mp3", &wav_bytes(16000, 1, 100)), None);
+        assert_eq!(check("empty.wav", b""), Some(DecodeFailure::Empty));
+        assert_eq!(
+            check("notes.wav", b"Meeting notes\nThursday\n"),
+            Some(DecodeFailure::NotAudio)
+        );
+        assert_eq!(check("cut.wav", b"RIF"), Some(DecodeFailure::Truncated));
+        assert_eq!(
+            check("random.flac", &[0x42; 64]),
+            Some(DecodeFailure::NotAudio)
+        );
+        // No fixed header to insist on for MP3
+        assert_eq!(check("junk.mp3", &[0x00, 0x42, 0x99, 0x13]), None);
+        let protected = [b"\0\0\0\x20ftypM4P ".as_slice(), &[0; 32], b"sinf"].concat();
+        assert_eq!(
+            check("song.m4a", &protected),
+            Some(DecodeFailure::Protected)
+        );
+    }
+
+    #[test]
+    fn test_probe_rejects_non_wav() {
+        assert!(probe_wav(&mut Cursor::new(b"ID3\x03 not a wav file".to_vec())).is_none());
+        assert!(probe_wav(&mut Cursor::new(Vec::new())).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::time::Duration;
+
+/// How far a transcription has got, estimated from the timestamps of decoded segments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Share of the audio decoded, from 0 to 1
+    pub fraction: f64,
+    /// Seconds of audio decoded
+    pub position: f64,
+    /// Seconds of audio in total
+    pub duration: f64,
+    /// Time left at the rate so far; `None` until any audio is decoded
+    pub eta: Option<Duration>,
+}
+
+/// Progress after decoding up to `latest_end` seconds of `duration` in `elapsed`.
+///
+/// The rest is assumed to decode at the same rate, so VAD-skipped silence makes this jump
+/// ahead; `ProgressEstimator` keeps it from going back.
+pub fn estimate(latest_end: f64, duration: f64, elapsed: Duration) -> Progress {
+    if !(duration > 0.0 && duration.is_finite()) {
+        return Progress {
+            fraction: 0.0,
+            position: 0.0,
+            duration: 0.0,
+            eta: None,
+        };
+    }
+    let position = if latest_end.is_finite() {
+        latest_end.clamp(0.0, duration)
+    } else {
+        0.0
+    };
+    let fraction = position / duration;
+    let eta = (fraction > 0.0).then(|| elapsed.mul_f64((1.0 - fraction) / fraction));
+    Progress {
+        fraction,
+        position,
+        duration,
+        eta,
+    }
+}
+
+/// Smooths `estimate` over a transcription: segments can end out of order, so progress only
+/// ever moves forward
+#[derive(Debug, Clone, Default)]
+pub struct ProgressEstimator {
+    furthest: f64,
+}
+
+impl ProgressEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, latest_end: f64, duration: f64, elapsed: Duration) -> Progress {
+        if latest_end > self.furthest {
+            self.furthest = latest_end;
+        }
+        estimate(self.furthest, duration, elapsed)
+    }
+}
+
+/// `01:02:03`
+pub(crate) fn clock(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// `45s`, `14m` or `2h 05m`, rounded to what's worth reading
+fn rough_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 3600 {
+        format!("{}m", (seconds + 30) / 60)
+    } else {
+        let minutes = (seconds + 30) / 60;
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+impl fmt::Display for Progress {
+    /// `~42% (00:51:23 / 02:02:10), ETA 14m`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "~{:.0}% ({} / {})",
+            (self.fraction * 100.0).floor(),
+            clock(self.position),
+            clock(self.duration)
+        )?;
+        if let Some(eta) = self.eta {
+            write!(f, ", ETA {}", rough_duration(eta))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let progress = estimate(3083.0, 7330.0, Duration::from_secs(600));
+        assert!((progress.fraction - 0.4206).abs() < 1e-3);
+        // 600s for 42% leaves about 826s
+        assert_eq!(progress.eta.unwrap().as_secs(), 826);
+        assert_eq!(progress.to_string(), "~42% (00:51:23 / 02:02:10), ETA 14m");
+
+        let start = estimate(0.0, 7330.0, Duration::from_secs(5));
+        assert_eq!(start.eta, None);
+        assert_eq!(start.to_string(), "~0% (00:00:00 / 02:02:10)");
+
+        // Timestamps past the end and unknown durations don't overshoot or divide by zero
+        let done = estimate(7400.0, 7330.0, Duration::from_secs(900));
+        assert_eq!(done.fraction, 1.0);
+        assert_eq!(done.eta, Some(Duration::ZERO));
+        assert_eq!(estimate(10.0, 0.0, Duration::from_secs(1)).fraction, 0.0);
+        assert_eq!(estimate(f64::NAN, 60.0, Duration::from_secs(1)).eta, None);
+
+        assert_eq!(rough_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(rough_duration(Duration::from_secs(7530)), "2h 06m");
+    }
+
+    #[test]
+    fn test_estimator_is_monotonic() {
+        let mut estimator = ProgressEstimator::new();
+        let elapsed = Duration::from_secs(10);
+        assert_eq!(estimator.update(30.0, 100.0, elapsed).position, 30.0);
+        // A segment that ends earlier than one already seen doesn't move progress back
+        assert_eq!(estimator.update(20.0, 100.0, elapsed).position, 30.0);
+        assert_eq!(estimator.update(60.0, 100.0, elapsed).fraction, 0.6);
+    }
+}
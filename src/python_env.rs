@@ -0,0 +1,334 @@
+use crate::error::{classify_python_error, PythonFailure, Result, TranscriptionError};
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::info;
+
+static ACTIVE_VENV: OnceLock<PythonEnv> = OnceLock::new();
+
+/// A virtualenv (or conda env) whose packages the embedded interpreter should import.
+///
+/// PyO3 binds the interpreter found at build time, which is often the system Python. Activating
+/// a venv puts its site-packages first on `sys.path` so faster-whisper installed there is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonEnv {
+    root: PathBuf,
+    site_packages: PathBuf,
+    /// `X.Y` from `lib/pythonX.Y`, when the layout names it
+    python_version: Option<String>,
+}
+
+impl PythonEnv {
+    /// Locate the site-packages directory of the venv at `path`
+    pub fn from_venv<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let root = path.as_ref();
+        if !root.is_dir() {
+            return Err(TranscriptionError::ConfigError(format!(
+                "Virtualenv not found: {}",
+                root.display()
+            )));
+        }
+        let (site_packages, python_version) = find_site_packages(root).ok_or_else(|| {
+            TranscriptionError::ConfigError(format!(
+                "No site-packages found in virtualenv {} (expected lib/pythonX.Y/site-packages)",
+                root.display()
+            ))
+        })?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            site_packages,
+            python_version,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn site_packages(&self) -> &Path {
+        &self.site_packages
+    }
+
+    /// Point the embedded interpreter at this venv. Must run before anything imports
+    /// faster_whisper; only the first activation in a process takes effect.
+    pub fn activate(&self) -> Result<()> {
+        if let Some(active) = ACTIVE_VENV.get() {
+            if active != self {
+                return Err(TranscriptionError::ConfigError(format!(
+                    "Virtualenv {} is already active; cannot switch to {}",
+                    active.root.display(),
+                    self.root.display()
+                )));
+            }
+            return Ok(());
+        }
+
+        Python::with_gil(|py| -> Result<()> {
+            let interpreter = interpreter_version(py)?;
+            check_version(self, &interpreter)?;
+
+            prepend_site_dir(py, &self.site_packages)?;
+            let sys = py.import("sys")?;
+            sys.setattr("prefix", &self.root)?;
+            sys.setattr("exec_prefix", &self.root)?;
+            py.import("importlib")?.call_method0("invalidate_caches")?;
+            Ok(())
+        })?;
+
+        info!(
+            "Using Python packages from {}",
+            self.site_packages.display()
+        );
+        let _ = ACTIVE_VENV.set(self.clone());
+        Ok(())
+    }
+}
+
+/// The venv activated in this process, if any
+pub fn active_venv() -> Option<&'static PythonEnv> {
+    ACTIVE_VENV.get()
+}
+
+/// Import faster_whisper, naming the interpreter and any active venv when it is missing
+pub fn import_faster_whisper(py: Python<'_>) -> Result<Bound<'_, PyModule>> {
+    py.import("faster_whisper")
+        .map_err(|e| match classify(py, &e) {
+            PythonFailure::PackageMissing(module) => {
+                let package = pip_package(&module);
+                TranscriptionError::PackageMissing {
+                    pip_hint: pip_hint(py, &package),
+                    package,
+                }
+            }
+            PythonFailure::PythonUnavailable => {
+                TranscriptionError::PythonUnavailable(e.to_string())
+            }
+            _ => {
+                let python = interpreter_version(py).unwrap_or_else(|_| "unknown".to_string());
+                let message = match active_venv() {
+                    Some(venv) => format!(
+                    "Failed to import faster_whisper from virtualenv {} (searched {}, Python {}). \
+                     Error: {}",
+                    venv.root.display(),
+                    venv.site_packages.display(),
+                    python,
+                    e
+                ),
+                    None => format!(
+                        "Failed to import faster_whisper (Python {}). Error: {}",
+                        python, e
+                    ),
+                };
+                TranscriptionError::ModelInitError(message)
+            }
+        })
+}
+
+/// Import an optional dependency such as `pyannote.audio`, reporting a missing package with
+/// the pip command that installs it
+pub fn import_package<'py>(py: Python<'py>, module: &str) -> Result<Bound<'py, PyModule>> {
+    py.import(module).map_err(|e| match classify(py, &e) {
+        PythonFailure::PackageMissing(missing) => {
+            let package = pip_package(&missing);
+            TranscriptionError::PackageMissing {
+                pip_hint: pip_hint(py, &package),
+                package,
+            }
+        }
+        PythonFailure::PythonUnavailable => TranscriptionError::PythonUnavailable(e.to_string()),
+        _ => TranscriptionError::ModelInitError(format!("Failed to import {}: {}", module, e)),
+    })
+}
+
+/// Classify an exception raised by Python code by its type and message
+pub fn classify(py: Python<'_>, err: &PyErr) -> PythonFailure {
+    let exception = err
+        .get_type(py)
+        .fully_qualified_name()
+        .map(|name| name.to_string())
+        .unwrap_or_default();
+    classify_python_error(&exception, &err.value(py).to_string())
+}
+
+/// The distribution pip knows a top-level module by
+fn pip_package(module: &str) -> String {
+    match module {
+        "yaml" => "pyyaml".to_string(),
+        "pyannote" => "pyannote.audio".to_string(),
+        other => other.replace('_', "-"),
+    }
+}
+
+/// How to install `package` for the interpreter in use: the active venv's pip, or plain pip
+/// naming the embedded Python version
+fn pip_hint(py: Python<'_>, package: &str) -> String {
+    match active_venv() {
+        Some(venv) => format!(
+            "{} install {}",
+            venv.root.join("bin").join("pip").display(),
+            package
+        ),
+        None => format!(
+            "pip install {} (for Python {}), or pass --python-venv",
+            package,
+            interpreter_version(py).unwrap_or_else(|_| "unknown".to_string())
+        ),
+    }
+}
+
+/// `lib/pythonX.Y/site-packages` (Unix, picking the newest if several) or `Lib/site-packages`
+/// (Windows)
+fn find_site_packages(root: &Path) -> Option<(PathBuf, Option<String>)> {
+    let mut candidates: Vec<(Vec<u32>, PathBuf, String)> = std::fs::read_dir(root.join("lib"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let version = name.strip_prefix("python")?.to_string();
+            let key = version
+                .split('.')
+                .map(|part| part.parse().ok())
+                .collect::<Option<Vec<u32>>>()?;
+            let site_packages = entry.path().join("site-packages");
+            site_packages
+                .is_dir()
+                .then_some((key, site_packages, version))
+        })
+        .collect();
+    candidates.sort();
+    if let Some((_, site_packages, version)) = candidates.pop() {
+        return Some((site_packages, Some(version)));
+    }
+
+    let windows = root.join("Lib").join("site-packages");
+    windows.is_dir().then_some((windows, None))
+}
+
+/// `X.Y` of the embedded interpreter
+fn interpreter_version(py: Python<'_>) -> PyResult<String> {
+    let version_info = py.import("sys")?.getattr("version_info")?;
+    let major: u32 = version_info.getattr("major")?.extract()?;
+    let minor: u32 = version_info.getattr("minor")?.extract()?;
+    Ok(format!("{}.{}", major, minor))
+}
+
+/// Packages built for another Python version won't import, so refuse a mismatched venv
+fn check_version(venv: &PythonEnv, interpreter: &str) -> Result<()> {
+    match &venv.python_version {
+        Some(version) if version != interpreter => Err(TranscriptionError::ConfigError(format!(
+            "Virtualenv {} is for Python {}, but this binary embeds Python {}; \
+             rebuild with PYO3_PYTHON={}",
+            venv.root.display(),
+            version,
+            interpreter,
+            venv.root.join("bin").join("python").display()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Put `dir` first on `sys.path`, processing its `.pth` files like a normal site directory
+fn prepend_site_dir(py: Python<'_>, dir: &Path) -> PyResult<()> {
+    let sys_path = py
+        .import("sys")?
+        .getattr("path")?
+        .downcast_into::<PyList>()?;
+    py.import("site")?.call_method1("addsitedir", (dir,))?;
+    let dir = dir.to_string_lossy();
+    while sys_path.contains(dir.as_ref())? {
+        sys_path.call_method1("remove", (dir.as_ref(),))?;
+    }
+    sys_path.insert(0, dir.as_ref())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_site_packages() {
+        let venv = tempdir().unwrap();
+        fs::create_dir_all(venv.path().join("lib/python3.9/site-packages")).unwrap();
+        fs::create_dir_all(venv.path().join("lib/python3.11/site-packages")).unwrap();
+        fs::create_dir_all(venv.path().join("lib/python3.12")).unwrap();
+
+        let env = PythonEnv::from_venv(venv.path()).unwrap();
+        assert_eq!(
+            env.site_packages(),
+            venv.path().join("lib/python3.11/site-packages")
+        );
+        assert_eq!(env.python_version.as_deref(), Some("3.11"));
+        assert!(check_version(&env, "3.11").is_ok());
+        let err = check_version(&env, "3.12").unwrap_err();
+        assert!(err.to_string().contains("is for Python 3.11"));
+
+        let windows = tempdir().unwrap();
+        fs::create_dir_all(windows.path().join("Lib/site-packages")).unwrap();
+        let env = PythonEnv::from_venv(windows.path()).unwrap();
+        assert_eq!(env.python_version, None);
+    }
+
+    #[test]
+    fn test_missing_venv_layout() {
+        let empty = tempdir().unwrap();
+        let err = PythonEnv::from_venv(empty.path()).unwrap_err();
+        assert!(err.to_string().contains("No site-packages found"));
+        assert!(PythonEnv::from_venv(empty.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_prepend_site_dir() {
+        let site = tempdir().unwrap();
+        fs::write(
+            site.path().join("whisper_venv_probe.py"),
+            "VALUE = 'from venv'\n",
+        )
+        .unwrap();
+
+        Python::with_gil(|py| {
+            prepend_site_dir(py, site.path()).unwrap();
+            // Idempotent: still exactly one entry, at the front
+            prepend_site_dir(py, site.path()).unwrap();
+
+            let sys_path: Vec<String> = py
+                .import("sys")
+                .unwrap()
+                .getattr("path")
+                .unwrap()
+                .extract()
+                .unwrap();
+            let dir = site.path().to_string_lossy().to_string();
+            assert_eq!(sys_path[0], dir);
+            assert_eq!(sys_path.iter().filter(|p| **p == dir).count(), 1);
+
+            let value: String = py
+                .import("whisper_venv_probe")
+                .unwrap()
+                .getattr("VALUE")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(value, "from venv");
+
+            let sys_path = py.import("sys").unwrap().getattr("path").unwrap();
+            sys_path.call_method1("remove", (dir,)).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_classify_real_exception() {
+        Python::with_gil(|py| {
+            let err = py.import("no_such_whisper_module.sub").unwrap_err();
+            assert_eq!(
+                classify(py, &err),
+                PythonFailure::PackageMissing("no_such_whisper_module".to_string())
+            );
+            assert_eq!(pip_package("faster_whisper"), "faster-whisper");
+        });
+    }
+}
@@ -0,0 +1,632 @@
+use crate::backend::TranscriptionBackend;
+use crate::error::{Result, TranscriptionError};
+use crate::model_manager::{ModelKey, ModelPool, PoolStats};
+use crate::types::{ModelConfig, TranscriptionOptions, TranscriptionResult};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// How urgently a job should run; higher priorities go ahead of everything queued below them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Bulk work such as backfills
+    Low,
+    #[default]
+    Normal,
+    /// Someone is waiting on the result
+    High,
+}
+
+/// Where a job is in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    /// Cancelled, or dropped when the queue shut down without draining
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has stopped for good
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// Transcription jobs run one at a time on a worker thread that owns the backend, so its
+/// model is loaded once and shared by every caller. Queued jobs run highest priority first
+/// and in submission order within a priority; a running job is never interrupted.
+pub struct JobQueue {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+    pool: Option<Arc<ModelPool>>,
+}
+
+/// Where the worker gets each job's model
+enum Models {
+    Owned(Box<dyn TranscriptionBackend>),
+    /// From the pool: the job's own model, or `default`
+    Pooled {
+        pool: Arc<ModelPool>,
+        default: ModelConfig,
+    },
+}
+
+impl Models {
+    fn transcribe(&self, pending: &Pending) -> Result<TranscriptionResult> {
+        match self {
+            Models::Owned(backend) => {
+                if let Some(model) = &pending.model {
+                    let owned = ModelKey::of(backend.config());
+                    if ModelKey::of(model) != owned {
+                        return Err(TranscriptionError::ConfigError(format!(
+                            "This queue only runs {}",
+                            owned
+                        )));
+                    }
+                }
+                backend.transcribe_path(&pending.path, &pending.options)
+            }
+            Models::Pooled { pool, default } => pool
+                .acquire(pending.model.as_ref().unwrap_or(default))?
+                .transcribe_path(&pending.path, &pending.options),
+        }
+    }
+}
+
+/// Follows one submitted job: its status, its result, and cancelling it while queued
+pub struct JobHandle {
+    id: u64,
+    job: Arc<JobState>,
+    shared: Arc<Shared>,
+    reply: oneshot::Receiver<Result<TranscriptionResult>>,
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    /// Signalled when a job is queued or the queue is closing
+    ready: Condvar,
+}
+
+struct QueueState {
+    pending: BinaryHeap<Pending>,
+    next_id: u64,
+    closing: bool,
+}
+
+struct JobState {
+    status: Mutex<JobStatus>,
+}
+
+impl JobState {
+    fn set(&self, status: JobStatus) {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner) = status;
+    }
+
+    fn get(&self) -> JobStatus {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A queued job, ordered so the heap pops the highest priority, then the oldest
+struct Pending {
+    priority: Priority,
+    id: u64,
+    path: PathBuf,
+    /// `None` runs the queue's own model
+    model: Option<ModelConfig>,
+    options: TranscriptionOptions,
+    job: Arc<JobState>,
+    reply: oneshot::Sender<Result<TranscriptionResult>>,
+}
+
+impl Pending {
+    fn cancel(self) {
+        self.job.set(JobStatus::Cancelled);
+        let _ = self.reply.send(Err(TranscriptionError::Cancelled));
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The next job to run, waiting for one; `None` once the queue is closing and empty
+    fn next(&self) -> Option<Pending> {
+        let mut state = self.lock();
+        loop {
+            if let Some(pending) = state.pending.pop() {
+                return Some(pending);
+            }
+            if state.closing {
+                return None;
+            }
+            state = self
+                .ready
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Stop taking jobs, cancelling those still queued unless `drain`
+    fn close(&self, drain: bool) {
+        let cancelled = {
+            let mut state = self.lock();
+            state.closing = true;
+            if drain {
+                Vec::new()
+            } else {
+                std::mem::take(&mut state.pending).into_vec()
+            }
+        };
+        if !cancelled.is_empty() {
+            debug!(
+                "Job queue shutting down; cancelled {} job(s)",
+                cancelled.len()
+            );
+        }
+        cancelled.into_iter().for_each(Pending::cancel);
+        self.ready.notify_all();
+    }
+}
+
+impl JobQueue {
+    /// Start the worker thread, which owns `backend` until the queue shuts down
+    pub fn new(backend: Box<dyn TranscriptionBackend>) -> Result<Self> {
+        Self::start(Models::Owned(backend), None)
+    }
+
+    /// Start a worker thread that takes each job's model from `pool`, so jobs can name
+    /// models of their own with `submit_with_model`. The others run `default`.
+    pub fn with_pool(pool: Arc<ModelPool>, default: ModelConfig) -> Result<Self> {
+        let models = Models::Pooled {
+            pool: Arc::clone(&pool),
+            default,
+        };
+        Self::start(models, Some(pool))
+    }
+
+    fn start(models: Models, pool: Option<Arc<ModelPool>>) -> Result<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(QueueState {
+                pending: BinaryHeap::new(),
+                next_id: 0,
+                closing: false,
+            }),
+            ready: Condvar::new(),
+        });
+        let worker = std::thread::Builder::new()
+            .name("job-queue".to_string())
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || run_worker(&models, &shared)
+            })?;
+        Ok(Self {
+            shared,
+            worker: Some(worker),
+            pool,
+        })
+    }
+
+    /// Queue `path` for transcription with `options`. The handle reports its progress and
+    /// result; dropping it leaves the job queued.
+    pub fn submit(
+        &self,
+        path: impl Into<PathBuf>,
+        options: TranscriptionOptions,
+        priority: Priority,
+    ) -> JobHandle {
+        self.enqueue(path.into(), None, options, priority)
+    }
+
+    /// `submit` on `model` rather than the queue's own. Only a queue made `with_pool` can
+    /// run other models; others fail the job unless it names theirs.
+    pub fn submit_with_model(
+        &self,
+        path: impl Into<PathBuf>,
+        model: ModelConfig,
+        options: TranscriptionOptions,
+        priority: Priority,
+    ) -> JobHandle {
+        self.enqueue(path.into(), Some(model), options, priority)
+    }
+
+    fn enqueue(
+        &self,
+        path: PathBuf,
+        model: Option<ModelConfig>,
+        options: TranscriptionOptions,
+        priority: Priority,
+    ) -> JobHandle {
+        let job = Arc::new(JobState {
+            status: Mutex::new(JobStatus::Queued),
+        });
+        let (reply, receiver) = oneshot::channel();
+        let mut state = self.shared.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        let pending = Pending {
+            priority,
+            id,
+            path,
+            model,
+            options,
+            job: Arc::clone(&job),
+            reply,
+        };
+        if state.closing {
+            drop(state);
+            pending.cancel();
+        } else {
+            state.pending.push(pending);
+            drop(state);
+            self.shared.ready.notify_one();
+        }
+        JobHandle {
+            id,
+            job,
+            shared: Arc::clone(&self.shared),
+            reply: receiver,
+        }
+    }
+
+    /// Jobs waiting to run, not counting the one running
+    pub fn queued(&self) -> usize {
+        self.shared.lock().pending.len()
+    }
+
+    /// Hits and misses of the model pool, for a queue made `with_pool`
+    pub fn pool_stats(&self) -> Option<PoolStats> {
+        self.pool.as_ref().map(|pool| pool.stats())
+    }
+
+    /// Stop the queue and wait for the worker to finish. With `drain` every queued job runs
+    /// first; without it they are cancelled and only the running job finishes.
+    pub fn shutdown(mut self, drain: bool) {
+        self.stop(drain);
+    }
+
+    fn stop(&mut self, drain: bool) {
+        self.shared.close(drain);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for JobQueue {
+    /// Dropping the queue cancels whatever it hasn't started
+    fn drop(&mut self) {
+        self.stop(false);
+    }
+}
+
+fn run_worker(models: &Models, shared: &Shared) {
+    while let Some(pending) = shared.next() {
+        pending.job.set(JobStatus::Running);
+        debug!(
+            "Job {} ({:?}): transcribing {}",
+            pending.id,
+            pending.priority,
+            pending.path.display()
+        );
+        // A panicking backend fails its job rather than the worker, which every other job
+        // is waiting on
+        let transcribe = AssertUnwindSafe(|| models.transcribe(&pending));
+        let result = std::panic::catch_unwind(transcribe)
+            .unwrap_or_else(|_| {
+                Err(TranscriptionError::TranscriptionFailed(
+                    "the backend panicked".to_string(),
+                ))
+            })
+            .map_err(|e| e.with_path(&pending.path));
+        pending.job.set(if result.is_ok() {
+            JobStatus::Done
+        } else {
+            JobStatus::Failed
+        });
+        // The caller may have stopped listening
+        let _ = pending.reply.send(result);
+    }
+}
+
+impl JobHandle {
+    /// Order the job was submitted in, unique within its queue
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.job.get()
+    }
+
+    /// Take the job out of the queue, returning whether it was still waiting. A running job
+    /// can't be stopped and finishes as usual.
+    pub fn cancel(&self) -> bool {
+        let cancelled = {
+            let mut state = self.shared.lock();
+            let (cancelled, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut state.pending)
+                .into_vec()
+                .into_iter()
+                .partition(|pending| pending.id == self.id);
+            state.pending = kept.into();
+            cancelled
+        };
+        let found = !cancelled.is_empty();
+        cancelled.into_iter().for_each(Pending::cancel);
+        found
+    }
+
+    /// Wait for the job's result. A cancelled job gives `TranscriptionError::Cancelled`.
+    pub async fn result(self) -> Result<TranscriptionResult> {
+        self.reply.await.unwrap_or_else(|_| Err(worker_gone()))
+    }
+
+    /// `result` for callers outside async code; it panics inside a Tokio runtime
+    pub fn wait(self) -> Result<TranscriptionResult> {
+        self.reply
+            .blocking_recv()
+            .unwrap_or_else(|_| Err(worker_gone()))
+    }
+}
+
+/// The worker dropped a job without answering, which only a panic does
+fn worker_gone() -> TranscriptionError {
+    TranscriptionError::TranscriptionFailed("the job queue's worker stopped".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockBackend, ResultBuilder};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+    use std::sync::mpsc;
+
+    /// A backend that reports each file as it starts, then holds it until the test lets it
+    /// finish or drops the gate
+    fn gated() -> (MockBackend, mpsc::Receiver<PathBuf>, mpsc::Sender<()>) {
+        let (started_tx, started) = mpsc::channel();
+        let (gate, gate_rx) = mpsc::channel();
+        let started_tx = Mutex::new(started_tx);
+        let gate_rx = Mutex::new(gate_rx);
+        let backend = MockBackend::new()
+            .before_each(move |audio_path, _| {
+                let _ = started_tx.lock().unwrap().send(audio_path.to_path_buf());
+                // A dropped gate lets everything through
+                let _ = gate_rx.lock().unwrap().recv();
+            })
+            .responding(|audio_path| {
+                if audio_path.starts_with("bad") {
+                    return Err(TranscriptionError::TranscriptionFailed("no audio".into()));
+                }
+                Ok(ResultBuilder::new()
+                    .language_probability(1.0)
+                    .duration(1.0)
+                    .full_text(audio_path.display().to_string())
+                    .transcription_time(0.1)
+                    .build())
+            });
+        (backend, started, gate)
+    }
+
+    fn submit(queue: &JobQueue, name: &str, priority: Priority) -> JobHandle {
+        queue.submit(name, TranscriptionOptions::default(), priority)
+    }
+
+    #[test]
+    fn test_priorities_order_the_queue_but_not_the_running_job() {
+        let (backend, started, gate) = gated();
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let first = submit(&queue, "first", Priority::Low);
+        assert_eq!(started.recv().unwrap(), PathBuf::from("first"));
+        assert_eq!(first.status(), JobStatus::Running);
+
+        let backfill = submit(&queue, "backfill", Priority::Low);
+        let normal_a = submit(&queue, "normal-a", Priority::Normal);
+        let urgent = submit(&queue, "urgent", Priority::High);
+        let normal_b = submit(&queue, "normal-b", Priority::Normal);
+        let bad = submit(&queue, "bad", Priority::Normal);
+        assert_eq!(queue.queued(), 5);
+        assert_eq!(urgent.status(), JobStatus::Queued);
+
+        drop(gate);
+        let order: Vec<_> = started.iter().take(5).collect();
+        assert_eq!(
+            order,
+            ["urgent", "normal-a", "normal-b", "bad", "backfill"].map(PathBuf::from)
+        );
+        assert_eq!(first.wait().unwrap().full_text, "first");
+        assert_eq!(backfill.wait().unwrap().full_text, "backfill");
+        assert!(normal_a.wait().is_ok() && normal_b.wait().is_ok() && urgent.wait().is_ok());
+        assert_eq!(bad.status(), JobStatus::Failed);
+        let err = bad.wait().unwrap_err();
+        assert_eq!(err.kind(), "transcription_failed");
+        assert_eq!(err.path(), Some(Path::new("bad")));
+        queue.shutdown(true);
+    }
+
+    #[test]
+    fn test_cancel_only_stops_queued_jobs() {
+        let (backend, started, gate) = gated();
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let running = submit(&queue, "running", Priority::Normal);
+        started.recv().unwrap();
+        let queued = submit(&queue, "queued", Priority::High);
+        let kept = submit(&queue, "kept", Priority::Normal);
+
+        assert!(!running.cancel());
+        assert!(queued.cancel());
+        assert!(!queued.cancel());
+        assert_eq!(queued.status(), JobStatus::Cancelled);
+        assert_eq!(queue.queued(), 1);
+
+        drop(gate);
+        assert_eq!(queued.wait().unwrap_err().kind(), "cancelled");
+        assert!(running.wait().is_ok());
+        assert_eq!(kept.wait().unwrap().full_text, "kept");
+        queue.shutdown(true);
+        // The cancelled job never reached the backend
+        assert_eq!(started.iter().collect::<Vec<_>>(), [PathBuf::from("kept")]);
+    }
+
+    #[test]
+    fn test_shutdown_drains_or_aborts() {
+        let (backend, _started, gate) = gated();
+        drop(gate);
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let handles: Vec<_> = (0..3)
+            .map(|i| submit(&queue, &format!("{}.wav", i), Priority::Low))
+            .collect();
+        queue.shutdown(true);
+        for handle in handles {
+            assert_eq!(handle.status(), JobStatus::Done);
+            assert!(handle.wait().is_ok());
+        }
+
+        let (backend, started, gate) = gated();
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let running = submit(&queue, "running", Priority::Normal);
+        started.recv().unwrap();
+        let waiting = submit(&queue, "waiting", Priority::High);
+        let closer = std::thread::spawn(move || queue.shutdown(false));
+        // Aborting answers the queued job at once, while the running one still holds
+        assert_eq!(waiting.wait().unwrap_err().kind(), "cancelled");
+        assert_eq!(running.status(), JobStatus::Running);
+        gate.send(()).unwrap();
+        closer.join().unwrap();
+        assert_eq!(running.status(), JobStatus::Done);
+        assert!(JobStatus::Done.is_finished() && !JobStatus::Running.is_finished());
+    }
+
+    #[test]
+    fn test_pooled_queue_loads_each_model_once() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let pool = ModelPool::default().with_loader({
+            let loads = Arc::clone(&loads);
+            move |config| {
+                loads.fetch_add(1, AtomicOrdering::SeqCst);
+                let (backend, _started, gate) = gated();
+                drop(gate);
+                Ok(Box::new(backend.with_config(config.clone())))
+            }
+        });
+        let small = ModelConfig::new("small", "cpu", "int8");
+        let queue = JobQueue::with_pool(Arc::new(pool), small.clone()).unwrap();
+        let beam = |beam_size| TranscriptionOptions {
+            beam_size: Some(beam_size),
+            ..TranscriptionOptions::default()
+        };
+        let handles = [
+            queue.submit("a.wav", beam(1), Priority::Normal),
+            queue.submit_with_model("b.wav", small, beam(5), Priority::Normal),
+            queue.submit_with_model(
+                "c.wav",
+                ModelConfig::new("base", "cpu", "int8"),
+                beam(5),
+                Priority::Normal,
+            ),
+        ];
+        for handle in handles {
+            assert!(handle.wait().is_ok());
+        }
+        assert_eq!(loads.load(AtomicOrdering::SeqCst), 2);
+        let stats = queue.pool_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.loaded), (1, 2, 2));
+        queue.shutdown(true);
+    }
+
+    #[test]
+    fn test_panicking_backend_fails_only_its_job() {
+        let backend = MockBackend::new().responding(|audio_path| {
+            if audio_path == Path::new("panics.wav") {
+                panic!("backend bug");
+            }
+            Ok(ResultBuilder::new()
+                .full_text(audio_path.display().to_string())
+                .build())
+        });
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let panicking = submit(&queue, "panics.wav", Priority::High);
+        let queued = submit(&queue, "queued.wav", Priority::Normal);
+
+        let err = panicking.wait().unwrap_err();
+        assert_eq!(err.kind(), "transcription_failed");
+        assert!(err.to_string().contains("the backend panicked"), "{}", err);
+        // The worker lives on for the jobs queued behind it and those submitted later
+        assert_eq!(queued.wait().unwrap().full_text, "queued.wav");
+        let later = submit(&queue, "later.wav", Priority::Normal);
+        assert_eq!(later.wait().unwrap().full_text, "later.wav");
+        queue.shutdown(true);
+    }
+
+    #[test]
+    fn test_queue_without_a_pool_only_runs_its_own_model() {
+        let (backend, _started, gate) = gated();
+        drop(gate);
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let own = queue.submit_with_model(
+            "a.wav",
+            ModelConfig::new("tiny", "cpu", "float32"),
+            TranscriptionOptions::default(),
+            Priority::Normal,
+        );
+        let other = queue.submit_with_model(
+            "b.wav",
+            ModelConfig::new("large-v3", "cpu", "float32"),
+            TranscriptionOptions::default(),
+            Priority::Normal,
+        );
+        assert!(own.wait().is_ok());
+        let err = other.wait().unwrap_err();
+        assert_eq!(err.kind(), "config");
+        assert!(
+            err.to_string().contains("only runs faster-whisper tiny"),
+            "{}",
+            err
+        );
+        assert!(queue.pool_stats().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_results_can_be_awaited() {
+        let (backend, _started, gate) = gated();
+        drop(gate);
+        let queue = JobQueue::new(Box::new(backend)).unwrap();
+        let handle = submit(&queue, "async.wav", Priority::High);
+        assert_eq!(handle.result().await.unwrap().full_text, "async.wav");
+    }
+}
@@ -0,0 +1,303 @@
+use crate::types::TranscriptionResult;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Built-in categories, in the order overlapping matches are resolved
+pub const CATEGORIES: &[&str] = &["email", "credit_card", "phone"];
+
+/// Category name used for `--redact-custom` patterns
+pub const CUSTOM_CATEGORY: &str = "custom";
+
+/// Mask used when none is configured; `{category}` is replaced by the category name
+pub const DEFAULT_MASK: &str = "[REDACTED:{category}]";
+
+fn builtin_pattern(category: &str) -> Option<&'static str> {
+    match category {
+        "email" => Some(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}"),
+        // 13 to 19 digits, optionally grouped with spaces or dashes
+        "credit_card" => Some(r"\b\d(?:[ -]?\d){12,18}\b"),
+        // 7 to 15 digits with the usual separators and an optional country code
+        "phone" => Some(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d(?:[ .-]?\d){6,14}\b"),
+        _ => None,
+    }
+}
+
+/// What a redaction pass did, kept on the result instead of the text it removed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RedactionSummary {
+    /// Every category that was applied, with the number of substitutions it made
+    pub substitutions: BTreeMap<String, usize>,
+}
+
+impl RedactionSummary {
+    pub fn total(&self) -> usize {
+        self.substitutions.values().sum()
+    }
+}
+
+/// A match to mask: byte range and the category it belongs to
+type Span = (usize, usize, usize);
+
+/// Masks personal data in transcripts with built-in and custom patterns
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    /// (category, pattern) in priority order
+    patterns: Vec<(String, Regex)>,
+    mask: String,
+}
+
+impl Redactor {
+    /// `categories` are built-in names (see `CATEGORIES`); every entry of `custom` is a regex
+    /// reported under `CUSTOM_CATEGORY`. `mask` may contain `{category}`.
+    pub fn new(categories: &[String], custom: &[String], mask: &str) -> Result<Self, String> {
+        let mut patterns = Vec::new();
+        for category in categories {
+            let category = category.trim().to_lowercase().replace('-', "_");
+            let pattern = builtin_pattern(&category).ok_or_else(|| {
+                format!(
+                    "Unknown redaction category: {} (expected {})",
+                    category,
+                    CATEGORIES.join(", ")
+                )
+            })?;
+            if !patterns.iter().any(|(existing, _)| *existing == category) {
+                patterns.push((category, Regex::new(pattern).expect("built-in pattern")));
+            }
+        }
+        // Keep built-ins in priority order whatever order they were asked for in
+        patterns.sort_by_key(|(category, _)| CATEGORIES.iter().position(|c| c == category));
+        for pattern in custom {
+            let regex = Regex::new(pattern)
+                .map_err(|e| format!("Invalid redaction pattern {:?}: {}", pattern, e))?;
+            patterns.push((CUSTOM_CATEGORY.to_string(), regex));
+        }
+        Ok(Self {
+            patterns,
+            mask: mask.to_string(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    fn mask(&self, category: usize) -> String {
+        self.mask.replace("{category}", &self.patterns[category].0)
+    }
+
+    /// Non-overlapping matches of every pattern, sorted by position. Where matches overlap
+    /// the earliest wins, then the longest, then the higher-priority category.
+    fn spans(&self, text: &str) -> Vec<Span> {
+        let mut candidates: Vec<Span> = self
+            .patterns
+            .iter()
+            .enumerate()
+            .flat_map(|(category, (_, regex))| {
+                regex
+                    .find_iter(text)
+                    .map(move |m| (m.start(), m.end(), category))
+            })
+            .filter(|(start, end, _)| end > start)
+            .collect();
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+        let mut spans: Vec<Span> = Vec::new();
+        for span in candidates {
+            if spans.last().is_none_or(|last| span.0 >= last.1) {
+                spans.push(span);
+            }
+        }
+        spans
+    }
+
+    /// The part of `text[start..end]` left after masking `spans`; a mask is written where
+    /// its match starts
+    fn masked_range(&self, text: &str, spans: &[Span], start: usize, end: usize) -> String {
+        let mut out = String::new();
+        let mut pos = start;
+        for &(span_start, span_end, category) in spans {
+            if span_end <= start || span_start >= end {
+                continue;
+            }
+            if span_start >= start {
+                out.push_str(&text[pos..span_start]);
+                out.push_str(&self.mask(category));
+            }
+            pos = pos.max(span_end.min(end));
+        }
+        out.push_str(&text[pos..end]);
+        out
+    }
+
+    fn redact_text(&self, text: &str) -> (String, Vec<Span>) {
+        let spans = self.spans(text);
+        (self.masked_range(text, &spans, 0, text.len()), spans)
+    }
+
+    /// Mask segment texts, word texts and `full_text` in place, and record the counts in
+    /// `result.redaction`.
+    ///
+    /// Where a segment's words spell out its text, matches are found once over the joined
+    /// words, so a phone number split across words is masked in both: the word where it
+    /// starts carries the mask and the rest of its words become empty, keeping their timings.
+    pub fn redact(&self, result: &mut TranscriptionResult) {
+        let mut counted = vec![0usize; self.patterns.len()];
+        let mut count = |spans: &[Span]| {
+            for &(_, _, category) in spans {
+                counted[category] += 1;
+            }
+        };
+
+        for segment in &mut result.segments {
+            // Token ids would decode back to the text being masked
+            segment.tokens = None;
+            for word in &mut segment.words {
+                word.tokens = None;
+            }
+            let joined: String = segment.words.iter().map(|w| w.word.as_str()).collect();
+            if !segment.words.is_empty() && joined.trim() == segment.text.trim() {
+                let spans = self.spans(&joined);
+                let mut offset = 0;
+                for word in &mut segment.words {
+                    let end = offset + word.word.len();
+                    word.word = self.masked_range(&joined, &spans, offset, end);
+                    offset = end;
+                }
+                let redacted = self.masked_range(&joined, &spans, 0, joined.len());
+                segment.text = redacted.trim().to_string();
+                count(&spans);
+            } else {
+                let (text, spans) = self.redact_text(&segment.text);
+                segment.text = text;
+                count(&spans);
+                for word in &mut segment.words {
+                    word.word = self.redact_text(&word.word).0;
+                }
+            }
+        }
+
+        let (full_text, spans) = self.redact_text(&result.full_text);
+        result.full_text = full_text;
+        if result.segments.is_empty() {
+            count(&spans);
+        }
+
+        let mut summary = RedactionSummary::default();
+        for ((category, _), n) in self.patterns.iter().zip(counted) {
+            *summary.substitutions.entry(category.clone()).or_default() += n;
+        }
+        result.redaction = Some(summary);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TranscriptionSegment, WordTiming};
+
+    fn redactor(categories: &[&str], custom: &[&str]) -> Redactor {
+        let categories: Vec<String> = categories.iter().map(|c| c.to_string()).collect();
+        let custom: Vec<String> = custom.iter().map(|c| c.to_string()).collect();
+        Redactor::new(&categories, &custom, DEFAULT_MASK).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_patterns() {
+        let redactor = redactor(&["phone", "email", "credit_card"], &[]);
+        let (text, spans) = redactor.redact_text(
+            "Mail jo.doe+x@example.co.uk or call +1 (555) 123-4567. Card 4111 1111 1111 1111.",
+        );
+        assert_eq!(
+            text,
+            "Mail [REDACTED:email] or call [REDACTED:phone]. Card [REDACTED:credit_card]."
+        );
+        assert_eq!(spans.len(), 3);
+        // Short numbers such as years and amounts stay
+        assert_eq!(
+            redactor.redact_text("In 2024 we sold 350 units").0,
+            "In 2024 we sold 350 units"
+        );
+    }
+
+    #[test]
+    fn test_unknown_category_and_bad_custom_pattern() {
+        let err = Redactor::new(&["ssn".to_string()], &[], DEFAULT_MASK).unwrap_err();
+        assert!(err.starts_with("Unknown redaction category: ssn"));
+        let err = Redactor::new(&[], &["(oops".to_string()], DEFAULT_MASK).unwrap_err();
+        assert!(err.starts_with("Invalid redaction pattern \"(oops\""));
+    }
+
+    fn word(start: f64, text: &str) -> WordTiming {
+        WordTiming {
+            start,
+            end: start + 0.4,
+            word: text.to_string(),
+            probability: 0.9,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_redact_result_consistently() {
+        let mut result = TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 4.0,
+            segments: vec![TranscriptionSegment {
+                start: 0.0,
+                end: 4.0,
+                text: "Call 555 123 4567 about Project Falcon".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: 0.0,
+                words: vec![
+                    word(0.0, " Call"),
+                    word(0.5, " 555"),
+                    word(1.0, " 123"),
+                    word(1.5, " 4567"),
+                    word(2.0, " about"),
+                    word(2.5, " Project"),
+                    word(3.0, " Falcon"),
+                ],
+                speaker: None,
+                tokens: Some(vec![50_364, 7_839, 12_330, 50_564]),
+            }],
+            full_text: "Call 555 123 4567 about Project Falcon".to_string(),
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        };
+        let redactor = Redactor::new(
+            &["phone".to_string()],
+            &["Project \\w+".to_string()],
+            "<{category}>",
+        )
+        .unwrap();
+        result.segments[0].words[1].tokens = Some(vec![12_330]);
+        redactor.redact(&mut result);
+
+        let expected = "Call <phone> about <custom>";
+        assert_eq!(result.segments[0].text, expected);
+        assert_eq!(result.full_text, expected);
+        let words: Vec<&str> = result.segments[0]
+            .words
+            .iter()
+            .map(|w| w.word.as_str())
+            .collect();
+        assert_eq!(
+            words,
+            [" Call", " <phone>", "", "", " about", " <custom>", ""]
+        );
+        assert_eq!(result.segments[0].words[1].start, 0.5);
+        // Token ids would give the masked text away
+        assert_eq!(result.segments[0].tokens, None);
+        assert!(result.segments[0].words.iter().all(|w| w.tokens.is_none()));
+
+        let summary = result.redaction.unwrap();
+        assert_eq!(summary.substitutions["phone"], 1);
+        assert_eq!(summary.substitutions["custom"], 1);
+        assert_eq!(summary.total(), 2);
+        let json = serde_json::to_string(&summary).unwrap();
+        assert!(!json.contains("555"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::error::{Result, TranscriptionError};
+use crate::types::TranscriptionResult;
+use regex::{NoExpand, Regex, RegexBuilder};
+use std::path::Path;
+
+/// One line of a replacement rules file, compiled
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pattern: Regex,
+    replacement: String,
+    /// Regex rules may refer to capture groups (`$1`) in the replacement; literal ones can't
+    expand: bool,
+}
+
+impl Rule {
+    fn apply(&self, text: &str) -> String {
+        if self.expand {
+            self.pattern
+                .replace_all(text, self.replacement.as_str())
+                .into_owned()
+        } else {
+            self.pattern
+                .replace_all(text, NoExpand(&self.replacement))
+                .into_owned()
+        }
+    }
+}
+
+/// Replacement rules applied in file order, each to the output of the previous one.
+///
+/// The file is tab-separated: `pattern<TAB>replacement[<TAB>flags]`. Patterns are literal
+/// and case-sensitive unless the flags say otherwise: `r` makes the pattern a regex, `i`
+/// ignores case and `w` only matches whole words. Blank lines and lines starting with `#`
+/// are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let mut rules = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split('\t');
+            let pattern = columns.next().unwrap_or_default();
+            let Some(replacement) = columns.next() else {
+                return Err(format!(
+                    "line {}: expected pattern<TAB>replacement",
+                    line_number
+                ));
+            };
+            let flags = columns.next().unwrap_or_default().trim();
+            if columns.next().is_some() {
+                return Err(format!("line {}: too many columns", line_number));
+            }
+            if pattern.is_empty() {
+                return Err(format!("line {}: empty pattern", line_number));
+            }
+            if let Some(unknown) = flags.chars().find(|c| !"riw".contains(*c)) {
+                return Err(format!(
+                    "line {}: unknown flag '{}' (expected r, i or w)",
+                    line_number, unknown
+                ));
+            }
+
+            let is_regex = flags.contains('r');
+            let mut source = if is_regex {
+                pattern.to_string()
+            } else {
+                regex::escape(pattern)
+            };
+            if flags.contains('w') {
+                source = format!(r"\b(?:{})\b", source);
+            }
+            let pattern = RegexBuilder::new(&source)
+                .case_insensitive(flags.contains('i'))
+                .build()
+                .map_err(|e| format!("line {}: invalid regex: {}", line_number, e))?;
+            rules.push(Rule {
+                pattern,
+                replacement: replacement.to_string(),
+                expand: is_regex,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let error = |e: &dyn std::fmt::Display| {
+            TranscriptionError::ConfigError(format!("Replacement rules {}: {}", path.display(), e))
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| error(&e))?;
+        Self::parse(&text).map_err(|e| error(&e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `text` with every rule applied in order
+    pub fn apply(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, rule| rule.apply(&text))
+    }
+}
+
+/// Rewrite segment texts and `full_text` with `rules`. Word timings keep the words the model
+/// produced, so their timestamps stay meaningful.
+pub fn apply_replacements(result: &mut TranscriptionResult, rules: &RuleSet) {
+    if rules.is_empty() {
+        return;
+    }
+    for segment in &mut result.segments {
+        segment.text = rules.apply(&segment.text);
+    }
+    result.full_text = rules.apply(&result.full_text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TranscriptionSegment, WordTiming};
+
+    #[test]
+    fn test_literal_and_regex_rules() {
+        let rules = RuleSet::parse(
+            "# product names\n\
+             pi oh three\tPyO3\ti\n\
+             \n\
+             (\\d+) percent\t$1%\tr\n\
+             cost $5\tcost five dollars\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(
+            rules.apply("Pi Oh Three is 90 percent done"),
+            "PyO3 is 90% done"
+        );
+        // Literal patterns and replacements take `$` at face value
+        assert_eq!(rules.apply("it will cost $5"), "it will cost five dollars");
+        // Case-sensitive unless flagged
+        assert_eq!(rules.apply("Cost $5"), "Cost $5");
+    }
+
+    #[test]
+    fn test_overlapping_matches() {
+        // Earlier rules win where patterns overlap; later rules see their output
+        let rules = RuleSet::parse("pi oh three\tPyO3\noh three\tO3\nthree\t3\n").unwrap();
+        assert_eq!(rules.apply("pi oh three, oh three, three"), "PyO3, O3, 3");
+
+        // Within one rule, matches don't overlap and are taken left to right
+        let rules = RuleSet::parse("aa\tb\n").unwrap();
+        assert_eq!(rules.apply("aaaaa"), "bba");
+    }
+
+    #[test]
+    fn test_whole_word_rules() {
+        let rules = RuleSet::parse("cat\tdog\tw\n").unwrap();
+        assert_eq!(rules.apply("cat concatenate cat."), "dog concatenate dog.");
+    }
+
+    #[test]
+    fn test_invalid_rules_name_the_line() {
+        let err = RuleSet::parse("ok\tfine\n(unclosed\tx\tr\n").unwrap_err();
+        assert!(err.starts_with("line 2: invalid regex"), "{}", err);
+        assert_eq!(
+            RuleSet::parse("\nno tab here\n").unwrap_err(),
+            "line 2: expected pattern<TAB>replacement"
+        );
+        assert_eq!(
+            RuleSet::parse("a\tb\tx\n").unwrap_err(),
+            "line 1: unknown flag 'x' (expected r, i or w)"
+        );
+    }
+
+    #[test]
+    fn test_apply_replacements_leaves_words_alone() {
+        let mut result = TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 2.0,
+            segments: vec![TranscriptionSegment {
+                start: 0.0,
+                end: 2.0,
+                text: "I like pi oh three".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: 0.0,
+                words: vec![WordTiming {
+                    start: 1.0,
+                    end: 1.2,
+                    word: " pi".to_string(),
+                    probability: 0.9,
+                    speaker: None,
+                    tokens: None,
+                }],
+                speaker: None,
+                tokens: None,
+            }],
+            full_text: "I like pi oh three".to_string(),
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        };
+        let rules = RuleSet::parse("pi oh three\tPyO3\n").unwrap();
+        apply_replacements(&mut result, &rules);
+        assert_eq!(result.segments[0].text, "I like PyO3");
+        assert_eq!(result.full_text, "I like PyO3");
+        assert_eq!(result.segments[0].words[0].word, " pi");
+    }
+}
@@ -0,0 +1,99 @@
+//! The high-water mark of this process's resident memory, which the embedded interpreter
+//! and every model loaded so far count toward.
+
+/// Bytes in a megabyte, as `BenchmarkResult` reports memory
+const MB: f64 = 1024.0 * 1024.0;
+
+/// The most memory this process has had resident at once so far, in bytes, or `None` where
+/// the platform doesn't say. It never goes down.
+pub fn peak_rss() -> Option<u64> {
+    platform::peak_rss()
+}
+
+/// `peak_rss` in megabytes
+pub fn peak_rss_mb() -> Option<f64> {
+    peak_rss().map(|bytes| bytes as f64 / MB)
+}
+
+/// The `VmHWM` line of a Linux `/proc/<pid>/status`, in bytes
+pub fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let mut fields = line["VmHWM:".len()..].split_whitespace();
+    let value: u64 = fields.next()?.parse().ok()?;
+    match fields.next() {
+        Some("kB") | None => value.checked_mul(1024),
+        Some(_) => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn peak_rss() -> Option<u64> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        super::parse_vm_hwm(&status)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod platform {
+    pub fn peak_rss() -> Option<u64> {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+        // SAFETY: `usage` is only read after getrusage filled it
+        let usage = unsafe {
+            if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+                return None;
+            }
+            usage.assume_init()
+        };
+        let max = u64::try_from(usage.ru_maxrss).ok()?;
+        // macOS reports bytes; the BSDs kilobytes
+        if cfg!(target_vendor = "apple") {
+            Some(max)
+        } else {
+            max.checked_mul(1024)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    pub fn peak_rss() -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_hwm() {
+        let status = "Name:\trust-whisper-app\nVmPeak:\t 2097152 kB\nVmHWM:\t  524288 kB\nVmRSS:\t  262144 kB\n";
+        assert_eq!(parse_vm_hwm(status), Some(512 * 1024 * 1024));
+        assert_eq!(parse_vm_hwm("VmHWM: 12\n"), Some(12 * 1024));
+        assert_eq!(parse_vm_hwm("VmRSS:\t 100 kB\n"), None);
+        assert_eq!(parse_vm_hwm("VmHWM:\t lots kB\n"), None);
+        assert_eq!(parse_vm_hwm("VmHWM:\t 100 MB\n"), None);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_peak_rss_smoke() {
+        let before = peak_rss().expect("the platform reports peak RSS");
+        assert!(before > 0);
+        // Touch every page so it is resident
+        let block = vec![1u8; 32 * 1024 * 1024];
+        assert_eq!(
+            block
+                .iter()
+                .step_by(4096)
+                .map(|&b| b as usize)
+                .sum::<usize>(),
+            8192
+        );
+        let after = peak_rss().unwrap();
+        assert!(after >= before, "{} then {}", before, after);
+        assert!(after >= block.len() as u64);
+        assert!(peak_rss_mb().unwrap() >= 32.0);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::error::{Result, TranscriptionError};
+use crate::types::is_supported_audio_file;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use tracing::debug;
+
+/// The AWS CLI, looked up on `PATH` unless `WHISPER_AWS_BIN` names another binary
+pub const DEFAULT_BINARY: &str = "aws";
+pub const BINARY_ENV_VAR: &str = "WHISPER_AWS_BIN";
+pub const SCHEME: &str = "s3://";
+
+/// Messages the AWS CLI prints when credentials are missing or lack a permission
+const ACCESS_MARKERS: &[&str] = &[
+    "Unable to locate credentials",
+    "NoCredentialProviders",
+    "InvalidAccessKeyId",
+    "SignatureDoesNotMatch",
+    "ExpiredToken",
+    "AccessDenied",
+    "AllAccessDisabled",
+    "Forbidden",
+    "(403)",
+];
+
+/// `s3://bucket/key`; a key that is empty or ends in `/` names a prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Uri {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Uri {
+    pub fn is_s3(uri: &str) -> bool {
+        uri.starts_with(SCHEME)
+    }
+
+    pub fn is_prefix(&self) -> bool {
+        self.key.is_empty() || self.key.ends_with('/')
+    }
+
+    /// The object `relative` below this prefix
+    pub fn join(&self, relative: &str) -> S3Uri {
+        let separator = if self.is_prefix() { "" } else { "/" };
+        S3Uri {
+            bucket: self.bucket.clone(),
+            key: format!("{}{}{}", self.key, separator, relative),
+        }
+    }
+
+    /// Part of `key` below this prefix, or its last component when it isn't below it
+    pub fn relative_key<'a>(&self, key: &'a str) -> &'a str {
+        match key.strip_prefix(&self.key) {
+            Some(relative) if self.is_prefix() && !relative.is_empty() => relative,
+            _ => key.rsplit('/').next().unwrap_or(key),
+        }
+    }
+}
+
+impl FromStr for S3Uri {
+    type Err = TranscriptionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| TranscriptionError::InvalidPath(format!("Not an s3:// URI: {}", s)))?;
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(TranscriptionError::InvalidPath(format!(
+                "Missing bucket in {}",
+                s
+            )));
+        }
+        Ok(S3Uri {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for S3Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}/{}", SCHEME, self.bucket, self.key)
+    }
+}
+
+/// One object found by `S3Client::list_audio`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct S3Object {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Size")]
+    pub size: u64,
+}
+
+#[derive(Deserialize)]
+struct ListObjectsOutput {
+    #[serde(rename = "Contents", default)]
+    contents: Vec<S3Object>,
+}
+
+/// Lists, downloads and uploads objects by running the AWS CLI.
+///
+/// Credentials, region and endpoint come from the CLI's own chain: `AWS_*` environment
+/// variables, `AWS_PROFILE` and the shared config files, SSO or instance roles.
+pub struct S3Client {
+    binary: PathBuf,
+}
+
+impl S3Client {
+    pub fn new() -> Self {
+        let binary = std::env::var_os(BINARY_ENV_VAR)
+            .filter(|bin| !bin.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BINARY));
+        Self { binary }
+    }
+
+    /// Use a specific AWS CLI binary instead of `aws` from `PATH`
+    pub fn with_binary<P: Into<PathBuf>>(mut self, binary: P) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    fn run(&self, uri: &S3Uri, args: &[&std::ffi::OsStr]) -> Result<Vec<u8>> {
+        let mut command = Command::new(&self.binary);
+        command.args(args);
+        debug!("Running {:?}", command);
+        let output = command
+            .output()
+            .map_err(|e| TranscriptionError::StorageError {
+                uri: uri.to_string(),
+                detail: format!(
+                    "cannot run the AWS CLI {} (set {} to its path): {}",
+                    self.binary.display(),
+                    BINARY_ENV_VAR,
+                    e
+                ),
+            })?;
+        if !output.status.success() {
+            return Err(classify_failure(
+                uri,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Supported audio objects under a prefix, or the single object a key names, sorted
+    /// by key
+    pub fn list_audio(&self, uri: &S3Uri) -> Result<Vec<S3Object>> {
+        let stdout = self.run(
+            uri,
+            &[
+                "s3api".as_ref(),
+                "list-objects-v2".as_ref(),
+                "--bucket".as_ref(),
+                uri.bucket.as_ref(),
+                "--prefix".as_ref(),
+                uri.key.as_ref(),
+                "--output".as_ref(),
+                "json".as_ref(),
+            ],
+        )?;
+        let mut objects = parse_listing(&stdout, uri)?;
+        if !uri.is_prefix() {
+            objects.retain(|object| object.key == uri.key);
+        }
+        objects.retain(|object| is_supported_audio_file(Path::new(&object.key)));
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    pub fn download(&self, uri: &S3Uri, destination: &Path) -> Result<()> {
+        let source = uri.to_string();
+        self.run(
+            uri,
+            &[
+                "s3".as_ref(),
+                "cp".as_ref(),
+                "--only-show-errors".as_ref(),
+                source.as_ref(),
+                destination.as_os_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn upload(&self, source: &Path, uri: &S3Uri) -> Result<()> {
+        let destination = uri.to_string();
+        self.run(
+            uri,
+            &[
+                "s3".as_ref(),
+                "cp".as_ref(),
+                "--only-show-errors".as_ref(),
+                source.as_os_str(),
+                destination.as_ref(),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl Default for S3Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_listing(stdout: &[u8], uri: &S3Uri) -> Result<Vec<S3Object>> {
+    // An empty listing prints nothing at all
+    if stdout.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Vec::new());
+    }
+    let listing: ListObjectsOutput =
+        serde_json::from_slice(stdout).map_err(|e| TranscriptionError::StorageError {
+            uri: uri.to_string(),
+            detail: format!("unexpected listing output: {}", e),
+        })?;
+    Ok(listing.contents)
+}
+
+/// Tell credential and permission problems apart from other failures
+fn classify_failure(uri: &S3Uri, stderr: &str) -> TranscriptionError {
+    let detail = if stderr.is_empty() {
+        "the AWS CLI failed without output".to_string()
+    } else {
+        stderr.to_string()
+    };
+    if ACCESS_MARKERS.iter().any(|marker| stderr.contains(marker)) {
+        TranscriptionError::StorageAccessDenied {
+            uri: uri.to_string(),
+            detail,
+        }
+    } else {
+        TranscriptionError::StorageError {
+            uri: uri.to_string(),
+            detail,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_uris() {
+        let prefix: S3Uri = "s3://media/calls/2024/".parse().unwrap();
+        assert_eq!(prefix.bucket, "media");
+        assert!(prefix.is_prefix());
+        assert_eq!(prefix.relative_key("calls/2024/jan/a.wav"), "jan/a.wav");
+        assert_eq!(
+            prefix.join("jan/a.json").to_string(),
+            "s3://media/calls/2024/jan/a.json"
+        );
+
+        let object: S3Uri = "s3://media/calls/a.wav".parse().unwrap();
+        assert!(!object.is_prefix());
+        assert_eq!(object.relative_key("calls/a.wav"), "a.wav");
+        assert!("s3://media".parse::<S3Uri>().unwrap().is_prefix());
+        assert!("s3:///key".parse::<S3Uri>().is_err());
+        assert!("/local/path".parse::<S3Uri>().is_err());
+    }
+
+    #[test]
+    fn test_listing_and_failures() {
+        let uri: S3Uri = "s3://media/calls/".parse().unwrap();
+        let json = br#"{"Contents": [{"Key": "calls/a.wav", "Size": 10, "ETag": "x"}]}"#;
+        assert_eq!(
+            parse_listing(json, &uri).unwrap(),
+            vec![S3Object {
+                key: "calls/a.wav".to_string(),
+                size: 10
+            }]
+        );
+        assert!(parse_listing(b"\n", &uri).unwrap().is_empty());
+
+        let denied = classify_failure(
+            &uri,
+            "An error occurred (AccessDenied) when calling the ListObjectsV2 operation",
+        );
+        assert_eq!(denied.kind(), "storage_access");
+        let missing = classify_failure(&uri, "Unable to locate credentials");
+        assert_eq!(missing.kind(), "storage_access");
+        let other = classify_failure(&uri, "An error occurred (NoSuchBucket)");
+        assert_eq!(other.kind(), "storage");
+    }
+}
@@ -0,0 +1,174 @@
+//! The order a batch hands its files to workers. Workers take the next file as soon as
+//! they're free, so the order decides how evenly the audio spreads across them.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Which files a batch starts first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Schedule {
+    /// Longest files first (the LPT heuristic), so no long file is left to run alone at the
+    /// end while the other workers sit idle
+    #[default]
+    LongestFirst,
+    /// Files in the order they were listed
+    Fifo,
+    /// Shortest files first, for quick results early on
+    SmallestFirst,
+}
+
+impl Schedule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Schedule::LongestFirst => "longest-first",
+            Schedule::Fifo => "fifo",
+            Schedule::SmallestFirst => "smallest-first",
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "longest-first" | "longest" | "lpt" => Ok(Schedule::LongestFirst),
+            "fifo" | "listed" => Ok(Schedule::Fifo),
+            "smallest-first" | "shortest-first" | "smallest" => Ok(Schedule::SmallestFirst),
+            other => Err(format!(
+                "Invalid schedule: {} (expected longest-first, fifo or smallest-first)",
+                other
+            )),
+        }
+    }
+}
+
+/// Indices into `durations` in the order to start them. Files of unknown duration come
+/// after the rest in their listed order, as do ties; `Fifo` keeps the listed order.
+pub fn order(durations: &[Option<f64>], schedule: Schedule) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..durations.len()).collect();
+    let key = |index: &usize| durations[*index].filter(|duration| duration.is_finite());
+    match schedule {
+        Schedule::Fifo => {}
+        Schedule::LongestFirst => order.sort_by(|a, b| match (key(a), key(b)) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }),
+        Schedule::SmallestFirst => order.sort_by(|a, b| match (key(a), key(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }),
+    }
+    order
+}
+
+/// When the last of `workers` workers would finish, running the files of `durations` in
+/// `order` with each worker taking the next file as soon as it's free, as a batch does
+pub fn makespan(durations: &[f64], order: &[usize], workers: usize) -> f64 {
+    let mut finish = vec![0.0_f64; workers.max(1)];
+    for &index in order {
+        let earliest = finish
+            .iter_mut()
+            .min_by(|a, b| a.total_cmp(b))
+            .expect("at least one worker");
+        *earliest += durations[index];
+    }
+    finish.into_iter().fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known(durations: &[f64]) -> Vec<Option<f64>> {
+        durations.iter().copied().map(Some).collect()
+    }
+
+    #[test]
+    fn test_order() {
+        let durations = vec![
+            Some(60.0),
+            None,
+            Some(7200.0),
+            Some(600.0),
+            None,
+            Some(60.0),
+        ];
+        assert_eq!(
+            order(&durations, Schedule::LongestFirst),
+            vec![2, 3, 0, 5, 1, 4]
+        );
+        assert_eq!(
+            order(&durations, Schedule::SmallestFirst),
+            vec![0, 5, 3, 2, 1, 4]
+        );
+        assert_eq!(order(&durations, Schedule::Fifo), vec![0, 1, 2, 3, 4, 5]);
+        assert!(order(&[], Schedule::LongestFirst).is_empty());
+    }
+
+    #[test]
+    fn test_longest_first_beats_fifo_when_long_files_come_last() {
+        // Clips, then recordings of two hours or one
+        let durations = [300.0, 300.0, 600.0, 300.0, 7200.0, 7200.0, 3600.0, 7200.0];
+        let makespan_of = |schedule| makespan(&durations, &order(&known(&durations), schedule), 2);
+        assert_eq!(makespan_of(Schedule::Fifo), 15300.0);
+        assert_eq!(makespan_of(Schedule::LongestFirst), 14400.0);
+        assert_eq!(makespan_of(Schedule::SmallestFirst), 15300.0);
+    }
+
+    #[test]
+    fn test_longest_first_beats_fifo_on_synthetic_batches() {
+        // A fixed pseudo-random sequence of durations between 10s and 2h
+        let mut state = 0x2545_f491_u64;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            10.0 + (state >> 33) as f64 % 7190.0
+        };
+        let (mut fifo_total, mut lpt_total) = (0.0, 0.0);
+        for workers in [2, 3, 4, 8] {
+            for files in [5, 17, 40] {
+                let durations: Vec<f64> = (0..files).map(|_| next()).collect();
+                let makespan_of =
+                    |schedule| makespan(&durations, &order(&known(&durations), schedule), workers);
+                let lpt = makespan_of(Schedule::LongestFirst);
+                fifo_total += makespan_of(Schedule::Fifo);
+                lpt_total += lpt;
+                // No schedule can beat the average load per worker or the longest file
+                let total: f64 = durations.iter().sum();
+                let longest = durations.iter().copied().fold(0.0, f64::max);
+                assert!(lpt >= (total / workers as f64).max(longest) - 1e-9);
+            }
+        }
+        assert!(
+            lpt_total < fifo_total * 0.95,
+            "{} vs {}",
+            lpt_total,
+            fifo_total
+        );
+    }
+
+    #[test]
+    fn test_makespan() {
+        assert_eq!(makespan(&[], &[], 4), 0.0);
+        assert_eq!(makespan(&[5.0, 3.0, 2.0], &[0, 1, 2], 1), 10.0);
+        assert_eq!(makespan(&[5.0, 3.0, 2.0], &[0, 1, 2], 2), 5.0);
+        assert_eq!(makespan(&[5.0, 3.0, 2.0], &[2, 1, 0], 2), 7.0);
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!("longest-first".parse(), Ok(Schedule::LongestFirst));
+        assert_eq!("FIFO".parse(), Ok(Schedule::Fifo));
+        assert_eq!("smallest-first".parse(), Ok(Schedule::SmallestFirst));
+        assert!("random".parse::<Schedule>().is_err());
+        assert_eq!(Schedule::default().to_string(), "longest-first");
+    }
+}
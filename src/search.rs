@@ -0,0 +1,248 @@
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+
+/// How `TranscriptionResult::find` matches query terms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchOptions {
+    /// Also accept words one edit (insertion, deletion or substitution) away from a query
+    /// term. Terms shorter than `FUZZY_MIN_CHARS` always match exactly.
+    pub fuzzy: bool,
+    /// Words of context shown on each side of a match
+    pub context_words: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            fuzzy: false,
+            context_words: 5,
+        }
+    }
+}
+
+/// Below this, one edit turns most words into other words ("a" into "I")
+pub const FUZZY_MIN_CHARS: usize = 4;
+
+/// One occurrence of a query in a transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Match {
+    /// The words as transcribed, which may differ from the query in case, punctuation or,
+    /// with fuzzy matching, spelling
+    pub text: String,
+    /// From word timestamps when available, otherwise the whole segment
+    pub start: f64,
+    pub end: f64,
+    pub segment: usize,
+    /// The match with up to `context_words` words either side
+    pub context: String,
+}
+
+/// Lowercase and strip surrounding punctuation, so "Falcon," matches "falcon"
+pub(crate) fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+fn within_one_edit(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (short, long) = if a.len() <= b.len() {
+        (&a, &b)
+    } else {
+        (&b, &a)
+    };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short
+        .iter()
+        .zip(long.iter())
+        .take_while(|(x, y)| x == y)
+        .count();
+    if short.len() == long.len() {
+        // One substitution at most
+        short[prefix..]
+            .iter()
+            .zip(&long[prefix..])
+            .filter(|(x, y)| x != y)
+            .count()
+            <= 1
+    } else {
+        // One insertion into the shorter word
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+fn term_matches(word: &str, term: &str, fuzzy: bool) -> bool {
+    word == term
+        || (fuzzy && term.chars().count() >= FUZZY_MIN_CHARS && within_one_edit(word, term))
+}
+
+/// Start indices of `terms` as a run of consecutive non-empty `words`, with the index past
+/// the last word of each run
+fn find_runs(words: &[String], terms: &[String], fuzzy: bool) -> Vec<(usize, usize)> {
+    // Words that are only punctuation are skipped, so "Project — Falcon" still matches
+    let positions: Vec<usize> = (0..words.len()).filter(|&i| !words[i].is_empty()).collect();
+    if terms.is_empty() || positions.len() < terms.len() {
+        return Vec::new();
+    }
+    positions
+        .windows(terms.len())
+        .filter(|run| {
+            run.iter()
+                .zip(terms)
+                .all(|(&i, term)| term_matches(&words[i], term, fuzzy))
+        })
+        .map(|run| (run[0], run[run.len() - 1] + 1))
+        .collect()
+}
+
+fn join_words<'a>(words: impl IntoIterator<Item = &'a str>) -> String {
+    words
+        .into_iter()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Every occurrence of `query` in `result`, in transcript order.
+///
+/// Matching is case-insensitive and ignores punctuation around words. A phrase matches
+/// consecutive words within one segment. Segments without word timestamps are searched by
+/// their text and matches are timed by the segment.
+pub fn search(result: &TranscriptionResult, query: &str, options: SearchOptions) -> Vec<Match> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(normalize)
+        .filter(|t| !t.is_empty())
+        .collect();
+    let mut matches = Vec::new();
+    for (index, segment) in result.segments.iter().enumerate() {
+        let raw: Vec<&str> = if segment.words.is_empty() {
+            segment.text.split_whitespace().collect()
+        } else {
+            segment.words.iter().map(|w| w.word.as_str()).collect()
+        };
+        let normalized: Vec<String> = raw.iter().map(|w| normalize(w)).collect();
+        for (first, last) in find_runs(&normalized, &terms, options.fuzzy) {
+            let (start, end) = if segment.words.is_empty() {
+                (segment.start, segment.end)
+            } else {
+                (segment.words[first].start, segment.words[last - 1].end)
+            };
+            let context_start = first.saturating_sub(options.context_words);
+            let context_end = (last + options.context_words).min(raw.len());
+            matches.push(Match {
+                text: join_words(raw[first..last].iter().copied()),
+                start,
+                end,
+                segment: index,
+                context: join_words(raw[context_start..context_end].iter().copied()),
+            });
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TranscriptionSegment, WordTiming};
+
+    fn segment(start: f64, text: &str, timed: bool) -> TranscriptionSegment {
+        let words = if timed {
+            text.split_whitespace()
+                .enumerate()
+                .map(|(i, w)| WordTiming {
+                    start: start + i as f64,
+                    end: start + i as f64 + 0.5,
+                    word: format!(" {}", w),
+                    probability: 0.9,
+                    speaker: None,
+                    tokens: None,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        TranscriptionSegment {
+            start,
+            end: start + text.split_whitespace().count() as f64,
+            text: text.to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: 0.0,
+            words,
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    fn result(segments: Vec<TranscriptionSegment>) -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 60.0,
+            segments,
+            full_text: String::new(),
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_within_one_edit() {
+        assert!(within_one_edit("falcon", "falcon"));
+        assert!(within_one_edit("falcon", "falcom"));
+        assert!(within_one_edit("falcon", "falcons"));
+        assert!(within_one_edit("falcon", "flcon"));
+        assert!(!within_one_edit("falcon", "flacon"));
+        assert!(!within_one_edit("falcon", "fal"));
+    }
+
+    #[test]
+    fn test_find_phrase_with_word_timestamps() {
+        let result = result(vec![
+            segment(0.0, "Welcome back everyone.", true),
+            segment(
+                10.0,
+                "Today: PROJECT Falcon, and why project falcon slipped.",
+                true,
+            ),
+        ]);
+        let options = SearchOptions {
+            context_words: 1,
+            ..Default::default()
+        };
+        let matches = result.find("project falcon", options);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].text, "PROJECT Falcon,");
+        assert_eq!((matches[0].start, matches[0].end), (11.0, 12.5));
+        assert_eq!(matches[0].segment, 1);
+        assert_eq!(matches[0].context, "Today: PROJECT Falcon, and");
+        assert_eq!(matches[1].context, "why project falcon slipped.");
+
+        // A phrase doesn't match across a segment boundary
+        assert!(result.find("everyone today", options).is_empty());
+        assert!(result.find("  ", options).is_empty());
+    }
+
+    #[test]
+    fn test_find_fuzzy_and_segment_fallback() {
+        let result = result(vec![segment(5.0, "The falcom team met on Tuesday", false)]);
+        assert!(result.find("falcon", SearchOptions::default()).is_empty());
+
+        let fuzzy = SearchOptions {
+            fuzzy: true,
+            ..Default::default()
+        };
+        let matches = result.find("Falcon team", fuzzy);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].text, "falcom team");
+        // No word timestamps: the segment's times
+        assert_eq!((matches[0].start, matches[0].end), (5.0, 11.0));
+        // Short terms stay exact
+        assert!(result.find("ten", fuzzy).is_empty());
+    }
+}
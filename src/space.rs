@@ -0,0 +1,217 @@
+use crate::error::{Result, TranscriptionError};
+use crate::output::OutputFormat;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Left free beyond an estimate, since estimates are rough and a full disk breaks more
+/// than this tool
+pub const HEADROOM: u64 = 100 * 1024 * 1024;
+
+/// Bytes free to unprivileged users on the volume holding `path`, or `None` when it can't
+/// be queried. A `path` that doesn't exist yet is measured at its nearest existing ancestor.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|dir| dir.exists())?;
+    statvfs_available(existing)
+}
+
+#[cfg(unix)]
+fn statvfs_available(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stats` is only read after statvfs filled it
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stats.assume_init()
+    };
+    #[allow(clippy::unnecessary_cast)] // the field types differ between platforms
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn statvfs_available(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Fail when `available` bytes at `path` can't hold `needed` plus `HEADROOM`. An unknown
+/// `available` passes, so volumes that can't be queried are never blocked.
+pub fn check_space(path: &Path, needed: u64, available: Option<u64>) -> Result<()> {
+    match available {
+        Some(available) if available < needed.saturating_add(HEADROOM) => {
+            Err(TranscriptionError::InsufficientDiskSpace {
+                needed,
+                available,
+                path: path.to_path_buf(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `check_space` against what the volume holding `path` has free
+pub fn ensure_space(path: &Path, needed: u64) -> Result<()> {
+    check_space(path, needed, available_space(path))
+}
+
+/// Rough size of one minute of audio's transcript in `format`, measured on speech with word
+/// timestamps off; JSON carries per-segment scores and subtitles their timestamps
+pub fn output_bytes_per_minute(format: OutputFormat) -> u64 {
+    match format {
+        OutputFormat::Txt => 1_000,
+        OutputFormat::Srt | OutputFormat::Vtt => 2_000,
+        OutputFormat::Json | OutputFormat::OpenAiJson => 6_000,
+    }
+}
+
+/// Rough bytes the transcripts of `audio_seconds` of audio take in all of `formats`. Word
+/// timestamps about quadruple the JSON outputs.
+pub fn estimate_output_bytes(audio_seconds: f64, formats: &[OutputFormat], words: bool) -> u64 {
+    let minutes = audio_seconds.max(0.0) / 60.0;
+    formats
+        .iter()
+        .map(|&format| {
+            let per_minute = match format {
+                OutputFormat::Json | OutputFormat::OpenAiJson if words => {
+                    4 * output_bytes_per_minute(format)
+                }
+                _ => output_bytes_per_minute(format),
+            };
+            (minutes * per_minute as f64).ceil() as u64
+        })
+        .sum()
+}
+
+/// Transcripts still to be written to an output directory, added up as files are found,
+/// that warns once they may no longer fit in what was free
+#[derive(Debug, Clone)]
+pub struct OutputBudget {
+    dir: PathBuf,
+    available: u64,
+    formats: Vec<OutputFormat>,
+    words: bool,
+    audio_seconds: f64,
+    warned: bool,
+}
+
+impl OutputBudget {
+    pub fn new(dir: &Path, available: u64, formats: Vec<OutputFormat>, words: bool) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            available,
+            formats,
+            words,
+            audio_seconds: 0.0,
+            warned: false,
+        }
+    }
+
+    /// Count `seconds` more audio, warning the first time the outputs may not fit. `false`
+    /// once they may not.
+    pub fn add(&mut self, seconds: f64) -> bool {
+        self.audio_seconds += seconds.max(0.0);
+        let needed = estimate_output_bytes(self.audio_seconds, &self.formats, self.words);
+        if check_space(&self.dir, needed, Some(self.available)).is_ok() {
+            return true;
+        }
+        if !self.warned {
+            self.warned = true;
+            warn!(
+                "The outputs may not fit in {}: about {} for {:.1} minutes of audio, {} free",
+                self.dir.display(),
+                format_size(needed),
+                self.audio_seconds / 60.0,
+                format_size(self.available)
+            );
+        }
+        false
+    }
+}
+
+/// `bytes` in the largest binary unit, such as `1.5 GB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_check_space_keeps_headroom() {
+        let path = Path::new("/models");
+        assert!(check_space(path, 3 * GB, Some(4 * GB)).is_ok());
+        assert!(check_space(path, 3 * GB, None).is_ok());
+
+        let err = check_space(path, 3 * GB, Some(3 * GB)).unwrap_err();
+        assert_eq!(err.kind(), "insufficient_disk_space");
+        assert_eq!(err.path(), Some(path));
+        assert_eq!(
+            err.to_string(),
+            "Not enough disk space at /models: needs about 3.0 GB, 3.0 GB free \
+             (--skip-space-check to try anyway)"
+        );
+        // No overflow adding the headroom
+        assert!(check_space(path, u64::MAX, Some(GB)).is_err());
+    }
+
+    #[test]
+    fn test_available_space_of_missing_dir_uses_ancestor() {
+        let dir = tempdir().unwrap();
+        let here = available_space(dir.path());
+        if cfg!(unix) {
+            assert!(here.is_some());
+        }
+        assert_eq!(
+            available_space(&dir.path().join("not/yet/created")).is_some(),
+            here.is_some()
+        );
+    }
+
+    #[test]
+    fn test_output_estimate() {
+        let formats = [OutputFormat::Json, OutputFormat::Srt];
+        // An hour: 60 * (6 KB + 2 KB)
+        assert_eq!(estimate_output_bytes(3600.0, &formats, false), 480_000);
+        assert_eq!(estimate_output_bytes(3600.0, &formats, true), 1_560_000);
+        assert_eq!(estimate_output_bytes(0.0, &formats, true), 0);
+    }
+
+    #[test]
+    fn test_output_budget_fills_as_files_are_added() {
+        // 6 KB a minute of JSON against the headroom plus 60 KB: ten minutes fit
+        let mut budget = OutputBudget::new(
+            Path::new("/out"),
+            HEADROOM + 60_000,
+            vec![OutputFormat::Json],
+            false,
+        );
+        assert!(budget.add(300.0));
+        assert!(budget.add(300.0));
+        assert!(!budget.add(1.0));
+        assert!(budget.warned);
+        assert!(!budget.add(0.0));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * GB), "3.0 GB");
+    }
+}
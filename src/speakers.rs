@@ -0,0 +1,338 @@
+use crate::backend::SAMPLE_RATE;
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+
+/// `SpeakerTurns::method` when only pauses were used
+pub const METHOD_GAP: &str = "heuristic:gap";
+/// `SpeakerTurns::method` when pauses were confirmed by a change in loudness
+pub const METHOD_GAP_ENERGY: &str = "heuristic:gap+energy";
+/// `SpeakerTurns::method` for labels from pyannote's diarization pipeline
+pub const METHOD_PYANNOTE: &str = "pyannote/speaker-diarization";
+/// `SpeakerTurns::method` when each speaker was recorded on a channel of their own
+pub const METHOD_CHANNELS: &str = "channels";
+
+/// Tuning of the speaker-turn heuristic
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeakerOptions {
+    /// Pause, in seconds, after which another speaker may start
+    pub min_gap: f64,
+    /// Labels cycle S1, S2, ... up to this many and back to S1
+    pub max_speakers: usize,
+    /// With audio available, a pause only counts when the average level of the segments
+    /// either side differs by at least this many dB
+    pub energy_shift_db: f64,
+}
+
+impl Default for SpeakerOptions {
+    fn default() -> Self {
+        Self {
+            min_gap: 1.0,
+            max_speakers: 2,
+            energy_shift_db: 3.0,
+        }
+    }
+}
+
+impl SpeakerOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_speakers == 0 {
+            return Err("Speaker count must be at least 1".to_string());
+        }
+        if !self.min_gap.is_finite() || self.min_gap < 0.0 {
+            return Err(format!(
+                "Speaker gap must be zero or more seconds, got {}",
+                self.min_gap
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Record of a speaker-labelling pass. With the heuristic methods the labels are guesses
+/// from pauses and loudness, not voice identification: S1 is whoever speaks first and turns
+/// alternate between labels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerTurns {
+    /// `METHOD_GAP`, `METHOD_GAP_ENERGY` or `METHOD_PYANNOTE`
+    pub method: String,
+    /// The configured maximum for the heuristic; the speakers found for diarization
+    pub max_speakers: usize,
+    /// Speaker changes between consecutive segments
+    pub turns: usize,
+    /// How long the diarization pass took, apart from transcription
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diarization_seconds: Option<f64>,
+}
+
+/// A stretch of audio a diarization model attributed to one speaker
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiarizedTurn {
+    pub start: f64,
+    pub end: f64,
+    pub speaker: String,
+}
+
+/// Average level of `samples` between `start` and `end` seconds, in dBFS
+fn level_db(samples: &[f32], start: f64, end: f64) -> Option<f64> {
+    let rate = SAMPLE_RATE as f64;
+    let from = ((start.max(0.0) * rate) as usize).min(samples.len());
+    let to = ((end.max(0.0) * rate) as usize).min(samples.len());
+    if to <= from {
+        return None;
+    }
+    let window = &samples[from..to];
+    let mean_square =
+        window.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / window.len() as f64;
+    Some(10.0 * mean_square.max(1e-12).log10())
+}
+
+/// Label segments with likely speaker turns, without a diarization model.
+///
+/// A turn is placed at every pause longer than `min_gap`. When `samples` (mono at
+/// `SAMPLE_RATE`) are given, a pause also needs the segments either side to differ in level
+/// by `energy_shift_db`, which drops most mid-sentence breaths. Speakers alternate in
+/// order, so with two speakers every turn swaps S1 and S2.
+pub fn assign_speakers(
+    result: &mut TranscriptionResult,
+    options: &SpeakerOptions,
+    samples: Option<&[f32]>,
+) {
+    let mut speaker = 0;
+    let mut turns = 0;
+    let mut previous: Option<(f64, Option<f64>)> = None;
+    for segment in &mut result.segments {
+        let level = samples.and_then(|samples| level_db(samples, segment.start, segment.end));
+        if let Some((previous_end, previous_level)) = previous {
+            let paused = segment.start - previous_end > options.min_gap;
+            let shifted = match (previous_level, level) {
+                (Some(a), Some(b)) => (a - b).abs() >= options.energy_shift_db,
+                _ => true,
+            };
+            if paused && shifted && options.max_speakers > 1 {
+                speaker = (speaker + 1) % options.max_speakers;
+                turns += 1;
+            }
+        }
+        segment.speaker = Some(format!("S{}", speaker + 1));
+        previous = Some((segment.end, level));
+    }
+    result.speakers = Some(SpeakerTurns {
+        method: if samples.is_some() {
+            METHOD_GAP_ENERGY
+        } else {
+            METHOD_GAP
+        }
+        .to_string(),
+        max_speakers: options.max_speakers,
+        turns,
+        diarization_seconds: None,
+    });
+}
+
+/// The speaker talking longest between `start` and `end`, or None when no turn overlaps
+fn dominant_speaker(turns: &[DiarizedTurn], start: f64, end: f64) -> Option<&str> {
+    let mut totals: Vec<(&str, f64)> = Vec::new();
+    for turn in turns {
+        let overlap = end.min(turn.end) - start.max(turn.start);
+        if overlap <= 0.0 {
+            continue;
+        }
+        match totals
+            .iter_mut()
+            .find(|(speaker, _)| *speaker == turn.speaker)
+        {
+            Some((_, total)) => *total += overlap,
+            None => totals.push((&turn.speaker, overlap)),
+        }
+    }
+    totals
+        .into_iter()
+        .fold(None::<(&str, f64)>, |best, (speaker, total)| match best {
+            Some((_, best_total)) if best_total >= total => best,
+            _ => Some((speaker, total)),
+        })
+        .map(|(speaker, _)| speaker)
+}
+
+/// Label every segment and word with the diarized speaker it overlaps most.
+///
+/// The model's labels are renamed S1, S2, ... in order of first appearance, matching the
+/// heuristic's. Segments and words no turn overlaps are left unlabelled.
+pub fn assign_turns(
+    result: &mut TranscriptionResult,
+    turns: &[DiarizedTurn],
+    diarization_seconds: f64,
+) {
+    let mut names: Vec<String> = Vec::new();
+    let mut rename = |speaker: Option<&str>| {
+        let speaker = speaker?;
+        let index = match names.iter().position(|name| name == speaker) {
+            Some(index) => index,
+            None => {
+                names.push(speaker.to_string());
+                names.len() - 1
+            }
+        };
+        Some(format!("S{}", index + 1))
+    };
+
+    let mut changes = 0;
+    let mut previous: Option<String> = None;
+    for segment in &mut result.segments {
+        segment.speaker = rename(dominant_speaker(turns, segment.start, segment.end));
+        for word in &mut segment.words {
+            word.speaker = rename(dominant_speaker(turns, word.start, word.end));
+        }
+        if let Some(speaker) = &segment.speaker {
+            if previous
+                .as_ref()
+                .is_some_and(|previous| previous != speaker)
+            {
+                changes += 1;
+            }
+            previous = Some(speaker.clone());
+        }
+    }
+    result.speakers = Some(SpeakerTurns {
+        method: METHOD_PYANNOTE.to_string(),
+        max_speakers: names.len(),
+        turns: changes,
+        diarization_seconds: Some(diarization_seconds),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TranscriptionSegment, WordTiming};
+
+    fn result(times: &[(f64, f64)]) -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 10.0,
+            segments: times
+                .iter()
+                .map(|&(start, end)| TranscriptionSegment {
+                    start,
+                    end,
+                    text: "words".to_string(),
+                    no_speech_prob: 0.0,
+                    avg_logprob: 0.0,
+                    words: Vec::new(),
+                    speaker: None,
+                    tokens: None,
+                })
+                .collect(),
+            full_text: String::new(),
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn labels(result: &TranscriptionResult) -> Vec<&str> {
+        result
+            .segments
+            .iter()
+            .map(|s| s.speaker.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_gap_only_alternation() {
+        let times = [(0.0, 1.0), (1.2, 2.0), (4.0, 5.0), (7.0, 8.0), (8.5, 9.0)];
+        let mut two = result(&times);
+        assign_speakers(&mut two, &SpeakerOptions::default(), None);
+        assert_eq!(labels(&two), ["S1", "S1", "S2", "S1", "S1"]);
+        let turns = two.speakers.unwrap();
+        assert_eq!(turns.method, METHOD_GAP);
+        assert_eq!(turns.turns, 2);
+
+        let mut three = result(&times);
+        let options = SpeakerOptions {
+            max_speakers: 3,
+            ..Default::default()
+        };
+        assign_speakers(&mut three, &options, None);
+        assert_eq!(labels(&three), ["S1", "S1", "S2", "S3", "S3"]);
+
+        let mut one = result(&times);
+        let options = SpeakerOptions {
+            max_speakers: 1,
+            ..Default::default()
+        };
+        assign_speakers(&mut one, &options, None);
+        assert!(labels(&one).iter().all(|&label| label == "S1"));
+        assert!(options.validate().is_ok());
+        assert!(SpeakerOptions {
+            max_speakers: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_assign_diarized_turns_by_overlap() {
+        let mut result = result(&[(0.0, 4.0), (4.0, 6.0), (9.0, 9.5)]);
+        result.segments[0].words = vec![
+            WordTiming {
+                start: 0.0,
+                end: 2.0,
+                word: " Hi".to_string(),
+                probability: 0.9,
+                speaker: None,
+                tokens: None,
+            },
+            WordTiming {
+                start: 3.0,
+                end: 4.0,
+                word: " there".to_string(),
+                probability: 0.9,
+                speaker: None,
+                tokens: None,
+            },
+        ];
+        let turn = |start: f64, end: f64, speaker: &str| DiarizedTurn {
+            start,
+            end,
+            speaker: speaker.to_string(),
+        };
+        // SPEAKER_01 talks first, so becomes S1; the 4-6s segment is mostly SPEAKER_00
+        let turns = [
+            turn(0.0, 2.5, "SPEAKER_01"),
+            turn(2.5, 4.5, "SPEAKER_00"),
+            turn(4.5, 5.0, "SPEAKER_01"),
+            turn(5.0, 6.0, "SPEAKER_00"),
+        ];
+        assign_turns(&mut result, &turns, 1.5);
+
+        let speakers: Vec<Option<&str>> = result
+            .segments
+            .iter()
+            .map(|s| s.speaker.as_deref())
+            .collect();
+        assert_eq!(speakers, [Some("S1"), Some("S2"), None]);
+        let words = &result.segments[0].words;
+        assert_eq!(words[0].speaker.as_deref(), Some("S1"));
+        assert_eq!(words[1].speaker.as_deref(), Some("S2"));
+
+        let summary = result.speakers.unwrap();
+        assert_eq!(summary.method, METHOD_PYANNOTE);
+        assert_eq!((summary.max_speakers, summary.turns), (2, 1));
+        assert_eq!(summary.diarization_seconds, Some(1.5));
+    }
+
+    #[test]
+    fn test_energy_confirms_pauses() {
+        // Loud, then a pause into equally loud speech, then a pause into quiet speech
+        let rate = SAMPLE_RATE as usize;
+        let mut samples = vec![0.5f32; 6 * rate];
+        samples.extend(vec![0.05f32; 4 * rate]);
+        let mut result = result(&[(0.0, 2.0), (4.0, 6.0), (8.0, 10.0)]);
+        assign_speakers(&mut result, &SpeakerOptions::default(), Some(&samples));
+        assert_eq!(labels(&result), ["S1", "S1", "S2"]);
+        assert_eq!(result.speakers.unwrap().method, METHOD_GAP_ENERGY);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::batch::{FileOutcome, FileStatus};
+use crate::error::{Result, TranscriptionError};
+use crate::output;
+use crate::plan::{BatchPlan, PlannedAction};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Version written into new state files; files with any other version are rejected
+pub const STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "error", rename_all = "lowercase")]
+pub enum EntryStatus {
+    Pending,
+    Done,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    #[serde(flatten)]
+    pub status: EntryStatus,
+}
+
+/// Per-file progress of a batch, persisted with `--state-file` so an interrupted run can resume
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchState {
+    pub version: u32,
+    pub entries: Vec<StateEntry>,
+}
+
+impl Default for BatchState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl BatchState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a state file, or start empty when it doesn't exist yet.
+    ///
+    /// A file that can't be parsed is an error rather than a fresh start, so a damaged state
+    /// file never silently causes a 2,000-file batch to be redone.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Self::parse(&contents).map_err(|e| {
+            TranscriptionError::ConfigError(format!(
+                "State file {} is unreadable ({}); fix or delete it to start over",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    fn parse(contents: &str) -> std::result::Result<Self, String> {
+        let state: Self = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        if state.version != STATE_VERSION {
+            return Err(format!(
+                "unsupported version {} (expected {})",
+                state.version, STATE_VERSION
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Write the state to a temporary sibling and rename it over `path`, so a crash mid-write
+    /// leaves the previous state intact
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        output::write_atomic(path.as_ref(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn entry(&self, input: &Path) -> Option<&StateEntry> {
+        self.entries.iter().find(|entry| entry.input == input)
+    }
+
+    pub fn status(&self, input: &Path) -> Option<&EntryStatus> {
+        self.entry(input).map(|entry| &entry.status)
+    }
+
+    /// Insert or update the entry for `input`
+    pub fn set(&mut self, input: &Path, output: Option<PathBuf>, status: EntryStatus) {
+        match self.entries.iter_mut().find(|entry| entry.input == input) {
+            Some(entry) => {
+                entry.output = output;
+                entry.status = status;
+            }
+            None => self.entries.push(StateEntry {
+                input: input.to_path_buf(),
+                output,
+                status,
+            }),
+        }
+    }
+
+    /// Record a finished file; skipped outcomes leave the entry untouched
+    pub fn record(&mut self, outcome: &FileOutcome) {
+        let status = match &outcome.status {
+            FileStatus::Succeeded => EntryStatus::Done,
+            FileStatus::Failed(error) => EntryStatus::Failed(error.clone()),
+            FileStatus::Skipped(_) => return,
+        };
+        self.set(&outcome.input, outcome.output.clone(), status);
+    }
+
+    /// Skip files this state has already finished and register the rest as pending.
+    ///
+    /// Failed files are skipped too unless `retry_failed` is set.
+    pub fn apply_to_plan(&mut self, plan: &mut BatchPlan, retry_failed: bool) {
+        for file in &mut plan.files {
+            if file.action != PlannedAction::Transcribe {
+                continue;
+            }
+            match self.status(&file.input) {
+                Some(EntryStatus::Done) => file.action = PlannedAction::AlreadyDone,
+                Some(EntryStatus::Failed(_)) if !retry_failed => {
+                    file.action = PlannedAction::PreviouslyFailed
+                }
+                _ => self.set(&file.input, file.output.clone(), EntryStatus::Pending),
+            }
+        }
+    }
+
+    /// Counts of (pending, done, failed) entries
+    pub fn counts(&self) -> (usize, usize, usize) {
+        self.entries
+            .iter()
+            .fold((0, 0, 0), |(pending, done, failed), entry| {
+                match entry.status {
+                    EntryStatus::Pending => (pending + 1, done, failed),
+                    EntryStatus::Done => (pending, done + 1, failed),
+                    EntryStatus::Failed(_) => (pending, done, failed + 1),
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::PlannedFile;
+    use tempfile::tempdir;
+
+    fn planned(input: &str) -> PlannedFile {
+        PlannedFile {
+            input: input.into(),
+            output: None,
+            action: PlannedAction::Transcribe,
+            language: None,
+            duration: None,
+            duplicate_of: None,
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("batch.state.json");
+        assert_eq!(BatchState::load(&path).unwrap(), BatchState::new());
+
+        let mut state = BatchState::new();
+        state.set(Path::new("a.wav"), Some("a.json".into()), EntryStatus::Done);
+        state.set(
+            Path::new("b.wav"),
+            None,
+            EntryStatus::Failed("decode error".into()),
+        );
+        state.set(Path::new("c.wav"), None, EntryStatus::Pending);
+        state.save(&path).unwrap();
+
+        assert!(!dir.path().join("batch.state.json.tmp").exists());
+        let loaded = BatchState::load(&path).unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.counts(), (1, 1, 1));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["version"], STATE_VERSION);
+        assert_eq!(json["entries"][1]["status"], "failed");
+        assert_eq!(json["entries"][1]["error"], "decode error");
+    }
+
+    #[test]
+    fn test_truncated_state_file_is_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("batch.state.json");
+        let mut state = BatchState::new();
+        state.set(Path::new("a.wav"), None, EntryStatus::Done);
+        state.save(&path).unwrap();
+
+        let full = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, &full[..full.len() / 2]).unwrap();
+        let err = BatchState::load(&path).unwrap_err();
+        assert!(err.to_string().contains("batch.state.json"));
+        assert!(err.to_string().contains("delete it"));
+
+        std::fs::write(&path, "").unwrap();
+        assert!(BatchState::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        let err = BatchState::parse(r#"{"version": 99, "entries": []}"#).unwrap_err();
+        assert!(err.contains("unsupported version 99"));
+    }
+
+    #[test]
+    fn test_apply_to_plan_resumes() {
+        let mut state = BatchState::new();
+        state.set(Path::new("done.wav"), None, EntryStatus::Done);
+        state.set(
+            Path::new("failed.wav"),
+            None,
+            EntryStatus::Failed("boom".into()),
+        );
+
+        let mut plan = BatchPlan {
+            files: vec![
+                planned("done.wav"),
+                planned("failed.wav"),
+                planned("new.wav"),
+            ],
+        };
+        state.apply_to_plan(&mut plan, false);
+        let actions: Vec<_> = plan.files.iter().map(|f| f.action.clone()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                PlannedAction::AlreadyDone,
+                PlannedAction::PreviouslyFailed,
+                PlannedAction::Transcribe
+            ]
+        );
+        assert_eq!(
+            state.status(Path::new("new.wav")),
+            Some(&EntryStatus::Pending)
+        );
+
+        let mut plan = BatchPlan {
+            files: vec![planned("failed.wav")],
+        };
+        state.apply_to_plan(&mut plan, true);
+        assert_eq!(plan.files[0].action, PlannedAction::Transcribe);
+        assert_eq!(
+            state.status(Path::new("failed.wav")),
+            Some(&EntryStatus::Pending)
+        );
+    }
+
+    #[test]
+    fn test_record_outcomes() {
+        let mut state = BatchState::new();
+        state.record(&FileOutcome::failed("a.wav".into(), None, "boom"));
+        state.record(&FileOutcome::skipped("b.wav".into(), "fail-fast"));
+        assert_eq!(
+            state.status(Path::new("a.wav")),
+            Some(&EntryStatus::Failed("boom".into()))
+        );
+        assert_eq!(state.status(Path::new("b.wav")), None);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Speaking-rate and dead-air figures for a transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptStats {
+    /// Words by Unicode word boundaries, so unspaced scripts are counted too
+    pub word_count: usize,
+    /// Audio covered by at least one segment, overlaps counted once
+    pub speech_seconds: f64,
+    /// The rest of the duration
+    pub silence_seconds: f64,
+    /// Words per minute of speech, not of total duration
+    pub words_per_minute: f64,
+    pub average_segment_seconds: f64,
+    /// Longest stretch with no segment, including before the first and after the last
+    pub longest_silence_seconds: f64,
+    /// Where that stretch starts
+    pub longest_silence_start: f64,
+}
+
+/// Words in the segment texts, by Unicode word boundaries
+pub fn word_count(result: &TranscriptionResult) -> usize {
+    result
+        .segments
+        .iter()
+        .map(|segment| segment.text.unicode_words().count())
+        .sum()
+}
+
+impl TranscriptStats {
+    pub fn from_result(result: &TranscriptionResult) -> Self {
+        let word_count = word_count(result);
+
+        // Merge the segment intervals, clipped to the audio, so overlaps don't count twice
+        let duration = result.duration.max(0.0);
+        let mut intervals: Vec<(f64, f64)> = result
+            .segments
+            .iter()
+            .map(|segment| (segment.start.max(0.0), segment.end.min(duration)))
+            .filter(|(start, end)| end > start)
+            .collect();
+        intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = Vec::new();
+        for (start, end) in intervals {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let speech_seconds: f64 = merged.iter().map(|(start, end)| end - start).sum();
+        let mut longest_silence = (0.0, 0.0);
+        let mut cursor = 0.0;
+        for &(start, end) in merged.iter().chain([(duration, duration)].iter()) {
+            if start - cursor > longest_silence.1 {
+                longest_silence = (cursor, start - cursor);
+            }
+            cursor = end;
+        }
+
+        let segment_total: f64 = result
+            .segments
+            .iter()
+            .map(|segment| (segment.end - segment.start).max(0.0))
+            .sum();
+        Self {
+            word_count,
+            speech_seconds,
+            silence_seconds: (duration - speech_seconds).max(0.0),
+            words_per_minute: if speech_seconds > 0.0 {
+                word_count as f64 / (speech_seconds / 60.0)
+            } else {
+                0.0
+            },
+            average_segment_seconds: if result.segments.is_empty() {
+                0.0
+            } else {
+                segment_total / result.segments.len() as f64
+            },
+            longest_silence_seconds: longest_silence.1,
+            longest_silence_start: longest_silence.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TranscriptionSegment;
+
+    fn result(duration: f64, segments: &[(f64, f64, &str)]) -> TranscriptionResult {
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration,
+            segments: segments
+                .iter()
+                .map(|&(start, end, text)| TranscriptionSegment {
+                    start,
+                    end,
+                    text: text.to_string(),
+                    no_speech_prob: 0.0,
+                    avg_logprob: 0.0,
+                    words: Vec::new(),
+                    speaker: None,
+                    tokens: None,
+                })
+                .collect(),
+            full_text: String::new(),
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = result(
+            60.0,
+            &[
+                (2.0, 12.0, " Hello there, it's a fine day."),
+                (20.0, 30.0, " Grüße — aus Köln!"),
+            ],
+        )
+        .stats();
+        // Punctuation such as the dash isn't a word
+        assert_eq!(stats.word_count, 6 + 3);
+        assert_eq!(stats.speech_seconds, 20.0);
+        assert_eq!(stats.silence_seconds, 40.0);
+        assert_eq!(stats.words_per_minute, 27.0);
+        assert_eq!(stats.average_segment_seconds, 10.0);
+        assert_eq!(stats.longest_silence_seconds, 30.0);
+        assert_eq!(stats.longest_silence_start, 30.0);
+    }
+
+    #[test]
+    fn test_stats_overlapping_segments() {
+        // Overlaps and a segment running past the end must not make silence negative
+        let stats = result(
+            10.0,
+            &[(0.0, 6.0, "a b"), (4.0, 11.0, "c"), (5.0, 5.5, "d")],
+        )
+        .stats();
+        assert_eq!(stats.speech_seconds, 10.0);
+        assert_eq!(stats.silence_seconds, 0.0);
+        assert_eq!(stats.longest_silence_seconds, 0.0);
+    }
+
+    #[test]
+    fn test_stats_empty_result() {
+        let stats = result(0.0, &[]).stats();
+        assert_eq!(stats.word_count, 0);
+        assert_eq!(stats.speech_seconds, 0.0);
+        assert_eq!(stats.silence_seconds, 0.0);
+        assert_eq!(stats.words_per_minute, 0.0);
+        assert_eq!(stats.average_segment_seconds, 0.0);
+
+        let stats = result(5.0, &[]).stats();
+        assert_eq!(stats.silence_seconds, 5.0);
+        assert_eq!(stats.longest_silence_seconds, 5.0);
+    }
+}
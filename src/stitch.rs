@@ -0,0 +1,309 @@
+use crate::align;
+use crate::error::{Result, TranscriptionError};
+use crate::manifest::split_csv_line;
+use crate::merge::{MergedPart, MergedTranscript};
+use crate::probe;
+use crate::types::{TranscriptionResult, TranscriptionSegment};
+use std::path::{Path, PathBuf};
+
+/// One chunk of a longer recording, from a `stitch` manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkEntry {
+    /// 1-based line number in the manifest, for error reporting
+    pub line: usize,
+    pub path: PathBuf,
+    /// Where the chunk starts in the original recording, in seconds
+    pub offset: Option<f64>,
+}
+
+/// Parse a stitch manifest of `file,offset_seconds` rows. The offset may be left out when
+/// offsets are to be derived from durations.
+///
+/// Blank lines and `#` comments are ignored, as is a `file,offset` header. Relative paths
+/// are resolved against `base_dir`.
+pub fn parse_chunk_manifest(contents: &str, base_dir: &Path) -> Result<Vec<ChunkEntry>> {
+    let mut entries = Vec::new();
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(trimmed).map_err(|e| chunk_error(line, &e))?;
+        if entries.is_empty()
+            && fields
+                .first()
+                .is_some_and(|f| f.trim().eq_ignore_ascii_case("file"))
+        {
+            continue;
+        }
+        if fields.len() > 2 {
+            return Err(chunk_error(
+                line,
+                &format!(
+                    "expected file,offset_seconds, found {} columns",
+                    fields.len()
+                ),
+            ));
+        }
+        let file = fields[0].trim();
+        if file.is_empty() {
+            return Err(chunk_error(line, "missing file"));
+        }
+        let offset = match fields.get(1).map(|f| f.trim()).filter(|f| !f.is_empty()) {
+            Some(value) => Some(
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|offset| offset.is_finite() && *offset >= 0.0)
+                    .ok_or_else(|| chunk_error(line, &format!("invalid offset '{}'", value)))?,
+            ),
+            None => None,
+        };
+        let path = PathBuf::from(file);
+        entries.push(ChunkEntry {
+            line,
+            path: if path.is_absolute() {
+                path
+            } else {
+                base_dir.join(path)
+            },
+            offset,
+        });
+    }
+    if entries.is_empty() {
+        return Err(TranscriptionError::ConfigError(
+            "stitch manifest lists no files".to_string(),
+        ));
+    }
+    Ok(entries)
+}
+
+fn chunk_error(line: usize, message: &str) -> TranscriptionError {
+    TranscriptionError::ConfigError(format!("stitch manifest line {}: {}", line, message))
+}
+
+/// When a segment repeats one from the end of the previous chunk, where chunks were cut with
+/// overlap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlapOptions {
+    /// Most the two segments' start times may differ, in seconds
+    pub max_start_gap: f64,
+    /// Least `align::word_agreement` between the two texts
+    pub min_agreement: f64,
+}
+
+impl Default for OverlapOptions {
+    fn default() -> Self {
+        Self {
+            max_start_gap: 1.5,
+            min_agreement: 0.6,
+        }
+    }
+}
+
+impl OverlapOptions {
+    fn is_repeat(&self, earlier: &TranscriptionSegment, later: &TranscriptionSegment) -> bool {
+        (earlier.start - later.start).abs() <= self.max_start_gap
+            && align::word_agreement(&earlier.text, &later.text) >= self.min_agreement
+    }
+}
+
+/// Put chunk transcripts back onto the original recording's timeline.
+///
+/// Each chunk's segments are shifted by its offset, or, without offsets, each chunk starts
+/// where the previous one ended by its probed duration (falling back to the duration
+/// faster-whisper reported). Chunks are kept in the order given.
+///
+/// A segment repeating one of the previous chunk's by `overlap` is dropped. Of the two, the
+/// one with more words is kept, since the copy at a chunk's cut edge is usually truncated.
+pub fn stitch(
+    chunks: Vec<(PathBuf, Option<f64>, TranscriptionResult)>,
+    overlap: &OverlapOptions,
+) -> MergedTranscript {
+    let mut next_offset = 0.0;
+    let mut parts = Vec::with_capacity(chunks.len());
+    let mut offset_results = Vec::with_capacity(chunks.len());
+    for (input, offset, result) in chunks {
+        let duration = probe::probe_duration(&input).unwrap_or(result.duration);
+        let offset = offset.unwrap_or(next_offset);
+        parts.push(MergedPart {
+            input,
+            offset,
+            duration,
+            language: result.language.clone(),
+        });
+        offset_results.push((offset, result));
+        next_offset = offset + duration;
+    }
+
+    // Segments in original time, with the index of the chunk they came from
+    let mut kept: Vec<(usize, TranscriptionSegment)> = Vec::new();
+    for (chunk, (offset, result)) in offset_results.iter().enumerate() {
+        for segment in &TranscriptionResult::merge(&[(*offset, result.clone())]).segments {
+            let repeat = kept
+                .iter()
+                .enumerate()
+                .rev()
+                .take_while(|(_, (from, _))| from + 1 >= chunk)
+                .find(|(_, (from, earlier))| {
+                    from + 1 == chunk && overlap.is_repeat(earlier, segment)
+                })
+                .map(|(index, _)| index);
+            match repeat {
+                Some(index) => {
+                    if word_count(segment) > word_count(&kept[index].1) {
+                        kept[index] = (chunk, segment.clone());
+                    }
+                }
+                None => kept.push((chunk, segment.clone())),
+            }
+        }
+    }
+
+    let mut result = TranscriptionResult::merge(&offset_results);
+    result.segments = kept.into_iter().map(|(_, segment)| segment).collect();
+    result.segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    result.full_text = TranscriptionResult::text_from_segments(&result.segments, None);
+    MergedTranscript { parts, result }
+}
+
+fn word_count(segment: &TranscriptionSegment) -> usize {
+    segment.text.split_whitespace().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(duration: f64, segments: &[(f64, f64, &str)]) -> TranscriptionResult {
+        let segments: Vec<TranscriptionSegment> = segments
+            .iter()
+            .map(|&(start, end, text)| TranscriptionSegment {
+                start,
+                end,
+                text: format!(" {}", text),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.2,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            })
+            .collect();
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration,
+            full_text: TranscriptionResult::text_from_segments(&segments, None),
+            segments,
+            transcription_time: duration / 10.0,
+            real_time_factor: 10.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_manifest_rows() {
+        let entries = parse_chunk_manifest(
+            "file,offset\n# hour one\nhour1.wav,0\n\"hour 2.wav\", 3600\n/abs/hour3.json\n",
+            Path::new("/rec"),
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].path, PathBuf::from("/rec/hour 2.wav"));
+        assert_eq!(entries[1].offset, Some(3600.0));
+        assert_eq!(entries[2].path, PathBuf::from("/abs/hour3.json"));
+        assert_eq!(entries[2].offset, None);
+
+        let err = parse_chunk_manifest("a.wav,-5\n", Path::new(".")).unwrap_err();
+        assert!(
+            err.to_string().contains("line 1: invalid offset '-5'"),
+            "{}",
+            err
+        );
+        assert!(parse_chunk_manifest("a.wav,1,2\n", Path::new(".")).is_err());
+        assert!(parse_chunk_manifest("# nothing\n", Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_offsets_given_or_derived() {
+        let stitched = stitch(
+            vec![
+                (
+                    "h1.wav".into(),
+                    Some(0.0),
+                    chunk(3600.0, &[(10.0, 12.0, "One.")]),
+                ),
+                (
+                    "h2.wav".into(),
+                    Some(3600.0),
+                    chunk(3600.0, &[(5.0, 8.0, "Two.")]),
+                ),
+            ],
+            &OverlapOptions::default(),
+        );
+        assert_eq!(stitched.result.segments[1].start, 3605.0);
+        assert_eq!(stitched.result.duration, 7200.0);
+        assert_eq!(stitched.result.full_text, "One. Two.");
+
+        // Derived from the reported durations when the files can't be probed
+        let stitched = stitch(
+            vec![
+                (
+                    "h1.wav".into(),
+                    None,
+                    chunk(3590.0, &[(10.0, 12.0, "One.")]),
+                ),
+                ("h2.wav".into(), None, chunk(3600.0, &[(5.0, 8.0, "Two.")])),
+            ],
+            &OverlapOptions::default(),
+        );
+        assert_eq!(stitched.parts[1].offset, 3590.0);
+        assert_eq!(stitched.result.segments[1].start, 3595.0);
+    }
+
+    #[test]
+    fn test_overlap_keeps_one_copy_of_boundary_segments() {
+        // Chunks of 60s cut with 10s of overlap: the second starts at 50s
+        let first = chunk(
+            60.0,
+            &[
+                (40.0, 48.0, "So that was the first part."),
+                (51.0, 60.0, "And then we"),
+            ],
+        );
+        let second = chunk(
+            60.0,
+            &[
+                (0.5, 1.5, "first part."),
+                (1.2, 6.0, "And then we went home early."),
+                (7.0, 9.0, "The end."),
+            ],
+        );
+        let stitched = stitch(
+            vec![
+                ("a.wav".into(), Some(0.0), first),
+                ("b.wav".into(), Some(50.0), second),
+            ],
+            &OverlapOptions::default(),
+        );
+        let texts: Vec<&str> = stitched
+            .result
+            .segments
+            .iter()
+            .map(|segment| segment.text.trim())
+            .collect();
+        // The truncated copy at the first chunk's edge gives way to the complete one; the
+        // fragment far from any similar segment stays
+        assert_eq!(
+            texts,
+            [
+                "So that was the first part.",
+                "first part.",
+                "And then we went home early.",
+                "The end."
+            ]
+        );
+        assert_eq!(stitched.result.segments[2].start, 51.2);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::{Result, TranscriptionError};
+use crate::output::OutputFormat;
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Naming used when no `--output-template` is given
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "{stem}_transcription.{format}";
+
+/// Placeholder names accepted in output templates
+pub const PLACEHOLDERS: &[&str] = &["stem", "ext", "lang", "model", "date", "format"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Stem,
+    Ext,
+    Lang,
+    Model,
+    Date,
+    Format,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// A parsed output file name template such as `{stem}.{lang}.{format}`.
+///
+/// Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+/// Values substituted into an output template for one input file
+#[derive(Debug, Clone)]
+pub struct TemplateContext<'a> {
+    pub input: &'a Path,
+    pub format: OutputFormat,
+    /// Forced or per-file language; `auto` is substituted when unknown
+    pub language: Option<&'a str>,
+    pub model: &'a str,
+    /// `YYYY-MM-DD`
+    pub date: &'a str,
+}
+
+impl Default for OutputTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_OUTPUT_TEMPLATE).expect("default template is valid")
+    }
+}
+
+impl OutputTemplate {
+    /// Parse a template, rejecting unknown placeholders and unbalanced braces
+    pub fn parse(template: &str) -> Result<Self> {
+        let error = |message: String| {
+            TranscriptionError::ConfigError(format!(
+                "Invalid output template '{}': {}",
+                template, message
+            ))
+        };
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(error(format!("unclosed '{{{}'", name))),
+                        }
+                    }
+                    let placeholder = match name.as_str() {
+                        "stem" => Placeholder::Stem,
+                        "ext" => Placeholder::Ext,
+                        "lang" => Placeholder::Lang,
+                        "model" => Placeholder::Model,
+                        "date" => Placeholder::Date,
+                        "format" => Placeholder::Format,
+                        other => {
+                            return Err(error(format!(
+                                "unknown placeholder {{{}}} (expected one of {})",
+                                other,
+                                PLACEHOLDERS
+                                    .iter()
+                                    .map(|p| format!("{{{}}}", p))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )))
+                        }
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                }
+                '}' => return Err(error("unmatched '}'".to_string())),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        if parts.is_empty() {
+            return Err(error("template is empty".to_string()));
+        }
+
+        Ok(Self {
+            source: template.to_string(),
+            parts,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Expand for one input. `{stem}` and `{ext}` keep the input's bytes, so names that
+    /// aren't valid UTF-8 survive unchanged.
+    pub fn expand(&self, context: &TemplateContext<'_>) -> OsString {
+        let mut out = OsString::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push(text),
+                Part::Placeholder(Placeholder::Stem) => {
+                    out.push(context.input.file_stem().unwrap_or_default())
+                }
+                Part::Placeholder(Placeholder::Ext) => {
+                    out.push(context.input.extension().unwrap_or_default())
+                }
+                Part::Placeholder(Placeholder::Lang) => {
+                    out.push(context.language.unwrap_or("auto"))
+                }
+                Part::Placeholder(Placeholder::Model) => out.push(context.model),
+                Part::Placeholder(Placeholder::Date) => out.push(context.date),
+                Part::Placeholder(Placeholder::Format) => out.push(context.format.extension()),
+            }
+        }
+        out
+    }
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`
+pub fn today_utc() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    civil_date(seconds)
+}
+
+/// Convert seconds since the Unix epoch to an RFC 3339 UTC time, `YYYY-MM-DDTHH:MM:SSZ`
+pub(crate) fn rfc3339(unix_seconds: u64) -> String {
+    let of_day = unix_seconds % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        civil_date(unix_seconds),
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60
+    )
+}
+
+/// Convert seconds since the Unix epoch to a `YYYY-MM-DD` date (proleptic Gregorian, UTC)
+pub(crate) fn civil_date(unix_seconds: u64) -> String {
+    // Howard Hinnant's days-from-civil algorithm, inverted
+    let days = (unix_seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(input: &Path) -> TemplateContext<'_> {
+        TemplateContext {
+            input,
+            format: OutputFormat::Srt,
+            language: Some("de"),
+            model: "small",
+            date: "2024-03-09",
+        }
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let input = Path::new("/audio/interview.final.m4a");
+        let template =
+            OutputTemplate::parse("{date}/{stem}.{lang}.{model}.{ext}.{format}").unwrap();
+        assert_eq!(
+            template.expand(&context(input)),
+            "2024-03-09/interview.final.de.small.m4a.srt"
+        );
+
+        let default = OutputTemplate::default();
+        assert_eq!(
+            default.expand(&context(input)),
+            "interview.final_transcription.srt"
+        );
+
+        let unknown_language = TemplateContext {
+            language: None,
+            ..context(input)
+        };
+        assert_eq!(
+            OutputTemplate::parse("{stem}.{lang}")
+                .unwrap()
+                .expand(&unknown_language),
+            "interview.final.auto"
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let err = OutputTemplate::parse("{stem}.{speaker}.json").unwrap_err();
+        assert!(err.to_string().contains("unknown placeholder {speaker}"));
+        assert!(OutputTemplate::parse("{stem").is_err());
+        assert!(OutputTemplate::parse("stem}").is_err());
+        assert!(OutputTemplate::parse("").is_err());
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        let template = OutputTemplate::parse("{{{stem}}}.txt").unwrap();
+        assert_eq!(template.expand(&context(Path::new("a.wav"))), "{a}.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_expand_keeps_non_utf8_stem() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let input = Path::new(OsStr::from_bytes(b"/audio/caf\xe9.wav"));
+        let expanded = OutputTemplate::default().expand(&context(input));
+        assert_eq!(expanded.as_bytes(), b"caf\xe9_transcription.srt");
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(951_782_400), "2000-02-29");
+        assert_eq!(civil_date(1_709_942_399), "2024-03-08");
+        assert_eq!(civil_date(1_709_942_400), "2024-03-09");
+        assert_eq!(rfc3339(1_709_994_301), "2024-03-09T14:25:01Z");
+    }
+}
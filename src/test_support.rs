@@ -0,0 +1,259 @@
+//! Fixtures shared by the unit tests: a scriptable `TranscriptionBackend` and a builder for
+//! `TranscriptionResult`s
+
+use crate::backend::{TranscriptionBackend, SAMPLE_RATE};
+use crate::error::Result;
+use crate::types::{
+    ModelConfig, TranscriptionOptions, TranscriptionResult, TranscriptionSegment, WordTiming,
+};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+type Respond = Box<dyn Fn(&Path) -> Result<TranscriptionResult> + Send + Sync>;
+type BeforeEach = Box<dyn Fn(&Path, usize) + Send + Sync>;
+
+/// A backend that answers every file with the same one-segment "mock" transcript unless
+/// told otherwise, loading on first use and counting loads, calls and unloads.
+///
+/// Samples are answered like a file with an empty name, lasting as long as the samples.
+pub(crate) struct MockBackend {
+    config: ModelConfig,
+    options: TranscriptionOptions,
+    respond: Respond,
+    before_each: Option<BeforeEach>,
+    /// Transcription times still to report, last first; the final one repeats
+    times: Mutex<Vec<f64>>,
+    loaded: AtomicBool,
+    pub loads: Arc<AtomicUsize>,
+    pub calls: Arc<AtomicUsize>,
+    pub unloads: Arc<AtomicUsize>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        let result = ResultBuilder::new()
+            .segment(segment(0.0, 1.0, "mock"))
+            .transcription_time(0.0)
+            .build();
+        Self {
+            config: ModelConfig::new("tiny", "cpu", "float32"),
+            options: TranscriptionOptions::default(),
+            respond: Box::new(move |_| Ok(result.clone())),
+            before_each: None,
+            times: Mutex::new(Vec::new()),
+            loaded: AtomicBool::new(false),
+            loads: Arc::new(AtomicUsize::new(0)),
+            calls: Arc::new(AtomicUsize::new(0)),
+            unloads: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn with_config(mut self, config: ModelConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Answer every file with `result`
+    pub fn with_result(self, result: TranscriptionResult) -> Self {
+        self.responding(move |_| Ok(result.clone()))
+    }
+
+    /// Answer each file with whatever `respond` makes of its path
+    pub fn responding(
+        mut self,
+        respond: impl Fn(&Path) -> Result<TranscriptionResult> + Send + Sync + 'static,
+    ) -> Self {
+        self.respond = Box::new(respond);
+        self
+    }
+
+    /// Call `f` with each file and how many transcriptions have started, this one included,
+    /// before answering it
+    pub fn before_each(mut self, f: impl Fn(&Path, usize) + Send + Sync + 'static) -> Self {
+        self.before_each = Some(Box::new(f));
+        self
+    }
+
+    /// Report the next of `times` as each transcription's time, then the last one again,
+    /// as a machine heating up would
+    pub fn with_times(self, times: &[f64]) -> Self {
+        *self.times.lock().unwrap() = times.iter().rev().copied().collect();
+        self
+    }
+
+    /// Start out loaded, as a model handed over by a loader would be
+    pub fn loaded(self) -> Self {
+        self.loaded.store(true, Ordering::SeqCst);
+        self
+    }
+
+    /// Count unloads in `unloads`, shared with other mocks
+    pub fn with_unloads(mut self, unloads: Arc<AtomicUsize>) -> Self {
+        self.unloads = unloads;
+        self
+    }
+
+    /// Answer `path`, lasting `duration` seconds if given
+    fn answer(&self, path: &Path, duration: Option<f64>) -> Result<TranscriptionResult> {
+        self.load()?;
+        let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(before_each) = &self.before_each {
+            before_each(path, calls);
+        }
+        let mut result = (self.respond)(path)?;
+        if let Some(duration) = duration {
+            result.duration = duration;
+        }
+        let mut times = self.times.lock().unwrap();
+        let time = if times.len() > 1 {
+            times.pop()
+        } else {
+            times.first().copied()
+        };
+        if let Some(time) = time {
+            result.transcription_time = time;
+            result.real_time_factor = result.duration / time;
+        }
+        Ok(result)
+    }
+}
+
+impl TranscriptionBackend for MockBackend {
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    fn load(&self) -> Result<()> {
+        if !self.loaded.swap(true, Ordering::SeqCst) {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.loaded.load(Ordering::SeqCst)
+    }
+
+    fn transcribe_path(
+        &self,
+        audio_path: &Path,
+        _options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.answer(audio_path, None)
+    }
+
+    fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        _options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let duration = (!samples.is_empty()).then(|| samples.len() as f64 / SAMPLE_RATE as f64);
+        self.answer(Path::new(""), duration)
+    }
+
+    fn device_info(&self) -> Result<String> {
+        Ok("mock".to_string())
+    }
+
+    fn unload(&self) -> bool {
+        self.unloads.fetch_add(1, Ordering::SeqCst);
+        self.loaded.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Builds a `TranscriptionResult` in English whose full text and duration follow from its
+/// segments unless set
+pub(crate) struct ResultBuilder {
+    result: TranscriptionResult,
+    duration: Option<f64>,
+}
+
+impl ResultBuilder {
+    pub fn new() -> Self {
+        Self {
+            result: TranscriptionResult {
+                language: "en".to_string(),
+                language_probability: 0.98,
+                transcription_time: 1.0,
+                ..Default::default()
+            },
+            duration: None,
+        }
+    }
+
+    pub fn language_probability(mut self, probability: f64) -> Self {
+        self.result.language_probability = probability;
+        self
+    }
+
+    pub fn segment(mut self, segment: TranscriptionSegment) -> Self {
+        self.result.segments.push(segment);
+        self
+    }
+
+    pub fn segments(mut self, segments: Vec<TranscriptionSegment>) -> Self {
+        self.result.segments.extend(segments);
+        self
+    }
+
+    /// Audio length in seconds, instead of the end of the last segment
+    pub fn duration(mut self, duration: f64) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Time taken to transcribe, from which the real-time factor follows
+    pub fn transcription_time(mut self, seconds: f64) -> Self {
+        self.result.transcription_time = seconds;
+        self
+    }
+
+    pub fn full_text(mut self, text: impl Into<String>) -> Self {
+        self.result.full_text = text.into();
+        self
+    }
+
+    pub fn build(self) -> TranscriptionResult {
+        let mut result = self.result;
+        result.duration = self
+            .duration
+            .unwrap_or_else(|| result.segments.last().map_or(0.0, |s| s.end));
+        if result.full_text.is_empty() {
+            result.full_text = TranscriptionResult::text_from_segments(&result.segments, None);
+        }
+        if result.transcription_time > 0.0 {
+            result.real_time_factor = result.duration / result.transcription_time;
+        }
+        result
+    }
+}
+
+/// A confident segment without words
+pub(crate) fn segment(start: f64, end: f64, text: &str) -> TranscriptionSegment {
+    TranscriptionSegment {
+        start,
+        end,
+        text: text.to_string(),
+        no_speech_prob: 0.01,
+        avg_logprob: -0.2,
+        words: vec![],
+        speaker: None,
+        tokens: None,
+    }
+}
+
+pub(crate) fn word(word: &str, start: f64, end: f64) -> WordTiming {
+    WordTiming {
+        start,
+        end,
+        word: word.to_string(),
+        probability: 0.9,
+        speaker: None,
+        tokens: None,
+    }
+}
@@ -0,0 +1,231 @@
+//! Re-timing one small transcription between benchmark configs, to notice when the machine
+//! slows down as it heats up and the configs measured after stop being comparable
+
+use crate::backend::TranscriptionBackend;
+use crate::error::Result;
+use crate::ffmpeg::FfmpegDecoder;
+use crate::types::ModelConfig;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Seconds from the start of the benchmark audio that the reference workload transcribes
+pub const DEFAULT_REFERENCE_SECONDS: f64 = 10.0;
+/// How much slower than its first time, in percent, the reference may run before throttling
+/// is suspected; a little above how far it varies on a machine that isn't hot
+pub const DEFAULT_THRESHOLD_PERCENT: f64 = 15.0;
+/// Most cooldowns to wait through before a config, after which it runs however slow
+pub const MAX_COOLDOWNS: usize = 10;
+
+/// The reference workload and how far it may slow down
+#[derive(Debug, Clone)]
+pub struct ThrottleCheck {
+    /// The reference model: tiny on CPU by default, which loads quickly and runs anywhere
+    pub model: ModelConfig,
+    /// Seconds of the audio it transcribes
+    pub seconds: f64,
+    pub threshold_percent: f64,
+    /// While the reference runs slow before a config, pause this long and time it again,
+    /// up to `MAX_COOLDOWNS` times; `None` carries straight on
+    pub cooldown: Option<Duration>,
+    /// Decodes the start of the audio for the reference workload
+    pub decoder: FfmpegDecoder,
+}
+
+impl Default for ThrottleCheck {
+    fn default() -> Self {
+        Self {
+            model: ModelConfig::new("tiny", "cpu", "int8"),
+            seconds: DEFAULT_REFERENCE_SECONDS,
+            threshold_percent: DEFAULT_THRESHOLD_PERCENT,
+            cooldown: None,
+            decoder: FfmpegDecoder::new(),
+        }
+    }
+}
+
+impl ThrottleCheck {
+    pub fn is_throttled(&self, slowdown_percent: f64) -> bool {
+        slowdown_percent > self.threshold_percent
+    }
+
+    /// The start of `audio_path` that the reference workload transcribes
+    pub fn reference_samples(&self, audio_path: &Path) -> Result<Vec<f32>> {
+        self.decoder.decode_head(audio_path, self.seconds)
+    }
+}
+
+/// Percent `time` is slower than `baseline`; zero when it's no slower or there's no baseline
+pub fn slowdown_percent(baseline: f64, time: f64) -> f64 {
+    if baseline > 0.0 && time.is_finite() {
+        ((time - baseline) / baseline * 100.0).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// One timing of the reference workload between configs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// The slowdown when first timed, which says how hot the config before it left the
+    /// machine
+    pub first_percent: f64,
+    /// The slowdown after any cooldowns, which the config after it starts at
+    pub settled_percent: f64,
+    pub cooldowns: usize,
+}
+
+/// Times the reference workload on a model kept loaded for the whole benchmark
+pub struct ThrottleMonitor {
+    check: ThrottleCheck,
+    reference: Box<dyn TranscriptionBackend>,
+    samples: Vec<f32>,
+    baseline: Option<f64>,
+}
+
+impl ThrottleMonitor {
+    pub fn new(
+        check: ThrottleCheck,
+        reference: Box<dyn TranscriptionBackend>,
+        samples: Vec<f32>,
+    ) -> Self {
+        Self {
+            check,
+            reference,
+            samples,
+            baseline: None,
+        }
+    }
+
+    pub fn check(&self) -> &ThrottleCheck {
+        &self.check
+    }
+
+    /// Seconds the first reading took, once there is one
+    pub fn baseline(&self) -> Option<f64> {
+        self.baseline
+    }
+
+    fn time(&self) -> Result<f64> {
+        let result = self
+            .reference
+            .transcribe_samples(&self.samples, self.reference.options())?;
+        Ok(result.transcription_time)
+    }
+
+    /// Time the reference workload against the first reading, which sets the baseline; that
+    /// one runs it twice and keeps the second, leaving out one-time setup. With `cool_down`,
+    /// while it runs too slow, pause for the check's cooldown and time it again.
+    pub async fn read(&mut self, cool_down: bool) -> Result<Reading> {
+        let Some(baseline) = self.baseline else {
+            self.time()?;
+            let time = self.time()?;
+            info!("Reference workload: {:.2}s", time);
+            self.baseline = Some(time);
+            return Ok(Reading {
+                first_percent: 0.0,
+                settled_percent: 0.0,
+                cooldowns: 0,
+            });
+        };
+        let time = self.time()?;
+        let first_percent = slowdown_percent(baseline, time);
+        info!(
+            "Reference workload: {:.2}s, {:.0}% slower than at first",
+            time, first_percent
+        );
+        let mut reading = Reading {
+            first_percent,
+            settled_percent: first_percent,
+            cooldowns: 0,
+        };
+        let cooldown = self.check.cooldown.filter(|_| cool_down);
+        while self.check.is_throttled(reading.settled_percent) {
+            let Some(pause) = cooldown.filter(|_| reading.cooldowns < MAX_COOLDOWNS) else {
+                break;
+            };
+            info!("Cooling down for {:.0}s", pause.as_secs_f64());
+            tokio::time::sleep(pause).await;
+            reading.cooldowns += 1;
+            reading.settled_percent = slowdown_percent(baseline, self.time()?);
+        }
+        if self.check.is_throttled(reading.settled_percent) {
+            warn!(
+                "Reference workload runs {:.0}% slower than at first; throttling suspected",
+                reading.settled_percent
+            );
+        } else if reading.cooldowns > 0 {
+            info!(
+                "Reference workload recovered after {} cooldown(s)",
+                reading.cooldowns
+            );
+        }
+        Ok(reading)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockBackend;
+
+    fn scripted_monitor(times: &[f64], cooldown: Option<Duration>) -> ThrottleMonitor {
+        let check = ThrottleCheck {
+            cooldown,
+            ..ThrottleCheck::default()
+        };
+        let backend = MockBackend::new()
+            .with_config(ModelConfig::new("tiny", "cpu", "int8"))
+            .with_times(times);
+        ThrottleMonitor::new(check, Box::new(backend), vec![0.0; 160])
+    }
+
+    #[test]
+    fn test_slowdown_percent() {
+        assert_eq!(slowdown_percent(2.0, 2.5), 25.0);
+        assert_eq!(slowdown_percent(2.0, 1.5), 0.0);
+        assert_eq!(slowdown_percent(0.0, 1.0), 0.0);
+        assert_eq!(slowdown_percent(1.0, f64::NAN), 0.0);
+
+        let check = ThrottleCheck::default();
+        assert!(!check.is_throttled(DEFAULT_THRESHOLD_PERCENT));
+        assert!(check.is_throttled(DEFAULT_THRESHOLD_PERCENT + 1.0));
+    }
+
+    #[tokio::test]
+    async fn test_readings_against_the_first() {
+        let mut monitor = scripted_monitor(&[3.0, 1.0, 1.1, 1.5], Some(Duration::from_millis(1)));
+        let first = monitor.read(true).await.unwrap();
+        assert_eq!(first.settled_percent, 0.0);
+        assert_eq!(monitor.baseline(), Some(1.0));
+
+        let within = monitor.read(true).await.unwrap();
+        assert!((within.first_percent - 10.0).abs() < 1e-9);
+        assert_eq!(within.cooldowns, 0);
+
+        // Without cooling down it carries straight on
+        let slow = monitor.read(false).await.unwrap();
+        assert_eq!(slow.first_percent, 50.0);
+        assert_eq!(slow.settled_percent, 50.0);
+        assert_eq!(slow.cooldowns, 0);
+        assert_eq!(monitor.baseline(), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_until_recovered() {
+        let mut monitor =
+            scripted_monitor(&[3.0, 1.0, 1.5, 1.3, 1.05], Some(Duration::from_millis(1)));
+        monitor.read(true).await.unwrap();
+        let reading = monitor.read(true).await.unwrap();
+        assert_eq!(reading.first_percent, 50.0);
+        assert!((reading.settled_percent - 5.0).abs() < 1e-9);
+        assert_eq!(reading.cooldowns, 2);
+
+        // A machine that never recovers is waited on only so long
+        let mut monitor = scripted_monitor(&[1.0, 1.0, 2.0], Some(Duration::from_millis(1)));
+        monitor.read(true).await.unwrap();
+        let reading = monitor.read(true).await.unwrap();
+        assert_eq!(reading.cooldowns, MAX_COOLDOWNS);
+        assert_eq!(reading.settled_percent, 100.0);
+    }
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A stage of transcribing one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Checking the file and decoding its audio, including language detection
+    Decode,
+    /// Waiting for the model: another file holding it, or loading it
+    ModelWait,
+    /// Decoding segments with the model
+    Inference,
+    /// Speaker labels, replacements and redaction
+    PostProcessing,
+    /// Rendering and writing the outputs
+    Output,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Decode,
+        Phase::ModelWait,
+        Phase::Inference,
+        Phase::PostProcessing,
+        Phase::Output,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Decode => "decode",
+            Phase::ModelWait => "model wait",
+            Phase::Inference => "inference",
+            Phase::PostProcessing => "post-processing",
+            Phase::Output => "output",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where one file's time went, in seconds. Backends fill in the phases they see; the phases
+/// after transcription are added by whoever post-processes and writes the result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub decode: f64,
+    pub model_wait: f64,
+    pub inference: f64,
+    pub post_processing: f64,
+    pub output: f64,
+}
+
+impl PhaseTimings {
+    pub fn get(&self, phase: Phase) -> f64 {
+        match phase {
+            Phase::Decode => self.decode,
+            Phase::ModelWait => self.model_wait,
+            Phase::Inference => self.inference,
+            Phase::PostProcessing => self.post_processing,
+            Phase::Output => self.output,
+        }
+    }
+
+    /// Add `elapsed` to `phase`
+    pub fn add(&mut self, phase: Phase, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        match phase {
+            Phase::Decode => self.decode += seconds,
+            Phase::ModelWait => self.model_wait += seconds,
+            Phase::Inference => self.inference += seconds,
+            Phase::PostProcessing => self.post_processing += seconds,
+            Phase::Output => self.output += seconds,
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        Phase::ALL.iter().map(|&phase| self.get(phase)).sum()
+    }
+
+    /// The phase that took longest, with its time; the earlier phase wins a tie
+    pub fn dominant(&self) -> (Phase, f64) {
+        Phase::ALL
+            .iter()
+            .map(|&phase| (phase, self.get(phase)))
+            .fold((Phase::Decode, f64::NEG_INFINITY), |best, current| {
+                if current.1 > best.1 {
+                    current
+                } else {
+                    best
+                }
+            })
+    }
+}
+
+/// Percentiles of one phase over the files of a batch, in seconds, by nearest rank
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PhasePercentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl PhasePercentiles {
+    /// None when there are no values
+    pub fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let rank = |q: f64| {
+            let index = (q * sorted.len() as f64).ceil() as usize;
+            sorted[index.clamp(1, sorted.len()) - 1]
+        };
+        Some(Self {
+            p50: rank(0.50),
+            p90: rank(0.90),
+            p99: rank(0.99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// One file's phases in a `TimingReport`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTimings {
+    pub input: PathBuf,
+    #[serde(flatten)]
+    pub timings: PhaseTimings,
+    pub total: f64,
+    pub dominant_phase: Phase,
+}
+
+/// Per-file phase timings of a batch with percentiles per phase, as `--timing-report` writes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimingReport {
+    pub files: Vec<FileTimings>,
+    /// Keyed by phase, plus `total`
+    pub percentiles: BTreeMap<String, PhasePercentiles>,
+}
+
+impl TimingReport {
+    pub fn new(timings: impl IntoIterator<Item = (PathBuf, PhaseTimings)>) -> Self {
+        let files: Vec<FileTimings> = timings
+            .into_iter()
+            .map(|(input, timings)| FileTimings {
+                input,
+                total: timings.total(),
+                dominant_phase: timings.dominant().0,
+                timings,
+            })
+            .collect();
+        let mut percentiles = BTreeMap::new();
+        for phase in Phase::ALL {
+            let values: Vec<f64> = files.iter().map(|file| file.timings.get(phase)).collect();
+            if let Some(summary) = PhasePercentiles::of(&values) {
+                let key = serde_json::to_value(phase)
+                    .ok()
+                    .and_then(|key| key.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                percentiles.insert(key, summary);
+            }
+        }
+        let totals: Vec<f64> = files.iter().map(|file| file.total).collect();
+        if let Some(summary) = PhasePercentiles::of(&totals) {
+            percentiles.insert("total".to_string(), summary);
+        }
+        Self { files, percentiles }
+    }
+
+    /// The `count` files that took longest overall, slowest first
+    pub fn slowest(&self, count: usize) -> Vec<&FileTimings> {
+        let mut files: Vec<&FileTimings> = self.files.iter().collect();
+        files.sort_by(|a, b| b.total.total_cmp(&a.total));
+        files.truncate(count);
+        files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(decode: f64, inference: f64, output: f64) -> PhaseTimings {
+        PhaseTimings {
+            decode,
+            inference,
+            output,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dominant_phase_and_total() {
+        let mut file = timings(0.5, 4.0, 0.25);
+        file.add(Phase::ModelWait, Duration::from_secs(6));
+        assert_eq!(file.total(), 10.75);
+        assert_eq!(file.dominant(), (Phase::ModelWait, 6.0));
+        assert_eq!(PhaseTimings::default().dominant().0, Phase::Decode);
+    }
+
+    #[test]
+    fn test_percentiles_by_nearest_rank() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        let summary = PhasePercentiles::of(&values).unwrap();
+        assert_eq!((summary.p50, summary.p90, summary.p99), (5.0, 9.0, 10.0));
+        assert_eq!(summary.max, 10.0);
+        assert_eq!(PhasePercentiles::of(&[2.0]).unwrap().p50, 2.0);
+        assert!(PhasePercentiles::of(&[]).is_none());
+    }
+
+    #[test]
+    fn test_timing_report() {
+        let report = TimingReport::new(vec![
+            ("a.wav".into(), timings(0.2, 3.0, 0.1)),
+            ("b.wav".into(), timings(5.0, 1.0, 0.1)),
+            ("c.wav".into(), timings(0.1, 0.5, 0.1)),
+        ]);
+        let slowest: Vec<(&str, Phase)> = report
+            .slowest(2)
+            .iter()
+            .map(|file| (file.input.to_str().unwrap(), file.dominant_phase))
+            .collect();
+        assert_eq!(
+            slowest,
+            vec![("b.wav", Phase::Decode), ("a.wav", Phase::Inference)]
+        );
+        assert_eq!(report.percentiles["inference"].max, 3.0);
+        assert!((report.percentiles["total"].p50 - 3.3).abs() < 1e-9);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["files"][1]["decode"], 5.0);
+        assert_eq!(json["files"][1]["dominant_phase"], "decode");
+        assert!(json["percentiles"]["model_wait"].is_object());
+    }
+}
@@ -1,22 +1,321 @@
-use crate::error::{Result, TranscriptionError};
-use crate::types::{ModelConfig, TranscriptionResult, TranscriptionSegment};
-use log::info;
+use crate::backend::{SegmentSink, TranscriptionBackend, SAMPLE_RATE};
+use crate::batch::{self, BatchOptions, BatchReport};
+use crate::error::{classify_decode_failure, PythonFailure, Result, TranscriptionError};
+use crate::language::{self, LanguageOverride};
+use crate::python_env;
+use crate::timing::{Phase, PhaseTimings};
+use crate::types::{
+    ModelConfig, TextBuilder, TranscriptionOptions, TranscriptionResult, TranscriptionSegment,
+    WordTiming, SUPPORTED_AUDIO_EXTENSIONS,
+};
+use crate::vad::{SpeechRegion, SpeechReport, VadOptions};
+use crate::version::{self, Version};
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
-use std::path::Path;
-use std::time::Instant;
+use pyo3::types::{PyBytes, PyDict, PyIterator};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError, TryLockError};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, info_span, warn, Span};
 
+/// Wraps a faster-whisper `WhisperModel`, loaded on first use and reused afterwards.
+///
+/// The transcriber is `Send + Sync` and can be shared through an `Arc`. Calls on one instance
+/// are serialized by a mutex around the model, which is always taken before the GIL so that
+/// concurrent callers queue instead of deadlocking.
 pub struct FasterWhisperTranscriber {
     config: ModelConfig,
+    options: TranscriptionOptions,
+    model: Mutex<Option<Py<PyAny>>>,
+    /// faster-whisper's version, read when the model is first loaded
+    faster_whisper_version: OnceLock<Option<Version>>,
+}
+
+/// Half a second of 16 kHz audio, enough to run the encoder and decoder once
+const WARMUP_SAMPLES: usize = 8_000;
+
+/// What `warmup` did and how long it took
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarmupReport {
+    /// Time spent constructing the model, including any download
+    pub load_time: Duration,
+    /// Time spent transcribing the generated silence
+    pub warmup_time: Duration,
+    /// The model files weren't in the local cache and were fetched during warmup
+    pub downloaded: bool,
+    /// The model was already loaded in this process, so nothing was constructed
+    pub already_loaded: bool,
+}
+
+/// What the Python side offers, found without loading a model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentInfo {
+    pub python_version: String,
+    pub faster_whisper_version: String,
+    /// `None` when ctranslate2 doesn't report a version
+    pub ctranslate2_version: Option<String>,
+    /// Devices a model could run on, e.g. `cpu`, `cuda`, `mps`
+    pub devices: Vec<String>,
+}
+
+/// A module's `__version__`, if it has one
+fn module_version(module: &Bound<'_, PyModule>) -> Option<String> {
+    module.getattr("__version__").ok()?.extract().ok()
+}
+
+/// Check that `path` exists, has a supported audio extension and could hold audio (see
+/// `probe::check_audio_content`)
+pub(crate) fn validate_audio_path(path: &Path) -> Result<()> {
+    // Validate file exists
+    if !path.exists() {
+        return Err(TranscriptionError::InvalidPath(format!(
+            "File does not exist: {}",
+            path.display()
+        )));
+    }
+
+    // Validate audio format
+    if let Some(ext) = path.extension() {
+        let ext = ext.to_string_lossy().to_lowercase();
+        if !SUPPORTED_AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+            return Err(TranscriptionError::UnsupportedFormat(ext));
+        }
+    } else {
+        return Err(TranscriptionError::UnsupportedFormat(
+            "no extension".to_string(),
+        ));
+    }
+    crate::probe::check_audio_content(path)
+}
+
+/// The object handed to `WhisperModel.transcribe` for `path`.
+///
+/// UTF-8 paths are passed as `str`. Anything else is opened from its raw bytes on Unix, since
+/// PyAV can't encode surrogate-escaped names; faster-whisper reads file objects just as well.
+fn audio_source<'py>(py: Python<'py>, path: &Path) -> PyResult<Bound<'py, PyAny>> {
+    if let Some(path) = path.to_str() {
+        return Ok(pyo3::types::PyString::new(py, path).into_any());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let raw = pyo3::types::PyBytes::new(py, path.as_os_str().as_bytes());
+        py.import("builtins")?.call_method1("open", (raw, "rb"))
+    }
+    #[cfg(not(unix))]
+    {
+        // Lossless via the filesystem encoding's surrogate escapes
+        path.as_os_str().into_pyobject(py).map(|p| p.into_any())
+    }
+}
+
+/// A transcription faster-whisper has started: the audio is open and its language known,
+/// and the segments are still to be decoded
+struct Started {
+    audio: Py<PyAny>,
+    segments: Py<PyIterator>,
+    tokenizer: Option<Py<PyAny>>,
+    language: String,
+    language_probability: f64,
+    duration: f64,
+    language_override: Option<LanguageOverride>,
+}
+
+/// A `SegmentSink` calling `on_segment` with each segment and the audio's duration
+struct Callback<'a> {
+    on_segment: &'a mut dyn FnMut(&TranscriptionSegment, f64),
+    duration: f64,
+}
+
+impl<'a> Callback<'a> {
+    fn new(on_segment: &'a mut dyn FnMut(&TranscriptionSegment, f64)) -> Self {
+        Self {
+            on_segment,
+            duration: 0.0,
+        }
+    }
+}
+
+impl SegmentSink for Callback<'_> {
+    fn begin(&mut self, _language: &str, _probability: f64, duration: f64) -> Result<()> {
+        self.duration = duration;
+        Ok(())
+    }
+
+    fn segment(&mut self, segment: &TranscriptionSegment) -> Result<()> {
+        (self.on_segment)(segment, self.duration);
+        Ok(())
+    }
+}
+
+/// What `run` hands to faster-whisper
+#[derive(Clone, Copy)]
+enum AudioInput<'a> {
+    Path(&'a Path),
+    /// Mono 16 kHz samples
+    Samples(&'a [f32]),
+}
+
+impl AudioInput<'_> {
+    fn path(&self) -> Option<&Path> {
+        match self {
+            AudioInput::Path(path) => Some(path),
+            AudioInput::Samples(_) => None,
+        }
+    }
+
+    fn to_python<'py>(self, py: Python<'py>) -> Result<Bound<'py, PyAny>> {
+        match self {
+            AudioInput::Path(path) => Ok(audio_source(py, path)?),
+            AudioInput::Samples(samples) => {
+                let numpy = py.import("numpy").map_err(|e| {
+                    TranscriptionError::ModelInitError(format!("Failed to import numpy: {}", e))
+                })?;
+                let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("dtype", "<f4")?;
+                Ok(numpy.call_method("frombuffer", (PyBytes::new(py, &bytes),), Some(&kwargs))?)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AudioInput<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AudioInput::Path(path) => write!(f, "{}", path.display()),
+            AudioInput::Samples(samples) => write!(f, "{} samples", samples.len()),
+        }
+    }
+}
+
+/// Release cached CUDA memory through torch, if it is installed and sees a GPU.
+/// faster-whisper itself doesn't need torch, so its absence isn't an error.
+fn empty_cuda_cache(py: Python<'_>) -> PyResult<()> {
+    let Ok(torch) = py.import("torch") else {
+        return Ok(());
+    };
+    let cuda = torch.getattr("cuda")?;
+    if cuda.call_method0("is_available")?.is_truthy()? {
+        cuda.call_method0("empty_cache")?;
+    }
+    Ok(())
+}
+
+/// faster-whisper's tokenizer for `model`, set up for transcribing `language`
+fn tokenizer<'py>(model: &Bound<'py, PyAny>, language: &str) -> PyResult<Bound<'py, PyAny>> {
+    let kwargs = PyDict::new(model.py());
+    kwargs.set_item("task", "transcribe")?;
+    kwargs.set_item("language", language)?;
+    model
+        .py()
+        .import("faster_whisper.tokenizer")?
+        .getattr("Tokenizer")?
+        .call(
+            (
+                model.getattr("hf_tokenizer")?,
+                model.getattr("model")?.getattr("is_multilingual")?,
+            ),
+            Some(&kwargs),
+        )
+}
+
+/// Share out a segment's text tokens, already split into word `pieces`, among its `words`.
+/// faster-whisper merges punctuation into neighbouring words, so a word can span several
+/// pieces. `None` when the pieces don't spell out the words.
+fn split_word_tokens(
+    pieces: &[String],
+    piece_tokens: Vec<Vec<i64>>,
+    words: &[WordTiming],
+) -> Option<Vec<Vec<i64>>> {
+    let mut pieces = pieces.iter().zip(piece_tokens);
+    let mut split = Vec::with_capacity(words.len());
+    for word in words {
+        let target = word.word.trim();
+        let mut text = String::new();
+        let mut tokens = Vec::new();
+        while text.trim() != target {
+            if text.trim().len() > target.len() {
+                return None;
+            }
+            let (piece, piece_tokens) = pieces.next()?;
+            text.push_str(piece);
+            tokens.extend(piece_tokens);
+        }
+        split.push(tokens);
+    }
+    // Anything left over must be whitespace the words don't show
+    pieces
+        .all(|(piece, _)| piece.trim().is_empty())
+        .then_some(split)
 }
 
 impl FasterWhisperTranscriber {
     pub fn new(config: ModelConfig) -> Result<Self> {
         config
             .validate()
-            .map_err(|e| TranscriptionError::ModelInitError(e))?;
+            .map_err(TranscriptionError::ModelInitError)?;
+
+        Ok(Self {
+            config,
+            options: TranscriptionOptions::default(),
+            model: Mutex::new(None),
+            faster_whisper_version: OnceLock::new(),
+        })
+    }
+
+    /// Run `check_environment` now, so a missing or broken faster-whisper install fails at
+    /// construction instead of on the first transcription
+    pub fn with_environment_check(self) -> Result<Self> {
+        Self::check_environment()?;
+        Ok(self)
+    }
+
+    /// Verify that Python initializes and faster_whisper imports, and report versions and
+    /// devices. Cheap: no model is loaded.
+    pub fn check_environment() -> Result<EnvironmentInfo> {
+        Python::with_gil(|py| -> Result<EnvironmentInfo> {
+            let version: String = py.import("sys")?.getattr("version")?.extract()?;
+            let python_version = version
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+
+            let faster_whisper = python_env::import_faster_whisper(py)?;
+            let faster_whisper_version =
+                module_version(&faster_whisper).unwrap_or_else(|| "unknown".to_string());
+
+            let ctranslate2 = py.import("ctranslate2").ok();
+            let ctranslate2_version = ctranslate2.as_ref().and_then(module_version);
+            let cuda_devices = ctranslate2
+                .and_then(|ct2| ct2.call_method0("get_cuda_device_count").ok())
+                .and_then(|count| count.extract::<usize>().ok())
+                .unwrap_or(0);
+
+            let mut devices = vec!["cpu".to_string()];
+            if cuda_devices > 0 {
+                devices.push("cuda".to_string());
+            }
+            if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+                devices.push("mps".to_string());
+            }
 
-        Ok(Self { config })
+            Ok(EnvironmentInfo {
+                python_version,
+                faster_whisper_version,
+                ctranslate2_version,
+                devices,
+            })
+        })
+    }
+
+    /// Replace the default decoding/VAD options used by `transcribe`
+    pub fn with_options(mut self, options: TranscriptionOptions) -> Result<Self> {
+        options
+            .validate()
+            .map_err(TranscriptionError::ModelInitError)?;
+        self.options = options;
+        Ok(self)
     }
 
     pub fn from_params(model_size: &str, device: &str, compute_type: &str) -> Result<Self> {
@@ -28,166 +327,732 @@ impl FasterWhisperTranscriber {
         &self.config
     }
 
+    pub fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
     pub fn transcribe<P: AsRef<Path>>(&self, audio_path: P) -> Result<TranscriptionResult> {
-        let audio_path = audio_path.as_ref();
+        self.transcribe_with_options(audio_path, &self.options)
+    }
 
-        // Validate file exists
-        if !audio_path.exists() {
-            return Err(TranscriptionError::InvalidPath(format!(
-                "File does not exist: {}",
-                audio_path.display()
-            )));
-        }
+    /// Transcribe using per-call options instead of the transcriber defaults
+    pub fn transcribe_with_options<P: AsRef<Path>>(
+        &self,
+        audio_path: P,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_streaming(audio_path, options, &mut |_, _| {})
+    }
 
-        // Validate audio format
-        if let Some(ext) = audio_path.extension() {
-            let ext = ext.to_string_lossy().to_lowercase();
-            if !matches!(
-                ext.as_str(),
-                "wav" | "mp3" | "flac" | "m4a" | "ogg" | "mp4" | "webm"
-            ) {
-                return Err(TranscriptionError::UnsupportedFormat(ext));
-            }
-        } else {
-            return Err(TranscriptionError::UnsupportedFormat(
-                "no extension".to_string(),
-            ));
-        }
+    /// Transcribe with per-call options, calling `on_segment` with each segment as it is
+    /// decoded and the audio's duration in seconds
+    pub fn transcribe_streaming<P: AsRef<Path>>(
+        &self,
+        audio_path: P,
+        options: &TranscriptionOptions,
+        on_segment: &mut dyn FnMut(&TranscriptionSegment, f64),
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_path_to(
+            audio_path.as_ref(),
+            options,
+            &mut Callback::new(on_segment),
+            true,
+        )
+    }
 
-        let audio_path_str = audio_path.to_str().ok_or_else(|| {
-            TranscriptionError::InvalidPath("Invalid Unicode in path".to_string())
-        })?;
+    /// Transcribe the file at `audio_path` into `sink`, keeping the segments in the result
+    /// too if `keep_segments`
+    fn transcribe_path_to(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+        sink: &mut dyn SegmentSink,
+        keep_segments: bool,
+    ) -> Result<TranscriptionResult> {
+        let _span = self.span(AudioInput::Path(audio_path)).entered();
+        let mut timings = PhaseTimings::default();
+        let phase_start = Instant::now();
+        info_span!("validate_audio").in_scope(|| validate_audio_path(audio_path))?;
+        timings.add(Phase::Decode, phase_start.elapsed());
+        let phase_start = Instant::now();
+        let mut model = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        timings.add(Phase::ModelWait, phase_start.elapsed());
+        self.run(
+            &mut model,
+            AudioInput::Path(audio_path),
+            options,
+            sink,
+            keep_segments,
+            timings,
+        )
+        .map_err(|e| e.with_path(audio_path))
+    }
 
-        info!("Starting transcription for: {}", audio_path_str);
-        let start_time = Instant::now();
+    /// Transcribe every file in `paths` with this transcriber's options, loading the model
+    /// once for all of them. See `batch::transcribe_many`.
+    pub fn transcribe_many(&self, paths: &[PathBuf], options: &BatchOptions) -> BatchReport {
+        batch::transcribe_many(self, paths, options)
+    }
 
-        let result = Python::with_gil(|py| -> Result<TranscriptionResult> {
-            // Import faster_whisper
-            let faster_whisper = py.import("faster_whisper")
-                .map_err(|e| TranscriptionError::ModelInitError(
-                    format!("Failed to import faster_whisper. Install with: pip install faster-whisper. Error: {}", e)
-                ))?;
-
-            // Create WhisperModel with Metal/GPU acceleration
-            let model_kwargs = PyDict::new(py);
-
-            // Enhanced device mapping for Metal acceleration on macOS
-            let device = match self.config.device.as_str() {
-                "mps" => "auto",  // faster-whisper auto-detects Metal acceleration
-                "cuda" => "auto", // faster-whisper auto-detects CUDA
-                "cpu" => "cpu",
-                "auto" => "auto",
-                _ => "auto",
-            };
+    /// Like `transcribe`, but fails with `WouldBlock` instead of waiting when another
+    /// thread is using the model
+    pub fn try_transcribe<P: AsRef<Path>>(&self, audio_path: P) -> Result<TranscriptionResult> {
+        let audio_path = audio_path.as_ref();
+        let _span = self.span(AudioInput::Path(audio_path)).entered();
+        let mut timings = PhaseTimings::default();
+        let phase_start = Instant::now();
+        info_span!("validate_audio").in_scope(|| validate_audio_path(audio_path))?;
+        timings.add(Phase::Decode, phase_start.elapsed());
+        let mut model = match self.model.try_lock() {
+            Ok(model) => model,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return Err(TranscriptionError::WouldBlock),
+        };
+        self.run(
+            &mut model,
+            AudioInput::Path(audio_path),
+            &self.options,
+            &mut Callback::new(&mut |_, _| {}),
+            true,
+            timings,
+        )
+        .map_err(|e| e.with_path(audio_path))
+    }
 
-            model_kwargs.set_item("device", device)?;
-            model_kwargs.set_item("compute_type", &self.config.compute_type)?;
-            
-            // Add Metal-specific optimizations for medium model
-            if self.config.model_size == "medium" && (self.config.device == "mps" || self.config.device == "auto") {
-                // Enable additional optimizations for medium model on Metal
-                model_kwargs.set_item("cpu_threads", 0)?; // Use all available cores
-                model_kwargs.set_item("num_workers", 1)?; // Optimal for Metal
-            }
+    /// Span around one transcription; the phases inside it have spans of their own
+    fn span(&self, input: AudioInput<'_>) -> Span {
+        info_span!(
+            "transcribe",
+            file = %input,
+            model = %self.config.model_size,
+            device = %self.config.device
+        )
+    }
 
-            info!(
-                "Initializing FasterWhisper model: {} on {} with compute_type: {}",
-                self.config.model_size, self.config.device, self.config.compute_type
-            );
+    /// Transcribe with the model lock held, loading the model into `cached` if needed. The
+    /// result only holds the segments if `keep_segments`; `sink` gets them either way. The
+    /// phases measured here are added to `timings`, which holds those the caller measured.
+    fn run(
+        &self,
+        cached: &mut Option<Py<PyAny>>,
+        input: AudioInput<'_>,
+        options: &TranscriptionOptions,
+        sink: &mut dyn SegmentSink,
+        keep_segments: bool,
+        mut timings: PhaseTimings,
+    ) -> Result<TranscriptionResult> {
+        info!("Starting transcription for: {}", input);
+        let start_time = Instant::now();
 
-            let model = faster_whisper
-                .getattr("WhisperModel")?
-                .call((&self.config.model_size,), Some(&model_kwargs))
-                .map_err(|e| {
-                    TranscriptionError::ModelInitError(format!("Failed to initialize model: {}", e))
-                })?;
+        // Up to the first segment runs under the GIL: loading the model, opening the audio
+        // and detecting its language
+        let started = Python::with_gil(|py| -> Result<Started> {
+            let phase_start = Instant::now();
+            let model = self.cached_model(py, cached)?;
+            timings.add(Phase::ModelWait, phase_start.elapsed());
+            if let Some(installed) = self.faster_whisper_version() {
+                version::check_options(options, installed)?;
+            }
 
             // Transcribe with optimized settings for medium model and Metal acceleration
             let transcribe_kwargs = PyDict::new(py);
-            
-            // Optimized parameters for medium model
-            if self.config.model_size == "medium" {
-                transcribe_kwargs.set_item("beam_size", 5)?; // Good balance for medium model
-                transcribe_kwargs.set_item("best_of", 5)?; // Better quality for medium model
-                transcribe_kwargs.set_item("temperature", 0.0)?; // Deterministic results
-            } else {
-                transcribe_kwargs.set_item("beam_size", 3)?; // Conservative for smaller models
+
+            // Optimized parameters for medium model, unless overridden by the options
+            let is_medium = self.config.model_size == "medium";
+            let beam_size = options.beam_size.unwrap_or(if is_medium { 5 } else { 3 });
+            transcribe_kwargs.set_item("beam_size", beam_size)?;
+            if let Some(best_of) = options.best_of.or(if is_medium { Some(5) } else { None }) {
+                transcribe_kwargs.set_item("best_of", best_of)?;
+            }
+            if let Some(temperature) =
+                options
+                    .temperature
+                    .or(if is_medium { Some(0.0) } else { None })
+            {
+                transcribe_kwargs.set_item("temperature", temperature)?;
             }
-            
-            transcribe_kwargs.set_item("word_timestamps", true)?;
-            transcribe_kwargs.set_item("vad_filter", true)?;
-            
+            if let Some(language) = &options.language {
+                transcribe_kwargs.set_item("language", language)?;
+            }
+
+            transcribe_kwargs.set_item("word_timestamps", options.word_timestamps)?;
+            transcribe_kwargs.set_item("vad_filter", options.vad_filter)?;
+            if let Some(start) = options.clip_start {
+                // A lone start runs to the end; segment times stay relative to the file
+                transcribe_kwargs.set_item("clip_timestamps", start.to_string())?;
+            }
+
             // Basic vad parameters that are widely supported
-            let vad_params = PyDict::new(py);
-            vad_params.set_item("threshold", 0.5)?;
-            transcribe_kwargs.set_item("vad_parameters", vad_params)?;
+            if options.vad_filter {
+                let vad_params = PyDict::new(py);
+                vad_params.set_item("threshold", options.vad_threshold)?;
+                transcribe_kwargs.set_item("vad_parameters", vad_params)?;
+            }
 
             info!("Starting transcription...");
-            let result = model
-                .call_method("transcribe", (audio_path_str,), Some(&transcribe_kwargs))
-                .map_err(|e| {
-                    TranscriptionError::TranscriptionFailed(format!("Transcription failed: {}", e))
-                })?;
-
-            // Extract segments and info
-            let segments_iter = result.get_item(0)?;
-            let info = result.get_item(1)?;
+            // Opening the audio and detecting its language happen here; segments come later
+            let start_transcription = |timings: &mut PhaseTimings| -> Result<_> {
+                let _span = info_span!("python_transcribe").entered();
+                let phase_start = Instant::now();
+                let audio = input.to_python(py)?;
+                let result = model
+                    .call_method("transcribe", (&audio,), Some(&transcribe_kwargs))
+                    .map_err(|e| {
+                        self.python_error(py, e, input.path(), |e| {
+                            TranscriptionError::TranscriptionFailed(format!(
+                                "Transcription failed: {}",
+                                e
+                            ))
+                        })
+                    })?;
+                timings.add(Phase::Decode, phase_start.elapsed());
+                Ok((audio, result))
+            };
+            let (mut audio, mut result) = start_transcription(&mut timings)?;
 
             // Get language info
-            let language = info.getattr("language")?.extract::<String>()?;
-            let language_probability = info.getattr("language_probability")?.extract::<f64>()?;
-            let duration = info.getattr("duration")?.extract::<f64>()?;
+            let mut info = result.get_item(1)?;
+            let mut language = info.getattr("language")?.extract::<String>()?;
+            let mut language_probability =
+                info.getattr("language_probability")?.extract::<f64>()?;
+            let mut language_override = None;
 
-            // Process segments
-            let mut segments = Vec::new();
-            let mut full_text = String::new();
-
-            for segment in segments_iter.try_iter()? {
-                let segment = segment?;
-                let start = segment.getattr("start")?.extract::<f64>()?;
-                let end = segment.getattr("end")?.extract::<f64>()?;
-                let text = segment.getattr("text")?.extract::<String>()?;
-                let no_speech_prob = segment.getattr("no_speech_prob")?.extract::<f64>()?;
+            // Segments are decoded lazily, so switching language only repeats the detection pass
+            let disallowed = match (&options.language, &options.allowed_languages) {
+                (None, Some(allowed)) if !allowed.contains(&language) => Some(allowed),
+                _ => None,
+            };
+            if let Some(allowed) = disallowed {
+                let probabilities = info
+                    .getattr("all_language_probs")
+                    .ok()
+                    .and_then(|probs| probs.extract::<Option<Vec<(String, f64)>>>().ok())
+                    .flatten()
+                    .unwrap_or_default();
+                let (forced, probability) = language::rerank(&probabilities, allowed)
+                    .unwrap_or_else(|| {
+                        warn!("No language probabilities to re-rank; using {}", allowed[0]);
+                        (allowed[0].clone(), 0.0)
+                    });
+                info!(
+                    "Detected language {} ({:.2}) isn't allowed; transcribing as {}",
+                    language, language_probability, forced
+                );
 
-                if !full_text.is_empty() {
-                    full_text.push(' ');
+                if audio.hasattr("close")? {
+                    audio.call_method0("close")?;
                 }
-                full_text.push_str(&text.trim());
-
-                segments.push(TranscriptionSegment {
-                    start,
-                    end,
-                    text: text.trim().to_string(),
-                    no_speech_prob,
+                transcribe_kwargs.set_item("language", &forced)?;
+                (audio, result) = start_transcription(&mut timings)?;
+                info = result.get_item(1)?;
+                language_override = Some(LanguageOverride {
+                    detected: std::mem::replace(&mut language, forced),
+                    detected_probability: language_probability,
                 });
+                language_probability = probability;
             }
-
-            let elapsed = start_time.elapsed();
-            let transcription_time = elapsed.as_secs_f64();
-            let real_time_factor = if transcription_time > 0.0 {
-                duration / transcription_time
+            let segments_iter = result.get_item(0)?.try_iter()?.unbind();
+            let duration = info.getattr("duration")?.extract::<f64>()?;
+            let tokenizer = if options.include_tokens {
+                Some(tokenizer(&model, &language)?.unbind())
             } else {
-                0.0
+                None
             };
 
-            info!("Transcription completed in {:.2}s", transcription_time);
-            info!(
-                "Audio duration: {:.2}s, Real-time factor: {:.2}x",
-                duration, real_time_factor
-            );
-
-            Ok(TranscriptionResult {
+            Ok(Started {
+                audio: audio.unbind(),
+                segments: segments_iter,
+                tokenizer,
                 language,
                 language_probability,
                 duration,
-                segments,
-                full_text,
-                transcription_time,
-                real_time_factor,
+                language_override,
             })
         })?;
 
-        Ok(result)
+        // The rest holds the GIL only while faster-whisper decodes each segment and it is
+        // read into Rust, so the sink, progress and heartbeat don't keep other threads out
+        sink.begin(
+            &started.language,
+            started.language_probability,
+            started.duration,
+        )?;
+        let mut full_text = TextBuilder::new(options.paragraph_gap);
+        let segments = {
+            let _span = info_span!("extract_segments").entered();
+            let phase_start = Instant::now();
+            let segments =
+                self.decode_segments(&started, input, sink, &mut full_text, keep_segments)?;
+            timings.add(Phase::Inference, phase_start.elapsed());
+            segments
+        };
+        Python::with_gil(|py| -> Result<()> {
+            let audio = started.audio.bind(py);
+            if audio.hasattr("close")? {
+                audio.call_method0("close")?;
+            }
+            Ok(())
+        })?;
+
+        let elapsed = start_time.elapsed();
+        let transcription_time = elapsed.as_secs_f64();
+        let duration = started.duration;
+        let real_time_factor = if transcription_time > 0.0 {
+            duration / transcription_time
+        } else {
+            0.0
+        };
+
+        info!("Transcription completed in {:.2}s", transcription_time);
+        info!(
+            "Audio duration: {:.2}s, Real-time factor: {:.2}x",
+            duration, real_time_factor
+        );
+
+        Ok(TranscriptionResult {
+            language: started.language,
+            language_probability: started.language_probability,
+            duration,
+            full_text: full_text.finish(),
+            segments,
+            transcription_time,
+            real_time_factor,
+            language_override: started.language_override,
+            timings: Some(timings),
+            ..Default::default()
+        })
+    }
+
+    /// Feed the rest of `started`'s segments to `sink` and `full_text`, returning them too if
+    /// `keep_segments`. The GIL is taken for each step of faster-whisper's generator and
+    /// released while the segment is handed on.
+    fn decode_segments(
+        &self,
+        started: &Started,
+        input: AudioInput<'_>,
+        sink: &mut dyn SegmentSink,
+        full_text: &mut TextBuilder,
+        keep_segments: bool,
+    ) -> Result<Vec<TranscriptionSegment>> {
+        let mut segments = Vec::new();
+        while let Some(segment) = Python::with_gil(|py| {
+            self.next_segment(py, &started.segments, started.tokenizer.as_ref(), input)
+        })? {
+            sink.segment(&segment)?;
+            full_text.push(&segment);
+            if keep_segments {
+                segments.push(segment);
+            }
+        }
+        Ok(segments)
+    }
+
+    /// Decode the next segment from faster-whisper's generator and read it into Rust. With
+    /// a `tokenizer`, the segment's tokens are kept and split among its words.
+    fn next_segment(
+        &self,
+        py: Python<'_>,
+        segments: &Py<PyIterator>,
+        tokenizer: Option<&Py<PyAny>>,
+        input: AudioInput<'_>,
+    ) -> Result<Option<TranscriptionSegment>> {
+        let Some(segment) = segments.bind(py).clone().next() else {
+            return Ok(None);
+        };
+        let segment = segment
+            .map_err(|e| self.python_error(py, e, input.path(), TranscriptionError::from))?;
+        let start = segment.getattr("start")?.extract::<f64>()?;
+        let end = segment.getattr("end")?.extract::<f64>()?;
+        let text = segment.getattr("text")?.extract::<String>()?;
+        let no_speech_prob = segment.getattr("no_speech_prob")?.extract::<f64>()?;
+        let avg_logprob = segment.getattr("avg_logprob")?.extract::<f64>()?;
+        let mut words = Vec::new();
+        let segment_words = segment.getattr("words")?;
+        if !segment_words.is_none() {
+            for word in segment_words.try_iter()? {
+                let word = word?;
+                words.push(WordTiming {
+                    start: word.getattr("start")?.extract::<f64>()?,
+                    end: word.getattr("end")?.extract::<f64>()?,
+                    word: word.getattr("word")?.extract::<String>()?,
+                    probability: word.getattr("probability")?.extract::<f64>()?,
+                    speaker: None,
+                    tokens: None,
+                });
+            }
+        }
+        let tokens = match tokenizer {
+            Some(tokenizer) => {
+                let tokens = segment.getattr("tokens")?.extract::<Vec<i64>>()?;
+                if !words.is_empty() {
+                    let tokenizer = tokenizer.bind(py);
+                    let eot = tokenizer.getattr("eot")?.extract::<i64>()?;
+                    let text_tokens: Vec<i64> = tokens
+                        .iter()
+                        .copied()
+                        .filter(|&token| token < eot)
+                        .collect();
+                    // The same split faster-whisper times words with
+                    let (pieces, piece_tokens): (Vec<String>, Vec<Vec<i64>>) = tokenizer
+                        .call_method1("split_to_word_tokens", (text_tokens,))?
+                        .extract()?;
+                    match split_word_tokens(&pieces, piece_tokens, &words) {
+                        Some(split) => {
+                            for (word, tokens) in words.iter_mut().zip(split) {
+                                word.tokens = Some(tokens);
+                            }
+                        }
+                        None => debug!(
+                            "Tokens of the segment at {:.2}s don't line up with its words",
+                            start
+                        ),
+                    }
+                }
+                Some(tokens)
+            }
+            None => None,
+        };
+
+        Ok(Some(TranscriptionSegment {
+            start,
+            end,
+            text: text.trim().to_string(),
+            no_speech_prob,
+            avg_logprob,
+            words,
+            speaker: None,
+            tokens,
+        }))
+    }
+
+    /// Decode token ids, as found on segments and words with `include_tokens`, back to text.
+    /// Timestamp and other special tokens are skipped. Loads the model if it isn't yet.
+    pub fn decode_tokens(&self, tokens: &[i64]) -> Result<String> {
+        let mut cached = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        Python::with_gil(|py| -> Result<String> {
+            let model = self.cached_model(py, &mut cached)?;
+            // Decoding doesn't depend on the language; any the tokenizer knows will do
+            let text = tokenizer(&model, "en")?
+                .call_method1("decode", (tokens.to_vec(),))?
+                .extract::<String>()?;
+            Ok(text)
+        })
+    }
+
+    /// Load the model and run it once over generated silence, so the first real request
+    /// doesn't pay for model loading or first-inference setup
+    pub fn warmup(&self) -> Result<WarmupReport> {
+        let mut cached = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        Python::with_gil(|py| -> Result<WarmupReport> {
+            let already_loaded = cached.is_some();
+            let downloaded = !already_loaded && !self.model_files_cached(py);
+
+            let load_start = Instant::now();
+            let model = self.cached_model(py, &mut cached)?;
+            let load_time = load_start.elapsed();
+
+            let warmup_start = Instant::now();
+            let numpy = py.import("numpy").map_err(|e| {
+                TranscriptionError::ModelInitError(format!("Failed to import numpy: {}", e))
+            })?;
+            let dtype_kwargs = PyDict::new(py);
+            dtype_kwargs.set_item("dtype", "float32")?;
+            let silence = numpy.call_method("zeros", (WARMUP_SAMPLES,), Some(&dtype_kwargs))?;
+
+            let transcribe_kwargs = PyDict::new(py);
+            transcribe_kwargs.set_item("beam_size", 1)?;
+            let result = model
+                .call_method("transcribe", (silence,), Some(&transcribe_kwargs))
+                .map_err(|e| {
+                    self.python_error(py, e, None, |e| {
+                        TranscriptionError::TranscriptionFailed(format!("Warmup failed: {}", e))
+                    })
+                })?;
+            // Segments are decoded lazily, so drain them to actually run the decoder
+            for segment in result.get_item(0)?.try_iter()? {
+                segment.map_err(|e| self.python_error(py, e, None, TranscriptionError::from))?;
+            }
+
+            Ok(WarmupReport {
+                load_time,
+                warmup_time: warmup_start.elapsed(),
+                downloaded,
+                already_loaded,
+            })
+        })
+    }
+
+    /// Speech regions in `audio_path` from faster-whisper's Silero VAD, without loading or
+    /// running the Whisper model. A file with no speech gives an empty list.
+    pub fn detect_speech(
+        &self,
+        audio_path: &Path,
+        vad_options: &VadOptions,
+    ) -> Result<Vec<SpeechRegion>> {
+        Ok(self.speech_report(audio_path, vad_options)?.regions)
+    }
+
+    /// `detect_speech` with the audio's duration and how much of it is speech
+    pub fn speech_report(
+        &self,
+        audio_path: &Path,
+        vad_options: &VadOptions,
+    ) -> Result<SpeechReport> {
+        validate_audio_path(audio_path)?;
+        vad_options
+            .validate()
+            .map_err(TranscriptionError::ConfigError)?;
+        Python::with_gil(|py| -> Result<SpeechReport> {
+            python_env::import_faster_whisper(py)?;
+            let audio = py
+                .import("faster_whisper.audio")?
+                .call_method1("decode_audio", (audio_source(py, audio_path)?,))
+                .map_err(|e| {
+                    self.python_error(py, e, Some(audio_path), TranscriptionError::from)
+                })?;
+            let samples: usize = audio.len()?;
+
+            let options = PyDict::new(py);
+            options.set_item("threshold", vad_options.threshold)?;
+            if let Some(ms) = vad_options.min_speech_duration_ms {
+                options.set_item("min_speech_duration_ms", ms)?;
+            }
+            if let Some(ms) = vad_options.min_silence_duration_ms {
+                options.set_item("min_silence_duration_ms", ms)?;
+            }
+            if let Some(ms) = vad_options.speech_pad_ms {
+                options.set_item("speech_pad_ms", ms)?;
+            }
+            let vad = py.import("faster_whisper.vad")?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item(
+                "vad_options",
+                vad.getattr("VadOptions")?.call((), Some(&options))?,
+            )?;
+            let timestamps = vad
+                .call_method("get_speech_timestamps", (audio,), Some(&kwargs))
+                .map_err(|e| {
+                    self.python_error(py, e, Some(audio_path), TranscriptionError::from)
+                })?;
+
+            // Timestamps are in samples at faster-whisper's 16 kHz
+            let rate = SAMPLE_RATE as f64;
+            let mut regions = Vec::new();
+            for stamp in timestamps.try_iter()? {
+                let stamp = stamp?;
+                let start: f64 = stamp.get_item("start")?.extract()?;
+                let end: f64 = stamp.get_item("end")?.extract()?;
+                regions.push(SpeechRegion {
+                    start: start / rate,
+                    end: end / rate,
+                });
+            }
+            Ok(SpeechReport::new(
+                audio_path.to_path_buf(),
+                samples as f64 / rate,
+                regions,
+            ))
+        })
+        .map_err(|e| e.with_path(audio_path))
+    }
+
+    /// Whether the configured model can be loaded without downloading anything
+    fn model_files_cached(&self, py: Python<'_>) -> bool {
+        if Path::new(&self.config.model_size).is_dir() {
+            return true;
+        }
+        let lookup = || -> PyResult<()> {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("local_files_only", true)?;
+            if let Some(model_dir) = &self.config.model_dir {
+                kwargs.set_item("cache_dir", model_dir)?;
+            }
+            py.import("faster_whisper.utils")?
+                .getattr("download_model")?
+                .call((&self.config.model_size,), Some(&kwargs))?;
+            Ok(())
+        };
+        lookup().is_ok()
+    }
+
+    /// Fetch the configured model into the cache without loading it, returning the
+    /// directory it was saved to
+    pub fn download_model(&self) -> Result<PathBuf> {
+        Python::with_gil(|py| -> Result<PathBuf> {
+            python_env::import_faster_whisper(py)?;
+            let kwargs = PyDict::new(py);
+            if let Some(model_dir) = &self.config.model_dir {
+                kwargs.set_item("cache_dir", model_dir)?;
+            }
+            let path: String = py
+                .import("faster_whisper.utils")?
+                .getattr("download_model")?
+                .call((&self.config.model_size,), Some(&kwargs))
+                .and_then(|path| path.extract())
+                .map_err(|e| {
+                    self.python_error(py, e, None, |e| {
+                        TranscriptionError::ModelInitError(format!(
+                            "Failed to download model: {}",
+                            e
+                        ))
+                    })
+                })?;
+            Ok(PathBuf::from(path))
+        })
+    }
+
+    /// The installed faster-whisper's version, once a model has been loaded. `None` before
+    /// that, or if the package doesn't report a parseable version.
+    pub fn faster_whisper_version(&self) -> Option<Version> {
+        self.faster_whisper_version.get().copied().flatten()
+    }
+
+    /// Whether a model is currently loaded
+    pub fn is_model_loaded(&self) -> bool {
+        self.model
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some()
+    }
+
+    /// Drop the cached model and give its memory back, returning whether one was loaded.
+    ///
+    /// The next transcription loads the model again. Waits for any transcription in progress.
+    pub fn unload_model(&self) -> bool {
+        let model = self
+            .model
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let Some(model) = model else {
+            return false;
+        };
+
+        Python::with_gil(|py| {
+            drop(model);
+            if let Err(e) = py.import("gc").and_then(|gc| gc.call_method0("collect")) {
+                debug!("gc.collect() failed: {}", e);
+            }
+            if matches!(self.config.device.as_str(), "cuda" | "auto") {
+                if let Err(e) = empty_cuda_cache(py) {
+                    debug!("torch.cuda.empty_cache() failed: {}", e);
+                }
+            }
+        });
+        info!("Unloaded model {}", self.config.model_size);
+        true
+    }
+
+    /// The loaded `WhisperModel`, constructing and caching it on first use
+    fn cached_model<'py>(
+        &self,
+        py: Python<'py>,
+        cached: &mut Option<Py<PyAny>>,
+    ) -> Result<Bound<'py, PyAny>> {
+        if let Some(model) = cached {
+            return Ok(model.bind(py).clone());
+        }
+        let _span = info_span!("load_model").entered();
+
+        let faster_whisper = python_env::import_faster_whisper(py)?;
+
+        self.faster_whisper_version
+            .get_or_init(|| module_version(&faster_whisper).and_then(|v| v.parse().ok()));
+
+        // Create WhisperModel with Metal/GPU acceleration
+        let model_kwargs = self.model_kwargs(py)?;
+
+        // Add Metal-specific optimizations for medium model
+        if self.config.model_size == "medium"
+            && (self.config.device == "mps" || self.config.device == "auto")
+            && self.config.cpu_threads.is_none()
+        {
+            // Enable additional optimizations for medium model on Metal
+            model_kwargs.set_item("cpu_threads", 0)?; // Use all available cores
+            model_kwargs.set_item("num_workers", 1)?; // Optimal for Metal
+        }
+
+        info!(
+            "Initializing FasterWhisper model: {} on {} with compute_type: {}",
+            self.config.model_size, self.config.device, self.config.compute_type
+        );
+
+        let model = faster_whisper
+            .getattr("WhisperModel")?
+            .call((&self.config.model_size,), Some(&model_kwargs))
+            .map_err(|e| {
+                self.python_error(py, e, None, |e| {
+                    TranscriptionError::ModelInitError(format!("Failed to initialize model: {}", e))
+                })
+            })?;
+
+        *cached = Some(model.clone().unbind());
+        Ok(model)
+    }
+
+    /// The specific error for a recognised Python failure, or `fallback(err)`. Decode failures
+    /// only count as such when the audio came from `path`.
+    fn python_error(
+        &self,
+        py: Python<'_>,
+        err: PyErr,
+        path: Option<&Path>,
+        fallback: impl FnOnce(PyErr) -> TranscriptionError,
+    ) -> TranscriptionError {
+        match (python_env::classify(py, &err), path) {
+            (PythonFailure::OutOfMemory, _) => {
+                // Hand back what the failed allocation left cached, so later files in a batch
+                // still have a chance on the same device
+                if let Err(e) = empty_cuda_cache(py) {
+                    debug!("torch.cuda.empty_cache() failed: {}", e);
+                }
+                TranscriptionError::out_of_memory(&self.config)
+            }
+            (PythonFailure::ModelDownload, _) => TranscriptionError::ModelDownloadFailed {
+                model: self.config.model_size.clone(),
+                source: err,
+            },
+            (PythonFailure::AudioDecode, Some(path)) => {
+                let message = err.value(py).to_string();
+                let reason = classify_decode_failure(&message);
+                TranscriptionError::AudioDecodeError {
+                    path: path.to_path_buf(),
+                    reason,
+                    detail: format!("{} ({})", reason, message),
+                }
+            }
+            (PythonFailure::PythonUnavailable, _) => {
+                TranscriptionError::PythonUnavailable(err.to_string())
+            }
+            _ => fallback(err),
+        }
+    }
+
+    /// Keyword arguments for `WhisperModel(...)` shared by every model construction
+    fn model_kwargs<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let model_kwargs = PyDict::new(py);
+
+        // Enhanced device mapping for Metal acceleration on macOS
+        let device = match self.config.device.as_str() {
+            "mps" => "auto",  // faster-whisper auto-detects Metal acceleration
+            "cuda" => "auto", // faster-whisper auto-detects CUDA
+            "cpu" => "cpu",
+            "auto" => "auto",
+            _ => "auto",
+        };
+
+        model_kwargs.set_item("device", device)?;
+        model_kwargs.set_item("compute_type", &self.config.compute_type)?;
+        if let Some(model_dir) = &self.config.model_dir {
+            model_kwargs.set_item("download_root", model_dir)?;
+        }
+        if self.config.offline {
+            model_kwargs.set_item("local_files_only", true)?;
+        }
+        if let Some(threads) = self.config.cpu_threads {
+            model_kwargs.set_item("cpu_threads", threads)?;
+        }
+        Ok(model_kwargs)
     }
 
     /// Test if the model can be initialized successfully
@@ -195,30 +1060,20 @@ impl FasterWhisperTranscriber {
         info!("Testing model initialization...");
 
         Python::with_gil(|py| -> Result<()> {
-            let faster_whisper = py.import("faster_whisper").map_err(|e| {
-                TranscriptionError::ModelInitError(format!(
-                    "Failed to import faster_whisper: {}",
-                    e
-                ))
-            })?;
+            let faster_whisper = python_env::import_faster_whisper(py)?;
 
-            let model_kwargs = PyDict::new(py);
-            let device = match self.config.device.as_str() {
-                "mps" => "auto",
-                "cuda" => "auto",
-                "cpu" => "cpu",
-                "auto" => "auto",
-                _ => "auto",
-            };
-
-            model_kwargs.set_item("device", device)?;
-            model_kwargs.set_item("compute_type", &self.config.compute_type)?;
+            let model_kwargs = self.model_kwargs(py)?;
 
             let _model = faster_whisper
                 .getattr("WhisperModel")?
                 .call((&self.config.model_size,), Some(&model_kwargs))
                 .map_err(|e| {
-                    TranscriptionError::ModelInitError(format!("Failed to initialize model: {}", e))
+                    self.python_error(py, e, None, |e| {
+                        TranscriptionError::ModelInitError(format!(
+                            "Failed to initialize model: {}",
+                            e
+                        ))
+                    })
                 })?;
 
             info!("✓ Model initialization successful");
@@ -229,41 +1084,36 @@ impl FasterWhisperTranscriber {
     /// Get information about the actual device being used
     pub fn get_device_info(&self) -> Result<String> {
         Python::with_gil(|py| -> Result<String> {
-            let faster_whisper = py.import("faster_whisper").map_err(|e| {
-                TranscriptionError::ModelInitError(format!(
-                    "Failed to import faster_whisper: {}",
-                    e
-                ))
-            })?;
-
-            let model_kwargs = PyDict::new(py);
-            let device = match self.config.device.as_str() {
-                "mps" => "auto",
-                "cuda" => "auto", 
-                "cpu" => "cpu",
-                "auto" => "auto",
-                _ => "auto",
-            };
+            let faster_whisper = python_env::import_faster_whisper(py)?;
 
-            model_kwargs.set_item("device", device)?;
-            model_kwargs.set_item("compute_type", &self.config.compute_type)?;
+            let model_kwargs = self.model_kwargs(py)?;
 
             let model = faster_whisper
                 .getattr("WhisperModel")?
                 .call((&self.config.model_size,), Some(&model_kwargs))
                 .map_err(|e| {
-                    TranscriptionError::ModelInitError(format!("Failed to initialize model: {}", e))
+                    self.python_error(py, e, None, |e| {
+                        TranscriptionError::ModelInitError(format!(
+                            "Failed to initialize model: {}",
+                            e
+                        ))
+                    })
                 })?;
 
             // Try to get device information
             let device_info = if let Ok(device_attr) = model.getattr("device") {
                 device_attr.to_string()
             } else {
-                format!("Device: {} (configured), Compute Type: {}", 
-                       self.config.device, self.config.compute_type)
+                format!(
+                    "Device: {} (configured), Compute Type: {}",
+                    self.config.device, self.config.compute_type
+                )
             };
 
-            Ok(device_info)
+            match module_version(&faster_whisper) {
+                Some(version) => Ok(format!("{} (faster-whisper {})", device_info, version)),
+                None => Ok(device_info),
+            }
         })
     }
 
@@ -289,6 +1139,90 @@ impl FasterWhisperTranscriber {
     }
 }
 
+impl TranscriptionBackend for FasterWhisperTranscriber {
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    fn load(&self) -> Result<()> {
+        let mut cached = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        Python::with_gil(|py| self.cached_model(py, &mut cached).map(|_| ()))
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.is_model_loaded()
+    }
+
+    fn transcribe_path(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_with_options(audio_path, options)
+    }
+
+    fn transcribe_path_streaming(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+        on_segment: &mut dyn FnMut(&TranscriptionSegment, f64),
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_streaming(audio_path, options, on_segment)
+    }
+
+    fn transcribe_path_into(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+        sink: &mut dyn SegmentSink,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_path_to(audio_path, options, sink, false)
+    }
+
+    fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let _span = self.span(AudioInput::Samples(samples)).entered();
+        let mut timings = PhaseTimings::default();
+        let phase_start = Instant::now();
+        let mut model = self.model.lock().unwrap_or_else(PoisonError::into_inner);
+        timings.add(Phase::ModelWait, phase_start.elapsed());
+        self.run(
+            &mut model,
+            AudioInput::Samples(samples),
+            options,
+            &mut Callback::new(&mut |_, _| {}),
+            true,
+            timings,
+        )
+    }
+
+    fn device_info(&self) -> Result<String> {
+        self.get_device_info()
+    }
+
+    fn unload(&self) -> bool {
+        self.unload_model()
+    }
+
+    /// Also reports whether the model had to be downloaded
+    fn warmup(&self) -> Result<WarmupReport> {
+        FasterWhisperTranscriber::warmup(self)
+    }
+}
+
+impl Drop for FasterWhisperTranscriber {
+    fn drop(&mut self) {
+        self.unload_model();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,4 +1287,325 @@ mod tests {
             Err(TranscriptionError::UnsupportedFormat(_))
         ));
     }
+
+    #[test]
+    fn test_transcriber_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FasterWhisperTranscriber>();
+        assert_send_sync::<std::sync::Arc<FasterWhisperTranscriber>>();
+    }
+
+    /// A span's name, parent and field names
+    type RecordedSpan = (&'static str, Option<u64>, Vec<&'static str>);
+
+    #[derive(Default)]
+    struct SpanRecorder {
+        spans: Mutex<Vec<RecordedSpan>>,
+        entered: Mutex<Vec<u64>>,
+    }
+
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let parent = match attrs.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if attrs.is_contextual() => self.entered.lock().unwrap().last().copied(),
+                None => None,
+            };
+            let fields = attrs.metadata().fields().iter().map(|f| f.name()).collect();
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((attrs.metadata().name(), parent, fields));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, span: &tracing::span::Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &tracing::span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    fn test_phases_are_spans_inside_the_transcription() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("spans.wav");
+        fs::write(&file_path, crate::probe::wav_bytes(16000, 1, 1600)).unwrap();
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+
+        let recorder = std::sync::Arc::new(SpanRecorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            // Without faster-whisper this stops at model loading, which still has its span
+            let _ = transcriber.transcribe(&file_path);
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        let (name, parent, fields) = &spans[0];
+        assert_eq!((*name, *parent), ("transcribe", None));
+        assert_eq!(fields, &["file", "model", "device"]);
+        let children: Vec<_> = spans[1..]
+            .iter()
+            .filter(|(_, parent, _)| *parent == Some(1))
+            .map(|(name, _, _)| *name)
+            .collect();
+        assert_eq!(&children[..2], ["validate_audio", "load_model"]);
+        assert!(children[2..]
+            .iter()
+            .all(|name| matches!(*name, "python_transcribe" | "extract_segments")));
+    }
+
+    #[test]
+    fn test_segment_sink_runs_without_the_gil() {
+        // Stand-in for faster-whisper's generator, decoding segments instantly
+        let segments = Python::with_gil(|py| {
+            let globals = PyDict::new(py);
+            py.run(
+                c"import types
+def decode():
+    for i in range(5):
+        yield types.SimpleNamespace(start=i, end=i + 1, text=' hi', no_speech_prob=0.0,
+                                    avg_logprob=-0.1, words=None)
+segments = decode()",
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+            let segments = globals.get_item("segments").unwrap().unwrap();
+            segments.try_iter().unwrap().unbind()
+        });
+        let started = Started {
+            audio: Python::with_gil(|py| py.None()),
+            segments,
+            tokenizer: None,
+            language: "en".to_string(),
+            language_probability: 1.0,
+            duration: 5.0,
+            language_override: None,
+        };
+
+        /// Takes its time over each segment, as a slow writer would, without touching Python
+        struct Slow(std::sync::mpsc::Sender<()>);
+        impl SegmentSink for Slow {
+            fn begin(&mut self, _: &str, _: f64, _: f64) -> Result<()> {
+                Ok(())
+            }
+            fn segment(&mut self, _segment: &TranscriptionSegment) -> Result<()> {
+                let _ = self.0.send(());
+                std::thread::sleep(Duration::from_millis(100));
+                Ok(())
+            }
+        }
+
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let decoding = std::thread::spawn(move || {
+            let mut full_text = TextBuilder::new(None);
+            transcriber
+                .decode_segments(
+                    &started,
+                    AudioInput::Samples(&[]),
+                    &mut Slow(started_tx),
+                    &mut full_text,
+                    true,
+                )
+                .unwrap()
+        });
+
+        started_rx.recv().unwrap();
+        let start = Instant::now();
+        Python::with_gil(|py| py.run(c"x = 1 + 1", None, None).unwrap());
+        let waited = start.elapsed();
+
+        let decoded = decoding.join().unwrap();
+        assert_eq!(decoded.len(), 5);
+        assert_eq!(decoded[4].text, "hi");
+        // Holding the GIL through the sink would keep this thread out for the ~500ms it takes
+        assert!(
+            waited < Duration::from_millis(80),
+            "waited {:?} for the GIL",
+            waited
+        );
+    }
+
+    #[test]
+    fn test_concurrent_transcribe_calls_complete() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("shared.wav");
+        fs::write(&file_path, crate::probe::wav_bytes(16000, 1, 1600)).unwrap();
+
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        let transcriber = std::sync::Arc::new(transcriber);
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let transcriber = transcriber.clone();
+                let file_path = file_path.clone();
+                std::thread::spawn(move || transcriber.transcribe(&file_path).is_ok())
+            })
+            .collect();
+        let outcomes: Vec<bool> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(outcomes.len(), 8);
+        // Every call failed or succeeded the same way; none hung or panicked
+        assert!(outcomes.iter().all(|ok| *ok == outcomes[0]));
+    }
+
+    #[test]
+    fn test_try_transcribe_would_block() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("busy.wav");
+        fs::write(&file_path, crate::probe::wav_bytes(16000, 1, 1600)).unwrap();
+
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        let _guard = transcriber.model.lock().unwrap();
+        assert!(matches!(
+            transcriber.try_transcribe(&file_path),
+            Err(TranscriptionError::WouldBlock)
+        ));
+    }
+
+    #[test]
+    fn test_unload_and_reload() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("reload.wav");
+        fs::write(&file_path, crate::probe::wav_bytes(16000, 1, 1600)).unwrap();
+
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        assert!(!transcriber.unload_model());
+
+        // Stand-in for a loaded WhisperModel
+        Python::with_gil(|py| {
+            *transcriber.model.lock().unwrap() = Some(py.None());
+        });
+        assert!(transcriber.is_model_loaded());
+        assert!(transcriber.unload_model());
+        assert!(!transcriber.is_model_loaded());
+        assert!(!transcriber.unload_model());
+
+        // The next call goes back through model loading rather than failing as unloaded
+        match transcriber.transcribe(&file_path) {
+            Ok(_) => assert!(transcriber.is_model_loaded()),
+            Err(e) => assert!(matches!(e.kind(), "model_init" | "package_missing")),
+        }
+    }
+
+    #[test]
+    fn test_check_environment() {
+        match FasterWhisperTranscriber::check_environment() {
+            Ok(info) => {
+                assert!(!info.python_version.is_empty());
+                assert!(info.devices.contains(&"cpu".to_string()));
+            }
+            Err(e) => {
+                assert_eq!(e.kind(), "package_missing");
+                assert!(e.to_string().contains("pip install faster-whisper"));
+            }
+        }
+
+        let checked = FasterWhisperTranscriber::from_params("base", "cpu", "float32")
+            .and_then(|t| t.with_environment_check());
+        assert_eq!(
+            checked.is_ok(),
+            FasterWhisperTranscriber::check_environment().is_ok()
+        );
+    }
+
+    #[test]
+    fn test_warmup_reports_load_failure() {
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        match transcriber.warmup() {
+            Ok(report) => {
+                assert!(!report.already_loaded);
+                assert!(transcriber.is_model_loaded());
+                assert!(transcriber.warmup().unwrap().already_loaded);
+            }
+            Err(e) => {
+                assert!(matches!(e.kind(), "model_init" | "package_missing"));
+                assert!(!transcriber.is_model_loaded());
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_word_tokens_follows_merged_punctuation() {
+        let word = |text: &str| WordTiming {
+            start: 0.0,
+            end: 0.0,
+            word: text.to_string(),
+            probability: 1.0,
+            speaker: None,
+            tokens: None,
+        };
+        let pieces: Vec<String> = [" \"", "Hello", ",\"", " world", "."]
+            .iter()
+            .map(|piece| piece.to_string())
+            .collect();
+        let tokens = vec![vec![366], vec![15947], vec![1600, 1], vec![1002], vec![13]];
+        // faster-whisper glues leading and trailing punctuation onto the words
+        let words = [word(" \"Hello,\""), word(" world.")];
+        assert_eq!(
+            split_word_tokens(&pieces, tokens.clone(), &words),
+            Some(vec![vec![366, 15947, 1600, 1], vec![1002, 13]])
+        );
+        // Words that the pieces don't spell out get no tokens
+        let words = [word(" \"Hello,\""), word(" there.")];
+        assert_eq!(split_word_tokens(&pieces, tokens.clone(), &words), None);
+        assert_eq!(split_word_tokens(&pieces, tokens, &words[..1]), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir
+            .path()
+            .join(OsStr::from_bytes(b"interview caf\xe9 \xff.wav"));
+        assert!(file_path.to_str().is_none());
+        fs::write(&file_path, b"RIFF").unwrap();
+
+        Python::with_gil(|py| {
+            let source = audio_source(py, &file_path).unwrap();
+            let bytes: Vec<u8> = source.call_method0("read").unwrap().extract().unwrap();
+            assert_eq!(bytes, b"RIFF");
+            source.call_method0("close").unwrap();
+        });
+
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        let result = transcriber.transcribe(&file_path);
+        assert!(!matches!(result, Err(TranscriptionError::InvalidPath(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_speech_report_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir
+            .path()
+            .join(OsStr::from_bytes(b"vad caf\xe9 \xff.wav"));
+        fs::write(&file_path, crate::probe::wav_bytes(16000, 1, 16000)).unwrap();
+
+        let transcriber = FasterWhisperTranscriber::from_params("base", "cpu", "float32").unwrap();
+        // A lossy name would point at no file, so decoding would fail rather than give 1 s
+        match transcriber.speech_report(&file_path, &VadOptions::default()) {
+            Ok(report) => {
+                assert!((report.duration - 1.0).abs() < 0.01);
+                assert!(!report.has_speech());
+            }
+            Err(e) => assert_eq!(e.kind(), "package_missing"),
+        }
+    }
 }
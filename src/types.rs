@@ -1,4 +1,46 @@
+use crate::align::AlignmentSummary;
+use crate::chapters::Chapter;
+use crate::confidence::ConfidentText;
+use crate::language::LanguageOverride;
+use crate::openai::VerboseJson;
+use crate::output::{format_timestamp, TimestampStyle};
+use crate::redact::RedactionSummary;
+use crate::search::{Match, SearchOptions};
+use crate::speakers::SpeakerTurns;
+use crate::stats::TranscriptStats;
+use crate::timing::{Phase, PhaseTimings};
+use crate::window::Windows;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Audio file extensions accepted by the transcriber (compared case-insensitively)
+pub const SUPPORTED_AUDIO_EXTENSIONS: &[&str] =
+    &["wav", "mp3", "flac", "m4a", "ogg", "mp4", "webm"];
+
+pub fn is_supported_audio_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| {
+            SUPPORTED_AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+        })
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WordTiming {
+    pub start: f64,
+    pub end: f64,
+    pub word: String,
+    pub probability: f64,
+    /// Speaker label from diarization, when it was run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// The segment's text tokens that make up this word, when tokens were requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<i64>>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TranscriptionSegment {
@@ -6,9 +48,71 @@ pub struct TranscriptionSegment {
     pub end: f64,
     pub text: String,
     pub no_speech_prob: f64,
+    /// Average token log probability; closer to zero is more confident
+    #[serde(default)]
+    pub avg_logprob: f64,
+    /// Per-word timings, present when word timestamps were requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<WordTiming>,
+    /// Speaker label such as "S1", from the speaker-turn heuristic when it was run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// Token ids the model decoded, timestamp tokens included, when tokens were requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<i64>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// `[00:01:02.500 -> 00:01:04.000] text`
+impl fmt::Display for TranscriptionSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{} -> {}] {}",
+            format_timestamp(self.start, TimestampStyle::Clock),
+            format_timestamp(self.end, TimestampStyle::Clock),
+            self.text.trim()
+        )
+    }
+}
+
+/// `TranscriptionResult::text_from_segments` one segment at a time, for callers that don't
+/// keep the segments
+#[derive(Debug, Clone, Default)]
+pub struct TextBuilder {
+    text: String,
+    previous_end: Option<f64>,
+    paragraph_gap: Option<f64>,
+}
+
+impl TextBuilder {
+    pub fn new(paragraph_gap: Option<f64>) -> Self {
+        Self {
+            paragraph_gap,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, segment: &TranscriptionSegment) {
+        let segment_text = segment.text.trim();
+        if segment_text.is_empty() {
+            return;
+        }
+        if let Some(end) = self.previous_end {
+            match self.paragraph_gap {
+                Some(gap) if segment.start - end > gap => self.text.push_str("\n\n"),
+                _ => self.text.push(' '),
+            }
+        }
+        self.text.push_str(segment_text);
+        self.previous_end = Some(segment.end);
+    }
+
+    pub fn finish(self) -> String {
+        self.text
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct TranscriptionResult {
     pub language: String,
     pub language_probability: f64,
@@ -17,9 +121,130 @@ pub struct TranscriptionResult {
     pub full_text: String,
     pub transcription_time: f64,
     pub real_time_factor: f64,
+    /// Loaded from a result cache instead of transcribed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// Filled in on request; see `stats()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<TranscriptStats>,
+    /// Set when personal data was masked; counts only, never the masked text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<RedactionSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chapters: Option<Vec<Chapter>>,
+    /// Present when segments carry heuristic speaker labels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speakers: Option<SpeakerTurns>,
+    /// Present when the text comes from a reference transcript aligned to the audio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alignment: Option<AlignmentSummary>,
+    /// Present when `allowed_languages` replaced the detected language
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_override: Option<LanguageOverride>,
+    /// Filled in on request; see `confident_text()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_text_confident: Option<ConfidentText>,
+    /// Set when something other than the backend decoded the audio, e.g. `ffmpeg` after
+    /// `--ffmpeg-fallback`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_decoder: Option<String>,
+    /// Where the time went, when whoever produced the result measured it. Kept out of
+    /// transcripts, since it describes the run rather than the audio.
+    #[serde(skip)]
+    pub timings: Option<PhaseTimings>,
 }
 
 impl TranscriptionResult {
+    /// Segment texts joined with spaces, or with a blank line where the pause before a
+    /// segment is longer than `paragraph_gap` seconds. Empty segments are left out.
+    pub fn text_from_segments(
+        segments: &[TranscriptionSegment],
+        paragraph_gap: Option<f64>,
+    ) -> String {
+        let mut text = TextBuilder::new(paragraph_gap);
+        for segment in segments {
+            text.push(segment);
+        }
+        text.finish()
+    }
+
+    /// Word count, speaking rate and silence figures
+    pub fn stats(&self) -> TranscriptStats {
+        TranscriptStats::from_result(self)
+    }
+
+    /// Chapters split at pauses; see `chapters::detect_chapters`
+    pub fn detect_chapters(&self, min_gap: f64, min_chapter_len: f64) -> Vec<Chapter> {
+        crate::chapters::detect_chapters(self, min_gap, min_chapter_len)
+    }
+
+    /// The text without segments that fail the thresholds; see `confidence::confident_text`
+    pub fn confident_text(&self, min_avg_logprob: f64, max_no_speech: f64) -> ConfidentText {
+        crate::confidence::confident_text(&self.segments, min_avg_logprob, max_no_speech)
+    }
+
+    /// Read whisper.cpp's `--output-json` output; see `import::whispercpp_json`
+    pub fn from_whispercpp_json(json: &str) -> crate::error::Result<Self> {
+        crate::import::whispercpp_json(json)
+    }
+
+    /// The result as an OpenAI `verbose_json` response; see `openai::verbose_json`
+    pub fn to_openai_verbose_json(&self, words: bool) -> crate::error::Result<VerboseJson> {
+        crate::openai::verbose_json(self, words)
+    }
+
+    /// The transcript in fixed time windows; see `window::windows`
+    pub fn windows(&self, window_secs: f64, step_secs: f64) -> crate::error::Result<Windows<'_>> {
+        crate::window::windows(self, window_secs, step_secs)
+    }
+
+    /// Where `query` was said; see `search::search`
+    pub fn find(&self, query: &str, options: SearchOptions) -> Vec<Match> {
+        crate::search::search(self, query, options)
+    }
+
+    /// Move every timestamp by `offset_secs`, e.g. `-90.0` after 90 seconds of pre-roll were
+    /// trimmed off the recording. Times are clamped at zero; segments, words and chapters
+    /// that end at or before zero are dropped.
+    pub fn shift(&mut self, offset_secs: f64) {
+        self.duration = (self.duration + offset_secs).max(0.0);
+        self.retime(|time| time + offset_secs);
+    }
+
+    /// Multiply every timestamp by `factor`, to correct the drift of resampled audio.
+    /// `factor` should be positive.
+    pub fn scale(&mut self, factor: f64) {
+        self.duration = (self.duration * factor).max(0.0);
+        self.retime(|time| time * factor);
+    }
+
+    fn retime(&mut self, map: impl Fn(f64) -> f64) {
+        let segment_count = self.segments.len();
+        self.segments.retain_mut(|segment| {
+            segment.end = map(segment.end);
+            segment.start = map(segment.start).max(0.0);
+            segment.words.retain_mut(|word| {
+                word.end = map(word.end);
+                word.start = map(word.start).max(0.0);
+                word.end > 0.0
+            });
+            segment.end > 0.0
+        });
+        if self.segments.len() != segment_count {
+            self.full_text = Self::text_from_segments(&self.segments, None);
+        }
+        if let Some(chapters) = &mut self.chapters {
+            chapters.retain_mut(|chapter| {
+                chapter.end = map(chapter.end);
+                chapter.start = map(chapter.start).max(0.0);
+                chapter.end > 0.0
+            });
+        }
+        if self.stats.is_some() {
+            self.stats = Some(self.stats());
+        }
+    }
+
     pub fn calculate_real_time_factor(&mut self, transcription_time: f64) {
         self.transcription_time = transcription_time;
         self.real_time_factor = if transcription_time > 0.0 {
@@ -28,31 +253,277 @@ impl TranscriptionResult {
             0.0
         };
     }
+
+    /// Add `elapsed` to `phase` of the timings, starting them if the backend kept none
+    pub fn add_timing(&mut self, phase: Phase, elapsed: Duration) {
+        self.timings
+            .get_or_insert_with(PhaseTimings::default)
+            .add(phase, elapsed);
+    }
+
+    /// Concatenate consecutive results, shifting each part's segments by its time offset.
+    ///
+    /// The merged language is the one covering the most audio; its probability is the
+    /// duration-weighted average over the parts detected in that language.
+    pub fn merge(parts: &[(f64, TranscriptionResult)]) -> TranscriptionResult {
+        let mut language_durations: Vec<(&str, f64)> = Vec::new();
+        for (_, part) in parts {
+            match language_durations
+                .iter_mut()
+                .find(|(language, _)| *language == part.language)
+            {
+                Some((_, total)) => *total += part.duration,
+                None => language_durations.push((&part.language, part.duration)),
+            }
+        }
+        let language = language_durations
+            .iter()
+            .fold(None::<(&str, f64)>, |best, &(language, total)| match best {
+                Some((_, best_total)) if best_total >= total => best,
+                _ => Some((language, total)),
+            })
+            .map(|(language, _)| language.to_string())
+            .unwrap_or_default();
+
+        let (weighted, weight) = parts
+            .iter()
+            .filter(|(_, part)| part.language == language)
+            .fold((0.0, 0.0), |(weighted, weight), (_, part)| {
+                (
+                    weighted + part.language_probability * part.duration,
+                    weight + part.duration,
+                )
+            });
+        let language_probability = if weight > 0.0 { weighted / weight } else { 0.0 };
+
+        let segments: Vec<TranscriptionSegment> = parts
+            .iter()
+            .flat_map(|(offset, part)| {
+                part.segments
+                    .iter()
+                    .map(move |segment| TranscriptionSegment {
+                        start: segment.start + offset,
+                        end: segment.end + offset,
+                        words: segment
+                            .words
+                            .iter()
+                            .map(|word| WordTiming {
+                                start: word.start + offset,
+                                end: word.end + offset,
+                                ..word.clone()
+                            })
+                            .collect(),
+                        ..segment.clone()
+                    })
+            })
+            .collect();
+        let full_text = parts
+            .iter()
+            .map(|(_, part)| part.full_text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut merged = TranscriptionResult {
+            language,
+            language_probability,
+            duration: parts.iter().fold(0.0, |end, (offset, part)| {
+                f64::max(end, offset + part.duration)
+            }),
+            segments,
+            full_text,
+            transcription_time: 0.0,
+            real_time_factor: 0.0,
+            ..Default::default()
+        };
+        merged.calculate_real_time_factor(
+            parts
+                .iter()
+                .fold(0.0, |total, (_, part)| total + part.transcription_time),
+        );
+        merged
+    }
+}
+
+/// Inference engine used to run a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Backend {
+    /// faster-whisper (CTranslate2) through the embedded Python interpreter
+    #[default]
+    #[serde(rename = "faster-whisper")]
+    FasterWhisper,
+    /// The whisper.cpp command line tool, with ggml models; needs the `whispercpp` feature
+    #[serde(rename = "whispercpp")]
+    WhisperCpp,
+    /// Pure-Rust inference with candle; needs the `candle` feature
+    #[serde(rename = "candle")]
+    Candle,
+}
+
+impl Backend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::FasterWhisper => "faster-whisper",
+            Backend::WhisperCpp => "whispercpp",
+            Backend::Candle => "candle",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "faster-whisper" | "faster_whisper" | "fasterwhisper" => Ok(Backend::FasterWhisper),
+            "whispercpp" | "whisper.cpp" | "whisper-cpp" => Ok(Backend::WhisperCpp),
+            "candle" => Ok(Backend::Candle),
+            other => Err(format!(
+                "Invalid backend: {} (expected faster-whisper, whispercpp or candle)",
+                other
+            )),
+        }
+    }
+}
+
+/// Hardware a model runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Device {
+    /// Let ctranslate2 pick (CUDA when available, otherwise CPU)
+    #[default]
+    Auto,
+    Cpu,
+    Cuda,
+    /// Apple Silicon
+    Mps,
+}
+
+impl Device {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Device::Auto => "auto",
+            Device::Cpu => "cpu",
+            Device::Cuda => "cuda",
+            Device::Mps => "mps",
+        }
+    }
+
+    fn is_gpu(&self) -> bool {
+        matches!(self, Device::Cuda | Device::Mps)
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Device {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Device::Auto),
+            "cpu" => Ok(Device::Cpu),
+            "cuda" => Ok(Device::Cuda),
+            "mps" | "metal" => Ok(Device::Mps),
+            other => Err(format!("Invalid device: {}", other)),
+        }
+    }
+}
+
+/// Precision of the model weights and computation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compute {
+    #[default]
+    Float16,
+    Float32,
+    Int8,
+    /// int8 weights with float16 computation, for GPUs
+    Int8Float16,
+}
+
+impl Compute {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compute::Float16 => "float16",
+            Compute::Float32 => "float32",
+            Compute::Int8 => "int8",
+            Compute::Int8Float16 => "int8_float16",
+        }
+    }
+}
+
+impl fmt::Display for Compute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Compute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "float16" => Ok(Compute::Float16),
+            "float32" => Ok(Compute::Float32),
+            "int8" => Ok(Compute::Int8),
+            "int8_float16" => Ok(Compute::Int8Float16),
+            other => Err(format!("Invalid compute type: {}", other)),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ModelConfig {
+    pub backend: Backend,
     pub model_size: String,
     pub device: String,
     pub compute_type: String,
+    /// Where models are downloaded to / loaded from (faster-whisper's `download_root`)
+    pub model_dir: Option<PathBuf>,
+    /// Never contact the Hugging Face hub; only use models already on disk
+    pub offline: bool,
+    /// CPU threads for inference; `None` leaves the choice to ctranslate2
+    pub cpu_threads: Option<usize>,
 }
 
 impl Default for ModelConfig {
     fn default() -> Self {
         Self {
+            backend: Backend::default(),
             model_size: "medium".to_string(),
             device: "auto".to_string(),
             compute_type: "float16".to_string(),
+            model_dir: None,
+            offline: false,
+            cpu_threads: None,
         }
     }
 }
 
 impl ModelConfig {
+    /// Build a config from typed fields, checking that they make sense together
+    pub fn builder() -> ModelConfigBuilder {
+        ModelConfigBuilder::default()
+    }
+
     pub fn new(model_size: &str, device: &str, compute_type: &str) -> Self {
         Self {
+            backend: Backend::default(),
             model_size: model_size.to_string(),
             device: device.to_string(),
             compute_type: compute_type.to_string(),
+            model_dir: None,
+            offline: false,
+            cpu_threads: None,
         }
     }
 
@@ -63,16 +534,27 @@ impl ModelConfig {
         )
     }
 
+    /// whisper.cpp also takes a path to a ggml model file in place of a size name
+    pub fn is_ggml_model_path(&self) -> bool {
+        let path = Path::new(&self.model_size);
+        self.backend == Backend::WhisperCpp
+            && path.extension().is_some_and(|ext| ext == "bin")
+            && path.is_file()
+    }
+
     pub fn is_valid_device(&self) -> bool {
         matches!(self.device.as_str(), "auto" | "cpu" | "cuda" | "mps")
     }
 
     pub fn is_valid_compute_type(&self) -> bool {
-        matches!(self.compute_type.as_str(), "float16" | "float32" | "int8")
+        matches!(
+            self.compute_type.as_str(),
+            "float16" | "float32" | "int8" | "int8_float16"
+        )
     }
 
     pub fn validate(&self) -> Result<(), String> {
-        if !self.is_valid_model_size() {
+        if !self.is_valid_model_size() && !self.is_ggml_model_path() {
             return Err(format!("Invalid model size: {}", self.model_size));
         }
         if !self.is_valid_device() {
@@ -84,3 +566,513 @@ impl ModelConfig {
         Ok(())
     }
 }
+
+/// Typed construction of a `ModelConfig`, from `ModelConfig::builder()`.
+///
+/// Unset fields keep `ModelConfig::default()`. `build` rejects combinations ctranslate2 would
+/// quietly run differently from what was asked, besides anything `validate` rejects.
+#[derive(Debug, Clone, Default)]
+pub struct ModelConfigBuilder {
+    backend: Backend,
+    model: Option<String>,
+    device: Device,
+    compute: Compute,
+    cpu_threads: Option<usize>,
+    model_dir: Option<PathBuf>,
+    offline: bool,
+}
+
+impl ModelConfigBuilder {
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// A model size such as `medium`, or a ggml file for whisper.cpp
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn device(mut self, device: Device) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn compute(mut self, compute: Compute) -> Self {
+        self.compute = compute;
+        self
+    }
+
+    pub fn cpu_threads(mut self, threads: usize) -> Self {
+        self.cpu_threads = Some(threads);
+        self
+    }
+
+    pub fn model_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.model_dir = Some(dir.into());
+        self
+    }
+
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn build(self) -> crate::error::Result<ModelConfig> {
+        self.check_combination(std::env::consts::OS)
+            .map_err(crate::error::TranscriptionError::ConfigError)?;
+        let config = ModelConfig {
+            backend: self.backend,
+            model_size: self
+                .model
+                .unwrap_or_else(|| ModelConfig::default().model_size),
+            device: self.device.to_string(),
+            compute_type: self.compute.to_string(),
+            model_dir: self.model_dir,
+            offline: self.offline,
+            cpu_threads: self.cpu_threads,
+        };
+        config
+            .validate()
+            .map_err(crate::error::TranscriptionError::ConfigError)?;
+        Ok(config)
+    }
+
+    /// Reject device/compute/thread combinations that can't do what they say on `os`
+    fn check_combination(&self, os: &str) -> Result<(), String> {
+        if self.device == Device::Cuda && os == "macos" {
+            return Err("CUDA isn't available on macOS; use device mps or cpu".to_string());
+        }
+        if self.device == Device::Mps
+            && matches!(self.compute, Compute::Int8 | Compute::Int8Float16)
+        {
+            return Err(format!(
+                "{} isn't supported on mps; ctranslate2 would silently fall back to another \
+                 compute type. Use float16, or device cpu for int8",
+                self.compute
+            ));
+        }
+        if self.device.is_gpu() && self.cpu_threads.is_some() {
+            return Err(format!(
+                "cpu_threads has no effect on device {}; leave it unset or use device cpu",
+                self.device
+            ));
+        }
+        if self.device == Device::Cpu && self.compute == Compute::Int8Float16 {
+            return Err(
+                "int8_float16 needs a GPU; ctranslate2 would run int8 on cpu. Use int8".to_string(),
+            );
+        }
+        if self.cpu_threads == Some(0) {
+            return Err("cpu_threads must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Decoding and VAD options passed to faster-whisper's `transcribe` call.
+///
+/// Unset decoding fields fall back to model-dependent defaults chosen by the transcriber.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionOptions {
+    pub language: Option<String>,
+    pub beam_size: Option<usize>,
+    pub best_of: Option<usize>,
+    pub temperature: Option<f64>,
+    pub word_timestamps: bool,
+    pub vad_filter: bool,
+    pub vad_threshold: f64,
+    /// Start a new paragraph in `full_text` after pauses longer than this many seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paragraph_gap: Option<f64>,
+    /// Restrict auto-detection to these language codes; ignored when `language` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_languages: Option<Vec<String>>,
+    /// Skip the audio before this many seconds; timestamps still count from the file's start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clip_start: Option<f64>,
+    /// Keep each segment's and word's token ids. Only faster-whisper has them; they roughly
+    /// double the size of the JSON.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_tokens: bool,
+}
+
+/// Largest beam size accepted. Beyond a handful, wider beams only cost time.
+pub const MAX_BEAM_SIZE: usize = 32;
+
+impl Default for TranscriptionOptions {
+    fn default() -> Self {
+        Self {
+            language: None,
+            beam_size: None,
+            best_of: None,
+            temperature: None,
+            word_timestamps: true,
+            vad_filter: true,
+            vad_threshold: 0.5,
+            paragraph_gap: None,
+            allowed_languages: None,
+            clip_start: None,
+            include_tokens: false,
+        }
+    }
+}
+
+impl TranscriptionOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.beam_size == Some(0) {
+            return Err("Beam size must be at least 1".to_string());
+        }
+        if let Some(beam_size) = self.beam_size.filter(|&b| b > MAX_BEAM_SIZE) {
+            return Err(format!(
+                "Beam size {} is too large; the maximum is {}",
+                beam_size, MAX_BEAM_SIZE
+            ));
+        }
+        if self.best_of == Some(0) {
+            return Err("best_of must be at least 1".to_string());
+        }
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=1.0).contains(&temperature) {
+                return Err(format!("Invalid temperature: {}", temperature));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.vad_threshold) {
+            return Err(format!("Invalid VAD threshold: {}", self.vad_threshold));
+        }
+        if let Some(gap) = self.paragraph_gap {
+            if !gap.is_finite() || gap < 0.0 {
+                return Err(format!("Invalid paragraph gap: {}", gap));
+            }
+        }
+        if let Some(start) = self.clip_start {
+            if !start.is_finite() || start < 0.0 {
+                return Err(format!("Invalid clip start: {}", start));
+            }
+        }
+        if let Some(allowed) = &self.allowed_languages {
+            if allowed.is_empty() {
+                return Err("allowed_languages must name at least one language".to_string());
+            }
+            if let Some(language) = &self.language {
+                if !allowed.contains(language) {
+                    return Err(format!(
+                        "Language {} is not one of the allowed languages ({})",
+                        language,
+                        allowed.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults_and_fields() {
+        let config = ModelConfig::builder()
+            .model("small")
+            .device(Device::Cpu)
+            .compute(Compute::Int8)
+            .cpu_threads(8)
+            .build()
+            .unwrap();
+        assert_eq!(config.model_size, "small");
+        assert_eq!(config.device, "cpu");
+        assert_eq!(config.compute_type, "int8");
+        assert_eq!(config.cpu_threads, Some(8));
+
+        let config = ModelConfig::builder().build().unwrap();
+        assert_eq!(config.model_size, ModelConfig::default().model_size);
+        assert_eq!(config.device, "auto");
+        assert_eq!(config.compute_type, "float16");
+        assert_eq!(config.cpu_threads, None);
+    }
+
+    #[test]
+    fn test_allowed_languages_validation() {
+        let restricted = TranscriptionOptions {
+            allowed_languages: Some(vec!["en".to_string(), "es".to_string()]),
+            ..Default::default()
+        };
+        assert!(restricted.validate().is_ok());
+        let forced = TranscriptionOptions {
+            language: Some("pt".to_string()),
+            ..restricted.clone()
+        };
+        assert!(forced.validate().unwrap_err().contains("allowed languages"));
+        let empty = TranscriptionOptions {
+            allowed_languages: Some(vec![]),
+            ..Default::default()
+        };
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_model() {
+        let err = ModelConfig::builder().model("huge").build().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: Invalid model size: huge"
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_cuda_on_macos() {
+        let builder = ModelConfig::builder().device(Device::Cuda);
+        assert_eq!(
+            builder.check_combination("macos").unwrap_err(),
+            "CUDA isn't available on macOS; use device mps or cpu"
+        );
+        assert!(builder.check_combination("linux").is_ok());
+    }
+
+    #[test]
+    fn test_builder_rejects_int8_on_mps() {
+        for compute in [Compute::Int8, Compute::Int8Float16] {
+            let err = ModelConfig::builder()
+                .device(Device::Mps)
+                .compute(compute)
+                .check_combination("macos")
+                .unwrap_err();
+            assert!(
+                err.starts_with(&format!("{} isn't supported on mps", compute)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_cpu_threads_on_gpu() {
+        let err = ModelConfig::builder()
+            .device(Device::Mps)
+            .cpu_threads(8)
+            .check_combination("macos")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "cpu_threads has no effect on device mps; leave it unset or use device cpu"
+        );
+        // auto may well end up on the CPU
+        assert!(ModelConfig::builder()
+            .cpu_threads(8)
+            .check_combination("linux")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_builder_rejects_int8_float16_on_cpu() {
+        let err = ModelConfig::builder()
+            .device(Device::Cpu)
+            .compute(Compute::Int8Float16)
+            .check_combination("linux")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "int8_float16 needs a GPU; ctranslate2 would run int8 on cpu. Use int8"
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_cpu_threads() {
+        let err = ModelConfig::builder()
+            .device(Device::Cpu)
+            .cpu_threads(0)
+            .check_combination("linux")
+            .unwrap_err();
+        assert_eq!(err, "cpu_threads must be at least 1");
+    }
+
+    #[test]
+    fn test_tokens_are_only_serialized_when_kept() {
+        let mut segment = TranscriptionSegment {
+            start: 0.0,
+            end: 1.0,
+            text: "Hi.".to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: -0.1,
+            words: vec![WordTiming {
+                start: 0.0,
+                end: 1.0,
+                word: " Hi.".to_string(),
+                probability: 0.9,
+                speaker: None,
+                tokens: None,
+            }],
+            speaker: None,
+            tokens: None,
+        };
+        let json = serde_json::to_string(&segment).unwrap();
+        assert!(!json.contains("tokens"));
+
+        segment.tokens = Some(vec![50_364, 2_421, 13, 50_414]);
+        segment.words[0].tokens = Some(vec![2_421, 13]);
+        let json = serde_json::to_string(&segment).unwrap();
+        assert!(json.contains(r#""tokens":[50364,2421,13,50414]"#));
+        assert!(json.contains(r#""tokens":[2421,13]"#));
+        let back: TranscriptionSegment = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.words[0].tokens, Some(vec![2_421, 13]));
+
+        // Off by default, and left out of the options' JSON so cache keys don't change
+        let options = TranscriptionOptions::default();
+        assert!(!serde_json::to_string(&options)
+            .unwrap()
+            .contains("include_tokens"));
+    }
+
+    #[test]
+    fn test_text_from_segments_paragraphs() {
+        let segment = |start: f64, end: f64, text: &str| TranscriptionSegment {
+            start,
+            end,
+            text: text.to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: 0.0,
+            words: vec![],
+            speaker: None,
+            tokens: None,
+        };
+        let segments = vec![
+            segment(0.0, 2.0, " Welcome back."),
+            segment(2.5, 4.0, "Today, tides."),
+            segment(4.0, 4.0, " "),
+            segment(7.0, 9.0, "First question?"),
+        ];
+        assert_eq!(
+            TranscriptionResult::text_from_segments(&segments, None),
+            "Welcome back. Today, tides. First question?"
+        );
+        assert_eq!(
+            TranscriptionResult::text_from_segments(&segments, Some(2.0)),
+            "Welcome back. Today, tides.\n\nFirst question?"
+        );
+        // The gap must be exceeded, not just reached
+        assert_eq!(
+            TranscriptionResult::text_from_segments(&segments, Some(3.0)),
+            "Welcome back. Today, tides. First question?"
+        );
+    }
+
+    fn timed_result() -> TranscriptionResult {
+        let word = |start: f64, end: f64, word: &str| WordTiming {
+            start,
+            end,
+            word: word.to_string(),
+            probability: 0.9,
+            speaker: None,
+            tokens: None,
+        };
+        let segments = vec![
+            TranscriptionSegment {
+                start: 80.0,
+                end: 88.0,
+                text: " Pre-roll.".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![word(80.0, 88.0, " Pre-roll.")],
+                speaker: None,
+                tokens: None,
+            },
+            TranscriptionSegment {
+                start: 88.0,
+                end: 94.0,
+                text: " Welcome to the show.".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![
+                    word(88.0, 91.0, " Welcome"),
+                    word(91.0, 94.0, " to the show."),
+                ],
+                speaker: None,
+                tokens: None,
+            },
+            TranscriptionSegment {
+                start: 100.0,
+                end: 102.5,
+                text: " First topic.".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            },
+        ];
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration: 120.0,
+            full_text: TranscriptionResult::text_from_segments(&segments, None),
+            segments,
+            transcription_time: 10.0,
+            real_time_factor: 12.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_shift_clamps_and_drops_before_zero() {
+        let mut result = timed_result();
+        result.chapters = Some(result.detect_chapters(5.0, 0.0));
+        result.stats = Some(result.stats());
+        result.shift(-90.0);
+
+        assert_eq!(result.duration, 30.0);
+        // The pre-roll segment ends before zero and is gone
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!(result.full_text, "Welcome to the show. First topic.");
+        // The one straddling zero starts at zero, keeping only the words after it
+        let straddling = &result.segments[0];
+        assert_eq!((straddling.start, straddling.end), (0.0, 4.0));
+        assert_eq!(straddling.words.len(), 2);
+        assert_eq!(
+            (straddling.words[0].start, straddling.words[0].end),
+            (0.0, 1.0)
+        );
+        assert_eq!(
+            (result.segments[1].start, result.segments[1].end),
+            (10.0, 12.5)
+        );
+
+        let chapters = result.chapters.as_ref().unwrap();
+        assert_eq!(chapters[0].start, 0.0);
+        assert_eq!(chapters.last().unwrap().start, 10.0);
+        assert_eq!(result.stats.as_ref().unwrap().word_count, 6);
+
+        // Everything before zero leaves nothing
+        result.shift(-60.0);
+        assert!(result.segments.is_empty());
+        assert_eq!(result.duration, 0.0);
+        assert_eq!(result.full_text, "");
+    }
+
+    #[test]
+    fn test_shift_forward_and_scale() {
+        let mut result = timed_result();
+        result.shift(10.0);
+        assert_eq!(result.segments[0].start, 90.0);
+        assert_eq!(result.segments[1].words[1].end, 104.0);
+        assert_eq!(result.duration, 130.0);
+        assert_eq!(
+            result.full_text,
+            "Pre-roll. Welcome to the show. First topic."
+        );
+
+        let mut result = timed_result();
+        result.scale(1.001);
+        assert!((result.segments[2].end - 102.6025).abs() < 1e-9);
+        assert!((result.duration - 120.12).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_device_and_compute_parse() {
+        assert_eq!("MPS".parse::<Device>(), Ok(Device::Mps));
+        assert_eq!("int8_float16".parse::<Compute>(), Ok(Compute::Int8Float16));
+        assert_eq!("tpu".parse::<Device>().unwrap_err(), "Invalid device: tpu");
+    }
+}
@@ -0,0 +1,306 @@
+use crate::types::{TranscriptionResult, TranscriptionSegment};
+use std::borrow::Cow;
+
+/// Wraps uncertain spans in TXT output unless other markers are given
+pub const DEFAULT_MARKERS: (&str, &str) = ("⟦", "⟧");
+/// Markdown marks uncertain spans as emphasis
+const MARKDOWN_MARKER: &str = "*";
+
+/// Marks words the model was unsure of in the text formats, so an editor knows where to
+/// listen again. The JSON output is never marked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UncertainMarking {
+    /// Words below this probability are marked; segments without word timings are marked
+    /// whole when `exp(avg_logprob)` is below it
+    pub threshold: f64,
+    /// Put before an uncertain span in TXT output
+    pub open: String,
+    /// Put after an uncertain span in TXT output
+    pub close: String,
+}
+
+impl UncertainMarking {
+    pub fn new(threshold: f64) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(format!(
+                "Uncertainty threshold must be a probability between 0 and 1, got {}",
+                threshold
+            ));
+        }
+        Ok(Self {
+            threshold,
+            open: DEFAULT_MARKERS.0.to_string(),
+            close: DEFAULT_MARKERS.1.to_string(),
+        })
+    }
+
+    /// Use the markers in `OPEN…CLOSE` (or `OPEN...CLOSE`), such as `[?…?]`
+    pub fn with_markers(mut self, markers: &str) -> Result<Self, String> {
+        let (open, close) = markers
+            .split_once('…')
+            .or_else(|| markers.split_once("..."))
+            .filter(|(open, close)| !open.is_empty() && !close.is_empty())
+            .ok_or_else(|| format!("Expected markers as OPEN…CLOSE, got '{}'", markers))?;
+        self.open = open.to_string();
+        self.close = close.to_string();
+        Ok(self)
+    }
+
+    /// `segment` as words, each marked uncertain or not.
+    ///
+    /// Word timings are only used when they spell out the segment's text; after vocabulary
+    /// replacements they may not, and the segment is judged as a whole instead.
+    pub fn pieces<'a>(&self, segment: &'a TranscriptionSegment) -> Vec<Piece<'a>> {
+        let joined: String = segment.words.iter().map(|w| w.word.as_str()).collect();
+        if segment.words.is_empty() || joined.trim() != segment.text.trim() {
+            let uncertain = segment.avg_logprob.exp() < self.threshold;
+            return plain_pieces(segment)
+                .into_iter()
+                .map(|piece| Piece { uncertain, ..piece })
+                .collect();
+        }
+        let mut pieces: Vec<Piece<'a>> = segment
+            .words
+            .iter()
+            .filter(|word| !word.word.trim().is_empty())
+            .map(|word| {
+                let text = word.word.trim();
+                let leading = word.word.len() - word.word.trim_start().len();
+                Piece {
+                    separator: &word.word[..leading],
+                    text,
+                    uncertain: word.probability < self.threshold,
+                }
+            })
+            .collect();
+        if let Some(first) = pieces.first_mut() {
+            first.separator = "";
+        }
+        pieces
+    }
+
+    /// Join `pieces` for TXT output, wrapping each run of uncertain ones in the markers
+    pub fn render_text(&self, pieces: &[Piece<'_>]) -> String {
+        join(pieces, &self.open, &self.close, Cow::Borrowed)
+    }
+}
+
+/// A word, or a whole segment, of rendered text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Piece<'a> {
+    /// Whitespace before the text: `""` for the first piece, `"\n\n"` for a new paragraph
+    pub separator: &'a str,
+    pub text: &'a str,
+    pub uncertain: bool,
+}
+
+/// `segment`'s text as a single unmarked piece, or nothing when it's empty
+pub fn plain_pieces(segment: &TranscriptionSegment) -> Vec<Piece<'_>> {
+    let text = segment.text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    vec![Piece {
+        separator: "",
+        text,
+        uncertain: false,
+    }]
+}
+
+/// Join `pieces` for Markdown, wrapping each run of uncertain ones in `*…*`. Markdown's
+/// inline syntax is escaped when anything is marked, so a `*` that was said can't be taken
+/// for a marker.
+pub fn render_markdown(pieces: &[Piece<'_>]) -> String {
+    if pieces.iter().any(|piece| piece.uncertain) {
+        join(pieces, MARKDOWN_MARKER, MARKDOWN_MARKER, escape_markdown)
+    } else {
+        join(pieces, "", "", Cow::Borrowed)
+    }
+}
+
+/// The TXT rendition of `result` with uncertain spans marked, keeping the paragraph
+/// breaks of `full_text`
+pub fn mark_text(result: &TranscriptionResult, marking: &UncertainMarking) -> String {
+    let mut pieces = Vec::new();
+    let mut rest = result.full_text.as_str();
+    for segment in &result.segments {
+        let mut segment_pieces = marking.pieces(segment);
+        let Some(first) = segment_pieces.first_mut() else {
+            continue;
+        };
+        let trimmed = rest.trim_start();
+        if !pieces.is_empty() {
+            first.separator = if rest[..rest.len() - trimmed.len()].contains('\n') {
+                "\n\n"
+            } else {
+                " "
+            };
+        }
+        rest = trimmed.strip_prefix(segment.text.trim()).unwrap_or(trimmed);
+        pieces.extend(segment_pieces);
+    }
+    marking.render_text(&pieces)
+}
+
+/// Adjacent uncertain pieces share one span; a span never runs across a paragraph break
+fn join<'a>(
+    pieces: &[Piece<'a>],
+    open: &str,
+    close: &str,
+    escape: fn(&'a str) -> Cow<'a, str>,
+) -> String {
+    let mut out = String::new();
+    let mut in_span = false;
+    for piece in pieces {
+        if in_span && (!piece.uncertain || piece.separator.contains('\n')) {
+            out.push_str(close);
+            in_span = false;
+        }
+        out.push_str(piece.separator);
+        if piece.uncertain && !in_span {
+            out.push_str(open);
+            in_span = true;
+        }
+        out.push_str(&escape(piece.text));
+    }
+    if in_span {
+        out.push_str(close);
+    }
+    out
+}
+
+fn escape_markdown(text: &str) -> Cow<'_, str> {
+    const SPECIAL: &[char] = &['\\', '*', '_', '`', '[', ']'];
+    if !text.contains(SPECIAL) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 4);
+    for c in text.chars() {
+        if SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WordTiming;
+
+    fn segment(words: &[(&str, f64)]) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start: 0.0,
+            end: 3.0,
+            text: words.iter().map(|(word, _)| *word).collect(),
+            no_speech_prob: 0.01,
+            avg_logprob: -0.2,
+            words: words
+                .iter()
+                .map(|&(word, probability)| WordTiming {
+                    start: 0.0,
+                    end: 0.5,
+                    word: word.to_string(),
+                    probability,
+                    speaker: None,
+                    tokens: None,
+                })
+                .collect(),
+            speaker: None,
+            tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_adjacent_uncertain_words_share_a_span() {
+        let marking = UncertainMarking::new(0.5).unwrap();
+        let segment = segment(&[
+            (" The", 0.9),
+            (" quay", 0.3),
+            (" side", 0.2),
+            (" opens", 0.8),
+            (" at", 0.95),
+            (" nine.", 0.4),
+        ]);
+        assert_eq!(
+            marking.render_text(&marking.pieces(&segment)),
+            "The ⟦quay side⟧ opens at ⟦nine.⟧"
+        );
+
+        let custom = marking.with_markers("[?...?]").unwrap();
+        assert_eq!(
+            custom.render_text(&custom.pieces(&segment)),
+            "The [?quay side?] opens at [?nine.?]"
+        );
+        assert!(UncertainMarking::new(1.5).is_err());
+        assert!(UncertainMarking::new(0.5)
+            .unwrap()
+            .with_markers("<<>>")
+            .is_err());
+    }
+
+    #[test]
+    fn test_segments_without_matching_words_are_judged_whole() {
+        let marking = UncertainMarking::new(0.5).unwrap();
+        let mut unsure = segment(&[]);
+        unsure.text = " Something about tides.".to_string();
+        unsure.avg_logprob = -1.2;
+        assert_eq!(
+            marking.render_text(&marking.pieces(&unsure)),
+            "⟦Something about tides.⟧"
+        );
+
+        // A replacement rewrote the text, so the words no longer apply
+        let mut replaced = segment(&[(" pi", 0.1), (" oh", 0.1), (" three", 0.1)]);
+        replaced.text = " PyO3".to_string();
+        assert_eq!(marking.render_text(&marking.pieces(&replaced)), "PyO3");
+    }
+
+    #[test]
+    fn test_markdown_escapes_text_around_markers() {
+        let marking = UncertainMarking::new(0.5).unwrap();
+        let segment = segment(&[
+            (" use", 0.9),
+            (" *args", 0.3),
+            (" and", 0.2),
+            (" snake_case", 0.9),
+        ]);
+        assert_eq!(
+            render_markdown(&marking.pieces(&segment)),
+            r"use *\*args and* snake\_case"
+        );
+
+        // Nothing marked leaves the text as it is
+        let sure = UncertainMarking::new(0.1).unwrap();
+        assert_eq!(
+            render_markdown(&sure.pieces(&segment)),
+            "use *args and snake_case"
+        );
+    }
+
+    #[test]
+    fn test_mark_text_keeps_paragraphs() {
+        let marking = UncertainMarking::new(0.5).unwrap();
+        let segments = vec![
+            segment(&[(" First", 0.9), (" maybe.", 0.2)]),
+            segment(&[(" Perhaps", 0.3), (" not.", 0.9)]),
+            segment(&[(" Later", 0.1), (" on.", 0.9)]),
+        ];
+        let result = TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.99,
+            duration: 9.0,
+            segments,
+            full_text: "First maybe. Perhaps not.\n\nLater on.".to_string(),
+            transcription_time: 1.0,
+            real_time_factor: 9.0,
+            ..Default::default()
+        };
+        // The span runs on into the next segment but not into the next paragraph
+        assert_eq!(
+            mark_text(&result, &marking),
+            "First ⟦maybe. Perhaps⟧ not.\n\n⟦Later⟧ on."
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A stretch of audio the voice activity detector judged to be speech, in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeechRegion {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl SpeechRegion {
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).max(0.0)
+    }
+}
+
+/// Silero VAD settings for `FasterWhisperTranscriber::detect_speech`. Unset fields keep
+/// faster-whisper's defaults, which differ between its versions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VadOptions {
+    /// Speech probability above which a frame counts as speech
+    pub threshold: f64,
+    /// Shorter bursts of speech are dropped
+    pub min_speech_duration_ms: Option<u64>,
+    /// Silence needed to end a region
+    pub min_silence_duration_ms: Option<u64>,
+    /// Padding added to both sides of each region
+    pub speech_pad_ms: Option<u64>,
+}
+
+impl Default for VadOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            min_speech_duration_ms: None,
+            min_silence_duration_ms: None,
+            speech_pad_ms: None,
+        }
+    }
+}
+
+impl VadOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(format!("Invalid VAD threshold: {}", self.threshold));
+        }
+        Ok(())
+    }
+}
+
+/// Speech found in one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeechReport {
+    pub input: PathBuf,
+    /// Length of the decoded audio in seconds
+    pub duration: f64,
+    /// Empty when the file has no speech
+    pub regions: Vec<SpeechRegion>,
+    pub speech_seconds: f64,
+    /// Share of the duration that is speech, 0 to 1
+    pub speech_ratio: f64,
+}
+
+impl SpeechReport {
+    pub fn new(input: PathBuf, duration: f64, regions: Vec<SpeechRegion>) -> Self {
+        let speech_seconds: f64 = regions.iter().map(SpeechRegion::duration).sum();
+        Self {
+            input,
+            duration,
+            speech_ratio: if duration > 0.0 {
+                (speech_seconds / duration).min(1.0)
+            } else {
+                0.0
+            },
+            speech_seconds,
+            regions,
+        }
+    }
+
+    pub fn has_speech(&self) -> bool {
+        !self.regions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speech_report_totals() {
+        let regions = vec![
+            SpeechRegion {
+                start: 1.0,
+                end: 3.5,
+            },
+            SpeechRegion {
+                start: 6.0,
+                end: 8.5,
+            },
+        ];
+        let report = SpeechReport::new("a.wav".into(), 10.0, regions);
+        assert_eq!(report.speech_seconds, 5.0);
+        assert_eq!(report.speech_ratio, 0.5);
+        assert!(report.has_speech());
+
+        let silent = SpeechReport::new("b.wav".into(), 0.0, Vec::new());
+        assert_eq!((silent.speech_seconds, silent.speech_ratio), (0.0, 0.0));
+        assert!(!silent.has_speech());
+        assert!(VadOptions {
+            threshold: 1.5,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::error::{Result, TranscriptionError};
+use crate::types::TranscriptionOptions;
+use std::fmt;
+use std::str::FromStr;
+
+/// A `major.minor.patch` release number. Pre-release and local suffixes are ignored, so
+/// `1.1.0rc1` and `0.10.1.dev0` compare as `1.1.0` and `0.10.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut numbers = [0u32; 3];
+        let mut parsed = 0;
+        for (slot, component) in numbers.iter_mut().zip(s.trim().split('.')) {
+            let digits: String = component
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            if digits.is_empty() {
+                break;
+            }
+            *slot = digits
+                .parse()
+                .map_err(|_| format!("Invalid version: {}", s))?;
+            parsed += 1;
+            if digits.len() < component.len() {
+                // `0rc1` and the like end the release number
+                break;
+            }
+        }
+        if parsed == 0 {
+            return Err(format!("Invalid version: {}", s));
+        }
+        Ok(Self::new(numbers[0], numbers[1], numbers[2]))
+    }
+}
+
+/// Oldest faster-whisper release supporting each transcription option.
+///
+/// This is the only place minimum versions are recorded; add a row alongside any new option
+/// that older releases would reject with a `TypeError`.
+pub const OPTION_REQUIREMENTS: &[(&str, Version)] = &[
+    ("vad_filter", Version::new(0, 2, 0)),
+    ("word_timestamps", Version::new(0, 3, 0)),
+    ("clip_timestamps", Version::new(1, 0, 0)),
+];
+
+/// Minimum version recorded for `option`
+pub fn required_version(option: &str) -> Option<Version> {
+    OPTION_REQUIREMENTS
+        .iter()
+        .find(|(name, _)| *name == option)
+        .map(|(_, version)| *version)
+}
+
+/// Names of the options `options` actually turns on
+fn requested_options(options: &TranscriptionOptions) -> Vec<&'static str> {
+    let mut requested = Vec::new();
+    if options.vad_filter {
+        requested.push("vad_filter");
+    }
+    if options.word_timestamps {
+        requested.push("word_timestamps");
+    }
+    if options.clip_start.is_some() {
+        requested.push("clip_timestamps");
+    }
+    requested
+}
+
+/// Fail with `RequiresVersion` for the first requested option `installed` is too old for
+pub fn check_options(options: &TranscriptionOptions, installed: Version) -> Result<()> {
+    for option in requested_options(options) {
+        if let Some(need) = required_version(option).filter(|need| installed < *need) {
+            return Err(TranscriptionError::RequiresVersion {
+                option: option.to_string(),
+                have: installed.to_string(),
+                need: need.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!("1.0.3".parse(), Ok(Version::new(1, 0, 3)));
+        assert_eq!("0.10.1.dev0".parse(), Ok(Version::new(0, 10, 1)));
+        assert_eq!("1.1.0rc1".parse(), Ok(Version::new(1, 1, 0)));
+        assert_eq!("2".parse(), Ok(Version::new(2, 0, 0)));
+        assert!("dev".parse::<Version>().is_err());
+        assert!(Version::new(0, 10, 0) > Version::new(0, 9, 9));
+        assert_eq!(Version::new(1, 0, 3).to_string(), "1.0.3");
+    }
+
+    #[test]
+    fn test_requirement_table() {
+        for (i, (name, _)) in OPTION_REQUIREMENTS.iter().enumerate() {
+            assert!(
+                !OPTION_REQUIREMENTS[i + 1..].iter().any(|(n, _)| n == name),
+                "{} listed twice",
+                name
+            );
+        }
+        assert_eq!(
+            required_version("word_timestamps"),
+            Some(Version::new(0, 3, 0))
+        );
+        assert_eq!(required_version("beam_size"), None);
+    }
+
+    #[test]
+    fn test_check_options() {
+        let options = TranscriptionOptions::default();
+        assert!(check_options(&options, Version::new(1, 0, 0)).is_ok());
+
+        let err = check_options(&options, Version::new(0, 2, 5)).unwrap_err();
+        assert_eq!(err.kind(), "requires_version");
+        assert_eq!(
+            err.to_string(),
+            "word_timestamps requires faster-whisper >= 0.3.0 (installed: 0.2.5)"
+        );
+
+        let plain = TranscriptionOptions {
+            word_timestamps: false,
+            vad_filter: false,
+            ..Default::default()
+        };
+        assert!(check_options(&plain, Version::new(0, 1, 0)).is_ok());
+
+        let clipped = TranscriptionOptions {
+            clip_start: Some(90.0),
+            ..Default::default()
+        };
+        let err = check_options(&clipped, Version::new(0, 10, 1)).unwrap_err();
+        assert!(err.to_string().starts_with("clip_timestamps requires"));
+    }
+}
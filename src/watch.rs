@@ -0,0 +1,240 @@
+use crate::error::{Result, TranscriptionError};
+use crate::types::is_supported_audio_file;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Name of the subfolder finished sources are moved into with `move_done`
+pub const DONE_DIR_NAME: &str = "done";
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// A new file is only picked up once its size hasn't changed for this long
+    pub stable_for: Duration,
+    /// How often pending files are re-checked
+    pub poll_interval: Duration,
+    /// Move each processed source into `<dir>/done/`
+    pub move_done: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            stable_for: Duration::from_secs(2),
+            poll_interval: Duration::from_millis(500),
+            move_done: false,
+        }
+    }
+}
+
+/// Debounces files that are still being written: a file is ready once its size has been
+/// stable for `stable_for`.
+#[derive(Debug)]
+pub struct StabilityTracker {
+    stable_for: Duration,
+    pending: HashMap<PathBuf, (u64, Instant)>,
+}
+
+impl StabilityTracker {
+    pub fn new(stable_for: Duration) -> Self {
+        Self {
+            stable_for,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Record the current size of `path`; a changed size restarts its stability timer
+    pub fn observe(&mut self, path: &Path, size: u64, now: Instant) {
+        match self.pending.get_mut(path) {
+            Some((known_size, changed_at)) if *known_size != size => {
+                *known_size = size;
+                *changed_at = now;
+            }
+            Some(_) => {}
+            None => {
+                self.pending.insert(path.to_path_buf(), (size, now));
+            }
+        }
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.pending.remove(path);
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &Path> {
+        self.pending.keys().map(PathBuf::as_path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Remove and return every file whose size has been stable long enough, oldest first
+    pub fn take_ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready: Vec<(PathBuf, Instant)> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, changed_at))| now.duration_since(*changed_at) >= self.stable_for)
+            .map(|(path, (_, changed_at))| (path.clone(), *changed_at))
+            .collect();
+        ready.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        for (path, _) in &ready {
+            self.pending.remove(path);
+        }
+        ready.into_iter().map(|(path, _)| path).collect()
+    }
+}
+
+/// Move a processed source into `<dir>/done/`, returning its new location
+pub fn move_to_done(dir: &Path, path: &Path) -> Result<PathBuf> {
+    let done_dir = dir.join(DONE_DIR_NAME);
+    std::fs::create_dir_all(&done_dir)?;
+    let file_name = path.file_name().ok_or_else(|| {
+        TranscriptionError::InvalidPath(format!("No file name: {}", path.display()))
+    })?;
+    let destination = done_dir.join(file_name);
+    std::fs::rename(path, &destination)?;
+    Ok(destination)
+}
+
+/// Watch `dir` for new audio files and call `handler` for each once it has stopped growing.
+///
+/// Runs until `shutdown` is set. The flag is only checked between files, so a file being
+/// handled when shutdown is requested is always finished. The handler is expected to report
+/// its own failures; errors it returns are only logged at debug level and the watch continues.
+pub fn watch_directory<F>(
+    dir: &Path,
+    options: &WatchOptions,
+    shutdown: &AtomicBool,
+    mut handler: F,
+) -> Result<()>
+where
+    F: FnMut(&Path) -> Result<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .map_err(|e| {
+        TranscriptionError::InvalidPath(format!("Cannot watch {}: {}", dir.display(), e))
+    })?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| {
+            TranscriptionError::InvalidPath(format!("Cannot watch {}: {}", dir.display(), e))
+        })?;
+
+    // Event paths are absolute, so compare parents against the canonical directory
+    let watch_root = dir.canonicalize()?;
+    info!("👀 Watching {} for new audio files", dir.display());
+    let mut tracker = StabilityTracker::new(options.stable_for);
+
+    while !shutdown.load(Ordering::SeqCst) {
+        match rx.recv_timeout(options.poll_interval) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        let in_root = path.parent().and_then(|p| p.canonicalize().ok())
+                            == Some(watch_root.clone());
+                        if in_root && is_supported_audio_file(&path) {
+                            if let Ok(metadata) = std::fs::metadata(&path) {
+                                tracker.observe(&path, metadata.len(), Instant::now());
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => warn!("Watch error: {}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        // Re-stat pending files so growth without further events still resets the timer
+        let now = Instant::now();
+        let pending: Vec<PathBuf> = tracker.pending().map(Path::to_path_buf).collect();
+        for path in pending {
+            match std::fs::metadata(&path) {
+                Ok(metadata) => tracker.observe(&path, metadata.len(), now),
+                Err(_) => tracker.forget(&path),
+            }
+        }
+
+        for path in tracker.take_ready(now) {
+            if shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            match handler(&path) {
+                Ok(()) if options.move_done => match move_to_done(dir, &path) {
+                    Ok(destination) => {
+                        info!("Moved {} to {}", path.display(), destination.display())
+                    }
+                    Err(e) => warn!("Could not move {} to done/: {}", path.display(), e),
+                },
+                Ok(()) => {}
+                Err(e) => debug!("Handler failed for {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    info!("Stopped watching {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_growing_file_resets_timer() {
+        let start = Instant::now();
+        let secs = |s: u64| start + Duration::from_secs(s);
+        let path = Path::new("incoming.wav");
+        let mut tracker = StabilityTracker::new(Duration::from_secs(3));
+
+        tracker.observe(path, 100, secs(0));
+        tracker.observe(path, 200, secs(2));
+        assert!(tracker.take_ready(secs(4)).is_empty(), "grew at t=2");
+
+        tracker.observe(path, 200, secs(4));
+        assert_eq!(
+            tracker.take_ready(secs(5)),
+            vec![PathBuf::from("incoming.wav")]
+        );
+        assert!(tracker.is_empty(), "ready files are handed out once");
+    }
+
+    #[test]
+    fn test_ready_in_arrival_order() {
+        let start = Instant::now();
+        let mut tracker = StabilityTracker::new(Duration::from_secs(1));
+
+        tracker.observe(Path::new("b.wav"), 10, start);
+        tracker.observe(Path::new("a.wav"), 10, start + Duration::from_millis(500));
+        tracker.observe(Path::new("c.wav"), 10, start + Duration::from_secs(5));
+
+        let ready = tracker.take_ready(start + Duration::from_secs(2));
+        assert_eq!(ready, vec![PathBuf::from("b.wav"), PathBuf::from("a.wav")]);
+
+        tracker.forget(Path::new("c.wav"));
+        assert!(tracker
+            .take_ready(start + Duration::from_secs(60))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_move_to_done() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("call.wav");
+        std::fs::write(&source, b"audio").unwrap();
+
+        let destination = move_to_done(dir.path(), &source).unwrap();
+        assert_eq!(destination, dir.path().join(DONE_DIR_NAME).join("call.wav"));
+        assert!(destination.exists());
+        assert!(!source.exists());
+    }
+}
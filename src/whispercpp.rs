@@ -0,0 +1,332 @@
+use crate::backend::{TranscriptionBackend, SAMPLE_RATE};
+use crate::error::{Result, TranscriptionError};
+use crate::import;
+use crate::probe;
+use crate::timing::Phase;
+use crate::transcriber::validate_audio_path;
+use crate::types::{ModelConfig, TranscriptionOptions, TranscriptionResult};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use tracing::{debug, info};
+
+/// whisper.cpp's CLI, looked up on `PATH` unless `WHISPER_CPP_BIN` names another binary
+pub const DEFAULT_BINARY: &str = "whisper-cli";
+pub const BINARY_ENV_VAR: &str = "WHISPER_CPP_BIN";
+/// Where ggml models are looked for when no model directory is configured
+pub const DEFAULT_MODEL_DIR: &str = "models";
+
+/// Runs whisper.cpp's command line tool, so no Python runtime is needed.
+///
+/// whisper.cpp builds use Metal automatically on Apple Silicon; `device = "cpu"` turns GPU use
+/// off. Models are ggml files such as `ggml-base.bin`.
+pub struct WhisperCppTranscriber {
+    config: ModelConfig,
+    options: TranscriptionOptions,
+    binary: PathBuf,
+}
+
+impl WhisperCppTranscriber {
+    pub fn new(config: ModelConfig) -> Result<Self> {
+        config
+            .validate()
+            .map_err(TranscriptionError::ModelInitError)?;
+        let binary = std::env::var_os(BINARY_ENV_VAR)
+            .filter(|bin| !bin.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BINARY));
+
+        Ok(Self {
+            config,
+            options: TranscriptionOptions::default(),
+            binary,
+        })
+    }
+
+    /// Replace the default decoding options used by `transcribe`
+    pub fn with_options(mut self, options: TranscriptionOptions) -> Result<Self> {
+        options
+            .validate()
+            .map_err(TranscriptionError::ModelInitError)?;
+        self.options = options;
+        Ok(self)
+    }
+
+    /// Use a specific whisper.cpp binary instead of `whisper-cli` from `PATH`
+    pub fn with_binary<P: Into<PathBuf>>(mut self, binary: P) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    pub fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    pub fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    /// The ggml model file: `model_size` itself when it is a path, otherwise
+    /// `<model_dir>/ggml-<size>.bin`
+    pub fn model_path(&self) -> PathBuf {
+        if self.config.is_ggml_model_path() {
+            return PathBuf::from(&self.config.model_size);
+        }
+        self.config
+            .model_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_MODEL_DIR))
+            .join(format!("ggml-{}.bin", self.config.model_size))
+    }
+
+    fn existing_model_path(&self) -> Result<PathBuf> {
+        let model_path = self.model_path();
+        if !model_path.is_file() {
+            return Err(TranscriptionError::ModelInitError(format!(
+                "ggml model not found: {} (download it with whisper.cpp's models/download-ggml-model.sh)",
+                model_path.display()
+            )));
+        }
+        Ok(model_path)
+    }
+
+    pub fn transcribe<P: AsRef<Path>>(&self, audio_path: P) -> Result<TranscriptionResult> {
+        self.transcribe_with_options(audio_path, &self.options)
+    }
+
+    /// Transcribe using per-call options instead of the transcriber defaults.
+    ///
+    /// Word timestamps, the VAD filter and `allowed_languages` aren't supported by this backend
+    /// and are ignored.
+    pub fn transcribe_with_options<P: AsRef<Path>>(
+        &self,
+        audio_path: P,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let audio_path = audio_path.as_ref();
+        // A missing model fails before the audio is read
+        let model_path = self.existing_model_path()?;
+        validate_audio_path(audio_path)?;
+
+        let output_prefix =
+            std::env::temp_dir().join(format!("whispercpp-{}", uuid::Uuid::new_v4()));
+        let mut command = Command::new(&self.binary);
+        command
+            .arg("--model")
+            .arg(&model_path)
+            .arg("--file")
+            .arg(audio_path)
+            .arg("--output-json-full")
+            .arg("--output-file")
+            .arg(&output_prefix)
+            .arg("--no-prints")
+            .arg("--language")
+            .arg(options.language.as_deref().unwrap_or("auto"));
+        if let Some(beam_size) = options.beam_size {
+            command.arg("--beam-size").arg(beam_size.to_string());
+        }
+        if let Some(best_of) = options.best_of {
+            command.arg("--best-of").arg(best_of.to_string());
+        }
+        if let Some(temperature) = options.temperature {
+            command.arg("--temperature").arg(temperature.to_string());
+        }
+        if let Some(start) = options.clip_start {
+            // whisper.cpp keeps timestamps relative to the start of the file
+            command
+                .arg("--offset-t")
+                .arg(((start * 1000.0).round() as u64).to_string());
+        }
+        if self.config.device == "cpu" {
+            command.arg("--no-gpu");
+        }
+
+        info!(
+            "Starting whisper.cpp transcription for: {}",
+            audio_path.display()
+        );
+        debug!("Running {:?}", command);
+        let start_time = Instant::now();
+        let output = command.output().map_err(|e| {
+            TranscriptionError::ModelInitError(format!(
+                "Failed to run whisper.cpp binary {} (set {} to its path): {}",
+                self.binary.display(),
+                BINARY_ENV_VAR,
+                e
+            ))
+        })?;
+        let elapsed = start_time.elapsed();
+        let transcription_time = elapsed.as_secs_f64();
+
+        let json_path = output_prefix.with_extension("json");
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&json_path);
+            return Err(TranscriptionError::TranscriptionFailed(format!(
+                "whisper.cpp exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let json = std::fs::read_to_string(&json_path);
+        let _ = std::fs::remove_file(&json_path);
+
+        let mut result = import::whispercpp_json(&json?)?;
+        if options.paragraph_gap.is_some() {
+            result.full_text =
+                TranscriptionResult::text_from_segments(&result.segments, options.paragraph_gap);
+        }
+        if let Some(duration) = probe::probe_duration(audio_path) {
+            result.duration = duration;
+        }
+        result.calculate_real_time_factor(transcription_time);
+        // Loading, decoding and inference all happen inside the one process
+        result.add_timing(Phase::Inference, elapsed);
+        info!(
+            "Transcription completed in {:.2}s ({:.2}x real-time)",
+            result.transcription_time, result.real_time_factor
+        );
+        Ok(result)
+    }
+}
+
+/// Each run is a separate process, so no model stays loaded between transcriptions
+impl TranscriptionBackend for WhisperCppTranscriber {
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    fn load(&self) -> Result<()> {
+        self.existing_model_path().map(|_| ())
+    }
+
+    fn is_loaded(&self) -> bool {
+        false
+    }
+
+    fn transcribe_path(
+        &self,
+        audio_path: &Path,
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_with_options(audio_path, options)
+            .map_err(|e| e.with_path(audio_path))
+    }
+
+    /// The CLI only reads files, so the samples go through a temporary WAV file
+    fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let wav_path =
+            std::env::temp_dir().join(format!("whispercpp-{}.wav", uuid::Uuid::new_v4()));
+        write_wav(&wav_path, samples)?;
+        let result = self.transcribe_with_options(&wav_path, options);
+        let _ = std::fs::remove_file(&wav_path);
+        let mut result = result?;
+        result.duration = samples.len() as f64 / SAMPLE_RATE as f64;
+        Ok(result)
+    }
+
+    fn device_info(&self) -> Result<String> {
+        let gpu = if self.config.device == "cpu" {
+            "GPU disabled"
+        } else {
+            "GPU when available"
+        };
+        Ok(format!("whisper.cpp ({}), {}", self.binary.display(), gpu))
+    }
+
+    fn unload(&self) -> bool {
+        false
+    }
+}
+
+/// Write mono `SAMPLE_RATE` samples as a 16-bit PCM WAV file
+fn write_wav(path: &Path, samples: &[f32]) -> std::io::Result<()> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // bytes per second
+    bytes.extend_from_slice(&2u16.to_le_bytes()); // bytes per frame
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    std::fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Backend;
+
+    #[test]
+    fn test_model_path() {
+        let mut config = ModelConfig::new("base", "auto", "float16");
+        config.backend = Backend::WhisperCpp;
+        config.model_dir = Some(PathBuf::from("/opt/ggml"));
+        let transcriber = WhisperCppTranscriber::new(config).unwrap();
+        assert_eq!(
+            transcriber.model_path(),
+            PathBuf::from("/opt/ggml/ggml-base.bin")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let model = dir.path().join("ggml-custom.bin");
+        std::fs::write(&model, b"ggml").unwrap();
+        let mut config = ModelConfig::new(&model.to_string_lossy(), "auto", "float16");
+        assert!(config.validate().is_err());
+        config.backend = Backend::WhisperCpp;
+        let transcriber = WhisperCppTranscriber::new(config).unwrap();
+        assert_eq!(transcriber.model_path(), model);
+    }
+
+    #[test]
+    fn test_missing_model_fails_before_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("clip.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+
+        let mut config = ModelConfig::new("tiny", "cpu", "float32");
+        config.backend = Backend::WhisperCpp;
+        config.model_dir = Some(dir.path().to_path_buf());
+        let err = WhisperCppTranscriber::new(config)
+            .unwrap()
+            .transcribe(&audio)
+            .unwrap_err();
+        assert_eq!(err.kind(), "model_init");
+        assert!(err.to_string().contains("ggml-tiny.bin"));
+    }
+
+    #[test]
+    fn test_write_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("samples.wav");
+        write_wav(&path, &[0.0, 1.0, -2.0]).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 44 + 6);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+            16_000
+        );
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(i16::from_le_bytes([bytes[46], bytes[47]]), i16::MAX);
+        assert_eq!(i16::from_le_bytes([bytes[48], bytes[49]]), -i16::MAX);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::error::{Result, TranscriptionError};
+use crate::types::TranscriptionResult;
+use serde::{Deserialize, Serialize};
+
+/// The transcript within a fixed stretch of time, e.g. one chunk for a summarizer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptWindow {
+    pub start: f64,
+    pub end: f64,
+    /// Texts of every segment overlapping the window, joined with spaces
+    pub text: String,
+    pub word_count: usize,
+}
+
+/// Iterator over the windows of a transcript; see `windows`
+#[derive(Debug, Clone)]
+pub struct Windows<'a> {
+    result: &'a TranscriptionResult,
+    window_secs: f64,
+    step_secs: f64,
+    /// Where the transcript ends: the audio's duration or the last segment, if later
+    end: f64,
+    index: usize,
+    done: bool,
+}
+
+/// Split `result` into windows `window_secs` long, starting every `step_secs`. Windows
+/// overlap when the step is shorter than the window, and leave gaps when it's longer.
+///
+/// A segment belongs to every window it overlaps, so one spanning a boundary appears in
+/// both. The last window is cut short where the transcript ends, and a window longer than
+/// the transcript gives a single window over all of it. A transcript with no duration and no
+/// segments has no windows. Sizes must be positive, so a zero step can't repeat forever.
+pub fn windows(
+    result: &TranscriptionResult,
+    window_secs: f64,
+    step_secs: f64,
+) -> Result<Windows<'_>> {
+    for (name, value) in [("window", window_secs), ("step", step_secs)] {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(TranscriptionError::ConfigError(format!(
+                "Transcript {} must be a positive number of seconds, got {}",
+                name, value
+            )));
+        }
+    }
+    let end = result
+        .segments
+        .iter()
+        .map(|segment| segment.end)
+        .fold(result.duration, f64::max);
+    Ok(Windows {
+        result,
+        window_secs,
+        step_secs,
+        end,
+        index: 0,
+        done: end <= 0.0,
+    })
+}
+
+impl Iterator for Windows<'_> {
+    type Item = TranscriptWindow;
+
+    fn next(&mut self) -> Option<TranscriptWindow> {
+        if self.done {
+            return None;
+        }
+        // Multiplied rather than accumulated, so long transcripts don't drift
+        let start = self.index as f64 * self.step_secs;
+        if start >= self.end {
+            self.done = true;
+            return None;
+        }
+        let end = (start + self.window_secs).min(self.end);
+        self.index += 1;
+        // Later windows would only repeat the tail of this one
+        self.done = end >= self.end;
+
+        let texts: Vec<&str> = self
+            .result
+            .segments
+            .iter()
+            .filter(|segment| {
+                // Instantaneous segments belong to the window they fall in
+                segment.start < end && (segment.end > start || segment.start >= start)
+            })
+            .map(|segment| segment.text.trim())
+            .filter(|text| !text.is_empty())
+            .collect();
+        let text = texts.join(" ");
+        Some(TranscriptWindow {
+            start,
+            end,
+            word_count: text.split_whitespace().count(),
+            text,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TranscriptionSegment;
+
+    fn result(duration: f64, segments: &[(f64, f64, &str)]) -> TranscriptionResult {
+        let segments: Vec<TranscriptionSegment> = segments
+            .iter()
+            .map(|&(start, end, text)| TranscriptionSegment {
+                start,
+                end,
+                text: format!(" {}", text),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.2,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            })
+            .collect();
+        TranscriptionResult {
+            language: "en".to_string(),
+            language_probability: 0.9,
+            duration,
+            full_text: TranscriptionResult::text_from_segments(&segments, None),
+            segments,
+            transcription_time: 1.0,
+            real_time_factor: duration,
+            ..Default::default()
+        }
+    }
+
+    fn spans(windows: &[TranscriptWindow]) -> Vec<(f64, f64)> {
+        windows.iter().map(|w| (w.start, w.end)).collect()
+    }
+
+    #[test]
+    fn test_boundary_segments_belong_to_both_windows() {
+        let result = result(
+            25.0,
+            &[
+                (0.0, 4.0, "One two."),
+                (8.0, 12.0, "Across the line."),
+                (15.0, 19.0, "Four."),
+                (21.0, 24.0, ""),
+            ],
+        );
+        let windows: Vec<_> = result.windows(10.0, 10.0).unwrap().collect();
+        assert_eq!(spans(&windows), [(0.0, 10.0), (10.0, 20.0), (20.0, 25.0)]);
+        assert_eq!(windows[0].text, "One two. Across the line.");
+        assert_eq!(windows[0].word_count, 5);
+        assert_eq!(windows[1].text, "Across the line. Four.");
+        // Silence still gets its window
+        assert_eq!((windows[2].text.as_str(), windows[2].word_count), ("", 0));
+    }
+
+    #[test]
+    fn test_overlapping_windows_stop_at_the_end() {
+        let result = result(20.0, &[(0.0, 3.0, "Start."), (17.0, 19.0, "End.")]);
+        let windows: Vec<_> = result.windows(10.0, 5.0).unwrap().collect();
+        assert_eq!(spans(&windows), [(0.0, 10.0), (5.0, 15.0), (10.0, 20.0)]);
+        assert_eq!(windows[2].text, "End.");
+
+        // Gaps between windows when the step is longer
+        let windows: Vec<_> = result.windows(2.0, 15.0).unwrap().collect();
+        assert_eq!(spans(&windows), [(0.0, 2.0), (15.0, 17.0)]);
+        assert_eq!(windows[1].text, "");
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let empty = result(0.0, &[]);
+        assert_eq!(empty.windows(30.0, 30.0).unwrap().count(), 0);
+
+        let short = result(12.0, &[(1.0, 13.0, "Runs past the audio.")]);
+        let windows: Vec<_> = short.windows(60.0, 30.0).unwrap().collect();
+        assert_eq!(spans(&windows), [(0.0, 13.0)]);
+        assert_eq!(windows[0].word_count, 4);
+
+        for (window, step) in [(30.0, 0.0), (0.0, 30.0), (30.0, -1.0), (f64::NAN, 30.0)] {
+            let err = short.windows(window, step).unwrap_err();
+            assert_eq!(err.kind(), "config");
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use rust_whisper_app::{
-    benchmark::Benchmark,
+    benchmark::{Benchmark, BenchmarkResult},
     transcriber::FasterWhisperTranscriber,
-    types::{ModelConfig, TranscriptionResult},
+    types::{ModelConfig, TranscriptionOptions, TranscriptionResult, TranscriptionSegment},
     TranscriptionError,
 };
 use std::path::PathBuf;
@@ -131,6 +131,7 @@ fn test_transcription_result_serialization() {
         full_text: "Test transcription".to_string(),
         transcription_time: 2.0,
         real_time_factor: 15.0,
+        ..Default::default()
     };
 
     // Test JSON serialization
@@ -240,12 +241,12 @@ async fn test_medium_model_creation() {
     }
 }
 
-#[tokio::test] 
+#[tokio::test]
 #[ignore] // Ignore by default due to potential OpenMP initialization issues
 async fn test_metal_acceleration_detection() {
     // Test Metal acceleration detection on macOS
     let config = ModelConfig::new("medium", "auto", "float16");
-    
+
     match FasterWhisperTranscriber::new(config) {
         Ok(transcriber) => {
             // This should not fail even if Metal is not available
@@ -253,16 +254,21 @@ async fn test_metal_acceleration_detection() {
                 Ok(info) => {
                     println!("Device info: {}", info);
                     // Test passed if we got this far
-                    assert!(true);
-                },
+                }
                 Err(e) => {
-                    println!("Failed to get device info: {}. This may be expected in CI environments.", e);
+                    println!(
+                        "Failed to get device info: {}. This may be expected in CI environments.",
+                        e
+                    );
                     // Don't fail the test, as this might be environment-specific
                 }
             }
-        },
+        }
         Err(e) => {
-            println!("Failed to create transcriber: {}. This may be expected in CI environments.", e);
+            println!(
+                "Failed to create transcriber: {}. This may be expected in CI environments.",
+                e
+            );
             // Don't fail the test, as this might be environment-specific
         }
     }
@@ -272,7 +278,7 @@ async fn test_metal_acceleration_detection() {
 async fn test_performance_comparison() {
     // Create a small test audio file if none exists
     let test_file = "test_audio.wav";
-    
+
     // Skip if no test audio file is available
     if !std::path::Path::new(test_file).exists() {
         println!("Skipping performance test - no test audio file available");
@@ -281,22 +287,1279 @@ async fn test_performance_comparison() {
 
     // Test performance comparison between base and medium
     let models = vec!["base", "medium"];
-    let results = FasterWhisperTranscriber::benchmark_model_comparison(
-        test_file,
-        &models,
-        "auto", 
-        "float16"
-    );
+    let results =
+        FasterWhisperTranscriber::benchmark_model_comparison(test_file, &models, "auto", "float16");
 
     if let Ok(benchmark_results) = results {
         assert_eq!(benchmark_results.len(), 2);
-        
+
         for (model, result) in &benchmark_results {
             println!("Model {}: RTF = {:.2}x", model, result.real_time_factor);
-            assert!(result.transcription_time > 0.0, "Transcription time should be positive");
+            assert!(
+                result.transcription_time > 0.0,
+                "Transcription time should be positive"
+            );
             assert!(result.duration > 0.0, "Audio duration should be positive");
         }
     } else {
         println!("Skipping performance comparison - faster-whisper not available");
     }
 }
+
+fn cli() -> std::process::Command {
+    let mut command = std::process::Command::new(env!("CARGO_BIN_EXE_rust-whisper-app"));
+    command.arg("--no-config").env_remove("RUST_LOG");
+    command
+}
+
+#[test]
+fn test_cli_logs_go_to_stderr() {
+    let temp_dir = tempdir().unwrap();
+
+    let output = cli()
+        .arg("-v")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stdout.is_empty(), "stdout should stay clean: {}", stdout);
+    assert!(stderr.contains("Effective configuration"));
+    assert!(stderr.contains("No audio files found"));
+}
+
+#[test]
+fn test_cli_quiet_suppresses_info_and_warnings() {
+    let temp_dir = tempdir().unwrap();
+
+    let output = cli()
+        .arg("--quiet")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+
+    assert!(output.stdout.is_empty());
+    assert!(
+        output.stderr.is_empty(),
+        "quiet mode should only log errors: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_cli_batch_exit_codes() {
+    let temp_dir = tempdir().unwrap();
+    for name in ["a.wav", "b.wav"] {
+        std::fs::write(temp_dir.path().join(name), name).unwrap();
+    }
+
+    let output = cli()
+        .args(["-m", "tiny", "-d", "cpu", "-c", "float32", "-i"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "total failure exits with 1");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("0 ok, 2 failed, 0 skipped"));
+
+    let output = cli()
+        .args([
+            "-m",
+            "tiny",
+            "-d",
+            "cpu",
+            "-c",
+            "float32",
+            "--fail-fast",
+            "-i",
+        ])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("0 ok, 1 failed, 1 skipped"));
+}
+
+#[test]
+fn test_cli_process_isolation_reports_worker_failures() {
+    let temp_dir = tempdir().unwrap();
+    for name in ["a.wav", "b.wav", "c.wav"] {
+        std::fs::write(temp_dir.path().join(name), name).unwrap();
+    }
+
+    let output = cli()
+        .args(["-m", "tiny", "-d", "cpu", "-c", "float32"])
+        .args(["--isolation", "process", "-j", "2", "-i"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("Started worker 2"), "{}", stderr);
+    assert!(!stderr.contains("Started worker 3"), "{}", stderr);
+    assert!(stderr.contains("0 ok, 3 failed, 0 skipped"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_state_file_resumes_batch() {
+    let temp_dir = tempdir().unwrap();
+    let audio_dir = temp_dir.path().join("audio");
+    std::fs::create_dir(&audio_dir).unwrap();
+    for name in ["a.wav", "b.wav"] {
+        std::fs::write(audio_dir.join(name), name).unwrap();
+    }
+    let state_path = temp_dir.path().join("batch.state.json");
+    let run = |extra: &[&str]| {
+        cli()
+            .args(["-m", "tiny", "-d", "cpu", "-c", "float32", "--state-file"])
+            .arg(&state_path)
+            .args(extra)
+            .arg("-i")
+            .arg(&audio_dir)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(1));
+    let state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&state_path).unwrap()).unwrap();
+    assert_eq!(state["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(state["entries"][0]["status"], "failed");
+
+    // Failed entries are not retried by default
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("0 ok, 0 failed, 2 skipped"));
+
+    let output = run(&["--retry-failed"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("0 ok, 2 failed, 0 skipped"));
+}
+
+#[test]
+fn test_cli_json_logs() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("a.wav"), b"not really audio").unwrap();
+
+    let output = cli()
+        .args([
+            "--log-format",
+            "json",
+            "-m",
+            "tiny",
+            "-d",
+            "cpu",
+            "-c",
+            "float32",
+            "-i",
+        ])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    let failed = events
+        .iter()
+        .find(|event| event["event"] == "file_failed")
+        .expect("a file_failed event");
+    assert_eq!(failed["level"], "ERROR");
+    assert_eq!(failed["model"], "tiny");
+    assert!(failed["file"].as_str().unwrap().ends_with("a.wav"));
+    assert!(failed["error_kind"].is_string());
+    assert!(events.iter().any(|event| event["event"] == "file_started"));
+}
+
+#[test]
+fn test_cli_checks_output_dir_before_loading_model() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("a.wav"), b"not really audio").unwrap();
+    let blocker = temp_dir.path().join("blocker");
+    std::fs::write(&blocker, b"").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .arg("-o")
+        .arg(blocker.join("out"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Output location is not writable"));
+    assert!(
+        !stderr.contains("Processing:"),
+        "no file should be attempted"
+    );
+
+    let output_dir = temp_dir.path().join("missing");
+    let output = cli()
+        .args(["--dry-run", "-i"])
+        .arg(temp_dir.path())
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!output_dir.exists(), "dry runs don't create directories");
+}
+
+#[test]
+fn test_cli_dry_run_plans_without_transcribing() {
+    let input_dir = tempdir().unwrap();
+    let output_dir = tempdir().unwrap();
+    for name in ["a.wav", "b.wav"] {
+        std::fs::write(input_dir.path().join(name), name).unwrap();
+    }
+    std::fs::write(output_dir.path().join("a_transcription.json"), b"{}").unwrap();
+
+    let output = cli()
+        .args(["--dry-run", "--skip-existing", "-i"])
+        .arg(input_dir.path())
+        .arg("-o")
+        .arg(output_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains("1 of 2 file(s) would be transcribed, 1 skipped"));
+    assert!(stdout.contains("b_transcription.json"));
+
+    std::fs::write(output_dir.path().join("b_transcription.json"), b"{}").unwrap();
+    let output = cli()
+        .args(["--dry-run", "--skip-existing", "-i"])
+        .arg(input_dir.path())
+        .arg("-o")
+        .arg(output_dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "nothing left to do");
+}
+
+#[test]
+fn test_cli_dry_run_shows_duplicates() {
+    let input_dir = tempdir().unwrap();
+    for (name, contents) in [("a.wav", "same"), ("b.wav", "other"), ("c.wav", "same")] {
+        std::fs::write(input_dir.path().join(name), contents).unwrap();
+    }
+
+    let output = cli()
+        .args(["--dry-run", "-i"])
+        .arg(input_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("2 of 3 file(s) would be transcribed, 0 skipped, 1 duplicate(s)"));
+    assert!(stdout.contains(&format!(
+        "{} = {}",
+        input_dir.path().join("a.wav").display(),
+        input_dir.path().join("c.wav").display()
+    )));
+
+    let output = cli()
+        .args(["--dry-run", "--no-dedupe", "-i"])
+        .arg(input_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("3 of 3 file(s) would be transcribed, 0 skipped\n"));
+}
+
+/// A silent 16 kHz mono 16-bit WAV of `seconds`
+fn silent_wav(seconds: u32) -> Vec<u8> {
+    silent_wav_channels(seconds, 1)
+}
+
+fn silent_wav_channels(seconds: u32, channels: u16) -> Vec<u8> {
+    let data_len = seconds * 16000 * 2 * channels as u32;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&16000u32.to_le_bytes());
+    bytes.extend_from_slice(&(32000 * channels as u32).to_le_bytes());
+    bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    bytes.resize(bytes.len() + data_len as usize, 0);
+    bytes
+}
+
+#[test]
+fn test_cli_max_duration_refuses_long_inputs() {
+    let input_dir = tempdir().unwrap();
+    let long = input_dir.path().join("long.wav");
+    std::fs::write(&long, silent_wav(3)).unwrap();
+    std::fs::write(input_dir.path().join("short.wav"), silent_wav(1)).unwrap();
+
+    let output = cli()
+        .args(["--dry-run", "--max-duration-minutes", "0.025", "-i"])
+        .arg(input_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("1 of 2 file(s) would be transcribed, 1 skipped"));
+    assert!(stdout.contains("skip (too long)"), "{}", stdout);
+
+    // Without a terminal to confirm on, a single file over the limit is an error, raised
+    // before any model is loaded
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .args(["--max-duration-minutes", "0.025", "-i"])
+        .arg(&long)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("is 0.1 minutes long, over the 0.025 minute limit"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("starting"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_rejects_bad_replacement_rules_at_startup() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("a.wav"), b"not really audio").unwrap();
+    let rules = temp_dir.path().join("rules.tsv");
+    std::fs::write(&rules, "pi oh three\tPyO3\n(unclosed\tx\tr\n").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .arg("--replace")
+        .arg(&rules)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("line 2: invalid regex"), "{}", stderr);
+    assert!(
+        !stderr.contains("Processing:"),
+        "no file should be attempted"
+    );
+}
+
+#[test]
+fn test_cli_rejects_bad_redaction_pattern_at_startup() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("a.wav"), b"not really audio").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .arg("--redact")
+        .arg("phone,email")
+        .arg("--redact-custom")
+        .arg("(unclosed")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("Invalid redaction pattern \"(unclosed\""),
+        "{}",
+        stderr
+    );
+    assert!(
+        !stderr.contains("Processing:"),
+        "no file should be attempted"
+    );
+}
+
+#[test]
+fn test_cli_search_transcript_json() {
+    let temp_dir = tempdir().unwrap();
+    let transcript = temp_dir.path().join("meeting.json");
+    let segment = |start: f64, end: f64, text: &str| TranscriptionSegment {
+        start,
+        end,
+        text: text.to_string(),
+        no_speech_prob: 0.0,
+        avg_logprob: 0.0,
+        words: vec![],
+        speaker: None,
+        tokens: None,
+    };
+    let result = TranscriptionResult {
+        language: "en".to_string(),
+        language_probability: 0.99,
+        duration: 90.0,
+        segments: vec![
+            segment(0.0, 4.0, "Welcome back."),
+            segment(61.5, 64.0, "Project Falcon slipped again."),
+        ],
+        full_text: "Welcome back. Project Falcon slipped again.".to_string(),
+        transcription_time: 1.0,
+        real_time_factor: 90.0,
+        ..Default::default()
+    };
+    std::fs::write(&transcript, serde_json::to_string(&result).unwrap()).unwrap();
+
+    let output = cli()
+        .args(["search", "project falcon"])
+        .arg(&transcript)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        stdout.trim(),
+        "[00:01:01.500 -> 00:01:04.000] Project Falcon\tProject Falcon slipped again."
+    );
+
+    let output = cli()
+        .args(["search", "osprey"])
+        .arg(&transcript)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_cli_diarize_needs_feature_and_token() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("a.wav"), b"not really audio").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .env_remove("HF_TOKEN")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .arg("--diarize")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    let expected = if cfg!(feature = "diarization") {
+        "needs a Hugging Face access token"
+    } else {
+        "rebuild with --features diarization"
+    };
+    assert!(stderr.contains(expected), "{}", stderr);
+    assert!(
+        !stderr.contains("Processing:"),
+        "no file should be attempted"
+    );
+}
+
+#[test]
+fn test_cli_split_channels_rejects_mono() {
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("call.wav");
+    std::fs::write(&input, silent_wav(1)).unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .arg("-i")
+        .arg(&input)
+        .arg("--split-channels")
+        .arg("--channel-names")
+        .arg("agent,customer")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    let expected = if cfg!(feature = "preprocess") {
+        "is mono; splitting channels needs two or more"
+    } else {
+        "rebuild with --features preprocess"
+    };
+    assert!(stderr.contains(expected), "{}", stderr);
+}
+
+#[test]
+fn test_cli_channel_must_exist() {
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("interpreted.wav");
+    std::fs::write(&input, silent_wav_channels(1, 2)).unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .arg("-i")
+        .arg(&input)
+        .arg("--channel")
+        .arg("3")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    let expected = if cfg!(feature = "preprocess") {
+        "has 2 channel(s); there is no channel 3"
+    } else {
+        "requires the preprocess feature"
+    };
+    assert!(stderr.contains(expected), "{}", stderr);
+}
+
+#[test]
+fn test_cli_batch_groups_undecodable_audio() {
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("in");
+    std::fs::create_dir(&input).unwrap();
+    std::fs::write(input.join("empty.wav"), b"").unwrap();
+    std::fs::write(input.join("notes.wav"), b"Meeting notes\nThursday\n").unwrap();
+    std::fs::write(input.join("readme.mp3"), b"Not a song").unwrap();
+
+    let output = cli()
+        .arg("-i")
+        .arg(&input)
+        .arg("-o")
+        .arg(temp_dir.path().join("out"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Undecodable audio:"), "{}", stderr);
+    assert!(stderr.contains("2 files: not actually audio"), "{}", stderr);
+    assert!(stderr.contains("1 file: empty file"), "{}", stderr);
+    assert!(
+        stderr.contains("empty.wav: Could not decode audio"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_ffmpeg_fallback_reports_missing_ffmpeg() {
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("broadcast.mxf");
+    std::fs::write(&input, b"\x06\x0e\x2b\x34 not for PyAV").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .env("WHISPER_FFMPEG_BIN", temp_dir.path().join("no-ffmpeg-here"))
+        .arg("-i")
+        .arg(&input)
+        .arg("--ffmpeg-fallback")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("decoding with ffmpeg instead"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("ffmpeg is not available"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_align_rejects_empty_transcript() {
+    let temp_dir = tempdir().unwrap();
+    let audio = temp_dir.path().join("talk.wav");
+    std::fs::write(&audio, b"not really audio").unwrap();
+    let script = temp_dir.path().join("script.txt");
+    std::fs::write(&script, " \n").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .args(["align", "-i"])
+        .arg(&audio)
+        .arg("--transcript")
+        .arg(&script)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("is empty"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_rejects_bad_confident_text_thresholds() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("a.wav"), b"not really audio").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .arg("-i")
+        .arg(temp_dir.path())
+        .args(["--confident-text", "--confident-max-no-speech", "1.5"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("no-speech probability"), "{}", stderr);
+    assert!(
+        !stderr.contains("Processing:"),
+        "no file should be attempted"
+    );
+}
+
+#[test]
+fn test_cli_convert_whispercpp_json() {
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("old.whispercpp.json");
+    std::fs::write(
+        &input,
+        r#"{"result": {"language": "en"}, "transcription": [
+            {"offsets": {"from": 0, "to": 2500}, "text": " Hello there."},
+            {"offsets": {"from": 2500, "to": 3661200}, "text": " General Kenobi."}
+        ]}"#,
+    )
+    .unwrap();
+
+    let output = cli()
+        .args(["convert", "--format", "srt", "-i"])
+        .arg(&input)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let srt = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        srt.starts_with("1\n00:00:00,000 --> 00:00:02,500\nHello there.\n"),
+        "{}",
+        srt
+    );
+    assert!(srt.contains("01:01:01,200"));
+
+    std::fs::write(&input, r#"{"transcription": [{"text": " Hi"}]}"#).unwrap();
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .args(["convert", "-i"])
+        .arg(&input)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("old.whispercpp.json"), "{}", stderr);
+    assert!(stderr.contains("missing field `offsets`"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_adjust_shifts_subtitles() {
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("a.json");
+    let result = TranscriptionResult {
+        language: "en".to_string(),
+        language_probability: 0.9,
+        duration: 100.0,
+        segments: vec![
+            TranscriptionSegment {
+                start: 30.0,
+                end: 40.0,
+                text: " Pre-roll.".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            },
+            TranscriptionSegment {
+                start: 95.0,
+                end: 97.5,
+                text: " Hello.".to_string(),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            },
+        ],
+        full_text: "Pre-roll. Hello.".to_string(),
+        transcription_time: 10.0,
+        real_time_factor: 10.0,
+        ..Default::default()
+    };
+    std::fs::write(&input, serde_json::to_string(&result).unwrap()).unwrap();
+
+    let output = cli()
+        .args(["adjust", "--shift", "-90", "--format", "srt", "-i"])
+        .arg(&input)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1\n00:00:05,000 --> 00:00:07,500\n Hello.\n\n"
+    );
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .args(["adjust", "--scale", "0", "-i"])
+        .arg(&input)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--scale"));
+}
+
+#[test]
+fn test_cli_vad_rejects_bad_threshold() {
+    let temp_dir = tempdir().unwrap();
+    let audio = temp_dir.path().join("talk.wav");
+    std::fs::write(&audio, b"not really audio").unwrap();
+
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .args(["vad", "--threshold", "1.5", "-i"])
+        .arg(&audio)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("threshold"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_language_map_in_dry_run() {
+    let input_dir = tempdir().unwrap();
+    for name in ["talk.fr.wav", "talk.wav"] {
+        std::fs::write(input_dir.path().join(name), name).unwrap();
+    }
+    let map = input_dir.path().join("langs.txt");
+    std::fs::write(&map, "*.fr.wav=fr\n").unwrap();
+
+    let output = cli()
+        .args(["--dry-run", "-i"])
+        .arg(input_dir.path())
+        .arg("--language-map")
+        .arg(&map)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("talk.fr.wav (fr)"), "{}", stdout);
+    assert!(!stdout.contains("talk.wav ("), "{}", stdout);
+
+    std::fs::write(&map, "*.fr.wav=fr\n*.xx.wav=xx\n").unwrap();
+    let output = cli()
+        .args(["--dry-run", "-i"])
+        .arg(input_dir.path())
+        .arg("--language-map")
+        .arg(&map)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("line 2: unknown language code 'xx'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_format_list() {
+    let input_dir = tempdir().unwrap();
+    let output_dir = tempdir().unwrap();
+    std::fs::write(input_dir.path().join("a.wav"), "a").unwrap();
+
+    let output = cli()
+        .args(["--dry-run", "-f", "srt,json", "-i"])
+        .arg(input_dir.path())
+        .arg("-o")
+        .arg(output_dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("a_transcription.srt"));
+
+    let output = cli()
+        .args(["--dry-run", "-f", "json,docx", "-i"])
+        .arg(input_dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("docx"));
+}
+
+#[test]
+fn test_cli_summary_csv_lists_every_input() {
+    let temp_dir = tempdir().unwrap();
+    let audio_dir = temp_dir.path().join("audio");
+    std::fs::create_dir(&audio_dir).unwrap();
+    for name in ["a, b.wav", "c.wav"] {
+        std::fs::write(audio_dir.join(name), name).unwrap();
+    }
+    let csv_path = temp_dir.path().join("summary.csv");
+
+    let output = cli()
+        .args(["-m", "tiny", "-d", "cpu", "-c", "float32", "-i"])
+        .arg(&audio_dir)
+        .arg("--summary-csv")
+        .arg(&csv_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", csv);
+    assert!(lines[0].starts_with("file,status,duration"));
+    let quoted = format!("\"{}\",failed,", audio_dir.join("a, b.wav").display());
+    assert!(lines[1].starts_with(&quoted), "{}", csv);
+    let plain = format!("{},failed,", audio_dir.join("c.wav").display());
+    assert!(lines[2].starts_with(&plain), "{}", csv);
+}
+
+#[test]
+fn test_cli_streamed_directory_fails_only_the_colliding_file() {
+    let temp_dir = tempdir().unwrap();
+    let audio_dir = temp_dir.path().join("audio");
+    std::fs::create_dir(&audio_dir).unwrap();
+    std::fs::write(audio_dir.join("a.wav"), "a").unwrap();
+    std::fs::write(audio_dir.join("a.mp3"), "a").unwrap();
+
+    let output = cli()
+        .args([
+            "-m",
+            "tiny",
+            "-d",
+            "cpu",
+            "-c",
+            "float32",
+            "--no-dedupe",
+            "-i",
+        ])
+        .arg(&audio_dir)
+        .arg("-o")
+        .arg(temp_dir.path().join("out"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(
+        stderr.contains("Transcribing audio files as they are found"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("is already written by"), "{}", stderr);
+    assert!(
+        stderr.contains("Files: 0 ok, 2 failed, 0 skipped"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_timing_report_of_failed_batch() {
+    let temp_dir = tempdir().unwrap();
+    let audio_dir = temp_dir.path().join("audio");
+    std::fs::create_dir(&audio_dir).unwrap();
+    std::fs::write(audio_dir.join("a.wav"), "a").unwrap();
+    let timing_path = temp_dir.path().join("timings.json");
+
+    let output = cli()
+        .args(["-m", "tiny", "-d", "cpu", "-c", "float32", "-i"])
+        .arg(&audio_dir)
+        .arg("--timing-report")
+        .arg(&timing_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    // Failed files have no timings to report
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&timing_path).unwrap()).unwrap();
+    assert_eq!(report["files"], serde_json::json!([]));
+    assert_eq!(report["percentiles"], serde_json::json!({}));
+}
+
+#[test]
+fn test_cli_archive_of_failed_batch() {
+    let temp_dir = tempdir().unwrap();
+    let audio_dir = temp_dir.path().join("audio");
+    std::fs::create_dir(&audio_dir).unwrap();
+    std::fs::write(audio_dir.join("a.wav"), "a").unwrap();
+    let archive_path = temp_dir.path().join("results.zip");
+
+    let output = cli()
+        .args(["-m", "tiny", "-d", "cpu", "-c", "float32", "-i"])
+        .arg(&audio_dir)
+        .arg("-o")
+        .arg(temp_dir.path().join("out"))
+        .arg("--archive")
+        .arg(&archive_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let archive = zip::ZipArchive::new(std::fs::File::open(&archive_path).unwrap()).unwrap();
+    let mut names: Vec<&str> = archive.file_names().collect();
+    names.sort();
+    assert_eq!(names, vec!["errors.txt", "summary.csv"]);
+
+    // Dry runs never write an archive
+    std::fs::remove_file(&archive_path).unwrap();
+    let output = cli()
+        .args(["--dry-run", "-i"])
+        .arg(&audio_dir)
+        .arg("--archive")
+        .arg(&archive_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!archive_path.exists());
+}
+
+#[test]
+#[cfg(not(feature = "s3"))]
+fn test_cli_s3_input_needs_feature() {
+    let output = cli().args(["-i", "s3://media/calls/"]).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("rebuild with --features s3"));
+
+    let temp_dir = tempdir().unwrap();
+    let output = cli()
+        .arg("-i")
+        .arg(temp_dir.path())
+        .args(["-o", "s3://media/out/"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs s3:// input"));
+}
+
+#[test]
+fn test_cli_url_input_options() {
+    let output = cli()
+        .args([
+            "-i",
+            "https://example.com/talk.wav",
+            "--expect-sha256",
+            "abc",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("64 hex digits"));
+
+    // Dry runs don't download anything
+    let output = cli()
+        .args(["--dry-run", "--max-download-size", "500M"])
+        .args(["-i", "https://example.com/talk.wav"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("https://example.com/talk.wav -> stdout"));
+}
+
+#[test]
+fn test_cli_models_verify() {
+    let temp_dir = tempdir().unwrap();
+    let output = cli()
+        .arg("--model-dir")
+        .arg(temp_dir.path())
+        .args(["--offline", "models", "verify", "tiny"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("tiny: not downloaded"));
+
+    let model_dir = temp_dir.path().join("tiny-ct2");
+    std::fs::create_dir_all(&model_dir).unwrap();
+    for name in ["model.bin", "config.json"] {
+        std::fs::write(model_dir.join(name), "{}").unwrap();
+    }
+    let output = cli()
+        .args(["--offline", "models", "verify"])
+        .arg(&model_dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("tokenizer.json"), "{}", stdout);
+    assert!(stdout.contains("1 damaged file(s)"));
+
+    std::fs::write(model_dir.join("tokenizer.json"), "{}").unwrap();
+    let output = cli()
+        .args(["--offline", "models", "verify"])
+        .arg(&model_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_models_pull_reports_failures() {
+    let temp_dir = tempdir().unwrap();
+    let output = cli()
+        .args(["models", "pull", "not-a-model", "--model-dir"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 1 model(s) failed"));
+
+    let output = cli()
+        .args(["--offline", "models", "pull", "tiny"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("drop --offline"));
+}
+
+#[test]
+fn test_cli_models_cached_and_rm() {
+    let temp_dir = tempdir().unwrap();
+    let repo_dir = temp_dir.path().join("models--Systran--faster-whisper-tiny");
+    let snapshot = repo_dir.join("snapshots").join("abc123");
+    std::fs::create_dir_all(&snapshot).unwrap();
+    std::fs::write(snapshot.join("model.bin"), vec![0; 2048]).unwrap();
+
+    let output = cli()
+        .args(["models", "cached", "--sort", "size", "--model-dir"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("tiny"), "{}", stdout);
+    assert!(stdout.contains("2.0 KB"));
+
+    // Without a terminal to confirm on, deleting needs --yes
+    let output = cli()
+        .args(["models", "rm", "tiny", "--model-dir"])
+        .arg(temp_dir.path())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(repo_dir.exists());
+
+    let output = cli()
+        .args(["models", "rm", "tiny", "--yes", "--model-dir"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!repo_dir.exists());
+}
+
+#[test]
+fn test_cli_stitch_rebases_chunks() {
+    let temp_dir = tempdir().unwrap();
+    let chunk = |segments: Vec<(f64, f64, &str)>| TranscriptionResult {
+        language: "en".to_string(),
+        language_probability: 0.9,
+        duration: 60.0,
+        full_text: String::new(),
+        segments: segments
+            .into_iter()
+            .map(|(start, end, text)| TranscriptionSegment {
+                start,
+                end,
+                text: format!(" {}", text),
+                no_speech_prob: 0.0,
+                avg_logprob: -0.1,
+                words: vec![],
+                speaker: None,
+                tokens: None,
+            })
+            .collect(),
+        transcription_time: 6.0,
+        real_time_factor: 10.0,
+        ..Default::default()
+    };
+    // Cut with 10 seconds of overlap, so the second chunk starts at 50s
+    let first = chunk(vec![(10.0, 15.0, "Welcome."), (51.0, 60.0, "And then we")]);
+    let second = chunk(vec![
+        (1.2, 6.0, "And then we went home."),
+        (8.0, 9.0, "Bye."),
+    ]);
+    std::fs::write(
+        temp_dir.path().join("part1.json"),
+        serde_json::to_string(&first).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("part2.json"),
+        serde_json::to_string(&second).unwrap(),
+    )
+    .unwrap();
+    let manifest = temp_dir.path().join("chunks.csv");
+    std::fs::write(&manifest, "file,offset\npart1.json,0\npart2.json,50\n").unwrap();
+
+    let output = cli()
+        .args(["stitch", "--format", "srt", "--manifest"])
+        .arg(&manifest)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1\n00:00:10,000 --> 00:00:15,000\n Welcome.\n\n\
+         2\n00:00:51,200 --> 00:00:56,000\n And then we went home.\n\n\
+         3\n00:00:58,000 --> 00:00:59,000\n Bye.\n\n"
+    );
+
+    // Offsets are either all given or all derived
+    let output = cli()
+        .env("RUST_BACKTRACE", "0")
+        .args(["stitch", "--auto-offsets", "--manifest"])
+        .arg(&manifest)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2"));
+}
+
+#[test]
+fn test_cli_benchmark_query() {
+    let temp_dir = tempdir().unwrap();
+    let row = |model: &str, device: &str, rtf: f64| {
+        serde_json::json!({
+            "model_size": model, "device": device, "compute_type": "int8",
+            "audio_duration": 60.0, "transcription_time": 60.0 / rtf,
+            "real_time_factor": rtf, "memory_usage_mb": null, "accuracy_score": null,
+            "segments_count": 9
+        })
+    };
+    // Saved before the machine was recorded
+    std::fs::write(
+        temp_dir.path().join("old.json"),
+        serde_json::json!([row("medium", "mps", 12.0)]).to_string(),
+    )
+    .unwrap();
+    let results: Vec<BenchmarkResult> = serde_json::from_value(serde_json::json!([
+        row("medium", "cpu", 20.0),
+        row("base", "cpu", 60.0)
+    ]))
+    .unwrap();
+    Benchmark::new()
+        .save_results_json(&results, temp_dir.path().join("new.json"))
+        .unwrap();
+
+    let output = cli()
+        .args(["benchmark", "query", "--best-for", "medium", "--dir"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.starts_with("medium"));
+    assert!(stdout.contains("20.0x  faster-whisper on cpu with int8"));
+
+    // Another kind of machine only has the results that didn't record one
+    let output = cli()
+        .args(["benchmark", "query", "--machine", "other-arch", "--dir"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("12.0x"));
+    assert!(stdout.contains("machine not recorded"));
+    assert!(!stdout.contains("base"));
+
+    let output = cli()
+        .args(["benchmark", "query", "--best-for", "large-v3", "--dir"])
+        .arg(temp_dir.path())
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No saved results for large-v3"));
+}
+
+#[test]
+fn test_cli_benchmark_diff() {
+    let temp_dir = tempdir().unwrap();
+    let run = |name: &str, rtf: f64| {
+        let path = temp_dir.path().join(name);
+        let results: Vec<BenchmarkResult> = serde_json::from_value(serde_json::json!([{
+            "model_size": "medium", "device": "mps", "compute_type": "float16",
+            "audio_duration": 60.0, "transcription_time": 60.0 / rtf,
+            "real_time_factor": rtf, "memory_usage_mb": null, "accuracy_score": null,
+            "segments_count": 9, "config_hash": "2a015a59feea8385"
+        }]))
+        .unwrap();
+        Benchmark::new().save_results_json(&results, &path).unwrap();
+        path
+    };
+    let old = run("old.json", 12.0);
+    let new = run("new.json", 10.0);
+
+    let diff = |threshold: &str| {
+        cli()
+            .args(["benchmark", "diff"])
+            .arg(&old)
+            .arg(&new)
+            .args(["--threshold", threshold])
+            .output()
+            .unwrap()
+    };
+    let output = diff("20");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("medium/mps/float16"));
+    assert!(stdout.contains("12.0 -> 10.0 (-16.7%)"));
+    // Not a terminal, so no colors
+    assert!(!stdout.contains('\x1b'));
+
+    let output = diff("10");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 config(s) regressed"));
+}
+
+#[test]
+fn test_segment_tokens_decode_to_their_text() {
+    let audio_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("OSR_uk_000_0020_8k.wav");
+    let transcriber = FasterWhisperTranscriber::from_params("tiny", "cpu", "float32").unwrap();
+    let options = TranscriptionOptions {
+        include_tokens: true,
+        ..TranscriptionOptions::default()
+    };
+    let result = match transcriber.transcribe_with_options(&audio_path, &options) {
+        Ok(result) => result,
+        // Without faster-whisper or the model there is nothing to decode with
+        Err(e) => {
+            assert!(matches!(e.kind(), "model_init" | "package_missing"));
+            return;
+        }
+    };
+    assert!(!result.segments.is_empty());
+    for segment in &result.segments {
+        let tokens = segment.tokens.as_ref().expect("tokens were requested");
+        let decoded = transcriber.decode_tokens(tokens).unwrap();
+        assert_eq!(decoded.trim(), segment.text.trim());
+        for word in &segment.words {
+            let decoded = transcriber
+                .decode_tokens(word.tokens.as_ref().unwrap())
+                .unwrap();
+            assert_eq!(decoded.trim(), word.word.trim());
+        }
+    }
+}
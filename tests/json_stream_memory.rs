@@ -0,0 +1,209 @@
+//! Peak memory of writing a long transcript's JSON at once versus streaming it with
+//! `json_stream::transcribe_to_writer`. A counting allocator tracks the peak, so this lives
+//! in its own test binary with a single test.
+//!
+//! Run with `cargo test --test json_stream_memory -- --nocapture` to see the figures. On
+//! 15,000 segments with word timings (about a 10-hour audiobook, 25 MB of JSON), writing at
+//! once peaked at 66 MB: the segments, then the whole document beside them. Streaming
+//! peaked at 3.7 MB, nearly all of it `full_text` and the fields after the segments.
+
+use rust_whisper_app::{
+    backend::{SegmentSink, TranscriptionBackend},
+    error::Result,
+    json_stream,
+    output::{self, OutputFormat},
+    types::{
+        ModelConfig, TextBuilder, TranscriptionOptions, TranscriptionResult, TranscriptionSegment,
+        WordTiming,
+    },
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(now, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Bytes allocated at the peak of `f`, beyond what was allocated when it started
+fn peak_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let base = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(base, Ordering::SeqCst);
+    let value = f();
+    (value, PEAK.load(Ordering::SeqCst) - base)
+}
+
+const SEGMENTS: usize = 15_000;
+const WORDS: usize = 10;
+
+fn segment(index: usize) -> TranscriptionSegment {
+    let start = index as f64 * 2.4;
+    let words: Vec<WordTiming> = (0..WORDS)
+        .map(|w| WordTiming {
+            start: start + w as f64 * 0.24,
+            end: start + (w + 1) as f64 * 0.24,
+            word: format!(" word{}", w),
+            probability: 0.93,
+            speaker: None,
+            tokens: None,
+        })
+        .collect();
+    TranscriptionSegment {
+        start,
+        end: start + 2.4,
+        text: words.iter().map(|w| w.word.as_str()).collect(),
+        no_speech_prob: 0.02,
+        avg_logprob: -0.21,
+        words,
+        speaker: None,
+        tokens: None,
+    }
+}
+
+fn summary(full_text: String) -> TranscriptionResult {
+    TranscriptionResult {
+        language: "en".to_string(),
+        language_probability: 0.98,
+        duration: SEGMENTS as f64 * 2.4,
+        segments: Vec::new(),
+        full_text,
+        transcription_time: 1200.0,
+        real_time_factor: 30.0,
+        ..Default::default()
+    }
+}
+
+/// Decodes segments one at a time, as faster-whisper does
+struct Audiobook {
+    config: ModelConfig,
+    options: TranscriptionOptions,
+}
+
+impl TranscriptionBackend for Audiobook {
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn options(&self) -> &TranscriptionOptions {
+        &self.options
+    }
+
+    fn load(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        true
+    }
+
+    fn transcribe_path(
+        &self,
+        _audio_path: &Path,
+        _options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        let segments: Vec<_> = (0..SEGMENTS).map(segment).collect();
+        Ok(TranscriptionResult {
+            full_text: TranscriptionResult::text_from_segments(&segments, None),
+            segments,
+            ..summary(String::new())
+        })
+    }
+
+    fn transcribe_path_into(
+        &self,
+        _audio_path: &Path,
+        _options: &TranscriptionOptions,
+        sink: &mut dyn SegmentSink,
+    ) -> Result<TranscriptionResult> {
+        let summary = summary(String::new());
+        sink.begin(
+            &summary.language,
+            summary.language_probability,
+            summary.duration,
+        )?;
+        let mut text = TextBuilder::new(None);
+        for index in 0..SEGMENTS {
+            let segment = segment(index);
+            sink.segment(&segment)?;
+            text.push(&segment);
+        }
+        Ok(TranscriptionResult {
+            full_text: text.finish(),
+            ..summary
+        })
+    }
+
+    fn transcribe_samples(
+        &self,
+        _samples: &[f32],
+        _options: &TranscriptionOptions,
+    ) -> Result<TranscriptionResult> {
+        unreachable!()
+    }
+
+    fn device_info(&self) -> Result<String> {
+        Ok("audiobook".to_string())
+    }
+
+    fn unload(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_streaming_json_saves_memory() {
+    let backend = Audiobook {
+        config: ModelConfig::new("tiny", "cpu", "float32"),
+        options: TranscriptionOptions::default(),
+    };
+    let path = Path::new("audiobook.wav");
+
+    let (at_once, at_once_peak) = peak_during(|| {
+        let result = backend.transcribe_path(path, backend.options()).unwrap();
+        output::render(&result, OutputFormat::Json).unwrap().len()
+    });
+    let (_, streamed_peak) = peak_during(|| {
+        json_stream::transcribe_to_writer(&backend, path, backend.options(), std::io::sink())
+            .unwrap()
+    });
+    println!(
+        "{} segments, {} bytes of JSON: peak {} bytes at once, {} streamed",
+        SEGMENTS, at_once, at_once_peak, streamed_peak
+    );
+    // The streamed peak still counts the growing full_text, which both ways build
+    assert!(
+        streamed_peak * 10 < at_once_peak,
+        "streaming peaked at {} bytes, writing at once at {}",
+        streamed_peak,
+        at_once_peak
+    );
+
+    // The same bytes either way
+    let mut streamed = Vec::new();
+    json_stream::transcribe_to_writer(&backend, path, backend.options(), &mut streamed).unwrap();
+    let result = backend.transcribe_path(path, backend.options()).unwrap();
+    assert_eq!(
+        String::from_utf8(streamed).unwrap(),
+        output::render(&result, OutputFormat::Json).unwrap()
+    );
+}